@@ -0,0 +1,62 @@
+//! Criterion benchmarks of each transform step type, on the same steps `yetii bench-transforms`
+//! times; run with `cargo bench --bench transforms`.
+
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use serde_json::{Map, Value};
+use yetii::bench::{self, TransformConfig};
+
+const TRANSFORM: &str = "filters:
+  - field: status
+    condition: ne
+    value: void
+conversions:
+  amount:
+    from: string
+    to: number
+  quantity:
+    from: string
+    to: integer
+  placed_at:
+    from: string
+    to: datetime
+mappings:
+  amount: total
+  customer: customer_id
+group_by: customer_id
+";
+
+fn rows(count: usize) -> Vec<Map<String, Value>> {
+    (0..count)
+        .map(|index| {
+            let row = serde_json::json!({
+                "customer": format!("c{}", index % 100),
+                "status": if index % 10 == 0 { "void" } else { "open" },
+                "amount": format!("{}.50", index % 1000),
+                "quantity": (index % 20).to_string(),
+                "placed_at": "2024-03-01T08:30:00Z",
+            });
+            row.as_object().cloned().unwrap_or_default()
+        })
+        .collect()
+}
+
+fn transform_steps(criterion: &mut Criterion) {
+    let transform: TransformConfig =
+        serde_yaml::from_str(TRANSFORM).expect("benchmark transform is valid");
+    let steps = bench::steps(&rows(10_000), &transform).expect("benchmark rows transform");
+    let mut group = criterion.benchmark_group("transform");
+    for step in &steps {
+        group.throughput(Throughput::Elements(step.rows.len() as u64));
+        group.bench_function(&step.name, |bencher| {
+            bencher.iter_batched(
+                || step.rows.clone(),
+                |rows| step.run(rows),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, transform_steps);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+use std::process::Command;
+
+fn main() {
+    for path in [".git/HEAD", ".git/refs/heads"] {
+        if std::path::Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+    println!("cargo:rerun-if-env-changed=YETII_GIT_COMMIT");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let commit = std::env::var("YETII_GIT_COMMIT")
+        .ok()
+        .filter(|commit| !commit.trim().is_empty())
+        .or_else(|| command_output("git", &["rev-parse", "--short=12", "HEAD"]))
+        .unwrap_or_else(|| "unknown".to_string());
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version =
+        command_output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+    let mut features = std::env::vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_ascii_lowercase().replace('_', "-"))
+        })
+        .collect::<Vec<_>>();
+    features.sort();
+
+    println!("cargo:rustc-env=YETII_GIT_COMMIT={commit}");
+    println!("cargo:rustc-env=YETII_RUSTC_VERSION={rustc_version}");
+    println!(
+        "cargo:rustc-env=YETII_BUILD_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+    println!(
+        "cargo:rustc-env=YETII_BUILD_PROFILE={}",
+        std::env::var("PROFILE").unwrap_or_default()
+    );
+    println!("cargo:rustc-env=YETII_FEATURES={}", features.join(","));
+
+    // The TLS crates do not report their own versions, so the locked ones are recorded.
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    for (package, variable) in [
+        ("rustls", "YETII_RUSTLS_VERSION"),
+        ("ring", "YETII_RING_VERSION"),
+    ] {
+        println!(
+            "cargo:rustc-env={variable}={}",
+            locked_versions(&lock, package)
+        );
+    }
+}
+
+fn locked_versions(lock: &str, package: &str) -> String {
+    let name = format!("name = \"{package}\"");
+    let versions = lock
+        .lines()
+        .zip(lock.lines().skip(1))
+        .filter(|(line, _)| *line == name)
+        .filter_map(|(_, next)| next.strip_prefix("version = \"")?.strip_suffix('"'))
+        .collect::<Vec<_>>();
+    if versions.is_empty() {
+        "unknown".to_string()
+    } else {
+        versions.join(", ")
+    }
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|value| !value.is_empty())
+}
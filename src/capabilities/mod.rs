@@ -0,0 +1,184 @@
+use crate::config::database::{DatabaseConfig, DatabaseType};
+use crate::database;
+
+/// Destination connectors built into this binary; all of them are compiled in unconditionally.
+pub const CONNECTORS: &[&str] = &[
+    "http", "grpc", "redis", "mqtt", "ftp", "sftp", "file", "s3", "kafka", "amqp", "database",
+];
+
+/// What the host offers for the native parts of a configuration.
+pub struct Capabilities {
+    /// Registered ODBC driver names, or why the driver manager could not list them.
+    odbc_drivers: Result<Vec<String>, String>,
+}
+
+/// A capability a database needs but the host lacks, with what to do about it.
+#[derive(Debug, thiserror::Error)]
+pub enum MissingCapability {
+    #[error(
+        "database '{database}' cannot be reached because the ODBC driver manager failed to load ({reason}); install unixODBC (Debian/Ubuntu: `apt install unixodbc`, macOS: `brew install unixodbc`)"
+    )]
+    DriverManager { database: String, reason: String },
+    #[error(
+        "database '{database}' needs ODBC driver '{driver}', which is not installed (installed drivers: {installed}); {remedy}"
+    )]
+    Driver {
+        database: String,
+        driver: String,
+        installed: String,
+        remedy: String,
+    },
+}
+
+impl Capabilities {
+    pub fn detect() -> Self {
+        let odbc_drivers =
+            database::env()
+                .map_err(|error| error.to_string())
+                .and_then(|environment| {
+                    environment
+                        .drivers()
+                        .map(|drivers| {
+                            drivers
+                                .into_iter()
+                                .map(|driver| driver.description)
+                                .collect()
+                        })
+                        .map_err(|error| error.to_string())
+                });
+        Self { odbc_drivers }
+    }
+
+    /// Registered ODBC drivers, `None` when they could not be listed.
+    pub fn odbc_drivers(&self) -> Option<&[String]> {
+        self.odbc_drivers.as_deref().ok()
+    }
+
+    /// The capability `database` needs that is missing; unknown requirements are not reported.
+    pub fn missing(&self, database: &DatabaseConfig) -> Option<MissingCapability> {
+        if database.db_type == DatabaseType::Generator {
+            return None;
+        }
+        match &self.odbc_drivers {
+            Ok(installed) => missing_driver(database, installed),
+            Err(reason) if database::env().is_err() => Some(MissingCapability::DriverManager {
+                database: database.name.clone(),
+                reason: reason.clone(),
+            }),
+            // The manager loaded but could not enumerate drivers, so leave it to the connection.
+            Err(_) => None,
+        }
+    }
+}
+
+fn missing_driver(database: &DatabaseConfig, installed: &[String]) -> Option<MissingCapability> {
+    let driver = required_driver(database)?;
+    if installed
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(&driver))
+    {
+        return None;
+    }
+    let family = installed
+        .iter()
+        .find(|name| is_driver_for(&database.db_type, name));
+    let remedy = match family {
+        Some(name) => format!("set databases.driver to '{name}' to use the installed driver"),
+        None => install_hint(&database.db_type).to_string(),
+    };
+    Some(MissingCapability::Driver {
+        database: database.name.clone(),
+        driver,
+        installed: if installed.is_empty() {
+            "none".to_string()
+        } else {
+            installed.join(", ")
+        },
+        remedy,
+    })
+}
+
+/// The configured driver, the one named in `connection_string`, or the type's default; `None`
+/// when a connection string only names a DSN.
+fn required_driver(database: &DatabaseConfig) -> Option<String> {
+    if let Some(driver) = &database.driver {
+        return Some(driver.clone());
+    }
+    let Some(connection_string) = &database.connection_string else {
+        return Some(database.db_type.default_odbc_driver().to_string());
+    };
+    connection_string.split(';').find_map(|attribute| {
+        let (name, value) = attribute.split_once('=')?;
+        name.trim().eq_ignore_ascii_case("driver").then(|| {
+            value
+                .trim()
+                .trim_start_matches('{')
+                .trim_end_matches('}')
+                .to_string()
+        })
+    })
+}
+
+fn is_driver_for(db_type: &DatabaseType, driver: &str) -> bool {
+    let driver = driver.to_ascii_lowercase();
+    match db_type {
+        DatabaseType::Postgres => driver.contains("postgres"),
+        DatabaseType::Mysql => driver.contains("mysql") || driver.contains("mariadb"),
+        DatabaseType::Mssql => driver.contains("sql server"),
+        DatabaseType::Oracle => driver.contains("oracle"),
+        DatabaseType::Generator => false,
+    }
+}
+
+fn install_hint(db_type: &DatabaseType) -> &'static str {
+    match db_type {
+        DatabaseType::Postgres => {
+            "run `yetii setup`, or install psqlODBC (Debian/Ubuntu: `apt install odbc-postgresql`, macOS: `brew install psqlodbc`)"
+        }
+        DatabaseType::Mysql => {
+            "install MariaDB Connector/ODBC (Debian/Ubuntu: `apt install odbc-mariadb`) or MySQL Connector/ODBC, then set databases.driver to its registered name"
+        }
+        DatabaseType::Mssql => {
+            "install Microsoft ODBC Driver 18 for SQL Server (package `msodbcsql18` from Microsoft's repository)"
+        }
+        DatabaseType::Oracle => {
+            "install Oracle Instant Client with its ODBC package and register the driver, then set databases.driver to its name"
+        }
+        DatabaseType::Generator => "generator databases need no driver",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database(yaml: &str) -> DatabaseConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn missing_drivers_name_an_installed_alternative_or_a_package() {
+        let mysql = database(
+            "name: shop\ntype: mysql\nhost: db\nport: 3306\ndatabase: shop\nauth:\n  username: app\n  password: secret\n",
+        );
+        let installed = vec![
+            "PostgreSQL Unicode".to_string(),
+            "MariaDB Unicode".to_string(),
+        ];
+        let message = missing_driver(&mysql, &installed).unwrap().to_string();
+        assert!(message.contains("needs ODBC driver 'MySQL ODBC 8.0 Unicode Driver'"));
+        assert!(message.contains("set databases.driver to 'MariaDB Unicode'"));
+
+        let mssql = database(
+            "name: erp\ntype: mssql\nconnection_string: \"Driver={ODBC Driver 18 for SQL Server};Server=sql\"\nauth:\n  username: null\n  password: null\n",
+        );
+        let message = missing_driver(&mssql, &installed).unwrap().to_string();
+        assert!(message.contains("'ODBC Driver 18 for SQL Server'"));
+        assert!(message.contains("msodbcsql18"));
+
+        let postgres = database(
+            "name: dw\ntype: postgres\nhost: db\nport: 5432\ndatabase: dw\nauth:\n  username: app\n  password: secret\n",
+        );
+        assert!(missing_driver(&postgres, &installed).is_none());
+    }
+}
@@ -0,0 +1,153 @@
+use once_cell::sync::OnceCell;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+static CHAOS: OnceCell<ChaosConfig> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Fault {
+    Endpoint500,
+    EndpointTimeout,
+    DbConnect,
+    DbTimeout,
+    Notification,
+}
+
+impl Fault {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Fault::Endpoint500 => "endpoint-500",
+            Fault::EndpointTimeout => "endpoint-timeout",
+            Fault::DbConnect => "db-connect",
+            Fault::DbTimeout => "db-timeout",
+            Fault::Notification => "notification",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        [
+            Fault::Endpoint500,
+            Fault::EndpointTimeout,
+            Fault::DbConnect,
+            Fault::DbTimeout,
+            Fault::Notification,
+        ]
+        .into_iter()
+        .find(|fault| fault.as_str() == name)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChaosConfig {
+    faults: BTreeMap<Fault, f64>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChaosError {
+    #[error("invalid chaos entry '{0}'; expected fault=probability")]
+    InvalidEntry(String),
+    #[error(
+        "unknown chaos fault '{0}'; expected endpoint-500, endpoint-timeout, db-connect, db-timeout, or notification"
+    )]
+    UnknownFault(String),
+    #[error("chaos probability for '{fault}' must be between 0 and 1, got '{value}'")]
+    InvalidProbability { fault: String, value: String },
+    #[error("chaos configuration has already been installed")]
+    AlreadyInstalled,
+}
+
+impl FromStr for ChaosConfig {
+    type Err = ChaosError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut faults = BTreeMap::new();
+        for entry in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (name, probability) = entry
+                .split_once('=')
+                .ok_or_else(|| ChaosError::InvalidEntry(entry.to_string()))?;
+            let name = name.trim();
+            let fault =
+                Fault::parse(name).ok_or_else(|| ChaosError::UnknownFault(name.to_string()))?;
+            let probability = probability
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|value| (0.0..=1.0).contains(value))
+                .ok_or_else(|| ChaosError::InvalidProbability {
+                    fault: name.to_string(),
+                    value: probability.trim().to_string(),
+                })?;
+            faults.insert(fault, probability);
+        }
+        Ok(Self { faults })
+    }
+}
+
+impl ChaosConfig {
+    pub fn roll(&self, fault: Fault) -> bool {
+        self.faults
+            .get(&fault)
+            .is_some_and(|probability| rand::random::<f64>() < *probability)
+    }
+}
+
+pub fn install(spec: &str) -> Result<(), ChaosError> {
+    let config = spec.parse::<ChaosConfig>()?;
+    CHAOS.set(config).map_err(|_| ChaosError::AlreadyInstalled)
+}
+
+/// Returns true when the installed chaos configuration decides to fail this stage.
+pub fn inject(fault: Fault) -> bool {
+    let injected = CHAOS.get().is_some_and(|config| config.roll(fault));
+    if injected {
+        tracing::warn!(fault = fault.as_str(), "chaos fault injected");
+    }
+    injected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_comma_separated_fault_probabilities() {
+        let config = "endpoint-500=0.1, db-timeout=0.05"
+            .parse::<ChaosConfig>()
+            .unwrap();
+
+        assert_eq!(Some(&0.1), config.faults.get(&Fault::Endpoint500));
+        assert_eq!(Some(&0.05), config.faults.get(&Fault::DbTimeout));
+        assert!(!config.faults.contains_key(&Fault::DbConnect));
+    }
+
+    #[test]
+    fn rejects_unknown_faults_and_out_of_range_probabilities() {
+        assert!(matches!(
+            "disk-full=0.5".parse::<ChaosConfig>(),
+            Err(ChaosError::UnknownFault(name)) if name == "disk-full"
+        ));
+        assert!(matches!(
+            "endpoint-500=1.5".parse::<ChaosConfig>(),
+            Err(ChaosError::InvalidProbability { .. })
+        ));
+        assert!(matches!(
+            "endpoint-500".parse::<ChaosConfig>(),
+            Err(ChaosError::InvalidEntry(_))
+        ));
+    }
+
+    #[test]
+    fn certain_and_zero_probabilities_are_deterministic() {
+        let config = "endpoint-500=1,db-timeout=0"
+            .parse::<ChaosConfig>()
+            .unwrap();
+
+        assert!((0..100).all(|_| config.roll(Fault::Endpoint500)));
+        assert!((0..100).all(|_| !config.roll(Fault::DbTimeout)));
+        assert!(!config.roll(Fault::Notification));
+    }
+}
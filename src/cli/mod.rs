@@ -1,16 +1,40 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+pub use clap_complete::Shell;
+use std::num::NonZeroUsize;
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 pub struct Yetii {
     #[arg(global = true, long, short = 'c', default_value = "yetii.yaml")]
     pub file: String,
+    /// Directory with `yetii.yaml`, `queries/*.yaml`, `sql/*.sql`, and `.env`; replaces
+    /// --file.
+    #[arg(global = true, long, value_name = "DIR", conflicts_with = "file")]
+    pub config_dir: Option<String>,
+    /// `.env` file for `${VAR}` references the environment does not set; defaults to the
+    /// `.env` beside the configuration file or inside the configuration directory.
+    #[arg(global = true, long, value_name = "PATH")]
+    pub env_file: Option<String>,
+    /// Apply this entry of `environments:` and make it global_settings.environment; defaults
+    /// to $YETII_ENV.
+    #[arg(global = true, long = "env", value_name = "NAME")]
+    pub environment: Option<String>,
     #[arg(global = true, long, short = 'v', action = clap::ArgAction::SetTrue)]
     pub verbose: bool,
+    /// Inject failures for resilience testing, e.g. "endpoint-500=0.1,db-timeout=0.05".
+    #[arg(global = true, long, hide = true)]
+    pub chaos: Option<String>,
     #[clap(subcommand)]
     pub commands: Commands,
 }
 
+impl Yetii {
+    /// The configuration file, or the configuration directory when --config-dir is given.
+    pub fn config_path(&self) -> &str {
+        self.config_dir.as_deref().unwrap_or(&self.file)
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Create a starter Yetii configuration.
@@ -18,7 +42,24 @@ pub enum Commands {
         /// Directory where the starter configuration should be written.
         #[clap(short, long, default_value = ".")]
         path: String,
+
+        /// Start from a curated configuration for a common topology instead of the annotated
+        /// example.
+        #[clap(long, value_enum, conflicts_with = "minimal")]
+        template: Option<InitTemplate>,
+
+        /// Overwrite an existing file without asking.
+        #[clap(long)]
+        force: bool,
+
+        /// Write only the required fields: one database and one query.
+        #[clap(long)]
+        minimal: bool,
     },
+    /// Create the state, log, dead-letter, and runtime directories the configuration writes
+    /// into, and an .env.example listing the variables it references.
+    #[clap(name = "bootstrap")]
+    Bootstrap,
     /// List registered ODBC drivers.
     #[clap(name = "odbc")]
     CheckExistingOdbc,
@@ -38,18 +79,238 @@ pub enum Commands {
     /// Execute configured queries and deliver their rows to HTTP endpoints.
     #[clap(name = "run")]
     Run {
-        /// Name of one query to run. Runs all enabled queries when omitted.
+        /// Query to run, or a comma-separated list of names and globs such as `orders_*`.
+        /// Runs all enabled queries when omitted.
         #[clap(short, long)]
         query: Option<String>,
 
+        /// Name of a flow to run instead of queries; its steps run their queries even when
+        /// they are disabled.
+        #[clap(long, conflicts_with_all = ["query", "force"])]
+        flow: Option<String>,
+
         /// Run disabled queries too.
         #[clap(short, long)]
         force: bool,
+
+        /// Read at most N rows per query, e.g. to smoke-test a new configuration; rows read
+        /// are still delivered, but state is not saved.
+        #[clap(long, value_name = "N")]
+        limit: Option<NonZeroUsize>,
+
+        /// Remove the run lock before starting, e.g. when it was left by a process on another
+        /// host that is no longer running.
+        #[clap(long)]
+        force_unlock: bool,
+    },
+
+    /// Print the requests a run would send, built from a sample of each query's rows, without
+    /// delivering anything or saving state.
+    #[clap(name = "dry-run")]
+    DryRun {
+        /// Query to preview, or a comma-separated list of names and globs; previews all enabled
+        /// queries when omitted.
+        #[clap(short, long)]
+        query: Option<String>,
+
+        /// Preview disabled queries too.
+        #[clap(short, long)]
+        force: bool,
+
+        /// Rows to read per query.
+        #[clap(long, value_name = "N", default_value = "10")]
+        limit: NonZeroUsize,
+    },
+
+    /// Ask each query's database to plan or prepare its SQL without running it, to catch syntax
+    /// errors and missing tables before a schedule fires.
+    #[clap(name = "validate-query")]
+    ValidateQuery {
+        /// Query to validate, or a comma-separated list of names and globs; validates all
+        /// enabled queries when omitted.
+        #[clap(short, long)]
+        query: Option<String>,
+
+        /// Validate disabled queries too.
+        #[clap(short, long)]
+        force: bool,
+    },
+
+    /// Run ad hoc SQL to standard output, or deliver records through a query's transform and
+    /// endpoint, for use in shell pipelines.
+    #[clap(name = "exec")]
+    Exec {
+        /// File with the SQL to run, or `-` to read it from standard input.
+        #[clap(long, value_name = "FILE", required_unless_present = "records")]
+        sql_file: Option<String>,
+
+        /// Newline-delimited JSON records to use instead of running SQL, or `-` to read them
+        /// from standard input.
+        #[clap(long, value_name = "FILE", conflicts_with_all = ["sql_file", "database", "limit"])]
+        records: Option<String>,
+
+        /// Database the SQL runs against; may be omitted when only one is configured.
+        #[clap(long)]
+        database: Option<String>,
+
+        /// `stdout`, or the name of a query whose transform and endpoint or destination
+        /// deliver the rows.
+        #[clap(long, default_value = "stdout")]
+        sink: String,
+
+        /// Format of the rows written to standard output.
+        #[clap(long, value_enum, default_value = "ndjson")]
+        format: ExecOutputFormat,
+
+        /// Read at most N rows.
+        #[clap(long, value_name = "N")]
+        limit: Option<NonZeroUsize>,
+    },
+
+    /// Show the most recent query executions from the run history.
+    #[clap(name = "history", args_conflicts_with_subcommands = true)]
+    History {
+        #[clap(subcommand)]
+        command: Option<HistoryCommand>,
+
+        /// Only show executions of this query.
+        #[clap(short, long)]
+        query: Option<String>,
+
+        /// Number of executions to show.
+        #[clap(short = 'n', long, default_value_t = 20)]
+        limit: usize,
     },
 
     /// Validate the Yetii configuration.
     #[clap(name = "check-config")]
-    CheckConfig,
+    CheckConfig {
+        /// `json` prints every finding with its code, severity, path, and line.
+        #[clap(long, value_enum, default_value = "text")]
+        output: CheckOutputFormat,
+
+        /// Also connect to each database, resolve each endpoint's host and complete its TLS
+        /// handshake, resolve every secret, and parse each schedule.
+        #[clap(long)]
+        connect: bool,
+    },
+
+    /// Print the version; add --verbose for build target, features, and linked libraries.
+    #[clap(name = "version")]
+    Version,
+
+    /// Inspect the configuration format.
+    #[clap(name = "config")]
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Print the JSON Schema of the configuration file, for editor completion and validation.
+    #[clap(name = "schema")]
+    Schema {
+        /// Write the schema to this file instead of stdout.
+        #[clap(short, long)]
+        output: Option<String>,
+    },
+
+    /// Print a tab-completion script for subcommands and flags, e.g.
+    /// `yetii completions bash > /etc/bash_completion.d/yetii`.
+    #[clap(name = "completions")]
+    Completions {
+        #[clap(value_enum)]
+        shell: Shell,
+    },
+
+    /// Manage the encrypted keystore that `${secret:NAME}` references in the configuration
+    /// resolve from. Set YETII_KEYSTORE to move it and YETII_KEYSTORE_PASSPHRASE to key it with
+    /// a passphrase instead of a key file.
+    #[clap(name = "secret")]
+    Secret {
+        #[clap(subcommand)]
+        command: SecretCommand,
+    },
+
+    /// Encrypt a configuration file at rest. It is sealed under YETII_CONFIG_PASSPHRASE when
+    /// set, and otherwise under the key in YETII_CONFIG_KEY_FILE (default .yetii/config.key in
+    /// the file's directory).
+    #[clap(name = "encrypt")]
+    Encrypt {
+        /// File to encrypt; defaults to the configuration file.
+        path: Option<String>,
+
+        /// Write the encrypted file here instead of replacing the original.
+        #[clap(short, long)]
+        output: Option<String>,
+    },
+
+    /// Decrypt a configuration file written by `encrypt`.
+    #[clap(name = "decrypt")]
+    Decrypt {
+        /// File to decrypt; defaults to the configuration file.
+        path: Option<String>,
+
+        /// Write the plaintext here instead of replacing the encrypted file; `-` for stdout.
+        #[clap(short, long)]
+        output: Option<String>,
+    },
+
+    /// Collect redacted configuration, recent logs, state, and diagnostics into one archive
+    /// to attach to bug reports.
+    #[clap(name = "support-bundle")]
+    SupportBundle {
+        /// Archive to write; defaults to yetii-support-<timestamp>.tar.gz.
+        #[clap(short, long)]
+        output: Option<String>,
+
+        /// Log file whose most recent lines are included.
+        #[clap(long, default_value = ".yetii/yetii.log")]
+        log_file: String,
+
+        /// Number of log lines to include.
+        #[clap(long, default_value_t = 1000)]
+        log_lines: usize,
+    },
+
+    /// Time each step of the configured transforms on fixture rows and report records/sec.
+    #[clap(name = "bench-transforms")]
+    BenchTransforms {
+        /// Newline-delimited JSON file with one row object per line.
+        #[clap(long)]
+        fixture: String,
+
+        /// Only measure this query's transform.
+        #[clap(short, long)]
+        query: Option<String>,
+
+        /// Timed iterations per step; the median is reported.
+        #[clap(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+        iterations: u32,
+
+        /// Fail when a step is slower than in this file written by --save-baseline.
+        #[clap(long)]
+        baseline: Option<String>,
+
+        /// Write the measured records/sec to this file.
+        #[clap(long)]
+        save_baseline: Option<String>,
+
+        /// Percentage a step may be slower than the baseline before the command fails.
+        #[clap(long, default_value_t = 20.0)]
+        max_regression: f64,
+    },
+
+    /// Ask the running daemon for its uptime, scheduled jobs, in-flight runs, and last errors.
+    #[clap(name = "status")]
+    Status {
+        /// Control socket of the daemon; see `daemon start --control-socket`.
+        #[clap(long, default_value = ".yetii/yetii.sock")]
+        socket: String,
+
+        /// Print the daemon's reply as JSON.
+        #[clap(long)]
+        json: bool,
+    },
 
     /// Run scheduled queries continuously.
     #[clap(name = "daemon")]
@@ -59,6 +320,103 @@ pub enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Render the configuration reference generated from the configuration types.
+    Docs {
+        /// Output format.
+        #[clap(long, value_enum, default_value = "markdown")]
+        format: DocsOutputFormat,
+
+        /// Write the reference to this file instead of stdout.
+        #[clap(short, long)]
+        output: Option<String>,
+    },
+
+    /// Set one value in the configuration file, keeping comments and key order.
+    Set {
+        /// Dot-separated path; list items by index or name, e.g. queries.orders.enabled.
+        path: String,
+
+        /// New value, parsed as YAML.
+        value: String,
+    },
+
+    /// Append a query read from a YAML file to the configuration's queries.
+    AddQuery {
+        /// File containing a single query definition.
+        query_file: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SecretCommand {
+    /// Store a secret, replacing any previous value.
+    Set {
+        /// Letters, digits, '_', '-', and '.'.
+        name: String,
+
+        /// Value to store; read from stdin when omitted, so it stays out of shell history.
+        #[clap(long)]
+        value: Option<String>,
+    },
+
+    /// Print a secret's value.
+    Get { name: String },
+
+    /// List secret names and when each was last set; values are not shown.
+    List,
+
+    /// Delete a secret.
+    Remove { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum HistoryCommand {
+    /// Remove old entries now, archiving them when execution.history.archive is set. Without
+    /// options the configured max_entries and max_age_seconds apply.
+    Prune {
+        /// Keep only the newest N entries.
+        #[clap(long, value_name = "N")]
+        keep: Option<NonZeroUsize>,
+
+        /// Remove entries older than this, e.g. 30d or 12h.
+        #[clap(long, value_name = "DURATION", value_parser = crate::config::units::parse_seconds)]
+        older_than: Option<u64>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum InitTemplate {
+    /// Changed PostgreSQL rows sent to a REST API.
+    PostgresToRest,
+    /// Nightly SQL Server exports to S3 as NDJSON.
+    MssqlToS3,
+    /// Changed Oracle rows published to a Kafka topic.
+    OracleToKafka,
+    /// Records of a paginated REST API written to PostgreSQL.
+    PullApiToDb,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CheckOutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExecOutputFormat {
+    Ndjson,
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DocsOutputFormat {
+    Markdown,
+    Html,
+}
+
 #[derive(Subcommand)]
 pub enum DaemonCommand {
     /// Start the scheduler daemon.
@@ -74,6 +432,14 @@ pub enum DaemonCommand {
         /// Log file used when starting detached.
         #[clap(long, default_value = ".yetii/yetii.log")]
         log_file: String,
+
+        /// Unix socket, or named pipe on Windows, that `yetii status` queries.
+        #[clap(long, default_value = ".yetii/yetii.sock")]
+        control_socket: String,
+
+        /// Remove the run lock before starting; see `run --force-unlock`.
+        #[clap(long)]
+        force_unlock: bool,
     },
 
     /// Report whether the daemon PID is running.
@@ -90,3 +456,33 @@ pub enum DaemonCommand {
         pid_file: String,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_limit_must_be_positive() {
+        let yetii = Yetii::try_parse_from(["yetii", "run", "--limit", "100"]).unwrap();
+        assert!(matches!(
+            yetii.commands,
+            Commands::Run { limit: Some(limit), .. } if limit.get() == 100
+        ));
+        assert!(Yetii::try_parse_from(["yetii", "run", "--limit", "0"]).is_err());
+    }
+
+    #[test]
+    fn completions_cover_subcommands_and_flags() {
+        let mut script = Vec::new();
+        clap_complete::generate(
+            Shell::Bash,
+            &mut <Yetii as clap::CommandFactory>::command(),
+            "yetii",
+            &mut script,
+        );
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("check-config"));
+        assert!(script.contains("--connect"));
+        assert!(Yetii::try_parse_from(["yetii", "completions", "tcsh"]).is_err());
+    }
+}
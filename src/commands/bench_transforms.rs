@@ -0,0 +1,156 @@
+//! `yetii bench-transforms` times each step of the configured transforms on fixture rows, so
+//! a slower transform engine shows up before release rather than in production runs.
+
+use crate::config;
+use crate::transform::bench::{self, StepThroughput};
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Records per second by query, then step; the format of `--save-baseline` files.
+type Baseline = BTreeMap<String, BTreeMap<String, f64>>;
+
+pub struct BenchOptions<'a> {
+    pub fixture: &'a str,
+    pub query: Option<&'a str>,
+    pub iterations: usize,
+    pub baseline: Option<&'a str>,
+    pub save_baseline: Option<&'a str>,
+    pub max_regression: f64,
+}
+
+/// Prints records/sec per query and step; fails when a step is more than `max_regression`
+/// percent slower than in the baseline.
+pub fn run(options: &BenchOptions) -> Result<String> {
+    let rows = read_fixture(options.fixture)?;
+    let config = config::get_config()?.clone();
+    let queries: Vec<_> = config
+        .queries
+        .iter()
+        .filter(|query| options.query.is_none_or(|name| query.name == name))
+        .filter(|query| query.transform.enabled)
+        .collect();
+    if let Some(name) = options.query
+        && queries.is_empty()
+    {
+        bail!("query '{name}' was not found or its transform is disabled");
+    }
+
+    let mut measured = Baseline::new();
+    let mut report = format!(
+        "{} fixture rows, median of {} iteration(s)\n",
+        rows.len(),
+        options.iterations
+    );
+    for query in queries {
+        let steps = bench::measure(&rows, &query.transform, options.iterations)
+            .with_context(|| format!("transform for query '{}' failed", query.name))?;
+        if steps.is_empty() {
+            continue;
+        }
+        for step in &steps {
+            let _ = writeln!(
+                report,
+                "{:<24} {:<24} {:>8} records {:>14.0} records/sec",
+                query.name, step.step, step.records, step.records_per_second
+            );
+        }
+        measured.insert(query.name.clone(), throughputs(&steps));
+    }
+    if measured.is_empty() {
+        bail!("no query has transform steps to measure");
+    }
+
+    if let Some(path) = options.save_baseline {
+        let content = serde_json::to_string_pretty(&measured)?;
+        std::fs::write(path, content).with_context(|| format!("failed to write '{path}'"))?;
+        let _ = writeln!(report, "baseline written to {path}");
+    }
+    if let Some(path) = options.baseline {
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("failed to read '{path}'"))?;
+        let baseline: Baseline = serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse baseline '{path}'"))?;
+        let regressions = regressions(&baseline, &measured, options.max_regression);
+        if !regressions.is_empty() {
+            println!("{report}");
+            bail!(
+                "transform throughput regressed more than {}%: {}",
+                options.max_regression,
+                regressions.join(", ")
+            );
+        }
+        let _ = writeln!(
+            report,
+            "no step regressed more than {}%",
+            options.max_regression
+        );
+    }
+    Ok(report.trim_end().to_string())
+}
+
+/// Reads one JSON object per line; blank lines are skipped.
+fn read_fixture(path: &str) -> Result<Vec<Map<String, Value>>> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("failed to read '{path}'"))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| match serde_json::from_str(line) {
+            Ok(Value::Object(row)) => Ok(row),
+            Ok(_) => Err(anyhow!("{path}:{}: expected a JSON object", index + 1)),
+            Err(error) => Err(anyhow!("{path}:{}: {error}", index + 1)),
+        })
+        .collect()
+}
+
+fn throughputs(steps: &[StepThroughput]) -> BTreeMap<String, f64> {
+    steps
+        .iter()
+        .map(|step| (step.step.clone(), step.records_per_second))
+        .collect()
+}
+
+/// Steps slower than the baseline by more than `max_regression` percent. Steps the baseline
+/// does not know are new and not compared.
+fn regressions(baseline: &Baseline, measured: &Baseline, max_regression: f64) -> Vec<String> {
+    let mut regressions = Vec::new();
+    for (query, steps) in measured {
+        for (step, records_per_second) in steps {
+            let Some(expected) = baseline.get(query).and_then(|steps| steps.get(step)) else {
+                continue;
+            };
+            if *records_per_second < expected * (1.0 - max_regression / 100.0) {
+                regressions.push(format!(
+                    "{query} {step} ({records_per_second:.0} records/sec, baseline {expected:.0})"
+                ));
+            }
+        }
+    }
+    regressions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_steps_slower_than_the_allowed_regression() {
+        let baseline: Baseline = serde_json::from_str(
+            r#"{"orders": {"filter": 1000.0, "mapping": 1000.0}, "customers": {"group_by": 10.0}}"#,
+        )
+        .unwrap();
+        let measured: Baseline = serde_json::from_str(
+            r#"{"orders": {"filter": 850.0, "mapping": 700.0, "conversion:date": 5.0}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["orders mapping (700 records/sec, baseline 1000)".to_string()],
+            regressions(&baseline, &measured, 20.0)
+        );
+        assert!(regressions(&baseline, &measured, 40.0).is_empty());
+    }
+}
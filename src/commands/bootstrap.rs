@@ -0,0 +1,193 @@
+use crate::config;
+use anyhow::{Context, Result, bail};
+use serde_yaml::Value;
+use std::path::{Path, PathBuf};
+
+/// Directory for the daemon's PID and log files unless `--pid-file`/`--log-file` say otherwise.
+const RUNTIME_DIRECTORY: &str = ".yetii";
+
+/// A directory the configuration writes into, with what it is used for.
+#[derive(Debug, PartialEq, Eq)]
+struct Target {
+    purpose: &'static str,
+    directory: PathBuf,
+}
+
+/// Creates the directories the configuration writes into and an `.env.example` listing the
+/// variables it references, so the first `run` does not fail on a missing path.
+pub fn run(config_file: &str) -> Result<()> {
+    let content = config::read_config_text(config_file)
+        .with_context(|| format!("failed to read configuration '{config_file}'"))?;
+    // Variables are not expanded: they are usually what is still missing before the first run.
+    let document: Value = serde_yaml::from_str(&content)
+        .with_context(|| format!("configuration '{config_file}' is not valid YAML"))?;
+
+    let mut failures = Vec::new();
+    for target in targets(&document) {
+        match prepare(&target.directory) {
+            Ok(created) => println!(
+                "{} {} directory {}",
+                if created { "created" } else { "found" },
+                target.purpose,
+                target.directory.display()
+            ),
+            Err(error) => {
+                println!(
+                    "cannot use {} directory {}: {error}",
+                    target.purpose,
+                    target.directory.display()
+                );
+                failures.push(target.directory.display().to_string());
+            }
+        }
+    }
+
+    let names = config::env_var_names(&content);
+    if !names.is_empty() {
+        let config_path = Path::new(config_file);
+        let example = if config_path.is_dir() {
+            config_path
+        } else {
+            config_path.parent().unwrap_or(Path::new("."))
+        }
+        .join(".env.example");
+        if example.exists() {
+            println!("kept existing {}", example.display());
+        } else {
+            let lines = names
+                .iter()
+                .map(|name| format!("{name}=\n"))
+                .collect::<String>();
+            std::fs::write(
+                &example,
+                format!(
+                    "# Variables referenced by {config_file}; set them, or copy this file to .env, before `yetii run`.\n{lines}"
+                ),
+            )
+            .with_context(|| format!("failed to write '{}'", example.display()))?;
+            println!(
+                "wrote {} with {} variable(s)",
+                example.display(),
+                names.len()
+            );
+        }
+        let dotenv = config::dotenv::variables(Path::new(config_file))?;
+        for name in names
+            .iter()
+            .filter(|name| std::env::var_os(name).is_none() && !dotenv.contains_key(*name))
+        {
+            println!("environment variable {name} is not set");
+        }
+    }
+
+    if !failures.is_empty() {
+        bail!("not writable: {}", failures.join(", "));
+    }
+    Ok(())
+}
+
+/// Directories named by the state file, log file, reject files, and file destinations, plus
+/// the daemon's runtime directory. Paths built from `${VAR}` or `{{placeholder}}` are skipped
+/// since they are only known at run time.
+fn targets(document: &Value) -> Vec<Target> {
+    let mut targets: Vec<Target> = Vec::new();
+    let mut add = |purpose: &'static str, directory: Option<PathBuf>| {
+        let Some(directory) = directory else {
+            return;
+        };
+        let text = directory.to_string_lossy();
+        if text.is_empty() || text.contains("${") || text.contains("{{") {
+            return;
+        }
+        if !targets.iter().any(|target| target.directory == directory) {
+            targets.push(Target { purpose, directory });
+        }
+    };
+    let text = |value: &Value, path: &[&str]| {
+        path.iter()
+            .try_fold(value, |value, key| value.get(key))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let parent = |file: Option<String>| {
+        file.map(|file| match Path::new(&file).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        })
+    };
+
+    add(
+        "state",
+        parent(text(
+            document,
+            &["execution", "state_management", "state_file"],
+        )),
+    );
+    add(
+        "logs",
+        parent(text(document, &["global_settings", "logging", "file_path"])),
+    );
+    for query in document
+        .get("queries")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+    {
+        add(
+            "dead-letter",
+            parent(text(query, &["transform", "reject_file"])),
+        );
+        if text(query, &["destination", "type"]).as_deref() == Some("file") {
+            add(
+                "outbox",
+                text(query, &["destination", "directory"]).map(PathBuf::from),
+            );
+        }
+    }
+    add("runtime", Some(PathBuf::from(RUNTIME_DIRECTORY)));
+    targets
+}
+
+/// Creates `directory` when missing and checks a file can be written in it; returns whether it
+/// was created.
+fn prepare(directory: &Path) -> std::io::Result<bool> {
+    let created = !directory.is_dir();
+    if created {
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o750);
+        builder.create(directory)?;
+    }
+    let probe = directory.join(format!(".yetii-bootstrap-{}", std::process::id()));
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(&probe)?;
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_directories_the_configuration_writes_into() {
+        let document: Value = serde_yaml::from_str(
+            "global_settings:\n  logging:\n    file_path: ./logs/yetii.log\nexecution:\n  state_management:\n    state_file: ./state/yetii_state.json\nqueries:\n  - name: orders\n    transform:\n      reject_file: rejects/orders.jsonl\n    destination:\n      type: file\n      directory: ./outbox\n  - name: invoices\n    transform:\n      reject_file: rejects/invoices.jsonl\n    destination:\n      type: file\n      directory: /data/{{query}}\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                ("state", "./state"),
+                ("logs", "./logs"),
+                ("dead-letter", "rejects"),
+                ("outbox", "./outbox"),
+                ("runtime", ".yetii"),
+            ],
+            targets(&document)
+                .iter()
+                .map(|target| (target.purpose, target.directory.to_str().unwrap()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
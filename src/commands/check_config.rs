@@ -0,0 +1,339 @@
+use super::readiness::{self, Check, CheckStatus};
+use super::sql_lint;
+use crate::config::format::ConfigFormat;
+use crate::config::schedule_config;
+use crate::config::yetii::YetiiConfig;
+use crate::config::{self, ConfigError, FieldPath};
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One problem found in the configuration file.
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    pub code: &'static str,
+    pub severity: Severity,
+    /// Dotted path into the file; sequence items are named by their `name`, e.g.
+    /// `queries.orders.destination.url`.
+    pub path: Option<String>,
+    pub message: String,
+    /// 1-based line of `path`, or of the closest parent that exists in the file.
+    pub line: Option<usize>,
+    /// 1-based column where that line's key or item starts.
+    pub column: Option<usize>,
+}
+
+/// How many upcoming runs of each schedule are shown.
+const NEXT_RUNS: usize = 3;
+
+/// When a scheduled query runs next, to confirm its cron expression means what was intended.
+#[derive(Debug, Serialize)]
+pub struct Schedule {
+    pub query: String,
+    pub cron: String,
+    pub next_runs: Vec<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    file: &'a str,
+    valid: bool,
+    findings: &'a [Finding],
+    schedules: &'a [Schedule],
+    /// Readiness checks, with `--connect`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checks: Option<&'a [Check]>,
+}
+
+/// Validates the configuration, printing every finding as a line of text or as JSON, and with
+/// `connect` runs the [`readiness`] checks; fails when the configuration is invalid or a check
+/// fails.
+pub async fn run(config_file: &str, json: bool, connect: bool) -> Result<()> {
+    let loaded = config::load_unvalidated(config_file);
+    let findings = findings(config_file, &loaded);
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .count();
+    let schedules = match &loaded {
+        Ok(config) if errors == 0 => schedules(config, Utc::now()),
+        _ => Vec::new(),
+    };
+    let checks = match connect {
+        true => Some(readiness::checks(config_file, loaded.as_ref().ok()).await),
+        false => None,
+    };
+    let failed = checks
+        .iter()
+        .flatten()
+        .filter(|check| check.status == CheckStatus::Failed)
+        .count();
+
+    if json {
+        let report = Report {
+            file: config_file,
+            valid: errors == 0 && failed == 0,
+            findings: &findings,
+            schedules: &schedules,
+            checks: checks.as_deref(),
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for finding in &findings {
+            println!("{}", render(finding));
+        }
+        for schedule in &schedules {
+            let next_runs = schedule
+                .next_runs
+                .iter()
+                .map(DateTime::to_rfc3339)
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!(
+                "schedule {} ({}): next runs {next_runs}",
+                schedule.query, schedule.cron
+            );
+        }
+        if let Some(checks) = &checks {
+            print!("{}", readiness::render(checks).0);
+        }
+    }
+    if errors > 0 {
+        bail!("configuration '{config_file}' has {errors} error(s)");
+    }
+    if failed > 0 {
+        bail!("{failed} readiness check(s) failed");
+    }
+    if !json {
+        println!("configuration '{config_file}' is valid");
+    }
+    Ok(())
+}
+
+/// The next runs of every enabled schedule, in UTC like the scheduler.
+pub fn schedules(config: &YetiiConfig, now: DateTime<Utc>) -> Vec<Schedule> {
+    config
+        .queries
+        .iter()
+        .filter(|query| query.enabled || query.until.is_some())
+        .filter_map(|query| {
+            let schedule = query
+                .schedule
+                .as_ref()
+                .filter(|schedule| schedule.enabled)?;
+            Some(Schedule {
+                query: query.name.clone(),
+                cron: schedule.cron.clone(),
+                next_runs: schedule_config::next_fire_times(&schedule.cron, now, NEXT_RUNS).ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Findings for `config_file`, given the result of loading it without validation: every
+/// validation error, then warnings and, for a valid configuration, SQL issues, in file order.
+pub fn findings(config_file: &str, loaded: &Result<YetiiConfig, ConfigError>) -> Vec<Finding> {
+    let path = std::path::Path::new(config_file);
+    let format = ConfigFormat::from_path(path);
+    let text = config::read_file(path)
+        .map(|(text, _)| text)
+        .unwrap_or_default();
+    let locate = |field: &FieldPath| format.locate(&text, field.keys()).unzip();
+    let finding = |code, severity, field: FieldPath, message| {
+        let (line, column) = locate(&field);
+        Finding {
+            code,
+            severity,
+            path: Some(field.keys().join(".")),
+            message,
+            line,
+            column,
+        }
+    };
+
+    let mut findings =
+        match loaded {
+            Ok(config) => {
+                let mut findings = config
+                    .validation_errors()
+                    .into_iter()
+                    .map(|error| error_finding(&error, format, &text, locate))
+                    .collect::<Vec<_>>();
+                findings.extend(config.warnings(Utc::now().date_naive()).into_iter().map(
+                    |warning| {
+                        finding(
+                            warning.code,
+                            Severity::Warning,
+                            warning.field,
+                            warning.message,
+                        )
+                    },
+                ));
+                // SQL is linted against the databases, which are only trustworthy once valid.
+                if findings
+                    .iter()
+                    .all(|finding| finding.severity != Severity::Error)
+                {
+                    findings.extend(sql_lint::lint(config).into_iter().map(|issue| {
+                        finding(issue.code, issue.severity, issue.field, issue.message)
+                    }));
+                }
+                findings
+            }
+            Err(error) => vec![error_finding(error, format, &text, locate)],
+        };
+    findings.sort_by_key(|finding| (finding.line.is_none(), finding.line, finding.column));
+    findings
+}
+
+/// An error finding for `error`, located in the configuration `text` written in `format`.
+fn error_finding(
+    error: &ConfigError,
+    format: ConfigFormat,
+    text: &str,
+    locate: impl Fn(&FieldPath) -> (Option<usize>, Option<usize>),
+) -> Finding {
+    let parse_location = match error {
+        // JSON and TOML are parsed as converted YAML text, whose lines are not the file's.
+        ConfigError::SerializationError(source) | ConfigError::Deserialization { source, .. }
+            if format == ConfigFormat::Yaml =>
+        {
+            source.location()
+        }
+        _ => None,
+    };
+    let (line, column) = match (parse_location, error) {
+        (Some(location), _) => (Some(location.line()), Some(location.column())),
+        (None, ConfigError::MissingEnvironmentVariable(name)) => {
+            let reference = format!("${{{name}}}");
+            text.lines()
+                .enumerate()
+                .find_map(|(index, line)| Some((index + 1, line.find(&reference)? + 1)))
+                .unzip()
+        }
+        (None, error) => error.path().map_or((None, None), locate),
+    };
+    Finding {
+        code: error.code(),
+        severity: Severity::Error,
+        path: error
+            .path()
+            .filter(|path| !path.keys().is_empty())
+            .map(|path| path.keys().join(".")),
+        message: error.to_string(),
+        line,
+        column,
+    }
+}
+
+/// `error[invalid_value] databases.erp.port (line 6, column 5): ...`, one line per finding.
+fn render(finding: &Finding) -> String {
+    let severity = match finding.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    let mut rendered = format!("{severity}[{}]", finding.code);
+    if let Some(path) = &finding.path {
+        rendered.push_str(&format!(" {path}"));
+    }
+    if let (Some(line), Some(column)) = (finding.line, finding.column) {
+        rendered.push_str(&format!(" (line {line}, column {column})"));
+    }
+    format!("{rendered}: {}", finding.message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_every_failing_field_with_its_location() {
+        let path =
+            std::env::temp_dir().join(format!("yetii-check-config-{}.yaml", std::process::id()));
+        std::fs::write(
+            &path,
+            "version: '1.0.0'\ndatabases:\n  - name: erp\n    type: postgres\n    host: localhost\n    port: 0\n    database: erp\n    auth:\n      username: yetii\n      password: secret\nglobal_settings:\n  environment: development\nexecution:\n  mode: sequential\nqueries:\n  - name: orders\n    description: Orders\n    query:\n      sql: SELECT 1\n    destination:\n      type: redis\n      url: http://cache\n      key: order\n",
+        )
+        .unwrap();
+
+        let findings = findings(
+            path.to_str().unwrap(),
+            &config::load_unvalidated(path.to_str().unwrap()),
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(2, findings.len());
+        assert!(
+            findings
+                .iter()
+                .all(|finding| finding.code == "invalid_value"
+                    && finding.severity == Severity::Error)
+        );
+        assert_eq!(Some("databases.erp.port"), findings[0].path.as_deref());
+        assert_eq!((Some(6), Some(5)), (findings[0].line, findings[0].column));
+        assert_eq!(
+            Some("queries.orders.destination.url"),
+            findings[1].path.as_deref()
+        );
+        assert_eq!((Some(22), Some(7)), (findings[1].line, findings[1].column));
+    }
+
+    #[test]
+    fn locates_findings_in_toml_and_json_files() {
+        let files = [
+            (
+                "toml",
+                "version = '1.0.0'\n\n[[databases]]\nname = 'erp'\ntype = 'postgres'\nhost = 'localhost'\nport = 0\ndatabase = 'erp'\n\n[databases.auth]\nusername = 'yetii'\npassword = 'secret'\n\n[global_settings]\nenvironment = 'development'\n\n[execution]\nmode = 'sequential'\n\n[[queries]]\nname = 'orders'\ndescription = 'Orders'\n\n[queries.query]\nsql = 'SELECT 1'\n\n[queries.endpoint]\nurl = 'http://api'\nmethod = 'POST'\n",
+                (7, 1),
+            ),
+            (
+                "json",
+                "{\n  \"version\": \"1.0.0\",\n  \"databases\": [\n    {\n      \"name\": \"erp\",\n      \"type\": \"postgres\",\n      \"host\": \"localhost\",\n      \"port\": 0,\n      \"database\": \"erp\",\n      \"auth\": {\"username\": \"yetii\", \"password\": \"secret\"}\n    }\n  ],\n  \"global_settings\": {\"environment\": \"development\"},\n  \"execution\": {\"mode\": \"sequential\"},\n  \"queries\": [\n    {\n      \"name\": \"orders\",\n      \"description\": \"Orders\",\n      \"query\": {\"sql\": \"SELECT 1\"},\n      \"endpoint\": {\"url\": \"http://api\", \"method\": \"POST\"}\n    }\n  ]\n}\n",
+                (8, 7),
+            ),
+        ];
+        for (extension, content, location) in files {
+            let path = std::env::temp_dir().join(format!(
+                "yetii-check-config-{}.{extension}",
+                std::process::id()
+            ));
+            std::fs::write(&path, content).unwrap();
+            let findings = findings(
+                path.to_str().unwrap(),
+                &config::load_unvalidated(path.to_str().unwrap()),
+            );
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(1, findings.len(), "{extension}: {findings:?}");
+            assert_eq!(Some("databases.erp.port"), findings[0].path.as_deref());
+            assert_eq!(
+                (Some(location.0), Some(location.1)),
+                (findings[0].line, findings[0].column),
+                "{extension}"
+            );
+        }
+    }
+
+    #[test]
+    fn renders_findings_as_lines() {
+        let finding = Finding {
+            code: "invalid_value",
+            severity: Severity::Error,
+            path: Some("databases.erp.port".to_string()),
+            message: "invalid configuration value for database 'erp'.port: 0".to_string(),
+            line: Some(6),
+            column: Some(5),
+        };
+        assert_eq!(
+            "error[invalid_value] databases.erp.port (line 6, column 5): invalid configuration value for database 'erp'.port: 0",
+            render(&finding)
+        );
+    }
+}
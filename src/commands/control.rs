@@ -0,0 +1,329 @@
+//! The daemon's control socket: a Unix socket, or a named pipe on Windows, where `yetii status`
+//! reads the daemon's uptime, schedule, in-flight runs, and last errors instead of its logs.
+//! A client writes one command line and reads the reply until the daemon closes the stream.
+
+use crate::monitoring;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use std::fmt::Write;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::task::JoinHandle;
+
+/// Commands are single words; anything longer is not a client of ours.
+const MAX_COMMAND_BYTES: u64 = 64;
+
+/// Serves the control socket until dropped.
+pub struct ControlSocket {
+    server: JoinHandle<()>,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    path: String,
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        self.server.abort();
+        #[cfg(unix)]
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+pub fn listen(path: &str) -> Result<ControlSocket> {
+    if std::path::Path::new(path).exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            bail!("control socket '{path}' is in use by another daemon");
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("failed to remove stale control socket '{path}'"))?;
+    }
+    if let Some(parent) = std::path::Path::new(path).parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create directory '{}'", parent.display()))?;
+    }
+    let listener = bind_private(std::path::Path::new(path))?;
+    let server = tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve(stream));
+                }
+                Err(error) => {
+                    tracing::warn!(error = %error, "control socket accept failed");
+                    return;
+                }
+            }
+        }
+    });
+    Ok(ControlSocket {
+        server,
+        path: path.to_string(),
+    })
+}
+
+/// Binds the socket inside a fresh directory only the daemon's user can enter, restricts it
+/// to that user, and only then moves it to `path`, so no other user can connect in between.
+#[cfg(unix)]
+fn bind_private(path: &std::path::Path) -> Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let private = path.with_file_name(format!(".{file_name}.{}", std::process::id()));
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&private)
+        .with_context(|| format!("failed to create directory '{}'", private.display()))?;
+    let staged = private.join("control.sock");
+    let bound = tokio::net::UnixListener::bind(&staged)
+        .with_context(|| format!("failed to bind control socket '{}'", path.display()))
+        .and_then(|listener| {
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))
+                .and_then(|()| std::fs::rename(&staged, path))
+                .with_context(|| {
+                    format!("failed to restrict control socket '{}'", path.display())
+                })?;
+            Ok(listener)
+        });
+    let _ = std::fs::remove_file(&staged);
+    let _ = std::fs::remove_dir(&private);
+    bound
+}
+
+#[cfg(windows)]
+pub fn listen(path: &str) -> Result<ControlSocket> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let name = pipe_name(path);
+    let mut pipe = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(&name)
+        .with_context(|| format!("failed to create control pipe '{name}'"))?;
+    let server = tokio::spawn(async move {
+        loop {
+            if let Err(error) = pipe.connect().await {
+                tracing::warn!(error = %error, "control pipe connection failed");
+                return;
+            }
+            let connected = pipe;
+            pipe = match ServerOptions::new().create(&name) {
+                Ok(next) => next,
+                Err(error) => {
+                    tracing::warn!(error = %error, "control pipe could not accept more clients");
+                    return;
+                }
+            };
+            tokio::spawn(serve(connected));
+        }
+    });
+    Ok(ControlSocket {
+        server,
+        path: path.to_string(),
+    })
+}
+
+/// Sends `command` to the daemon listening on `path` and returns its reply.
+#[cfg(unix)]
+pub async fn request(path: &str, command: &str) -> Result<String> {
+    let stream = tokio::net::UnixStream::connect(path)
+        .await
+        .with_context(|| format!("no daemon is listening on control socket '{path}'"))?;
+    exchange(stream, command).await
+}
+
+#[cfg(windows)]
+pub async fn request(path: &str, command: &str) -> Result<String> {
+    let name = pipe_name(path);
+    let pipe = tokio::net::windows::named_pipe::ClientOptions::new()
+        .open(&name)
+        .with_context(|| format!("no daemon is listening on control pipe '{name}'"))?;
+    exchange(pipe, command).await
+}
+
+/// Named pipes live in their own namespace, so the socket path becomes part of the pipe name.
+#[cfg(windows)]
+fn pipe_name(path: &str) -> String {
+    let name = path
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() {
+                character
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    format!(r"\\.\pipe\yetii-{name}")
+}
+
+async fn exchange(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    command: &str,
+) -> Result<String> {
+    stream
+        .write_all(format!("{command}\n").as_bytes())
+        .await
+        .context("failed to send the control command")?;
+    let mut reply = String::new();
+    stream
+        .read_to_string(&mut reply)
+        .await
+        .context("failed to read the daemon's reply")?;
+    Ok(reply)
+}
+
+async fn serve(stream: impl AsyncRead + AsyncWrite + Unpin) {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut command = String::new();
+    if let Err(error) = BufReader::new(reader.take(MAX_COMMAND_BYTES))
+        .read_line(&mut command)
+        .await
+    {
+        tracing::debug!(error = %error, "control socket client sent no command");
+        return;
+    }
+    let reply = match command.trim() {
+        "status" => monitoring::status(),
+        other => json!({ "error": format!("unknown command '{other}'") }),
+    };
+    if let Err(error) = writer.write_all(format!("{reply}\n").as_bytes()).await {
+        tracing::debug!(error = %error, "control socket reply failed");
+    }
+    let _ = writer.shutdown().await;
+}
+
+/// Human-readable form of the `status` reply.
+pub fn render_status(status: &Value, now: DateTime<Utc>) -> Result<String> {
+    if let Some(error) = status["error"].as_str() {
+        bail!("daemon refused the request: {error}");
+    }
+    let time = |value: &Value| {
+        value
+            .as_str()
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.with_timezone(&Utc))
+    };
+    let mut output = String::new();
+    write!(
+        output,
+        "Yetii daemon pid {}: {}",
+        status["pid"],
+        status["status"].as_str().unwrap_or("unknown")
+    )?;
+    if let Some(started_at) = time(&status["started_at"]) {
+        write!(output, ", up {}", uptime(now - started_at))?;
+    }
+    output.push('\n');
+
+    let jobs = status["scheduler"]["jobs"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    writeln!(output, "\nScheduled jobs: {}", jobs.len())?;
+    for job in &jobs {
+        writeln!(
+            output,
+            "  {}  {}",
+            job["query"].as_str().unwrap_or_default(),
+            job["cron"].as_str().unwrap_or_default()
+        )?;
+    }
+
+    let queries = status["queries"].as_object().cloned().unwrap_or_default();
+    let in_flight = queries
+        .iter()
+        .filter(|(_, query)| query["active"] == true)
+        .collect::<Vec<_>>();
+    writeln!(output, "\nIn-flight runs: {}", in_flight.len())?;
+    for (name, query) in in_flight {
+        match time(&query["last_started_at"]) {
+            Some(started_at) => {
+                writeln!(output, "  {name}  running for {}", uptime(now - started_at))?
+            }
+            None => writeln!(output, "  {name}")?,
+        }
+    }
+
+    let mut errors = queries
+        .iter()
+        .filter_map(|(name, query)| Some((name, query["last_error"].as_str()?, query)))
+        .collect::<Vec<_>>();
+    errors.sort_by_key(|(_, _, query)| std::cmp::Reverse(time(&query["last_failure_at"])));
+    writeln!(output, "\nLast errors: {}", errors.len())?;
+    for (name, error, query) in errors {
+        match time(&query["last_failure_at"]) {
+            Some(failed_at) => writeln!(
+                output,
+                "  {name}  {}  {error}",
+                failed_at.format("%Y-%m-%d %H:%M:%S UTC")
+            )?,
+            None => writeln!(output, "  {name}  {error}")?,
+        }
+    }
+    Ok(output)
+}
+
+fn uptime(elapsed: chrono::Duration) -> String {
+    let seconds = elapsed.num_seconds().max(0);
+    let (days, hours, minutes) = (seconds / 86_400, seconds / 3600 % 24, seconds / 60 % 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{seconds}s"),
+        (0, 0, _) => format!("{minutes}m"),
+        (0, _, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h"),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_status_over_the_socket() {
+        let path = std::env::temp_dir().join(format!("yetii-control-{}.sock", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let socket = listen(&path).unwrap();
+        assert!(listen(&path).is_err());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+
+        let reply: Value = serde_json::from_str(&request(&path, "status").await.unwrap()).unwrap();
+        assert_eq!(std::process::id(), reply["pid"]);
+        let reply: Value = serde_json::from_str(&request(&path, "reboot").await.unwrap()).unwrap();
+        assert_eq!("unknown command 'reboot'", reply["error"]);
+
+        let now = Utc::now();
+        let status = json!({
+            "status": "healthy",
+            "pid": 4242,
+            "started_at": (now - chrono::Duration::minutes(125)).to_rfc3339(),
+            "scheduler": {"jobs": [{"query": "orders", "cron": "0 */5 * * * *"}]},
+            "queries": {
+                "orders": {
+                    "active": true,
+                    "last_started_at": (now - chrono::Duration::seconds(42)).to_rfc3339(),
+                },
+                "customers": {
+                    "active": false,
+                    "last_error": "endpoint returned unexpected status 503",
+                    "last_failure_at": "2026-10-16T02:00:00Z",
+                },
+            },
+        });
+        assert_eq!(
+            "Yetii daemon pid 4242: healthy, up 2h 5m\n\
+             \nScheduled jobs: 1\n  orders  0 */5 * * * *\n\
+             \nIn-flight runs: 1\n  orders  running for 42s\n\
+             \nLast errors: 1\n  customers  2026-10-16 02:00:00 UTC  endpoint returned unexpected status 503\n",
+            render_status(&status, now).unwrap()
+        );
+
+        drop(socket);
+        assert!(!std::path::Path::new(&path).exists());
+    }
+}
@@ -1,10 +1,17 @@
+use super::control;
+use super::reload::Reloads;
 use super::run;
+use super::run_lock::RunLock;
+use super::systemd;
+use crate::capabilities::Capabilities;
 use crate::cli::Yetii;
 use crate::config;
 use crate::config::execution_config::SchedulerConfig;
-use crate::config::monitor_config::NotificationEventKind;
+use crate::config::monitor_config::{MonitoringConfig, NotificationEventKind};
 use crate::config::query_config::QueryConfig;
 use crate::config::schedule_config::normalized_cron;
+use crate::database;
+use crate::history::{self, HistoryEntry, HistoryKind, HistoryLog, HistoryStatus, Trigger};
 use crate::monitoring;
 use crate::notifications::{self, NotificationEvent};
 use anyhow::{Context, Result, bail};
@@ -13,14 +20,20 @@ use std::fs::{OpenOptions, read_to_string, remove_file};
 use std::path::Path;
 use std::process::{Command, Stdio};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, Semaphore};
 use tokio_cron_scheduler::{Job, JobScheduler};
 
+/// How often databases are checked when `database_check_seconds` is not set.
+const DATABASE_CHECK_SECONDS: u32 = 60;
+/// A database that has not accepted a connection by then counts as unreachable.
+const DATABASE_CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ScheduledQuery {
     pub name: String,
     pub cron: String,
+    pub run_on_start: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -29,12 +42,19 @@ struct SchedulerRuntimeConfig {
     job_timeout_minutes: Option<u32>,
 }
 
-pub async fn start(yetii: &Yetii, detach: bool, pid_file: &str, log_file: &str) -> Result<String> {
+pub async fn start(
+    yetii: &Yetii,
+    detach: bool,
+    pid_file: &str,
+    log_file: &str,
+    control_socket: &str,
+    force_unlock: bool,
+) -> Result<String> {
     if detach {
-        return start_detached(yetii, pid_file, log_file);
+        return start_detached(yetii, pid_file, log_file, control_socket, force_unlock);
     }
 
-    run_foreground(pid_file).await
+    run_foreground(yetii.config_path(), pid_file, control_socket, force_unlock).await
 }
 
 pub fn status(pid_file: &str) -> Result<String> {
@@ -62,67 +82,69 @@ pub fn stop(pid_file: &str) -> Result<String> {
     Ok(format!("Stop signal sent to Yetii daemon pid {pid}"))
 }
 
-async fn run_foreground(pid_file: &str) -> Result<String> {
+async fn run_foreground(
+    config_path: &str,
+    pid_file: &str,
+    control_socket: &str,
+    force_unlock: bool,
+) -> Result<String> {
     ensure_no_running_pid(pid_file)?;
     write_pid_file(pid_file, std::process::id())?;
     let _pid_guard = PidFileGuard::new(pid_file);
+    monitoring::initialize();
+    let _control_socket = control::listen(control_socket)?;
 
     let config = config::get_config()?.clone();
-    let monitoring_server = monitoring::start(config.monitoring.as_ref()).await?;
+    let _run_lock = RunLock::acquire_for(&config, config_path, "daemon start", force_unlock)?;
+    let reload_config = config
+        .execution
+        .scheduler
+        .as_ref()
+        .map(|scheduler| scheduler.reload.clone())
+        .unwrap_or_default();
+    let mut reloads = Reloads::start(config_path, &reload_config)?;
+    let reload_endpoint = reload_config
+        .endpoint
+        .map(|path| monitoring::ReloadEndpoint {
+            path,
+            sender: reloads.sender(),
+        });
+    let monitoring_server = monitoring::start(config.monitoring.as_ref(), reload_endpoint).await?;
     let runtime = scheduler_runtime_config(config.execution.scheduler.as_ref())?;
     let scheduled_queries = scheduled_queries(&config.queries)?;
+    // Each run fails such queries with the same message; saying so at startup saves a wait.
+    let capabilities = Capabilities::detect();
+    for query in &scheduled_queries {
+        if let Some(database) = config
+            .queries
+            .iter()
+            .find(|candidate| candidate.name == query.name)
+            .and_then(|candidate| {
+                config
+                    .databases
+                    .resolve_for_query(candidate.database.as_deref())
+            })
+            && let Some(missing) = capabilities.missing(database)
+        {
+            tracing::error!(query = %query.name, "{missing}");
+        }
+    }
     if scheduled_queries.is_empty() {
         tracing::warn!("no enabled scheduled queries found");
     }
 
-    let scheduler = JobScheduler::new()
-        .await
-        .context("failed to create scheduler")?;
     let semaphore = Arc::new(Semaphore::new(runtime.max_concurrent_jobs));
     let running_queries = Arc::new(Mutex::new(HashSet::<String>::new()));
-
-    for scheduled_query in scheduled_queries {
-        let query_name = scheduled_query.name.clone();
-        let cron = scheduled_query.cron.clone();
-        let semaphore = semaphore.clone();
-        let running_queries = running_queries.clone();
-        let timeout_minutes = runtime.job_timeout_minutes;
-        scheduler
-            .add(Job::new_async(cron.clone(), move |_uuid, _lock| {
-                let query_name = query_name.clone();
-                let semaphore = semaphore.clone();
-                let running_queries = running_queries.clone();
-                Box::pin(async move {
-                    {
-                        let mut running = running_queries.lock().await;
-                        if !running.insert(query_name.clone()) {
-                            monitoring::record_overlap_skip(&query_name);
-                            tracing::warn!(query = %query_name, "overlapping scheduled execution skipped");
-                            return;
-                        }
-                    }
-                    let Ok(_permit) = semaphore.acquire_owned().await else {
-                        running_queries.lock().await.remove(&query_name);
-                        tracing::error!(query = %query_name, "scheduler concurrency limiter was closed");
-                        return;
-                    };
-                    run_scheduled_query(query_name.clone(), timeout_minutes).await;
-                    running_queries.lock().await.remove(&query_name);
-                })
-            })?)
-            .await
-            .with_context(|| format!("failed to register scheduled query '{}'", scheduled_query.name))?;
-        tracing::info!(
-            query = %scheduled_query.name,
-            cron = %scheduled_query.cron,
-            "scheduled query registered"
-        );
-    }
-
-    scheduler
-        .start()
-        .await
-        .context("failed to start scheduler")?;
+    let mut scheduler = start_scheduler(
+        &scheduled_queries,
+        &semaphore,
+        &running_queries,
+        runtime.job_timeout_minutes,
+    )
+    .await?;
+    monitoring::set_schedule(scheduled_jobs(&scheduled_queries));
+    let database_checks = database_check_interval(config.monitoring.as_ref())
+        .map(|interval| tokio::spawn(check_databases(interval)));
     tracing::info!(
         pid = std::process::id(),
         pid_file,
@@ -130,13 +152,53 @@ async fn run_foreground(pid_file: &str) -> Result<String> {
         "Yetii daemon started"
     );
     monitoring::set_ready(true);
+    systemd::notify("READY=1");
+    let watchdog = systemd::watchdog_interval().map(systemd::start_watchdog);
     notify_daemon_lifecycle(NotificationEventKind::DaemonStarted).await;
+    for scheduled_query in scheduled_queries.iter().filter(|query| query.run_on_start) {
+        tracing::info!(query = %scheduled_query.name, "running query on start");
+        tokio::spawn(run_exclusive(
+            scheduled_query.name.clone(),
+            semaphore.clone(),
+            running_queries.clone(),
+            runtime.job_timeout_minutes,
+            Trigger::Startup,
+        ));
+    }
 
-    shutdown_signal().await?;
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            result = &mut shutdown => {
+                result?;
+                break;
+            }
+            Some(trigger) = reloads.next() => {
+                systemd::notify("RELOADING=1");
+                scheduler = reload(
+                    config_path,
+                    trigger,
+                    scheduler,
+                    &semaphore,
+                    &running_queries,
+                    runtime.job_timeout_minutes,
+                )
+                .await;
+                systemd::notify("READY=1");
+            }
+        }
+    }
     tracing::info!("shutdown signal received");
+    systemd::notify("STOPPING=1");
+    if let Some(watchdog) = watchdog {
+        watchdog.abort();
+    }
     monitoring::set_shutting_down();
+    if let Some(database_checks) = database_checks {
+        database_checks.abort();
+    }
     notify_daemon_lifecycle(NotificationEventKind::DaemonStopping).await;
-    let mut scheduler = scheduler;
     scheduler
         .shutdown()
         .await
@@ -151,10 +213,255 @@ async fn run_foreground(pid_file: &str) -> Result<String> {
     Ok("Yetii daemon stopped".to_string())
 }
 
-async fn run_scheduled_query(query_name: String, timeout_minutes: Option<u32>) {
+/// Registers one job per scheduled query on a new scheduler and starts it. Jobs share
+/// `semaphore` and `running_queries` across reloads, so limits and overlap checks still hold.
+async fn start_scheduler(
+    scheduled_queries: &[ScheduledQuery],
+    semaphore: &Arc<Semaphore>,
+    running_queries: &Arc<Mutex<HashSet<String>>>,
+    timeout_minutes: Option<u32>,
+) -> Result<JobScheduler> {
+    let scheduler = JobScheduler::new()
+        .await
+        .context("failed to create scheduler")?;
+    // The systemd watchdog is only pinged while this job keeps running.
+    if let Some(watchdog) = systemd::watchdog_interval() {
+        scheduler
+            .add(Job::new_repeated_async(
+                systemd::heartbeat_interval(watchdog),
+                |_uuid, _lock| Box::pin(async { systemd::scheduler_ticked() }),
+            )?)
+            .await
+            .context("failed to register the watchdog heartbeat")?;
+    }
+
+    for scheduled_query in scheduled_queries {
+        let query_name = scheduled_query.name.clone();
+        let cron = scheduled_query.cron.clone();
+        let semaphore = semaphore.clone();
+        let running_queries = running_queries.clone();
+        scheduler
+            .add(Job::new_async(cron.clone(), move |_uuid, _lock| {
+                Box::pin(run_exclusive(
+                    query_name.clone(),
+                    semaphore.clone(),
+                    running_queries.clone(),
+                    timeout_minutes,
+                    Trigger::Schedule,
+                ))
+            })?)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to register scheduled query '{}'",
+                    scheduled_query.name
+                )
+            })?;
+        tracing::info!(
+            query = %scheduled_query.name,
+            cron = %scheduled_query.cron,
+            "scheduled query registered"
+        );
+    }
+
+    scheduler
+        .start()
+        .await
+        .context("failed to start scheduler")?;
+    Ok(scheduler)
+}
+
+/// Runs the query unless a run of it is in progress, within the concurrency limit.
+async fn run_exclusive(
+    query_name: String,
+    semaphore: Arc<Semaphore>,
+    running_queries: Arc<Mutex<HashSet<String>>>,
+    timeout_minutes: Option<u32>,
+    trigger: Trigger,
+) {
+    {
+        let mut running = running_queries.lock().await;
+        if !running.insert(query_name.clone()) {
+            monitoring::record_overlap_skip(&query_name);
+            tracing::warn!(query = %query_name, "overlapping scheduled execution skipped");
+            return;
+        }
+    }
+    let Ok(_permit) = semaphore.acquire_owned().await else {
+        running_queries.lock().await.remove(&query_name);
+        tracing::error!(query = %query_name, "scheduler concurrency limiter was closed");
+        return;
+    };
+    run_scheduled_query(query_name.clone(), timeout_minutes, trigger).await;
+    running_queries.lock().await.remove(&query_name);
+    if let Ok(config) = config::get_config().map(|config| config.clone()) {
+        history::compact_configured(&config).await;
+    }
+}
+
+/// A run cut short by `job_timeout_minutes` never finishes its own history entry.
+fn record_timeout(
+    query_name: &str,
+    trigger: Trigger,
+    started_at: chrono::DateTime<chrono::Utc>,
+    error: &str,
+) {
+    let Some(history) = config::get_config()
+        .ok()
+        .and_then(|config| HistoryLog::from_config(&config))
+    else {
+        return;
+    };
+    let entry = HistoryEntry {
+        kind: HistoryKind::Query,
+        query: query_name.to_string(),
+        flow: None,
+        run_id: None,
+        trigger,
+        started_at,
+        finished_at: chrono::Utc::now(),
+        status: HistoryStatus::Failure,
+        rows_read: 0,
+        rows_delivered: 0,
+        batches_sent: 0,
+        batches_failed: 0,
+        error: Some(error.to_string()),
+    };
+    if let Err(error) = history.append(&entry) {
+        tracing::warn!(query = %query_name, error = %error, "run history was not recorded");
+    }
+}
+
+/// Loads the configuration again and replaces the schedule with its queries. When it fails to
+/// load, validate, or schedule, the running configuration and schedule are kept.
+async fn reload(
+    config_path: &str,
+    trigger: &str,
+    scheduler: JobScheduler,
+    semaphore: &Arc<Semaphore>,
+    running_queries: &Arc<Mutex<HashSet<String>>>,
+    timeout_minutes: Option<u32>,
+) -> JobScheduler {
+    tracing::info!(trigger, "reloading configuration");
+    let replacement = async {
+        let config = config::load_config(config_path)
+            .with_context(|| format!("failed to load configuration '{config_path}'"))?;
+        let scheduled_queries = scheduled_queries(&config.queries)?;
+        let replacement = start_scheduler(
+            &scheduled_queries,
+            semaphore,
+            running_queries,
+            timeout_minutes,
+        )
+        .await?;
+        config::replace_config(config)?;
+        anyhow::Ok((replacement, scheduled_queries))
+    }
+    .await;
+
+    match replacement {
+        Ok((replacement, scheduled_queries)) => {
+            let mut previous = scheduler;
+            if let Err(error) = previous.shutdown().await {
+                tracing::warn!(error = %error, "failed to stop the previous schedule");
+            }
+            monitoring::set_schedule(scheduled_jobs(&scheduled_queries));
+            tracing::info!(
+                trigger,
+                scheduled_queries = scheduled_queries.len(),
+                "configuration reloaded"
+            );
+            replacement
+        }
+        Err(error) => {
+            tracing::error!(
+                trigger,
+                error = %format!("{error:#}"),
+                "configuration reload failed; keeping the running configuration"
+            );
+            scheduler
+        }
+    }
+}
+
+fn scheduled_jobs(scheduled_queries: &[ScheduledQuery]) -> Vec<monitoring::ScheduledJob> {
+    scheduled_queries
+        .iter()
+        .map(|query| monitoring::ScheduledJob {
+            query: query.name.clone(),
+            cron: query.cron.clone(),
+        })
+        .collect()
+}
+
+/// `None` when the health endpoint is off or `database_check_seconds` is `0`.
+fn database_check_interval(monitoring: Option<&MonitoringConfig>) -> Option<Duration> {
+    let health = monitoring
+        .filter(|monitoring| monitoring.enabled)?
+        .health_check
+        .as_ref()
+        .filter(|health| health.enabled)?;
+    let seconds = health
+        .database_check_seconds
+        .unwrap_or(DATABASE_CHECK_SECONDS);
+    (seconds > 0).then(|| Duration::from_secs(seconds.into()))
+}
+
+/// Opens a session to each configured database every `interval` and reports the outcome on
+/// the health endpoint. The databases are read again each round, so reloads are followed.
+async fn check_databases(interval: Duration) {
+    loop {
+        let databases = match config::get_config() {
+            Ok(config) => config.databases.as_slice().to_vec(),
+            Err(error) => {
+                tracing::warn!(error = %error, "database checks skipped");
+                Vec::new()
+            }
+        };
+        let mut checks = Vec::with_capacity(databases.len());
+        for database in databases {
+            let started = Instant::now();
+            let error = match tokio::time::timeout(
+                DATABASE_CHECK_TIMEOUT,
+                database::open_session(&database),
+            )
+            .await
+            {
+                Ok(Ok(_session)) => None,
+                Ok(Err(error)) => Some(error.to_string()),
+                Err(_) => Some(format!(
+                    "no connection after {}s",
+                    DATABASE_CHECK_TIMEOUT.as_secs()
+                )),
+            };
+            if let Some(error) = &error {
+                tracing::warn!(database = %database.name, error = %error, "database is unreachable");
+            }
+            checks.push((database.name, error, started.elapsed()));
+        }
+        monitoring::databases_checked(checks);
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn run_scheduled_query(query_name: String, timeout_minutes: Option<u32>, trigger: Trigger) {
+    if let Ok(config) = config::get_config()
+        && let Some(query) = config
+            .queries
+            .iter()
+            .find(|query| query.name == query_name && !query.is_enabled())
+    {
+        tracing::debug!(
+            query = %query_name,
+            until = ?query.until,
+            "scheduled query skipped; query is disabled"
+        );
+        return;
+    }
     let started = Instant::now();
+    let started_at = chrono::Utc::now();
     tracing::info!(query = %query_name, "scheduled query started");
-    let run_future = run::run(Some(&query_name), false);
+    let run_future = run::run(Some(&query_name), false, None, trigger);
     let result = if let Some(timeout_minutes) = timeout_minutes.filter(|value| *value > 0) {
         match tokio::time::timeout(
             std::time::Duration::from_secs(timeout_minutes as u64 * 60),
@@ -166,6 +473,7 @@ async fn run_scheduled_query(query_name: String, timeout_minutes: Option<u32>) {
             Err(_) => {
                 let error = "scheduled query timed out";
                 monitoring::query_failed(&query_name, error, 0, 0, 0, started.elapsed());
+                record_timeout(&query_name, trigger, started_at, error);
                 notify_scheduled_failure(&query_name, error, started.elapsed()).await;
                 tracing::error!(
                     query = %query_name,
@@ -184,6 +492,8 @@ async fn run_scheduled_query(query_name: String, timeout_minutes: Option<u32>) {
             query = %query_name,
             rows_read = report.rows_read,
             batches_sent = report.batches_sent,
+            duplicates_skipped = report.duplicates_skipped,
+            duplicates_updated = report.duplicates_updated,
             duration_ms = started.elapsed().as_millis(),
             "scheduled query completed"
         ),
@@ -191,6 +501,7 @@ async fn run_scheduled_query(query_name: String, timeout_minutes: Option<u32>) {
             query = %query_name,
             rows_read = report.rows_read,
             batches_sent = report.batches_sent,
+            batches_failed = report.batches_failed,
             failures = report.failures.len(),
             duration_ms = started.elapsed().as_millis(),
             "scheduled query completed with failures"
@@ -254,7 +565,8 @@ async fn notify_daemon_lifecycle(event_kind: NotificationEventKind) {
 pub fn scheduled_queries(queries: &[QueryConfig]) -> Result<Vec<ScheduledQuery>> {
     queries
         .iter()
-        .filter(|query| query.enabled)
+        // Queries disabled until a date are scheduled too and skipped until it arrives.
+        .filter(|query| query.enabled || query.until.is_some())
         .filter_map(|query| {
             let schedule = query.schedule.as_ref()?;
             schedule.enabled.then_some((query, schedule))
@@ -263,6 +575,7 @@ pub fn scheduled_queries(queries: &[QueryConfig]) -> Result<Vec<ScheduledQuery>>
             Ok(ScheduledQuery {
                 name: query.name.clone(),
                 cron: normalized_cron(&schedule.cron)?,
+                run_on_start: schedule.run_on_start,
             })
         })
         .collect()
@@ -282,9 +595,6 @@ fn scheduler_runtime_config(scheduler: Option<&SchedulerConfig>) -> Result<Sched
     if scheduler.max_concurrent_jobs == 0 {
         bail!("execution.scheduler.max_concurrent_jobs must be greater than zero");
     }
-    if scheduler.missed_job_policy != "skip" {
-        bail!("only execution.scheduler.missed_job_policy=skip is supported currently");
-    }
 
     Ok(SchedulerRuntimeConfig {
         max_concurrent_jobs: scheduler.max_concurrent_jobs as usize,
@@ -292,7 +602,13 @@ fn scheduler_runtime_config(scheduler: Option<&SchedulerConfig>) -> Result<Sched
     })
 }
 
-fn start_detached(yetii: &Yetii, pid_file: &str, log_file: &str) -> Result<String> {
+fn start_detached(
+    yetii: &Yetii,
+    pid_file: &str,
+    log_file: &str,
+    control_socket: &str,
+    force_unlock: bool,
+) -> Result<String> {
     ensure_no_running_pid(pid_file)?;
     ensure_parent_dir(pid_file)?;
     ensure_parent_dir(log_file)?;
@@ -307,15 +623,43 @@ fn start_detached(yetii: &Yetii, pid_file: &str, log_file: &str) -> Result<Strin
     let exe = std::env::current_exe().context("failed to determine current executable")?;
     let mut command = Command::new(exe);
     command
-        .arg("--file")
-        .arg(&yetii.file)
+        .arg(if yetii.config_dir.is_some() {
+            "--config-dir"
+        } else {
+            "--file"
+        })
+        .arg(yetii.config_path())
+        .args(
+            yetii
+                .env_file
+                .as_deref()
+                .into_iter()
+                .flat_map(|path| ["--env-file", path]),
+        )
+        .args(
+            yetii
+                .environment
+                .as_deref()
+                .into_iter()
+                .flat_map(|name| ["--env", name]),
+        )
         .args(yetii.verbose.then_some("--verbose"))
+        .args(
+            yetii
+                .chaos
+                .as_deref()
+                .into_iter()
+                .flat_map(|spec| ["--chaos", spec]),
+        )
         .arg("daemon")
         .arg("start")
         .arg("--pid-file")
         .arg(pid_file)
         .arg("--log-file")
         .arg(log_file)
+        .arg("--control-socket")
+        .arg(control_socket)
+        .args(force_unlock.then_some("--force-unlock"))
         .stdin(Stdio::null())
         .stdout(Stdio::from(log))
         .stderr(Stdio::from(log_for_stderr));
@@ -434,7 +778,7 @@ fn configure_detached_process(command: &mut Command) {
 fn configure_detached_process(_command: &mut Command) {}
 
 #[cfg(unix)]
-fn process_is_running(pid: u32) -> bool {
+pub(super) fn process_is_running(pid: u32) -> bool {
     Command::new("kill")
         .arg("-0")
         .arg(pid.to_string())
@@ -443,7 +787,7 @@ fn process_is_running(pid: u32) -> bool {
 }
 
 #[cfg(windows)]
-fn process_is_running(pid: u32) -> bool {
+pub(super) fn process_is_running(pid: u32) -> bool {
     Command::new("tasklist")
         .args(["/FI", &format!("PID eq {pid}")])
         .output()
@@ -454,7 +798,7 @@ fn process_is_running(pid: u32) -> bool {
 }
 
 #[cfg(not(any(unix, windows)))]
-fn process_is_running(_pid: u32) -> bool {
+pub(super) fn process_is_running(_pid: u32) -> bool {
     false
 }
 
@@ -490,10 +834,9 @@ fn stop_process(pid: u32) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::endpoint_config::EndpointConfig;
+    use crate::config::endpoint_config::{EndpointConfig, HttpMethod};
+    use crate::config::execution_config::MissedJobPolicy;
     use crate::config::schedule_config::ScheduleConfig;
-    use crate::config::sql_query::SqlQuery;
-    use crate::config::transform_config::TransformConfig;
 
     #[test]
     fn only_enabled_scheduled_queries_are_selected() {
@@ -502,28 +845,42 @@ mod tests {
             query("query_disabled", false, Some(schedule(true))),
             query("schedule_disabled", true, Some(schedule(false))),
             query("manual", true, None),
+            query(
+                "on_start",
+                true,
+                Some(ScheduleConfig {
+                    run_on_start: true,
+                    ..schedule(true)
+                }),
+            ),
         ])
         .unwrap();
 
         assert_eq!(
-            vec![ScheduledQuery {
-                name: "scheduled".to_string(),
-                cron: "0 */5 * * * *".to_string()
-            }],
+            vec![
+                ScheduledQuery {
+                    name: "scheduled".to_string(),
+                    cron: "0 */5 * * * *".to_string(),
+                    run_on_start: false,
+                },
+                ScheduledQuery {
+                    name: "on_start".to_string(),
+                    cron: "0 */5 * * * *".to_string(),
+                    run_on_start: true,
+                },
+            ],
             selected
         );
     }
 
     #[test]
-    fn scheduler_runtime_rejects_unsupported_missed_policy() {
-        let config = SchedulerConfig {
-            enabled: true,
-            max_concurrent_jobs: 1,
-            job_timeout_minutes: 30,
-            missed_job_policy: "replay".to_string(),
-        };
+    fn scheduler_config_rejects_unsupported_missed_policy() {
+        let error = serde_yaml::from_str::<SchedulerConfig>(
+            "enabled: true\nmax_concurrent_jobs: 1\njob_timeout_minutes: 30\nmissed_job_policy: replay\n",
+        )
+        .unwrap_err();
 
-        assert!(scheduler_runtime_config(Some(&config)).is_err());
+        assert!(error.to_string().contains("expected `skip`"), "{error}");
     }
 
     #[test]
@@ -532,7 +889,8 @@ mod tests {
             enabled: true,
             max_concurrent_jobs: 0,
             job_timeout_minutes: 30,
-            missed_job_policy: "skip".to_string(),
+            missed_job_policy: MissedJobPolicy::Skip,
+            reload: Default::default(),
         };
 
         assert!(scheduler_runtime_config(Some(&config)).is_err());
@@ -543,31 +901,24 @@ mod tests {
             cron: "*/5 * * * *".to_string(),
             timezone: "UTC".to_string(),
             enabled,
+            run_on_start: false,
         }
     }
 
     fn query(name: &str, enabled: bool, schedule: Option<ScheduleConfig>) -> QueryConfig {
-        QueryConfig {
-            name: name.to_string(),
-            description: String::new(),
-            enabled,
-            database: None,
-            schedule,
-            query: SqlQuery {
-                sql: "SELECT 1".to_string(),
-                parameters: None,
-                validation: None,
-            },
-            watermark: None,
-            transform: TransformConfig::default(),
-            endpoint: EndpointConfig {
+        let mut query = QueryConfig::builder(name, "SELECT 1")
+            .endpoint(EndpointConfig {
                 url: "http://127.0.0.1/sync".to_string(),
-                method: "POST".to_string(),
+                failover_url: Vec::new(),
+                method: HttpMethod::Post,
                 auth: None,
                 headers: None,
                 request: Default::default(),
                 response: None,
-            },
-        }
+            })
+            .build();
+        query.enabled = enabled;
+        query.schedule = schedule;
+        query
     }
 }
@@ -0,0 +1,127 @@
+//! `yetii dry-run` reads a sample of each query's rows, applies its transform, and prints the
+//! requests a run would send: method, URL, headers, and body per batch. Nothing is delivered,
+//! and state is read for watermark parameters but never saved.
+
+use super::{parameter_sources, run};
+use crate::config;
+use crate::config::query_config::QueryConfig;
+use crate::config::yetii::YetiiConfig;
+use crate::database::{self, QuerySession};
+use crate::http::RequestPreview;
+use crate::sinks::Sink;
+use crate::state::YetiiState;
+use crate::workspace::JobWorkspace;
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Batch size of sinks that do not set `batch_size`, as in `yetii run`.
+const DEFAULT_BATCH_SIZE: usize = 100;
+
+pub async fn run(query_name: Option<&str>, force: bool, limit: usize) -> Result<()> {
+    let config = config::get_config()?.clone();
+    let queries = run::select_queries(&config.queries, query_name, force)?;
+    let state = run::read_state(&config)?;
+    let mut sessions = HashMap::new();
+    for query in queries {
+        let output = preview_query(&config, query, state.as_ref(), &mut sessions, limit).await?;
+        print!("{output}");
+    }
+    Ok(())
+}
+
+async fn preview_query(
+    config: &YetiiConfig,
+    query: &QueryConfig,
+    state: Option<&YetiiState>,
+    sessions: &mut HashMap<String, QuerySession>,
+    limit: usize,
+) -> Result<String> {
+    let mut output = String::new();
+    if query.source.is_some() {
+        writeln!(
+            output,
+            "query '{}' pulls from an HTTP source and is skipped\n",
+            query.name
+        )?;
+        return Ok(output);
+    }
+    let database_config = run::resolve_database(&config.databases, query)?;
+    if !sessions.contains_key(&database_config.name) {
+        let session = database::open_session(database_config)
+            .await
+            .with_context(|| format!("failed to connect to database '{}'", database_config.name))?;
+        sessions.insert(database_config.name.clone(), session);
+    }
+    let query = parameter_sources::bind(query, config, sessions).await?;
+    let query = query.as_ref();
+    let session = &sessions[&database_config.name];
+    let workspace = JobWorkspace::create(config.execution.workspace.as_ref(), &query.name)?;
+    let rows = run::sample_rows(query, session, state, &workspace, limit).await?;
+    writeln!(
+        output,
+        "query '{}': {} row(s) sampled",
+        query.name,
+        rows.len()
+    )?;
+    if rows.is_empty() {
+        writeln!(output, "nothing would be sent\n")?;
+        return Ok(output);
+    }
+
+    match (&query.endpoint, &query.destination) {
+        (Some(_), _) => {
+            let Sink::Http { sender, endpoint } = run::query_sink(query, &config.databases).await?
+            else {
+                unreachable!("queries with an endpoint deliver over HTTP");
+            };
+            let batch_size = endpoint
+                .request
+                .batch_size
+                .map_or(DEFAULT_BATCH_SIZE, |size| size as usize);
+            let batches = rows.len().div_ceil(batch_size);
+            for (index, batch) in rows.chunks(batch_size).enumerate() {
+                let preview = sender.preview(&endpoint, batch).await.with_context(|| {
+                    format!("request for query '{}' could not be built", query.name)
+                })?;
+                write_request(&mut output, index + 1, batches, batch.len(), &preview)?;
+            }
+        }
+        (None, Some(destination)) => {
+            let batch_size = destination
+                .batch_size()
+                .map_or(DEFAULT_BATCH_SIZE, |size| size as usize);
+            let batches = rows.len().div_ceil(batch_size);
+            for (index, batch) in rows.chunks(batch_size).enumerate() {
+                writeln!(
+                    output,
+                    "--- batch {} of {batches} ({} row(s)) to the {} destination",
+                    index + 1,
+                    batch.len(),
+                    destination.kind()
+                )?;
+                writeln!(output, "{}\n", serde_json::to_string_pretty(batch)?)?;
+            }
+        }
+        (None, None) => bail!("query '{}' has no endpoint or destination", query.name),
+    }
+    Ok(output)
+}
+
+fn write_request(
+    output: &mut String,
+    batch: usize,
+    batches: usize,
+    rows: usize,
+    preview: &RequestPreview,
+) -> std::fmt::Result {
+    writeln!(output, "--- batch {batch} of {batches} ({rows} row(s))")?;
+    writeln!(output, "{} {}", preview.method, preview.url)?;
+    for (name, value) in &preview.headers {
+        writeln!(output, "{name}: {value}")?;
+    }
+    match std::str::from_utf8(&preview.body) {
+        Ok(body) => writeln!(output, "\n{}\n", body.trim_end()),
+        Err(_) => writeln!(output, "\n<{} byte binary body>\n", preview.body.len()),
+    }
+}
@@ -0,0 +1,77 @@
+use crate::config::{self, query_config::QueryConfig, yaml_edit::YamlDocument};
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+/// Sets a single value in the configuration file, keeping comments and key order.
+pub fn set_value(config_path: &str, path: &str, value: &str) -> Result<String> {
+    let value: serde_yaml::Value =
+        serde_yaml::from_str(value).with_context(|| format!("'{value}' is not a YAML value"))?;
+    rewrite(config_path, |document| Ok(document.set(path, &value)?))?;
+    Ok(format!("Updated '{path}' in {config_path}"))
+}
+
+/// Appends the query defined in `query_file` to the `queries` list.
+pub fn add_query(config_path: &str, query_file: &str) -> Result<String> {
+    let content = std::fs::read_to_string(query_file)
+        .with_context(|| format!("failed to read '{query_file}'"))?;
+    let value: serde_yaml::Value = serde_yaml::from_str(&content)
+        .with_context(|| format!("'{query_file}' is not valid YAML"))?;
+
+    rewrite(config_path, |document| {
+        let existing = document.to_value()?;
+        // The query is checked with its endpoint references expanded but appended as written.
+        let mut resolved = value.clone();
+        config::references::resolve_query(&mut resolved, &existing)?;
+        let query: QueryConfig = serde_yaml::from_value(resolved)
+            .with_context(|| format!("'{query_file}' is not a query definition"))?;
+        query.validate()?;
+
+        let duplicate = existing["queries"].as_sequence().is_some_and(|queries| {
+            queries
+                .iter()
+                .any(|entry| entry["name"].as_str() == Some(query.name.as_str()))
+        });
+        if duplicate {
+            bail!("query '{}' already exists", query.name);
+        }
+        Ok(document.append("queries", &value)?)
+    })?;
+    Ok(format!(
+        "Added query '{}' to {config_path}",
+        value["name"].as_str().unwrap_or_default()
+    ))
+}
+
+/// Applies `edit`, refuses results that no longer load, and replaces the file atomically. An
+/// encrypted file is edited in plaintext and encrypted again.
+fn rewrite(config_path: &str, edit: impl FnOnce(&mut YamlDocument) -> Result<()>) -> Result<()> {
+    let format = config::format::ConfigFormat::from_path(Path::new(config_path));
+    if format != config::format::ConfigFormat::Yaml {
+        bail!(
+            "'{config_path}' is {}; only YAML configuration files can be edited in place",
+            format.name()
+        );
+    }
+    let (original, encrypted) = config::read_file(Path::new(config_path))
+        .with_context(|| format!("failed to read '{config_path}'"))?;
+    let mut document = YamlDocument::parse(&original)?;
+    edit(&mut document)?;
+
+    let updated = document.render();
+    config::expand_includes(Path::new(config_path), updated.clone())
+        .and_then(|expanded| config::parse_config(&expanded))
+        .context("the edited configuration would be invalid")?;
+    let updated = if encrypted {
+        config::encryption::encrypt_config(&updated, Path::new(config_path))?
+    } else {
+        updated
+    };
+
+    let target = Path::new(config_path);
+    let temporary = target.with_extension("yaml.tmp");
+    std::fs::write(&temporary, updated)
+        .with_context(|| format!("failed to write '{}'", temporary.display()))?;
+    std::fs::rename(&temporary, target)
+        .with_context(|| format!("failed to replace '{config_path}'"))?;
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! `yetii encrypt` and `yetii decrypt` seal configuration files at rest; loading decrypts them
+//! again, see [`config::encryption`].
+
+use crate::config::encryption;
+use crate::config::format::ConfigFormat;
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+pub fn encrypt(path: &str, output: Option<&str>) -> Result<String> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("failed to read '{path}'"))?;
+    if encryption::is_encrypted(&content) {
+        bail!("'{path}' is already encrypted");
+    }
+    // A file that does not parse would only fail later, when nobody can read it any more.
+    let format = ConfigFormat::from_path(Path::new(path));
+    format
+        .to_yaml(content.clone())
+        .and_then(|yaml| Ok(serde_yaml::from_str::<serde_yaml::Value>(&yaml)?))
+        .with_context(|| format!("'{path}' is not {}", format.name()))?;
+    let target = output.unwrap_or(path);
+    let sealed = encryption::encrypt_config(&content, Path::new(target))?;
+    encryption::write_private(Path::new(target), sealed.as_bytes())
+        .with_context(|| format!("failed to write '{target}'"))?;
+
+    let key = match std::env::var(encryption::CONFIG_PASSPHRASE_VARIABLE) {
+        Ok(passphrase) if !passphrase.is_empty() => {
+            format!(
+                "the passphrase in {}",
+                encryption::CONFIG_PASSPHRASE_VARIABLE
+            )
+        }
+        _ => format!(
+            "the key in {}",
+            encryption::config_key_file(Path::new(target)).display()
+        ),
+    };
+    Ok(format!(
+        "Encrypted '{path}' to '{target}' with {key}; set global_settings.security.encrypt_config: true to refuse plaintext configurations"
+    ))
+}
+
+/// Returns nothing when the plaintext went to stdout.
+pub fn decrypt(path: &str, output: Option<&str>) -> Result<Option<String>> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("failed to read '{path}'"))?;
+    if !encryption::is_encrypted(&content) {
+        bail!("'{path}' is not encrypted");
+    }
+    let plaintext = encryption::decrypt_config(&content, Path::new(path))?;
+    match output.unwrap_or(path) {
+        "-" => {
+            print!("{plaintext}");
+            Ok(None)
+        }
+        target => {
+            encryption::write_private(Path::new(target), plaintext.as_bytes())
+                .with_context(|| format!("failed to write '{target}'"))?;
+            Ok(Some(format!("Decrypted '{path}' to '{target}'")))
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! `yetii exec` puts Yetii inside shell pipelines: it runs ad hoc SQL and writes the rows to
+//! standard output, or delivers records it is given through a configured query's transform and
+//! endpoint or destination. Nothing is read from or saved to the state file.
+
+use super::run::{self, RunFailure, RunReport};
+use crate::config;
+use crate::config::query_config::QueryConfig;
+use crate::config::yetii::YetiiConfig;
+use crate::database::{self, QueryRequest};
+use crate::http::format::{BodyLayout, PayloadFormat};
+use crate::transform::{self, Enricher};
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::{Map, Value};
+use std::io::{self, Write};
+use tokio::io::AsyncReadExt;
+
+/// `--sink` value that writes rows to standard output instead of delivering them.
+pub const STDOUT_SINK: &str = "stdout";
+
+/// `-` in place of a file name reads standard input.
+const STDIN: &str = "-";
+
+pub struct ExecOptions<'a> {
+    pub sql_file: Option<&'a str>,
+    pub records: Option<&'a str>,
+    pub database: Option<&'a str>,
+    pub sink: &'a str,
+    pub format: PayloadFormat,
+    pub limit: Option<usize>,
+}
+
+/// Returns the delivery report, or `None` when the rows went to standard output.
+pub async fn run(options: &ExecOptions<'_>) -> Result<Option<RunReport>> {
+    let config = config::get_config()?.clone();
+    let rows = match (options.sql_file, options.records) {
+        (Some(sql_file), _) => extract(&config, sql_file, options.database, options.limit).await?,
+        (None, Some(records)) => parse_records(&read_input(records).await?)?,
+        (None, None) => bail!("exec needs --sql-file or --records"),
+    };
+    if options.sink == STDOUT_SINK {
+        write_rows(io::stdout().lock(), rows, options.format)?;
+        return Ok(None);
+    }
+    let query = config
+        .queries
+        .iter()
+        .find(|query| query.name == options.sink)
+        .ok_or_else(|| anyhow!("--sink '{}' is neither stdout nor a query", options.sink))?;
+    if query.source.is_some() {
+        bail!(
+            "query '{}' pulls from an HTTP source and cannot deliver records",
+            query.name
+        );
+    }
+    deliver(&config, query, rows).await.map(Some)
+}
+
+async fn extract(
+    config: &YetiiConfig,
+    sql_file: &str,
+    database: Option<&str>,
+    limit: Option<usize>,
+) -> Result<Vec<Map<String, Value>>> {
+    let sql = read_input(sql_file).await?;
+    let database_config =
+        config
+            .databases
+            .resolve_for_query(database)
+            .ok_or_else(|| match database {
+                Some(name) => anyhow!("database '{name}' was not found"),
+                None => anyhow!("--database is required when multiple databases are configured"),
+            })?;
+    let session = database::open_session(database_config)
+        .await
+        .with_context(|| format!("failed to connect to database '{}'", database_config.name))?;
+    let rows = session
+        .run(QueryRequest {
+            sql,
+            parameters: None,
+            max_rows: limit,
+        })
+        .await
+        .with_context(|| format!("query on database '{}' failed", database_config.name))?;
+    tracing::info!(database = %database_config.name, rows = rows.len(), "exec query completed");
+    Ok(rows)
+}
+
+/// Runs the records through the query's transform and sends them to its endpoint or
+/// destination in `batch_size` batches, as `yetii run` would.
+async fn deliver(
+    config: &YetiiConfig,
+    query: &QueryConfig,
+    rows: Vec<Map<String, Value>>,
+) -> Result<RunReport> {
+    let mut report = RunReport {
+        rows_read: rows.len(),
+        ..RunReport::default()
+    };
+    let sink = run::query_sink(query, &config.databases).await?;
+    let enricher = Enricher::connect(&query.transform)
+        .await
+        .with_context(|| format!("enrichment for query '{}' could not connect", query.name))?;
+    let rows = transform::apply(rows, &query.transform, enricher.as_ref())
+        .await
+        .with_context(|| format!("transform for query '{}' failed", query.name))?
+        .into_iter()
+        .map(Value::Object)
+        .collect::<Vec<_>>();
+
+    let delivery = run::send_rows(query, &sink, &rows, 0, 0, None).await;
+    report.record_delivery(&delivery);
+    if let Some(error) = delivery.first_error {
+        report.failures.push(RunFailure {
+            query: query.name.clone(),
+            error,
+            partial: delivery.sent > 0,
+        });
+    } else if let Some(location) = sink
+        .finish()
+        .await
+        .with_context(|| format!("manifest for query '{}' could not be delivered", query.name))?
+    {
+        tracing::info!(query = %query.name, manifest = %location, "manifest delivered");
+    }
+    Ok(report)
+}
+
+async fn read_input(path: &str) -> Result<String> {
+    if path == STDIN {
+        let mut input = String::new();
+        tokio::io::stdin()
+            .read_to_string(&mut input)
+            .await
+            .context("failed to read standard input")?;
+        Ok(input)
+    } else {
+        tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read '{path}'"))
+    }
+}
+
+/// One JSON object per line; blank lines are skipped.
+fn parse_records(input: &str) -> Result<Vec<Map<String, Value>>> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| match serde_json::from_str(line) {
+            Ok(Value::Object(record)) => Ok(record),
+            Ok(_) => Err(anyhow!("record on line {} is not a JSON object", index + 1)),
+            Err(error) => Err(anyhow!("record on line {} is invalid: {error}", index + 1)),
+        })
+        .collect()
+}
+
+/// A closed pipe, as from `| head`, ends the output without an error.
+fn write_rows(
+    mut output: impl Write,
+    rows: Vec<Map<String, Value>>,
+    format: PayloadFormat,
+) -> Result<()> {
+    let rows = Value::Array(rows.into_iter().map(Value::Object).collect());
+    let mut body = format.encode(&rows, &BodyLayout::default())?.bytes;
+    if format == PayloadFormat::Json {
+        body.push(b'\n');
+    }
+    match output.write_all(&body).and_then(|()| output.flush()) {
+        Err(error) if error.kind() != io::ErrorKind::BrokenPipe => {
+            Err(error).context("failed to write to standard output")
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_ndjson_records_and_writes_them_back() {
+        let rows =
+            parse_records("{\"id\":1,\"name\":\"Ada\"}\n\n{\"id\":2,\"name\":\"Lin\"}\n").unwrap();
+        assert_eq!(2, rows.len());
+
+        let mut output = Vec::new();
+        write_rows(&mut output, rows.clone(), PayloadFormat::Ndjson).unwrap();
+        assert_eq!(
+            "{\"id\":1,\"name\":\"Ada\"}\n{\"id\":2,\"name\":\"Lin\"}\n",
+            String::from_utf8(output).unwrap()
+        );
+        let mut output = Vec::new();
+        write_rows(&mut output, rows, PayloadFormat::Csv).unwrap();
+        assert_eq!(
+            "id,name\n1,Ada\n2,Lin\n",
+            String::from_utf8(output).unwrap()
+        );
+
+        let error = parse_records("{\"id\":1}\n[1, 2]\n").unwrap_err();
+        assert_eq!("record on line 2 is not a JSON object", error.to_string());
+    }
+}
@@ -0,0 +1,376 @@
+//! `yetii run --flow <name>` runs a flow's steps in order. Each step runs its query like
+//! `yetii run --query` does, and the flow as a whole gets one state entry, one history entry,
+//! and one notification.
+
+use super::run::{QueryRunner, RunReport};
+use crate::config;
+use crate::config::flow_config::{FlowConfig, StepOutcome, StepValue};
+use crate::config::query_config::QueryConfig;
+use crate::config::yetii::YetiiConfig;
+use crate::history::{self, HistoryEntry, HistoryKind, HistoryStatus, Trigger};
+use crate::monitoring;
+use crate::notifications::{self, NotificationEvent};
+use crate::state::{StateStore, YetiiState};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+
+pub async fn run(flow_name: &str, limit: Option<usize>) -> Result<RunReport> {
+    let run_id = monitoring::new_run_id();
+    let span = tracing::info_span!("flow", flow = %flow_name, run_id = %run_id);
+    monitoring::for_run(&run_id, run_flow(flow_name, limit).instrument(span)).await
+}
+
+async fn run_flow(flow_name: &str, limit: Option<usize>) -> Result<RunReport> {
+    let started = Instant::now();
+    let started_at = Utc::now();
+    let config = config::get_config()?.clone();
+    let flow = config
+        .flows
+        .iter()
+        .flatten()
+        .find(|flow| flow.name == flow_name)
+        .ok_or_else(|| anyhow!("flow '{flow_name}' was not found"))?;
+
+    let mut runner = QueryRunner::new(&config, limit, Trigger::Manual)?;
+    runner.in_flow(&flow.name);
+    let steps = run_steps(&config, flow, &mut runner).await;
+    if let Some(history) = runner.history() {
+        let entry = flow_entry(flow, runner.trigger(), started_at, &runner.report, &steps);
+        if let Err(error) = history.append(&entry) {
+            tracing::warn!(flow = %flow.name, error = %error, "run history was not recorded");
+        }
+    }
+
+    let store = runner.state_store().cloned();
+    let report = runner.report;
+    if steps.is_ok() {
+        record_flow(store.as_ref(), flow, started_at, &report).await;
+        tracing::info!(
+            flow = %flow.name,
+            rows_read = report.rows_read,
+            batches_sent = report.batches_sent,
+            failures = report.failures.len(),
+            "flow completed"
+        );
+    }
+    // A step that could not run at all still ends the flow with a `flow_failure`.
+    let event = flow_event(flow, &report, &steps, started.elapsed());
+    if let Err(error) = notifications::notify(config.monitoring.as_ref(), &event).await {
+        tracing::warn!(flow = %flow.name, error = %error, "flow notification delivery failed");
+    }
+    history::compact_configured(&config).await;
+    steps?;
+    Ok(report)
+}
+
+/// The flow's `flow_success` or `flow_failure` notification.
+fn flow_event(
+    flow: &FlowConfig,
+    report: &RunReport,
+    steps: &Result<Option<FailedStep>>,
+    elapsed: Duration,
+) -> NotificationEvent {
+    let error = match steps {
+        Err(error) => Some(format!("{error:#}")),
+        Ok(_) if report.failures.is_empty() => None,
+        Ok(_) => Some(format!(
+            "step(s) failed: {}",
+            report
+                .failures
+                .iter()
+                .map(|failure| failure.query.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    };
+    let mut event = NotificationEvent::flow_outcome(
+        flow.name.clone(),
+        error.is_none(),
+        error,
+        report.rows_read,
+        report.pages_read,
+        report.batches_sent,
+        elapsed,
+    );
+    event.failures = report.failures.len() + usize::from(steps.is_err());
+    if steps.is_ok() && report.is_partial() {
+        event.status = "partial".to_string();
+    }
+    event
+}
+
+/// Runs the steps whose conditions hold; returns the first step that failed or was partial.
+async fn run_steps(
+    config: &YetiiConfig,
+    flow: &FlowConfig,
+    runner: &mut QueryRunner<'_>,
+) -> Result<Option<FailedStep>> {
+    let mut variables = flow.variables.clone();
+    let mut outcomes = HashMap::new();
+    let mut failed_step = None;
+    for step in &flow.steps {
+        let runs = match &step.when {
+            Some(condition) => outcomes.get(condition.step.as_str()) == Some(&condition.outcome),
+            None => !outcomes
+                .values()
+                .any(|outcome| matches!(outcome, StepOutcome::Failure | StepOutcome::Partial)),
+        };
+        if !runs {
+            tracing::info!(flow = %flow.name, step = %step.name(), "flow step skipped");
+            outcomes.insert(step.name(), StepOutcome::Skipped);
+            continue;
+        }
+
+        let query = config
+            .queries
+            .iter()
+            .find(|query| query.name == step.query)
+            .ok_or_else(|| anyhow!("query '{}' was not found", step.query))?;
+        let query = with_variables(query, &variables);
+        let previous_watermarks = watermarks(runner.state(), &query.name);
+        let failures = runner.report.failures.len();
+        let rows_read = runner.report.rows_read;
+        let batches_sent = runner.report.batches_sent;
+        tracing::info!(flow = %flow.name, step = %step.name(), query = %query.name, "flow step started");
+        if let Err(error) = runner.run_query(&query).await {
+            return Err(error.context(format!("flow step '{}' failed", step.name())));
+        }
+        let outcome = match runner.report.failures.get(failures) {
+            None => StepOutcome::Success,
+            Some(failure) if failure.partial => StepOutcome::Partial,
+            Some(_) => StepOutcome::Failure,
+        };
+        if let (None, Some(failure)) = (&failed_step, runner.report.failures.get(failures)) {
+            failed_step = Some(FailedStep {
+                name: step.name().to_string(),
+                error: failure.error.clone(),
+            });
+        }
+        outcomes.insert(step.name(), outcome);
+
+        let current_watermarks = watermarks(runner.state(), &query.name);
+        for (variable, source) in &step.set {
+            let value = match StepValue::parse(source) {
+                Some(StepValue::RowsRead) => {
+                    Some((runner.report.rows_read - rows_read).to_string())
+                }
+                Some(StepValue::BatchesSent) => {
+                    Some((runner.report.batches_sent - batches_sent).to_string())
+                }
+                Some(StepValue::Outcome) => Some(outcome.as_str().to_string()),
+                Some(StepValue::Watermark(name)) => current_watermarks.get(name).cloned(),
+                Some(StepValue::PreviousWatermark(name)) => previous_watermarks.get(name).cloned(),
+                None => None,
+            };
+            match value {
+                Some(value) => {
+                    variables.insert(variable.clone(), value);
+                }
+                None => tracing::warn!(
+                    flow = %flow.name,
+                    step = %step.name(),
+                    variable = %variable,
+                    "flow variable was not set: the query has no such watermark yet"
+                ),
+            }
+        }
+    }
+    Ok(failed_step)
+}
+
+struct FailedStep {
+    name: String,
+    error: String,
+}
+
+/// The flow's own history entry, totalling its steps and naming the first one that failed.
+fn flow_entry(
+    flow: &FlowConfig,
+    trigger: Trigger,
+    started_at: DateTime<Utc>,
+    report: &RunReport,
+    steps: &Result<Option<FailedStep>>,
+) -> HistoryEntry {
+    let (status, error) = match steps {
+        Err(error) => (HistoryStatus::Failure, Some(format!("{error:#}"))),
+        Ok(None) => (HistoryStatus::Success, None),
+        Ok(Some(step)) => (
+            if report.is_partial() {
+                HistoryStatus::Partial
+            } else {
+                HistoryStatus::Failure
+            },
+            Some(format!("step '{}' failed: {}", step.name, step.error)),
+        ),
+    };
+    HistoryEntry {
+        kind: HistoryKind::Flow,
+        query: flow.name.clone(),
+        flow: None,
+        run_id: monitoring::current_run_id(),
+        trigger,
+        started_at,
+        finished_at: Utc::now(),
+        status,
+        rows_read: report.rows_read,
+        rows_delivered: report.rows_delivered,
+        batches_sent: report.batches_sent,
+        batches_failed: report.batches_failed,
+        error,
+    }
+}
+
+/// Saves the flow's run under `flow:<name>`, apart from the state of its queries. As for a
+/// query, a run that delivered nothing is not recorded.
+async fn record_flow(
+    store: Option<&StateStore>,
+    flow: &FlowConfig,
+    started_at: DateTime<Utc>,
+    report: &RunReport,
+) {
+    let Some(store) = store else {
+        return;
+    };
+    let key = state_key(&flow.name);
+    let saved = if report.failures.is_empty() {
+        store
+            .record_success(
+                &key,
+                started_at,
+                report.rows_read,
+                report.batches_sent,
+                None,
+            )
+            .await
+    } else if report.batches_sent > 0 {
+        store
+            .record_partial(
+                &key,
+                started_at,
+                report.rows_read,
+                report.batches_sent,
+                None,
+            )
+            .await
+    } else {
+        return;
+    };
+    if let Err(error) = saved {
+        tracing::warn!(
+            flow = %flow.name,
+            error = %format!("{error:#}"),
+            "failed to save flow state"
+        );
+    }
+}
+
+/// The state entry of a flow, kept apart from query entries by its prefix.
+fn state_key(flow_name: &str) -> String {
+    format!("flow:{flow_name}")
+}
+
+/// The query with its `source: flow:<variable>` parameters bound to the variables set so far.
+/// Parameters whose variable is not set keep their `default`.
+fn with_variables(query: &QueryConfig, variables: &IndexMap<String, String>) -> QueryConfig {
+    let mut query = query.clone();
+    for parameter in query
+        .query
+        .parameters
+        .iter_mut()
+        .flat_map(|parameters| parameters.values_mut())
+    {
+        if let Some(value) = parameter
+            .source
+            .as_deref()
+            .and_then(|source| source.strip_prefix("flow:"))
+            .and_then(|variable| variables.get(variable.trim()))
+        {
+            parameter.default = Some(value.clone());
+            parameter.source = None;
+        }
+    }
+    query
+}
+
+fn watermarks(state: Option<&YetiiState>, query_name: &str) -> BTreeMap<String, String> {
+    state
+        .and_then(|state| state.query(query_name))
+        .map(|query_state| query_state.watermarks.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::run::RunFailure;
+    use crate::config::monitor_config::NotificationEventKind;
+
+    #[test]
+    fn flow_entry_totals_the_steps_and_names_the_failed_one() {
+        let flow: FlowConfig = serde_yaml::from_str(
+            "name: nightly\nsteps:\n  - query: extract\n  - name: load\n    query: load_orders\n",
+        )
+        .unwrap();
+        let report = RunReport {
+            rows_read: 120,
+            rows_delivered: 80,
+            batches_sent: 4,
+            batches_failed: 1,
+            failures: vec![RunFailure {
+                query: "load_orders".to_string(),
+                error: "batch 5: HTTP 500".to_string(),
+                partial: true,
+            }],
+            ..RunReport::default()
+        };
+        let steps = Ok(Some(FailedStep {
+            name: "load".to_string(),
+            error: "batch 5: HTTP 500".to_string(),
+        }));
+
+        let entry = flow_entry(&flow, Trigger::Manual, Utc::now(), &report, &steps);
+        assert_eq!(HistoryKind::Flow, entry.kind);
+        assert_eq!("nightly", entry.query);
+        assert_eq!(HistoryStatus::Partial, entry.status);
+        assert_eq!(
+            (120, 80, 4, 1),
+            (
+                entry.rows_read,
+                entry.rows_delivered,
+                entry.batches_sent,
+                entry.batches_failed
+            )
+        );
+        assert_eq!(
+            Some("step 'load' failed: batch 5: HTTP 500"),
+            entry.error.as_deref()
+        );
+        assert!(entry.to_string().contains(" flow:nightly partial "));
+    }
+
+    #[test]
+    fn a_step_that_cannot_run_still_ends_in_flow_failure() {
+        let flow: FlowConfig =
+            serde_yaml::from_str("name: nightly\nsteps:\n  - query: extract\n").unwrap();
+        let report = RunReport {
+            rows_read: 10,
+            batches_sent: 1,
+            ..RunReport::default()
+        };
+        let steps = Err(anyhow!("query 'extract' was not found"));
+
+        let event = flow_event(&flow, &report, &steps, Duration::from_secs(1));
+        assert_eq!(NotificationEventKind::FlowFailure, event.event);
+        assert!(!event.success);
+        assert_eq!(1, event.failures);
+        assert_eq!(
+            Some("query 'extract' was not found"),
+            event.error.as_deref()
+        );
+        assert_eq!("flow:nightly", state_key(&flow.name));
+    }
+}
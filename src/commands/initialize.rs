@@ -1,62 +1,104 @@
+/// The creation of this file was inspired by the `cargo init` command.
+use crate::cli::InitTemplate;
 use crate::config::connection_config::ConnectionConfig;
-use crate::config::database::{AuthConfig, DatabaseConfig, DatabaseConfigs, DatabaseType};
-use crate::config::endpoint_config::{EndpointAuth, EndpointConfig, ResponseConfig};
-use crate::config::error_handling::ErrorHandling;
-use crate::config::execution_config::{ExecutionConfig, SchedulerConfig, StateManagement};
+use crate::config::database::{AuthConfig, DatabaseConfig, DatabaseType};
+use crate::config::endpoint_config::{
+    DuplicatePolicy, EndpointAuth, EndpointConfig, HttpMethod, ResponseConfig,
+};
+use crate::config::error_handling::{ErrorAction, ErrorHandling};
+use crate::config::execution_config::{
+    ExecutionConfig, ExecutionMode, MissedJobPolicy, SchedulerConfig, StateManagement,
+};
 use crate::config::global_settings::{GlobalSettings, Logging};
-use crate::config::logging::LogRotation;
+use crate::config::logging::{LogFormat, LogLevel, LogOutput, LogRotation};
 use crate::config::monitor_config::{
-    HealthCheckConfig, MetricsConfig, MonitoringConfig, NotificationEndpointConfig,
-    NotificationEventKind, NotificationPayloadConfig, NotificationResponseConfig,
-    NotificationRetryConfig, NotificationServiceConfig, NotificationServiceType,
-    NotificationSettings,
+    HealthCheckConfig, MetricsConfig, MetricsMode, MonitoringConfig, NotificationEndpointConfig,
+    NotificationEventKind, NotificationPayloadConfig, NotificationPayloadFormat,
+    NotificationResponseConfig, NotificationRetryConfig, NotificationServiceConfig,
+    NotificationServiceType, NotificationSettings,
 };
 use crate::config::query_config::QueryConfig;
-use crate::config::request_config::RequestConfig;
+use crate::config::request_config::{
+    BatchFailurePolicy, FormatOptions, RequestConfig, RetryBackoff,
+};
 use crate::config::schedule_config::ScheduleConfig;
 use crate::config::security_settings::SecuritySettings;
-use crate::config::sql_query::{QueryParameter, QueryValidation, SqlQuery};
-use crate::config::transform_config::{DataConversion, DataFilter, TransformConfig};
+use crate::config::sql_query::{QueryParameter, QueryValidation};
+use crate::config::transform_config::{
+    ConversionTarget, DataConversion, DataFilter, FilterCondition, TransformConfig,
+};
 use crate::config::watermark_config::{WatermarkConfig, WatermarkStrategy};
 use crate::config::yetii::YetiiConfig;
-/// The creation of this file was inspired by the `cargo init` command.
+use indexmap::IndexMap;
 use std::collections::HashMap;
 use std::error::Error;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::path::Path;
+/// What `yetii init` writes, and whether it may replace an existing file.
+#[derive(Default)]
+pub struct InitOptions {
+    pub template: Option<InitTemplate>,
+    /// Overwrite without asking, for containers and CI.
+    pub force: bool,
+    /// Only the required fields.
+    pub minimal: bool,
+}
+
+/// One database and one query with nothing but their required fields.
+const MINIMAL_CONFIG: &str = include_str!("templates/minimal.yaml");
+
 /// Initializes the Yetii configuration file with default values and helpful comments.
 /// # Arguments
 /// * `config_name`: The name of the configuration file to be created.
 /// * `path`: The path where the configuration file will be created.
+/// * `options`: The template or minimal file to write instead of the annotated example, and
+///   whether to overwrite without asking.
 /// # Returns
 /// * `Ok(String)` with success message if the configuration file is created successfully.
 /// * `Err(Box<dyn Error>)` if there is an error during the creation process.
 /// # Example usage
 /// ```rust
 /// use yetii::initialize_yetii_config;
-/// match initialize_yetii_config("yetii.yaml", &"./".to_string()) {
+/// match initialize_yetii_config("yetii.yaml", &"./".to_string(), InitOptions::default()) {
 ///     Ok(msg) => println!("{}", msg),
 ///     Err(e) => eprintln!("Error initializing Yetii configuration: {}", e),
 /// }
 /// ```
-pub fn initialize_yetii_config(config_name: &str, path: &String) -> Result<String, Box<dyn Error>> {
-    let config = create_default_config(config_name)?;
-
-    // Generate YAML with comments
-    let yaml_content = generate_commented_yaml(&config)?;
+pub fn initialize_yetii_config(
+    config_name: &str,
+    path: &String,
+    options: InitOptions,
+) -> Result<String, Box<dyn Error>> {
+    let yaml_content = match options.template {
+        Some(template) => template_yaml(template).to_string(),
+        None if options.minimal => MINIMAL_CONFIG.to_string(),
+        // Generate YAML with comments
+        None => generate_commented_yaml(&create_default_config(config_name)?)?,
+    };
 
     // Create the full path for the configuration file
     let full_path = Path::new(path).join(config_name);
     let full_path_str = full_path.to_string_lossy();
 
     // Save the YAML string to the specified path
-    save_yaml_file_simple(&full_path_str, &yaml_content)?;
+    save_yaml_file_simple(&full_path_str, &yaml_content, options.force)?;
 
     println!("Yetii configuration file created at: {}", full_path_str);
+    println!("Run `yetii bootstrap` to create the directories it writes into.");
     Ok("Yetii configuration initialized successfully.".to_string())
 }
 
-fn save_yaml_file_simple(full_path: &str, yaml_string: &str) -> Result<(), String> {
+/// The curated configuration of `template`, kept as YAML so its comments survive.
+fn template_yaml(template: InitTemplate) -> &'static str {
+    match template {
+        InitTemplate::PostgresToRest => include_str!("templates/postgres-to-rest.yaml"),
+        InitTemplate::MssqlToS3 => include_str!("templates/mssql-to-s3.yaml"),
+        InitTemplate::OracleToKafka => include_str!("templates/oracle-to-kafka.yaml"),
+        InitTemplate::PullApiToDb => include_str!("templates/pull-api-to-db.yaml"),
+    }
+}
+
+fn save_yaml_file_simple(full_path: &str, yaml_string: &str, force: bool) -> Result<(), String> {
     let file_path = Path::new(full_path);
 
     // Create parent directory if it doesn't exist
@@ -67,8 +109,14 @@ fn save_yaml_file_simple(full_path: &str, yaml_string: &str) -> Result<(), Strin
             .map_err(|e| format!("Failed to create directory: {}", e))?;
     }
 
-    // Check if file exists and prompt for overwrite
-    if file_path.exists() {
+    // Check if file exists and prompt for overwrite; without a terminal nobody can answer.
+    if file_path.exists() && !force {
+        if !io::stdin().is_terminal() {
+            return Err(format!(
+                "File '{}' already exists; pass --force to overwrite it.",
+                full_path
+            ));
+        }
         print!("File '{}' already exists. Overwrite? (y/N): ", full_path);
         io::stdout()
             .flush()
@@ -92,25 +140,7 @@ fn save_yaml_file_simple(full_path: &str, yaml_string: &str) -> Result<(), Strin
     Ok(())
 }
 fn create_default_config(config_name: &str) -> Result<YetiiConfig, Box<dyn Error>> {
-    let mut query_parameters = HashMap::new();
-    query_parameters.insert(
-        "last_run_time".to_string(),
-        QueryParameter {
-            param_type: "timestamp".to_string(),
-            default: Some("1970-01-01T00:00:00Z".to_string()),
-            source: Some("state_file".to_string()),
-        },
-    );
-    query_parameters.insert(
-        "last_customer_id".to_string(),
-        QueryParameter {
-            param_type: "bigint".to_string(),
-            default: Some("0".to_string()),
-            source: Some("state_file".to_string()),
-        },
-    );
-
-    let mut field_mappings = HashMap::new();
+    let mut field_mappings = IndexMap::new();
     field_mappings.insert("id".to_string(), "customer_id".to_string());
     field_mappings.insert("name".to_string(), "customer_name".to_string());
     field_mappings.insert("email".to_string(), "email_address".to_string());
@@ -120,8 +150,8 @@ fn create_default_config(config_name: &str) -> Result<YetiiConfig, Box<dyn Error
         "created_at".to_string(),
         DataConversion {
             from: "timestamp".to_string(),
-            to: "iso8601_string".to_string(),
-            format: None,
+            target: ConversionTarget::String,
+            ..DataConversion::default()
         },
     );
 
@@ -129,11 +159,7 @@ fn create_default_config(config_name: &str) -> Result<YetiiConfig, Box<dyn Error
     headers.insert("Content-Type".to_string(), "application/json".to_string());
     headers.insert("X-Source".to_string(), "yetii-erp-sync".to_string());
 
-    let config = YetiiConfig {
-        version: Some("1.0.0".to_string()),
-        name: Some(config_name.to_string()),
-        description: Some("Yetii configuration for ERP data integration and transformation".to_string()),
-        databases: DatabaseConfigs::from(DatabaseConfig {
+    let config = YetiiConfig::builder(DatabaseConfig {
             name: "main_erp".to_string(),
             db_type: DatabaseType::Postgres,
             driver: None,
@@ -145,26 +171,30 @@ fn create_default_config(config_name: &str) -> Result<YetiiConfig, Box<dyn Error
             schema: Some("public".to_string()),
             auth: AuthConfig {
                 username: Some("erp_user".to_string()),
-                password: Some("${ERP_PASSWORD}".to_string()),
+                password: Some("${ERP_PASSWORD}".into()),
+                dynamic: None,
             },
             pool: ConnectionConfig {
                 max_connections: Some(10),
                 timeout_seconds: Some(30),
                 retry_attempts: Some(3),
             },
-        }),
-        global_settings: GlobalSettings {
+            generator: None,
+        })
+        .name(config_name)
+        .description("Yetii configuration for ERP data integration and transformation")
+        .global_settings(GlobalSettings {
             environment: "development".to_string(),
             error_handling: ErrorHandling {
-                on_query_error: "log_and_continue".to_string(),
-                on_transform_error: "stop".to_string(),
-                on_endpoint_error: "retry".to_string(),
+                on_query_error: ErrorAction::LogAndContinue,
+                on_transform_error: ErrorAction::Stop,
+                on_endpoint_error: ErrorAction::Retry,
                 max_retries: 3,
             },
             logging: Logging {
-                level: "info".to_string(),
-                format: "json".to_string(),
-                output: "file".to_string(),
+                level: LogLevel::Info,
+                format: LogFormat::Json,
+                output: LogOutput::File,
                 file_path: Some("./logs/yetii.log".to_string()),
                 rotation: Some(LogRotation {
                     max_size_mb: 100,
@@ -176,28 +206,41 @@ fn create_default_config(config_name: &str) -> Result<YetiiConfig, Box<dyn Error
                 validate_ssl: true,
                 timeout_seconds: Some(300),
             },
-        },
-        queries: vec![
-            QueryConfig {
-                name: "customer_data_sync".to_string(),
-                description: "Sync customer data from ERP to external system".to_string(),
-                enabled: true,
-                database: Some("main_erp".to_string()),
-                schedule: Some(ScheduleConfig {
+        })
+        .query(
+            QueryConfig::builder("customer_data_sync", "SELECT \n  customer_id,\n  customer_name,\n  email,\n  created_at,\n  updated_at\nFROM customers \nWHERE updated_at > $last_run_time\n   OR (updated_at = $last_run_time AND customer_id > $last_customer_id)\nORDER BY updated_at, customer_id\nLIMIT 1000")
+                .description("Sync customer data from ERP to external system")
+                .database("main_erp")
+                .schedule(ScheduleConfig {
                     cron: "0 */6 * * *".to_string(),
                     timezone: "UTC".to_string(),
                     enabled: true,
-                }),
-                query: SqlQuery {
-                    sql: "SELECT \n  customer_id,\n  customer_name,\n  email,\n  created_at,\n  updated_at\nFROM customers \nWHERE updated_at > $last_run_time\n   OR (updated_at = $last_run_time AND customer_id > $last_customer_id)\nORDER BY updated_at, customer_id\nLIMIT 1000".to_string(),
-                    parameters: Some(query_parameters),
-                    validation: Some(QueryValidation {
-                        strict_mapping: Some(true),
-                        warn_unmapped_columns: Some(true),
-                        validate_filter_fields: Some(true),
-                    }),
-                },
-                watermark: Some(WatermarkConfig {
+                    run_on_start: false,
+                })
+                .parameter(
+                    "last_run_time",
+                    QueryParameter {
+                        param_type: "timestamp".to_string(),
+                        default: Some("1970-01-01T00:00:00Z".to_string()),
+                        source: Some("state_file".to_string()),
+                        source_options: None,
+                    },
+                )
+                .parameter(
+                    "last_customer_id",
+                    QueryParameter {
+                        param_type: "bigint".to_string(),
+                        default: Some("0".to_string()),
+                        source: Some("state_file".to_string()),
+                        source_options: None,
+                    },
+                )
+                .validation(QueryValidation {
+                    strict_mapping: Some(true),
+                    warn_unmapped_columns: Some(true),
+                    validate_filter_fields: Some(true),
+                })
+                .watermark(WatermarkConfig {
                     strategy: WatermarkStrategy::MaxTuple,
                     column: None,
                     parameter: None,
@@ -207,69 +250,86 @@ fn create_default_config(config_name: &str) -> Result<YetiiConfig, Box<dyn Error
                         "last_customer_id".to_string(),
                     ]),
                     page_size: Some(1000),
-                }),
-                transform: TransformConfig {
+                    timezone: None,
+                })
+                .transform(TransformConfig {
                     enabled: true,
                     mappings: Some(field_mappings),
                     group_by: None,
                     filters: Some(vec![
                         DataFilter {
                             field: "email".to_string(),
-                            condition: "not_null".to_string(),
+                            condition: FilterCondition::NotNull,
                             value: None,
                         }
                     ]),
                     conversions: Some(data_conversions),
-                },
-                endpoint: EndpointConfig {
+                    reject_file: None,
+                    enrich: None,
+                })
+                .endpoint(EndpointConfig {
                     url: "https://api.example.com/customers".to_string(),
-                    method: "POST".to_string(),
+                    failover_url: Vec::new(),
+                    method: HttpMethod::Post,
                     auth: Some(EndpointAuth::Bearer {
-                        token: "${API_TOKEN}".to_string(),
+                        token: "${API_TOKEN}".into(),
                         header_name: Some("Authorization".to_string()),
                     }),
                     headers: Some(headers),
                     request: RequestConfig {
-                        format: "json".to_string(),
+                        body: FormatOptions::default(),
                         batch_size: Some(100),
                         timeout_seconds: Some(30),
                         retry_attempts: Some(3),
                         retry_delay_seconds: Some(5),
-                        retry_backoff: Some("exponential".to_string()),
+                        retry_backoff: Some(RetryBackoff::Exponential),
+                        retry_jitter: Some(0.2),
+                        retry_max_delay_seconds: Some(300),
+                        retry_on_status: None,
+                        on_batch_failure: BatchFailurePolicy::Abort,
+                        circuit_breaker: None,
                     },
                     response: Some(ResponseConfig {
                         success_codes: vec![200, 201, 202],
-                        handle_duplicates: "skip".to_string(),
+                        handle_duplicates: DuplicatePolicy::Skip,
+                        duplicate_codes: vec![409],
+                        update_method: None,
                     }),
-                },
-            }
-        ],
-        execution: ExecutionConfig {
-            mode: "parallel".to_string(),
+                })
+                .build(),
+        )
+        .execution(ExecutionConfig {
+            mode: ExecutionMode::Parallel,
             global_timeout_minutes: Some(60),
             state_management: Some(StateManagement {
                 enabled: true,
                 state_file: "./state/yetii_state.json".to_string(),
                 backup_states: 5,
+                backend: None,
             }),
             scheduler: Some(SchedulerConfig {
                 enabled: true,
                 max_concurrent_jobs: 5,
                 job_timeout_minutes: 30,
-                missed_job_policy: "skip".to_string(),
+                missed_job_policy: MissedJobPolicy::Skip,
+                reload: Default::default(),
             }),
-        },
-        monitoring: Some(MonitoringConfig {
+            workspace: None,
+            history: None,
+        })
+        .monitoring(MonitoringConfig {
             enabled: true,
             metrics: Some(MetricsConfig {
                 enabled: true,
                 endpoint: "http://localhost:9090/metrics".to_string(),
                 interval_seconds: 30,
+                mode: MetricsMode::Serve,
             }),
             health_check: Some(HealthCheckConfig {
                 enabled: true,
                 endpoint: "/health".to_string(),
                 port: 8080,
+                database_check_seconds: None,
             }),
             notifications: Some(NotificationSettings {
                 enabled: true,
@@ -283,10 +343,10 @@ fn create_default_config(config_name: &str) -> Result<YetiiConfig, Box<dyn Error
                     events: vec![NotificationEventKind::QueryFailure],
                     endpoint: NotificationEndpointConfig {
                         url: "https://ops.example.com/yetii/events".to_string(),
-                        method: "POST".to_string(),
+                        method: HttpMethod::Post,
                     },
                     auth: Some(EndpointAuth::Bearer {
-                        token: "$OPS_API_TOKEN".to_string(),
+                        token: "${OPS_API_TOKEN}".into(),
                         header_name: None,
                     }),
                     headers: Some(HashMap::from([(
@@ -294,7 +354,7 @@ fn create_default_config(config_name: &str) -> Result<YetiiConfig, Box<dyn Error
                         "yetii".to_string(),
                     )])),
                     payload: Some(NotificationPayloadConfig {
-                        format: "json".to_string(),
+                        format: NotificationPayloadFormat::Json,
                         template: serde_json::json!({
                             "app": "yetii",
                             "event": "{{event}}",
@@ -313,14 +373,15 @@ fn create_default_config(config_name: &str) -> Result<YetiiConfig, Box<dyn Error
                     retry: Some(NotificationRetryConfig {
                         attempts: Some(3),
                         delay_seconds: Some(5),
-                        backoff: Some("exponential".to_string()),
+                        backoff: Some(RetryBackoff::Exponential),
                         timeout_seconds: Some(30),
                     }),
                 }],
             }),
-        }),
-        environments: None,
-    };
+            failure_policy: None,
+            tracing: None,
+        })
+        .build();
 
     Ok(config)
 }
@@ -378,3 +439,50 @@ fn generate_commented_yaml(config: &YetiiConfig) -> Result<String, Box<dyn Error
 
     Ok(commented_yaml)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use clap::ValueEnum;
+
+    #[test]
+    fn every_template_is_a_valid_configuration() {
+        for template in InitTemplate::value_variants() {
+            let yaml = template_yaml(*template);
+            for name in config::env_var_names(yaml) {
+                unsafe {
+                    std::env::set_var(name, "template-value");
+                }
+            }
+            let name = template.to_possible_value().unwrap().get_name().to_string();
+            let parsed = config::parse_config(yaml);
+            assert!(parsed.is_ok(), "{name}: {:?}", parsed.err());
+        }
+    }
+
+    #[test]
+    fn minimal_configuration_is_valid_and_force_overwrites() {
+        let directory =
+            std::env::temp_dir().join(format!("yetii-init-minimal-{}", std::process::id()));
+        let path = directory.to_string_lossy().to_string();
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("yetii.yaml"), "existing").unwrap();
+
+        let options = InitOptions {
+            force: true,
+            minimal: true,
+            ..InitOptions::default()
+        };
+        initialize_yetii_config("yetii.yaml", &path, options).unwrap();
+        let written = std::fs::read_to_string(directory.join("yetii.yaml")).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(MINIMAL_CONFIG, written);
+        unsafe {
+            std::env::set_var("ERP_PASSWORD", "template-value");
+        }
+        let config = config::parse_config(&written).unwrap();
+        assert_eq!(1, config.queries.len());
+    }
+}
@@ -1,24 +1,70 @@
+mod bench_transforms;
+mod bootstrap;
+mod check_config;
+mod control;
 mod daemon;
+mod dry_run;
+mod edit;
+mod encryption;
+mod exec;
+mod flow;
 mod initialize;
 mod odbc;
+mod parameter_sources;
+mod readiness;
+mod reload;
 mod run;
+mod run_lock;
+mod secret;
 mod setup;
+mod sql_lint;
+mod support_bundle;
+mod systemd;
+mod validate_query;
+mod version;
 
-use crate::cli::{Commands, DaemonCommand, Yetii};
+pub use run::{PARTIAL_DELIVERY_EXIT_CODE, PartialDelivery};
+
+use crate::cli::{
+    CheckOutputFormat, Commands, ConfigCommand, DaemonCommand, DocsOutputFormat, ExecOutputFormat,
+    HistoryCommand, Yetii,
+};
 use crate::config;
-use anyhow::{Result, bail};
+use crate::history::{HistoryLog, Retention, Trigger};
+use crate::http::format::PayloadFormat;
+use anyhow::{Context, Result, bail};
+use clap::CommandFactory;
+use std::num::NonZeroUsize;
 
 pub async fn going_through_commands(yetii: &Yetii) -> Result<()> {
     match &yetii.commands {
-        Commands::Init { path } => {
-            let config_name = std::path::Path::new(&yetii.file)
-                .file_name()
-                .and_then(|name| name.to_str())
-                .unwrap_or("yetii.yaml");
-            let message = initialize::initialize_yetii_config(config_name, path)
+        Commands::Init {
+            path,
+            template,
+            force,
+            minimal,
+        } => {
+            // With --config-dir the starter file becomes the directory's main file.
+            let (config_name, path) = match &yetii.config_dir {
+                Some(directory) => (config::layout::MAIN_FILE, directory),
+                None => (
+                    std::path::Path::new(&yetii.file)
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .unwrap_or("yetii.yaml"),
+                    path,
+                ),
+            };
+            let options = initialize::InitOptions {
+                template: *template,
+                force: *force,
+                minimal: *minimal,
+            };
+            let message = initialize::initialize_yetii_config(config_name, path, options)
                 .map_err(|error| anyhow::anyhow!(error.to_string()))?;
             println!("{message}");
         }
+        Commands::Bootstrap => bootstrap::run(yetii.config_path())?,
         Commands::CheckExistingOdbc => {
             let output = tokio::task::spawn_blocking(odbc::check_odbc_drivers).await??;
             println!("ODBC configuration:\n{output}");
@@ -31,32 +77,250 @@ pub async fn going_through_commands(yetii: &Yetii) -> Result<()> {
             let report = setup::run(&config.databases, *dry_run, *check_only).await?;
             println!("{report}");
         }
-        Commands::Run { query, force } => {
-            let report = run::run(query.as_deref(), *force).await?;
+        Commands::Run {
+            query,
+            flow: flow_name,
+            force,
+            limit,
+            force_unlock,
+        } => {
+            let _lock = run_lock::RunLock::acquire_for(
+                &*config::get_config()?,
+                yetii.config_path(),
+                "run",
+                *force_unlock,
+            )?;
+            let limit = limit.map(NonZeroUsize::get);
+            let report = match flow_name {
+                Some(flow_name) => flow::run(flow_name, limit).await?,
+                None => run::run(query.as_deref(), *force, limit, Trigger::Manual).await?,
+            };
             println!("{report}");
-            if !report.failures.is_empty() {
-                for failure in &report.failures {
-                    tracing::error!(
-                        query = %failure.query,
-                        error = %failure.error,
-                        "query failed"
-                    );
+            fail_on_failures(&report)?;
+        }
+        Commands::DryRun {
+            query,
+            force,
+            limit,
+        } => dry_run::run(query.as_deref(), *force, limit.get()).await?,
+        Commands::ValidateQuery { query, force } => {
+            validate_query::run(query.as_deref(), *force).await?
+        }
+        Commands::Exec {
+            sql_file,
+            records,
+            database,
+            sink,
+            format,
+            limit,
+        } => {
+            let options = exec::ExecOptions {
+                sql_file: sql_file.as_deref(),
+                records: records.as_deref(),
+                database: database.as_deref(),
+                sink,
+                format: match format {
+                    ExecOutputFormat::Ndjson => PayloadFormat::Ndjson,
+                    ExecOutputFormat::Json => PayloadFormat::Json,
+                    ExecOutputFormat::Csv => PayloadFormat::Csv,
+                },
+                limit: limit.map(NonZeroUsize::get),
+            };
+            if let Some(report) = exec::run(&options).await? {
+                eprintln!("{report}");
+                fail_on_failures(&report)?;
+            }
+        }
+        Commands::History {
+            command: Some(HistoryCommand::Prune { keep, older_than }),
+            ..
+        } => {
+            let config = config::get_config()?.clone();
+            let history = HistoryLog::from_config(&config)
+                .context("run history is disabled with execution.history.enabled: false")?;
+            let history_config = config.execution.history.as_ref();
+            let retention = if keep.is_some() || older_than.is_some() {
+                Retention {
+                    max_entries: keep.map(NonZeroUsize::get),
+                    max_age_seconds: *older_than,
                 }
-                bail!("{} query execution(s) failed", report.failures.len());
+            } else {
+                history_config
+                    .map(Retention::from_config)
+                    .unwrap_or_default()
+            };
+            if retention.is_unlimited() {
+                bail!(
+                    "nothing to prune by; pass --keep or --older-than, or set execution.history.max_entries or max_age_seconds"
+                );
+            }
+            let compaction = history
+                .compact(
+                    &retention,
+                    history_config.and_then(|history| history.archive.as_ref()),
+                    false,
+                    chrono::Utc::now(),
+                )
+                .await?;
+            match &compaction.archive {
+                Some(archive) => println!(
+                    "archived {} entries to {archive}; {} kept",
+                    compaction.removed, compaction.kept
+                ),
+                None => println!(
+                    "removed {} entries; {} kept",
+                    compaction.removed, compaction.kept
+                ),
             }
         }
-        Commands::CheckConfig => {
+        Commands::History {
+            command: None,
+            query,
+            limit,
+        } => {
             let config = config::get_config()?;
-            config.validate()?;
-            tracing::info!("configuration is valid");
+            let history = HistoryLog::from_config(&config)
+                .context("run history is disabled with execution.history.enabled: false")?;
+            let entries = history.read(query.as_deref(), *limit)?;
+            if entries.is_empty() {
+                println!("no executions recorded in {}", history.path().display());
+            }
+            for entry in entries {
+                println!("{entry}");
+            }
+        }
+        Commands::CheckConfig { output, connect } => {
+            check_config::run(
+                yetii.config_path(),
+                *output == CheckOutputFormat::Json,
+                *connect,
+            )
+            .await?;
+        }
+        Commands::Version => println!("{}", version::report(yetii.verbose)),
+        Commands::SupportBundle {
+            output,
+            log_file,
+            log_lines,
+        } => {
+            let path = support_bundle::create(
+                yetii.config_path(),
+                output.as_deref(),
+                log_file,
+                *log_lines,
+            )?;
+            println!(
+                "support bundle written to {}; review it before sharing",
+                path.display()
+            );
+        }
+        Commands::Completions { shell } => {
+            clap_complete::generate(
+                *shell,
+                &mut Yetii::command(),
+                "yetii",
+                &mut std::io::stdout(),
+            );
+        }
+        Commands::Schema { output } => {
+            let schema = config::schema::render();
+            match output {
+                Some(path) => {
+                    std::fs::write(path, schema)
+                        .with_context(|| format!("failed to write '{path}'"))?;
+                    println!("Configuration schema written to {path}");
+                }
+                None => print!("{schema}"),
+            }
+        }
+        Commands::Config { command } => match command {
+            ConfigCommand::Docs { format, output } => {
+                let docs = config::docs::render(match format {
+                    DocsOutputFormat::Markdown => config::docs::DocsFormat::Markdown,
+                    DocsOutputFormat::Html => config::docs::DocsFormat::Html,
+                });
+                match output {
+                    Some(path) => {
+                        std::fs::write(path, docs)
+                            .with_context(|| format!("failed to write '{path}'"))?;
+                        println!("Configuration reference written to {path}");
+                    }
+                    None => print!("{docs}"),
+                }
+            }
+            ConfigCommand::Set { path, value } => {
+                let file = config::layout::main_file(yetii.config_path());
+                println!("{}", edit::set_value(&file.to_string_lossy(), path, value)?);
+            }
+            ConfigCommand::AddQuery { query_file } => {
+                let file = config::layout::main_file(yetii.config_path());
+                println!("{}", edit::add_query(&file.to_string_lossy(), query_file)?);
+            }
+        },
+        Commands::Secret { command } => secret::run(command)?,
+        Commands::Encrypt { path, output } => {
+            let path = path.clone().unwrap_or_else(|| {
+                config::layout::main_file(yetii.config_path())
+                    .to_string_lossy()
+                    .into_owned()
+            });
+            println!("{}", encryption::encrypt(&path, output.as_deref())?);
+        }
+        Commands::Decrypt { path, output } => {
+            let path = path.clone().unwrap_or_else(|| {
+                config::layout::main_file(yetii.config_path())
+                    .to_string_lossy()
+                    .into_owned()
+            });
+            if let Some(message) = encryption::decrypt(&path, output.as_deref())? {
+                println!("{message}");
+            }
+        }
+        Commands::BenchTransforms {
+            fixture,
+            query,
+            iterations,
+            baseline,
+            save_baseline,
+            max_regression,
+        } => {
+            let report = bench_transforms::run(&bench_transforms::BenchOptions {
+                fixture,
+                query: query.as_deref(),
+                iterations: *iterations as usize,
+                baseline: baseline.as_deref(),
+                save_baseline: save_baseline.as_deref(),
+                max_regression: *max_regression,
+            })?;
+            println!("{report}");
+        }
+        Commands::Status { socket, json } => {
+            let reply = control::request(socket, "status").await?;
+            if *json {
+                print!("{reply}");
+            } else {
+                let status =
+                    serde_json::from_str(&reply).context("the daemon's reply is not valid JSON")?;
+                print!("{}", control::render_status(&status, chrono::Utc::now())?);
+            }
         }
         Commands::Daemon { command } => match command {
             DaemonCommand::Start {
                 detach,
                 pid_file,
                 log_file,
+                control_socket,
+                force_unlock,
             } => {
-                let message = daemon::start(yetii, *detach, pid_file, log_file).await?;
+                let message = daemon::start(
+                    yetii,
+                    *detach,
+                    pid_file,
+                    log_file,
+                    control_socket,
+                    *force_unlock,
+                )
+                .await?;
                 println!("{message}");
             }
             DaemonCommand::Status { pid_file } => {
@@ -71,3 +335,23 @@ pub async fn going_through_commands(yetii: &Yetii) -> Result<()> {
     }
     Ok(())
 }
+
+/// Logs each failed query and turns them into the command's error; partial deliveries get
+/// their own exit code.
+fn fail_on_failures(report: &run::RunReport) -> Result<()> {
+    if report.failures.is_empty() {
+        return Ok(());
+    }
+    for failure in &report.failures {
+        tracing::error!(
+            query = %failure.query,
+            error = %failure.error,
+            partial = failure.partial,
+            "query failed"
+        );
+    }
+    if report.is_partial() {
+        return Err(run::PartialDelivery(report.failures.len()).into());
+    }
+    bail!("{} query execution(s) failed", report.failures.len());
+}
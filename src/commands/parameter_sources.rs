@@ -0,0 +1,269 @@
+//! Query parameters read when a job starts from a shell command (`command:`), an HTTP GET
+//! (`http:`), or the first row of another query (`query:`).
+
+use crate::config::query_config::QueryConfig;
+use crate::config::sql_query::{
+    ExternalSource, ParameterSourceErrorAction, ParameterSourceOptions, QueryParameter,
+};
+use crate::config::yetii::YetiiConfig;
+use crate::database::{self, QueryRequest, QuerySession};
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+const DEFAULT_TIMEOUT_SECONDS: u32 = 30;
+
+/// Values of sources with `cache_seconds`, by source and path, with when they were read.
+static CACHE: LazyLock<Mutex<HashMap<String, (Instant, String)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The query with its external parameters bound to their values, like `flow:` variables are.
+/// `sessions` gets the sessions opened for `query:` sources.
+pub(super) async fn bind<'q>(
+    query: &'q QueryConfig,
+    config: &YetiiConfig,
+    sessions: &mut HashMap<String, QuerySession>,
+) -> Result<Cow<'q, QueryConfig>> {
+    let external = query
+        .query
+        .parameters
+        .iter()
+        .flatten()
+        .any(|(_, parameter)| parameter.external_source().is_some());
+    if !external {
+        return Ok(Cow::Borrowed(query));
+    }
+
+    let mut query = query.clone();
+    for (name, parameter) in query.query.parameters.iter_mut().flatten() {
+        let Some(source) = parameter.external_source() else {
+            continue;
+        };
+        let options = parameter.source_options.clone().unwrap_or_default();
+        let value = match cached_or_read(source, &options, config, sessions).await {
+            Ok(value) => value,
+            Err(error) if options.on_error == ParameterSourceErrorAction::UseDefault => {
+                tracing::warn!(
+                    query = %query.name,
+                    parameter = %name,
+                    error = %format!("{error:#}"),
+                    "parameter source failed; using its default"
+                );
+                bound_default(parameter)?
+            }
+            Err(error) => {
+                return Err(error.context(format!(
+                    "parameter '{name}' of query '{}' could not be read from its source",
+                    query.name
+                )));
+            }
+        };
+        parameter.default = Some(value);
+        parameter.source = None;
+        parameter.source_options = None;
+    }
+    Ok(Cow::Owned(query))
+}
+
+fn bound_default(parameter: &QueryParameter) -> Result<String> {
+    parameter
+        .default
+        .clone()
+        .ok_or_else(|| anyhow!("the parameter has no default"))
+}
+
+async fn cached_or_read(
+    source: ExternalSource<'_>,
+    options: &ParameterSourceOptions,
+    config: &YetiiConfig,
+    sessions: &mut HashMap<String, QuerySession>,
+) -> Result<String> {
+    let key = format!("{source:?}{:?}", options.path);
+    let max_age = options
+        .cache_seconds
+        .map(|seconds| Duration::from_secs(seconds.into()));
+    if let Some(max_age) = max_age
+        && let Some((read_at, value)) = lock_cache().get(&key)
+        && read_at.elapsed() < max_age
+    {
+        return Ok(value.clone());
+    }
+
+    let timeout = Duration::from_secs(
+        options
+            .timeout_seconds
+            .unwrap_or(DEFAULT_TIMEOUT_SECONDS)
+            .into(),
+    );
+    let path = options.path.as_deref();
+    let value = tokio::time::timeout(timeout, read(source, path, config, sessions))
+        .await
+        .map_err(|_| anyhow!("no value within {} second(s)", timeout.as_secs()))??;
+    if max_age.is_some() {
+        lock_cache().insert(key, (Instant::now(), value.clone()));
+    }
+    Ok(value)
+}
+
+async fn read(
+    source: ExternalSource<'_>,
+    path: Option<&str>,
+    config: &YetiiConfig,
+    sessions: &mut HashMap<String, QuerySession>,
+) -> Result<String> {
+    match source {
+        ExternalSource::Command(command) => {
+            let output = tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .kill_on_drop(true)
+                .output()
+                .await
+                .with_context(|| format!("failed to start '{command}'"))?;
+            if !output.status.success() {
+                bail!(
+                    "'{command}' exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+        ExternalSource::Http(url) => {
+            let body = reqwest::get(url)
+                .await
+                .and_then(|response| response.error_for_status())
+                .with_context(|| format!("GET {url} failed"))?
+                .text()
+                .await
+                .with_context(|| format!("failed to read the response of {url}"))?;
+            let Some(path) = path else {
+                return Ok(body.trim().to_string());
+            };
+            let body: Value = serde_json::from_str(&body)
+                .with_context(|| format!("the response of {url} is not JSON"))?;
+            json_path(&body, path)
+                .and_then(as_parameter_text)
+                .ok_or_else(|| anyhow!("the response of {url} has no value at '{path}'"))
+        }
+        ExternalSource::Query(name) => {
+            let source_query = config
+                .queries
+                .iter()
+                .find(|query| query.name == name)
+                .ok_or_else(|| anyhow!("query '{name}' was not found"))?;
+            let database_config = config
+                .databases
+                .resolve_for_query(source_query.database.as_deref())
+                .ok_or_else(|| anyhow!("query '{name}' has no database"))?;
+            if !sessions.contains_key(&database_config.name) {
+                let session = database::open_session(database_config).await?;
+                sessions.insert(database_config.name.clone(), session);
+            }
+            let rows = sessions[&database_config.name]
+                .run(QueryRequest {
+                    sql: source_query.query.sql.clone(),
+                    parameters: source_query.query.parameters.clone(),
+                    max_rows: Some(1),
+                })
+                .await
+                .with_context(|| format!("query '{name}' failed"))?;
+            let row = rows
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("query '{name}' returned no rows"))?;
+            let value = match path {
+                Some(column) => row.get(column),
+                None if row.len() == 1 => row.values().next(),
+                None => bail!(
+                    "query '{name}' returns {} columns; set source_options.path to one of them",
+                    row.len()
+                ),
+            };
+            value
+                .and_then(as_parameter_text)
+                .ok_or_else(|| anyhow!("query '{name}' returned no value"))
+        }
+    }
+}
+
+/// Follows a JSONPath of member names and array indexes, e.g. `$.data[0].id` or
+/// `$['meta']['next']`.
+fn json_path<'v>(value: &'v Value, path: &str) -> Option<&'v Value> {
+    let mut rest = path.trim().strip_prefix('$').unwrap_or(path.trim());
+    let mut value = value;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            value = value.get(&after[..end])?;
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']')?;
+            let segment = after[..end].trim();
+            value = match segment.parse::<usize>() {
+                Ok(index) => value.get(index)?,
+                Err(_) => value.get(segment.trim_matches(['\'', '"']))?,
+            };
+            rest = &after[end + 1..];
+        } else {
+            return None;
+        }
+    }
+    Some(value)
+}
+
+fn as_parameter_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(text.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn lock_cache() -> std::sync::MutexGuard<'static, HashMap<String, (Instant, String)>> {
+    CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn binds_command_output_and_falls_back_to_defaults() {
+        let config: YetiiConfig = serde_yaml::from_str(
+            "version: '1.0'\ndatabases: []\nqueries:\n  - name: orders\n    description: ''\n    query:\n      sql: SELECT * FROM orders WHERE region = ? AND id > ?\n      parameters:\n        region:\n          type: string\n          source: \"command: echo '  eu-west  '\"\n        since:\n          type: bigint\n          default: \"0\"\n          source: \"command: exit 3\"\n          source_options:\n            on_error: use_default\n",
+        )
+        .unwrap();
+        let mut sessions = HashMap::new();
+
+        let bound = bind(&config.queries[0], &config, &mut sessions)
+            .await
+            .unwrap();
+        let parameters = bound.query.parameters.as_ref().unwrap();
+        assert_eq!(Some("eu-west"), parameters["region"].default.as_deref());
+        assert_eq!(None, parameters["region"].source);
+        assert_eq!(Some("0"), parameters["since"].default.as_deref());
+
+        let mut failing = config.queries[0].clone();
+        let parameters = failing.query.parameters.as_mut().unwrap();
+        parameters.remove("region");
+        parameters.get_mut("since").unwrap().source_options = None;
+        assert!(bind(&failing, &config, &mut sessions).await.is_err());
+    }
+
+    #[test]
+    fn follows_json_paths() {
+        let body = serde_json::json!({"data": [{"id": 7}, {"id": 9}], "meta": {"next": "abc"}});
+        assert_eq!(Some(&Value::from(9)), json_path(&body, "$.data[1].id"));
+        assert_eq!(
+            Some(&Value::from("abc")),
+            json_path(&body, "$['meta']['next']")
+        );
+        assert_eq!(None, json_path(&body, "$.data[5].id"));
+    }
+}
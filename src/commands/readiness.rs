@@ -0,0 +1,262 @@
+//! `check-config --connect`: checks that go beyond the file itself, for a pre-flight before a
+//! deploy. Each database is connected to, each endpoint host is resolved and, for `https`, a TLS
+//! handshake is made, every `${…}` reference is resolved, and each schedule is parsed.
+
+use crate::config;
+use crate::config::database::DatabaseType;
+use crate::config::schedule_config;
+use crate::config::yetii::YetiiConfig;
+use crate::database;
+use crate::sinks::tls::client_config;
+use chrono::Utc;
+use rustls::pki_types::ServerName;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+/// How long a connection, lookup, or handshake may take before its check fails.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckKind {
+    Database,
+    Endpoint,
+    Secret,
+    Schedule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// The outcome of one readiness check.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub kind: CheckKind,
+    /// The database, endpoint URL, `${…}` reference, or query checked.
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(kind: CheckKind, name: impl Into<String>, result: Result<String, String>) -> Self {
+        let (status, detail) = match result {
+            Ok(detail) => (CheckStatus::Passed, detail),
+            Err(detail) => (CheckStatus::Failed, detail),
+        };
+        Self {
+            kind,
+            name: name.into(),
+            status,
+            detail,
+        }
+    }
+}
+
+/// Runs every check for `config_file`. References are checked from the file's text, so they are
+/// reported even when `config` could not be loaded because of one.
+pub async fn checks(config_file: &str, config: Option<&YetiiConfig>) -> Vec<Check> {
+    let mut checks = Vec::new();
+    if let Some(config) = config {
+        checks.extend(database_checks(config).await);
+        for url in endpoint_urls(config) {
+            let result = endpoint(&url).await;
+            checks.push(Check::new(CheckKind::Endpoint, url, result));
+        }
+    }
+    match config::check_references(config_file) {
+        Ok(references) => checks.extend(references.into_iter().map(|(reference, resolved)| {
+            let result = match resolved {
+                None => Ok("resolved".to_string()),
+                Some(error) => Err(error.to_string()),
+            };
+            Check::new(CheckKind::Secret, reference, result)
+        })),
+        Err(error) => checks.push(Check::new(
+            CheckKind::Secret,
+            config_file,
+            Err(error.to_string()),
+        )),
+    }
+    if let Some(config) = config {
+        checks.extend(schedule_checks(config));
+    }
+    checks
+}
+
+async fn database_checks(config: &YetiiConfig) -> Vec<Check> {
+    let mut checks = Vec::new();
+    for database in config.databases.as_slice() {
+        if database.db_type == DatabaseType::Generator {
+            checks.push(Check {
+                kind: CheckKind::Database,
+                name: database.name.clone(),
+                status: CheckStatus::Skipped,
+                detail: "generator database".to_string(),
+            });
+            continue;
+        }
+        let result = match tokio::time::timeout(TIMEOUT, database::open_session(database)).await {
+            Ok(Ok(_session)) => Ok("connected".to_string()),
+            Ok(Err(error)) => Err(error.to_string()),
+            Err(_) => Err(format!("no connection within {}s", TIMEOUT.as_secs())),
+        };
+        checks.push(Check::new(CheckKind::Database, &database.name, result));
+    }
+    checks
+}
+
+/// Every distinct URL of the named endpoints and of the queries' endpoints and pull sources,
+/// failover URLs included.
+fn endpoint_urls(config: &YetiiConfig) -> Vec<String> {
+    let endpoints = config
+        .endpoints
+        .iter()
+        .flat_map(|endpoints| endpoints.values())
+        .chain(config.queries.iter().flat_map(|query| {
+            query
+                .endpoint
+                .iter()
+                .chain(query.source.iter().map(|source| &source.endpoint))
+        }));
+    let mut urls = Vec::new();
+    for endpoint in endpoints {
+        for url in std::iter::once(&endpoint.url).chain(&endpoint.failover_url) {
+            if !urls.contains(url) {
+                urls.push(url.clone());
+            }
+        }
+    }
+    urls
+}
+
+/// Resolves the host of `url` and, for `https`, completes a TLS handshake with it.
+async fn endpoint(url: &str) -> Result<String, String> {
+    let parsed = url::Url::parse(url).map_err(|error| format!("invalid URL: {error}"))?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| "URL has no host".to_string())?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = parsed
+        .port_or_known_default()
+        .ok_or_else(|| format!("no default port for '{}'", parsed.scheme()))?;
+    let address = tokio::time::timeout(TIMEOUT, tokio::net::lookup_host((host.as_str(), port)))
+        .await
+        .map_err(|_| format!("DNS lookup of '{host}' timed out"))?
+        .map_err(|error| format!("DNS lookup of '{host}' failed: {error}"))?
+        .next()
+        .ok_or_else(|| format!("'{host}' has no addresses"))?;
+    if parsed.scheme() != "https" {
+        return Ok(format!("resolved to {}", address.ip()));
+    }
+
+    let handshake = async {
+        let stream = TcpStream::connect(address)
+            .await
+            .map_err(|error| format!("connecting to {address} failed: {error}"))?;
+        let tls = client_config(None).map_err(|error| error.to_string())?;
+        let server_name = ServerName::try_from(host.clone()).map_err(|error| error.to_string())?;
+        TlsConnector::from(Arc::new(tls))
+            .connect(server_name, stream)
+            .await
+            .map_err(|error| format!("TLS handshake failed: {error}"))
+    };
+    tokio::time::timeout(TIMEOUT, handshake)
+        .await
+        .map_err(|_| {
+            format!(
+                "no TLS handshake with {address} within {}s",
+                TIMEOUT.as_secs()
+            )
+        })??;
+    Ok(format!(
+        "resolved to {}; TLS handshake completed",
+        address.ip()
+    ))
+}
+
+fn schedule_checks(config: &YetiiConfig) -> Vec<Check> {
+    config
+        .queries
+        .iter()
+        .filter_map(|query| {
+            let schedule = query.schedule.as_ref()?;
+            let result = schedule_config::next_fire_times(&schedule.cron, Utc::now(), 1)
+                .map_err(|error| error.to_string())
+                .and_then(|runs| {
+                    runs.first()
+                        .map(|next| {
+                            format!("'{}' next runs at {}", schedule.cron, next.to_rfc3339())
+                        })
+                        .ok_or_else(|| format!("'{}' never runs", schedule.cron))
+                });
+            Some(Check::new(CheckKind::Schedule, &query.name, result))
+        })
+        .collect()
+}
+
+/// One line per check, and the number that failed.
+pub fn render(checks: &[Check]) -> (String, usize) {
+    let mut report = String::new();
+    let mut failed = 0;
+    for check in checks {
+        let kind = match check.kind {
+            CheckKind::Database => "database",
+            CheckKind::Endpoint => "endpoint",
+            CheckKind::Secret => "secret",
+            CheckKind::Schedule => "schedule",
+        };
+        let line = match check.status {
+            CheckStatus::Passed => format!("ok       {kind} {}: {}\n", check.name, check.detail),
+            CheckStatus::Skipped => format!("skipped  {kind} {} ({})\n", check.name, check.detail),
+            CheckStatus::Failed => {
+                failed += 1;
+                format!("FAILED   {kind} {}: {}\n", check.name, check.detail)
+            }
+        };
+        report.push_str(&line);
+    }
+    (report, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_one_line_per_check_and_counts_failures() {
+        let checks = [
+            Check::new(CheckKind::Database, "erp", Ok("connected".to_string())),
+            Check {
+                kind: CheckKind::Database,
+                name: "synthetic".to_string(),
+                status: CheckStatus::Skipped,
+                detail: "generator database".to_string(),
+            },
+            Check::new(
+                CheckKind::Secret,
+                "${ERP_PASSWORD}",
+                Err(
+                    "environment variable 'ERP_PASSWORD' referenced by configuration is not set"
+                        .to_string(),
+                ),
+            ),
+        ];
+        let (report, failed) = render(&checks);
+        assert_eq!(
+            "ok       database erp: connected\nskipped  database synthetic (generator database)\nFAILED   secret ${ERP_PASSWORD}: environment variable 'ERP_PASSWORD' referenced by configuration is not set\n",
+            report
+        );
+        assert_eq!(1, failed);
+    }
+}
@@ -0,0 +1,109 @@
+//! Triggers that make a running daemon reload its configuration: changes to the configuration
+//! file, `SIGHUP`, and `POST` on the monitoring server's reload endpoint. Each is switched on
+//! separately under `execution.scheduler.reload`.
+
+use crate::config::execution_config::ReloadConfig;
+use anyhow::{Context, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Editors often write a file in several steps; triggers this close together are one reload.
+const SETTLE: Duration = Duration::from_millis(500);
+
+pub struct Reloads {
+    sender: mpsc::UnboundedSender<&'static str>,
+    receiver: mpsc::UnboundedReceiver<&'static str>,
+    /// Dropping the watcher stops it.
+    _watcher: Option<RecommendedWatcher>,
+}
+
+impl Reloads {
+    pub fn start(config_path: &str, config: &ReloadConfig) -> Result<Self> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let watcher = config
+            .watch_file
+            .then(|| watch(Path::new(config_path), sender.clone()))
+            .transpose()?;
+        if config.signal {
+            listen_for_hangup(sender.clone())?;
+        }
+        Ok(Self {
+            sender,
+            receiver,
+            _watcher: watcher,
+        })
+    }
+
+    /// Sender for triggers from elsewhere, such as the reload endpoint.
+    pub fn sender(&self) -> mpsc::UnboundedSender<&'static str> {
+        self.sender.clone()
+    }
+
+    /// Waits for the next trigger and returns what caused it, once the burst it belongs to has
+    /// settled.
+    pub async fn next(&mut self) -> Option<&'static str> {
+        let trigger = self.receiver.recv().await?;
+        tokio::time::sleep(SETTLE).await;
+        while self.receiver.try_recv().is_ok() {}
+        Some(trigger)
+    }
+}
+
+/// Watches the configuration file's directory, since editors and `config set` replace the file
+/// rather than write it in place, or every file of a configuration directory.
+fn watch(path: &Path, sender: mpsc::UnboundedSender<&'static str>) -> Result<RecommendedWatcher> {
+    let (directory, file_name, mode) = if path.is_dir() {
+        (path, None, RecursiveMode::Recursive)
+    } else {
+        let directory = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        (
+            directory,
+            path.file_name().map(ToOwned::to_owned),
+            RecursiveMode::NonRecursive,
+        )
+    };
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        let relevant = !matches!(event.kind, EventKind::Access(_))
+            && file_name.as_ref().is_none_or(|file_name| {
+                event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name() == Some(file_name.as_os_str()))
+            });
+        if relevant {
+            let _ = sender.send("file");
+        }
+    })
+    .context("failed to create configuration file watcher")?;
+    watcher
+        .watch(directory, mode)
+        .with_context(|| format!("failed to watch '{}' for changes", directory.display()))?;
+    Ok(watcher)
+}
+
+#[cfg(unix)]
+fn listen_for_hangup(sender: mpsc::UnboundedSender<&'static str>) -> Result<()> {
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        .context("failed to listen for SIGHUP")?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            if sender.send("signal").is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn listen_for_hangup(_sender: mpsc::UnboundedSender<&'static str>) -> Result<()> {
+    Ok(())
+}
@@ -1,11 +1,23 @@
+use super::parameter_sources;
+use crate::capabilities::Capabilities;
 use crate::config;
 use crate::config::query_config::QueryConfig;
+use crate::config::request_config::BatchFailurePolicy;
+use crate::config::source_config::HttpSourceConfig;
+use crate::config::sql_query::{CostCheck, CostCheckAction};
+use crate::config::yetii::YetiiConfig;
 use crate::database::{self, QueryRequest};
-use crate::http::HttpSender;
+use crate::history::{self, HistoryEntry, HistoryKind, HistoryLog, HistoryStatus, Trigger};
+use crate::http::{DuplicateOutcome, HttpSender};
 use crate::monitoring;
+use crate::monitoring::otlp::{self, QueryTrace};
 use crate::notifications::{self, NotificationEvent};
-use crate::state::{self, StateStore, WatermarkUpdate, YetiiState};
-use crate::transform;
+use crate::pull::Pager;
+use crate::sinks::Sink;
+use crate::sort::ExternalSorter;
+use crate::state::{self, Checkpoint, StateStore, WatermarkUpdate, YetiiState};
+use crate::transform::{self, Enricher};
+use crate::workspace::JobWorkspace;
 use anyhow::{Context, Result, anyhow, bail};
 use chrono::Utc;
 use serde_json::Value;
@@ -13,84 +25,473 @@ use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
 use std::time::Instant;
+use tracing::Instrument;
 
-struct DeliveryOutcome {
-    rows_read: usize,
+/// Exit code of `yetii run` when every failed query still delivered some of its batches.
+pub const PARTIAL_DELIVERY_EXIT_CODE: u8 = 3;
+
+/// Result of delivering one set of rows; `failed` holds batch numbers that failed after retries.
+#[derive(Debug, Default)]
+pub(super) struct Delivery {
+    pub(super) sent: usize,
+    /// Rows in the batches that were sent.
+    pub(super) rows_sent: usize,
+    pub(super) failed: Vec<usize>,
+    /// Batches delivered before the first failure.
+    leading_sent: usize,
+    /// Inclusive, 1-based ranges of rows, numbered across the query, that were not delivered.
+    undelivered: Vec<(usize, usize)>,
+    pub(super) first_error: Option<String>,
+    /// Delivery stopped at the first failure (`on_batch_failure: abort`).
+    stopped: bool,
+    duplicates_skipped: usize,
+    duplicates_updated: usize,
+}
+
+impl Delivery {
+    fn skip_rows(&mut self, first: usize, last: usize) {
+        match self.undelivered.last_mut() {
+            Some((_, end)) if *end + 1 == first => *end = last,
+            _ => self.undelivered.push((first, last)),
+        }
+    }
+
+    /// Adds a later delivery of the same query.
+    fn extend(&mut self, later: Delivery) {
+        if self.failed.is_empty() {
+            self.leading_sent += later.leading_sent;
+        }
+        self.sent += later.sent;
+        self.rows_sent += later.rows_sent;
+        self.failed.extend(later.failed);
+        for (first, last) in later.undelivered {
+            self.skip_rows(first, last);
+        }
+        self.first_error = self.first_error.take().or(later.first_error);
+        self.stopped |= later.stopped;
+        self.duplicates_skipped += later.duplicates_skipped;
+        self.duplicates_updated += later.duplicates_updated;
+    }
+}
+
+/// Saves `checkpoint` progress while a query delivers.
+pub(super) struct Checkpointer<'a> {
+    query: &'a str,
+    store: &'a StateStore,
+    started_at: chrono::DateTime<Utc>,
+    every_batches: usize,
+    /// Rows delivered since the state last advanced, including rows skipped on resume.
+    rows_delivered: usize,
+    unsaved_batches: usize,
+    /// A batch failed, so later batches no longer extend the checkpoint.
+    stopped: bool,
+}
+
+impl Checkpointer<'_> {
+    async fn delivered(&mut self, rows: usize) {
+        if self.stopped {
+            return;
+        }
+        self.rows_delivered += rows;
+        self.unsaved_batches += 1;
+        if self.unsaved_batches >= self.every_batches {
+            self.save().await;
+        }
+    }
+
+    async fn failed(&mut self) {
+        if !self.stopped {
+            self.save().await;
+            self.stopped = true;
+        }
+    }
+
+    /// The state advanced past every row delivered so far, which cleared the checkpoint.
+    fn committed(&mut self) {
+        self.rows_delivered = 0;
+        self.unsaved_batches = 0;
+    }
+
+    /// A checkpoint that cannot be saved only means more rows are sent again after a restart,
+    /// so delivery goes on.
+    async fn save(&mut self) {
+        if self.unsaved_batches == 0 {
+            return;
+        }
+        self.unsaved_batches = 0;
+        let checkpoint = Checkpoint {
+            started_at: self.started_at,
+            saved_at: Utc::now(),
+            rows_delivered: self.rows_delivered,
+        };
+        match self.store.save_checkpoint(self.query, checkpoint).await {
+            Ok(()) => tracing::debug!(
+                query = %self.query,
+                rows_delivered = self.rows_delivered,
+                "checkpoint saved"
+            ),
+            Err(error) => tracing::warn!(
+                query = %self.query,
+                error = %format!("{error:#}"),
+                "failed to save checkpoint"
+            ),
+        }
+    }
+}
+
+/// Rows of a query that were not delivered; the query is partial when `batches_sent > 0`.
+#[derive(Debug)]
+pub struct UndeliveredRows {
+    query: String,
     batches_sent: usize,
+    batches_failed: usize,
+    ranges: Vec<(usize, usize)>,
+    stopped: bool,
+    /// Last batch the saved watermark covers.
+    watermark_through: Option<usize>,
+    first_error: String,
+}
+
+impl fmt::Display for UndeliveredRows {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|(first, last)| {
+                if first == last {
+                    first.to_string()
+                } else {
+                    format!("{first}-{last}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(
+            formatter,
+            "query '{}' delivered {} batch(es) and failed {}; rows {ranges} were not delivered",
+            self.query, self.batches_sent, self.batches_failed
+        )?;
+        if self.stopped {
+            write!(formatter, " and later rows were not read")?;
+        }
+        match self.watermark_through {
+            Some(batch) => write!(formatter, "; state advanced through batch {batch}")?,
+            None => write!(formatter, "; state was not advanced")?,
+        }
+        write!(formatter, "; first failure: {}", self.first_error)
+    }
+}
+
+impl std::error::Error for UndeliveredRows {}
+
+/// Returned by `yetii run` when every failed query delivered part of its rows.
+#[derive(Debug, thiserror::Error)]
+#[error("{0} query execution(s) delivered only part of their rows")]
+pub struct PartialDelivery(pub usize);
+
+struct PreparedRows {
+    rows_read: usize,
+    rows: Vec<serde_json::Map<String, Value>>,
     watermark: Option<WatermarkUpdate>,
 }
 
 #[derive(Debug, Default)]
 pub struct RunReport {
     pub rows_read: usize,
+    /// Rows in batches the endpoint, destination, or database accepted.
+    pub rows_delivered: usize,
     pub pages_read: usize,
     pub batches_sent: usize,
+    pub batches_failed: usize,
+    /// Batches the endpoint reported as duplicates, resolved by `handle_duplicates`.
+    pub duplicates_skipped: usize,
+    pub duplicates_updated: usize,
     pub failures: Vec<RunFailure>,
 }
 
+impl RunReport {
+    pub(super) fn record_delivery(&mut self, delivery: &Delivery) {
+        self.batches_sent += delivery.sent;
+        self.rows_delivered += delivery.rows_sent;
+        self.batches_failed += delivery.failed.len();
+        self.duplicates_skipped += delivery.duplicates_skipped;
+        self.duplicates_updated += delivery.duplicates_updated;
+    }
+}
+
 #[derive(Debug)]
 pub struct RunFailure {
     pub query: String,
     pub error: String,
+    /// Some batches were delivered before or after the failure.
+    pub partial: bool,
+}
+
+impl RunReport {
+    /// Every failed query delivered part of its rows.
+    pub fn is_partial(&self) -> bool {
+        !self.failures.is_empty() && self.failures.iter().all(|failure| failure.partial)
+    }
 }
 
 impl fmt::Display for RunReport {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             formatter,
-            "rows_read={} pages_read={} batches_sent={} failures={}",
+            "rows_read={} pages_read={} batches_sent={} batches_failed={} duplicates_skipped={} duplicates_updated={} failures={} partial={}",
             self.rows_read,
             self.pages_read,
             self.batches_sent,
-            self.failures.len()
+            self.batches_failed,
+            self.duplicates_skipped,
+            self.duplicates_updated,
+            self.failures.len(),
+            self.failures
+                .iter()
+                .filter(|failure| failure.partial)
+                .count()
         )
     }
 }
 
-pub async fn run(query_name: Option<&str>, force: bool) -> Result<RunReport> {
+/// Runs the selected queries. `limit` caps the rows each query reads for a smoke test; such
+/// runs still deliver what they read but never save state.
+/// Every log line, notification, state record, and history entry of the run carries its
+/// `run_id`.
+pub async fn run(
+    query_name: Option<&str>,
+    force: bool,
+    limit: Option<usize>,
+    trigger: Trigger,
+) -> Result<RunReport> {
+    let run_id = monitoring::new_run_id();
+    let span = tracing::info_span!("run", run_id = %run_id);
+    monitoring::for_run(
+        &run_id,
+        run_selected(query_name, force, limit, trigger).instrument(span),
+    )
+    .await
+}
+
+async fn run_selected(
+    query_name: Option<&str>,
+    force: bool,
+    limit: Option<usize>,
+    trigger: Trigger,
+) -> Result<RunReport> {
     let run_started = Instant::now();
     let config = config::get_config()?.clone();
     let selected_queries = select_queries(&config.queries, query_name, force)?;
-    let state_store = config
-        .execution
-        .state_management
-        .as_ref()
-        .filter(|state_management| state_management.enabled)
-        .map(StateStore::from_config);
-    let mut state = match &state_store {
-        Some(store) => {
-            let state = store.load_or_default().with_context(|| {
-                format!("failed to load state file '{}'", store.path().display())
-            })?;
-            tracing::debug!(path = %store.path().display(), "loaded run state");
-            Some(state)
-        }
-        None => None,
-    };
-    let mut report = RunReport::default();
-    let mut sessions = HashMap::new();
-
+    let mut runner = QueryRunner::new(&config, limit, trigger)?;
+    let today = Utc::now().date_naive();
     for query in selected_queries {
+        warn_if_forced(query, force, today);
+        runner.run_query(query).await?;
+    }
+    let report = runner.report;
+
+    tracing::info!(
+        rows_read = report.rows_read,
+        batches_sent = report.batches_sent,
+        batches_failed = report.batches_failed,
+        duplicates_skipped = report.duplicates_skipped,
+        duplicates_updated = report.duplicates_updated,
+        failures = report.failures.len(),
+        "run completed"
+    );
+    let mut run_event = NotificationEvent::run_outcome(
+        report.failures.is_empty(),
+        report.rows_read,
+        report.pages_read,
+        report.batches_sent,
+        report.failures.len(),
+        run_started.elapsed(),
+    );
+    if report.is_partial() {
+        run_event.status = "partial".to_string();
+    }
+    if let Err(notification_error) =
+        notifications::notify(config.monitoring.as_ref(), &run_event).await
+    {
+        tracing::warn!(
+            error = %notification_error,
+            "run notification delivery failed"
+        );
+    }
+    history::compact_configured(&config).await;
+    Ok(report)
+}
+
+/// Runs queries one at a time, sharing database sessions and the loaded state between them.
+pub(super) struct QueryRunner<'a> {
+    config: &'a YetiiConfig,
+    state_store: Option<StateStore>,
+    state: Option<YetiiState>,
+    limit: Option<usize>,
+    sessions: HashMap<String, database::QuerySession>,
+    capabilities: Capabilities,
+    history: Option<HistoryLog>,
+    trigger: Trigger,
+    /// The flow whose steps are being run, named in their history entries.
+    flow: Option<String>,
+    pub(super) report: RunReport,
+}
+
+impl<'a> QueryRunner<'a> {
+    pub(super) fn new(
+        config: &'a YetiiConfig,
+        limit: Option<usize>,
+        trigger: Trigger,
+    ) -> Result<Self> {
+        let state_store = config
+            .execution
+            .state_management
+            .as_ref()
+            .filter(|state_management| state_management.enabled)
+            .map(StateStore::from_config);
+        let state = match &state_store {
+            Some(store) => {
+                let state = store
+                    .load_or_default()
+                    .with_context(|| format!("failed to load {}", store.location()))?;
+                tracing::debug!(store = %store.location(), "loaded run state");
+                Some(state)
+            }
+            None => None,
+        };
+        if let Some(limit) = limit {
+            tracing::info!(
+                limit,
+                "reading at most {limit} row(s) per query; state will not be saved"
+            );
+        }
+        Ok(Self {
+            config,
+            state_store,
+            state,
+            limit,
+            sessions: HashMap::new(),
+            capabilities: Capabilities::detect(),
+            history: HistoryLog::from_config(config),
+            trigger,
+            flow: None,
+            report: RunReport::default(),
+        })
+    }
+
+    /// Where state is saved; `None` without state management and for `--limit` runs.
+    pub(super) fn state_store(&self) -> Option<&StateStore> {
+        self.state_store.as_ref().filter(|_| self.limit.is_none())
+    }
+
+    pub(super) fn state(&self) -> Option<&YetiiState> {
+        self.state.as_ref()
+    }
+
+    pub(super) fn history(&self) -> Option<&HistoryLog> {
+        self.history.as_ref()
+    }
+
+    pub(super) fn trigger(&self) -> Trigger {
+        self.trigger
+    }
+
+    /// Marks the queries run from now on as steps of `flow` in the run history.
+    pub(super) fn in_flow(&mut self, flow: &str) {
+        self.flow = Some(flow.to_string());
+    }
+
+    /// Runs one query and adds its results, or its failure, to the report. Only configuration
+    /// problems are returned as errors.
+    pub(super) async fn run_query(&mut self, query: &QueryConfig) -> Result<()> {
+        let span = tracing::info_span!("query", query = %query.name);
+        let started_at = Utc::now();
+        let rows_read = self.report.rows_read;
+        let rows_delivered = self.report.rows_delivered;
+        let batches_sent = self.report.batches_sent;
+        let batches_failed = self.report.batches_failed;
+        let failures = self.report.failures.len();
+        let result = self.run_query_in_span(query).instrument(span.clone()).await;
+
+        let Some(history) = &self.history else {
+            return result;
+        };
+        let failure = self.report.failures.get(failures);
+        let (status, error) = match (&result, failure) {
+            (Err(error), _) => (HistoryStatus::Failure, Some(format!("{error:#}"))),
+            (Ok(()), Some(failure)) if failure.partial => {
+                (HistoryStatus::Partial, Some(failure.error.clone()))
+            }
+            (Ok(()), Some(failure)) => (HistoryStatus::Failure, Some(failure.error.clone())),
+            (Ok(()), None) => (HistoryStatus::Success, None),
+        };
+        let entry = HistoryEntry {
+            kind: HistoryKind::Query,
+            query: query.name.clone(),
+            flow: self.flow.clone(),
+            run_id: monitoring::current_run_id(),
+            trigger: self.trigger,
+            started_at,
+            finished_at: Utc::now(),
+            status,
+            rows_read: self.report.rows_read - rows_read,
+            rows_delivered: self.report.rows_delivered - rows_delivered,
+            batches_sent: self.report.batches_sent - batches_sent,
+            batches_failed: self.report.batches_failed - batches_failed,
+            error,
+        };
+        if let Err(error) = history.append(&entry) {
+            span.in_scope(|| tracing::warn!(error = %error, "run history was not recorded"));
+        }
+        result
+    }
+
+    async fn run_query_in_span(&mut self, query: &QueryConfig) -> Result<()> {
         let started = Instant::now();
-        let initial_rows = report.rows_read;
-        let initial_pages = report.pages_read;
-        let initial_batches = report.batches_sent;
+        let initial_rows = self.report.rows_read;
+        let initial_pages = self.report.pages_read;
+        let initial_batches = self.report.batches_sent;
         monitoring::query_started(&query.name);
-        let database_config = resolve_database(&config.databases, query)?;
-        if !sessions.contains_key(&database_config.name) {
+        let database_config = resolve_database(&self.config.databases, query)?;
+        if !self.sessions.contains_key(&database_config.name)
+            && let Some(missing) = self.capabilities.missing(database_config)
+        {
+            let error = missing.to_string();
+            self.report.failures.push(RunFailure {
+                query: query.name.clone(),
+                error: error.clone(),
+                partial: false,
+            });
+            record_query_outcome(
+                self.config.monitoring.as_ref(),
+                query,
+                false,
+                false,
+                &error,
+                0,
+                0,
+                0,
+                started,
+            )
+            .await;
+            return Ok(());
+        }
+        if !self.sessions.contains_key(&database_config.name) {
             match database::open_session(database_config).await {
                 Ok(session) => {
-                    sessions.insert(database_config.name.clone(), session);
+                    self.sessions.insert(database_config.name.clone(), session);
                 }
                 Err(error) => {
-                    report.failures.push(RunFailure {
+                    self.report.failures.push(RunFailure {
                         query: query.name.clone(),
                         error: format!("database connection failed: {error}"),
+                        partial: false,
                     });
                     record_query_outcome(
-                        config.monitoring.as_ref(),
+                        self.config.monitoring.as_ref(),
                         query,
                         false,
+                        false,
                         &error.to_string(),
                         0,
                         0,
@@ -98,31 +499,83 @@ pub async fn run(query_name: Option<&str>, force: bool) -> Result<RunReport> {
                         started,
                     )
                     .await;
-                    continue;
+                    return Ok(());
                 }
             }
         }
 
-        let session = sessions
+        let query = match parameter_sources::bind(query, self.config, &mut self.sessions).await {
+            Ok(query) => query,
+            Err(error) => {
+                let error = format!("{error:#}");
+                self.report.failures.push(RunFailure {
+                    query: query.name.clone(),
+                    error: error.clone(),
+                    partial: false,
+                });
+                record_query_outcome(
+                    self.config.monitoring.as_ref(),
+                    query,
+                    false,
+                    false,
+                    &error,
+                    0,
+                    0,
+                    0,
+                    started,
+                )
+                .await;
+                return Ok(());
+            }
+        };
+        let query = query.as_ref();
+        let session = self
+            .sessions
             .get(&database_config.name)
             .expect("session was just initialized");
-        let result = execute_query_pages(
-            query,
-            session,
-            state_store.as_ref(),
-            &mut state,
-            &mut report,
-        )
-        .await;
-        let rows = report.rows_read - initial_rows;
-        let pages = report.pages_read - initial_pages;
-        let batches = report.batches_sent - initial_batches;
+        let trace = QueryTrace::start(self.config.monitoring.as_ref(), &query.name);
+        let result =
+            match JobWorkspace::create(self.config.execution.workspace.as_ref(), &query.name) {
+                Ok(workspace) => {
+                    let pages = monitoring::for_query(
+                        &query.name,
+                        execute_query_pages(
+                            query,
+                            session,
+                            &self.config.databases,
+                            &workspace,
+                            self.state_store.as_ref().filter(|_| self.limit.is_none()),
+                            &mut self.state,
+                            &mut self.report,
+                            self.limit,
+                        ),
+                    );
+                    match &trace {
+                        Some(trace) => trace.scope(pages).await,
+                        None => pages.await,
+                    }
+                }
+                Err(error) => Err(anyhow!(error)),
+            };
+        let rows = self.report.rows_read - initial_rows;
+        let pages = self.report.pages_read - initial_pages;
+        let batches = self.report.batches_sent - initial_batches;
+        if let Some(trace) = trace {
+            let error = result.as_ref().err().map(|error| format!("{error:#}"));
+            let attributes = [
+                ("rows_read", rows.into()),
+                ("pages_read", pages.into()),
+                ("batches_sent", batches.into()),
+            ];
+            trace.finish(&attributes, error.as_deref()).await;
+        }
         match result {
             Ok(()) => {
                 record_query_outcome(
-                    config.monitoring.as_ref(),
+                    self.config.monitoring.as_ref(),
                     query,
                     true,
+                    false,
                     "",
                     rows,
                     pages,
@@ -132,15 +585,20 @@ pub async fn run(query_name: Option<&str>, force: bool) -> Result<RunReport> {
                 .await;
             }
             Err(error) => {
-                tracing::error!(query = %query.name, error = %error, "query run failed");
-                report.failures.push(RunFailure {
+                let partial = error
+                    .downcast_ref::<UndeliveredRows>()
+                    .is_some_and(|undelivered| undelivered.batches_sent > 0);
+                tracing::error!(query = %query.name, error = %error, partial, "query run failed");
+                self.report.failures.push(RunFailure {
                     query: query.name.clone(),
                     error: format!("{error:#}"),
+                    partial,
                 });
                 record_query_outcome(
-                    config.monitoring.as_ref(),
+                    self.config.monitoring.as_ref(),
                     query,
                     false,
+                    partial,
                     &format!("{error:#}"),
                     rows,
                     pages,
@@ -150,31 +608,8 @@ pub async fn run(query_name: Option<&str>, force: bool) -> Result<RunReport> {
                 .await;
             }
         }
+        Ok(())
     }
-
-    tracing::info!(
-        rows_read = report.rows_read,
-        batches_sent = report.batches_sent,
-        failures = report.failures.len(),
-        "run completed"
-    );
-    let run_event = NotificationEvent::run_outcome(
-        report.failures.is_empty(),
-        report.rows_read,
-        report.pages_read,
-        report.batches_sent,
-        report.failures.len(),
-        run_started.elapsed(),
-    );
-    if let Err(notification_error) =
-        notifications::notify(config.monitoring.as_ref(), &run_event).await
-    {
-        tracing::warn!(
-            error = %notification_error,
-            "run notification delivery failed"
-        );
-    }
-    Ok(report)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -182,6 +617,7 @@ async fn record_query_outcome(
     monitoring_config: Option<&config::monitor_config::MonitoringConfig>,
     query: &QueryConfig,
     success: bool,
+    partial: bool,
     error: &str,
     rows: usize,
     pages: usize,
@@ -194,7 +630,7 @@ async fn record_query_outcome(
     } else {
         monitoring::query_failed(&query.name, error, rows, pages, batches, duration);
     }
-    let event = NotificationEvent::query_outcome(
+    let mut event = NotificationEvent::query_outcome(
         query.name.clone(),
         success,
         (!success).then(|| error.to_string()),
@@ -203,7 +639,12 @@ async fn record_query_outcome(
         batches,
         duration,
     );
-    if let Err(notification_error) = notifications::notify(monitoring_config, &event).await {
+    if partial {
+        event.status = "partial".to_string();
+    }
+    if let Err(notification_error) =
+        notifications::notify_query(monitoring_config, query.notifications.as_ref(), &event).await
+    {
         tracing::warn!(
             query = %query.name,
             error = %notification_error,
@@ -212,37 +653,134 @@ async fn record_query_outcome(
     }
 }
 
+/// Compares the planner's estimates with `query.cost_check` before the first page runs.
+async fn check_query_cost(
+    query: &QueryConfig,
+    cost_check: &CostCheck,
+    session: &database::QuerySession,
+    parameters: Option<database::QueryParameters>,
+) -> Result<()> {
+    let estimate = session
+        .explain(QueryRequest {
+            sql: query.query.sql.clone(),
+            parameters,
+            max_rows: None,
+        })
+        .await
+        .with_context(|| format!("EXPLAIN for query '{}' failed", query.name))?;
+    let exceeded = [
+        ("cost", estimate.cost, cost_check.max_cost),
+        ("rows", estimate.rows, cost_check.max_rows),
+    ]
+    .into_iter()
+    .filter_map(|(name, estimate, limit)| match (estimate, limit) {
+        (Some(estimate), Some(limit)) if estimate > limit => Some(format!(
+            "estimated {name} {estimate} exceeds max_{name}={limit}"
+        )),
+        _ => None,
+    })
+    .collect::<Vec<_>>();
+    if exceeded.is_empty() {
+        tracing::debug!(
+            query = %query.name,
+            cost = ?estimate.cost,
+            rows = ?estimate.rows,
+            "query plan is within cost_check limits"
+        );
+        return Ok(());
+    }
+    let reason = exceeded.join(", ");
+    match cost_check.action {
+        CostCheckAction::Block => {
+            bail!("query '{}' blocked by cost_check: {reason}", query.name)
+        }
+        CostCheckAction::Warn => {
+            tracing::warn!(query = %query.name, "cost_check exceeded, running anyway: {reason}");
+            Ok(())
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn execute_query_pages(
     query: &QueryConfig,
     session: &database::QuerySession,
+    databases: &config::database::DatabaseConfigs,
+    workspace: &JobWorkspace,
     state_store: Option<&StateStore>,
     state: &mut Option<YetiiState>,
     report: &mut RunReport,
+    limit: Option<usize>,
 ) -> Result<()> {
+    if let Some(source) = &query.source {
+        return execute_pull(query, source, session, state_store, state, report, limit).await;
+    }
     let started_at = Utc::now();
     let page_size = query
         .watermark
         .as_ref()
         .and_then(|watermark| watermark.page_size);
+    let sink = query_sink(query, databases).await?;
+    let enricher = Enricher::connect(&query.transform)
+        .await
+        .with_context(|| format!("enrichment for query '{}' could not connect", query.name))?;
+    // Sorted queries buffer every page before delivering, so paging state only
+    // advances in memory until the merged output has been sent.
+    let mut sorter = query
+        .sort
+        .as_ref()
+        .map(|sort| ExternalSorter::new(sort, workspace));
+    let mut paging_state = state.clone();
+    let mut final_watermark = None;
     let mut page = 0usize;
     let mut query_rows = 0usize;
     let mut query_batches = 0usize;
+    let mut query_sent = 0usize;
+    // Transformed rows handed to the sink so far, to number undelivered rows across pages.
+    let mut rows_offered = 0usize;
+    // Rows an interrupted run already delivered, counted from the saved watermark.
+    let mut resume_skip = 0usize;
+    let mut checkpointer = match (&query.checkpoint, state_store) {
+        (Some(checkpoint), Some(store)) => {
+            if let Some(saved) = state
+                .as_ref()
+                .and_then(|state| state.query(&query.name)?.checkpoint.as_ref())
+            {
+                resume_skip = saved.rows_delivered;
+                tracing::info!(
+                    query = %query.name,
+                    rows_delivered = saved.rows_delivered,
+                    interrupted_run = %saved.started_at,
+                    "resuming from checkpoint"
+                );
+            }
+            Some(Checkpointer {
+                query: &query.name,
+                store,
+                started_at,
+                every_batches: checkpoint.every_batches.unwrap_or(10) as usize,
+                rows_delivered: resume_skip,
+                unsaved_batches: 0,
+                stopped: false,
+            })
+        }
+        _ => None,
+    };
 
+    // The next page, fetched while the previous one was being delivered.
+    let mut prefetched = None;
     loop {
         page += 1;
-        let parameters = resolve_parameters(query, state.as_ref())?;
-        let current_watermark = parameters
-            .as_ref()
-            .map(|parameters| state::current_watermark(query, parameters))
-            .transpose()?
-            .flatten();
-        let rows = session
-            .run(QueryRequest {
-                sql: query.query.sql.clone(),
-                parameters,
-            })
-            .await
-            .with_context(|| format!("database query '{}' failed on page {page}", query.name))?;
+        let FetchedPage {
+            watermark: current_watermark,
+            result: database::ResultSet { columns, rows },
+        } = match prefetched.take() {
+            Some(fetched) => fetched?,
+            None => {
+                let max_rows = limit.map(|limit| limit - query_rows);
+                fetch_page(query, session, paging_state.as_ref(), max_rows, page).await?
+            }
+        };
 
         if let Some(page_size) = page_size
             && rows.len() > page_size
@@ -257,37 +795,176 @@ async fn execute_query_pages(
             break;
         }
 
-        let delivery = deliver_query_rows(query, rows, current_watermark.as_ref()).await?;
-        query_rows += delivery.rows_read;
-        query_batches += delivery.batches_sent;
-        report.rows_read += delivery.rows_read;
+        let prepared = otlp::span(
+            "transform",
+            prepare_query_rows(
+                query,
+                &columns,
+                rows,
+                current_watermark.as_ref(),
+                enricher.as_ref(),
+            ),
+        )
+        .await?;
+        let rows_read = prepared.rows_read;
+        let has_watermark = prepared.watermark.is_some();
+        query_rows += rows_read;
+        report.rows_read += rows_read;
         report.pages_read += 1;
-        report.batches_sent += delivery.batches_sent;
 
-        if let Some(store) = state_store {
-            *state = Some(
-                store
-                    .record_success(
-                        &query.name,
+        match sorter.as_mut() {
+            Some(sorter) => {
+                sorter
+                    .push(prepared.rows)
+                    .with_context(|| format!("sorting query '{}' failed", query.name))?;
+                if let Some(watermark) = &prepared.watermark {
+                    paging_state
+                        .get_or_insert_with(YetiiState::default)
+                        .record_success(
+                            &query.name,
+                            started_at,
+                            Utc::now(),
+                            query_rows,
+                            0,
+                            Some(watermark),
+                        )?;
+                    final_watermark = prepared.watermark;
+                }
+            }
+            None => {
+                let skipped = resume_skip.min(prepared.rows.len());
+                resume_skip -= skipped;
+                rows_offered += skipped;
+                let rows = prepared
+                    .rows
+                    .into_iter()
+                    .skip(skipped)
+                    .map(Value::Object)
+                    .collect::<Vec<_>>();
+                // A full page with a watermark is followed by another, which the database can
+                // return while this one is delivered; its parameters come from the watermark
+                // this delivery saves.
+                let continues = page_size.is_some_and(|page_size| rows_read >= page_size)
+                    && limit.is_none_or(|limit| query_rows < limit);
+                let lookahead = match &prepared.watermark {
+                    Some(watermark) if continues => {
+                        let mut next = paging_state.clone().unwrap_or_default();
+                        next.record_success(
+                            &query.name,
+                            started_at,
+                            Utc::now(),
+                            query_rows,
+                            query_batches,
+                            Some(watermark),
+                        )?;
+                        Some(next)
+                    }
+                    _ => None,
+                };
+                let next_page = async {
+                    let state = lookahead.as_ref()?;
+                    let max_rows = limit.map(|limit| limit - query_rows);
+                    Some(fetch_page(query, session, Some(state), max_rows, page + 1).await)
+                };
+                // Returning an error drops the prefetch, which cancels its query; the page is
+                // only kept once this one's delivery and state are saved.
+                let mut next_page = std::pin::pin!(next_page);
+                let mut fetched = None;
+                let delivery = {
+                    let mut deliver = std::pin::pin!(otlp::span(
+                        "deliver",
+                        send_rows(
+                            query,
+                            &sink,
+                            &rows,
+                            query_batches,
+                            rows_offered,
+                            checkpointer.as_mut(),
+                        ),
+                    ));
+                    tokio::select! {
+                        delivery = &mut deliver => delivery,
+                        page = &mut next_page => {
+                            fetched = Some(page);
+                            deliver.await
+                        }
+                    }
+                };
+                query_batches += delivery.sent + delivery.failed.len();
+                query_sent += delivery.sent;
+                rows_offered += rows.len();
+                report.record_delivery(&delivery);
+                if !delivery.failed.is_empty() {
+                    let delivered =
+                        &rows[..(delivery.leading_sent * sink.batch_size()).min(rows.len())];
+                    let watermark =
+                        delivered_watermark(query, delivered, rows.get(delivered.len()));
+                    return Err(undelivered(
+                        query,
+                        state_store,
+                        state,
                         started_at,
                         query_rows,
-                        query_batches,
-                        delivery.watermark.clone(),
+                        query_sent,
+                        delivery,
+                        watermark,
                     )
-                    .await
-                    .with_context(|| {
-                        format!("failed to save state file '{}'", store.path().display())
-                    })?,
-            );
+                    .await);
+                }
+                match state_store {
+                    Some(store) => {
+                        record_state(
+                            store,
+                            state,
+                            query,
+                            started_at,
+                            query_rows,
+                            query_batches,
+                            prepared.watermark,
+                        )
+                        .await?;
+                        paging_state = state.clone();
+                        resume_skip = 0;
+                        if let Some(checkpointer) = checkpointer.as_mut() {
+                            checkpointer.committed();
+                        }
+                    }
+                    // Unsaved runs still page forward in memory.
+                    None => {
+                        if let Some(watermark) = &prepared.watermark {
+                            paging_state
+                                .get_or_insert_with(YetiiState::default)
+                                .record_success(
+                                    &query.name,
+                                    started_at,
+                                    Utc::now(),
+                                    query_rows,
+                                    query_batches,
+                                    Some(watermark),
+                                )?;
+                        }
+                    }
+                }
+                prefetched = match fetched {
+                    Some(page) => page,
+                    None => next_page.await,
+                };
+            }
         }
 
+        if let Some(limit) = limit
+            && query_rows >= limit
+        {
+            tracing::info!(query = %query.name, limit, "row limit reached");
+            break;
+        }
         let Some(page_size) = page_size else {
             break;
         };
-        if delivery.rows_read < page_size {
+        if rows_read < page_size {
             break;
         }
-        if delivery.watermark.is_none() {
+        if !has_watermark {
             bail!(
                 "query '{}' returned a full page without an advancing watermark",
                 query.name
@@ -296,34 +973,274 @@ async fn execute_query_pages(
         tracing::debug!(query = %query.name, page, "continuing paginated query");
     }
 
+    if let Some(sorter) = sorter {
+        let batch_size = sink.batch_size();
+        let mut sorted = sorter
+            .finish()
+            .with_context(|| format!("sorting query '{}' failed", query.name))?;
+        for _ in 0..resume_skip {
+            let row = sorted
+                .next()
+                .transpose()
+                .with_context(|| format!("sorting query '{}' failed", query.name))?;
+            if row.is_none() {
+                break;
+            }
+            rows_offered += 1;
+        }
+        let mut sorted_delivery = Delivery::default();
+        while !sorted_delivery.stopped {
+            let batch = sorted
+                .by_ref()
+                .take(batch_size)
+                .map(|row| row.map(Value::Object))
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("sorting query '{}' failed", query.name))?;
+            if batch.is_empty() {
+                break;
+            }
+            let delivery = otlp::span(
+                "deliver",
+                send_rows(
+                    query,
+                    &sink,
+                    &batch,
+                    query_batches,
+                    rows_offered,
+                    checkpointer.as_mut(),
+                ),
+            )
+            .await;
+            query_batches += delivery.sent + delivery.failed.len();
+            query_sent += delivery.sent;
+            rows_offered += batch.len();
+            report.record_delivery(&delivery);
+            sorted_delivery.extend(delivery);
+        }
+        // Sorted output is not in watermark order, so no part of the watermark is saved.
+        if !sorted_delivery.failed.is_empty() {
+            return Err(undelivered(
+                query,
+                state_store,
+                state,
+                started_at,
+                query_rows,
+                query_sent,
+                sorted_delivery,
+                None,
+            )
+            .await);
+        }
+        if let Some(store) = state_store {
+            record_state(
+                store,
+                state,
+                query,
+                started_at,
+                query_rows,
+                query_batches,
+                final_watermark,
+            )
+            .await?;
+        }
+    }
+
+    if let Some(location) = sink
+        .finish()
+        .await
+        .with_context(|| format!("manifest for query '{}' could not be delivered", query.name))?
+    {
+        tracing::info!(query = %query.name, manifest = %location, "manifest delivered");
+    }
+    tracing::info!(
+        query = %query.name,
+        rows_read = query_rows,
+        batches_sent = query_batches,
+        "query completed"
+    );
     Ok(())
 }
 
-fn resolve_database<'a>(
-    databases: &'a config::database::DatabaseConfigs,
+/// Rows of one page, and the watermark they were selected after.
+struct FetchedPage {
+    watermark: Option<WatermarkUpdate>,
+    result: database::ResultSet,
+}
+
+/// Runs page `page` of the query with parameters resolved from `paging_state`, checking the
+/// query's cost before the first page.
+async fn fetch_page(
     query: &QueryConfig,
-) -> Result<&'a config::database::DatabaseConfig> {
-    databases
-        .resolve_for_query(query.database.as_deref())
-        .ok_or_else(|| {
-            if databases.len() > 1 && query.database.is_none() {
-                anyhow!(
-                    "query '{}' must set database when multiple databases are configured",
-                    query.name
-                )
-            } else {
-                anyhow!(
-                    "query '{}' references unknown database '{}'",
-                    query.name,
-                    query.database.as_deref().unwrap_or("<missing>")
-                )
-            }
-        })
+    session: &database::QuerySession,
+    paging_state: Option<&YetiiState>,
+    max_rows: Option<usize>,
+    page: usize,
+) -> Result<FetchedPage> {
+    let parameters = resolve_parameters(query, paging_state)?;
+    let watermark = parameters
+        .as_ref()
+        .map(|parameters| state::current_watermark(query, parameters))
+        .transpose()?
+        .flatten();
+    if page == 1
+        && let Some(cost_check) = &query.query.cost_check
+    {
+        check_query_cost(query, cost_check, session, parameters.clone()).await?;
+    }
+    let result = otlp::span(
+        "fetch",
+        session.query(QueryRequest {
+            sql: query.query.sql.clone(),
+            parameters,
+            max_rows,
+        }),
+    )
+    .await
+    .with_context(|| format!("database query '{}' failed on page {page}", query.name))?;
+    Ok(FetchedPage { watermark, result })
 }
 
-fn resolve_parameters(
+/// Pages through the query's HTTP source and writes each record with `query.sql`, one
+/// database transaction per `source.batch_size` records.
+async fn execute_pull(
     query: &QueryConfig,
-    state: Option<&YetiiState>,
+    source: &HttpSourceConfig,
+    session: &database::QuerySession,
+    state_store: Option<&StateStore>,
+    state: &mut Option<YetiiState>,
+    report: &mut RunReport,
+    limit: Option<usize>,
+) -> Result<()> {
+    let started_at = Utc::now();
+    let sender = HttpSender::new(&source.endpoint.request)
+        .with_context(|| format!("source for query '{}' could not be created", query.name))?;
+    let enricher = Enricher::connect(&query.transform)
+        .await
+        .with_context(|| format!("enrichment for query '{}' could not connect", query.name))?;
+    let batch_size = source.batch_size.unwrap_or(500) as usize;
+    let mut pager = Pager::new(source);
+    let mut query_rows = 0usize;
+    let mut query_batches = 0usize;
+
+    loop {
+        let page = pager.pages() + 1;
+        let Some(mut records) = otlp::span("fetch", pager.next_page(&sender))
+            .await
+            .with_context(|| format!("source of query '{}' failed on page {page}", query.name))?
+        else {
+            break;
+        };
+        if let Some(limit) = limit {
+            records.truncate(limit - query_rows);
+        }
+        query_rows += records.len();
+        report.rows_read += records.len();
+        report.pages_read += 1;
+
+        let mut rows = otlp::span(
+            "transform",
+            transform::apply(records, &query.transform, enricher.as_ref()),
+        )
+        .await
+        .with_context(|| format!("transform for query '{}' failed", query.name))?;
+        while !rows.is_empty() {
+            let batch = rows.drain(..batch_size.min(rows.len())).collect::<Vec<_>>();
+            query_batches += 1;
+            let writing = Instant::now();
+            let written = match otlp::span("deliver", session.write(&query.query.sql, batch)).await
+            {
+                Ok(written) => written,
+                Err(error) => {
+                    monitoring::batch_failed(&query.name, writing.elapsed());
+                    return Err(error).with_context(|| {
+                        format!(
+                            "writing query '{}' batch {query_batches} to the database failed",
+                            query.name
+                        )
+                    });
+                }
+            };
+            monitoring::batch_delivered(&query.name, written, writing.elapsed());
+            report.batches_sent += 1;
+            report.rows_delivered += written;
+            tracing::debug!(query = %query.name, batch = query_batches, rows = written, "batch written");
+        }
+        if let Some(limit) = limit
+            && query_rows >= limit
+        {
+            tracing::info!(query = %query.name, limit, "row limit reached");
+            break;
+        }
+    }
+
+    if let Some(store) = state_store {
+        record_state(
+            store,
+            state,
+            query,
+            started_at,
+            query_rows,
+            query_batches,
+            None,
+        )
+        .await?;
+    }
+    tracing::info!(
+        query = %query.name,
+        rows_read = query_rows,
+        pages_read = pager.pages(),
+        batches_written = query_batches,
+        "pull query completed"
+    );
+    Ok(())
+}
+
+/// Saves the query's run and keeps `state` in step with what was saved.
+async fn record_state(
+    store: &StateStore,
+    state: &mut Option<YetiiState>,
+    query: &QueryConfig,
+    started_at: chrono::DateTime<Utc>,
+    rows_read: usize,
+    batches_sent: usize,
+    watermark: Option<WatermarkUpdate>,
+) -> Result<()> {
+    let saved = store
+        .record_success(&query.name, started_at, rows_read, batches_sent, watermark)
+        .await
+        .with_context(|| format!("failed to save {}", store.location()))?;
+    state
+        .get_or_insert_with(YetiiState::default)
+        .queries
+        .insert(query.name.clone(), saved);
+    Ok(())
+}
+
+pub(super) fn resolve_database<'a>(
+    databases: &'a config::database::DatabaseConfigs,
+    query: &QueryConfig,
+) -> Result<&'a config::database::DatabaseConfig> {
+    databases
+        .resolve_for_query(query.database.as_deref())
+        .ok_or_else(|| {
+            if databases.len() > 1 && query.database.is_none() {
+                anyhow!(
+                    "query '{}' must set database when multiple databases are configured",
+                    query.name
+                )
+            } else {
+                anyhow!(
+                    "query '{}' references unknown database '{}'",
+                    query.name,
+                    query.database.as_deref().unwrap_or("<missing>")
+                )
+            }
+        })
+}
+
+pub(super) fn resolve_parameters(
+    query: &QueryConfig,
+    state: Option<&YetiiState>,
 ) -> Result<Option<database::QueryParameters>> {
     let mut parameters = query.query.parameters.clone();
     if state.is_none()
@@ -345,12 +1262,13 @@ fn resolve_parameters(
     Ok(parameters)
 }
 
-async fn deliver_query_rows(
+async fn prepare_query_rows(
     query: &QueryConfig,
+    columns: &[String],
     rows: Vec<serde_json::Map<String, serde_json::Value>>,
     current_watermark: Option<&WatermarkUpdate>,
-) -> Result<DeliveryOutcome> {
-    tracing::info!(query = %query.name, "delivering query rows");
+    enricher: Option<&Enricher>,
+) -> Result<PreparedRows> {
     let rows_read = rows.len();
     let watermark = state::extract_watermark(query, &rows)
         .with_context(|| format!("watermark extraction for query '{}' failed", query.name))?;
@@ -362,103 +1280,822 @@ async fn deliver_query_rows(
             query.name
         );
     }
-    let rows = transform::apply(rows, &query.transform)
+    if query
+        .query
+        .validation
+        .as_ref()
+        .and_then(|validation| validation.validate_filter_fields)
+        .unwrap_or(false)
+    {
+        transform::validate_filter_fields(&query.transform, columns)
+            .with_context(|| format!("filter validation for query '{}' failed", query.name))?;
+    }
+    let rows = transform::apply(rows, &query.transform, enricher)
+        .await
         .with_context(|| format!("transform for query '{}' failed", query.name))?;
-    let rows = rows.into_iter().map(Value::Object).collect::<Vec<_>>();
-    let batch_size = query.endpoint.request.batch_size.unwrap_or(100) as usize;
-    let sender = HttpSender::new(&query.endpoint.request).with_context(|| {
+    Ok(PreparedRows {
+        rows_read,
+        rows,
+        watermark,
+    })
+}
+
+/// The saved state when state management is enabled, for commands that read watermark
+/// parameters without running queries.
+pub(super) fn read_state(config: &YetiiConfig) -> Result<Option<YetiiState>> {
+    config
+        .execution
+        .state_management
+        .as_ref()
+        .filter(|state_management| state_management.enabled)
+        .map(|state_management| {
+            let store = StateStore::from_config(state_management);
+            store
+                .load_or_default()
+                .with_context(|| format!("failed to load {}", store.location()))
+        })
+        .transpose()
+}
+
+/// Up to `limit` rows of the query's first page, transformed and sorted as a run would deliver
+/// them. `state` supplies the watermark parameters and is never updated.
+pub(super) async fn sample_rows(
+    query: &QueryConfig,
+    session: &database::QuerySession,
+    state: Option<&YetiiState>,
+    workspace: &JobWorkspace,
+    limit: usize,
+) -> Result<Vec<Value>> {
+    let parameters = resolve_parameters(query, state)?;
+    let current_watermark = parameters
+        .as_ref()
+        .map(|parameters| state::current_watermark(query, parameters))
+        .transpose()?
+        .flatten();
+    let database::ResultSet { columns, rows } = session
+        .query(QueryRequest {
+            sql: query.query.sql.clone(),
+            parameters,
+            max_rows: Some(limit),
+        })
+        .await
+        .with_context(|| format!("database query '{}' failed", query.name))?;
+    let enricher = Enricher::connect(&query.transform)
+        .await
+        .with_context(|| format!("enrichment for query '{}' could not connect", query.name))?;
+    let prepared = prepare_query_rows(
+        query,
+        &columns,
+        rows,
+        current_watermark.as_ref(),
+        enricher.as_ref(),
+    )
+    .await?;
+    let rows = match &query.sort {
+        Some(sort) => {
+            let mut sorter = ExternalSorter::new(sort, workspace);
+            sorter
+                .push(prepared.rows)
+                .and_then(|()| sorter.finish()?.collect::<Result<Vec<_>, _>>())
+                .with_context(|| format!("sorting query '{}' failed", query.name))?
+        }
+        None => prepared.rows,
+    };
+    Ok(rows.into_iter().map(Value::Object).collect())
+}
+
+pub(super) async fn query_sink(
+    query: &QueryConfig,
+    databases: &config::database::DatabaseConfigs,
+) -> Result<Sink> {
+    Sink::for_query(query, databases).await.with_context(|| {
         format!(
-            "HTTP client for query '{}' could not be created",
+            "delivery target for query '{}' could not be created",
             query.name
         )
-    })?;
-    let mut batches_sent = 0;
+    })
+}
 
-    for (batch_index, batch) in rows.chunks(batch_size).enumerate() {
-        let outcome = sender.send(&query.endpoint, batch).await.with_context(|| {
-            format!(
-                "delivery of query '{}' batch {} failed",
-                query.name,
-                batch_index + 1
-            )
-        })?;
-        batches_sent += 1;
-        tracing::debug!(
-            query = %query.name,
-            batch = batch_index + 1,
-            rows = batch.len(),
-            status = outcome.status.as_u16(),
-            "batch delivered"
-        );
+/// Sends rows in `batch_size` chunks; `batch_offset` and `row_offset` keep batch and row numbers
+/// continuous across pages.
+///
+/// A batch that fails after retries stops delivery unless `on_batch_failure: continue` is set,
+/// in which case it is recorded and the remaining batches are still sent.
+pub(super) async fn send_rows(
+    query: &QueryConfig,
+    sink: &Sink,
+    rows: &[Value],
+    batch_offset: usize,
+    row_offset: usize,
+    mut checkpointer: Option<&mut Checkpointer<'_>>,
+) -> Delivery {
+    tracing::info!(query = %query.name, rows = rows.len(), "delivering query rows");
+    let batch_size = sink.batch_size();
+    let mut delivery = Delivery::default();
+
+    for (index, batch) in rows.chunks(batch_size).enumerate() {
+        let batch_number = batch_offset + index + 1;
+        let first_row = row_offset + index * batch_size + 1;
+        let sending = Instant::now();
+        match sink.send(batch).await {
+            Ok(outcome) => {
+                monitoring::batch_delivered(&query.name, batch.len(), sending.elapsed());
+                delivery.sent += 1;
+                delivery.rows_sent += batch.len();
+                if delivery.failed.is_empty() {
+                    delivery.leading_sent += 1;
+                }
+                if let Some(checkpointer) = checkpointer.as_deref_mut() {
+                    checkpointer.delivered(batch.len()).await;
+                }
+                match outcome.duplicate {
+                    Some(DuplicateOutcome::Skipped) => delivery.duplicates_skipped += 1,
+                    Some(DuplicateOutcome::Updated) => delivery.duplicates_updated += 1,
+                    None => {}
+                }
+                tracing::debug!(
+                    query = %query.name,
+                    batch = batch_number,
+                    rows = batch.len(),
+                    outcome = %outcome.detail,
+                    "batch delivered"
+                );
+            }
+            Err(error) => {
+                monitoring::batch_failed(&query.name, sending.elapsed());
+                let stop = sink.on_batch_failure() == BatchFailurePolicy::Abort;
+                tracing::error!(
+                    query = %query.name,
+                    batch = batch_number,
+                    rows = batch.len(),
+                    error = %error,
+                    stop,
+                    "batch delivery failed"
+                );
+                delivery.failed.push(batch_number);
+                if let Some(checkpointer) = checkpointer.as_deref_mut() {
+                    checkpointer.failed().await;
+                }
+                delivery
+                    .first_error
+                    .get_or_insert_with(|| format!("batch {batch_number}: {error}"));
+                if stop {
+                    // The rest of the rows are never attempted.
+                    delivery.skip_rows(first_row, row_offset + rows.len());
+                    delivery.stopped = true;
+                    break;
+                }
+                delivery.skip_rows(first_row, first_row + batch.len() - 1);
+            }
+        }
     }
 
-    tracing::info!(
-        query = %query.name,
-        rows_read,
-        batches_sent,
-        "query completed"
-    );
-    Ok(DeliveryOutcome {
-        rows_read,
+    delivery
+}
+
+/// Saves a partial outcome when any batch of the query was delivered, and returns the error
+/// that names the rows that were not.
+#[allow(clippy::too_many_arguments)]
+async fn undelivered(
+    query: &QueryConfig,
+    state_store: Option<&StateStore>,
+    state: &mut Option<YetiiState>,
+    started_at: chrono::DateTime<Utc>,
+    rows_read: usize,
+    batches_sent: usize,
+    delivery: Delivery,
+    watermark: Option<WatermarkUpdate>,
+) -> anyhow::Error {
+    let mut watermark_through = None;
+    if batches_sent > 0
+        && let Some(store) = state_store
+    {
+        if watermark.is_some() {
+            watermark_through = delivery.failed.first().map(|batch| batch - 1);
+        }
+        match store
+            .record_partial(&query.name, started_at, rows_read, batches_sent, watermark)
+            .await
+        {
+            Ok(saved) => {
+                state
+                    .get_or_insert_with(YetiiState::default)
+                    .queries
+                    .insert(query.name.clone(), saved);
+            }
+            Err(error) => return error.context(format!("failed to save {}", store.location())),
+        }
+    }
+    anyhow!(UndeliveredRows {
+        query: query.name.clone(),
         batches_sent,
-        watermark,
+        batches_failed: delivery.failed.len(),
+        ranges: delivery.undelivered,
+        stopped: delivery.stopped,
+        watermark_through,
+        first_error: delivery.first_error.unwrap_or_default(),
     })
 }
 
-fn select_queries<'a>(
+/// The watermark of the rows delivered before the first failed batch, when it can be read back
+/// from the delivered rows and stays below the first row that was not delivered; rows that tie
+/// with it would otherwise be skipped by the next run.
+fn delivered_watermark(
+    query: &QueryConfig,
+    delivered: &[Value],
+    next: Option<&Value>,
+) -> Option<WatermarkUpdate> {
+    let watermark = query
+        .watermark
+        .as_ref()
+        .filter(|watermark| watermark.is_incremental())?;
+    let transform = query.transform.enabled.then_some(&query.transform);
+    if delivered.is_empty() || transform.is_some_and(|transform| transform.group_by.is_some()) {
+        return None;
+    }
+    let columns = watermark.cursor_columns();
+    // Converted values would not match the database's representation in the next WHERE clause.
+    if columns.iter().any(|column| {
+        transform
+            .and_then(|transform| transform.conversions.as_ref())
+            .is_some_and(|conversions| conversions.contains_key(*column))
+    }) {
+        return None;
+    }
+    let source_row = |row: &Value| {
+        columns
+            .iter()
+            .map(|column| {
+                let delivered_name = transform
+                    .and_then(|transform| transform.mappings.as_ref()?.get(*column))
+                    .map_or(*column, String::as_str);
+                Some((column.to_string(), row.get(delivered_name)?.clone()))
+            })
+            .collect::<Option<serde_json::Map<String, Value>>>()
+    };
+
+    let rows = delivered
+        .iter()
+        .map(source_row)
+        .collect::<Option<Vec<_>>>()?;
+    let through = state::extract_watermark(query, &rows).ok()??;
+    if let Some(next) = next {
+        let next = state::extract_watermark(query, &[source_row(next)?]).ok()??;
+        if state::compare_watermarks(&through, &next).ok()? != Ordering::Less {
+            return None;
+        }
+    }
+    Some(through)
+}
+
+/// Under `--force`, warns for each selected query the configuration does not expect to run:
+/// one that is disabled, whose disable has expired, or whose schedule is disabled.
+fn warn_if_forced(query: &QueryConfig, force: bool, today: chrono::NaiveDate) {
+    if !force {
+        return;
+    }
+    let reason = if let Some(until) = query.expired_disable(today) {
+        format!("query is still disabled in the configuration; its until date {until} has passed")
+    } else if !query.enabled {
+        "query is disabled in the configuration and runs only because of --force".to_string()
+    } else if query
+        .schedule
+        .as_ref()
+        .is_some_and(|schedule| !schedule.enabled)
+    {
+        "query's schedule is disabled in the configuration".to_string()
+    } else {
+        return;
+    };
+    tracing::warn!(query = %query.name, until = ?query.until, "FORCED RUN: {reason}");
+}
+
+/// `query_name` is one query name, or a comma-separated list of names and `*`/`?` globs;
+/// queries run in configuration order. A name matching no query, or naming a disabled query
+/// without `force`, is an error, as is a glob matching no query.
+pub(super) fn select_queries<'a>(
     queries: &'a [QueryConfig],
     query_name: Option<&str>,
     force: bool,
 ) -> Result<Vec<&'a QueryConfig>> {
-    if let Some(query_name) = query_name {
-        let query = queries
+    let Some(query_name) = query_name else {
+        return Ok(queries
             .iter()
-            .find(|query| query.name == query_name)
-            .ok_or_else(|| anyhow!("query '{query_name}' was not found"))?;
-        if !query.enabled && !force {
-            bail!("query '{query_name}' is disabled; pass --force to run it");
-        }
-        return Ok(vec![query]);
+            .filter(|query| query.is_enabled() || force)
+            .collect());
+    };
+    // A query whose name contains a comma or glob character can still be named whole.
+    let patterns = if queries.iter().any(|query| query.name == query_name) {
+        vec![query_name]
+    } else {
+        query_name
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .collect()
+    };
+    if patterns.is_empty() {
+        bail!("--query names no queries");
     }
 
+    let mut selected = vec![false; queries.len()];
+    for pattern in patterns {
+        if !pattern.contains(['*', '?']) {
+            let (index, query) = queries
+                .iter()
+                .enumerate()
+                .find(|(_, query)| query.name == pattern)
+                .ok_or_else(|| anyhow!("query '{pattern}' was not found"))?;
+            if !query.is_enabled() && !force {
+                match query.until {
+                    Some(until) => {
+                        bail!("query '{pattern}' is disabled until {until}; pass --force to run it")
+                    }
+                    None => bail!("query '{pattern}' is disabled; pass --force to run it"),
+                }
+            }
+            selected[index] = true;
+            continue;
+        }
+        let matching = queries
+            .iter()
+            .enumerate()
+            .filter(|(_, query)| glob_matches(pattern, &query.name))
+            .collect::<Vec<_>>();
+        if matching.is_empty() {
+            bail!("no query matches '{pattern}'");
+        }
+        let mut any_enabled = false;
+        for (index, query) in matching {
+            if query.is_enabled() || force {
+                selected[index] = true;
+                any_enabled = true;
+            }
+        }
+        if !any_enabled {
+            bail!("every query matching '{pattern}' is disabled; pass --force to run them");
+        }
+    }
     Ok(queries
         .iter()
-        .filter(|query| query.enabled || force)
+        .zip(selected)
+        .filter_map(|(query, selected)| selected.then_some(query))
         .collect())
 }
 
+/// `*` matches any run of characters and `?` any one character.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was and the name position it is currently matched up to.
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&ch) if ch == '?' || ch == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&ch| ch == '*')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::connection_config::ConnectionConfig;
     use crate::config::database::{AuthConfig, DatabaseConfig, DatabaseConfigs, DatabaseType};
-    use crate::config::endpoint_config::EndpointConfig;
-    use crate::config::sql_query::{QueryParameter, SqlQuery};
-    use crate::config::transform_config::TransformConfig;
+    use crate::config::endpoint_config::{EndpointConfig, HttpMethod};
+    use crate::config::sql_query::QueryParameter;
+    use crate::state::QueryOutcome;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
 
     fn query(name: &str, enabled: bool) -> QueryConfig {
-        QueryConfig {
-            name: name.to_string(),
-            description: String::new(),
-            enabled,
-            database: None,
-            schedule: None,
-            query: SqlQuery {
-                sql: "SELECT 1".to_string(),
-                parameters: None,
-                validation: None,
-            },
-            watermark: None,
-            transform: TransformConfig::default(),
-            endpoint: EndpointConfig {
+        let mut query = QueryConfig::builder(name, "SELECT 1")
+            .endpoint(EndpointConfig {
                 url: "https://example.test".to_string(),
-                method: "POST".to_string(),
+                failover_url: Vec::new(),
+                method: HttpMethod::Post,
                 auth: None,
                 headers: None,
                 request: Default::default(),
                 response: None,
+            })
+            .build();
+        query.enabled = enabled;
+        query
+    }
+
+    #[tokio::test]
+    async fn continue_policy_delivers_remaining_batches_and_reports_failures() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            for attempt in 0..3 {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0_u8; 1024];
+                while !String::from_utf8_lossy(&request).contains("}]") {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let status = if attempt == 1 {
+                    "500 Internal Server Error"
+                } else {
+                    "200 OK"
+                };
+                let response =
+                    format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        let mut query = query("orders", true);
+        let endpoint = query.endpoint.as_mut().unwrap();
+        endpoint.url = format!("http://{address}/rows");
+        endpoint.request.batch_size = Some(1);
+        endpoint.request.retry_attempts = Some(0);
+        endpoint.request.on_batch_failure = BatchFailurePolicy::Continue;
+        let sink = query_sink(&query, &DatabaseConfigs::from(Vec::new()))
+            .await
+            .unwrap();
+        let rows = (1..=3)
+            .map(|id| serde_json::json!({ "id": id }))
+            .collect::<Vec<_>>();
+
+        let path =
+            std::env::temp_dir().join(format!("yetii-run-checkpoint-{}.json", std::process::id()));
+        let store = StateStore::new(&path, 0);
+        let mut checkpointer = Checkpointer {
+            query: "orders",
+            store: &store,
+            started_at: Utc::now(),
+            every_batches: 1,
+            rows_delivered: 10,
+            unsaved_batches: 0,
+            stopped: false,
+        };
+
+        let delivery = send_rows(&query, &sink, &rows, 4, 10, Some(&mut checkpointer)).await;
+        server.await.unwrap();
+
+        assert_eq!(2, delivery.sent);
+        assert_eq!(1, delivery.leading_sent);
+        assert_eq!(vec![6], delivery.failed);
+        assert_eq!(vec![(12, 12)], delivery.undelivered);
+        let error = undelivered(
+            &query,
+            Some(&store),
+            &mut None,
+            Utc::now(),
+            3,
+            6,
+            delivery,
+            None,
+        )
+        .await;
+        assert!(
+            error
+                .to_string()
+                .starts_with("query 'orders' delivered 6 batch(es) and failed 1; rows 12 were not delivered; state was not advanced; first failure: batch 6: ")
+        );
+        // The checkpoint stops before the failed batch and survives the partial outcome.
+        let state = store.load_or_default().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            11,
+            state
+                .query("orders")
+                .unwrap()
+                .checkpoint
+                .as_ref()
+                .unwrap()
+                .rows_delivered
+        );
+    }
+
+    #[test]
+    fn watermark_advances_only_below_the_first_undelivered_row() {
+        let mut query = query("orders", true);
+        query.query.parameters = Some(HashMap::from([(
+            "last_id".to_string(),
+            QueryParameter {
+                param_type: "integer".to_string(),
+                source: Some("state_file".to_string()),
+                default: Some("0".to_string()),
+                source_options: None,
             },
+        )]));
+        query.watermark = Some(crate::config::watermark_config::WatermarkConfig {
+            strategy: crate::config::watermark_config::WatermarkStrategy::Max,
+            column: Some("id".to_string()),
+            parameter: Some("last_id".to_string()),
+            columns: None,
+            parameters: None,
+            page_size: None,
+            timezone: None,
+        });
+        query.transform.mappings = Some(indexmap::IndexMap::from([(
+            "id".to_string(),
+            "order_id".to_string(),
+        )]));
+        let rows = [1, 2, 3, 3]
+            .map(|id| serde_json::json!({ "order_id": id }))
+            .to_vec();
+
+        let through = delivered_watermark(&query, &rows[..2], rows.get(2)).unwrap();
+        assert_eq!("2", through.components[0].value);
+        // Row 4 ties with the last delivered row, so advancing would skip it next run.
+        assert!(delivered_watermark(&query, &rows[..3], rows.get(3)).is_none());
+
+        query.transform.group_by = Some("order_id".to_string());
+        assert!(delivered_watermark(&query, &rows[..2], rows.get(2)).is_none());
+    }
+
+    /// A paged `id` query whose pages hold ids 1 to 4, 5 to 8, and 9 to 12, delivered in
+    /// batches of two to `url`.
+    fn paged_query(url: String) -> (QueryConfig, database::QuerySession) {
+        let mut query = query("orders", true);
+        query.query.parameters = Some(HashMap::from([(
+            "last_id".to_string(),
+            QueryParameter {
+                param_type: "integer".to_string(),
+                source: Some("state_file".to_string()),
+                default: Some("0".to_string()),
+                source_options: None,
+            },
+        )]));
+        query.watermark = Some(crate::config::watermark_config::WatermarkConfig {
+            strategy: crate::config::watermark_config::WatermarkStrategy::Max,
+            column: Some("id".to_string()),
+            parameter: Some("last_id".to_string()),
+            columns: None,
+            parameters: None,
+            page_size: Some(4),
+            timezone: None,
+        });
+        let endpoint = query.endpoint.as_mut().unwrap();
+        endpoint.url = url;
+        endpoint.request.batch_size = Some(2);
+        endpoint.request.retry_attempts = Some(0);
+        let pages = [1, 5, 9]
+            .map(|first: i64| database::ResultSet {
+                columns: vec!["id".to_string()],
+                rows: (first..first + 4)
+                    .map(|id| serde_json::json!({ "id": id }).as_object().unwrap().clone())
+                    .collect(),
+            })
+            .into();
+        (query, database::scripted_session(pages))
+    }
+
+    /// Answers the first `succeeding` requests with 200 and every later one with 500, counting
+    /// the requests it answers.
+    async fn sink_failing_after(
+        succeeding: usize,
+    ) -> (String, Arc<AtomicUsize>, tokio::task::JoinHandle<()>) {
+        use std::sync::atomic::Ordering::SeqCst;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let answered = Arc::new(AtomicUsize::new(0));
+        let counter = answered.clone();
+        let server = tokio::spawn(async move {
+            for attempt in 0.. {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0_u8; 1024];
+                while !String::from_utf8_lossy(&request).contains("}]") {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let status = if attempt < succeeding {
+                    "200 OK"
+                } else {
+                    "500 Internal Server Error"
+                };
+                let response =
+                    format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.unwrap();
+                counter.fetch_add(1, SeqCst);
+            }
+        });
+        (format!("http://{address}/rows"), answered, server)
+    }
+
+    /// Runs the paged query until a delivery fails; returns the saved state, the report, and
+    /// the number of batches the sink received.
+    async fn run_until_undelivered(
+        succeeding: usize,
+        state_file: &str,
+    ) -> (Option<YetiiState>, RunReport, usize) {
+        let (url, answered, server) = sink_failing_after(succeeding).await;
+        let (query, session) = paged_query(url);
+        let path = std::env::temp_dir().join(format!("{state_file}-{}.json", std::process::id()));
+        let store = StateStore::new(&path, 0);
+        let workspace = JobWorkspace::create(None, "orders").unwrap();
+        let mut state = Some(YetiiState::default());
+        let mut report = RunReport::default();
+
+        let result = execute_query_pages(
+            &query,
+            &session,
+            &DatabaseConfigs::from(Vec::new()),
+            &workspace,
+            Some(&store),
+            &mut state,
+            &mut report,
+            None,
+        )
+        .await;
+        server.abort();
+        let error = result.unwrap_err();
+        assert!(
+            error.downcast_ref::<UndeliveredRows>().is_some(),
+            "{error:#}"
+        );
+        let saved = store.load_or_default().unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(state.as_ref(), Some(&saved));
+        (
+            state,
+            report,
+            answered.load(std::sync::atomic::Ordering::SeqCst),
+        )
+    }
+
+    #[tokio::test]
+    async fn failed_delivery_discards_the_prefetched_page_and_its_watermark() {
+        // The second page is fetched with last_id=4 while the first is delivered, but only
+        // ids 1 and 2 reach the sink.
+        let (state, report, batches) =
+            run_until_undelivered(1, "yetii-run-prefetch-first-page").await;
+
+        let saved = state.unwrap();
+        let orders = saved.query("orders").unwrap();
+        assert_eq!(
+            Some("2"),
+            orders.watermarks.get("last_id").map(String::as_str)
+        );
+        assert_eq!(Some(QueryOutcome::Partial), orders.last_outcome);
+        assert_eq!(1, report.pages_read);
+        assert_eq!(4, report.rows_read);
+        assert_eq!(2, batches);
+    }
+
+    #[tokio::test]
+    async fn failed_delivery_cancels_the_prefetch_of_the_next_page() {
+        // Page 1 is delivered; page 2 fails on its first batch while page 3 is being fetched,
+        // and ids 9 to 12 must never reach the sink.
+        let (state, report, batches) =
+            run_until_undelivered(2, "yetii-run-prefetch-later-page").await;
+
+        assert_eq!(3, batches, "no batch after the failed one is sent");
+        assert_eq!(2, report.pages_read);
+        assert_eq!(8, report.rows_read);
+        let saved = state.unwrap();
+        let orders = saved.query("orders").unwrap();
+        assert_eq!(
+            Some("4"),
+            orders.watermarks.get("last_id").map(String::as_str)
+        );
+        assert_eq!(Some(QueryOutcome::Partial), orders.last_outcome);
+    }
+
+    #[tokio::test]
+    async fn failed_first_batch_leaves_the_watermark_unset() {
+        let (state, report, batches) =
+            run_until_undelivered(0, "yetii-run-prefetch-first-batch").await;
+
+        assert!(state.unwrap().query("orders").is_none());
+        assert_eq!(1, report.pages_read);
+        assert_eq!(1, batches);
+    }
+
+    #[tokio::test]
+    async fn filter_fields_are_validated_against_an_empty_result() {
+        let mut query = query("orders", true);
+        query.query.validation = Some(crate::config::sql_query::QueryValidation {
+            strict_mapping: None,
+            warn_unmapped_columns: None,
+            validate_filter_fields: Some(true),
+        });
+        query.transform.filters = Some(
+            ["emial", "id", "totl"]
+                .into_iter()
+                .map(|field| crate::config::transform_config::DataFilter {
+                    field: field.to_string(),
+                    condition: crate::config::transform_config::FilterCondition::NotNull,
+                    value: None,
+                })
+                .collect(),
+        );
+        let database: DatabaseConfig = serde_yaml::from_str(
+            "name: synthetic\ntype: generator\ngenerator:\n  rows: 0\n  columns:\n    id: { type: sequence }\n    email: { type: email }\n",
+        )
+        .unwrap();
+        let session = database::open_session(&database).await.unwrap();
+        let workspace = JobWorkspace::create(None, "filter-validation").unwrap();
+
+        let error = sample_rows(&query, &session, None, &workspace, 10)
+            .await
+            .unwrap_err();
+
+        assert!(
+            format!("{error:#}").contains(
+                "filter fields 'emial', 'totl' are not result columns; available columns: email, id"
+            ),
+            "{error:#}"
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buffer);
+            Ok(buffer.len())
         }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    const TODAY: chrono::NaiveDate = chrono::NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+
+    /// What `warn_if_forced` logs for `query` on `TODAY`.
+    fn forced_warnings(query: &QueryConfig, force: bool) -> String {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, || warn_if_forced(query, force, TODAY));
+        String::from_utf8(captured.0.lock().unwrap().clone()).unwrap()
+    }
+
+    #[test]
+    fn forced_runs_of_disabled_queries_are_warned() {
+        let disabled = forced_warnings(&query("disabled", false), true);
+        assert!(disabled.contains("FORCED RUN"), "{disabled}");
+        assert!(disabled.contains("query=disabled"), "{disabled}");
+        assert!(forced_warnings(&query("enabled", true), true).is_empty());
+        assert!(forced_warnings(&query("disabled", false), false).is_empty());
+
+        let mut paused = query("paused", false);
+        paused.until = TODAY.succ_opt();
+        let warning = forced_warnings(&paused, true);
+        assert!(
+            warning.contains("is disabled in the configuration"),
+            "{warning}"
+        );
+    }
+
+    #[test]
+    fn forced_runs_of_expired_disables_are_warned() {
+        let mut expired = query("expired", false);
+        expired.until = Some(TODAY);
+
+        let warning = forced_warnings(&expired, true);
+        assert!(warning.contains("FORCED RUN"), "{warning}");
+        assert!(
+            warning.contains("its until date 2025-06-01 has passed"),
+            "{warning}"
+        );
+        assert!(forced_warnings(&expired, false).is_empty());
+    }
+
+    #[test]
+    fn forced_runs_of_disabled_schedules_are_warned() {
+        let mut unscheduled = query("unscheduled", true);
+        unscheduled.schedule =
+            Some(serde_yaml::from_str("cron: '0 * * * *'\nenabled: false\n").unwrap());
+
+        let warning = forced_warnings(&unscheduled, true);
+        assert!(warning.contains("FORCED RUN"), "{warning}");
+        assert!(warning.contains("schedule is disabled"), "{warning}");
+        assert!(forced_warnings(&unscheduled, false).is_empty());
+
+        unscheduled.schedule.as_mut().unwrap().enabled = true;
+        assert!(forced_warnings(&unscheduled, true).is_empty());
     }
 
     #[test]
@@ -482,6 +2119,82 @@ mod tests {
         );
     }
 
+    #[test]
+    fn selects_listed_and_globbed_queries_in_configuration_order() {
+        let queries = vec![
+            query("orders_daily", true),
+            query("customers", true),
+            query("orders_hourly", true),
+            query("orders_archive", false),
+            query("ledger,eu", true),
+        ];
+        let names = |selected: Vec<&QueryConfig>| {
+            selected
+                .iter()
+                .map(|query| query.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec!["orders_daily", "customers", "orders_hourly"],
+            names(select_queries(&queries, Some("customers, orders_*"), false).unwrap())
+        );
+        assert_eq!(
+            4,
+            select_queries(&queries, Some("orders_*,customers"), true)
+                .unwrap()
+                .len()
+        );
+        assert_eq!(
+            vec!["orders_daily"],
+            names(select_queries(&queries, Some("orders_d?ily"), false).unwrap())
+        );
+        assert_eq!(
+            vec!["ledger,eu"],
+            names(select_queries(&queries, Some("ledger,eu"), false).unwrap())
+        );
+        assert_eq!(
+            "query 'invoices' was not found",
+            select_queries(&queries, Some("customers,invoices"), false)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "no query matches 'inv*'",
+            select_queries(&queries, Some("inv*"), false)
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(select_queries(&queries, Some("*_archive"), false).is_err());
+    }
+
+    #[test]
+    fn disabled_query_runs_again_once_until_arrives() {
+        let today = Utc::now().date_naive();
+        let mut expired = query("expired", false);
+        expired.until = Some(today);
+        let mut pending = query("pending", false);
+        pending.until = today.succ_opt();
+        let queries = vec![expired, pending];
+
+        assert_eq!(
+            vec!["expired"],
+            select_queries(&queries, None, false)
+                .unwrap()
+                .iter()
+                .map(|query| query.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(
+            select_queries(&queries, Some("pending"), false)
+                .unwrap_err()
+                .to_string()
+                .contains("is disabled until")
+        );
+        assert_eq!(Some(today), queries[0].expired_disable(today));
+        assert_eq!(None, queries[1].expired_disable(today));
+    }
+
     #[test]
     fn groups_queries_by_resolved_database() {
         let databases = DatabaseConfigs::from(vec![database("erp"), database("billing")]);
@@ -526,6 +2239,7 @@ mod tests {
                 param_type: "timestamp".to_string(),
                 default: Some("1970-01-01T00:00:00Z".to_string()),
                 source: Some("state_file".to_string()),
+                source_options: None,
             },
         );
         query.query.sql = "SELECT * FROM orders WHERE updated_at > $last_run_time".to_string();
@@ -563,8 +2277,10 @@ mod tests {
             auth: AuthConfig {
                 username: None,
                 password: None,
+                dynamic: None,
             },
             pool: ConnectionConfig::default(),
+            generator: None,
         }
     }
 }
@@ -0,0 +1,402 @@
+//! The run lock keeps two Yetii processes from running queries with the same state at once,
+//! which would deliver rows twice and overwrite each other's watermarks. `yetii run` holds it
+//! for the run and `yetii daemon start` for the life of the daemon. For file and SQLite state
+//! the lock is an advisory lock on a file taken from the OS, so it is released when its holder
+//! exits, however it exits. Redis state is shared across hosts, so its lock is a key in Redis
+//! that expires unless its holder keeps renewing it.
+
+use crate::config::execution_config::StateBackendConfig;
+use crate::config::yetii::YetiiConfig;
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Lock file, relative to the configuration file, used when state management is disabled.
+const DEFAULT_LOCK_FILE: &str = ".yetii/run.lock";
+
+/// How long a Redis lock outlives its last renewal; it is renewed three times as often.
+const REDIS_LOCK_TTL: Duration = Duration::from_secs(30);
+
+const REDIS_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Extends the lock's expiry only while it still holds this process's value.
+const RENEW_SCRIPT: &str = "if redis.call('GET', KEYS[1]) == ARGV[1] then return redis.call('PEXPIRE', KEYS[1], ARGV[2]) else return 0 end";
+
+/// Deletes the lock only while it still holds this process's value.
+const RELEASE_SCRIPT: &str = "if redis.call('GET', KEYS[1]) == ARGV[1] then return redis.call('DEL', KEYS[1]) else return 0 end";
+
+#[derive(Debug)]
+pub struct RunLock {
+    held: Held,
+}
+
+#[derive(Debug)]
+enum Held {
+    File(File),
+    Redis(Box<RedisLock>),
+}
+
+/// A lock key in Redis holding this process's [`Holder`], renewed by a thread until dropped.
+#[derive(Debug)]
+struct RedisLock {
+    client: redis::Client,
+    key: String,
+    value: String,
+    stop: Option<mpsc::Sender<()>>,
+    renewal: Option<JoinHandle<()>>,
+}
+
+/// Written into the lock file or key so a refused process can say who holds it.
+#[derive(Debug, Serialize, Deserialize)]
+struct Holder {
+    pid: u32,
+    host: String,
+    command: String,
+    since: DateTime<Utc>,
+    /// Random value telling this holder's Redis lock from a later one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+impl Holder {
+    fn new(command: &str) -> Self {
+        Self {
+            pid: std::process::id(),
+            host: host_name(),
+            command: command.to_string(),
+            since: Utc::now(),
+            token: None,
+        }
+    }
+}
+
+impl RunLock {
+    /// Takes the run lock for `config`: the key `<state key>:run-lock` in Redis for Redis
+    /// state, otherwise the lock file at [`RunLock::path_for`].
+    pub fn acquire_for(
+        config: &YetiiConfig,
+        config_path: &str,
+        command: &str,
+        force_unlock: bool,
+    ) -> Result<Self> {
+        let redis = config
+            .execution
+            .state_management
+            .as_ref()
+            .filter(|state_management| state_management.enabled)
+            .and_then(|state_management| match &state_management.backend {
+                Some(StateBackendConfig::Redis { url, key }) => Some((url.expose(), key)),
+                _ => None,
+            });
+        match redis {
+            Some((url, key)) => Ok(Self {
+                held: Held::Redis(Box::new(RedisLock::acquire(
+                    url,
+                    &format!("{key}:run-lock"),
+                    command,
+                    force_unlock,
+                )?)),
+            }),
+            None => Self::acquire(&Self::path_for(config, config_path), command, force_unlock),
+        }
+    }
+
+    /// The lock file beside the state file or SQLite database, so every process sharing it
+    /// shares the lock; `.yetii/run.lock` beside `config_path` without state management.
+    pub fn path_for(config: &YetiiConfig, config_path: &str) -> PathBuf {
+        let state_management = config
+            .execution
+            .state_management
+            .as_ref()
+            .filter(|state_management| state_management.enabled);
+        match state_management
+            .map(|state_management| (&state_management.backend, &state_management.state_file))
+        {
+            Some((None | Some(StateBackendConfig::File), state_file)) => {
+                PathBuf::from(format!("{state_file}.lock"))
+            }
+            Some((Some(StateBackendConfig::Sqlite { path }), _)) => {
+                PathBuf::from(format!("{path}.lock"))
+            }
+            Some((Some(StateBackendConfig::Redis { .. }), _)) | None => Path::new(config_path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(DEFAULT_LOCK_FILE),
+        }
+    }
+
+    /// Takes the lock, or fails naming the process that holds it. `force_unlock` removes the
+    /// lock file first, for a holder whose lock the OS cannot release, such as one on another
+    /// host sharing the file over a network filesystem.
+    pub fn acquire(path: &Path, command: &str, force_unlock: bool) -> Result<Self> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create directory '{}'", parent.display()))?;
+        }
+        if force_unlock && path.exists() {
+            tracing::warn!(
+                lock_file = %path.display(),
+                holder = ?read_holder(path),
+                "removing run lock because of --force-unlock"
+            );
+            remove(path)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("failed to open run lock '{}'", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return Err(held_by(&format!("'{}'", path.display()), read_holder(path)));
+            }
+            Err(TryLockError::Error(error)) => {
+                return Err(error)
+                    .with_context(|| format!("failed to lock run lock '{}'", path.display()));
+            }
+        }
+
+        let content = serde_json::to_string(&Holder::new(command))?;
+        file.set_len(0)
+            .and_then(|()| file.write_all(format!("{content}\n").as_bytes()))
+            .and_then(|()| file.sync_all())
+            .with_context(|| format!("failed to write run lock '{}'", path.display()))?;
+        Ok(Self {
+            held: Held::File(file),
+        })
+    }
+}
+
+impl Drop for RunLock {
+    /// Clears the holder and releases the lock. The file stays: removing it would let a process
+    /// that opened it before the removal and one that creates it afresh both hold a lock.
+    fn drop(&mut self) {
+        match &mut self.held {
+            Held::File(file) => {
+                let _ = file.set_len(0);
+                let _ = file.unlock();
+            }
+            Held::Redis(lock) => lock.release(),
+        }
+    }
+}
+
+impl RedisLock {
+    /// Sets `key` to this process's holder with `SET NX PX`, or fails naming the process whose
+    /// value is there. `force_unlock` deletes the key first.
+    fn acquire(url: &str, key: &str, command: &str, force_unlock: bool) -> Result<Self> {
+        let client = redis::Client::open(url).context("invalid Redis state URL")?;
+        let location = format!("'{key}' in Redis on {}", redis_host(url));
+        let mut connection = client
+            .get_connection_with_timeout(REDIS_CONNECT_TIMEOUT)
+            .with_context(|| format!("failed to connect to run lock {location}"))?;
+        let read = |connection: &mut redis::Connection| {
+            redis::cmd("GET")
+                .arg(key)
+                .query::<Option<String>>(connection)
+                .ok()
+                .flatten()
+                .and_then(|content| serde_json::from_str::<Holder>(&content).ok())
+        };
+        if force_unlock {
+            tracing::warn!(
+                lock = %location,
+                holder = ?read(&mut connection),
+                "removing run lock because of --force-unlock"
+            );
+            redis::cmd("DEL")
+                .arg(key)
+                .query::<()>(&mut connection)
+                .with_context(|| format!("failed to remove run lock {location}"))?;
+        }
+
+        let holder = Holder {
+            token: Some(format!("{:032x}", rand::random::<u128>())),
+            ..Holder::new(command)
+        };
+        let value = serde_json::to_string(&holder)?;
+        let set: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(&value)
+            .arg("NX")
+            .arg("PX")
+            .arg(REDIS_LOCK_TTL.as_millis() as u64)
+            .query(&mut connection)
+            .with_context(|| format!("failed to take run lock {location}"))?;
+        if set.is_none() {
+            return Err(held_by(&location, read(&mut connection)));
+        }
+
+        let (stop, stopped) = mpsc::channel();
+        let renewal = {
+            let (client, key, value) = (client.clone(), key.to_string(), value.clone());
+            std::thread::spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(REDIS_LOCK_TTL / 3)
+                {
+                    match run_script(&client, RENEW_SCRIPT, &key, &value) {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            tracing::error!(
+                                lock = %key,
+                                "the run lock in Redis expired or was removed; another process may run with the same state"
+                            );
+                            break;
+                        }
+                        Err(error) => tracing::warn!(
+                            lock = %key,
+                            error = %format!("{error:#}"),
+                            "failed to renew the run lock in Redis; retrying"
+                        ),
+                    }
+                }
+            })
+        };
+        Ok(Self {
+            client,
+            key: key.to_string(),
+            value,
+            stop: Some(stop),
+            renewal: Some(renewal),
+        })
+    }
+
+    /// Stops the renewal and deletes the key, unless another process has taken it since.
+    fn release(&mut self) {
+        drop(self.stop.take());
+        if let Some(renewal) = self.renewal.take() {
+            let _ = renewal.join();
+        }
+        if let Err(error) = run_script(&self.client, RELEASE_SCRIPT, &self.key, &self.value) {
+            tracing::warn!(
+                lock = %self.key,
+                error = %format!("{error:#}"),
+                "failed to release the run lock in Redis; it expires on its own"
+            );
+        }
+    }
+}
+
+/// Runs a renew or release script on the lock; true when the key still held `value`.
+fn run_script(client: &redis::Client, script: &str, key: &str, value: &str) -> Result<bool> {
+    let mut connection = client.get_connection_with_timeout(REDIS_CONNECT_TIMEOUT)?;
+    let changed: i64 = redis::cmd("EVAL")
+        .arg(script)
+        .arg(1)
+        .arg(key)
+        .arg(value)
+        .arg(REDIS_LOCK_TTL.as_millis() as u64)
+        .query(&mut connection)?;
+    Ok(changed == 1)
+}
+
+/// The host of a Redis URL, which usually carries the password.
+fn redis_host(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// The error for a lock at `location` that another process holds.
+fn held_by(location: &str, holder: Option<Holder>) -> anyhow::Error {
+    match holder {
+        Some(existing) => anyhow!(
+            "another Yetii process holds the run lock {location}: `{}` with pid {} on {} since {}; wait for it to finish, or rerun with --force-unlock if it is no longer running",
+            existing.command,
+            existing.pid,
+            existing.host,
+            existing.since
+        ),
+        None => {
+            anyhow!("another Yetii process holds the run lock {location}; wait for it to finish")
+        }
+    }
+}
+
+fn read_holder(path: &Path) -> Option<Holder> {
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+fn remove(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(error) if error.kind() != ErrorKind::NotFound => {
+            Err(error).with_context(|| format!("failed to remove run lock '{}'", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn host_name() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_a_held_lock_and_takes_one_whose_holder_exited() {
+        let path = std::env::temp_dir().join(format!("yetii-run-lock-{}.lock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let lock = RunLock::acquire(&path, "run", false).unwrap();
+        let error = RunLock::acquire(&path, "daemon start", false).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("another Yetii process holds the run lock")
+        );
+        assert!(error.to_string().contains("`run` with pid"));
+        drop(lock);
+        assert!(read_holder(&path).is_none());
+
+        // A holder that exited without cleaning up left its details but no lock behind.
+        let exited = Holder {
+            pid: u32::MAX,
+            ..Holder::new("run")
+        };
+        std::fs::write(&path, serde_json::to_string(&exited).unwrap()).unwrap();
+        let lock = RunLock::acquire(&path, "daemon start", false).unwrap();
+        assert_eq!("daemon start", read_holder(&path).unwrap().command);
+
+        // --force-unlock takes the lock even while it is held.
+        let forced = RunLock::acquire(&path, "run", true).unwrap();
+        assert_eq!("run", read_holder(&path).unwrap().command);
+        drop(forced);
+        drop(lock);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn lock_without_state_management_is_beside_the_configuration() {
+        let config: YetiiConfig =
+            serde_yaml::from_str("version: \"1.0.0\"\ndatabases: []\nqueries: []\n").unwrap();
+
+        assert_eq!(
+            PathBuf::from("/etc/yetii/.yetii/run.lock"),
+            RunLock::path_for(&config, "/etc/yetii/yetii.yaml")
+        );
+        assert_eq!(
+            PathBuf::from(".yetii/run.lock"),
+            RunLock::path_for(&config, "yetii.yaml")
+        );
+    }
+}
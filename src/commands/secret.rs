@@ -0,0 +1,59 @@
+//! `yetii secret` manages the encrypted keystore that `${secret:NAME}` references resolve from.
+
+use crate::cli::SecretCommand;
+use crate::config::keystore::Keystore;
+use anyhow::{Context, Result, bail};
+use std::io::Read;
+
+pub fn run(command: &SecretCommand) -> Result<()> {
+    let path = Keystore::location();
+    match command {
+        SecretCommand::Set { name, value } => {
+            let value = match value {
+                Some(value) => value.clone(),
+                None => read_stdin()?,
+            };
+            let mut keystore = Keystore::open(&path, true)?;
+            keystore.set(name, &value)?;
+            println!(
+                "Secret '{name}' stored in {}; reference it as ${{secret:{name}}}",
+                path.display()
+            );
+        }
+        SecretCommand::Get { name } => println!("{}", Keystore::open(&path, false)?.get(name)?),
+        SecretCommand::List => {
+            let keystore = Keystore::open(&path, false)?;
+            let mut empty = true;
+            for (name, updated_at) in keystore.list() {
+                empty = false;
+                println!("{name}  {}", updated_at.format("%Y-%m-%d %H:%M:%S UTC"));
+            }
+            if empty {
+                println!("no secrets in {}", path.display());
+            }
+        }
+        SecretCommand::Remove { name } => {
+            if !Keystore::open(&path, false)?.remove(name)? {
+                bail!("secret '{name}' is not in the keystore");
+            }
+            println!("Secret '{name}' removed");
+        }
+    }
+    Ok(())
+}
+
+/// The value piped to `secret set`, without the newline `echo` adds.
+fn read_stdin() -> Result<String> {
+    let mut value = String::new();
+    std::io::stdin()
+        .read_to_string(&mut value)
+        .context("failed to read the secret from stdin")?;
+    let value = value
+        .strip_suffix('\n')
+        .map(|value| value.strip_suffix('\r').unwrap_or(value))
+        .unwrap_or(&value);
+    if value.is_empty() {
+        bail!("no value given; pass --value or pipe the secret to stdin");
+    }
+    Ok(value.to_string())
+}
@@ -54,6 +54,9 @@ fn run_all_blocking(
     let mut seen = HashSet::new();
 
     for database in databases.as_slice() {
+        if database.db_type == DatabaseType::Generator {
+            continue;
+        }
         let key = (
             database_type_name(&database.db_type).to_string(),
             requested_driver(database).to_string(),
@@ -79,6 +82,9 @@ fn check_all_blocking(databases: &DatabaseConfigs) -> Result<SetupReport> {
     let mut seen = HashSet::new();
 
     for database in databases.as_slice() {
+        if database.db_type == DatabaseType::Generator {
+            continue;
+        }
         if database.connection_string.is_some() && database.driver.is_none() {
             missing.push(format!(
                 "database '{}' uses database.connection_string; set databases.driver so setup --check-only can verify the required registered ODBC driver",
@@ -547,6 +553,7 @@ fn database_type_name(database_type: &DatabaseType) -> &'static str {
         DatabaseType::Mysql => "mysql",
         DatabaseType::Mssql => "mssql",
         DatabaseType::Oracle => "oracle",
+        DatabaseType::Generator => "generator",
     }
 }
 
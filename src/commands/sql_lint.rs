@@ -0,0 +1,191 @@
+//! SQL checks for `check-config`: each query's SQL must parse in its database's dialect, its
+//! placeholders must match `parameters`, and it must not update or delete every row unless
+//! `lint.allow_unfiltered_writes` says so.
+
+use super::check_config::Severity;
+use crate::config::FieldPath;
+use crate::config::database::DatabaseType;
+use crate::config::query_config::QueryConfig;
+use crate::config::yetii::YetiiConfig;
+use crate::database;
+use sqlparser::ast::{Delete, Statement};
+use sqlparser::dialect::{Dialect, GenericDialect, MsSqlDialect, MySqlDialect, PostgreSqlDialect};
+use sqlparser::parser::Parser;
+use std::collections::HashSet;
+
+/// One problem in a query's SQL.
+#[derive(Debug)]
+pub struct SqlIssue {
+    pub code: &'static str,
+    pub severity: Severity,
+    /// Field the issue is about, e.g. `queries.orders.query.sql`.
+    pub field: FieldPath,
+    pub message: String,
+}
+
+/// Issues in the SQL of every query, in configuration order. Queries on generator databases are
+/// skipped because their SQL never runs.
+pub fn lint(config: &YetiiConfig) -> Vec<SqlIssue> {
+    let mut issues = Vec::new();
+    for query in &config.queries {
+        let db_type = config
+            .databases
+            .resolve_for_query(query.database.as_deref())
+            .map(|database| &database.db_type);
+        if db_type == Some(&DatabaseType::Generator) {
+            continue;
+        }
+        lint_query(query, db_type, &mut issues);
+    }
+    issues
+}
+
+fn lint_query(query: &QueryConfig, db_type: Option<&DatabaseType>, issues: &mut Vec<SqlIssue>) {
+    let lint = query.query.lint.clone().unwrap_or_default();
+    let sql = &query.query.sql;
+    let field = FieldPath::query(&query.name).join("query");
+    let mut issue = |code, field: FieldPath, message: String| {
+        issues.push(SqlIssue {
+            code,
+            severity: Severity::Error,
+            field,
+            message: format!("query '{}': {message}", query.name),
+        })
+    };
+
+    // A pull query's placeholders bind fields of the fetched records, not parameters.
+    if query.source.is_none() {
+        let placeholders = database::placeholder_names(sql);
+        let declared = query
+            .query
+            .parameters
+            .iter()
+            .flat_map(|parameters| parameters.keys())
+            .collect::<HashSet<_>>();
+        if placeholders.is_empty() {
+            if !declared.is_empty() && database::count_positional_placeholders(sql) == 0 {
+                issue(
+                    "unused_parameter",
+                    field.join("parameters"),
+                    "parameters are declared, but the SQL has no placeholders".to_string(),
+                );
+            }
+        } else {
+            for name in placeholders.iter().filter(|name| !declared.contains(name)) {
+                issue(
+                    "undeclared_parameter",
+                    field.join("sql"),
+                    format!("placeholder '{name}' has no entry in parameters"),
+                );
+            }
+            let used = placeholders.iter().collect::<HashSet<_>>();
+            let mut unused = declared
+                .into_iter()
+                .filter(|name| !used.contains(name))
+                .collect::<Vec<_>>();
+            unused.sort();
+            for name in unused {
+                issue(
+                    "unused_parameter",
+                    field.join("parameters").key(name.as_str()),
+                    format!("parameter '{name}' is not used by the SQL"),
+                );
+            }
+        }
+    }
+
+    if !lint.syntax {
+        return;
+    }
+    let statements = match Parser::parse_sql(dialect(db_type).as_ref(), sql) {
+        Ok(statements) => statements,
+        Err(error) => {
+            issue(
+                "sql_syntax",
+                field.join("sql"),
+                format!("{error}; set query.lint.syntax: false if the database accepts it"),
+            );
+            return;
+        }
+    };
+    if lint.allow_unfiltered_writes {
+        return;
+    }
+    for statement in &statements {
+        let write = match statement {
+            Statement::Update {
+                selection: None, ..
+            } => "UPDATE without WHERE",
+            Statement::Delete(Delete {
+                selection: None, ..
+            }) => "DELETE without WHERE",
+            Statement::Truncate { .. } => "TRUNCATE",
+            _ => continue,
+        };
+        issue(
+            "unfiltered_write",
+            field.join("sql"),
+            format!(
+                "{write} changes every row; add a WHERE clause or set query.lint.allow_unfiltered_writes: true"
+            ),
+        );
+    }
+}
+
+/// Oracle has no dialect of its own and is parsed as generic SQL.
+fn dialect(db_type: Option<&DatabaseType>) -> Box<dyn Dialect> {
+    match db_type {
+        Some(DatabaseType::Postgres) => Box::new(PostgreSqlDialect {}),
+        Some(DatabaseType::Mysql) => Box::new(MySqlDialect {}),
+        Some(DatabaseType::Mssql) => Box::new(MsSqlDialect {}),
+        Some(DatabaseType::Oracle | DatabaseType::Generator) | None => Box::new(GenericDialect {}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::sql_query::QueryParameter;
+
+    fn codes(query: &QueryConfig, db_type: DatabaseType) -> Vec<&'static str> {
+        let mut issues = Vec::new();
+        lint_query(query, Some(&db_type), &mut issues);
+        issues.iter().map(|issue| issue.code).collect()
+    }
+
+    #[test]
+    fn reports_syntax_placeholder_and_unfiltered_write_problems() {
+        let parameter = QueryParameter {
+            param_type: "string".to_string(),
+            default: Some("2026-01-01".to_string()),
+            source: None,
+            source_options: None,
+        };
+        let query = QueryConfig::builder(
+            "orders",
+            "SELECT * FROM orders WHERE updated_at > $after AND id::text <> :tenant",
+        )
+        .parameter("after", parameter.clone())
+        .parameter("unused", parameter)
+        .build();
+        assert_eq!(
+            vec!["undeclared_parameter", "unused_parameter"],
+            codes(&query, DatabaseType::Postgres)
+        );
+
+        let query = QueryConfig::builder("orders", "SELEC * FROM orders").build();
+        assert_eq!(vec!["sql_syntax"], codes(&query, DatabaseType::Mysql));
+
+        let mut query = QueryConfig::builder(
+            "purge",
+            "DELETE FROM staging; UPDATE t SET a = 1 WHERE id = 2",
+        )
+        .build();
+        assert_eq!(vec!["unfiltered_write"], codes(&query, DatabaseType::Mssql));
+        query.query.lint = Some(crate::config::sql_query::SqlLint {
+            syntax: true,
+            allow_unfiltered_writes: true,
+        });
+        assert!(codes(&query, DatabaseType::Mssql).is_empty());
+    }
+}
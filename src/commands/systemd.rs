@@ -0,0 +1,123 @@
+//! systemd `Type=notify` support for the daemon: `READY=1` once queries are scheduled,
+//! `RELOADING=1` and `STOPPING=1` around reloads and shutdown, and `WATCHDOG=1` pings while the
+//! scheduler keeps ticking. Nothing is sent unless systemd set `NOTIFY_SOCKET`.
+
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// When the scheduler last ran its heartbeat job.
+static LAST_TICK: LazyLock<Mutex<Instant>> = LazyLock::new(|| Mutex::new(Instant::now()));
+
+/// Sends `state`, such as `READY=1`, to systemd. Failures are logged, never fatal.
+pub fn notify(state: &str) {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(error) = send(&socket.to_string_lossy(), state) {
+        tracing::warn!(state, error = %error, "systemd notification failed");
+    }
+}
+
+#[cfg(unix)]
+fn send(socket: &str, state: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let datagram = UnixDatagram::unbound()?;
+    // `@name` is a socket in the Linux abstract namespace.
+    #[cfg(target_os = "linux")]
+    if let Some(name) = socket.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+        let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        return datagram
+            .send_to_addr(state.as_bytes(), &address)
+            .map(|_| ());
+    }
+    datagram.send_to(state.as_bytes(), socket).map(|_| ())
+}
+
+#[cfg(not(unix))]
+fn send(_socket: &str, _state: &str) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// The unit's `WatchdogSec=`, when systemd enabled the watchdog for this process.
+pub fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = std::env::var("WATCHDOG_PID")
+        && pid.trim() != std::process::id().to_string()
+    {
+        return None;
+    }
+    std::env::var("WATCHDOG_USEC")
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|usec| *usec > 0)
+        .map(Duration::from_micros)
+}
+
+/// How often the scheduler's heartbeat job runs: a quarter of the watchdog interval, so a
+/// ping is withheld only after several missed ticks.
+pub fn heartbeat_interval(watchdog: Duration) -> Duration {
+    (watchdog / 4).max(Duration::from_secs(1))
+}
+
+/// Called by the scheduler's heartbeat job.
+pub fn scheduler_ticked() {
+    *LAST_TICK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Instant::now();
+}
+
+/// Pings the watchdog twice per interval while the scheduler has ticked within the interval.
+/// A wedged scheduler stops the pings, and systemd restarts the daemon.
+pub fn start_watchdog(interval: Duration) -> JoinHandle<()> {
+    scheduler_ticked();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval / 2);
+        loop {
+            ticker.tick().await;
+            let since_tick = LAST_TICK
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .elapsed();
+            if scheduler_is_alive(since_tick, interval) {
+                notify("WATCHDOG=1");
+            } else {
+                tracing::error!(
+                    seconds_since_tick = since_tick.as_secs(),
+                    "scheduler has stopped ticking; withholding the systemd watchdog ping"
+                );
+            }
+        }
+    })
+}
+
+fn scheduler_is_alive(since_tick: Duration, watchdog: Duration) -> bool {
+    since_tick < watchdog
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixDatagram;
+
+    #[test]
+    fn sends_states_to_the_notify_socket() {
+        let path = std::env::temp_dir().join(format!("yetii-notify-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixDatagram::bind(&path).unwrap();
+
+        send(&path.to_string_lossy(), "READY=1").unwrap();
+        let mut buffer = [0; 64];
+        let read = listener.recv(&mut buffer).unwrap();
+        assert_eq!(b"READY=1", &buffer[..read]);
+
+        let watchdog = Duration::from_secs(30);
+        assert_eq!(Duration::from_millis(7500), heartbeat_interval(watchdog));
+        assert!(scheduler_is_alive(Duration::from_secs(29), watchdog));
+        assert!(!scheduler_is_alive(Duration::from_secs(30), watchdog));
+        let _ = std::fs::remove_file(&path);
+    }
+}
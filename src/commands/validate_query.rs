@@ -0,0 +1,124 @@
+//! `yetii validate-query` asks each query's database whether it accepts the SQL, without
+//! running it, so syntax errors and missing tables or columns surface before a schedule fires.
+
+use super::{parameter_sources, run};
+use crate::config;
+use crate::config::database::DatabaseType;
+use crate::config::query_config::QueryConfig;
+use crate::config::yetii::YetiiConfig;
+use crate::database::{self, QueryRequest, QuerySession};
+use crate::state::YetiiState;
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+
+enum Outcome {
+    Valid,
+    Skipped(&'static str),
+    Invalid(String),
+}
+
+pub async fn run(query_name: Option<&str>, force: bool) -> Result<()> {
+    let config = config::get_config()?.clone();
+    let queries = run::select_queries(&config.queries, query_name, force)?;
+    let state = run::read_state(&config)?;
+    let mut sessions = HashMap::new();
+    let mut outcomes = Vec::new();
+    for query in queries {
+        let outcome = match validate(&config, query, state.as_ref(), &mut sessions).await {
+            Ok(outcome) => outcome,
+            Err(error) => Outcome::Invalid(format!("{error:#}")),
+        };
+        outcomes.push((query.name.as_str(), outcome));
+    }
+    let (report, invalid) = render(&outcomes);
+    print!("{report}");
+    if invalid > 0 {
+        bail!(
+            "{invalid} of {} query(ies) failed validation",
+            outcomes.len()
+        );
+    }
+    Ok(())
+}
+
+async fn validate(
+    config: &YetiiConfig,
+    query: &QueryConfig,
+    state: Option<&YetiiState>,
+    sessions: &mut HashMap<String, QuerySession>,
+) -> Result<Outcome> {
+    let database_config = run::resolve_database(&config.databases, query)?;
+    if database_config.db_type == DatabaseType::Generator {
+        return Ok(Outcome::Skipped("generator database"));
+    }
+    if !sessions.contains_key(&database_config.name) {
+        let session = database::open_session(database_config)
+            .await
+            .with_context(|| format!("failed to connect to database '{}'", database_config.name))?;
+        sessions.insert(database_config.name.clone(), session);
+    }
+    let query = parameter_sources::bind(query, config, sessions).await?;
+    let query = query.as_ref();
+    // A pull query's placeholders bind fields of the fetched records, not parameters.
+    let binds_records = query.source.is_some();
+    let parameters = if binds_records {
+        None
+    } else {
+        run::resolve_parameters(query, state)?
+    };
+    let result = sessions[&database_config.name]
+        .validate(
+            QueryRequest {
+                sql: query.query.sql.clone(),
+                parameters,
+                max_rows: None,
+            },
+            binds_records,
+        )
+        .await;
+    Ok(match result {
+        Ok(()) => Outcome::Valid,
+        Err(error) => Outcome::Invalid(error.to_string()),
+    })
+}
+
+/// One line per query, and the number that failed.
+fn render(outcomes: &[(&str, Outcome)]) -> (String, usize) {
+    let mut report = String::new();
+    let mut invalid = 0;
+    for (query, outcome) in outcomes {
+        let line = match outcome {
+            Outcome::Valid => format!("ok       {query}\n"),
+            Outcome::Skipped(reason) => format!("skipped  {query} ({reason})\n"),
+            Outcome::Invalid(error) => {
+                invalid += 1;
+                format!("FAILED   {query}: {error}\n")
+            }
+        };
+        report.push_str(&line);
+    }
+    (report, invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_one_line_per_query_and_counts_failures() {
+        let outcomes = [
+            ("orders", Outcome::Valid),
+            ("synthetic", Outcome::Skipped("generator database")),
+            (
+                "customers",
+                Outcome::Invalid("relation \"customer\" does not exist".to_string()),
+            ),
+        ];
+        let (report, invalid) = render(&outcomes);
+        assert_eq!(
+            "ok       orders\nskipped  synthetic (generator database)\nFAILED   customers: relation \"customer\" does not exist\n",
+            report
+        );
+        assert_eq!(1, invalid);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::capabilities::{self, Capabilities};
+use odbc_api::handles::{Environment, SqlResult};
+use odbc_api::sys::{AttrOdbcVersion, HDbc, Pointer, SqlReturn};
+use std::ffi::CStr;
+
+/// `SQL_DM_VER`, which odbc-sys has no `InfoType` for.
+const SQL_DM_VER: u16 = 171;
+
+// Resolved from the driver manager odbc-sys links; declared here to pass `SQL_DM_VER`.
+unsafe extern "system" {
+    fn SQLGetInfo(
+        connection: HDbc,
+        info_type: u16,
+        value: Pointer,
+        buffer_length: i16,
+        string_length: *mut i16,
+    ) -> SqlReturn;
+}
+
+pub fn report(verbose: bool) -> String {
+    let mut report = format!("yetii {}", env!("CARGO_PKG_VERSION"));
+    if !verbose {
+        return report;
+    }
+
+    let features = env!("YETII_FEATURES");
+    let lines = [
+        ("commit", env!("YETII_GIT_COMMIT").to_string()),
+        ("target", env!("YETII_BUILD_TARGET").to_string()),
+        ("profile", env!("YETII_BUILD_PROFILE").to_string()),
+        ("rustc", env!("YETII_RUSTC_VERSION").to_string()),
+        (
+            "features",
+            if features.is_empty() {
+                "none".to_string()
+            } else {
+                features.replace(',', ", ")
+            },
+        ),
+        (
+            "os",
+            format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+        ),
+        ("odbc driver manager", odbc_driver_manager_version()),
+        ("odbc drivers", odbc_drivers()),
+        ("connectors", capabilities::CONNECTORS.join(", ")),
+        (
+            "tls",
+            format!(
+                "rustls {} (ring {})",
+                env!("YETII_RUSTLS_VERSION"),
+                env!("YETII_RING_VERSION")
+            ),
+        ),
+        ("openssl", format!("{} (sftp, kafka)", openssl_version())),
+    ];
+    for (name, value) in lines {
+        report.push_str(&format!("\n{name}: {value}"));
+    }
+    report
+}
+
+/// The version of the driver manager odbc-sys links against, as `SQLGetInfo(SQL_DM_VER)`
+/// reports it on a connection handle that is never connected.
+fn odbc_driver_manager_version() -> String {
+    let Some(environment) = succeeded(Environment::new()) else {
+        return "not found".to_string();
+    };
+    // Connection handles can only be allocated once an ODBC version is declared.
+    let connection = succeeded(environment.declare_version(AttrOdbcVersion::Odbc3))
+        .and_then(|()| succeeded(environment.allocate_connection()));
+    let Some(connection) = connection else {
+        return "unknown".to_string();
+    };
+    let mut buffer = [0u8; 64];
+    let mut length = 0;
+    // SAFETY: the connection handle is live, and the buffer's length is passed with it.
+    let result = unsafe {
+        SQLGetInfo(
+            connection.as_sys(),
+            SQL_DM_VER,
+            buffer.as_mut_ptr().cast(),
+            buffer.len() as i16,
+            &mut length,
+        )
+    };
+    if result != SqlReturn::SUCCESS {
+        return "unknown".to_string();
+    }
+    CStr::from_bytes_until_nul(&buffer)
+        .ok()
+        .map(|version| version.to_string_lossy().into_owned())
+        .filter(|version| !version.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// The version string of the OpenSSL that libssh2 and librdkafka link against.
+fn openssl_version() -> String {
+    // SAFETY: OpenSSL_version returns a pointer to a static NUL-terminated string.
+    unsafe { CStr::from_ptr(openssl_sys::OpenSSL_version(openssl_sys::OPENSSL_VERSION)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn succeeded<T>(result: SqlResult<T>) -> Option<T> {
+    match result {
+        SqlResult::Success(value) | SqlResult::SuccessWithInfo(value) => Some(value),
+        _ => None,
+    }
+}
+
+fn odbc_drivers() -> String {
+    match Capabilities::detect().odbc_drivers() {
+        Some([]) => "none registered".to_string(),
+        Some(drivers) => drivers.join(", "),
+        None => "unavailable".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbose_report_includes_build_diagnostics() {
+        assert_eq!(
+            format!("yetii {}", env!("CARGO_PKG_VERSION")),
+            report(false)
+        );
+
+        let report = report(true);
+        assert!(report.contains(&format!("target: {}", env!("YETII_BUILD_TARGET"))));
+        assert!(report.contains("commit: "));
+        assert!(report.contains("odbc driver manager: "));
+        assert!(report.contains("connectors: http, grpc"));
+        assert!(report.contains("tls: rustls "));
+        assert!(report.contains("openssl: OpenSSL "));
+    }
+}
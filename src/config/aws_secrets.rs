@@ -0,0 +1,251 @@
+//! `${aws-sm:SECRET}` and `${aws-ssm:PARAMETER}` references, resolved from AWS Secrets Manager
+//! and SSM Parameter Store when the configuration loads. Credentials and the default region come
+//! from the standard AWS configuration, so environment keys, profiles in `~/.aws`, SSO, web
+//! identity (EKS IRSA), and ECS or EC2 roles all work.
+//!
+//! `${aws-sm:prod/erp#password}` reads one key of a JSON secret. The region is taken from an
+//! ARN, or else from the AWS configuration; `AWS_ENDPOINT_URL` points every request at another
+//! endpoint, such as LocalStack.
+
+use aws_config::{BehaviorVersion, Region, SdkConfig};
+use aws_sdk_secretsmanager::error::DisplayErrorContext;
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+pub const SECRETS_MANAGER_PREFIX: &str = "aws-sm:";
+pub const PARAMETER_STORE_PREFIX: &str = "aws-ssm:";
+
+#[derive(Debug, thiserror::Error)]
+pub enum AwsSecretError {
+    #[error("'${{{0}}}' names no region; use an ARN or set AWS_REGION")]
+    MissingRegion(String),
+    #[error("AWS references could not be resolved: {0}")]
+    Client(String),
+    #[error("'${{{reference}}}' could not be resolved: {message}")]
+    Request { reference: String, message: String },
+    #[error("'${{{reference}}}' is not a JSON object with the key '{key}'")]
+    MissingKey { reference: String, key: String },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Service {
+    SecretsManager,
+    ParameterStore,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Reference<'a> {
+    /// As written in the configuration, without `${` and `}`.
+    text: &'a str,
+    service: Service,
+    id: &'a str,
+    key: Option<&'a str>,
+    region: Option<&'a str>,
+}
+
+impl<'a> Reference<'a> {
+    fn parse(reference: &'a str) -> Option<Self> {
+        let (service, rest) = if let Some(rest) = reference.strip_prefix(SECRETS_MANAGER_PREFIX) {
+            (Service::SecretsManager, rest)
+        } else {
+            (
+                Service::ParameterStore,
+                reference.strip_prefix(PARAMETER_STORE_PREFIX)?,
+            )
+        };
+        // Parameter names may not contain '#', and secret names may not either.
+        let (id, key) = match rest.split_once('#') {
+            Some((id, key)) => (id, Some(key)),
+            None => (rest, None),
+        };
+        // arn:aws:secretsmanager:eu-west-1:123456789012:secret:prod/erp-AbCdEf
+        let region = id
+            .strip_prefix("arn:")
+            .and_then(|arn| arn.split(':').nth(2))
+            .filter(|region| !region.is_empty());
+        Some(Self {
+            text: reference,
+            service,
+            id,
+            key,
+            region,
+        })
+    }
+}
+
+/// Whether `name`, the text between `${` and `}`, is an AWS reference.
+pub fn is_reference(name: &str) -> bool {
+    name.starts_with(SECRETS_MANAGER_PREFIX) || name.starts_with(PARAMETER_STORE_PREFIX)
+}
+
+/// Values of the AWS references among `names`, keyed by reference. Loading is synchronous, so
+/// on the runtime the requests block the calling worker through `block_in_place`; outside one
+/// they run on a runtime of their own.
+pub fn resolve<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+) -> Result<HashMap<String, String>, AwsSecretError> {
+    let references = names
+        .into_iter()
+        .filter_map(Reference::parse)
+        .collect::<Vec<_>>();
+    if references.is_empty() {
+        return Ok(HashMap::new());
+    }
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| handle.block_on(resolve_all(&references)))
+        }
+        // `block_in_place` would panic on a current-thread runtime.
+        Ok(_) => Err(AwsSecretError::Client(
+            "loading needs the multi-threaded runtime".to_string(),
+        )),
+        Err(_) => tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|error| AwsSecretError::Client(error.to_string()))?
+            .block_on(resolve_all(&references)),
+    }
+}
+
+async fn resolve_all(
+    references: &[Reference<'_>],
+) -> Result<HashMap<String, String>, AwsSecretError> {
+    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let mut values = HashMap::new();
+    for reference in references {
+        let value = fetch(&config, reference).await?;
+        tracing::debug!(reference = %reference.text, "resolved AWS configuration reference");
+        values.insert(reference.text.to_string(), value);
+    }
+    Ok(values)
+}
+
+async fn fetch(config: &SdkConfig, reference: &Reference<'_>) -> Result<String, AwsSecretError> {
+    let failed = |message: String| AwsSecretError::Request {
+        reference: reference.text.to_string(),
+        message,
+    };
+    let region = reference
+        .region
+        .map(|region| Region::new(region.to_string()))
+        .or_else(|| config.region().cloned())
+        .ok_or_else(|| AwsSecretError::MissingRegion(reference.text.to_string()))?;
+    let value = match reference.service {
+        Service::SecretsManager => {
+            let client = aws_sdk_secretsmanager::Client::from_conf(
+                aws_sdk_secretsmanager::config::Builder::from(config)
+                    .region(region)
+                    .build(),
+            );
+            client
+                .get_secret_value()
+                .secret_id(reference.id)
+                .send()
+                .await
+                .map_err(|error| failed(DisplayErrorContext(&error).to_string()))?
+                .secret_string
+        }
+        Service::ParameterStore => {
+            let client = aws_sdk_ssm::Client::from_conf(
+                aws_sdk_ssm::config::Builder::from(config)
+                    .region(region)
+                    .build(),
+            );
+            client
+                .get_parameter()
+                .name(reference.id)
+                .with_decryption(true)
+                .send()
+                .await
+                .map_err(|error| failed(DisplayErrorContext(&error).to_string()))?
+                .parameter
+                .and_then(|parameter| parameter.value)
+        }
+    }
+    .ok_or_else(|| failed("the response has no string value".to_string()))?;
+
+    let Some(key) = reference.key else {
+        return Ok(value);
+    };
+    let missing = || AwsSecretError::MissingKey {
+        reference: reference.text.to_string(),
+        key: key.to_string(),
+    };
+    let object: Value = serde_json::from_str(&value).map_err(|_| missing())?;
+    match &object[key] {
+        Value::String(value) => Ok(value.clone()),
+        Value::Null => Err(missing()),
+        other => Ok(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_credential_types::Credentials;
+    use aws_credential_types::provider::SharedCredentialsProvider;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn reads_a_key_of_a_signed_secrets_manager_secret() {
+        assert_eq!(
+            Some(Reference {
+                text: "aws-ssm:arn:aws:ssm:eu-west-1:123456789012:parameter/erp/url",
+                service: Service::ParameterStore,
+                id: "arn:aws:ssm:eu-west-1:123456789012:parameter/erp/url",
+                key: None,
+                region: Some("eu-west-1"),
+            }),
+            Reference::parse("aws-ssm:arn:aws:ssm:eu-west-1:123456789012:parameter/erp/url")
+        );
+        assert_eq!(None, Reference::parse("secret:DB_PASSWORD"));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0_u8; 4096];
+            while !request.ends_with(b"}") {
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            let body = r#"{"Name":"prod/erp","SecretString":"{\"username\":\"erp\",\"password\":\"s3cret\"}"}"#;
+            stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Type: application/x-amz-json-1.1\r\nContent-Length: {}\r\n\r\n{body}",
+                        body.len()
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&request).to_ascii_lowercase()
+        });
+
+        let config = SdkConfig::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .endpoint_url(format!("http://{address}"))
+            .credentials_provider(SharedCredentialsProvider::new(Credentials::new(
+                "AKIDEXAMPLE",
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                None,
+                None,
+                "test",
+            )))
+            .build();
+        let reference = Reference::parse("aws-sm:prod/erp#password").unwrap();
+        let value = fetch(&config, &reference).await.unwrap();
+        assert_eq!("s3cret", value);
+
+        let request = server.await.unwrap();
+        assert!(request.contains("x-amz-target: secretsmanager.getsecretvalue"));
+        assert!(request.contains("authorization: aws4-hmac-sha256 credential=akidexample/"));
+        assert!(request.contains("/us-east-1/secretsmanager/aws4_request"));
+        assert!(request.contains(r#"{"secretid":"prod/erp"}"#));
+    }
+}
@@ -1,14 +1,20 @@
-use crate::config::ConfigError;
+use crate::config::units;
 use crate::config::utils::default_max_connections;
 use crate::config::utils::default_retry_attempts;
 use crate::config::utils::default_timeout_seconds;
+use crate::config::{ConfigError, FieldPath};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 /// Enhanced connection config with validation
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct ConnectionConfig {
     #[serde(default = "default_max_connections")]
     pub max_connections: Option<u32>,
-    #[serde(default = "default_timeout_seconds")]
+    #[serde(
+        default = "default_timeout_seconds",
+        deserialize_with = "units::seconds"
+    )]
+    #[schemars(with = "Option<units::DurationValue>")]
     pub timeout_seconds: Option<u32>,
     #[serde(default = "default_retry_attempts")]
     pub retry_attempts: Option<u32>,
@@ -23,13 +29,24 @@ impl Default for ConnectionConfig {
     }
 }
 impl ConnectionConfig {
-    pub fn validate(&self) -> Result<(), ConfigError> {
-        if self.max_connections == Some(0) || self.max_connections > Some(1000) {
-            return Err(ConfigError::InvalidTimeout(self.max_connections));
+    /// Checks the pool of the database named `database`.
+    pub fn validate(&self, database: &str) -> Result<(), ConfigError> {
+        if let Some(max_connections) = self.max_connections
+            && !(1..=1000).contains(&max_connections)
+        {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::database(database).join("pool.max_connections"),
+                value: format!("{max_connections} is not between 1 and 1000"),
+            });
         }
 
-        if self.timeout_seconds > Some(300) {
-            return Err(ConfigError::InvalidTimeout(self.timeout_seconds));
+        if let Some(timeout) = self.timeout_seconds
+            && timeout > 300
+        {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::database(database).join("pool.timeout_seconds"),
+                value: format!("{timeout} is more than 300 seconds"),
+            });
         }
 
         Ok(())
@@ -1,6 +1,10 @@
-use crate::config::ConfigError;
 use crate::config::connection_config::ConnectionConfig;
+use crate::config::generator_config::GeneratorConfig;
+use crate::config::secret::Secret;
+use crate::config::{ConfigError, FieldPath};
+use schemars::{JsonSchema, Schema, SchemaGenerator};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone)]
@@ -27,23 +31,28 @@ impl DatabaseConfigs {
         }
     }
 
-    pub fn validate(&self) -> Result<(), ConfigError> {
+    /// The first problem with each database, so one bad entry does not hide the others.
+    pub fn validation_errors(&self) -> Vec<ConfigError> {
         if self.0.is_empty() {
-            return Err(ConfigError::MissingRequiredField("databases".to_string()));
+            return vec![ConfigError::MissingRequiredField(FieldPath::new(
+                "databases",
+            ))];
         }
 
+        let mut errors = Vec::new();
         let mut names = HashSet::new();
         for database in &self.0 {
-            database.validate()?;
+            if let Err(error) = database.validate() {
+                errors.push(error);
+            }
             if !names.insert(database.name.clone()) {
-                return Err(ConfigError::InvalidValue {
-                    field: "databases.name".to_string(),
+                errors.push(ConfigError::InvalidValue {
+                    field: FieldPath::database(&database.name).join("name"),
                     value: format!("duplicate database name '{}'", database.name),
                 });
             }
         }
-
-        Ok(())
+        errors
     }
 }
 
@@ -68,6 +77,22 @@ impl Serialize for DatabaseConfigs {
     }
 }
 
+impl JsonSchema for DatabaseConfigs {
+    fn schema_name() -> Cow<'static, str> {
+        "DatabaseConfigs".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        schemars::json_schema!({
+            "description": "A single database or a list of databases.",
+            "anyOf": [
+                generator.subschema_for::<DatabaseConfig>(),
+                generator.subschema_for::<Vec<DatabaseConfig>>(),
+            ]
+        })
+    }
+}
+
 impl<'de> Deserialize<'de> for DatabaseConfigs {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -88,7 +113,7 @@ impl<'de> Deserialize<'de> for DatabaseConfigs {
 }
 
 /// Enhanced database configuration with validation
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct DatabaseConfig {
     pub name: String,
     #[serde(rename = "type")]
@@ -106,52 +131,74 @@ pub struct DatabaseConfig {
     #[serde(default)]
     pub database: String,
     pub schema: Option<String>,
+    #[serde(default)]
     pub auth: AuthConfig,
     #[serde(default)]
     pub pool: ConnectionConfig,
+    /// Rows a `type: generator` database returns in place of running SQL.
+    #[serde(default)]
+    pub generator: Option<GeneratorConfig>,
 }
 impl DatabaseConfig {
     pub fn validate(&self) -> Result<(), ConfigError> {
         if self.name.is_empty() {
-            return Err(ConfigError::MissingRequiredField(
-                "database.name".to_string(),
-            ));
+            return Err(ConfigError::MissingRequiredField(FieldPath::new(
+                "database.name",
+            )));
+        }
+
+        if self.db_type == DatabaseType::Generator {
+            return match &self.generator {
+                Some(generator) => generator.validate(&self.name),
+                None => Err(ConfigError::MissingRequiredField(
+                    FieldPath::database(&self.name).join("generator"),
+                )),
+            };
+        }
+        if self.generator.is_some() {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::database(&self.name).join("generator"),
+                value: "generator is only used by databases with type: generator".to_string(),
+            });
         }
 
         if self.connection_string.is_none() {
             if self.host.trim().is_empty() {
                 return Err(ConfigError::MissingRequiredField(
-                    "database.host".to_string(),
+                    FieldPath::database(&self.name).join("host"),
                 ));
             }
             if self.port == 0 {
-                return Err(ConfigError::InvalidPort(self.port));
+                return Err(ConfigError::InvalidValue {
+                    field: FieldPath::database(&self.name).join("port"),
+                    value: "0 is not a valid port".to_string(),
+                });
             }
             if self.database.trim().is_empty() {
                 return Err(ConfigError::MissingRequiredField(
-                    "database.database".to_string(),
+                    FieldPath::database(&self.name).join("database"),
                 ));
             }
         }
 
-        if self.db_type.validate().is_err() {
-            return Err(ConfigError::InvalidDatabaseType(
-                "database.type not supported".to_string(),
-            ));
+        if let Some(dynamic) = &self.auth.dynamic {
+            dynamic.validate(self)?;
         }
 
-        self.pool.validate()?;
+        self.pool.validate(&self.name)?;
 
         Ok(())
     }
 }
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum DatabaseType {
     Postgres,
     Mysql,
     Mssql,
     Oracle,
+    // Synthetic rows from `generator:`; no connection or driver.
+    Generator,
 }
 impl DatabaseType {
     pub fn default_odbc_driver(&self) -> &'static str {
@@ -160,21 +207,107 @@ impl DatabaseType {
             DatabaseType::Mysql => "MySQL ODBC 8.0 Unicode Driver",
             DatabaseType::Mssql => "ODBC Driver 18 for SQL Server",
             DatabaseType::Oracle => "Oracle in instantclient",
-        }
-    }
-
-    #[allow(unused)]
-    pub fn validate(&self) -> Result<(), ConfigError> {
-        match self {
-            DatabaseType::Postgres
-            | DatabaseType::Mysql
-            | DatabaseType::Mssql
-            | DatabaseType::Oracle => Ok(()),
+            DatabaseType::Generator => "",
         }
     }
 }
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, Serialize)]
 pub struct AuthConfig {
     pub username: Option<String>,
-    pub password: Option<String>,
+    pub password: Option<Secret<String>>,
+    /// Short-lived credentials fetched each time a connection opens, in place of `password`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dynamic: Option<DynamicCredentials>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum DynamicCredentials {
+    /// A username and password leased from Vault's database secrets engine. The lease is
+    /// renewed while the connection stays open.
+    Vault {
+        address: String,
+        token: Secret<String>,
+        /// Mount path of the secrets engine.
+        #[serde(default = "default_vault_mount")]
+        mount: String,
+        role: String,
+        #[serde(default)]
+        namespace: Option<String>,
+    },
+    /// An RDS IAM authentication token for `auth.username`, signed for every connection.
+    RdsIam {
+        region: String,
+        #[serde(default)]
+        access_key_id: Option<String>,
+        #[serde(default)]
+        secret_access_key: Option<Secret<String>>,
+        #[serde(default)]
+        session_token: Option<Secret<String>>,
+        /// AWS profile for the default credentials chain, used when keys are not set inline.
+        #[serde(default)]
+        profile: Option<String>,
+    },
+}
+
+fn default_vault_mount() -> String {
+    "database".to_string()
+}
+
+impl DynamicCredentials {
+    fn validate(&self, database: &DatabaseConfig) -> Result<(), ConfigError> {
+        let field = |name: &str| {
+            FieldPath::database(&database.name)
+                .join("auth.dynamic")
+                .join(name)
+        };
+        if database.connection_string.is_some() {
+            return Err(ConfigError::InvalidValue {
+                field: field("provider"),
+                value:
+                    "dynamic credentials need host, port, and database instead of connection_string"
+                        .to_string(),
+            });
+        }
+        let required = match self {
+            DynamicCredentials::Vault {
+                address,
+                token,
+                mount,
+                role,
+                ..
+            } => vec![
+                ("address", address.as_str()),
+                ("token", token.expose().as_str()),
+                ("mount", mount.as_str()),
+                ("role", role.as_str()),
+            ],
+            DynamicCredentials::RdsIam {
+                region,
+                access_key_id,
+                secret_access_key,
+                ..
+            } => {
+                if access_key_id.is_some() != secret_access_key.is_some() {
+                    return Err(ConfigError::MissingRequiredField(field(
+                        if access_key_id.is_some() {
+                            "secret_access_key"
+                        } else {
+                            "access_key_id"
+                        },
+                    )));
+                }
+                if database.auth.username.as_deref().is_none_or(str::is_empty) {
+                    return Err(ConfigError::MissingRequiredField(
+                        FieldPath::database(&database.name).join("auth.username"),
+                    ));
+                }
+                vec![("region", region.as_str())]
+            }
+        };
+        match required.iter().find(|(_, value)| value.trim().is_empty()) {
+            Some((name, _)) => Err(ConfigError::MissingRequiredField(field(name))),
+            None => Ok(()),
+        }
+    }
 }
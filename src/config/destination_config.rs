@@ -0,0 +1,1289 @@
+use crate::config::request_config::{BatchFailurePolicy, FormatOptions, RetryBackoff};
+use crate::config::secret::Secret;
+use crate::config::units;
+use crate::config::{ConfigError, FieldPath};
+use crate::http::format::PayloadFormat;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Non-HTTP delivery target for a query, used instead of `endpoint`.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[serde(tag = "type")]
+pub enum DestinationConfig {
+    #[serde(rename = "grpc")]
+    Grpc(GrpcSinkConfig),
+    #[serde(rename = "redis")]
+    Redis(RedisSinkConfig),
+    #[serde(rename = "mqtt")]
+    Mqtt(MqttSinkConfig),
+    #[serde(rename = "ftp")]
+    Ftp(Box<FtpSinkConfig>),
+    #[serde(rename = "file")]
+    File(Box<FileSinkConfig>),
+    #[serde(rename = "s3")]
+    S3(Box<S3SinkConfig>),
+    #[serde(rename = "sftp")]
+    Sftp(Box<SftpSinkConfig>),
+    #[serde(rename = "kafka")]
+    Kafka(Box<KafkaSinkConfig>),
+    #[serde(rename = "amqp")]
+    Amqp(Box<AmqpSinkConfig>),
+    #[serde(rename = "database")]
+    Database(DatabaseSinkConfig),
+}
+
+impl DestinationConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        match self {
+            DestinationConfig::Grpc(grpc) => grpc.validate(query_name),
+            DestinationConfig::Redis(redis) => redis.validate(query_name),
+            DestinationConfig::Mqtt(mqtt) => mqtt.validate(query_name),
+            DestinationConfig::Ftp(ftp) => ftp.validate(query_name),
+            DestinationConfig::File(file) => file.validate(query_name),
+            DestinationConfig::S3(s3) => s3.validate(query_name),
+            DestinationConfig::Sftp(sftp) => sftp.validate(query_name),
+            DestinationConfig::Kafka(kafka) => kafka.validate(query_name),
+            DestinationConfig::Amqp(amqp) => amqp.validate(query_name),
+            DestinationConfig::Database(database) => database.validate(query_name),
+        }
+    }
+
+    /// The `type` value, e.g. `kafka`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DestinationConfig::Grpc(_) => "grpc",
+            DestinationConfig::Redis(_) => "redis",
+            DestinationConfig::Mqtt(_) => "mqtt",
+            DestinationConfig::Ftp(_) => "ftp",
+            DestinationConfig::File(_) => "file",
+            DestinationConfig::S3(_) => "s3",
+            DestinationConfig::Sftp(_) => "sftp",
+            DestinationConfig::Kafka(_) => "kafka",
+            DestinationConfig::Amqp(_) => "amqp",
+            DestinationConfig::Database(_) => "database",
+        }
+    }
+
+    pub fn batch_size(&self) -> Option<u32> {
+        match self {
+            DestinationConfig::Grpc(grpc) => grpc.batch_size,
+            DestinationConfig::Redis(redis) => redis.batch_size,
+            DestinationConfig::Mqtt(mqtt) => mqtt.batch_size,
+            DestinationConfig::Ftp(ftp) => ftp.batch_size,
+            DestinationConfig::File(file) => file.batch_size,
+            DestinationConfig::S3(s3) => s3.batch_size,
+            DestinationConfig::Sftp(sftp) => sftp.batch_size,
+            DestinationConfig::Kafka(kafka) => kafka.batch_size,
+            DestinationConfig::Amqp(amqp) => amqp.batch_size,
+            DestinationConfig::Database(database) => database.batch_size,
+        }
+    }
+}
+
+/// `retry_*` settings shared by every destination that retries failed deliveries.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, Serialize)]
+pub struct RetrySettings {
+    /// Retries after the first failed attempt (default 0).
+    pub retry_attempts: Option<u32>,
+    /// Delay before the first retry (default 1).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub retry_delay_seconds: Option<u32>,
+    pub retry_backoff: Option<RetryBackoff>,
+    /// Fraction of each retry delay that is randomized, from 0.0 to 1.0 (default 0.2).
+    pub retry_jitter: Option<f64>,
+    /// Upper bound for a single retry delay (default 300).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub retry_max_delay_seconds: Option<u32>,
+}
+
+impl RetrySettings {
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        if let Some(jitter) = self.retry_jitter
+            && !(0.0..=1.0).contains(&jitter)
+        {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("retry_jitter"),
+                value: jitter.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Sends rows as messages to a unary gRPC method described by a descriptor set or server reflection.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct GrpcSinkConfig {
+    /// `http://` or `https://` address of the gRPC server.
+    pub url: String,
+    /// Fully qualified method, e.g. `ingest.v1.IngestService/Ingest`.
+    pub method: String,
+    /// Compiled `FileDescriptorSet` (`protoc --include_imports --descriptor_set_out`);
+    /// server reflection is used when omitted.
+    pub descriptor_set: Option<String>,
+    /// Repeated message field that receives a whole batch; without it every row is its own request.
+    pub batch_field: Option<String>,
+    pub batch_size: Option<u32>,
+    /// Per-call deadline (default 30).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub deadline_seconds: Option<u32>,
+    /// Request metadata (headers) sent with every call.
+    pub metadata: Option<HashMap<String, String>>,
+    /// Drop row fields that the request message does not define instead of failing.
+    #[serde(default)]
+    pub ignore_unknown_fields: bool,
+    #[serde(flatten)]
+    pub retry: RetrySettings,
+    /// gRPC status codes that are retried, by name (default `unavailable`, `deadline_exceeded`,
+    /// `resource_exhausted`, `aborted`).
+    pub retry_on_codes: Option<Vec<GrpcStatusCode>>,
+    #[serde(default)]
+    pub on_batch_failure: BatchFailurePolicy,
+}
+
+impl GrpcSinkConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::query(query_name).join("destination").join(name);
+        let invalid = |name: &str, value: &str| ConfigError::InvalidValue {
+            field: field(name),
+            value: value.to_string(),
+        };
+
+        match url::Url::parse(&self.url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => {}
+            _ => return Err(invalid("url", &self.url)),
+        }
+        match self.method.trim_start_matches('/').split_once('/') {
+            Some((service, method)) if !service.is_empty() && !method.is_empty() => {}
+            _ => return Err(invalid("method", &self.method)),
+        }
+        if self
+            .descriptor_set
+            .as_ref()
+            .is_some_and(|path| path.trim().is_empty())
+        {
+            return Err(ConfigError::MissingRequiredField(field("descriptor_set")));
+        }
+        if self.batch_size == Some(0) {
+            return Err(invalid("batch_size", "0"));
+        }
+        if self.deadline_seconds == Some(0) {
+            return Err(invalid("deadline_seconds", "0"));
+        }
+        self.retry
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        Ok(())
+    }
+}
+
+/// A gRPC status code by its snake_case name; the upper-case names are accepted too.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrpcStatusCode {
+    #[serde(alias = "CANCELLED")]
+    Cancelled,
+    #[serde(alias = "UNKNOWN")]
+    Unknown,
+    #[serde(alias = "INVALID_ARGUMENT")]
+    InvalidArgument,
+    #[serde(alias = "DEADLINE_EXCEEDED")]
+    DeadlineExceeded,
+    #[serde(alias = "NOT_FOUND")]
+    NotFound,
+    #[serde(alias = "ALREADY_EXISTS")]
+    AlreadyExists,
+    #[serde(alias = "PERMISSION_DENIED")]
+    PermissionDenied,
+    #[serde(alias = "RESOURCE_EXHAUSTED")]
+    ResourceExhausted,
+    #[serde(alias = "FAILED_PRECONDITION")]
+    FailedPrecondition,
+    #[serde(alias = "ABORTED")]
+    Aborted,
+    #[serde(alias = "OUT_OF_RANGE")]
+    OutOfRange,
+    #[serde(alias = "UNIMPLEMENTED")]
+    Unimplemented,
+    #[serde(alias = "INTERNAL")]
+    Internal,
+    #[serde(alias = "UNAVAILABLE")]
+    Unavailable,
+    #[serde(alias = "DATA_LOSS")]
+    DataLoss,
+    #[serde(alias = "UNAUTHENTICATED")]
+    Unauthenticated,
+}
+
+/// Writes rows to Redis as hashes or JSON strings under templated keys, or appends them to a
+/// stream or list.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct RedisSinkConfig {
+    /// `redis://` or `rediss://` URL, e.g. `redis://:${REDIS_PASSWORD}@cache:6379/0`.
+    pub url: Secret<String>,
+    #[serde(default)]
+    pub mode: RedisWriteMode,
+    /// Key template; `{{field}}` placeholders are replaced with row values, e.g. `customer:{{id}}`.
+    /// Names the stream or list for the `stream` and `list` modes.
+    pub key: String,
+    /// Expiry set on `hash` and `json` keys.
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub ttl_seconds: Option<u64>,
+    /// Approximate stream length cap (`XADD MAXLEN ~`), `stream` mode only.
+    pub stream_max_len: Option<usize>,
+    pub batch_size: Option<u32>,
+    /// Connection and command timeout (default 30).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+    #[serde(flatten)]
+    pub retry: RetrySettings,
+    #[serde(default)]
+    pub on_batch_failure: BatchFailurePolicy,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedisWriteMode {
+    /// Replace the hash at `key` with the row's non-null fields.
+    #[default]
+    Hash,
+    /// Store the row as a JSON string at `key`.
+    Json,
+    /// Append the row's fields as a stream entry.
+    Stream,
+    /// Push the row as JSON onto the tail of a list.
+    List,
+}
+
+impl RedisSinkConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::query(query_name).join("destination").join(name);
+        let invalid = |name: &str, value: &str| ConfigError::InvalidValue {
+            field: field(name),
+            value: value.to_string(),
+        };
+
+        validate_redis_url(self.url.expose(), &field("url"))?;
+        validate_key_template(&self.key, &field("key"))?;
+        if self.ttl_seconds == Some(0) {
+            return Err(invalid("ttl_seconds", "0"));
+        }
+        if self.ttl_seconds.is_some()
+            && matches!(self.mode, RedisWriteMode::Stream | RedisWriteMode::List)
+        {
+            return Err(invalid(
+                "ttl_seconds",
+                "only supported with the hash and json modes",
+            ));
+        }
+        if self.stream_max_len.is_some() && self.mode != RedisWriteMode::Stream {
+            return Err(invalid(
+                "stream_max_len",
+                "only supported with the stream mode",
+            ));
+        }
+        if self.batch_size == Some(0) {
+            return Err(invalid("batch_size", "0"));
+        }
+        if self.timeout_seconds == Some(0) {
+            return Err(invalid("timeout_seconds", "0"));
+        }
+        self.retry
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        Ok(())
+    }
+}
+
+/// Publishes each row as a JSON message to an MQTT broker (protocol 3.1.1).
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct MqttSinkConfig {
+    /// `mqtt://host:1883` or, for TLS, `mqtts://host:8883`.
+    pub url: String,
+    /// Topic template; `{{field}}` placeholders are replaced with row values,
+    /// e.g. `factory/{{plant}}/machines/{{machine_id}}/status`.
+    pub topic: String,
+    /// 0 (at most once), 1 (at least once, default), or 2 (exactly once).
+    pub qos: Option<u8>,
+    /// Ask the broker to keep the last message on each topic for new subscribers.
+    #[serde(default)]
+    pub retain: bool,
+    /// Defaults to `yetii-<query name>`.
+    pub client_id: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    pub tls: Option<ClientTlsConfig>,
+    /// Keep-alive interval (default 30).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub keep_alive_seconds: Option<u32>,
+    pub batch_size: Option<u32>,
+    /// Time allowed for a batch to be acknowledged by the broker (default 30).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+    #[serde(flatten)]
+    pub retry: RetrySettings,
+    #[serde(default)]
+    pub on_batch_failure: BatchFailurePolicy,
+}
+
+/// TLS settings for `mqtts://`, `ftps://`, and `amqps://` servers and Kafka brokers; the bundled web PKI roots are trusted when
+/// `ca_file` is not set.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct ClientTlsConfig {
+    /// PEM bundle of CA certificates trusted instead of the web PKI roots.
+    pub ca_file: Option<String>,
+    /// PEM client certificate chain for mutual TLS; requires `client_key_file`.
+    pub client_cert_file: Option<String>,
+    pub client_key_file: Option<String>,
+}
+
+impl MqttSinkConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::query(query_name).join("destination").join(name);
+        let invalid = |name: &str, value: &str| ConfigError::InvalidValue {
+            field: field(name),
+            value: value.to_string(),
+        };
+
+        let scheme = match url::Url::parse(&self.url) {
+            Ok(url)
+                if matches!(url.scheme(), "mqtt" | "mqtts")
+                    && url.host_str().is_some()
+                    && url.username().is_empty()
+                    && url.password().is_none() =>
+            {
+                url.scheme().to_string()
+            }
+            _ => {
+                return Err(invalid(
+                    "url",
+                    "expected mqtt://host[:port] or mqtts://host[:port]; set username and password separately",
+                ));
+            }
+        };
+        validate_key_template(&self.topic, &field("topic"))?;
+        if self.topic.contains(['+', '#']) {
+            return Err(invalid("topic", "wildcards cannot be published to"));
+        }
+        if let Some(qos) = self.qos
+            && qos > 2
+        {
+            return Err(invalid("qos", &qos.to_string()));
+        }
+        if self
+            .client_id
+            .as_ref()
+            .is_some_and(|client_id| client_id.trim().is_empty())
+        {
+            return Err(ConfigError::MissingRequiredField(field("client_id")));
+        }
+        if self.password.is_some() && self.username.is_none() {
+            return Err(ConfigError::MissingRequiredField(
+                field("username").note("required with password"),
+            ));
+        }
+        if let Some(tls) = &self.tls {
+            if scheme != "mqtts" {
+                return Err(invalid("tls", "only supported with mqtts:// URLs"));
+            }
+            if tls.client_cert_file.is_some() != tls.client_key_file.is_some() {
+                return Err(ConfigError::MissingRequiredField(field(
+                    if tls.client_cert_file.is_some() {
+                        "tls.client_key_file"
+                    } else {
+                        "tls.client_cert_file"
+                    },
+                )));
+            }
+        }
+        if self.keep_alive_seconds == Some(0) {
+            return Err(invalid("keep_alive_seconds", "0"));
+        }
+        if self.batch_size == Some(0) {
+            return Err(invalid("batch_size", "0"));
+        }
+        if self.timeout_seconds == Some(0) {
+            return Err(invalid("timeout_seconds", "0"));
+        }
+        self.retry
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        Ok(())
+    }
+}
+
+/// Uploads each batch as a file to an FTP or FTPS server.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct FtpSinkConfig {
+    /// `ftp://host[:port]` or `ftps://host[:port]`.
+    pub url: String,
+    /// Anonymous login when omitted.
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    /// Remote directory for uploads; relative paths start at the login directory.
+    pub directory: Option<String>,
+    /// File name template with `{{query}}`, `{{date}}` (UTC, `2026-01-31`), `{{timestamp}}` (UTC,
+    /// e.g. `20260131T120000Z`), and `{{sequence}}` (batch number within the run), e.g.
+    /// `orders_{{timestamp}}_{{sequence}}.csv`.
+    pub file_name: String,
+    #[serde(flatten)]
+    pub body: FormatOptions,
+    #[serde(default)]
+    pub mode: FtpMode,
+    /// How `ftps://` connections start TLS.
+    #[serde(default)]
+    pub tls_mode: FtpTlsMode,
+    pub tls: Option<ClientTlsConfig>,
+    /// Suffix of the temporary name a file is uploaded under before it is renamed into place
+    /// (default `.part`); an empty suffix uploads directly to the final name.
+    pub temp_suffix: Option<String>,
+    pub batch_size: Option<u32>,
+    /// Timeout for connecting and for each command or transfer (default 30).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+    #[serde(flatten)]
+    pub retry: RetrySettings,
+    #[serde(default)]
+    pub on_batch_failure: BatchFailurePolicy,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FtpMode {
+    /// The client opens data connections to the address the server announces.
+    #[default]
+    Passive,
+    /// The server connects back to a port the client listens on.
+    Active,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FtpTlsMode {
+    /// Connect in plain text and upgrade with `AUTH TLS` (default port 21).
+    #[default]
+    Explicit,
+    /// TLS from the first byte (default port 990).
+    Implicit,
+}
+
+impl FtpSinkConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::query(query_name).join("destination").join(name);
+        let invalid = |name: &str, value: &str| ConfigError::InvalidValue {
+            field: field(name),
+            value: value.to_string(),
+        };
+
+        let scheme = match url::Url::parse(&self.url) {
+            Ok(url)
+                if matches!(url.scheme(), "ftp" | "ftps")
+                    && url.host_str().is_some()
+                    && url.username().is_empty()
+                    && url.password().is_none()
+                    && matches!(url.path(), "" | "/") =>
+            {
+                url.scheme().to_string()
+            }
+            _ => {
+                return Err(invalid(
+                    "url",
+                    "expected ftp://host[:port] or ftps://host[:port]; set username, password, and directory separately",
+                ));
+            }
+        };
+        validate_file_name(&self.file_name, &field("file_name"))?;
+        if self.password.is_some() && self.username.is_none() {
+            return Err(ConfigError::MissingRequiredField(
+                field("username").note("required with password"),
+            ));
+        }
+        if scheme != "ftps" && self.tls.is_some() {
+            return Err(invalid("tls", "only supported with ftps:// URLs"));
+        }
+        if let Some(tls) = &self.tls
+            && tls.client_cert_file.is_some() != tls.client_key_file.is_some()
+        {
+            return Err(ConfigError::MissingRequiredField(field(
+                if tls.client_cert_file.is_some() {
+                    "tls.client_key_file"
+                } else {
+                    "tls.client_cert_file"
+                },
+            )));
+        }
+        self.body
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        if self.batch_size == Some(0) {
+            return Err(invalid("batch_size", "0"));
+        }
+        if self.timeout_seconds == Some(0) {
+            return Err(invalid("timeout_seconds", "0"));
+        }
+        self.retry
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        Ok(())
+    }
+}
+
+/// Writes each batch as a file in a local directory, e.g. an outbox for air-gapped exchange.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct FileSinkConfig {
+    /// Output directory; created when missing.
+    pub directory: String,
+    /// File name template with `{{query}}`, `{{date}}` (UTC, `2026-01-31`), `{{timestamp}}` (UTC,
+    /// e.g. `20260131T120000Z`), and `{{sequence}}` (batch number within the run), e.g.
+    /// `orders_{{timestamp}}_{{sequence}}.csv`.
+    pub file_name: String,
+    #[serde(flatten)]
+    pub body: FormatOptions,
+    /// Suffix of the temporary name a file is written under before it is renamed into place
+    /// (default `.part`); an empty suffix writes directly to the final name.
+    pub temp_suffix: Option<String>,
+    /// Replace a file that already has the rendered name instead of failing the batch.
+    #[serde(default)]
+    pub overwrite: bool,
+    /// Manifest of the run's files, written next to them after the last batch.
+    pub manifest: Option<ManifestConfig>,
+    pub batch_size: Option<u32>,
+    #[serde(default)]
+    pub on_batch_failure: BatchFailurePolicy,
+}
+
+impl FileSinkConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::query(query_name).join("destination").join(name);
+        if self.directory.trim().is_empty() {
+            return Err(ConfigError::MissingRequiredField(field("directory")));
+        }
+        validate_file_name(&self.file_name, &field("file_name"))?;
+        self.body
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        if let Some(manifest) = &self.manifest {
+            manifest.validate(&field("manifest"))?;
+        }
+        if self.batch_size == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                field: field("batch_size"),
+                value: "0".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A JSON file listing the files a run delivered, with their row counts and SHA-256 checksums,
+/// for receivers that process a drop only once it is complete.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct ManifestConfig {
+    /// Name template with `{{query}}`, `{{date}}`, `{{timestamp}}`, and `{{sequence}}` (the
+    /// number of files listed); default `{{query}}_{{timestamp}}.manifest.json`.
+    pub file_name: Option<String>,
+    /// Detached signature of the manifest, delivered before it.
+    pub signature: Option<ManifestSignatureConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct ManifestSignatureConfig {
+    #[serde(default)]
+    pub algorithm: SignatureAlgorithm,
+    /// `ed25519`: PKCS#8 private key, PEM or DER, as written by `openssl genpkey -algorithm
+    /// ed25519`. `hmac_sha256`: file holding the shared secret; a trailing newline is ignored.
+    pub key_file: String,
+    /// Appended to the manifest name to name the signature (default `.sig`).
+    pub suffix: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureAlgorithm {
+    /// 64-byte Ed25519 signature, verifiable with the public key alone.
+    #[default]
+    Ed25519,
+    /// 32-byte HMAC-SHA256 over the manifest with a shared secret.
+    HmacSha256,
+}
+
+impl ManifestConfig {
+    pub fn file_name(&self) -> &str {
+        self.file_name
+            .as_deref()
+            .unwrap_or("{{query}}_{{timestamp}}.manifest.json")
+    }
+
+    fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        validate_file_name(self.file_name(), &field.join("file_name"))?;
+        if let Some(signature) = &self.signature {
+            if signature.key_file.trim().is_empty() {
+                return Err(ConfigError::MissingRequiredField(
+                    field.join("signature.key_file"),
+                ));
+            }
+            if signature
+                .suffix
+                .as_deref()
+                .is_some_and(|suffix| suffix.is_empty() || suffix.contains(['/', '\\']))
+            {
+                return Err(ConfigError::InvalidValue {
+                    field: field.join("signature.suffix"),
+                    value: "must be a non-empty name suffix".to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Uploads each batch as a file to an SFTP server.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct SftpSinkConfig {
+    pub host: String,
+    /// Default 22.
+    pub port: Option<u16>,
+    pub username: String,
+    /// Password authentication; set this or `private_key_file`.
+    pub password: Option<Secret<String>>,
+    /// OpenSSH or PEM private key for public key authentication.
+    pub private_key_file: Option<String>,
+    pub private_key_passphrase: Option<Secret<String>>,
+    /// OpenSSH `known_hosts` file the server's key must appear in (default
+    /// `~/.ssh/known_hosts`); ignored when `host_key_fingerprint` is set.
+    pub known_hosts_file: Option<String>,
+    /// Pinned server key as printed by `ssh-keygen -lf`, e.g.
+    /// `SHA256:nThbg6kXUpJWGl7E1IGOCspRomTxdCARLviKw6E5SY8`.
+    pub host_key_fingerprint: Option<String>,
+    /// Remote directory template with the same placeholders as `file_name`, e.g.
+    /// `/inbound/{{query}}/{{date}}`; relative paths start at the login directory.
+    pub directory: Option<String>,
+    /// Create missing directories along `directory` before uploading.
+    #[serde(default)]
+    pub create_directories: bool,
+    /// File name template with `{{query}}`, `{{date}}` (UTC, `2026-01-31`), `{{timestamp}}` (UTC,
+    /// e.g. `20260131T120000Z`), and `{{sequence}}` (batch number within the run), e.g.
+    /// `orders_{{timestamp}}_{{sequence}}.csv`.
+    pub file_name: String,
+    #[serde(flatten)]
+    pub body: FormatOptions,
+    /// Suffix of the temporary name a file is uploaded under before it is renamed into place
+    /// (default `.part`); an empty suffix uploads directly to the final name.
+    pub temp_suffix: Option<String>,
+    pub batch_size: Option<u32>,
+    /// Timeout for connecting and for each SSH operation (default 30).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+    #[serde(flatten)]
+    pub retry: RetrySettings,
+    #[serde(default)]
+    pub on_batch_failure: BatchFailurePolicy,
+}
+
+impl SftpSinkConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::query(query_name).join("destination").join(name);
+        let invalid = |name: &str, value: &str| ConfigError::InvalidValue {
+            field: field(name),
+            value: value.to_string(),
+        };
+
+        if self.host.trim().is_empty() {
+            return Err(ConfigError::MissingRequiredField(field("host")));
+        }
+        if self.port == Some(0) {
+            return Err(invalid("port", "0"));
+        }
+        if self.username.trim().is_empty() {
+            return Err(ConfigError::MissingRequiredField(field("username")));
+        }
+        match (&self.password, &self.private_key_file) {
+            (None, None) => {
+                return Err(ConfigError::MissingRequiredField(field(
+                    "password or private_key_file",
+                )));
+            }
+            (Some(_), Some(_)) => {
+                return Err(invalid(
+                    "private_key_file",
+                    "set either password or private_key_file, not both",
+                ));
+            }
+            _ => {}
+        }
+        if self.private_key_passphrase.is_some() && self.private_key_file.is_none() {
+            return Err(ConfigError::MissingRequiredField(
+                field("private_key_file").note("required with private_key_passphrase"),
+            ));
+        }
+        if let Some(fingerprint) = &self.host_key_fingerprint
+            && fingerprint
+                .strip_prefix("SHA256:")
+                .is_none_or(|hash| hash.trim_end_matches('=').len() != 43)
+        {
+            return Err(invalid("host_key_fingerprint", fingerprint));
+        }
+        if let Some(directory) = &self.directory {
+            validate_key_template(directory, &field("directory"))?;
+            validate_placeholders(directory, &field("directory"))?;
+        }
+        validate_file_name(&self.file_name, &field("file_name"))?;
+        self.body
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        if self.batch_size == Some(0) {
+            return Err(invalid("batch_size", "0"));
+        }
+        if self.timeout_seconds == Some(0) {
+            return Err(invalid("timeout_seconds", "0"));
+        }
+        self.retry
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        Ok(())
+    }
+}
+
+/// Uploads each batch as an object to Amazon S3 or an S3-compatible store.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct S3SinkConfig {
+    pub bucket: String,
+    pub region: String,
+    /// Key prefix template with the same placeholders as `file_name`, e.g.
+    /// `raw/{{query}}/dt={{date}}`.
+    pub prefix: Option<String>,
+    /// Object name template with `{{query}}`, `{{date}}` (UTC, `2026-01-31`), `{{timestamp}}`
+    /// (UTC, e.g. `20260131T120000Z`), and `{{sequence}}` (batch number within the run).
+    pub file_name: String,
+    #[serde(flatten)]
+    pub body: FormatOptions,
+    /// Base URL of an S3-compatible service such as MinIO; AWS when omitted.
+    pub endpoint: Option<String>,
+    /// Address buckets as `<endpoint>/<bucket>` instead of `<bucket>.<endpoint host>`; the
+    /// default when `endpoint` is set.
+    pub path_style: Option<bool>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<Secret<String>>,
+    pub session_token: Option<Secret<String>>,
+    /// AWS profile used when keys are not set inline; the default credential chain otherwise.
+    pub profile: Option<String>,
+    pub server_side_encryption: Option<S3Encryption>,
+    /// KMS key for `server_side_encryption: aws_kms`; the bucket's default key when omitted.
+    pub kms_key_id: Option<String>,
+    /// e.g. `STANDARD_IA` or `INTELLIGENT_TIERING`; the bucket default when omitted.
+    pub storage_class: Option<String>,
+    /// Manifest of the run's objects, uploaded under `prefix` after the last batch.
+    pub manifest: Option<ManifestConfig>,
+    pub batch_size: Option<u32>,
+    /// Timeout of each upload attempt (default 30).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+    #[serde(flatten)]
+    pub retry: RetrySettings,
+    #[serde(default)]
+    pub on_batch_failure: BatchFailurePolicy,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum S3Encryption {
+    /// S3-managed keys (`AES256`).
+    Aes256,
+    /// AWS KMS keys (`aws:kms`).
+    AwsKms,
+}
+
+impl S3Encryption {
+    pub fn header_value(self) -> &'static str {
+        match self {
+            S3Encryption::Aes256 => "AES256",
+            S3Encryption::AwsKms => "aws:kms",
+        }
+    }
+}
+
+impl S3SinkConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::query(query_name).join("destination").join(name);
+        let invalid = |name: &str, value: &str| ConfigError::InvalidValue {
+            field: field(name),
+            value: value.to_string(),
+        };
+
+        // Lowercase letters, digits, dots, and hyphens keep the name valid as a host label.
+        if self.bucket.len() < 3
+            || self.bucket.len() > 63
+            || !self.bucket.chars().all(|character| {
+                character.is_ascii_lowercase()
+                    || character.is_ascii_digit()
+                    || matches!(character, '.' | '-')
+            })
+        {
+            return Err(invalid("bucket", &self.bucket));
+        }
+        if self.region.trim().is_empty() {
+            return Err(ConfigError::MissingRequiredField(field("region")));
+        }
+        if let Some(prefix) = &self.prefix {
+            validate_key_template(prefix, &field("prefix"))?;
+            validate_placeholders(prefix, &field("prefix"))?;
+        }
+        validate_file_name(&self.file_name, &field("file_name"))?;
+        self.body
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        if let Some(manifest) = &self.manifest {
+            manifest.validate(&field("manifest"))?;
+        }
+        if let Some(endpoint) = &self.endpoint {
+            match url::Url::parse(endpoint) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => {
+                }
+                _ => return Err(invalid("endpoint", endpoint)),
+            }
+        }
+        if self.access_key_id.is_some() != self.secret_access_key.is_some() {
+            return Err(ConfigError::MissingRequiredField(field(
+                if self.access_key_id.is_some() {
+                    "secret_access_key"
+                } else {
+                    "access_key_id"
+                },
+            )));
+        }
+        if self.kms_key_id.is_some() && self.server_side_encryption != Some(S3Encryption::AwsKms) {
+            return Err(invalid(
+                "kms_key_id",
+                "requires server_side_encryption: aws_kms",
+            ));
+        }
+        if self.batch_size == Some(0) {
+            return Err(invalid("batch_size", "0"));
+        }
+        if self.timeout_seconds == Some(0) {
+            return Err(invalid("timeout_seconds", "0"));
+        }
+        self.retry
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        Ok(())
+    }
+}
+
+/// Publishes rows to a Kafka topic, one message per row or one message per batch.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct KafkaSinkConfig {
+    /// Bootstrap brokers as `host:port`; the rest of the cluster is discovered from them.
+    pub brokers: Vec<String>,
+    pub topic: String,
+    #[serde(default)]
+    pub mode: KafkaMessageMode,
+    /// Row field whose value becomes the message key (`row` mode only); keyed messages land on
+    /// the same partition as with the Java client's default partitioner.
+    pub key_field: Option<String>,
+    /// Static headers added to every message.
+    pub headers: Option<HashMap<String, String>>,
+    /// `format` applies to `batch` messages; `row` messages are always JSON objects.
+    #[serde(flatten)]
+    pub body: FormatOptions,
+    #[serde(default)]
+    pub acks: KafkaAcks,
+    #[serde(default)]
+    pub compression: KafkaCompression,
+    /// Connect to the brokers over TLS; an empty mapping trusts the system CA certificates.
+    pub tls: Option<ClientTlsConfig>,
+    pub sasl: Option<KafkaSaslConfig>,
+    /// Defaults to `yetii-<query name>`.
+    pub client_id: Option<String>,
+    pub batch_size: Option<u32>,
+    /// Timeout for connecting, for each broker request, and for a message to be acknowledged
+    /// (default 30).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+    #[serde(flatten)]
+    pub retry: RetrySettings,
+    #[serde(default)]
+    pub on_batch_failure: BatchFailurePolicy,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaMessageMode {
+    /// One JSON message per row.
+    #[default]
+    Row,
+    /// One message per batch, encoded with `format`.
+    Batch,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaAcks {
+    /// Wait until every in-sync replica has the messages.
+    #[default]
+    All,
+    /// Wait for the partition leader only.
+    Leader,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaCompression {
+    #[default]
+    None,
+    Gzip,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct KafkaSaslConfig {
+    pub mechanism: KafkaSaslMechanism,
+    pub username: String,
+    pub password: Secret<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaSaslMechanism {
+    /// `PLAIN`; only use it together with `tls`.
+    Plain,
+    /// `SCRAM-SHA-256`.
+    ScramSha256,
+    /// `SCRAM-SHA-512`.
+    ScramSha512,
+}
+
+impl KafkaSinkConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::query(query_name).join("destination").join(name);
+        let invalid = |name: &str, value: &str| ConfigError::InvalidValue {
+            field: field(name),
+            value: value.to_string(),
+        };
+
+        if self.brokers.is_empty() {
+            return Err(ConfigError::MissingRequiredField(field("brokers")));
+        }
+        for broker in &self.brokers {
+            match broker.rsplit_once(':') {
+                Some((host, port))
+                    if !host.trim().is_empty()
+                        && port.parse::<u16>().is_ok_and(|port| port > 0) => {}
+                _ => {
+                    return Err(invalid(
+                        "brokers",
+                        &format!("expected host:port, got '{broker}'"),
+                    ));
+                }
+            }
+        }
+        // Kafka's own rule for topic names.
+        if self.topic.is_empty()
+            || self.topic.len() > 249
+            || matches!(self.topic.as_str(), "." | "..")
+            || !self.topic.chars().all(|character| {
+                character.is_ascii_alphanumeric() || matches!(character, '.' | '_' | '-')
+            })
+        {
+            return Err(invalid("topic", &self.topic));
+        }
+        if let Some(key_field) = &self.key_field {
+            if key_field.trim().is_empty() {
+                return Err(ConfigError::MissingRequiredField(field("key_field")));
+            }
+            if self.mode == KafkaMessageMode::Batch {
+                return Err(invalid("key_field", "only supported with mode: row"));
+            }
+        }
+        if self.mode == KafkaMessageMode::Row && self.body.format != PayloadFormat::Json {
+            return Err(invalid(
+                "format",
+                "row messages are always JSON; use mode: batch for other formats",
+            ));
+        }
+        self.body
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        if let Some(tls) = &self.tls
+            && tls.client_cert_file.is_some() != tls.client_key_file.is_some()
+        {
+            return Err(ConfigError::MissingRequiredField(field(
+                if tls.client_cert_file.is_some() {
+                    "tls.client_key_file"
+                } else {
+                    "tls.client_cert_file"
+                },
+            )));
+        }
+        if let Some(sasl) = &self.sasl
+            && sasl.username.is_empty()
+        {
+            return Err(ConfigError::MissingRequiredField(field("sasl.username")));
+        }
+        if self
+            .client_id
+            .as_ref()
+            .is_some_and(|client_id| client_id.trim().is_empty())
+        {
+            return Err(ConfigError::MissingRequiredField(field("client_id")));
+        }
+        if self.batch_size == Some(0) {
+            return Err(invalid("batch_size", "0"));
+        }
+        if self.timeout_seconds == Some(0) {
+            return Err(invalid("timeout_seconds", "0"));
+        }
+        self.retry
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        Ok(())
+    }
+}
+
+/// Publishes each row as a JSON message to an AMQP 0-9-1 broker such as RabbitMQ.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct AmqpSinkConfig {
+    /// `amqp://host[:port][/vhost]` or, for TLS, `amqps://host[:port][/vhost]`; the virtual host
+    /// defaults to `/`.
+    pub url: Secret<String>,
+    /// Exchange to publish to; the default exchange (routing straight to the queue named by the
+    /// routing key) when omitted.
+    pub exchange: Option<String>,
+    /// Routing key template; `{{field}}` placeholders are replaced with row values,
+    /// e.g. `orders.{{region}}.created`.
+    pub routing_key: String,
+    /// Defaults to `guest`.
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    /// TLS options for `amqps://` URLs; a `ca_file` is trusted alongside the web PKI roots.
+    pub tls: Option<ClientTlsConfig>,
+    /// Fail the batch when the broker cannot route a message to any queue.
+    #[serde(default)]
+    pub mandatory: bool,
+    /// Ask the broker to write messages to disk (delivery mode 2, default true).
+    pub persistent: Option<bool>,
+    /// Static headers added to every message.
+    pub headers: Option<HashMap<String, String>>,
+    pub batch_size: Option<u32>,
+    /// Time allowed for a batch to be confirmed by the broker (default 30).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+    #[serde(flatten)]
+    pub retry: RetrySettings,
+    #[serde(default)]
+    pub on_batch_failure: BatchFailurePolicy,
+}
+
+impl AmqpSinkConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::query(query_name).join("destination").join(name);
+        let invalid = |name: &str, value: &str| ConfigError::InvalidValue {
+            field: field(name),
+            value: value.to_string(),
+        };
+
+        let scheme = match url::Url::parse(self.url.expose()) {
+            Ok(url)
+                if matches!(url.scheme(), "amqp" | "amqps")
+                    && url.host_str().is_some()
+                    && url.username().is_empty()
+                    && url.password().is_none()
+                    && !url.path().trim_start_matches('/').contains('/') =>
+            {
+                url.scheme().to_string()
+            }
+            _ => {
+                return Err(invalid(
+                    "url",
+                    "expected amqp://host[:port][/vhost] or amqps://host[:port][/vhost]; set username and password separately",
+                ));
+            }
+        };
+        validate_key_template(&self.routing_key, &field("routing_key"))?;
+        if self.routing_key.len() > 255 {
+            return Err(invalid("routing_key", "longer than 255 bytes"));
+        }
+        if self
+            .exchange
+            .as_ref()
+            .is_some_and(|exchange| exchange.len() > 127)
+        {
+            return Err(invalid("exchange", "longer than 127 bytes"));
+        }
+        if self.password.is_some() && self.username.is_none() {
+            return Err(ConfigError::MissingRequiredField(
+                field("username").note("required with password"),
+            ));
+        }
+        if let Some(tls) = &self.tls {
+            if scheme != "amqps" {
+                return Err(invalid("tls", "only supported with amqps:// URLs"));
+            }
+            if tls.client_cert_file.is_some() != tls.client_key_file.is_some() {
+                return Err(ConfigError::MissingRequiredField(field(
+                    if tls.client_cert_file.is_some() {
+                        "tls.client_key_file"
+                    } else {
+                        "tls.client_cert_file"
+                    },
+                )));
+            }
+        }
+        if self.batch_size == Some(0) {
+            return Err(invalid("batch_size", "0"));
+        }
+        if self.timeout_seconds == Some(0) {
+            return Err(invalid("timeout_seconds", "0"));
+        }
+        self.retry
+            .validate(&FieldPath::query(query_name).join("destination"))?;
+        Ok(())
+    }
+}
+
+/// Inserts or upserts rows into a table of another configured database.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct DatabaseSinkConfig {
+    /// Name of an entry in `databases`.
+    pub database: String,
+    /// Target table, optionally schema-qualified, e.g. `analytics.orders`.
+    pub table: String,
+    #[serde(default)]
+    pub mode: DatabaseWriteMode,
+    /// Columns to write, each bound from the row field of the same name (NULL when the field is
+    /// missing); defaults to the fields of the first row of each batch.
+    pub columns: Option<Vec<String>>,
+    /// Primary or unique key columns matched by `mode: upsert`.
+    pub key_columns: Option<Vec<String>>,
+    /// Rows per transaction.
+    pub batch_size: Option<u32>,
+    #[serde(default)]
+    pub on_batch_failure: BatchFailurePolicy,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseWriteMode {
+    /// Plain `INSERT`; rows that violate a key fail the batch.
+    #[default]
+    Insert,
+    /// Insert new rows and update the non-key columns of rows whose `key_columns` already exist.
+    Upsert,
+}
+
+impl DatabaseSinkConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::query(query_name).join("destination").join(name);
+        let invalid = |name: &str, value: &str| ConfigError::InvalidValue {
+            field: field(name),
+            value: value.to_string(),
+        };
+
+        if self.database.trim().is_empty() {
+            return Err(ConfigError::MissingRequiredField(field("database")));
+        }
+        if self.table.split('.').any(|part| !is_sql_identifier(part)) {
+            return Err(invalid("table", &self.table));
+        }
+        for (name, columns) in [
+            ("columns", &self.columns),
+            ("key_columns", &self.key_columns),
+        ] {
+            let Some(columns) = columns else {
+                continue;
+            };
+            if columns.is_empty() {
+                return Err(ConfigError::MissingRequiredField(field(name)));
+            }
+            if let Some(column) = columns.iter().find(|column| !is_sql_identifier(column)) {
+                return Err(invalid(name, column));
+            }
+        }
+        match (self.mode, &self.key_columns, &self.columns) {
+            (DatabaseWriteMode::Upsert, None, _) => {
+                return Err(ConfigError::MissingRequiredField(
+                    field("key_columns").note("required with mode: upsert"),
+                ));
+            }
+            (DatabaseWriteMode::Insert, Some(_), _) => {
+                return Err(invalid("key_columns", "only used with mode: upsert"));
+            }
+            (DatabaseWriteMode::Upsert, Some(keys), Some(columns)) => {
+                if let Some(key) = keys.iter().find(|key| !columns.contains(key)) {
+                    return Err(invalid(
+                        "key_columns",
+                        &format!("'{key}' is not in columns"),
+                    ));
+                }
+            }
+            _ => {}
+        }
+        if self.batch_size == Some(0) {
+            return Err(invalid("batch_size", "0"));
+        }
+        Ok(())
+    }
+}
+
+/// Plain `[A-Za-z_][A-Za-z0-9_]*` names, written into SQL unquoted and bound as `:name`.
+pub(crate) fn is_sql_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|first| first == '_' || first.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|character| character == '_' || character.is_ascii_alphanumeric())
+}
+
+/// Checks a `file_name` template of the file-writing destinations.
+fn validate_file_name(template: &str, field: &FieldPath) -> Result<(), ConfigError> {
+    validate_key_template(template, field)?;
+    validate_placeholders(template, field)?;
+    if template.contains(['/', '\\']) {
+        return Err(ConfigError::InvalidValue {
+            field: field.clone(),
+            value: "must not contain a path separator".to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn validate_placeholders(template: &str, field: &FieldPath) -> Result<(), ConfigError> {
+    match crate::sinks::redis::template_fields(template)
+        .into_iter()
+        .flatten()
+        .find(|name| !["query", "date", "timestamp", "sequence"].contains(name))
+    {
+        Some(placeholder) => Err(ConfigError::InvalidValue {
+            field: field.clone(),
+            value: format!("unknown placeholder '{{{{{placeholder}}}}}'"),
+        }),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn validate_redis_url(value: &str, field: &FieldPath) -> Result<(), ConfigError> {
+    match url::Url::parse(value) {
+        Ok(url) if matches!(url.scheme(), "redis" | "rediss") && url.host_str().is_some() => Ok(()),
+        // Never echo the URL: it usually carries the password.
+        _ => Err(ConfigError::InvalidValue {
+            field: field.clone(),
+            value: "expected a redis:// or rediss:// URL".to_string(),
+        }),
+    }
+}
+
+pub(crate) fn validate_key_template(template: &str, field: &FieldPath) -> Result<(), ConfigError> {
+    if template.trim().is_empty() {
+        return Err(ConfigError::MissingRequiredField(field.clone()));
+    }
+    if crate::sinks::redis::template_fields(template).is_none() {
+        return Err(ConfigError::InvalidValue {
+            field: field.clone(),
+            value: format!("unterminated placeholder in '{template}'"),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,425 @@
+use crate::config::yetii::YetiiConfig;
+use serde_json::{Map, Value};
+use std::collections::{HashSet, VecDeque};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocsFormat {
+    Markdown,
+    Html,
+}
+
+struct Section {
+    name: String,
+    description: Option<String>,
+    variants: Vec<Variant>,
+}
+
+struct Variant {
+    tag: Option<String>,
+    description: Option<String>,
+    fields: Vec<FieldDoc>,
+}
+
+struct FieldDoc {
+    name: String,
+    type_name: String,
+    required: bool,
+    default: Option<String>,
+    allowed: Vec<String>,
+    description: Option<String>,
+}
+
+/// Renders the configuration reference straight from the `YetiiConfig` schema.
+pub fn render(format: DocsFormat) -> String {
+    let schema = serde_json::to_value(schemars::schema_for!(YetiiConfig))
+        .expect("configuration schema is always serializable");
+    let sections = collect_sections(&schema);
+    match format {
+        DocsFormat::Markdown => render_markdown(&sections),
+        DocsFormat::Html => render_html(&sections),
+    }
+}
+
+fn collect_sections(root: &Value) -> Vec<Section> {
+    let definitions = root
+        .get("$defs")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let mut sections = Vec::new();
+    let mut pending = VecDeque::from([("YetiiConfig".to_string(), root.clone())]);
+    let mut seen = HashSet::from(["YetiiConfig".to_string()]);
+
+    while let Some((name, schema)) = pending.pop_front() {
+        let mut references = Vec::new();
+        let variants = match schema.get("oneOf").and_then(Value::as_array) {
+            Some(variants) => variants
+                .iter()
+                .map(|variant| describe_object(variant, &definitions, &mut references))
+                .collect(),
+            None => vec![describe_object(&schema, &definitions, &mut references)],
+        };
+        if variants.iter().any(|variant| !variant.fields.is_empty()) {
+            sections.push(Section {
+                name,
+                description: description(&schema),
+                variants,
+            });
+        }
+        for reference in references {
+            if seen.insert(reference.clone())
+                && let Some(definition) = definitions.get(&reference)
+            {
+                pending.push_back((reference, definition.clone()));
+            }
+        }
+    }
+
+    sections
+}
+
+fn describe_object(
+    schema: &Value,
+    definitions: &Map<String, Value>,
+    references: &mut Vec<String>,
+) -> Variant {
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<HashSet<_>>()
+        })
+        .unwrap_or_default();
+    let mut tag = None;
+    let mut fields = Vec::new();
+
+    for (name, property) in schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+    {
+        if let Some(constant) = property.get("const").and_then(Value::as_str) {
+            tag = Some(format!("{name}: {constant}"));
+            continue;
+        }
+        let (type_name, allowed) = describe_type(property, definitions, references);
+        fields.push(FieldDoc {
+            name: name.clone(),
+            type_name,
+            required: required.contains(name.as_str()),
+            default: property.get("default").and_then(render_default),
+            allowed,
+            description: description(property),
+        });
+    }
+
+    Variant {
+        tag,
+        description: description(schema),
+        fields,
+    }
+}
+
+fn describe_type(
+    schema: &Value,
+    definitions: &Map<String, Value>,
+    references: &mut Vec<String>,
+) -> (String, Vec<String>) {
+    if let Some(reference) = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix("#/$defs/"))
+    {
+        let definition = definitions.get(reference).unwrap_or(&Value::Null);
+        let allowed = enum_values(definition);
+        if !allowed.is_empty() {
+            return ("string".to_string(), allowed);
+        }
+        if definition.get("anyOf").is_some() {
+            return describe_type(definition, definitions, references);
+        }
+        references.push(reference.to_string());
+        return (reference.to_string(), Vec::new());
+    }
+
+    if let Some(options) = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array)
+    {
+        let mut allowed = Vec::new();
+        let names = options
+            .iter()
+            .filter(|option| option.get("type").and_then(Value::as_str) != Some("null"))
+            .map(|option| {
+                let (name, values) = describe_type(option, definitions, references);
+                allowed.extend(values);
+                name
+            })
+            .collect::<Vec<_>>();
+        return (names.join(" or "), allowed);
+    }
+
+    let mut allowed = enum_values(schema);
+    let kinds = match schema.get("type") {
+        Some(Value::String(kind)) => vec![kind.as_str()],
+        Some(Value::Array(kinds)) => kinds
+            .iter()
+            .filter_map(Value::as_str)
+            .filter(|kind| *kind != "null")
+            .collect(),
+        _ => return ("any".to_string(), allowed),
+    };
+    let type_name = kinds
+        .into_iter()
+        .map(|kind| {
+            let (name, values) = describe_kind(kind, schema, definitions, references);
+            allowed.extend(values);
+            name
+        })
+        .collect::<Vec<_>>()
+        .join(" or ");
+    (type_name, allowed)
+}
+
+fn describe_kind(
+    kind: &str,
+    schema: &Value,
+    definitions: &Map<String, Value>,
+    references: &mut Vec<String>,
+) -> (String, Vec<String>) {
+    match kind {
+        "array" => {
+            let (items, allowed) = describe_type(
+                schema.get("items").unwrap_or(&Value::Bool(true)),
+                definitions,
+                references,
+            );
+            (format!("list of {items}"), allowed)
+        }
+        "object" => match schema.get("additionalProperties") {
+            Some(values) if values.is_object() => {
+                let (values, allowed) = describe_type(values, definitions, references);
+                (format!("map of {values}"), allowed)
+            }
+            _ => ("object".to_string(), Vec::new()),
+        },
+        other => (other.to_string(), Vec::new()),
+    }
+}
+
+fn enum_values(schema: &Value) -> Vec<String> {
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return values.iter().map(render_value).collect();
+    }
+    schema
+        .get("oneOf")
+        .and_then(Value::as_array)
+        .filter(|variants| {
+            variants
+                .iter()
+                .all(|variant| variant.get("const").is_some())
+        })
+        .map(|variants| {
+            variants
+                .iter()
+                .filter_map(|variant| variant.get("const"))
+                .map(render_value)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn description(schema: &Value) -> Option<String> {
+    schema
+        .get("description")
+        .and_then(Value::as_str)
+        .map(|description| description.replace('\n', " "))
+}
+
+/// Nested section defaults are documented on their own section, so only scalars are shown.
+fn render_default(value: &Value) -> Option<String> {
+    match value {
+        Value::Null | Value::Object(_) => None,
+        Value::String(value) if value.is_empty() => Some("\"\"".to_string()),
+        other => Some(render_value(other)),
+    }
+}
+
+fn render_value(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn anchor(name: &str) -> String {
+    name.to_ascii_lowercase()
+}
+
+fn render_markdown(sections: &[Section]) -> String {
+    let mut output = String::from(
+        "# Yetii configuration reference\n\nGenerated from the configuration types by `yetii config docs`.\n",
+    );
+    for section in sections {
+        output.push_str(&format!("\n## {}\n", section.name));
+        if let Some(description) = &section.description {
+            output.push_str(&format!("\n{description}\n"));
+        }
+        for variant in &section.variants {
+            if let Some(tag) = &variant.tag {
+                output.push_str(&format!("\n### `{tag}`\n"));
+                if let Some(description) = &variant.description {
+                    output.push_str(&format!("\n{description}\n"));
+                }
+            }
+            if variant.fields.is_empty() {
+                continue;
+            }
+            output.push_str(
+                "\n| Field | Type | Required | Default | Allowed values | Description |\n",
+            );
+            output.push_str("| --- | --- | --- | --- | --- | --- |\n");
+            for field in &variant.fields {
+                output.push_str(&format!(
+                    "| `{}` | {} | {} | {} | {} | {} |\n",
+                    field.name,
+                    markdown_type(&field.type_name, sections),
+                    if field.required { "yes" } else { "no" },
+                    field
+                        .default
+                        .as_ref()
+                        .map(|default| format!("`{default}`"))
+                        .unwrap_or_default(),
+                    field
+                        .allowed
+                        .iter()
+                        .map(|value| format!("`{value}`"))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    field
+                        .description
+                        .as_deref()
+                        .unwrap_or_default()
+                        .replace('|', "\\|"),
+                ));
+            }
+        }
+    }
+    output
+}
+
+fn markdown_type(type_name: &str, sections: &[Section]) -> String {
+    sections
+        .iter()
+        .fold(type_name.to_string(), |rendered, section| {
+            replace_word(&rendered, &section.name, |name| {
+                format!("[{name}](#{})", anchor(name))
+            })
+        })
+}
+
+fn render_html(sections: &[Section]) -> String {
+    let mut output = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Yetii configuration reference</title></head>\n<body>\n<h1>Yetii configuration reference</h1>\n",
+    );
+    for section in sections {
+        output.push_str(&format!(
+            "<h2 id=\"{}\">{}</h2>\n",
+            anchor(&section.name),
+            escape_html(&section.name)
+        ));
+        if let Some(description) = &section.description {
+            output.push_str(&format!("<p>{}</p>\n", escape_html(description)));
+        }
+        for variant in &section.variants {
+            if let Some(tag) = &variant.tag {
+                output.push_str(&format!("<h3><code>{}</code></h3>\n", escape_html(tag)));
+            }
+            if variant.fields.is_empty() {
+                continue;
+            }
+            output.push_str("<table>\n<tr><th>Field</th><th>Type</th><th>Required</th><th>Default</th><th>Allowed values</th><th>Description</th></tr>\n");
+            for field in &variant.fields {
+                output.push_str(&format!(
+                    "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(&field.name),
+                    html_type(&field.type_name, sections),
+                    if field.required { "yes" } else { "no" },
+                    escape_html(field.default.as_deref().unwrap_or_default()),
+                    escape_html(&field.allowed.join(", ")),
+                    escape_html(field.description.as_deref().unwrap_or_default()),
+                ));
+            }
+            output.push_str("</table>\n");
+        }
+    }
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+fn html_type(type_name: &str, sections: &[Section]) -> String {
+    sections
+        .iter()
+        .fold(escape_html(type_name), |rendered, section| {
+            replace_word(&rendered, &section.name, |name| {
+                format!("<a href=\"#{}\">{name}</a>", anchor(name))
+            })
+        })
+}
+
+/// Replaces whole-word occurrences so `Logging` does not match inside `LoggingRotation`.
+fn replace_word(text: &str, word: &str, render: impl Fn(&str) -> String) -> String {
+    text.split(' ')
+        .map(|part| {
+            if part == word {
+                render(part)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_reference_covers_fields_defaults_and_allowed_values() {
+        let docs = render(DocsFormat::Markdown);
+
+        assert!(docs.starts_with("# Yetii configuration reference"));
+        assert!(docs.contains("\n## YetiiConfig\n"));
+        assert!(docs.contains("| `queries` | list of [QueryConfig](#queryconfig) | yes |"));
+        assert!(docs.contains("\n## DatabaseConfig\n"));
+        assert!(docs.contains("`postgres`, `mysql`, `mssql`, `oracle`"));
+        assert!(docs.contains("\n### `type: oauth2`\n"));
+        assert!(docs.contains("| `format` | string | no | `json` |"));
+        assert!(
+            docs.contains("| `events` | list of string | no | `[]` | `run_success`, `run_failure`")
+        );
+    }
+
+    #[test]
+    fn html_reference_escapes_and_links_types() {
+        let docs = render(DocsFormat::Html);
+
+        assert!(docs.starts_with("<!DOCTYPE html>"));
+        assert!(docs.contains("<h2 id=\"queryconfig\">QueryConfig</h2>"));
+        assert!(docs.contains("list of <a href=\"#queryconfig\">QueryConfig</a>"));
+    }
+}
@@ -0,0 +1,65 @@
+//! `.env` files that fill `${VAR}` references the environment leaves unset: the file given
+//! with `--env-file`, or else the `.env` beside the configuration file or inside the
+//! configuration directory.
+
+use crate::config::{ConfigError, FieldPath};
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+static ENV_FILE: OnceCell<PathBuf> = OnceCell::new();
+
+/// Uses `path` instead of the `.env` found next to the configuration, for every later load.
+pub fn set_env_file(path: impl Into<PathBuf>) -> Result<(), ConfigError> {
+    ENV_FILE
+        .set(path.into())
+        .map_err(|_| ConfigError::ConfigAlreadySet)
+}
+
+/// The variables for the configuration at `config_path`. An explicit `--env-file` must exist;
+/// the default `.env` is optional.
+pub fn variables(config_path: &Path) -> Result<HashMap<String, String>, ConfigError> {
+    if let Some(path) = ENV_FILE.get() {
+        return read(path);
+    }
+    let default = if config_path.is_dir() {
+        config_path.join(".env")
+    } else {
+        config_path.parent().unwrap_or(Path::new("")).join(".env")
+    };
+    if default.is_file() {
+        read(&default)
+    } else {
+        Ok(HashMap::new())
+    }
+}
+
+/// Reads `KEY=value` lines. Blank lines, `#` comments, `export ` prefixes, and matching quotes
+/// around values are accepted.
+pub fn read(path: &Path) -> Result<HashMap<String, String>, ConfigError> {
+    let (content, _) = super::read_file(path).map_err(|error| ConfigError::InvalidValue {
+        field: FieldPath::default().key(path.display().to_string()),
+        value: error.to_string(),
+    })?;
+    let mut variables = HashMap::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((name, value)) = line.split_once('=') else {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::default().key(format!("{} line {}", path.display(), index + 1)),
+                value: line.to_string(),
+            });
+        };
+        let value = value.trim();
+        let value = [('"', '"'), ('\'', '\'')]
+            .iter()
+            .find_map(|(open, close)| value.strip_prefix(*open)?.strip_suffix(*close))
+            .unwrap_or(value);
+        variables.insert(name.trim().to_string(), value.to_string());
+    }
+    Ok(variables)
+}
@@ -0,0 +1,303 @@
+//! AES-256-GCM sealing shared by the [`keystore`](super::keystore) and encrypted
+//! configuration files (`yetii encrypt`). Keys are stretched from a passphrase with PBKDF2, or
+//! are random and kept in a key file that only its owner can read.
+//!
+//! An encrypted configuration file is a JSON envelope that names where its key comes from:
+//!
+//! ```json
+//! {"yetii_encrypted_config": 1, "key_source": "key_file", "salt": "…", "iterations": 600000,
+//!  "nonce": "…", "ciphertext": "…"}
+//! ```
+
+use crate::config::ConfigError;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::pbkdf2;
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+pub const KEY_LEN: usize = 32;
+pub const PBKDF2_ITERATIONS: u32 = 600_000;
+pub const CONFIG_PASSPHRASE_VARIABLE: &str = "YETII_CONFIG_PASSPHRASE";
+/// Environment variable that moves the configuration key file away from
+/// [`DEFAULT_CONFIG_KEY_FILE`].
+pub const CONFIG_KEY_FILE_VARIABLE: &str = "YETII_CONFIG_KEY_FILE";
+/// Key file relative to the directory of the configuration file it seals.
+pub const DEFAULT_CONFIG_KEY_FILE: &str = ".yetii/config.key";
+const ENVELOPE_VERSION: u32 = 1;
+/// Authenticated with every configuration, so a keystore value cannot pass for one.
+const CONFIG_AAD: &[u8] = b"yetii-config";
+
+/// Where the key that seals data comes from; fixed when the data is first sealed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    Passphrase,
+    KeyFile,
+}
+
+pub struct SealingKey(LessSafeKey);
+
+impl SealingKey {
+    pub fn from_passphrase(passphrase: &str, salt: &[u8], iterations: NonZeroU32) -> Self {
+        let mut key = [0; KEY_LEN];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        Self::from_bytes(&key)
+    }
+
+    /// Reads the base64 key in `path`; with `create`, a missing file gets a new random key.
+    pub fn from_key_file(path: &Path, create: bool) -> Result<Self, KeyFileError> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => {
+                let key: [u8; KEY_LEN] = STANDARD
+                    .decode(content.trim())
+                    .ok()
+                    .and_then(|key| key.try_into().ok())
+                    .ok_or_else(|| KeyFileError::Invalid(path.to_path_buf()))?;
+                Ok(Self::from_bytes(&key))
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound && create => {
+                let key = rand::random::<[u8; KEY_LEN]>();
+                write_private(path, format!("{}\n", STANDARD.encode(key)).as_bytes()).map_err(
+                    |source| KeyFileError::Io {
+                        path: path.to_path_buf(),
+                        source,
+                    },
+                )?;
+                Ok(Self::from_bytes(&key))
+            }
+            Err(source) => Err(KeyFileError::Io {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    fn from_bytes(key: &[u8; KEY_LEN]) -> Self {
+        Self(LessSafeKey::new(
+            UnboundKey::new(&AES_256_GCM, key).expect("AES-256-GCM keys are 32 bytes"),
+        ))
+    }
+
+    /// Encrypts `plaintext` under a fresh nonce; returns the nonce and ciphertext, base64.
+    pub fn seal(&self, aad: &[u8], plaintext: &[u8]) -> (String, String) {
+        let nonce = rand::random::<[u8; NONCE_LEN]>();
+        let mut data = plaintext.to_vec();
+        self.0
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad),
+                &mut data,
+            )
+            .expect("sealing fails only for inputs larger than AES-GCM allows");
+        (STANDARD.encode(nonce), STANDARD.encode(data))
+    }
+
+    /// `None` when the data was sealed under another key or `aad`, or was altered.
+    pub fn open(&self, aad: &[u8], nonce: &str, ciphertext: &str) -> Option<Vec<u8>> {
+        let nonce = STANDARD.decode(nonce).ok()?;
+        let mut data = STANDARD.decode(ciphertext).ok()?;
+        let nonce = Nonce::try_assume_unique_for_key(&nonce).ok()?;
+        let plaintext = self
+            .0
+            .open_in_place(nonce, Aad::from(aad), &mut data)
+            .ok()?;
+        Some(plaintext.to_vec())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeyFileError {
+    #[error("key file '{}' could not be accessed: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("key file '{}' does not hold a base64 {KEY_LEN}-byte key", .0.display())]
+    Invalid(PathBuf),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Envelope {
+    yetii_encrypted_config: u32,
+    key_source: KeySource,
+    /// PBKDF2 salt for passphrase keys, base64.
+    salt: String,
+    iterations: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EncryptionError {
+    #[error(transparent)]
+    KeyFile(#[from] KeyFileError),
+    #[error("configuration is encrypted with a passphrase; set {CONFIG_PASSPHRASE_VARIABLE}")]
+    MissingPassphrase,
+    #[error("encrypted configuration is not valid: {0}")]
+    Format(String),
+    #[error(
+        "configuration could not be decrypted; {CONFIG_PASSPHRASE_VARIABLE} or the key file does not match"
+    )]
+    Decrypt,
+}
+
+/// Whether `content` is a configuration written by [`encrypt_config`].
+pub fn is_encrypted(content: &str) -> bool {
+    content.trim_start().starts_with('{') && content.contains("\"yetii_encrypted_config\"")
+}
+
+/// The key file for the configuration file at `path`: `$YETII_CONFIG_KEY_FILE`, or
+/// [`DEFAULT_CONFIG_KEY_FILE`] in the file's directory, so the key is found from any working
+/// directory.
+pub fn config_key_file(path: &Path) -> PathBuf {
+    std::env::var_os(CONFIG_KEY_FILE_VARIABLE)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            path.parent()
+                .unwrap_or(Path::new(""))
+                .join(DEFAULT_CONFIG_KEY_FILE)
+        })
+}
+
+fn config_passphrase() -> Option<String> {
+    std::env::var(CONFIG_PASSPHRASE_VARIABLE)
+        .ok()
+        .filter(|passphrase| !passphrase.is_empty())
+}
+
+/// Seals configuration text to be written to `path` under `$YETII_CONFIG_PASSPHRASE` when it
+/// is set, and otherwise under the key file for `path`, which is created on first use.
+pub fn encrypt_config(plaintext: &str, path: &Path) -> Result<String, EncryptionError> {
+    let salt = rand::random::<[u8; 16]>();
+    let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).expect("iterations are positive");
+    let (key_source, key) = match config_passphrase() {
+        Some(passphrase) => (
+            KeySource::Passphrase,
+            SealingKey::from_passphrase(&passphrase, &salt, iterations),
+        ),
+        None => (
+            KeySource::KeyFile,
+            SealingKey::from_key_file(&config_key_file(path), true)?,
+        ),
+    };
+    let (nonce, ciphertext) = key.seal(CONFIG_AAD, plaintext.as_bytes());
+    let envelope = Envelope {
+        yetii_encrypted_config: ENVELOPE_VERSION,
+        key_source,
+        salt: STANDARD.encode(salt),
+        iterations: iterations.get(),
+        nonce,
+        ciphertext,
+    };
+    let mut content = serde_json::to_string_pretty(&envelope)
+        .map_err(|error| EncryptionError::Format(error.to_string()))?;
+    content.push('\n');
+    Ok(content)
+}
+
+/// Opens the `content` of the encrypted configuration file at `path`.
+pub fn decrypt_config(content: &str, path: &Path) -> Result<String, EncryptionError> {
+    let envelope: Envelope = serde_json::from_str(content)
+        .map_err(|error| EncryptionError::Format(error.to_string()))?;
+    if envelope.yetii_encrypted_config != ENVELOPE_VERSION {
+        return Err(EncryptionError::Format(format!(
+            "unsupported version {}",
+            envelope.yetii_encrypted_config
+        )));
+    }
+    let key = match envelope.key_source {
+        KeySource::Passphrase => {
+            let passphrase = config_passphrase().ok_or(EncryptionError::MissingPassphrase)?;
+            let salt = STANDARD
+                .decode(&envelope.salt)
+                .map_err(|error| EncryptionError::Format(format!("salt: {error}")))?;
+            let iterations = NonZeroU32::new(envelope.iterations).ok_or_else(|| {
+                EncryptionError::Format("iterations must be positive".to_string())
+            })?;
+            SealingKey::from_passphrase(&passphrase, &salt, iterations)
+        }
+        KeySource::KeyFile => SealingKey::from_key_file(&config_key_file(path), false)?,
+    };
+    let plaintext = key
+        .open(CONFIG_AAD, &envelope.nonce, &envelope.ciphertext)
+        .ok_or(EncryptionError::Decrypt)?;
+    String::from_utf8(plaintext).map_err(|_| EncryptionError::Decrypt)
+}
+
+/// Configuration text as read from the file at `path`, decrypted when it is an encrypted
+/// envelope. The flag says whether it was.
+pub(crate) fn decrypt_if_encrypted(
+    content: String,
+    path: &Path,
+) -> Result<(String, bool), ConfigError> {
+    if is_encrypted(&content) {
+        Ok((decrypt_config(&content, path)?, true))
+    } else {
+        Ok((content, false))
+    }
+}
+
+/// Writes through a temporary file readable only by the current user, then renames it.
+pub fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    std::io::Write::write_all(&mut options.open(&temporary)?, content)?;
+    std::fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_data_opens_only_with_the_same_key_and_context() {
+        let salt = [7; 16];
+        let iterations = NonZeroU32::new(1000).unwrap();
+        let key = SealingKey::from_passphrase("correct horse", &salt, iterations);
+        let (nonce, ciphertext) = key.seal(CONFIG_AAD, b"databases: []");
+        assert_eq!(
+            Some(b"databases: []".to_vec()),
+            key.open(CONFIG_AAD, &nonce, &ciphertext)
+        );
+        assert_eq!(None, key.open(b"DB_PASSWORD", &nonce, &ciphertext));
+        let other = SealingKey::from_passphrase("battery staple", &salt, iterations);
+        assert_eq!(None, other.open(CONFIG_AAD, &nonce, &ciphertext));
+
+        assert!(!is_encrypted("global_settings:\n  environment: dev\n"));
+        assert!(is_encrypted(
+            "{\n  \"yetii_encrypted_config\": 1,\n  \"key_source\": \"key_file\"\n}"
+        ));
+    }
+
+    #[test]
+    fn key_file_is_found_beside_the_configuration() {
+        let dir = std::env::temp_dir().join(format!("yetii-config-key-{}", std::process::id()));
+        let path = dir.join("yetii.yaml");
+        assert_eq!(dir.join(".yetii/config.key"), config_key_file(&path));
+
+        let sealed = encrypt_config("queries: []\n", &path).unwrap();
+        assert!(dir.join(DEFAULT_CONFIG_KEY_FILE).exists());
+        assert_eq!("queries: []\n", decrypt_config(&sealed, &path).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
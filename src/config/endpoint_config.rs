@@ -1,12 +1,23 @@
-use crate::config::ConfigError;
 pub use crate::config::request_config::RequestConfig;
-use serde::{Deserialize, Serialize};
+use crate::config::secret::Secret;
+use crate::config::{ConfigError, FieldPath};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct EndpointConfig {
     pub url: String,
-    pub method: String,
+    /// URLs tried in order once the current one's retries are used up, with the same method,
+    /// auth, headers, and request settings; one URL or a list.
+    #[serde(
+        default,
+        deserialize_with = "one_or_more_urls",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    #[schemars(with = "Option<FailoverUrls>")]
+    pub failover_url: Vec<String>,
+    pub method: HttpMethod,
     pub auth: Option<EndpointAuth>,
     pub headers: Option<HashMap<String, String>>,
     #[serde(default)]
@@ -15,29 +26,34 @@ pub struct EndpointConfig {
 }
 
 impl EndpointConfig {
-    pub fn validate(&self) -> Result<(), ConfigError> {
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
         if self.url.is_empty() {
-            return Err(ConfigError::MissingRequiredField(
-                "endpoint.url".to_string(),
-            ));
+            return Err(ConfigError::MissingRequiredField(field.join("url")));
         }
 
-        let method = self.method.to_ascii_uppercase();
-        let valid_methods = ["GET", "POST", "PUT", "PATCH", "DELETE"];
-        if !valid_methods.contains(&method.as_str()) {
-            return Err(ConfigError::InvalidHttpMethod(self.method.clone()));
+        for failover_url in &self.failover_url {
+            let is_http = url::Url::parse(failover_url)
+                .is_ok_and(|parsed| matches!(parsed.scheme(), "http" | "https"));
+            if !is_http || failover_url == &self.url {
+                return Err(ConfigError::InvalidValue {
+                    field: field.join("failover_url"),
+                    value: failover_url.clone(),
+                });
+            }
         }
 
-        if self.request.format != "json" {
-            return Err(ConfigError::InvalidValue {
-                field: "endpoint.request.format".to_string(),
-                value: self.request.format.clone(),
-            });
+        if let Some(auth) = &self.auth {
+            auth.validate(&field.join("auth"))?;
+        }
+
+        self.request.validate(&field.join("request"))?;
+        if let Some(response) = &self.response {
+            response.validate(&field.join("response"))?;
         }
 
         if self.request.batch_size == Some(0) {
             return Err(ConfigError::InvalidValue {
-                field: "endpoint.request.batch_size".to_string(),
+                field: field.join("request.batch_size"),
                 value: "0".to_string(),
             });
         }
@@ -46,38 +62,269 @@ impl EndpointConfig {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum FailoverUrls {
+    One(String),
+    Many(Vec<String>),
+}
+
+fn one_or_more_urls<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    Ok(match Option::<FailoverUrls>::deserialize(deserializer)? {
+        Some(FailoverUrls::One(url)) => vec![url],
+        Some(FailoverUrls::Many(urls)) => urls,
+        None => Vec::new(),
+    })
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(tag = "type")]
 pub enum EndpointAuth {
     #[serde(rename = "bearer")]
     Bearer {
-        token: String,
+        token: Secret<String>,
         #[serde(default)]
         header_name: Option<String>,
     },
     #[serde(rename = "api_key")]
-    ApiKey { header_name: String, token: String },
+    ApiKey {
+        header_name: String,
+        token: Secret<String>,
+    },
     #[serde(rename = "basic")]
-    Basic { username: String, password: String },
+    Basic {
+        username: String,
+        password: Secret<String>,
+    },
     #[serde(rename = "oauth2")]
     OAuth2 {
         client_id: String,
-        client_secret: String,
+        client_secret: Secret<String>,
         token_url: String,
         #[serde(default)]
         scopes: Option<Vec<String>>,
         #[serde(default)]
         audience: Option<String>,
     },
+    /// Signs each request with AWS Signature Version 4.
+    #[serde(rename = "aws_sigv4")]
+    AwsSigV4 {
+        region: String,
+        service: String,
+        #[serde(default)]
+        access_key_id: Option<String>,
+        #[serde(default)]
+        secret_access_key: Option<Secret<String>>,
+        #[serde(default)]
+        session_token: Option<Secret<String>>,
+        /// AWS profile for the default credentials chain, used when keys are not set inline.
+        #[serde(default)]
+        profile: Option<String>,
+    },
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+impl EndpointAuth {
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        let required = match self {
+            EndpointAuth::Bearer { token, header_name } => vec![
+                ("token", token.expose().as_str()),
+                (
+                    "header_name",
+                    header_name.as_deref().unwrap_or("Authorization"),
+                ),
+            ],
+            EndpointAuth::ApiKey { header_name, token } => vec![
+                ("header_name", header_name.as_str()),
+                ("token", token.expose().as_str()),
+            ],
+            EndpointAuth::Basic { username, .. } => {
+                vec![("username", username.as_str())]
+            }
+            EndpointAuth::OAuth2 {
+                client_id,
+                token_url,
+                ..
+            } => vec![
+                ("client_id", client_id.as_str()),
+                ("token_url", token_url.as_str()),
+            ],
+            EndpointAuth::AwsSigV4 {
+                region,
+                service,
+                access_key_id,
+                secret_access_key,
+                ..
+            } => {
+                if access_key_id.is_some() != secret_access_key.is_some() {
+                    return Err(ConfigError::MissingRequiredField(field.join(
+                        if access_key_id.is_some() {
+                            "secret_access_key"
+                        } else {
+                            "access_key_id"
+                        },
+                    )));
+                }
+                vec![("region", region.as_str()), ("service", service.as_str())]
+            }
+        };
+
+        match required.iter().find(|(_, value)| value.trim().is_empty()) {
+            Some((name, _)) => Err(ConfigError::MissingRequiredField(field.join(name))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct ResponseConfig {
+    /// Statuses that mark a batch as delivered.
     #[serde(default = "default_success_codes")]
     pub success_codes: Vec<u16>,
-    pub handle_duplicates: String,
+    /// What to do when the endpoint reports that the batch already exists.
+    #[serde(default)]
+    pub handle_duplicates: DuplicatePolicy,
+    /// Statuses that indicate a duplicate (default `409`).
+    #[serde(default = "default_duplicate_codes")]
+    pub duplicate_codes: Vec<u16>,
+    /// Method used to resend a duplicate batch with `handle_duplicates: update` (default `PUT`).
+    pub update_method: Option<HttpMethod>,
+}
+
+/// Written in upper case, as in HTTP; lower case is accepted too.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    #[serde(alias = "get")]
+    Get,
+    #[serde(alias = "post")]
+    Post,
+    #[serde(alias = "put")]
+    Put,
+    #[serde(alias = "patch")]
+    Patch,
+    #[serde(alias = "delete")]
+    Delete,
+}
+
+impl HttpMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Patch => "PATCH",
+            HttpMethod::Delete => "DELETE",
+        }
+    }
+}
+
+impl std::fmt::Display for HttpMethod {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// Treat the batch as delivered without sending it again.
+    Skip,
+    /// Resend the batch with `update_method` so the endpoint overwrites the existing records.
+    Update,
+    /// Fail the batch.
+    #[default]
+    Error,
+}
+
+impl ResponseConfig {
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        let invalid_codes = |codes: &[u16]| codes.iter().any(|code| !(100..=599).contains(code));
+        if self.success_codes.is_empty() || invalid_codes(&self.success_codes) {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("success_codes"),
+                value: "must contain HTTP status codes from 100 to 599".to_string(),
+            });
+        }
+        if invalid_codes(&self.duplicate_codes) {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("duplicate_codes"),
+                value: "must contain HTTP status codes from 100 to 599".to_string(),
+            });
+        }
+        if let Some(code) = self
+            .duplicate_codes
+            .iter()
+            .find(|code| self.success_codes.contains(code))
+        {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("duplicate_codes"),
+                value: format!("{code} is also listed in success_codes"),
+            });
+        }
+        if let Some(method) = self.update_method
+            && !matches!(
+                method,
+                HttpMethod::Post | HttpMethod::Put | HttpMethod::Patch
+            )
+        {
+            return Err(ConfigError::InvalidHttpMethod(method.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns whether `status` signals a duplicate that `handle_duplicates` should resolve.
+    pub fn is_duplicate(&self, status: u16) -> bool {
+        self.duplicate_codes.contains(&status) && !self.success_codes.contains(&status)
+    }
 }
 
 fn default_success_codes() -> Vec<u16> {
     vec![200, 201, 202, 204]
 }
+
+fn default_duplicate_codes() -> Vec<u16> {
+    vec![409]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::secret::REDACTED;
+
+    #[test]
+    fn debug_output_redacts_auth_secrets() {
+        let auths = [
+            EndpointAuth::Bearer {
+                token: "bearer-secret".into(),
+                header_name: None,
+            },
+            EndpointAuth::ApiKey {
+                header_name: "X-Api-Key".to_string(),
+                token: "api-secret".into(),
+            },
+            EndpointAuth::Basic {
+                username: "sync".to_string(),
+                password: "basic-secret".into(),
+            },
+        ];
+
+        for auth in auths {
+            let rendered = format!("{auth:?}");
+            assert!(!rendered.contains("secret"), "{rendered}");
+            assert!(rendered.contains(REDACTED));
+        }
+    }
+
+    #[test]
+    fn rejects_empty_auth_credentials_without_echoing_them() {
+        let auth = EndpointAuth::ApiKey {
+            header_name: "X-Api-Key".to_string(),
+            token: " ".into(),
+        };
+
+        assert!(matches!(
+            auth.validate(&FieldPath::new("endpoint.auth")),
+            Err(ConfigError::MissingRequiredField(field)) if field == FieldPath::new("endpoint.auth.token")
+        ));
+    }
+}
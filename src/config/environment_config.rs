@@ -1,11 +1,223 @@
-use crate::config::database::DatabaseConfigs;
-use crate::config::global_settings::GlobalSettings;
-use crate::config::monitor_config::MonitoringConfig;
+use crate::config::utils::default_environment;
+use crate::config::{ConfigError, FieldPath};
+use once_cell::sync::OnceCell;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Environment variable that selects an environment when `--env` is not given.
+pub const ENVIRONMENT_VARIABLE: &str = "YETII_ENV";
+
+static ENVIRONMENT: OnceCell<String> = OnceCell::new();
+
+/// Applies the `environments:` override named `name` to every later load.
+pub fn set_environment(name: impl Into<String>) -> Result<(), ConfigError> {
+    ENVIRONMENT
+        .set(name.into())
+        .map_err(|_| ConfigError::ConfigAlreadySet)
+}
+
+/// The environment chosen with `--env` or `$YETII_ENV`, if any.
+pub fn selected_environment() -> Option<String> {
+    ENVIRONMENT.get().cloned().or_else(|| {
+        std::env::var(ENVIRONMENT_VARIABLE)
+            .ok()
+            .filter(|name| !name.is_empty())
+    })
+}
+
+/// Sections of the top level that an environment may override.
+const OVERRIDDEN_SECTIONS: [&str; 3] = ["global_settings", "databases", "monitoring"];
+
+/// Settings of one environment, deep-merged into the top level by [`apply_override`], so they
+/// only need the values that differ.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct EnvironmentOverride {
-    pub global_settings: Option<GlobalSettings>,
-    pub databases: Option<DatabaseConfigs>,
-    pub monitoring: Option<MonitoringConfig>,
+    /// Merged into `global_settings`, e.g. `{logging: {level: debug}}`.
+    pub global_settings: Option<serde_json::Value>,
+    /// Merged into `databases`; list entries are matched by `name`.
+    pub databases: Option<serde_json::Value>,
+    /// Merged into `monitoring`.
+    pub monitoring: Option<serde_json::Value>,
+    /// Predicates every matching query's rows must satisfy in this environment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row_filters: Option<Vec<RowFilter>>,
+}
+
+/// A SQL condition the engine applies by wrapping a query, so its SQL needs no edits.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct RowFilter {
+    /// Boolean SQL expression over the query's result columns, e.g. `company_id IN (901, 902)`.
+    pub predicate: String,
+    /// Queries the filter applies to; every database-reading query when omitted.
+    pub queries: Option<Vec<String>>,
+}
+
+impl RowFilter {
+    pub fn applies_to(&self, query_name: &str) -> bool {
+        self.queries
+            .as_ref()
+            .is_none_or(|queries| queries.iter().any(|name| name == query_name))
+    }
+}
+
+/// Merges the override of `environment` from the configuration `document` into its top level:
+/// maps key by key, lists of named entries by `name`, and anything else by replacing it.
+/// Returns whether there was an override to apply.
+pub fn apply_override(document: &mut Value, environment: &str) -> bool {
+    let Some(sections) = document
+        .get("environments")
+        .and_then(|environments| environments.get(environment))
+        .and_then(Value::as_mapping)
+        .cloned()
+    else {
+        return false;
+    };
+    let Some(root) = document.as_mapping_mut() else {
+        return false;
+    };
+    for section in OVERRIDDEN_SECTIONS {
+        if let Some(value) = sections.get(section) {
+            match root.get_mut(section) {
+                Some(existing) => merge(existing, value.clone()),
+                None => {
+                    root.insert(Value::from(section), value.clone());
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Applies the override of the `environment` chosen with `--env` or `$YETII_ENV`, which must
+/// be a key of `environments:` or the configured `global_settings.environment`; a mistyped
+/// name would otherwise run without the environment's row filters. Returns whether there was
+/// an override to apply.
+pub fn apply_selected(document: &mut Value, environment: &str) -> Result<bool, ConfigError> {
+    if apply_override(document, environment) {
+        return Ok(true);
+    }
+    let configured = document
+        .get("global_settings")
+        .and_then(|settings| settings.get("environment"))
+        .and_then(Value::as_str)
+        .map_or_else(default_environment, str::to_string);
+    if environment == configured {
+        return Ok(false);
+    }
+    let mut known = document
+        .get("environments")
+        .and_then(Value::as_mapping)
+        .into_iter()
+        .flat_map(|environments| environments.keys())
+        .filter_map(Value::as_str)
+        .collect::<Vec<_>>();
+    known.push(&configured);
+    Err(ConfigError::InvalidValue {
+        field: FieldPath::new("environment"),
+        value: format!(
+            "'{environment}' is not a key of environments or global_settings.environment; expected one of {}",
+            known.join(", ")
+        ),
+    })
+}
+
+fn merge(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Mapping(base), Value::Mapping(value)) => merge_mapping(base, value),
+        (Value::Sequence(base), Value::Sequence(value))
+            if value.iter().all(|entry| entry.get("name").is_some()) =>
+        {
+            for entry in value {
+                match base
+                    .iter_mut()
+                    .find(|existing| existing.get("name") == entry.get("name"))
+                {
+                    Some(existing) => merge(existing, entry),
+                    None => base.push(entry),
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+fn merge_mapping(base: &mut Mapping, value: Mapping) {
+    for (key, value) in value {
+        match base.get_mut(&key) {
+            Some(existing) => merge(existing, value),
+            None => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Wraps `sql` in a derived table filtered by every predicate.
+pub fn scope_sql(sql: &str, predicates: &[&str]) -> String {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    let conditions = predicates
+        .iter()
+        .map(|predicate| format!("({})", predicate.trim()))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    // No `AS` before the alias: Oracle rejects it for tables.
+    format!("SELECT * FROM (\n{sql}\n) yetii_scoped WHERE {conditions}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_merge_field_by_field() {
+        let mut document: Value = serde_yaml::from_str(
+            r#"
+global_settings:
+  logging: {level: info, format: json}
+databases:
+  - {name: erp, type: postgres, host: localhost, port: 5432}
+  - {name: crm, type: mysql, host: localhost, port: 3306}
+environments:
+  production:
+    global_settings:
+      logging: {level: warn}
+    databases:
+      - {name: erp, host: erp-db.internal}
+      - {name: archive, type: sqlite, path: archive.db}
+"#,
+        )
+        .unwrap();
+
+        assert!(!apply_override(&mut document, "staging"));
+        assert!(apply_override(&mut document, "production"));
+        assert_eq!(document["global_settings"]["logging"]["level"], "warn");
+        assert_eq!(document["global_settings"]["logging"]["format"], "json");
+        let databases = document["databases"].as_sequence().unwrap();
+        assert_eq!(3, databases.len());
+        assert_eq!(databases[0]["host"], "erp-db.internal");
+        assert_eq!(5432, databases[0]["port"].as_u64().unwrap());
+        assert_eq!(databases[1]["host"], "localhost");
+        assert_eq!(databases[2]["name"], "archive");
+    }
+
+    #[test]
+    fn an_unknown_selected_environment_is_an_error() {
+        let mut document: Value = serde_yaml::from_str(
+            "global_settings:\n  environment: staging\nenvironments:\n  production:\n    global_settings: {}\n",
+        )
+        .unwrap();
+
+        assert!(apply_selected(&mut document.clone(), "production").unwrap());
+        assert!(!apply_selected(&mut document.clone(), "staging").unwrap());
+        let error = apply_selected(&mut document, "stagign").unwrap_err();
+        assert!(
+            matches!(&error, ConfigError::InvalidValue { field, value }
+                if *field == FieldPath::new("environment") && value.contains("'stagign'") && value.ends_with("production, staging")),
+            "{error}"
+        );
+
+        let mut unconfigured: Value = serde_yaml::from_str("queries: []\n").unwrap();
+        assert!(!apply_selected(&mut unconfigured, "development").unwrap());
+    }
 }
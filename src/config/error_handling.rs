@@ -1,44 +1,47 @@
-use crate::config::ConfigError;
-use crate::config::utils::default_error_action;
 use crate::config::utils::default_max_retries;
+use crate::config::{ConfigError, FieldPath};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 /// Enhanced error handling with validation
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct ErrorHandling {
-    #[serde(default = "default_error_action")]
-    pub on_query_error: String,
-    #[serde(default = "default_error_action")]
-    pub on_transform_error: String,
-    #[serde(default = "default_error_action")]
-    pub on_endpoint_error: String,
+    #[serde(default)]
+    pub on_query_error: ErrorAction,
+    #[serde(default)]
+    pub on_transform_error: ErrorAction,
+    #[serde(default)]
+    pub on_endpoint_error: ErrorAction,
     #[serde(default = "default_max_retries")]
     pub max_retries: u32,
 }
+/// What happens when a step fails.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorAction {
+    #[default]
+    Stop,
+    LogAndContinue,
+    Retry,
+}
 impl Default for ErrorHandling {
     fn default() -> Self {
         Self {
-            on_query_error: default_error_action(),
-            on_transform_error: default_error_action(),
-            on_endpoint_error: default_error_action(),
+            on_query_error: ErrorAction::default(),
+            on_transform_error: ErrorAction::default(),
+            on_endpoint_error: ErrorAction::default(),
             max_retries: default_max_retries(),
         }
     }
 }
 impl ErrorHandling {
     pub fn validate(&self) -> Result<(), ConfigError> {
-        let valid_actions = ["stop", "log_and_continue", "retry"];
-
-        if !valid_actions.contains(&self.on_query_error.as_str()) {
+        if self.max_retries > 10 {
             return Err(ConfigError::InvalidValue {
-                field: "global_settings.error_handling.on_query_error".to_string(),
-                value: self.on_query_error.clone(),
+                field: FieldPath::new("global_settings.error_handling.max_retries"),
+                value: format!("{} is more than 10", self.max_retries),
             });
         }
 
-        if self.max_retries > 10 {
-            return Err(ConfigError::InvalidTimeout(Option::from(self.max_retries)));
-        }
-
         Ok(())
     }
 }
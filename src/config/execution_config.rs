@@ -1,77 +1,255 @@
-use crate::config::ConfigError;
-use crate::config::utils::default_execution_mode;
+use crate::config::destination_config::S3SinkConfig;
+use crate::config::secret::Secret;
+use crate::config::units;
+use crate::config::utils::default_true;
+use crate::config::workspace_config::WorkspaceConfig;
+use crate::config::{ConfigError, FieldPath};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct ExecutionConfig {
-    #[serde(default = "default_execution_mode")]
-    pub mode: String,
+    #[serde(default)]
+    pub mode: ExecutionMode,
+    #[serde(default, deserialize_with = "units::minutes")]
+    #[schemars(with = "Option<units::DurationValue>")]
     pub global_timeout_minutes: Option<u32>,
     pub state_management: Option<StateManagement>,
     pub scheduler: Option<SchedulerConfig>,
+    #[serde(default)]
+    pub workspace: Option<WorkspaceConfig>,
+    /// The run history read by `yetii history`; kept beside the state when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<HistoryConfig>,
 }
 impl Default for ExecutionConfig {
     fn default() -> Self {
         Self {
-            mode: default_execution_mode(),
+            mode: ExecutionMode::default(),
             global_timeout_minutes: Some(60),
             state_management: None,
             scheduler: None,
+            workspace: None,
+            history: None,
         }
     }
 }
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionMode {
+    Parallel,
+    #[default]
+    Sequential,
+}
 impl ExecutionConfig {
     pub fn validate(&self) -> Result<(), ConfigError> {
-        let valid_modes = ["parallel", "sequential"];
-        if !valid_modes.contains(&self.mode.as_str()) {
-            return Err(ConfigError::InvalidExecutionMode(self.mode.clone()));
-        }
         if let Some(state_management) = &self.state_management {
             state_management.validate()?;
         }
         if let Some(scheduler) = &self.scheduler {
             scheduler.validate()?;
         }
+        if let Some(workspace) = &self.workspace {
+            workspace.validate()?;
+        }
+        if let Some(history) = &self.history {
+            history.validate()?;
+        }
         Ok(())
     }
 }
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Every query execution is appended to the history as one JSON line.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct HistoryConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// History file; `history.jsonl` beside the state file, or in `.yetii/` without state
+    /// management, when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Newest entries kept when the history is compacted after a run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_entries: Option<usize>,
+    /// Entries that finished longer ago than this are removed when the history is compacted.
+    #[serde(
+        default,
+        deserialize_with = "units::seconds",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub max_age_seconds: Option<u64>,
+    /// Where removed entries are kept as gzipped JSON lines; they are deleted when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<HistoryArchiveConfig>,
+}
+
+impl HistoryConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_entries == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::new("execution.history.max_entries"),
+                value: "0".to_string(),
+            });
+        }
+        if self.max_age_seconds == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::new("execution.history.max_age_seconds"),
+                value: "0".to_string(),
+            });
+        }
+        if let Some(archive) = &self.archive
+            && archive.directory.is_some() == archive.s3.is_some()
+        {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::new("execution.history.archive"),
+                value: "set exactly one of directory or s3".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// One `.jsonl.gz` file per compaction.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct HistoryArchiveConfig {
+    /// Local directory for the archives.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// Bucket for the archives; `file_name` and `prefix` render with `{{query}}` as `history`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s3: Option<Box<S3SinkConfig>>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct StateManagement {
     pub enabled: bool,
+    /// JSON state file; required unless `backend` selects another store.
+    #[serde(default)]
     pub state_file: String,
+    /// Rotated copies of the JSON state file to keep.
+    #[serde(default)]
     pub backup_states: u32,
+    /// Where state is kept; the JSON `state_file` when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<StateBackendConfig>,
+}
+
+/// Stores for watermarks, run history, and checkpoints besides the JSON state file. SQLite and
+/// Redis write one query at a time, and Redis can be shared by daemons on several hosts.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StateBackendConfig {
+    /// The JSON `state_file`.
+    File,
+    Sqlite {
+        /// Database file, created when missing.
+        path: String,
+    },
+    Redis {
+        /// `redis://` or `rediss://` URL.
+        url: Secret<String>,
+        /// Hash holding one field per query (default `yetii:state`).
+        #[serde(default = "default_state_key")]
+        key: String,
+    },
+}
+
+fn default_state_key() -> String {
+    "yetii:state".to_string()
 }
 
 impl StateManagement {
     pub fn validate(&self) -> Result<(), ConfigError> {
-        if self.enabled && self.state_file.trim().is_empty() {
-            return Err(ConfigError::MissingRequiredField(
-                "execution.state_management.state_file".to_string(),
-            ));
+        if !self.enabled {
+            return Ok(());
+        }
+        match &self.backend {
+            None | Some(StateBackendConfig::File) if self.state_file.trim().is_empty() => {
+                Err(ConfigError::MissingRequiredField(FieldPath::new(
+                    "execution.state_management.state_file",
+                )))
+            }
+            Some(StateBackendConfig::Sqlite { path }) if path.trim().is_empty() => {
+                Err(ConfigError::MissingRequiredField(FieldPath::new(
+                    "execution.state_management.backend.path",
+                )))
+            }
+            Some(StateBackendConfig::Redis { url, key }) => {
+                crate::config::destination_config::validate_redis_url(
+                    url.expose(),
+                    &FieldPath::new("execution.state_management.backend.url"),
+                )?;
+                if key.trim().is_empty() {
+                    return Err(ConfigError::MissingRequiredField(FieldPath::new(
+                        "execution.state_management.backend.key",
+                    )));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
         }
-        Ok(())
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct SchedulerConfig {
     pub enabled: bool,
     pub max_concurrent_jobs: u32,
+    #[serde(deserialize_with = "units::minutes")]
+    #[schemars(with = "units::DurationValue")]
     pub job_timeout_minutes: u32,
-    pub missed_job_policy: String,
+    pub missed_job_policy: MissedJobPolicy,
+    #[serde(default)]
+    pub reload: ReloadConfig,
+}
+
+/// What the scheduler does with runs missed while the daemon was down or busy.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedJobPolicy {
+    /// Drop missed runs and wait for the next scheduled time.
+    Skip,
+}
+
+/// How a running daemon picks up configuration changes. A reload that fails to load or
+/// validate is logged and the running configuration is kept.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct ReloadConfig {
+    /// Reload when the configuration file, or a file in `--config-dir`, changes (default
+    /// true). Turn it off where change events are unreliable, such as NFS mounts.
+    #[serde(default = "default_true")]
+    pub watch_file: bool,
+    /// Reload on `SIGHUP` (default true; Unix only).
+    #[serde(default = "default_true")]
+    pub signal: bool,
+    /// Path on the `monitoring.health_check` listener that reloads on `POST`, e.g. `/-/reload`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+}
+
+impl Default for ReloadConfig {
+    fn default() -> Self {
+        Self {
+            watch_file: true,
+            signal: true,
+            endpoint: None,
+        }
+    }
 }
 
 impl SchedulerConfig {
     pub fn validate(&self) -> Result<(), ConfigError> {
         if self.max_concurrent_jobs == 0 {
             return Err(ConfigError::InvalidValue {
-                field: "execution.scheduler.max_concurrent_jobs".to_string(),
+                field: FieldPath::new("execution.scheduler.max_concurrent_jobs"),
                 value: "0".to_string(),
             });
         }
-        if self.missed_job_policy != "skip" {
+        if let Some(endpoint) = &self.reload.endpoint
+            && !endpoint.starts_with('/')
+        {
             return Err(ConfigError::InvalidValue {
-                field: "execution.scheduler.missed_job_policy".to_string(),
-                value: self.missed_job_policy.clone(),
+                field: FieldPath::new("execution.scheduler.reload.endpoint"),
+                value: endpoint.clone(),
             });
         }
         Ok(())
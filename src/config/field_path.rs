@@ -0,0 +1,92 @@
+use std::fmt;
+
+/// Where a configuration value lives, as the keys leading to it from the root of the file.
+/// Items of the `queries`, `databases`, and `flows` sequences are named by their `name`, so
+/// `queries.orders.destination.url` is the `url` of query `orders`'s destination; other
+/// sequence items are named by their index.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldPath {
+    keys: Vec<String>,
+    /// Why the value is needed or wrong, e.g. `required with password`.
+    note: Option<String>,
+}
+
+impl FieldPath {
+    /// A path from the root; `keys` are separated by `.`, e.g. `global_settings.environment`.
+    pub fn new(keys: &str) -> Self {
+        Self::default().join(keys)
+    }
+
+    pub fn query(name: &str) -> Self {
+        Self::item("queries", name)
+    }
+
+    pub fn database(name: &str) -> Self {
+        Self::item("databases", name)
+    }
+
+    pub fn flow(name: &str) -> Self {
+        Self::item("flows", name)
+    }
+
+    fn item(section: &str, name: &str) -> Self {
+        Self::default().key(section).key(name)
+    }
+
+    /// The path extended by `keys`, separated by `.`.
+    pub fn join(&self, keys: &str) -> Self {
+        let mut path = self.clone();
+        path.keys.extend(
+            keys.split('.')
+                .filter(|key| !key.is_empty())
+                .map(str::to_string),
+        );
+        path
+    }
+
+    /// The path extended by one key taken as written, for names that may contain `.` such as
+    /// column or parameter names.
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.keys.push(key.into());
+        self
+    }
+
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+}
+
+/// `query 'orders'.destination.url (note)`, as validation messages name fields.
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = |section: &str| match section {
+            "queries" => Some("query"),
+            "databases" => Some("database"),
+            "flows" => Some("flow"),
+            _ => None,
+        };
+        let rest = match self.keys.as_slice() {
+            [section, name, rest @ ..] if let Some(kind) = kind(section) => {
+                write!(f, "{kind} '{name}'")?;
+                rest
+            }
+            [first, rest @ ..] => {
+                f.write_str(first)?;
+                rest
+            }
+            [] => &[],
+        };
+        for key in rest {
+            write!(f, ".{key}")?;
+        }
+        if let Some(note) = &self.note {
+            write!(f, " ({note})")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use crate::config::{ConfigError, FieldPath};
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Queries run one after another as one unit, e.g. invoices, then their lines. A flow reports
+/// one outcome: one `flow_success` or `flow_failure` notification and one state entry.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct FlowConfig {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Initial values of the flow's variables, read by query parameters with
+    /// `source: flow:<variable>`.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub variables: IndexMap<String, String>,
+    pub steps: Vec<FlowStep>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct FlowStep {
+    /// Name used by `when` of later steps; defaults to the query name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Query to run, also when it is disabled.
+    pub query: String,
+    /// Runs the step only when an earlier step had this outcome. Without it, the step runs
+    /// when no earlier step failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<FlowCondition>,
+    /// Variables set from the step's results for later steps: `rows_read`, `batches_sent`,
+    /// `outcome`, `watermark:<name>` after the step, or `previous_watermark:<name>` before it.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub set: IndexMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct FlowCondition {
+    pub step: String,
+    pub outcome: StepOutcome,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepOutcome {
+    Success,
+    /// Some batches were delivered and others failed.
+    Partial,
+    Failure,
+    /// The step's `when` did not hold.
+    Skipped,
+}
+
+impl StepOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepOutcome::Success => "success",
+            StepOutcome::Partial => "partial",
+            StepOutcome::Failure => "failure",
+            StepOutcome::Skipped => "skipped",
+        }
+    }
+}
+
+/// A value a step publishes to the steps after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepValue<'a> {
+    RowsRead,
+    BatchesSent,
+    Outcome,
+    Watermark(&'a str),
+    PreviousWatermark(&'a str),
+}
+
+impl<'a> StepValue<'a> {
+    pub fn parse(value: &'a str) -> Option<Self> {
+        match value {
+            "rows_read" => Some(Self::RowsRead),
+            "batches_sent" => Some(Self::BatchesSent),
+            "outcome" => Some(Self::Outcome),
+            _ => {
+                if let Some(name) = value.strip_prefix("watermark:") {
+                    Some(Self::Watermark(name.trim())).filter(|_| !name.trim().is_empty())
+                } else if let Some(name) = value.strip_prefix("previous_watermark:") {
+                    Some(Self::PreviousWatermark(name.trim())).filter(|_| !name.trim().is_empty())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl FlowStep {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.query)
+    }
+}
+
+impl FlowConfig {
+    pub fn validate(&self, query_names: &HashSet<&str>) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::flow(&self.name).join(name);
+        if self.name.trim().is_empty() {
+            return Err(ConfigError::MissingRequiredField(FieldPath::new(
+                "flows.name",
+            )));
+        }
+        // History and notifications name a flow the way they name a query.
+        if query_names.contains(self.name.as_str()) {
+            return Err(ConfigError::InvalidValue {
+                field: field("name"),
+                value: format!("'{}' is also the name of a query", self.name),
+            });
+        }
+        if self.steps.is_empty() {
+            return Err(ConfigError::MissingRequiredField(field("steps")));
+        }
+        let mut earlier = HashSet::new();
+        for (index, step) in self.steps.iter().enumerate() {
+            let step_field = |name: &str| field("steps").key(index.to_string()).join(name);
+            if !query_names.contains(step.query.as_str()) {
+                return Err(ConfigError::InvalidValue {
+                    field: step_field("query"),
+                    value: step.query.clone(),
+                });
+            }
+            if let Some(condition) = &step.when
+                && !earlier.contains(condition.step.as_str())
+            {
+                return Err(ConfigError::InvalidValue {
+                    field: step_field("when.step"),
+                    value: format!("'{}' is not an earlier step", condition.step),
+                });
+            }
+            for (variable, value) in &step.set {
+                if StepValue::parse(value).is_none() {
+                    return Err(ConfigError::InvalidValue {
+                        field: step_field("set").key(variable),
+                        value: value.clone(),
+                    });
+                }
+            }
+            if !earlier.insert(step.name()) {
+                return Err(ConfigError::InvalidValue {
+                    field: step_field("name"),
+                    value: format!("'{}' names two steps", step.name()),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_steps_conditions_and_step_values() {
+        let flow: FlowConfig = serde_yaml::from_str(
+            "name: invoices_with_lines\nsteps:\n  - query: invoices\n    set:\n      from_id: previous_watermark:last_id\n      to_id: watermark:last_id\n  - query: invoice_lines\n  - name: cleanup\n    query: invoices\n    when:\n      step: invoice_lines\n      outcome: failure\n",
+        )
+        .unwrap();
+        let queries = HashSet::from(["invoices", "invoice_lines"]);
+        flow.validate(&queries).unwrap();
+        assert_eq!(
+            Some(StepValue::PreviousWatermark("last_id")),
+            StepValue::parse(&flow.steps[0].set["from_id"])
+        );
+        assert_eq!(None, StepValue::parse("watermark:"));
+
+        let mut later = flow.clone();
+        later.steps[2].when.as_mut().unwrap().step = "cleanup".to_string();
+        assert!(later.validate(&queries).is_err());
+        let mut twice = flow.clone();
+        twice.steps[2].name = None;
+        assert!(twice.validate(&queries).is_err());
+        assert!(
+            flow.validate(&HashSet::from([
+                "invoices",
+                "invoice_lines",
+                "invoices_with_lines"
+            ]))
+            .is_err()
+        );
+    }
+}
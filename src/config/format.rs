@@ -0,0 +1,186 @@
+//! Configuration file formats. YAML is read as is; `.json` and `.toml` files are converted to
+//! YAML text when read, so includes, `${VAR}` expansion, and validation treat every format
+//! alike. Line numbers in parse errors then refer to the converted text, so `check-config`
+//! points at findings with [`ConfigFormat::locate`] on the file as written.
+
+use crate::config::ConfigError;
+use crate::config::yaml_edit::YamlDocument;
+use jsonc_parser::ast;
+use jsonc_parser::common::Ranged;
+use std::path::Path;
+use toml::de::{DeTable, DeValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Chosen by extension; anything other than `.json` or `.toml` is YAML.
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("json") => ConfigFormat::Json,
+            Some("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Yaml,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ConfigFormat::Yaml => "YAML",
+            ConfigFormat::Json => "JSON",
+            ConfigFormat::Toml => "TOML",
+        }
+    }
+
+    pub fn to_yaml(self, content: String) -> Result<String, ConfigError> {
+        let invalid = |message: String| ConfigError::InvalidFormat {
+            format: self.name(),
+            message,
+        };
+        let document: serde_yaml::Value = match self {
+            ConfigFormat::Yaml => return Ok(content),
+            ConfigFormat::Json => {
+                serde_json::from_str(&content).map_err(|error| invalid(error.to_string()))?
+            }
+            ConfigFormat::Toml => {
+                toml::from_str(&content).map_err(|error| invalid(error.to_string()))?
+            }
+        };
+        Ok(serde_yaml::to_string(&document)?)
+    }
+
+    /// 1-based line and column of the deepest node of `keys` that exists in `text`, for pointing
+    /// at a problem. Sequence items are found by their `name` or by their index.
+    pub fn locate(self, text: &str, keys: &[String]) -> Option<(usize, usize)> {
+        let offset = match self {
+            ConfigFormat::Yaml => return YamlDocument::parse(text).ok()?.locate(keys),
+            ConfigFormat::Json => locate_json(text, keys)?,
+            ConfigFormat::Toml => locate_toml(text, keys)?,
+        };
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Some((
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        ))
+    }
+}
+
+/// Whether the sequence item at `index`, named `name`, is the one `key` refers to.
+fn is_item(key: &str, index: usize, name: Option<&str>) -> bool {
+    match key.parse::<usize>() {
+        Ok(wanted) => wanted == index,
+        Err(_) => name == Some(key),
+    }
+}
+
+/// Byte offset of the deepest node of `keys` in a JSON document.
+fn locate_json(text: &str, keys: &[String]) -> Option<usize> {
+    let parsed = jsonc_parser::parse_to_ast(text, &Default::default(), &Default::default()).ok()?;
+    let mut node = parsed.value.as_ref()?;
+    let mut found = None;
+    for key in keys {
+        let child = match node {
+            ast::Value::Object(object) => object
+                .properties
+                .iter()
+                .find(|property| property.name.as_str() == key)
+                .map(|property| (property.range.start, &property.value)),
+            ast::Value::Array(array) => array
+                .elements
+                .iter()
+                .enumerate()
+                .find(|(index, item)| {
+                    let name = match item {
+                        ast::Value::Object(object) => {
+                            object.get_string("name").map(|name| name.value.as_ref())
+                        }
+                        _ => None,
+                    };
+                    is_item(key, *index, name)
+                })
+                .map(|(_, item)| (item.start(), item)),
+            _ => None,
+        };
+        let Some((start, child)) = child else {
+            break;
+        };
+        found = Some(start);
+        node = child;
+    }
+    found
+}
+
+/// Byte offset of the deepest node of `keys` in a TOML document.
+fn locate_toml(text: &str, keys: &[String]) -> Option<usize> {
+    let root = DeValue::Table(DeTable::parse(text).ok()?.into_inner());
+    let mut node = &root;
+    let mut found = None;
+    for key in keys {
+        let child = match node {
+            DeValue::Table(table) => table
+                .iter()
+                .find(|(name, _)| name.get_ref() == key)
+                .map(|(name, value)| (name.span().start, value.get_ref())),
+            DeValue::Array(array) => array
+                .iter()
+                .enumerate()
+                .find(|(index, item)| {
+                    let name = item
+                        .get_ref()
+                        .as_table()
+                        .and_then(|table| table.iter().find(|(name, _)| name.get_ref() == "name"))
+                        .and_then(|(_, name)| name.get_ref().as_str());
+                    is_item(key, *index, name)
+                })
+                .map(|(_, item)| (item.span().start, item.get_ref())),
+            _ => None,
+        };
+        let Some((start, child)) = child else {
+            break;
+        };
+        found = Some(start);
+        node = child;
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_json_and_toml_to_the_same_document() {
+        let yaml =
+            "version: 1.0.0\ndatabases:\n- name: erp\n  port: 5432\n  password: ${ERP_PASSWORD}\n";
+        let json = r#"{"version": "1.0.0", "databases": [{"name": "erp", "port": 5432, "password": "${ERP_PASSWORD}"}]}"#;
+        let toml = "version = \"1.0.0\"\n\n[[databases]]\nname = \"erp\"\nport = 5432\npassword = \"${ERP_PASSWORD}\"\n";
+        let parse = |format: ConfigFormat, content: &str| -> serde_yaml::Value {
+            serde_yaml::from_str(&format.to_yaml(content.to_string()).unwrap()).unwrap()
+        };
+        let expected = parse(ConfigFormat::Yaml, yaml);
+        assert_eq!(expected, parse(ConfigFormat::Json, json));
+        assert_eq!(expected, parse(ConfigFormat::Toml, toml));
+
+        assert_eq!(
+            ConfigFormat::Toml,
+            ConfigFormat::from_path(Path::new("yetii.TOML"))
+        );
+        assert_eq!(
+            ConfigFormat::Yaml,
+            ConfigFormat::from_path(Path::new("yetii.yml"))
+        );
+        assert!(matches!(
+            ConfigFormat::Json.to_yaml("{\"version\": ".to_string()),
+            Err(ConfigError::InvalidFormat { format: "JSON", .. })
+        ));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::config::{ConfigError, FieldPath};
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Synthetic rows for a `type: generator` database, so configs run without database access.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct GeneratorConfig {
+    /// Rows returned by every query against the database.
+    pub rows: usize,
+    /// Makes every run produce the same rows; random each run when unset.
+    pub seed: Option<u64>,
+    /// Generated values by column name.
+    pub columns: IndexMap<String, GeneratedColumn>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct GeneratedColumn {
+    #[serde(flatten)]
+    pub kind: GeneratedValue,
+    /// Share of rows, between 0 and 1, where the column is NULL.
+    #[serde(default)]
+    pub null_rate: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GeneratedValue {
+    /// `start`, `start + step`, ... in row order.
+    Sequence {
+        #[serde(default = "default_start")]
+        start: i64,
+        #[serde(default = "default_step")]
+        step: i64,
+    },
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    Float {
+        min: f64,
+        max: f64,
+        /// Digits kept after the decimal point.
+        decimals: Option<u32>,
+    },
+    Boolean,
+    /// One of `values`, picked uniformly.
+    Choice {
+        values: Vec<serde_json::Value>,
+    },
+    /// An RFC 3339 timestamp between `min` and `max`.
+    Timestamp {
+        #[schemars(with = "String")]
+        min: DateTime<Utc>,
+        #[schemars(with = "String")]
+        max: DateTime<Utc>,
+    },
+    /// A first and last name.
+    Name,
+    /// An address derived from a generated name.
+    Email {
+        domain: Option<String>,
+    },
+    Uuid,
+    /// The same value in every row.
+    Constant {
+        value: serde_json::Value,
+    },
+}
+
+fn default_start() -> i64 {
+    1
+}
+
+fn default_step() -> i64 {
+    1
+}
+
+impl GeneratorConfig {
+    pub fn validate(&self, database: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::database(database).join("generator").join(name);
+        if self.columns.is_empty() {
+            return Err(ConfigError::MissingRequiredField(field("columns")));
+        }
+        for (name, column) in &self.columns {
+            let invalid = |value: String| ConfigError::InvalidValue {
+                field: field("columns").key(name),
+                value,
+            };
+            if !(0.0..=1.0).contains(&column.null_rate) {
+                return Err(invalid(format!(
+                    "null_rate {} is not between 0 and 1",
+                    column.null_rate
+                )));
+            }
+            match &column.kind {
+                GeneratedValue::Integer { min, max } if min > max => {
+                    return Err(invalid(format!("min {min} is greater than max {max}")));
+                }
+                GeneratedValue::Float { min, max, .. } if min > max => {
+                    return Err(invalid(format!("min {min} is greater than max {max}")));
+                }
+                GeneratedValue::Timestamp { min, max } if min > max => {
+                    return Err(invalid(format!("min {min} is later than max {max}")));
+                }
+                GeneratedValue::Choice { values } if values.is_empty() => {
+                    return Err(invalid("choice has no values".to_string()));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
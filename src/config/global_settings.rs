@@ -1,11 +1,12 @@
-use crate::config::ConfigError;
 pub use crate::config::error_handling::ErrorHandling;
 pub use crate::config::logging::Logging;
 pub use crate::config::security_settings::SecuritySettings;
 use crate::config::utils::default_environment;
+use crate::config::{ConfigError, FieldPath};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 /// Enhanced global settings with defaults and validation
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct GlobalSettings {
     #[serde(default = "default_environment")]
     pub environment: String,
@@ -27,19 +28,20 @@ impl Default for GlobalSettings {
     }
 }
 impl GlobalSettings {
-    pub fn validate(&self) -> Result<(), ConfigError> {
+    /// The first problem in each of the settings' sections.
+    pub fn validation_errors(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
         let valid_environments = ["development", "staging", "production"];
         if !valid_environments.contains(&self.environment.as_str()) {
-            return Err(ConfigError::InvalidValue {
-                field: "global_settings.environment".to_string(),
+            errors.push(ConfigError::InvalidValue {
+                field: FieldPath::new("global_settings.environment"),
                 value: self.environment.clone(),
             });
         }
 
-        self.error_handling.validate()?;
-        self.logging.validate()?;
-        self.security.validate()?;
-
-        Ok(())
+        errors.extend(self.error_handling.validate().err());
+        errors.extend(self.logging.validate().err());
+        errors.extend(self.security.validate().err());
+        errors
     }
 }
@@ -0,0 +1,257 @@
+//! `include:` and `queries_dir:` in a configuration file, so a large configuration can be split
+//! like a `--config-dir` layout while still being loaded with `--file`:
+//!
+//! ```yaml
+//! include:
+//!   - databases.yaml      # top-level sections, merged into this file
+//!   - endpoints/          # every *.yaml and *.yml file in the directory, in name order
+//! queries_dir: queries/   # one query, or a list of queries, per file
+//! ```
+//!
+//! Paths are relative to the file that names them, and included files may include others.
+//! Lists from several files are concatenated and maps are merged; a key or query name defined
+//! twice is an error naming both files.
+
+use crate::config::layout;
+use crate::config::{ConfigError, FieldPath};
+use serde_yaml::{Mapping, Value};
+use std::path::{Path, PathBuf};
+
+pub const INCLUDE_KEY: &str = "include";
+pub const QUERIES_DIR_KEY: &str = "queries_dir";
+
+/// Where each query came from, in the order they were added.
+pub(crate) type QueryOrigins = Vec<(String, PathBuf)>;
+
+/// The configuration text with includes expanded, or `None` when `path` uses neither key, so
+/// the original text with its line numbers is parsed instead.
+pub fn expand(path: &Path, content: &str) -> Result<Option<String>, ConfigError> {
+    let mut document: Value = serde_yaml::from_str(content)?;
+    let uses_includes = document
+        .as_mapping()
+        .is_some_and(|root| root.contains_key(INCLUDE_KEY) || root.contains_key(QUERIES_DIR_KEY));
+    if !uses_includes {
+        return Ok(None);
+    }
+    let mut origins = QueryOrigins::new();
+    let mut stack = vec![path.canonicalize().unwrap_or_else(|_| path.to_path_buf())];
+    expand_document(path, &mut document, &mut stack, &mut origins)?;
+    check_duplicate_queries(&origins)?;
+    Ok(Some(serde_yaml::to_string(&document)?))
+}
+
+/// Expands the includes of `document`, read from `path`; `stack` holds the files being
+/// expanded, to refuse cycles.
+pub(crate) fn expand_document(
+    path: &Path,
+    document: &mut Value,
+    stack: &mut Vec<PathBuf>,
+    origins: &mut QueryOrigins,
+) -> Result<(), ConfigError> {
+    let Some(root) = document.as_mapping_mut() else {
+        return Err(invalid(path, "expected a mapping of settings"));
+    };
+    record_queries(root.get("queries"), path, origins);
+    let base = path.parent().unwrap_or(Path::new(""));
+
+    let includes = match root.remove(INCLUDE_KEY) {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::String(include)) => vec![include],
+        Some(Value::Sequence(includes)) => includes
+            .into_iter()
+            .map(|include| match include {
+                Value::String(include) => Ok(include),
+                _ => Err(invalid(path, "`include` entries must be paths")),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(invalid(path, "`include` must be a path or a list of paths")),
+    };
+    for include in includes {
+        let target = base.join(&include);
+        let files = if target.is_dir() {
+            layout::files(&target, &["yaml", "yml"])?
+        } else {
+            vec![target]
+        };
+        for file in files {
+            let canonical = file.canonicalize().unwrap_or_else(|_| file.clone());
+            if stack.contains(&canonical) {
+                return Err(invalid(&file, "includes itself"));
+            }
+            let mut included: Value = serde_yaml::from_str(&layout::read(&file)?)?;
+            stack.push(canonical);
+            expand_document(&file, &mut included, stack, origins)?;
+            stack.pop();
+            let Value::Mapping(included) = included else {
+                unreachable!("expand_document accepts only mappings");
+            };
+            let root = document.as_mapping_mut().expect("checked above");
+            merge(root, included, "", &file)?;
+        }
+    }
+
+    let root = document.as_mapping_mut().expect("checked above");
+    match root.remove(QUERIES_DIR_KEY) {
+        None | Some(Value::Null) => {}
+        Some(Value::String(directory)) => {
+            let directory = base.join(directory);
+            if !directory.is_dir() {
+                return Err(invalid(&directory, "`queries_dir` is not a directory"));
+            }
+            match root.get("queries") {
+                Some(Value::Sequence(_)) => {}
+                None | Some(Value::Null) => {
+                    root.insert(Value::from("queries"), Value::Sequence(Vec::new()));
+                }
+                Some(_) => return Err(invalid(path, "`queries` must be a list")),
+            }
+            let queries = root
+                .get_mut("queries")
+                .and_then(Value::as_sequence_mut)
+                .expect("ensured above");
+            append_query_files(&directory, queries, origins)?;
+        }
+        Some(_) => return Err(invalid(path, "`queries_dir` must be a path")),
+    }
+    Ok(())
+}
+
+/// Appends the queries in the directory's YAML files, in file name order.
+pub(crate) fn append_query_files(
+    directory: &Path,
+    queries: &mut Vec<Value>,
+    origins: &mut QueryOrigins,
+) -> Result<(), ConfigError> {
+    for file in layout::files(directory, &["yaml", "yml"])? {
+        let added = match serde_yaml::from_str(&layout::read(&file)?)? {
+            Value::Sequence(list) => list,
+            query @ Value::Mapping(_) => vec![query],
+            Value::Null => Vec::new(),
+            _ => return Err(invalid(&file, "expected a query or a list of queries")),
+        };
+        record_queries(Some(&Value::Sequence(added.clone())), &file, origins);
+        queries.extend(added);
+    }
+    Ok(())
+}
+
+pub(crate) fn check_duplicate_queries(origins: &QueryOrigins) -> Result<(), ConfigError> {
+    for (index, (name, file)) in origins.iter().enumerate() {
+        if let Some((_, first)) = origins[..index].iter().find(|(other, _)| other == name) {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::query(name),
+                value: format!(
+                    "defined in both '{}' and '{}'",
+                    first.display(),
+                    file.display()
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn record_queries(queries: Option<&Value>, file: &Path, origins: &mut QueryOrigins) {
+    let names = queries
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(|query| query.get("name")?.as_str());
+    origins.extend(names.map(|name| (name.to_string(), file.to_path_buf())));
+}
+
+/// Merges `included` into `root`: lists are appended, maps merged key by key, and any other
+/// value set in both is a conflict.
+fn merge(
+    root: &mut Mapping,
+    included: Mapping,
+    prefix: &str,
+    file: &Path,
+) -> Result<(), ConfigError> {
+    for (key, value) in included {
+        let name = match key.as_str() {
+            Some(key) if prefix.is_empty() => key.to_string(),
+            Some(key) => format!("{prefix}.{key}"),
+            None => prefix.to_string(),
+        };
+        match (root.get_mut(&key), value) {
+            (None | Some(Value::Null), value) => {
+                root.insert(key, value);
+            }
+            (Some(Value::Sequence(existing)), Value::Sequence(added)) => existing.extend(added),
+            (Some(Value::Mapping(existing)), Value::Mapping(added)) => {
+                merge(existing, added, &name, file)?
+            }
+            (Some(_), _) => {
+                return Err(invalid(file, &format!("'{name}' is already set")));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn invalid(path: &Path, reason: &str) -> ConfigError {
+    ConfigError::InvalidValue {
+        field: FieldPath::default().key(path.display().to_string()),
+        value: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_included_sections_and_query_files() {
+        let directory = std::env::temp_dir().join(format!("yetii-include-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("queries")).unwrap();
+        let main = directory.join("yetii.yaml");
+        std::fs::write(
+            &main,
+            "version: '1.0.0'\ninclude: [shared.yaml]\nqueries_dir: queries\nqueries:\n  - name: inline\n",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("shared.yaml"),
+            "include: endpoints.yaml\ndatabases:\n  - name: erp\nendpoints:\n  crm: {url: https://crm}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("endpoints.yaml"),
+            "endpoints:\n  billing: {url: https://billing}\n",
+        )
+        .unwrap();
+        std::fs::write(directory.join("queries/a.yaml"), "name: orders\n").unwrap();
+        std::fs::write(directory.join("queries/b.yml"), "- name: invoices\n").unwrap();
+
+        let expanded = expand(&main, &std::fs::read_to_string(&main).unwrap())
+            .unwrap()
+            .unwrap();
+        let document: Value = serde_yaml::from_str(&expanded).unwrap();
+        let names = document["queries"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|query| query["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["inline", "orders", "invoices"], names);
+        assert_eq!("erp", document["databases"][0]["name"].as_str().unwrap());
+        assert_eq!(2, document["endpoints"].as_mapping().unwrap().len());
+        assert!(document.get(INCLUDE_KEY).is_none());
+        assert_eq!(None, expand(&main, "version: '1.0.0'\n").unwrap());
+
+        std::fs::write(directory.join("queries/c.yaml"), "name: inline\n").unwrap();
+        let error = expand(&main, &std::fs::read_to_string(&main).unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("query 'inline'"), "{error}");
+        assert!(error.contains("c.yaml"), "{error}");
+
+        std::fs::write(directory.join("endpoints.yaml"), "include: shared.yaml\n").unwrap();
+        let error = expand(&main, "include: shared.yaml\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("includes itself"), "{error}");
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+//! The encrypted keystore behind `yetii secret` and `${secret:NAME}` references. Each value is
+//! sealed with AES-256-GCM under a key stretched from `YETII_KEYSTORE_PASSPHRASE`, or read from
+//! a random key file created next to the keystore, so the keystore alone reveals no secret.
+
+use crate::config::encryption::{self, KeyFileError, KeySource, PBKDF2_ITERATIONS, SealingKey};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_PATH: &str = ".yetii/secrets.json";
+/// Environment variable that moves the keystore away from [`DEFAULT_PATH`].
+pub const PATH_VARIABLE: &str = "YETII_KEYSTORE";
+pub const PASSPHRASE_VARIABLE: &str = "YETII_KEYSTORE_PASSPHRASE";
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeystoreError {
+    #[error("keystore '{}' could not be accessed: {source}", .path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    KeyFile(#[from] KeyFileError),
+    #[error("keystore '{}' is not valid: {reason}", .path.display())]
+    Format { path: PathBuf, reason: String },
+    #[error("no keystore at '{}'; add a secret with `yetii secret set`", .0.display())]
+    NotFound(PathBuf),
+    #[error("keystore was created with a passphrase; set {PASSPHRASE_VARIABLE}")]
+    MissingPassphrase,
+    #[error("secret '{0}' is not in the keystore")]
+    MissingSecret(String),
+    #[error("invalid secret name '{0}'; use letters, digits, '_', '-', and '.'")]
+    InvalidName(String),
+    #[error("secret '{0}' could not be decrypted; the passphrase or key file does not match")]
+    Decrypt(String),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct KeystoreFile {
+    version: u32,
+    key_source: KeySource,
+    /// PBKDF2 salt for passphrase keys, base64.
+    salt: String,
+    iterations: u32,
+    secrets: BTreeMap<String, SealedSecret>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct SealedSecret {
+    nonce: String,
+    ciphertext: String,
+    updated_at: DateTime<Utc>,
+}
+
+pub struct Keystore {
+    path: PathBuf,
+    file: KeystoreFile,
+    key: SealingKey,
+}
+
+impl Keystore {
+    /// `$YETII_KEYSTORE`, or [`DEFAULT_PATH`] in the working directory.
+    pub fn location() -> PathBuf {
+        std::env::var_os(PATH_VARIABLE)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_PATH))
+    }
+
+    /// Opens the keystore at `path`. With `create`, a missing keystore is started, keyed by
+    /// the passphrase when one is set and by a new key file otherwise.
+    pub fn open(path: &Path, create: bool) -> Result<Self, KeystoreError> {
+        Self::open_with(
+            path,
+            create,
+            std::env::var(PASSPHRASE_VARIABLE)
+                .ok()
+                .filter(|passphrase| !passphrase.is_empty()),
+        )
+    }
+
+    fn open_with(
+        path: &Path,
+        create: bool,
+        passphrase: Option<String>,
+    ) -> Result<Self, KeystoreError> {
+        let file = match std::fs::read_to_string(path) {
+            Ok(content) => {
+                serde_json::from_str(&content).map_err(|error| KeystoreError::Format {
+                    path: path.to_path_buf(),
+                    reason: error.to_string(),
+                })?
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound && create => KeystoreFile {
+                version: 1,
+                key_source: if passphrase.is_some() {
+                    KeySource::Passphrase
+                } else {
+                    KeySource::KeyFile
+                },
+                salt: STANDARD.encode(rand::random::<[u8; 16]>()),
+                iterations: PBKDF2_ITERATIONS,
+                secrets: BTreeMap::new(),
+            },
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Err(KeystoreError::NotFound(path.to_path_buf()));
+            }
+            Err(source) => {
+                return Err(KeystoreError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                });
+            }
+        };
+
+        let key = match file.key_source {
+            KeySource::Passphrase => {
+                let passphrase = passphrase.ok_or(KeystoreError::MissingPassphrase)?;
+                let salt = STANDARD
+                    .decode(&file.salt)
+                    .map_err(|error| KeystoreError::Format {
+                        path: path.to_path_buf(),
+                        reason: format!("salt: {error}"),
+                    })?;
+                let iterations =
+                    NonZeroU32::new(file.iterations).ok_or_else(|| KeystoreError::Format {
+                        path: path.to_path_buf(),
+                        reason: "iterations must be positive".to_string(),
+                    })?;
+                SealingKey::from_passphrase(&passphrase, &salt, iterations)
+            }
+            KeySource::KeyFile => SealingKey::from_key_file(&key_file(path), create)?,
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            key,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Result<String, KeystoreError> {
+        let sealed = self
+            .file
+            .secrets
+            .get(name)
+            .ok_or_else(|| KeystoreError::MissingSecret(name.to_string()))?;
+        // The name is authenticated too, so a value cannot be moved to another name.
+        self.key
+            .open(name.as_bytes(), &sealed.nonce, &sealed.ciphertext)
+            .and_then(|plaintext| String::from_utf8(plaintext).ok())
+            .ok_or_else(|| KeystoreError::Decrypt(name.to_string()))
+    }
+
+    /// Seals `value` under `name`, replacing any previous value, and saves the keystore.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), KeystoreError> {
+        validate_name(name)?;
+        let (nonce, ciphertext) = self.key.seal(name.as_bytes(), value.as_bytes());
+        self.file.secrets.insert(
+            name.to_string(),
+            SealedSecret {
+                nonce,
+                ciphertext,
+                updated_at: Utc::now(),
+            },
+        );
+        self.save()
+    }
+
+    /// Returns whether the secret existed.
+    pub fn remove(&mut self, name: &str) -> Result<bool, KeystoreError> {
+        if self.file.secrets.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.save().map(|()| true)
+    }
+
+    /// Names and when each was last set, in name order.
+    pub fn list(&self) -> impl Iterator<Item = (&str, DateTime<Utc>)> {
+        self.file
+            .secrets
+            .iter()
+            .map(|(name, sealed)| (name.as_str(), sealed.updated_at))
+    }
+
+    fn save(&self) -> Result<(), KeystoreError> {
+        let content =
+            serde_json::to_string_pretty(&self.file).map_err(|error| KeystoreError::Format {
+                path: self.path.clone(),
+                reason: error.to_string(),
+            })?;
+        encryption::write_private(&self.path, content.as_bytes()).map_err(|source| {
+            KeystoreError::Io {
+                path: self.path.clone(),
+                source,
+            }
+        })
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), KeystoreError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || "_-.".contains(character));
+    if valid {
+        Ok(())
+    } else {
+        Err(KeystoreError::InvalidName(name.to_string()))
+    }
+}
+
+/// `secrets.json` is keyed by `secrets.key` beside it.
+fn key_file(path: &Path) -> PathBuf {
+    path.with_extension("key")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seals_secrets_that_only_the_key_opens() {
+        let directory = std::env::temp_dir().join(format!("yetii-keystore-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let path = directory.join("secrets.json");
+        assert!(matches!(
+            Keystore::open_with(&path, false, None),
+            Err(KeystoreError::NotFound(_))
+        ));
+
+        let mut keystore = Keystore::open_with(&path, true, None).unwrap();
+        keystore.set("DB_PASSWORD", "s3cret: 'quoted'").unwrap();
+        assert!(matches!(
+            keystore.set("bad name", "x"),
+            Err(KeystoreError::InvalidName(_))
+        ));
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("s3cret"));
+
+        let keystore = Keystore::open_with(&path, false, None).unwrap();
+        assert_eq!("s3cret: 'quoted'", keystore.get("DB_PASSWORD").unwrap());
+        assert_eq!(
+            vec!["DB_PASSWORD"],
+            keystore.list().map(|(name, _)| name).collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            keystore.get("API_TOKEN"),
+            Err(KeystoreError::MissingSecret(_))
+        ));
+
+        // A sealed value moved to another name no longer opens.
+        let mut file: serde_json::Value = serde_json::from_str(&content).unwrap();
+        file["secrets"]["API_TOKEN"] = file["secrets"]["DB_PASSWORD"].clone();
+        std::fs::write(&path, file.to_string()).unwrap();
+        let keystore = Keystore::open_with(&path, false, None).unwrap();
+        assert!(matches!(
+            keystore.get("API_TOKEN"),
+            Err(KeystoreError::Decrypt(_))
+        ));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
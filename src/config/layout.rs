@@ -0,0 +1,163 @@
+//! Configuration directories (`--config-dir`) with a conventional layout:
+//!
+//! ```text
+//! yetii/
+//!   yetii.yaml        databases, settings, and any inline queries
+//!   queries/*.yaml    one query, or a list of queries, per file
+//!   sql/*.sql         SQL for the query of the same name when it has no `query.sql`
+//!   .env              variables for `${VAR}` references; the process environment wins
+//! ```
+
+use crate::config::format::ConfigFormat;
+use crate::config::include;
+use crate::config::{ConfigError, FieldPath};
+use serde_yaml::{Mapping, Value};
+use std::path::{Path, PathBuf};
+
+pub const MAIN_FILE: &str = "yetii.yaml";
+
+/// The file that settings are read from and `config set`/`add-query` edit.
+pub fn main_file(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_dir() {
+        path.join(MAIN_FILE)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Merges the directory into the text of a single configuration, before `${VAR}` expansion.
+pub fn assemble(directory: &Path) -> Result<String, ConfigError> {
+    let main = directory.join(MAIN_FILE);
+    let mut document: Value = serde_yaml::from_str(&read(&main)?)?;
+    let mut origins = include::QueryOrigins::new();
+    let mut stack = vec![main.canonicalize().unwrap_or_else(|_| main.clone())];
+    include::expand_document(&main, &mut document, &mut stack, &mut origins)?;
+    let root = document
+        .as_mapping_mut()
+        .expect("expand_document accepts only mappings");
+
+    let mut queries = match root.remove("queries") {
+        Some(Value::Sequence(queries)) => queries,
+        Some(Value::Null) | None => Vec::new(),
+        Some(_) => return Err(invalid(&main, "`queries` must be a list")),
+    };
+    include::append_query_files(&directory.join("queries"), &mut queries, &mut origins)?;
+    include::check_duplicate_queries(&origins)?;
+
+    let sql_directory = directory.join("sql");
+    for query in &mut queries {
+        let Some(name) = query
+            .get("name")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        let sql_file = sql_directory.join(format!("{name}.sql"));
+        if query.get("query").and_then(|sql| sql.get("sql")).is_some() || !sql_file.is_file() {
+            continue;
+        }
+        let Some(query) = query.as_mapping_mut() else {
+            continue;
+        };
+        let section = query
+            .entry(Value::from("query"))
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        if let Some(section) = section.as_mapping_mut() {
+            section.insert(Value::from("sql"), Value::from(read(&sql_file)?));
+        }
+    }
+    root.insert(Value::from("queries"), Value::Sequence(queries));
+
+    Ok(serde_yaml::to_string(&document)?)
+}
+
+/// Files with one of `extensions` directly in `directory`, sorted by name.
+pub(crate) fn files(directory: &Path, extensions: &[&str]) -> Result<Vec<PathBuf>, ConfigError> {
+    if !directory.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files = std::fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.retain(|file| {
+        file.is_file()
+            && file
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| extensions.contains(&extension))
+    });
+    files.sort();
+    Ok(files)
+}
+
+pub(crate) fn read(path: &Path) -> Result<String, ConfigError> {
+    let content = std::fs::read_to_string(path).map_err(|error| ConfigError::InvalidValue {
+        field: FieldPath::default().key(path.display().to_string()),
+        value: error.to_string(),
+    })?;
+    let content = crate::config::encryption::decrypt_if_encrypted(content, path)?.0;
+    ConfigFormat::from_path(path).to_yaml(content)
+}
+
+fn invalid(path: &Path, reason: &str) -> ConfigError {
+    ConfigError::InvalidValue {
+        field: FieldPath::default().key(path.display().to_string()),
+        value: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_query_files_sql_files_and_dotenv() {
+        let directory =
+            std::env::temp_dir().join(format!("yetii-config-dir-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("queries")).unwrap();
+        std::fs::create_dir_all(directory.join("sql")).unwrap();
+        std::fs::write(
+            directory.join(MAIN_FILE),
+            "version: '1.0.0'\nqueries:\n  - name: inline\n    query:\n      sql: SELECT 1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("queries/orders.yaml"),
+            "name: orders\ndescription: Orders\n",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("queries/more.yml"),
+            "- name: invoices\n  query:\n    sql: SELECT 2\n",
+        )
+        .unwrap();
+        std::fs::write(directory.join("sql/orders.sql"), "SELECT * FROM orders\n").unwrap();
+        std::fs::write(directory.join("sql/invoices.sql"), "SELECT 3\n").unwrap();
+        std::fs::write(
+            directory.join(".env"),
+            "# secrets\nexport ERP_PASSWORD=\"s3cret\"\nAPI_TOKEN=abc=def\n",
+        )
+        .unwrap();
+
+        let assembled: Value = serde_yaml::from_str(&assemble(&directory).unwrap()).unwrap();
+        let variables = crate::config::dotenv::variables(&directory).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let queries = assembled["queries"].as_sequence().unwrap();
+        let sql = |index: usize| {
+            (
+                queries[index]["name"].as_str().unwrap(),
+                queries[index]["query"]["sql"].as_str().unwrap(),
+            )
+        };
+        assert_eq!(3, queries.len());
+        assert_eq!(("inline", "SELECT 1"), sql(0));
+        // `more.yml` sorts before `orders.yaml`, and inline SQL wins over `sql/invoices.sql`.
+        assert_eq!(("invoices", "SELECT 2"), sql(1));
+        assert_eq!(("orders", "SELECT * FROM orders\n"), sql(2));
+        assert_eq!("s3cret", variables["ERP_PASSWORD"]);
+        assert_eq!("abc=def", variables["API_TOKEN"]);
+    }
+}
@@ -1,46 +1,112 @@
-use crate::config::ConfigError;
-use crate::config::utils::{default_log_format, default_log_level, default_log_output};
+use crate::config::units;
+use crate::config::{ConfigError, FieldPath};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, Serialize)]
 pub struct Logging {
-    #[serde(default = "default_log_level")]
-    pub level: String,
-    #[serde(default = "default_log_format")]
-    pub format: String,
-    #[serde(default = "default_log_output")]
-    pub output: String,
+    #[serde(default)]
+    pub level: LogLevel,
+    #[serde(default)]
+    pub format: LogFormat,
+    #[serde(default)]
+    pub output: LogOutput,
     pub file_path: Option<String>,
     pub rotation: Option<LogRotation>,
 }
-impl Default for Logging {
-    fn default() -> Self {
-        Self {
-            level: default_log_level(),
-            format: default_log_format(),
-            output: default_log_output(),
-            file_path: None,
-            rotation: None,
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+impl LogLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Trace => "trace",
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
         }
     }
 }
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// One JSON object per event.
+    #[default]
+    Json,
+    /// Compact text lines.
+    Plain,
+    /// Text lines with the event target and spans.
+    Structured,
+}
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogOutput {
+    #[default]
+    Console,
+    /// Appends to `file_path`.
+    File,
+}
 impl Logging {
     pub fn validate(&self) -> Result<(), ConfigError> {
-        let valid_levels = ["trace", "debug", "info", "warn", "error"];
-        if !valid_levels.contains(&self.level.as_str()) {
-            return Err(ConfigError::InvalidDatabaseType(self.level.clone()));
+        let has_file_path = self
+            .file_path
+            .as_deref()
+            .is_some_and(|path| !path.trim().is_empty());
+        if self.output == LogOutput::File && !has_file_path {
+            return Err(ConfigError::MissingRequiredField(FieldPath::new(
+                "global_settings.logging.file_path",
+            )));
         }
 
-        let valid_formats = ["json", "plain", "structured"];
-        if !valid_formats.contains(&self.format.as_str()) {
-            return Err(ConfigError::InvalidDatabaseType(self.format.clone()));
+        if self
+            .rotation
+            .as_ref()
+            .is_some_and(|rotation| rotation.max_size_mb == 0)
+        {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::new("global_settings.logging.rotation.max_size_mb"),
+                value: "0".to_string(),
+            });
         }
 
         Ok(())
     }
 }
-// Placeholder implementations for remaining structs
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+/// Size-based rotation of the log file.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize, Default)]
 pub struct LogRotation {
+    /// Size at which the file is renamed to `<file_path>.1` and a new one started.
+    #[serde(deserialize_with = "units::megabytes")]
+    #[schemars(with = "units::SizeValue")]
     pub max_size_mb: u32,
+    /// Rotated files kept besides the current one; older ones are deleted.
     pub max_files: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_output_needs_a_path() {
+        let logging: Logging = serde_yaml::from_str("level: debug\nformat: plain\n").unwrap();
+        logging.validate().unwrap();
+
+        let logging: Logging = serde_yaml::from_str("output: file\n").unwrap();
+        assert!(matches!(
+            logging.validate(),
+            Err(ConfigError::MissingRequiredField(field)) if field == FieldPath::new("global_settings.logging.file_path")
+        ));
+        let error = serde_yaml::from_str::<Logging>("output: syslog\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("unknown variant `syslog`"), "{error}");
+    }
+}
@@ -1,45 +1,60 @@
+pub(crate) mod aws_secrets;
 pub(crate) mod connection_config;
 pub(crate) mod database;
+pub(crate) mod destination_config;
+pub(crate) mod docs;
+pub(crate) mod dotenv;
+pub(crate) mod encryption;
 pub(crate) mod endpoint_config;
-mod environment_config;
+pub(crate) mod environment_config;
 pub(crate) mod error_handling;
 pub(crate) mod execution_config;
+pub(crate) mod field_path;
+pub(crate) mod flow_config;
+pub(crate) mod format;
+pub(crate) mod generator_config;
 pub(crate) mod global_settings;
+pub(crate) mod include;
+pub(crate) mod keystore;
+pub(crate) mod layout;
 pub(crate) mod logging;
 pub(crate) mod monitor_config;
 pub(crate) mod query_config;
+pub(crate) mod references;
 pub(crate) mod request_config;
 pub(crate) mod schedule_config;
+pub(crate) mod schema;
+pub(crate) mod secret;
 pub(crate) mod security_settings;
+pub(crate) mod sort_config;
+pub(crate) mod source_config;
 pub(crate) mod sql_query;
 pub(crate) mod transform_config;
+pub(crate) mod units;
 mod utils;
 pub(crate) mod watermark_config;
+pub(crate) mod workspace_config;
+pub(crate) mod yaml_edit;
 pub(crate) mod yetii;
 
+pub use field_path::FieldPath;
 use once_cell::sync::OnceCell;
+use serde_path_to_error::Segment;
+use std::collections::HashMap;
 use std::sync::RwLock;
 
 pub static CONFIG: OnceCell<RwLock<yetii::YetiiConfig>> = OnceCell::new();
 
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
-    #[error("invalid database type: {0}")]
-    InvalidDatabaseType(String),
     #[error("invalid schedule format: {0}")]
     InvalidSchedule(String),
     #[error("missing required field: {0}")]
-    MissingRequiredField(String),
-    #[error("invalid timeout value: {0:?}")]
-    InvalidTimeout(Option<u32>),
-    #[error("invalid port: {0}")]
-    InvalidPort(u16),
+    MissingRequiredField(FieldPath),
     #[error("invalid HTTP method: {0}")]
     InvalidHttpMethod(String),
-    #[error("invalid execution mode: {0}")]
-    InvalidExecutionMode(String),
     #[error("invalid configuration value for {field}: {value}")]
-    InvalidValue { field: String, value: String },
+    InvalidValue { field: FieldPath, value: String },
     #[error("configuration not initialized; call load_config_once() first")]
     NotInitialized,
     #[error("configuration lock is poisoned")]
@@ -48,25 +63,214 @@ pub enum ConfigError {
     IoError(#[from] std::io::Error),
     #[error("configuration serialization error: {0}")]
     SerializationError(#[from] serde_yaml::Error),
+    /// A value of the wrong type or shape; `path` leads to it.
+    #[error("configuration serialization error: {source}")]
+    Deserialization {
+        path: FieldPath,
+        source: serde_yaml::Error,
+    },
+    #[error("configuration is not valid {format}: {message}")]
+    InvalidFormat {
+        format: &'static str,
+        message: String,
+    },
     #[error("configuration has already been initialized")]
     ConfigAlreadySet,
     #[error("environment variable '{0}' referenced by configuration is not set")]
     MissingEnvironmentVariable(String),
+    #[error(transparent)]
+    Keystore(#[from] keystore::KeystoreError),
+    #[error(transparent)]
+    Encryption(#[from] encryption::EncryptionError),
+    #[error(transparent)]
+    AwsSecret(#[from] aws_secrets::AwsSecretError),
 }
 
-/// Load configuration from a file path
+impl ConfigError {
+    /// Stable identifier of the kind of problem, for tools that act on `check-config` output.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfigError::InvalidSchedule(_) => "invalid_schedule",
+            ConfigError::MissingRequiredField(_) => "missing_required_field",
+            ConfigError::InvalidHttpMethod(_) => "invalid_http_method",
+            ConfigError::InvalidValue { .. } => "invalid_value",
+            ConfigError::IoError(_) => "unreadable_file",
+            ConfigError::SerializationError(_)
+            | ConfigError::Deserialization { .. }
+            | ConfigError::InvalidFormat { .. } => "parse_error",
+            ConfigError::MissingEnvironmentVariable(_) => "missing_environment_variable",
+            ConfigError::Keystore(_) => "keystore",
+            ConfigError::Encryption(_) => "encryption",
+            ConfigError::AwsSecret(_) => "aws_secret",
+            ConfigError::NotInitialized
+            | ConfigError::LockPoisoned
+            | ConfigError::ConfigAlreadySet => "internal",
+        }
+    }
+
+    /// The configuration value the error is about, when it is about one.
+    pub fn path(&self) -> Option<&FieldPath> {
+        match self {
+            ConfigError::MissingRequiredField(path)
+            | ConfigError::InvalidValue { field: path, .. }
+            | ConfigError::Deserialization { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+/// Load configuration from a file path, or from a directory laid out as described in
+/// [`layout`]. JSON and TOML files are read as described in [`format`]. Files written by `yetii encrypt` are decrypted first, and `${VAR}` references
+/// the environment does not set are filled from the [`dotenv`] file.
 pub fn load_config(path: &str) -> Result<yetii::YetiiConfig, ConfigError> {
-    let content = std::fs::read_to_string(path)?;
-    let content = interpolate_env_vars(&content)?;
-    let config: yetii::YetiiConfig = serde_yaml::from_str(&content)?;
+    let config = load_unvalidated(path)?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Like [`load_config`], without [`validate`](yetii::YetiiConfig::validate), so `check-config`
+/// can report every problem through [`validation_errors`](yetii::YetiiConfig::validation_errors).
+pub fn load_unvalidated(path: &str) -> Result<yetii::YetiiConfig, ConfigError> {
+    let directory = std::path::Path::new(path);
+    let variables = dotenv::variables(directory)?;
+    let (content, encrypted) = if directory.is_dir() {
+        let main = std::fs::read_to_string(directory.join(layout::MAIN_FILE))?;
+        (
+            layout::assemble(directory)?,
+            encryption::is_encrypted(&main),
+        )
+    } else {
+        let (content, encrypted) = read_file(directory)?;
+        let content = format::ConfigFormat::from_path(directory).to_yaml(content)?;
+        (expand_includes(directory, content)?, encrypted)
+    };
+    let config = parse_unvalidated(&content, &variables)?;
+    // The flag makes encryption at rest a requirement rather than a habit.
+    if config.global_settings.security.encrypt_config && !encrypted {
+        return Err(ConfigError::InvalidValue {
+            field: FieldPath::new("global_settings.security.encrypt_config"),
+            value: format!("'{path}' is not encrypted; run `yetii encrypt` or turn the flag off"),
+        });
+    }
+    Ok(config)
+}
+
+/// The configuration text as written, before `${VAR}` expansion; for a directory, its files
+/// merged into one document.
+pub fn read_config_text(path: &str) -> Result<String, ConfigError> {
+    let directory = std::path::Path::new(path);
+    if directory.is_dir() {
+        layout::assemble(directory)
+    } else {
+        let content =
+            format::ConfigFormat::from_path(directory).to_yaml(read_file(directory)?.0)?;
+        expand_includes(directory, content)
+    }
+}
 
-    // Validate the configuration
+/// `content` of the file at `path` with its [`include`]s merged in.
+pub(crate) fn expand_includes(
+    path: &std::path::Path,
+    content: String,
+) -> Result<String, ConfigError> {
+    Ok(include::expand(path, &content)?.unwrap_or(content))
+}
+
+/// A configuration file's text, decrypted when `yetii encrypt` wrote it, and whether it was.
+pub(crate) fn read_file(path: &std::path::Path) -> Result<(String, bool), ConfigError> {
+    encryption::decrypt_if_encrypted(std::fs::read_to_string(path)?, path)
+}
+
+/// Parses and validates configuration text exactly as `load_config` does for a file.
+pub fn parse_config(content: &str) -> Result<yetii::YetiiConfig, ConfigError> {
+    let config = parse_unvalidated(content, &HashMap::new())?;
     config.validate()?;
+    Ok(config)
+}
 
+/// Like [`parse_config`] without validation, with `fallback` values for variables missing from
+/// the environment.
+fn parse_unvalidated(
+    content: &str,
+    fallback: &HashMap<String, String>,
+) -> Result<yetii::YetiiConfig, ConfigError> {
+    let content = interpolate_vars(content, fallback)?;
+    let mut document: serde_yaml::Value = serde_yaml::from_str(&content)?;
+    let secrets = resolve_secrets(&mut document)?;
+    check_environment_overrides(&document)?;
+    let selected = environment_config::selected_environment();
+    let overridden = match selected.as_deref() {
+        Some(environment) => environment_config::apply_selected(&mut document, environment)?,
+        None => false,
+    };
+    // Parsing the text again keeps line numbers in errors when there is nothing to expand.
+    let mut config: yetii::YetiiConfig =
+        if references::resolve(&mut document)? || overridden || secrets {
+            deserialize(document)?
+        } else {
+            deserialize(serde_yaml::Deserializer::from_str(&content))?
+        };
+    // A selected environment replaces the configured one. Row filters are enforced for the
+    // environment, not left to the SQL authors.
+    if let Some(environment) = selected {
+        config.global_settings.environment = environment;
+    }
+    let environment = config.global_settings.environment.clone();
+    config.apply_row_filters(&environment);
+    config.apply_incremental();
     Ok(config)
 }
 
-fn interpolate_env_vars(content: &str) -> Result<String, ConfigError> {
+/// Deserializes the configuration, keeping the path to a value of the wrong type or shape.
+fn deserialize<'de, D>(deserializer: D) -> Result<yetii::YetiiConfig, ConfigError>
+where
+    D: serde::Deserializer<'de, Error = serde_yaml::Error>,
+{
+    serde_path_to_error::deserialize(deserializer).map_err(|error| {
+        let path = error
+            .path()
+            .iter()
+            .fold(FieldPath::default(), |path, segment| match segment {
+                Segment::Seq { index } => path.key(index.to_string()),
+                Segment::Map { key } | Segment::Enum { variant: key } => path.key(key.clone()),
+                _ => path,
+            });
+        ConfigError::Deserialization {
+            path,
+            source: error.into_inner(),
+        }
+    })
+}
+
+/// Merges each environment's override into a copy of `document` and parses the result, so an
+/// override with a wrong type or value fails every load, not just those selecting it.
+fn check_environment_overrides(document: &serde_yaml::Value) -> Result<(), ConfigError> {
+    let environments = document
+        .get("environments")
+        .and_then(serde_yaml::Value::as_mapping)
+        .into_iter()
+        .flat_map(|environments| environments.keys())
+        .filter_map(serde_yaml::Value::as_str);
+    for environment in environments {
+        let mut merged = document.clone();
+        environment_config::apply_override(&mut merged, environment);
+        references::resolve(&mut merged)?;
+        if let Err(error) = serde_yaml::from_value::<yetii::YetiiConfig>(merged) {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::new("environments").key(environment),
+                value: error.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Expands the `${VAR}` references in configuration text. `${secret:NAME}` and AWS references
+/// are left as written for [`resolve_secrets`].
+fn interpolate_vars(
+    content: &str,
+    fallback: &HashMap<String, String>,
+) -> Result<String, ConfigError> {
     let mut output = String::with_capacity(content.len());
     let mut rest = content;
 
@@ -79,9 +283,11 @@ fn interpolate_env_vars(content: &str) -> Result<String, ConfigError> {
         };
 
         let name = &after_start[..end];
-        let value = std::env::var(name)
-            .map_err(|_| ConfigError::MissingEnvironmentVariable(name.to_string()))?;
-        output.push_str(&value);
+        if name.starts_with(SECRET_PREFIX) || aws_secrets::is_reference(name) {
+            output.push_str(&rest[start..start + end + 3]);
+        } else {
+            output.push_str(&variable(name, fallback)?);
+        }
         rest = &after_start[end + 1..];
     }
 
@@ -89,6 +295,172 @@ fn interpolate_env_vars(content: &str) -> Result<String, ConfigError> {
     Ok(output)
 }
 
+/// Replaces the `${secret:NAME}` and AWS references in the document's string values with values
+/// from the [`keystore`] and [`aws_secrets`]. They are substituted after parsing, so a secret
+/// containing YAML syntax stays one string, and references in comments are never looked up.
+/// Returns whether the document had any.
+fn resolve_secrets(document: &mut serde_yaml::Value) -> Result<bool, ConfigError> {
+    let aws = aws_secrets::resolve(value_references(document).iter().map(String::as_str))?;
+    // Opened on the first reference, so configurations without one never need a key.
+    let mut keystore = None;
+    substitute_references(document, &mut |name| {
+        if let Some(value) = aws.get(name) {
+            return Ok(Some(value.clone()));
+        }
+        let Some(secret) = name.strip_prefix(SECRET_PREFIX) else {
+            return Ok(None);
+        };
+        if keystore.is_none() {
+            keystore = Some(keystore::Keystore::open(
+                &keystore::Keystore::location(),
+                false,
+            )?);
+        }
+        Ok(Some(keystore.as_ref().expect("opened above").get(secret)?))
+    })
+}
+
+/// Replaces each `${…}` reference in the string values under `value` for which `resolve`
+/// returns a value. Mapping keys are left alone. Returns whether any reference was replaced.
+fn substitute_references(
+    value: &mut serde_yaml::Value,
+    resolve: &mut impl FnMut(&str) -> Result<Option<String>, ConfigError>,
+) -> Result<bool, ConfigError> {
+    match value {
+        serde_yaml::Value::String(text) => {
+            let mut output = String::with_capacity(text.len());
+            let mut rest = text.as_str();
+            let mut replaced = false;
+            while let Some(start) = rest.find("${") {
+                let after_start = &rest[start + 2..];
+                let Some(end) = after_start.find('}') else {
+                    break;
+                };
+                output.push_str(&rest[..start]);
+                match resolve(&after_start[..end])? {
+                    Some(resolved) => {
+                        output.push_str(&resolved);
+                        replaced = true;
+                    }
+                    None => output.push_str(&rest[start..start + end + 3]),
+                }
+                rest = &after_start[end + 1..];
+            }
+            if replaced {
+                output.push_str(rest);
+                *text = output;
+            }
+            Ok(replaced)
+        }
+        serde_yaml::Value::Sequence(items) => items.iter_mut().try_fold(false, |replaced, item| {
+            Ok(substitute_references(item, resolve)? || replaced)
+        }),
+        serde_yaml::Value::Mapping(mapping) => {
+            mapping.values_mut().try_fold(false, |replaced, item| {
+                Ok(substitute_references(item, resolve)? || replaced)
+            })
+        }
+        serde_yaml::Value::Tagged(tagged) => substitute_references(&mut tagged.value, resolve),
+        _ => Ok(false),
+    }
+}
+
+/// The `${…}` references in the document's string values, in order of first use.
+fn value_references(document: &serde_yaml::Value) -> Vec<String> {
+    fn collect(value: &serde_yaml::Value, names: &mut Vec<String>) {
+        match value {
+            serde_yaml::Value::String(text) => {
+                for name in reference_names(text) {
+                    if !names.iter().any(|known| known == name) {
+                        names.push(name.to_string());
+                    }
+                }
+            }
+            serde_yaml::Value::Sequence(items) => {
+                items.iter().for_each(|item| collect(item, names));
+            }
+            serde_yaml::Value::Mapping(mapping) => {
+                mapping.values().for_each(|item| collect(item, names));
+            }
+            serde_yaml::Value::Tagged(tagged) => collect(&tagged.value, names),
+            _ => {}
+        }
+    }
+    let mut names = Vec::new();
+    collect(document, &mut names);
+    names
+}
+
+/// The environment variable `name`, or its `fallback` value.
+fn variable(name: &str, fallback: &HashMap<String, String>) -> Result<String, ConfigError> {
+    std::env::var(name)
+        .ok()
+        .or_else(|| fallback.get(name).cloned())
+        .ok_or_else(|| ConfigError::MissingEnvironmentVariable(name.to_string()))
+}
+
+/// Each `${…}` reference of the configuration at `path` with the error resolving it, if any:
+/// the environment variables in order of first use, then the keystore and AWS references in
+/// the document's string values. Each is resolved on its own, so one failure does not hide the
+/// others; values are never returned.
+pub fn check_references(path: &str) -> Result<Vec<(String, Option<ConfigError>)>, ConfigError> {
+    let text = read_config_text(path)?;
+    let fallback = dotenv::variables(std::path::Path::new(path))?;
+    let variables = env_var_names(&text).into_iter().map(|name| {
+        let resolved = variable(&name, &fallback).map(drop);
+        (format!("${{{name}}}"), resolved.err())
+    });
+    let document: serde_yaml::Value = serde_yaml::from_str(&text)?;
+    let secrets = value_references(&document)
+        .into_iter()
+        .filter(|name| name.starts_with(SECRET_PREFIX) || aws_secrets::is_reference(name))
+        .map(|name| {
+            let resolved = match name.strip_prefix(SECRET_PREFIX) {
+                Some(secret) => keystore::Keystore::open(&keystore::Keystore::location(), false)
+                    .and_then(|keystore| keystore.get(secret))
+                    .map(drop)
+                    .map_err(ConfigError::from),
+                None => aws_secrets::resolve([name.as_str()])
+                    .map(drop)
+                    .map_err(ConfigError::from),
+            };
+            (format!("${{{name}}}"), resolved.err())
+        });
+    Ok(variables.chain(secrets).collect())
+}
+
+/// Prefix of references resolved from the [`keystore`] rather than the environment.
+const SECRET_PREFIX: &str = "secret:";
+
+/// Names of the `${VAR}` references in configuration text, in order of first use.
+/// `${secret:NAME}` and `${aws-sm:…}` style references are not environment variables and are
+/// left out.
+pub(crate) fn env_var_names(content: &str) -> Vec<String> {
+    reference_names(content)
+        .into_iter()
+        .filter(|name| !name.starts_with(SECRET_PREFIX) && !aws_secrets::is_reference(name))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The text of every `${…}` reference, in order of first use.
+fn reference_names(content: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("${") {
+        let after_start = &rest[start + 2..];
+        let Some(end) = after_start.find('}') else {
+            break;
+        };
+        let name = &after_start[..end];
+        if !names.contains(&name) {
+            names.push(name);
+        }
+        rest = &after_start[end + 1..];
+    }
+    names
+}
+
 /// Load configuration once into the global CONFIG static
 pub fn load_config_once(path: &str) -> Result<(), ConfigError> {
     let config = load_config(path)?;
@@ -98,6 +470,13 @@ pub fn load_config_once(path: &str) -> Result<(), ConfigError> {
     Ok(())
 }
 
+/// Replaces the global configuration, e.g. after the daemon reloaded and validated the file.
+pub fn replace_config(config: yetii::YetiiConfig) -> Result<(), ConfigError> {
+    let current = CONFIG.get().ok_or(ConfigError::NotInitialized)?;
+    *current.write().map_err(|_| ConfigError::LockPoisoned)? = config;
+    Ok(())
+}
+
 /// Get a read guard to the global configuration
 /// Returns an error if config is not initialized or lock is poisoned
 pub fn get_config() -> Result<std::sync::RwLockReadGuard<'static, yetii::YetiiConfig>, ConfigError>
@@ -120,7 +499,7 @@ mod tests {
 
         assert_eq!(
             "password: resolved-secret\n",
-            interpolate_env_vars(content).unwrap()
+            interpolate_vars(content, &HashMap::new()).unwrap()
         );
     }
 
@@ -131,11 +510,48 @@ mod tests {
         }
 
         assert!(matches!(
-            interpolate_env_vars("${YETII_TEST_MISSING}"),
+            interpolate_vars("${YETII_TEST_MISSING}", &HashMap::new()),
             Err(ConfigError::MissingEnvironmentVariable(name)) if name == "YETII_TEST_MISSING"
         ));
     }
 
+    #[test]
+    fn references_are_substituted_inside_string_values() {
+        let mut document: serde_yaml::Value = serde_yaml::from_str(
+            "auth:\n  password: ${secret:DB}\n  username: ${secret:USER}-svc\n  '${secret:DB}': 1\n",
+        )
+        .unwrap();
+
+        let replaced = substitute_references(&mut document, &mut |name| {
+            Ok(match name {
+                "secret:DB" => Some("x'\n  admin: true".to_string()),
+                "secret:USER" => Some("etl".to_string()),
+                _ => None,
+            })
+        })
+        .unwrap();
+
+        assert!(replaced);
+        let auth = document["auth"].as_mapping().unwrap();
+        assert_eq!(3, auth.len());
+        assert_eq!(auth["password"], "x'\n  admin: true");
+        assert_eq!(auth["username"], "etl-svc");
+        assert_eq!(auth["${secret:DB}"], 1);
+    }
+
+    #[test]
+    fn references_are_collected_from_string_values_only() {
+        let document: serde_yaml::Value = serde_yaml::from_str(
+            "# password: ${aws-sm:prod/old}\nauth:\n  username: ${aws-sm:prod/erp#user}\n  password: ${secret:DB}\nurls: [\"${aws-sm:prod/erp#user}\"]\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["aws-sm:prod/erp#user", "secret:DB"],
+            value_references(&document)
+        );
+    }
+
     #[test]
     fn single_database_object_yaml_still_loads() {
         let config: yetii::YetiiConfig = serde_yaml::from_str(
@@ -160,6 +576,39 @@ queries: []
         assert!(config.databases.get("main").is_some());
     }
 
+    #[test]
+    fn environment_overrides_are_type_checked_at_load() {
+        let config = |port: &str| {
+            format!(
+                r#"
+version: "1.0.0"
+databases:
+  - name: erp
+    type: postgres
+    host: localhost
+    port: 5432
+    database: postgres
+    auth:
+      username: null
+      password: null
+queries: []
+environments:
+  production:
+    databases:
+      - name: erp
+        port: {port}
+"#
+            )
+        };
+
+        parse_unvalidated(&config("6432"), &HashMap::new()).unwrap();
+        let error = parse_unvalidated(&config("primary"), &HashMap::new()).unwrap_err();
+        assert!(
+            matches!(&error, ConfigError::InvalidValue { field, .. } if *field == FieldPath::new("environments.production")),
+            "{error}"
+        );
+    }
+
     #[test]
     fn database_list_yaml_loads() {
         let config: yetii::YetiiConfig = serde_yaml::from_str(
@@ -222,7 +671,7 @@ queries: []
 
         assert!(matches!(
             config.validate(),
-            Err(ConfigError::InvalidValue { field, .. }) if field == "databases.name"
+            Err(ConfigError::InvalidValue { field, .. }) if field == FieldPath::new("databases.main.name")
         ));
     }
 
@@ -233,7 +682,7 @@ queries: []
 
         assert!(matches!(
             config.validate(),
-            Err(ConfigError::MissingRequiredField(field)) if field == "query 'sync'.database"
+            Err(ConfigError::MissingRequiredField(field)) if field == FieldPath::new("queries.sync.database")
         ));
     }
 
@@ -245,7 +694,7 @@ queries: []
         assert!(matches!(
             config.validate(),
             Err(ConfigError::InvalidValue { field, value })
-                if field == "query 'sync'.database" && value == "missing"
+                if field == FieldPath::new("queries.sync.database") && value == "missing"
         ));
     }
 
@@ -257,13 +706,94 @@ queries: []
         assert!(matches!(
             config.validate(),
             Err(ConfigError::InvalidValue { field, .. })
-                if field == "query 'sync'.watermark"
+                if field == FieldPath::new("queries.sync.watermark")
         ));
 
         let config: yetii::YetiiConfig = serde_yaml::from_str(&watermark_query_yaml(true)).unwrap();
         config.validate().unwrap();
     }
 
+    #[test]
+    fn row_filters_of_the_configured_environment_wrap_query_sql() {
+        let config = parse_config(
+            r#"
+version: "1.0.0"
+databases:
+  name: main
+  type: postgres
+  host: localhost
+  port: 5432
+  database: postgres
+  auth:
+    username: null
+    password: null
+global_settings:
+  environment: staging
+queries:
+  - name: orders
+    description: orders
+    query:
+      sql: SELECT * FROM orders;
+    endpoint:
+      url: http://127.0.0.1/orders
+      method: POST
+  - name: rates
+    description: rates
+    query:
+      sql: SELECT * FROM rates
+    endpoint:
+      url: http://127.0.0.1/rates
+      method: POST
+environments:
+  staging:
+    row_filters:
+      - predicate: company_id IN (901, 902)
+        queries: [orders]
+  production:
+    row_filters:
+      - predicate: "1 = 0"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            "SELECT * FROM (\nSELECT * FROM orders\n) yetii_scoped WHERE (company_id IN (901, 902))",
+            config.queries[0].query.sql
+        );
+        assert_eq!("SELECT * FROM rates", config.queries[1].query.sql);
+    }
+
+    #[test]
+    fn incremental_expands_to_a_state_watermark_around_the_sql() {
+        let yaml = watermark_query_yaml(true).replace(
+            "      sql: SELECT id FROM orders WHERE id > $last_id\n      parameters:\n        last_id:\n          type: bigint\n          default: \"0\"\n          source: state_file\n    watermark:\n      strategy: max\n      column: id\n      parameter: last_id\n",
+            "      sql: SELECT id, updated_at FROM orders;\n    incremental:\n      column: updated_at\n      type: timestamp\n",
+        );
+        let config = parse_config(&yaml).unwrap();
+        let query = &config.queries[0];
+
+        assert_eq!(
+            "SELECT * FROM (\nSELECT id, updated_at FROM orders\n) yetii_incremental WHERE updated_at > $incremental_watermark ORDER BY updated_at",
+            query.query.sql
+        );
+        let parameter = &query.query.parameters.as_ref().unwrap()["incremental_watermark"];
+        assert_eq!(Some("1970-01-01T00:00:00Z"), parameter.default.as_deref());
+        assert_eq!(Some("state_file"), parameter.source.as_deref());
+        assert_eq!(
+            vec!["updated_at"],
+            query.watermark.as_ref().unwrap().cursor_columns()
+        );
+
+        let both = watermark_query_yaml(true).replace(
+            "    endpoint:",
+            "    incremental:\n      column: id\n      type: integer\n    endpoint:",
+        );
+        assert!(matches!(
+            parse_config(&both),
+            Err(ConfigError::InvalidValue { field, .. }) if field == FieldPath::new("queries.sync.incremental")
+        ));
+    }
+
     fn multi_database_query_yaml(database: Option<&str>) -> String {
         let database_line = database
             .map(|name| format!("    database: {name}\n"))
@@ -1,32 +1,129 @@
-use crate::config::ConfigError;
-use crate::config::endpoint_config::EndpointAuth;
+use crate::config::endpoint_config::{EndpointAuth, HttpMethod};
+use crate::config::request_config::RetryBackoff;
+use crate::config::secret::Secret;
+use crate::config::units;
+use crate::config::{ConfigError, FieldPath};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct MonitoringConfig {
     pub enabled: bool,
     pub metrics: Option<MetricsConfig>,
     pub health_check: Option<HealthCheckConfig>,
     pub notifications: Option<NotificationSettings>,
+    /// What the daemon does when the health or metrics server or a notification service fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_policy: Option<MonitoringFailurePolicy>,
+    /// OpenTelemetry traces of query runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracing: Option<TracingConfig>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Sends one trace per query run, with spans for its fetch, transform, and deliver stages, to
+/// an OTLP/HTTP collector such as Tempo or the OpenTelemetry Collector.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct TracingConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Collector URL, e.g. `http://tempo:4318`; `/v1/traces` is added when it has no path.
+    pub endpoint: String,
+    /// `service.name` of the traces (default `yetii`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+    /// Headers sent with each export, e.g. an `Authorization` header.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Export request timeout (default 10).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+}
+
+/// Monitoring failures are logged and shown in the health body and metrics; by default they
+/// never stop the daemon from running its queries.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, Serialize)]
+pub struct MonitoringFailurePolicy {
+    /// When the health or metrics server cannot bind its port.
+    #[serde(default)]
+    pub server: ServerFailureAction,
+    /// How often a server that could not bind tries again (default 30).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub server_retry_seconds: Option<u32>,
+    /// When a notification service cannot be reached or rejects a notification.
+    #[serde(default)]
+    pub notifications: NotificationFailureAction,
+    /// How often undelivered notifications are sent again (default 60).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub notification_retry_seconds: Option<u32>,
+    /// Undelivered notifications kept for `retry`; the oldest are dropped past it (default 100).
+    #[serde(default)]
+    pub notification_queue_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerFailureAction {
+    /// Start without the server and bind again every `server_retry_seconds`.
+    #[default]
+    Retry,
+    /// Start without the server.
+    Disable,
+    /// Stop the daemon from starting.
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationFailureAction {
+    /// Queue the notification and send it again every `notification_retry_seconds`.
+    #[default]
+    Retry,
+    /// Log the failure and drop the notification.
+    Drop,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct MetricsConfig {
     pub enabled: bool,
+    /// Address served in `serve` mode; the Pushgateway URL, e.g.
+    /// `http://pushgateway:9091/metrics/job/yetii`, in `push` mode.
     pub endpoint: String,
+    /// How often metrics are pushed in `push` mode.
+    #[serde(deserialize_with = "units::seconds")]
+    #[schemars(with = "units::DurationValue")]
     pub interval_seconds: u32,
+    #[serde(default)]
+    pub mode: MetricsMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsMode {
+    /// Serve `/metrics` for Prometheus to scrape.
+    #[default]
+    Serve,
+    /// Push the metrics to a Prometheus Pushgateway every `interval_seconds`.
+    Push,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct HealthCheckConfig {
     pub enabled: bool,
     pub endpoint: String,
     pub port: u16,
+    /// How often the daemon checks that each database accepts a connection (default 60);
+    /// `0` turns the check off.
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub database_check_seconds: Option<u32>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct NotificationSettings {
     #[serde(default = "default_true")]
     pub enabled: bool,
@@ -40,19 +137,135 @@ pub struct NotificationSettings {
     pub services: Vec<NotificationServiceConfig>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Per-query replacement for parts of `monitoring.notifications`, applied to that query's events.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct QueryNotificationConfig {
+    /// `false` silences notifications for this query; outcomes are still logged.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub on_success: Option<bool>,
+    pub on_failure: Option<bool>,
+    /// Channels used instead of the global `channels`.
+    pub channels: Option<Vec<NotificationChannel>>,
+    /// Names of global `services` that receive this query's events instead of the usual selection.
+    pub services: Option<Vec<String>>,
+}
+
+impl QueryNotificationConfig {
+    /// Checks channel settings and that every referenced service exists.
+    pub fn validate(
+        &self,
+        query: &str,
+        global: Option<&NotificationSettings>,
+    ) -> Result<(), ConfigError> {
+        let field = FieldPath::query(query).join("notifications");
+        NotificationSettings {
+            enabled: self.enabled,
+            on_failure: false,
+            on_success: false,
+            channels: self.channels.clone().unwrap_or_default(),
+            services: Vec::new(),
+        }
+        .validate()?;
+        for name in self.services.iter().flatten() {
+            let known = global.is_some_and(|settings| {
+                settings
+                    .services
+                    .iter()
+                    .any(|service| service.enabled && &service.name == name)
+            });
+            if !known {
+                return Err(invalid(&format!("{field}.services"), name));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(tag = "type")]
 pub enum NotificationChannel {
+    /// POSTs the event JSON of each query outcome to `url`.
     #[serde(rename = "webhook")]
-    Webhook { url: String },
-    #[serde(rename = "email")]
-    Email {
-        smtp_host: String,
-        recipients: Vec<String>,
+    Webhook {
+        url: String,
+        /// Retries of a failed delivery; 3 attempts after the first unless `attempts` is set.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry: Option<NotificationRetryConfig>,
     },
+    /// Emails a summary of each query outcome to `recipients` over SMTP.
+    #[serde(rename = "email")]
+    Email(EmailChannelConfig),
+    /// Posts a Block Kit message about each query outcome to Slack.
+    #[serde(rename = "slack")]
+    Slack(SlackChannelConfig),
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct SlackChannelConfig {
+    /// Incoming webhook URL; set this or `token` and `channel`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<Secret<String>>,
+    /// Bot token (`xoxb-...`) with `chat:write`, posting through `chat.postMessage`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<Secret<String>>,
+    /// Channel ID or name for `token`, e.g. `C0123456789` or `#data-alerts`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Headline template with `{{placeholder}}` fields of the event; also the notification text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// mrkdwn template shown under the headline instead of the default fields.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Block Kit `blocks` template replacing the whole default layout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocks: Option<Value>,
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct EmailChannelConfig {
+    pub smtp_host: String,
+    /// Defaults to 465 with `tls: implicit`, 587 with `starttls`, and 25 with `none`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp_port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<Secret<String>>,
+    /// Sender address, e.g. `Yetii <yetii@example.com>`; defaults to `username`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    pub recipients: Vec<String>,
+    /// Subject template with `{{placeholder}}` fields of the event.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// Plain-text body template with `{{placeholder}}` fields of the event.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    /// Upgrade the connection with STARTTLS; refuse servers that do not offer it.
+    #[default]
+    Starttls,
+    /// TLS from the first byte (SMTPS).
+    Implicit,
+    /// Plain text, e.g. for a relay on localhost.
+    None,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct NotificationServiceConfig {
     pub name: String,
     #[serde(rename = "type")]
@@ -69,13 +282,13 @@ pub struct NotificationServiceConfig {
     pub retry: Option<NotificationRetryConfig>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum NotificationServiceType {
     Http,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum NotificationEventKind {
     RunSuccess,
@@ -84,6 +297,11 @@ pub enum NotificationEventKind {
     QueryFailure,
     DaemonStarted,
     DaemonStopping,
+    CircuitOpened,
+    CircuitClosed,
+    EndpointFailover,
+    FlowSuccess,
+    FlowFailure,
 }
 
 impl NotificationEventKind {
@@ -95,36 +313,52 @@ impl NotificationEventKind {
             NotificationEventKind::QueryFailure => "query_failure",
             NotificationEventKind::DaemonStarted => "daemon_started",
             NotificationEventKind::DaemonStopping => "daemon_stopping",
+            NotificationEventKind::CircuitOpened => "circuit_opened",
+            NotificationEventKind::CircuitClosed => "circuit_closed",
+            NotificationEventKind::EndpointFailover => "endpoint_failover",
+            NotificationEventKind::FlowSuccess => "flow_success",
+            NotificationEventKind::FlowFailure => "flow_failure",
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct NotificationEndpointConfig {
     pub url: String,
     #[serde(default = "default_http_method")]
-    pub method: String,
+    pub method: HttpMethod,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct NotificationPayloadConfig {
-    #[serde(default = "default_payload_format")]
-    pub format: String,
+    #[serde(default)]
+    pub format: NotificationPayloadFormat,
     #[serde(default)]
     pub template: Value,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct NotificationResponseConfig {
     #[serde(default = "default_success_codes")]
     pub success_codes: Vec<u16>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationPayloadFormat {
+    #[default]
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize, Default)]
 pub struct NotificationRetryConfig {
     pub attempts: Option<u32>,
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
     pub delay_seconds: Option<u32>,
-    pub backoff: Option<String>,
+    pub backoff: Option<RetryBackoff>,
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
     pub timeout_seconds: Option<u32>,
 }
 
@@ -138,7 +372,11 @@ impl MonitoringConfig {
         {
             let url = url::Url::parse(&metrics.endpoint)
                 .map_err(|_| invalid("monitoring.metrics.endpoint", &metrics.endpoint))?;
-            if !matches!(url.scheme(), "http")
+            let schemes: &[&str] = match metrics.mode {
+                MetricsMode::Serve => &["http"],
+                MetricsMode::Push => &["http", "https"],
+            };
+            if !schemes.contains(&url.scheme())
                 || url.host_str().is_none()
                 || url.port_or_known_default().is_none()
             {
@@ -163,6 +401,31 @@ impl MonitoringConfig {
         if let Some(notifications) = &self.notifications {
             notifications.validate()?;
         }
+        if let Some(tracing) = self.tracing.as_ref().filter(|tracing| tracing.enabled) {
+            validate_http_url("monitoring.tracing.endpoint", &tracing.endpoint)?;
+        }
+        if let Some(policy) = &self.failure_policy {
+            for (field, value) in [
+                ("server_retry_seconds", policy.server_retry_seconds),
+                (
+                    "notification_retry_seconds",
+                    policy.notification_retry_seconds,
+                ),
+            ] {
+                if value == Some(0) {
+                    return Err(invalid(
+                        &format!("monitoring.failure_policy.{field}"),
+                        "must be greater than zero",
+                    ));
+                }
+            }
+            if policy.notification_queue_size == Some(0) {
+                return Err(invalid(
+                    "monitoring.failure_policy.notification_queue_size",
+                    "must be greater than zero; use `notifications: drop` to keep none",
+                ));
+            }
+        }
         Ok(())
     }
 }
@@ -175,19 +438,11 @@ impl NotificationSettings {
 
         for channel in &self.channels {
             match channel {
-                NotificationChannel::Webhook { url } => {
+                NotificationChannel::Webhook { url, .. } => {
                     validate_http_url("monitoring.notifications.webhook.url", url)?;
                 }
-                NotificationChannel::Email {
-                    smtp_host,
-                    recipients,
-                } if smtp_host.trim().is_empty() || recipients.is_empty() => {
-                    return Err(invalid(
-                        "monitoring.notifications.email",
-                        "smtp_host and at least one recipient are required",
-                    ));
-                }
-                NotificationChannel::Email { .. } => {}
+                NotificationChannel::Email(email) => email.validate()?,
+                NotificationChannel::Slack(slack) => slack.validate()?,
             }
         }
 
@@ -214,23 +469,11 @@ impl NotificationSettings {
                     "at least one event is required",
                 ));
             }
-            validate_http_method(
-                "monitoring.notifications.services.endpoint.method",
-                &service.endpoint.method,
-            )?;
             validate_http_url(
                 "monitoring.notifications.services.endpoint.url",
                 &service.endpoint.url,
             )?;
             validate_notification_auth(service.auth.as_ref())?;
-            if let Some(payload) = &service.payload
-                && payload.format != "json"
-            {
-                return Err(invalid(
-                    "monitoring.notifications.services.payload.format",
-                    &payload.format,
-                ));
-            }
             if let Some(response) = &service.response
                 && (response.success_codes.is_empty()
                     || response
@@ -243,33 +486,96 @@ impl NotificationSettings {
                     "must contain HTTP status codes from 100 to 599",
                 ));
             }
-            if let Some(retry) = &service.retry
-                && retry
-                    .backoff
-                    .as_deref()
-                    .is_some_and(|backoff| !matches!(backoff, "fixed" | "exponential"))
-            {
+        }
+
+        Ok(())
+    }
+}
+
+impl EmailChannelConfig {
+    /// The sender: `from`, or else `username`.
+    pub fn sender(&self) -> Option<&str> {
+        self.from.as_deref().or(self.username.as_deref())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let field = "monitoring.notifications.email";
+        if self.smtp_host.trim().is_empty() || self.recipients.is_empty() {
+            return Err(invalid(
+                field,
+                "smtp_host and at least one recipient are required",
+            ));
+        }
+        let Some(sender) = self.sender() else {
+            return Err(invalid(
+                &format!("{field}.from"),
+                "from or username is required",
+            ));
+        };
+        for address in std::iter::once(sender).chain(self.recipients.iter().map(String::as_str)) {
+            if address.parse::<lettre::message::Mailbox>().is_err() {
                 return Err(invalid(
-                    "monitoring.notifications.services.retry.backoff",
-                    retry.backoff.as_deref().unwrap_or_default(),
+                    field,
+                    &format!("'{address}' is not an email address"),
                 ));
             }
         }
-
+        if self.username.is_some() != self.password.is_some() {
+            return Err(invalid(field, "username and password must be set together"));
+        }
         Ok(())
     }
 }
 
-fn validate_http_method(field: &str, method: &str) -> Result<(), ConfigError> {
-    let method = method.to_ascii_uppercase();
-    let valid_methods = ["GET", "POST", "PUT", "PATCH", "DELETE"];
-    if valid_methods.contains(&method.as_str()) {
+impl SlackChannelConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        let field = "monitoring.notifications.slack";
+        match (&self.webhook_url, &self.token) {
+            (Some(url), None) => {
+                // Never echo the URL: its path is the credential.
+                validate_http_url(&format!("{field}.webhook_url"), url.expose()).map_err(|_| {
+                    invalid(
+                        &format!("{field}.webhook_url"),
+                        "expected an http:// or https:// URL",
+                    )
+                })?;
+                if self.channel.is_some() {
+                    return Err(invalid(
+                        &format!("{field}.channel"),
+                        "incoming webhooks post to their own channel; channel needs token",
+                    ));
+                }
+            }
+            (None, Some(_)) => {
+                if self
+                    .channel
+                    .as_deref()
+                    .is_none_or(|channel| channel.trim().is_empty())
+                {
+                    return Err(invalid(
+                        &format!("{field}.channel"),
+                        "channel is required with token",
+                    ));
+                }
+            }
+            _ => {
+                return Err(invalid(
+                    field,
+                    "set either webhook_url or token and channel",
+                ));
+            }
+        }
+        if self
+            .blocks
+            .as_ref()
+            .is_some_and(|blocks| !blocks.is_array())
+        {
+            return Err(invalid(
+                &format!("{field}.blocks"),
+                "must be a list of blocks",
+            ));
+        }
         Ok(())
-    } else {
-        Err(ConfigError::InvalidValue {
-            field: field.to_string(),
-            value: method,
-        })
     }
 }
 
@@ -284,12 +590,14 @@ fn validate_http_url(field: &str, value: &str) -> Result<(), ConfigError> {
 
 fn validate_notification_auth(auth: Option<&EndpointAuth>) -> Result<(), ConfigError> {
     match auth {
-        Some(EndpointAuth::Bearer { token, .. }) if token.trim().is_empty() => Err(invalid(
-            "monitoring.notifications.services.auth.token",
-            "token is required",
-        )),
+        Some(EndpointAuth::Bearer { token, .. }) if token.expose().trim().is_empty() => {
+            Err(invalid(
+                "monitoring.notifications.services.auth.token",
+                "token is required",
+            ))
+        }
         Some(EndpointAuth::ApiKey { header_name, token })
-            if header_name.trim().is_empty() || token.trim().is_empty() =>
+            if header_name.trim().is_empty() || token.expose().trim().is_empty() =>
         {
             Err(invalid(
                 "monitoring.notifications.services.auth",
@@ -297,7 +605,7 @@ fn validate_notification_auth(auth: Option<&EndpointAuth>) -> Result<(), ConfigE
             ))
         }
         Some(EndpointAuth::Basic { username, password })
-            if username.trim().is_empty() || password.trim().is_empty() =>
+            if username.trim().is_empty() || password.expose().trim().is_empty() =>
         {
             Err(invalid(
                 "monitoring.notifications.services.auth",
@@ -310,7 +618,7 @@ fn validate_notification_auth(auth: Option<&EndpointAuth>) -> Result<(), ConfigE
             token_url,
             ..
         }) if client_id.trim().is_empty()
-            || client_secret.trim().is_empty()
+            || client_secret.expose().trim().is_empty()
             || token_url.trim().is_empty() =>
         {
             Err(invalid(
@@ -328,7 +636,7 @@ fn validate_notification_auth(auth: Option<&EndpointAuth>) -> Result<(), ConfigE
 
 fn invalid(field: &str, value: &str) -> ConfigError {
     ConfigError::InvalidValue {
-        field: field.to_string(),
+        field: FieldPath::new(field),
         value: value.to_string(),
     }
 }
@@ -337,12 +645,8 @@ fn default_true() -> bool {
     true
 }
 
-fn default_http_method() -> String {
-    "POST".to_string()
-}
-
-fn default_payload_format() -> String {
-    "json".to_string()
+fn default_http_method() -> HttpMethod {
+    HttpMethod::Post
 }
 
 fn default_success_codes() -> Vec<u16> {
@@ -361,11 +665,13 @@ mod tests {
                 enabled: true,
                 endpoint: "http://127.0.0.1:9090/metrics".to_string(),
                 interval_seconds: 30,
+                mode: MetricsMode::Serve,
             }),
             health_check: Some(HealthCheckConfig {
                 enabled: true,
                 endpoint: "/health".to_string(),
                 port: 8080,
+                database_check_seconds: None,
             }),
             notifications: Some(NotificationSettings {
                 enabled: true,
@@ -373,6 +679,7 @@ mod tests {
                 on_success: false,
                 channels: vec![NotificationChannel::Webhook {
                     url: "https://example.test/alert".to_string(),
+                    retry: None,
                 }],
                 services: vec![NotificationServiceConfig {
                     name: "ops".to_string(),
@@ -381,12 +688,12 @@ mod tests {
                     events: vec![NotificationEventKind::QueryFailure],
                     endpoint: NotificationEndpointConfig {
                         url: "https://example.test/events".to_string(),
-                        method: "POST".to_string(),
+                        method: HttpMethod::Post,
                     },
                     auth: None,
                     headers: None,
                     payload: Some(NotificationPayloadConfig {
-                        format: "json".to_string(),
+                        format: NotificationPayloadFormat::Json,
                         template: serde_json::json!({
                             "event": "{{event}}",
                             "query": "{{query_name}}",
@@ -399,11 +706,13 @@ mod tests {
                     retry: Some(NotificationRetryConfig {
                         attempts: Some(3),
                         delay_seconds: Some(1),
-                        backoff: Some("exponential".to_string()),
+                        backoff: Some(RetryBackoff::Exponential),
                         timeout_seconds: Some(10),
                     }),
                 }],
             }),
+            failure_policy: None,
+            tracing: None,
         };
 
         config.validate().unwrap();
@@ -417,13 +726,17 @@ mod tests {
                 enabled: true,
                 endpoint: "http://127.0.0.1:9090/metrics".to_string(),
                 interval_seconds: 0,
+                mode: MetricsMode::Serve,
             }),
             health_check: Some(HealthCheckConfig {
                 enabled: true,
                 endpoint: "health".to_string(),
                 port: 0,
+                database_check_seconds: None,
             }),
             notifications: None,
+            failure_policy: None,
+            tracing: None,
         };
 
         assert!(config.validate().is_err());
@@ -447,7 +760,7 @@ mod tests {
                     events: vec![],
                     endpoint: NotificationEndpointConfig {
                         url: "ftp://example.test/events".to_string(),
-                        method: "POST".to_string(),
+                        method: HttpMethod::Post,
                     },
                     auth: None,
                     headers: None,
@@ -456,6 +769,8 @@ mod tests {
                     retry: None,
                 }],
             }),
+            failure_policy: None,
+            tracing: None,
         };
 
         assert!(config.validate().is_err());
@@ -484,4 +799,23 @@ notifications:
         assert!(notifications.channels.is_empty());
         assert_eq!(1, notifications.services.len());
     }
+
+    #[test]
+    fn keeps_the_slack_webhook_out_of_debug_output_and_errors() {
+        let slack: SlackChannelConfig =
+            serde_yaml::from_str("webhook_url: https://hooks.slack.test/T0/B0/hunter2\n").unwrap();
+        slack.validate().unwrap();
+        assert!(format!("{slack:?}").contains("webhook_url: Some(***)"));
+        assert!(!format!("{slack:?}").contains("hunter2"));
+
+        let slack: SlackChannelConfig =
+            serde_yaml::from_str("webhook_url: hooks.slack.test/T0/B0/hunter2\n").unwrap();
+        assert!(
+            !slack
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains("hunter2")
+        );
+    }
 }
@@ -1,38 +1,150 @@
-use crate::config::ConfigError;
+use crate::config::destination_config::DestinationConfig;
 use crate::config::endpoint_config::EndpointConfig;
+use crate::config::monitor_config::QueryNotificationConfig;
 use crate::config::schedule_config::ScheduleConfig;
-use crate::config::sql_query::SqlQuery;
+use crate::config::sort_config::SortConfig;
+use crate::config::source_config::HttpSourceConfig;
+use crate::config::sql_query::{QueryParameter, QueryValidation, SqlQuery};
 use crate::config::transform_config::TransformConfig;
 use crate::config::utils::default_true;
-use crate::config::watermark_config::{WatermarkConfig, is_state_parameter};
+use crate::config::watermark_config::{IncrementalConfig, WatermarkConfig, is_state_parameter};
+use crate::config::{ConfigError, FieldPath};
+use chrono::{NaiveDate, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 /// Enhanced query config with validation
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct QueryConfig {
     pub name: String,
     pub description: String,
     #[serde(default = "default_true")]
     pub enabled: bool,
+    /// With `enabled: false`, the date (UTC) from which the query runs again, e.g. `2025-02-01`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    pub until: Option<NaiveDate>,
     pub database: Option<String>,
     pub schedule: Option<ScheduleConfig>,
     pub query: SqlQuery,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub watermark: Option<WatermarkConfig>,
+    /// Reads only rows past the highest delivered value of one column; replaces `watermark`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incremental: Option<IncrementalConfig>,
+    /// May start from top-level `transform_profiles` with `extends`.
     #[serde(default)]
     pub transform: TransformConfig,
-    pub endpoint: EndpointConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<SortConfig>,
+    /// Saves delivery progress during a run so an interrupted run resumes where it stopped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<CheckpointConfig>,
+    /// HTTP endpoint receiving the rows; exactly one of `endpoint` and `destination` is required.
+    /// May name an entry of the top-level `endpoints` instead of defining one inline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<EndpointConfig>,
+    /// Non-HTTP sink receiving the rows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<DestinationConfig>,
+    /// Makes this a pull query: records are read from this HTTP source and written to the
+    /// database with `query.sql`, instead of being read from the database.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<HttpSourceConfig>,
+    /// Overrides `monitoring.notifications` for this query's success and failure events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<QueryNotificationConfig>,
 }
 impl QueryConfig {
+    /// Starts a query for `init` and tests. The name and SQL are required; everything else
+    /// starts as YAML that leaves it out would set it.
+    pub(crate) fn builder(name: impl Into<String>, sql: impl Into<String>) -> QueryConfigBuilder {
+        QueryConfigBuilder(QueryConfig {
+            name: name.into(),
+            description: String::new(),
+            enabled: true,
+            until: None,
+            database: None,
+            schedule: None,
+            query: SqlQuery {
+                sql: sql.into(),
+                parameters: None,
+                validation: None,
+                cost_check: None,
+                lint: None,
+            },
+            watermark: None,
+            incremental: None,
+            transform: TransformConfig::default(),
+            sort: None,
+            checkpoint: None,
+            endpoint: None,
+            destination: None,
+            source: None,
+            notifications: None,
+        })
+    }
+
+    /// Whether the query runs today: it is enabled, or its `until` date has arrived.
+    pub fn is_enabled(&self) -> bool {
+        self.is_enabled_on(Utc::now().date_naive())
+    }
+
+    pub fn is_enabled_on(&self, today: NaiveDate) -> bool {
+        self.enabled || self.until.is_some_and(|until| today >= until)
+    }
+
+    /// The `until` date of a disabled query once it has passed, so the query runs again while
+    /// the configuration still says `enabled: false`.
+    pub fn expired_disable(&self, today: NaiveDate) -> Option<NaiveDate> {
+        self.until.filter(|until| !self.enabled && today >= *until)
+    }
+
     pub fn validate(&self) -> Result<(), ConfigError> {
         if self.name.is_empty() {
-            return Err(ConfigError::MissingRequiredField("query.name".to_string()));
+            return Err(ConfigError::MissingRequiredField(FieldPath::new(
+                "query.name",
+            )));
+        }
+        if self.enabled
+            && let Some(until) = self.until
+        {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::query(&self.name).join("until"),
+                value: format!("{until} (only used with enabled: false)"),
+            });
         }
 
         if let Some(schedule) = &self.schedule {
-            schedule.validate()?;
+            schedule.validate().map_err(|error| match error {
+                ConfigError::InvalidSchedule(reason) => ConfigError::InvalidValue {
+                    field: FieldPath::query(&self.name).join("schedule.cron"),
+                    value: reason,
+                },
+                error => error,
+            })?;
         }
 
-        self.query.validate()?;
+        self.query
+            .validate(&FieldPath::query(&self.name).join("query"))?;
+        for (name, parameter) in self.query.parameters.iter().flatten() {
+            parameter.validate(
+                &FieldPath::query(&self.name)
+                    .join("query.parameters")
+                    .key(name),
+            )?;
+        }
+        if let Some(incremental) = &self.incremental {
+            incremental.validate(&self.name)?;
+            let expanded = self.watermark.as_ref().is_some_and(|watermark| {
+                watermark.parameter.as_deref() == Some(IncrementalConfig::PARAMETER)
+            });
+            if self.watermark.is_some() && !expanded {
+                return Err(ConfigError::InvalidValue {
+                    field: FieldPath::query(&self.name).join("incremental"),
+                    value: "set either incremental or watermark".to_string(),
+                });
+            }
+        }
         if let Some(watermark) = &self.watermark {
             watermark.validate(&self.name, self.query.parameters.as_ref())?;
         }
@@ -53,7 +165,10 @@ impl QueryConfig {
         for parameter in state_parameters {
             if !configured_parameters.contains(&parameter) {
                 return Err(ConfigError::InvalidValue {
-                    field: format!("query '{}'.query.parameters.{parameter}.source", self.name),
+                    field: FieldPath::query(&self.name)
+                        .join("query.parameters")
+                        .key(parameter)
+                        .join("source"),
                     value:
                         "state_file parameters require a matching max/max_tuple watermark parameter"
                             .to_string(),
@@ -61,9 +176,144 @@ impl QueryConfig {
             }
         }
 
-        self.transform.validate()?;
-        self.endpoint.validate()?;
+        self.transform
+            .validate(&FieldPath::query(&self.name).join("transform"))?;
+        if let Some(sort) = &self.sort {
+            sort.validate(&self.name)?;
+        }
+        if self
+            .checkpoint
+            .as_ref()
+            .is_some_and(|checkpoint| checkpoint.every_batches == Some(0))
+        {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::query(&self.name).join("checkpoint.every_batches"),
+                value: "0".to_string(),
+            });
+        }
+        if let Some(source) = &self.source {
+            source.validate(&self.name)?;
+            let unsupported = [
+                ("endpoint", self.endpoint.is_some()),
+                ("destination", self.destination.is_some()),
+                ("watermark", self.watermark.is_some()),
+                ("incremental", self.incremental.is_some()),
+                ("sort", self.sort.is_some()),
+                ("checkpoint", self.checkpoint.is_some()),
+            ];
+            if let Some((field, _)) = unsupported.iter().find(|(_, set)| *set) {
+                return Err(ConfigError::InvalidValue {
+                    field: FieldPath::query(&self.name).join(field),
+                    value: "not supported for pull queries with a source".to_string(),
+                });
+            }
+            return Ok(());
+        }
+        match (&self.endpoint, &self.destination) {
+            (Some(endpoint), None) => {
+                endpoint.validate(&FieldPath::query(&self.name).join("endpoint"))?
+            }
+            (None, Some(destination)) => destination.validate(&self.name)?,
+            (None, None) => {
+                return Err(ConfigError::MissingRequiredField(
+                    FieldPath::query(&self.name).join("endpoint"),
+                ));
+            }
+            (Some(_), Some(_)) => {
+                return Err(ConfigError::InvalidValue {
+                    field: FieldPath::query(&self.name).join("destination"),
+                    value: "set either endpoint or destination, not both".to_string(),
+                });
+            }
+        }
 
         Ok(())
     }
 }
+/// Delivery progress is saved in the state file every `every_batches` batches and cleared when
+/// the query's state next advances. A run that finds a checkpoint skips the rows it counts, so
+/// the SQL must return rows in a stable order (`ORDER BY`); watermark pages resume from the
+/// saved watermark and skip only the rows delivered since.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct CheckpointConfig {
+    /// Delivered batches between checkpoints (default 10).
+    pub every_batches: Option<u32>,
+}
+
+/// A [`QueryConfig`] under construction, from [`QueryConfig::builder`].
+pub(crate) struct QueryConfigBuilder(QueryConfig);
+
+impl QueryConfigBuilder {
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.0.description = description.into();
+        self
+    }
+
+    /// Names the database in `databases` the query reads; the first one when unset.
+    pub fn database(mut self, database: impl Into<String>) -> Self {
+        self.0.database = Some(database.into());
+        self
+    }
+
+    pub fn schedule(mut self, schedule: ScheduleConfig) -> Self {
+        self.0.schedule = Some(schedule);
+        self
+    }
+
+    /// Adds a `$name` parameter of the SQL.
+    pub fn parameter(mut self, name: impl Into<String>, parameter: QueryParameter) -> Self {
+        self.0
+            .query
+            .parameters
+            .get_or_insert_with(Default::default)
+            .insert(name.into(), parameter);
+        self
+    }
+
+    pub fn validation(mut self, validation: QueryValidation) -> Self {
+        self.0.query.validation = Some(validation);
+        self
+    }
+
+    pub fn watermark(mut self, watermark: WatermarkConfig) -> Self {
+        self.0.watermark = Some(watermark);
+        self
+    }
+
+    pub fn transform(mut self, transform: TransformConfig) -> Self {
+        self.0.transform = transform;
+        self
+    }
+
+    pub fn endpoint(mut self, endpoint: EndpointConfig) -> Self {
+        self.0.endpoint = Some(endpoint);
+        self
+    }
+
+    /// The query, unvalidated: [`YetiiConfig::validate`](crate::config::yetii::YetiiConfig::validate)
+    /// checks it with the rest of the configuration, e.g. that it has an endpoint or a
+    /// destination.
+    pub fn build(self) -> QueryConfig {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_defaults_match_yaml_that_omits_them() {
+        let built = QueryConfig::builder("orders", "SELECT * FROM orders")
+            .database("erp")
+            .build();
+        let parsed: QueryConfig = serde_yaml::from_str(
+            "name: orders\ndescription: ''\ndatabase: erp\nquery:\n  sql: SELECT * FROM orders\n",
+        )
+        .unwrap();
+        assert_eq!(
+            serde_yaml::to_value(&parsed).unwrap(),
+            serde_yaml::to_value(&built).unwrap()
+        );
+    }
+}
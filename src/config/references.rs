@@ -0,0 +1,395 @@
+use crate::config::{ConfigError, FieldPath};
+use serde_yaml::{Mapping, Value};
+
+/// Named `endpoints`, `auth_profiles`, and `transform_profiles` that queries can reference.
+struct Definitions {
+    endpoints: Mapping,
+    auth_profiles: Mapping,
+    transform_profiles: Mapping,
+}
+
+impl Definitions {
+    fn from_root(root: &Value) -> Result<Self, ConfigError> {
+        let auth_profiles = section(root, "auth_profiles")?;
+        let mut endpoints = section(root, "endpoints")?;
+        for (name, endpoint) in endpoints.iter_mut() {
+            let field = FieldPath::new("endpoints").key(name.as_str().unwrap_or_default());
+            expand_auth_profile(endpoint, &auth_profiles, &field)?;
+        }
+        let declared = section(root, "transform_profiles")?;
+        let mut transform_profiles = declared.clone();
+        for (name, profile) in transform_profiles.iter_mut() {
+            let name = name.as_str().unwrap_or_default().to_string();
+            let field = FieldPath::new("transform_profiles").key(name.as_str());
+            resolve_transform(profile, &declared, &field, &mut vec![name])?;
+        }
+        Ok(Self {
+            endpoints,
+            auth_profiles,
+            transform_profiles,
+        })
+    }
+}
+
+/// Expands every `ref`, `auth_profile`, and `extends` reference in the document so that it
+/// deserializes into plain definitions. Returns whether the document uses references at all.
+pub fn resolve(root: &mut Value) -> Result<bool, ConfigError> {
+    let definitions = Definitions::from_root(root)?;
+    let mut uses_references = ["endpoints", "auth_profiles", "transform_profiles"]
+        .iter()
+        .any(|section| root.get(section).is_some());
+    if let Some(endpoints) = root.get_mut("endpoints") {
+        *endpoints = Value::Mapping(definitions.endpoints.clone());
+    }
+    if let Some(profiles) = root.get_mut("transform_profiles") {
+        *profiles = Value::Mapping(definitions.transform_profiles.clone());
+    }
+
+    if let Some(Value::Sequence(queries)) = root.get_mut("queries") {
+        for query in queries {
+            uses_references |= resolve_query_with(query, &definitions)?;
+        }
+    }
+    Ok(uses_references)
+}
+
+/// Expands the references of a single query against the definitions in `root`.
+pub fn resolve_query(query: &mut Value, root: &Value) -> Result<bool, ConfigError> {
+    resolve_query_with(query, &Definitions::from_root(root)?)
+}
+
+fn resolve_query_with(query: &mut Value, definitions: &Definitions) -> Result<bool, ConfigError> {
+    let field = FieldPath::query(
+        query
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default(),
+    );
+    let mut resolved = false;
+    if let Some(endpoint) = query.get_mut("endpoint") {
+        resolved |= resolve_endpoint(endpoint, definitions, &field.join("endpoint"))?;
+    }
+    if let Some(endpoint) = query
+        .get_mut("source")
+        .and_then(|source| source.get_mut("endpoint"))
+    {
+        resolved |= resolve_endpoint(endpoint, definitions, &field.join("source.endpoint"))?;
+    }
+    if let Some(transform) = query.get_mut("transform") {
+        resolved |= resolve_transform(
+            transform,
+            &definitions.transform_profiles,
+            &field.join("transform"),
+            &mut Vec::new(),
+        )?;
+    }
+    Ok(resolved)
+}
+
+/// Replaces `transform: { extends: [a, b], ... }` with the named profiles applied in order and
+/// the transform's own settings on top. `chain` holds the profiles being expanded, so that
+/// profiles extending each other in a cycle are reported instead of recursing forever.
+fn resolve_transform(
+    transform: &mut Value,
+    profiles: &Mapping,
+    field: &FieldPath,
+    chain: &mut Vec<String>,
+) -> Result<bool, ConfigError> {
+    let Some(extends) = transform
+        .as_mapping_mut()
+        .and_then(|mapping| mapping.remove("extends"))
+    else {
+        return Ok(false);
+    };
+    let invalid = |value: String| ConfigError::InvalidValue {
+        field: field.join("extends"),
+        value,
+    };
+    let names = match &extends {
+        Value::String(name) => vec![name.as_str()],
+        Value::Sequence(names) => names
+            .iter()
+            .map(|name| {
+                name.as_str()
+                    .ok_or_else(|| invalid("profile names must be strings".to_string()))
+            })
+            .collect::<Result<_, _>>()?,
+        _ => {
+            return Err(invalid(
+                "must be a profile name or a list of names".to_string(),
+            ));
+        }
+    };
+
+    let mut merged = Value::Mapping(Mapping::new());
+    for name in names {
+        if chain.iter().any(|expanding| expanding == name) {
+            return Err(invalid(format!(
+                "transform profiles extend each other in a cycle: {} -> {name}",
+                chain.join(" -> ")
+            )));
+        }
+        let mut profile = profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| invalid(format!("unknown transform profile '{name}'")))?;
+        chain.push(name.to_string());
+        resolve_transform(
+            &mut profile,
+            profiles,
+            &FieldPath::new("transform_profiles").key(name),
+            chain,
+        )?;
+        chain.pop();
+        extend_transform(&mut merged, profile);
+    }
+    extend_transform(&mut merged, std::mem::take(transform));
+    *transform = merged;
+    Ok(true)
+}
+
+/// Merges like [`merge`], except that `filters` and `enrich` steps accumulate: the base's
+/// steps run first and the overlay's are appended.
+fn extend_transform(base: &mut Value, mut overlay: Value) {
+    for key in ["filters", "enrich"] {
+        if let (Some(Value::Sequence(steps)), Some(Value::Sequence(more))) =
+            (base.get(key), overlay.get_mut(key))
+        {
+            let appended = std::mem::take(more);
+            more.extend(steps.iter().cloned());
+            more.extend(appended);
+        }
+    }
+    merge(base, overlay);
+}
+
+/// Replaces `endpoint: name` or `endpoint: { ref: name, ... }` with the named endpoint, with
+/// the query's own keys merged over it.
+fn resolve_endpoint(
+    endpoint: &mut Value,
+    definitions: &Definitions,
+    field: &FieldPath,
+) -> Result<bool, ConfigError> {
+    if let Value::String(name) = endpoint {
+        *endpoint = Value::Mapping(Mapping::from_iter([(
+            Value::from("ref"),
+            Value::from(name.as_str()),
+        )]));
+    }
+    let expanded = expand_auth_profile(endpoint, &definitions.auth_profiles, field)?;
+    let Some(reference) = endpoint
+        .as_mapping_mut()
+        .and_then(|mapping| mapping.remove("ref"))
+    else {
+        return Ok(expanded);
+    };
+    let Some(name) = reference.as_str() else {
+        return Err(ConfigError::InvalidValue {
+            field: field.join("ref"),
+            value: "must be the name of an entry in endpoints".to_string(),
+        });
+    };
+    let base = definitions
+        .endpoints
+        .get(name)
+        .ok_or_else(|| ConfigError::InvalidValue {
+            field: field.join("ref"),
+            value: format!("unknown endpoint '{name}'"),
+        })?;
+
+    let overrides = std::mem::replace(endpoint, base.clone());
+    // A query that sets its own auth replaces the shared one rather than merging into it.
+    if overrides.get("auth").is_some()
+        && let Some(mapping) = endpoint.as_mapping_mut()
+    {
+        mapping.remove("auth");
+    }
+    merge(endpoint, overrides);
+    Ok(true)
+}
+
+/// Replaces `auth_profile: name` with `auth:` set to the named profile.
+fn expand_auth_profile(
+    endpoint: &mut Value,
+    auth_profiles: &Mapping,
+    field: &FieldPath,
+) -> Result<bool, ConfigError> {
+    let Some(mapping) = endpoint.as_mapping_mut() else {
+        return Ok(false);
+    };
+    let Some(profile) = mapping.remove("auth_profile") else {
+        return Ok(false);
+    };
+    if mapping.contains_key("auth") {
+        return Err(ConfigError::InvalidValue {
+            field: field.join("auth_profile"),
+            value: "set either auth or auth_profile, not both".to_string(),
+        });
+    }
+    let auth = profile
+        .as_str()
+        .and_then(|name| auth_profiles.get(name))
+        .ok_or_else(|| ConfigError::InvalidValue {
+            field: field.join("auth_profile"),
+            value: format!(
+                "unknown auth profile '{}'",
+                profile.as_str().unwrap_or_default()
+            ),
+        })?;
+    mapping.insert(Value::from("auth"), auth.clone());
+    Ok(true)
+}
+
+fn section(root: &Value, name: &str) -> Result<Mapping, ConfigError> {
+    match root.get(name) {
+        None | Some(Value::Null) => Ok(Mapping::new()),
+        Some(Value::Mapping(mapping)) => Ok(mapping.clone()),
+        Some(_) => Err(ConfigError::InvalidValue {
+            field: FieldPath::new(name),
+            value: "must be a mapping of names to definitions".to_string(),
+        }),
+    }
+}
+
+/// Merges `overlay` into `base`: nested mappings are merged key by key, anything else replaces.
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_named_endpoints_and_auth_profiles_with_query_overrides() {
+        let mut root: Value = serde_yaml::from_str(
+            r#"
+auth_profiles:
+  erp_oauth:
+    type: oauth2
+    client_id: yetii
+    client_secret: secret
+    token_url: https://login.example.com/token
+endpoints:
+  orders_api:
+    url: https://api.example.com/orders
+    method: POST
+    auth_profile: erp_oauth
+    headers:
+      X-Tenant: acme
+queries:
+  - name: orders
+    endpoint: orders_api
+  - name: refunds
+    endpoint:
+      ref: orders_api
+      url: https://api.example.com/refunds
+      headers:
+        X-Source: yetii
+  - name: legacy
+    endpoint:
+      ref: orders_api
+      auth:
+        type: bearer
+        token: legacy-token
+"#,
+        )
+        .unwrap();
+
+        assert!(resolve(&mut root).unwrap());
+        let queries = &root["queries"];
+        assert_eq!(root["endpoints"]["orders_api"], queries[0]["endpoint"]);
+        assert_eq!(queries[0]["endpoint"]["auth"]["type"], "oauth2");
+        let refunds = &queries[1]["endpoint"];
+        assert_eq!(refunds["url"], "https://api.example.com/refunds");
+        assert_eq!(refunds["method"], "POST");
+        assert_eq!(refunds["headers"]["X-Tenant"], "acme");
+        assert_eq!(refunds["headers"]["X-Source"], "yetii");
+        assert_eq!(
+            serde_yaml::from_str::<Value>("{type: bearer, token: legacy-token}").unwrap(),
+            queries[2]["endpoint"]["auth"]
+        );
+    }
+
+    #[test]
+    fn transforms_extend_profiles_in_order_and_append_their_steps() {
+        let mut root: Value = serde_yaml::from_str(
+            r#"
+transform_profiles:
+  masking:
+    filters:
+      - {field: email, condition: not_null}
+    conversions:
+      created_at: {from: timestamp, to: iso8601_string}
+  audit:
+    extends: masking
+    mappings:
+      id: record_id
+queries:
+  - name: orders
+    transform:
+      extends: [audit]
+      filters:
+        - {field: status, condition: equals, value: open}
+      conversions:
+        created_at: {from: timestamp, to: date, format: "%Y-%m-%d"}
+  - name: loop
+    transform:
+      extends: missing
+"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            resolve(&mut root.clone()),
+            Err(ConfigError::InvalidValue { field, value })
+                if field == FieldPath::new("queries.loop.transform.extends")
+                    && value == "unknown transform profile 'missing'"
+        ));
+        root["queries"].as_sequence_mut().unwrap().pop();
+        let mut cyclic = root.clone();
+        assert!(resolve(&mut root).unwrap());
+
+        let transform = &root["queries"][0]["transform"];
+        assert_eq!(transform["mappings"]["id"], "record_id");
+        assert_eq!(transform["filters"][0]["field"], "email");
+        assert_eq!(transform["filters"][1]["field"], "status");
+        assert_eq!(transform["conversions"]["created_at"]["to"], "date");
+        assert_eq!(
+            root["transform_profiles"]["audit"]["filters"][0]["field"],
+            "email"
+        );
+
+        cyclic["transform_profiles"]["masking"]["extends"] = Value::from("audit");
+        assert!(matches!(
+            resolve(&mut cyclic),
+            Err(ConfigError::InvalidValue { value, .. }) if value.contains("cycle")
+        ));
+    }
+
+    #[test]
+    fn unknown_references_name_the_query() {
+        let mut root: Value = serde_yaml::from_str(
+            "queries:\n  - name: orders\n    endpoint:\n      ref: missing_api\n",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            resolve(&mut root),
+            Err(ConfigError::InvalidValue { field, value })
+                if field == FieldPath::new("queries.orders.endpoint.ref") && value == "unknown endpoint 'missing_api'"
+        ));
+    }
+}
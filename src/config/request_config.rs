@@ -1,24 +1,303 @@
-use crate::config::utils::default_request_format;
+use crate::config::units;
+use crate::config::{ConfigError, FieldPath};
+use crate::http::format::{PayloadFormat, is_xml_name};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-#[derive(Debug, Clone, Deserialize, Serialize)]
+use std::collections::HashMap;
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct RequestConfig {
-    #[serde(default = "default_request_format")]
-    pub format: String,
+    #[serde(flatten)]
+    pub body: FormatOptions,
     pub batch_size: Option<u32>,
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
     pub timeout_seconds: Option<u32>,
     pub retry_attempts: Option<u32>,
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
     pub retry_delay_seconds: Option<u32>,
-    pub retry_backoff: Option<String>,
+    pub retry_backoff: Option<RetryBackoff>,
+    /// Fraction of each retry delay that is randomized, from 0.0 to 1.0 (default 0.2).
+    pub retry_jitter: Option<f64>,
+    /// Upper bound for a single retry delay (default 300).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub retry_max_delay_seconds: Option<u32>,
+    /// HTTP statuses that are retried (default 408, 425, 429, 500, 502, 503, 504).
+    pub retry_on_status: Option<Vec<u16>>,
+    /// Whether a batch that still fails after retries stops the query or lets later batches go out.
+    #[serde(default)]
+    pub on_batch_failure: BatchFailurePolicy,
+    /// Pauses delivery to this endpoint after repeated failed batches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
 }
+
+/// `format` and its options, shared by the endpoint request and every destination that writes
+/// batch bodies.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, Serialize)]
+pub struct FormatOptions {
+    /// `json` (default) writes each batch as an array; `ndjson` writes one row per line, `csv`
+    /// one CSV record per row, and `xml` one element per row. The binary `avro` and `protobuf`
+    /// formats need their `avro` or `protobuf` options.
+    #[serde(default)]
+    pub format: PayloadFormat,
+    /// Options for `format: csv`.
+    pub csv: Option<CsvOptions>,
+    /// Options for `format: xml`.
+    pub xml: Option<XmlOptions>,
+    /// Options for `format: avro`.
+    pub avro: Option<AvroOptions>,
+    /// Options for `format: protobuf`.
+    pub protobuf: Option<ProtobufOptions>,
+}
+
+/// Stops sending to an endpoint after consecutive batches fail with timeouts, connection
+/// errors, or retryable statuses, then lets one probe batch through per cool-down until one
+/// succeeds. The state is shared by every query delivering to the same method and URL.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failed batches, after retries, that open the circuit (default 5).
+    pub failure_threshold: Option<u32>,
+    /// How long an open circuit fails batches without sending them (default 60).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub cooldown_seconds: Option<u32>,
+}
+/// How the delay between retries grows.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryBackoff {
+    /// The same delay before every retry.
+    Fixed,
+    /// The delay times the retry number.
+    Linear,
+    /// The delay doubles with each retry.
+    #[default]
+    Exponential,
+}
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchFailurePolicy {
+    /// Stop delivering the query at the first failed batch.
+    #[default]
+    Abort,
+    /// Deliver the remaining batches, then fail the query without advancing its state.
+    Continue,
+}
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, Serialize)]
+pub struct CsvOptions {
+    /// Single ASCII character separating fields (default `,`).
+    pub delimiter: Option<String>,
+    /// Write a header row with the column names (default true).
+    pub header: Option<bool>,
+    #[serde(default)]
+    pub quote: CsvQuoteStyle,
+    /// Exact columns and their order; other fields are left out. Without it, the targets of
+    /// `transform.mappings` come first in mapping order, followed by the remaining fields by name.
+    pub columns: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvQuoteStyle {
+    /// Quote fields containing the delimiter, quotes, or line breaks.
+    #[default]
+    Necessary,
+    Always,
+    /// Quote every field that is not a number.
+    NonNumeric,
+    /// Never quote; fields must not contain the delimiter.
+    Never,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, Serialize)]
+pub struct XmlOptions {
+    /// Document element wrapping the batch (default `rows`).
+    pub root: Option<String>,
+    /// Element written for each row (default `row`).
+    pub record: Option<String>,
+    /// Fields written as attributes of the row element; every other field becomes a child element.
+    pub attributes: Option<Vec<String>>,
+    /// Element or attribute name per field, for fields whose names differ from the partner's
+    /// schema or are not valid XML names.
+    pub names: Option<HashMap<String, String>>,
+    /// Start the document with an `<?xml ...?>` declaration (default true).
+    pub declaration: Option<bool>,
+}
+
+/// Writes each batch as an Avro object container file with the schema embedded.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct AvroOptions {
+    /// Avro schema (`.avsc`) whose top-level type is the record written for each row.
+    pub schema_file: String,
+}
+
+/// Encodes rows as messages of a type from a compiled descriptor set.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct ProtobufOptions {
+    /// Compiled `FileDescriptorSet` (`protoc --include_imports --descriptor_set_out`).
+    pub descriptor_set: String,
+    /// Fully qualified message type, e.g. `orders.v1.Order`.
+    pub message: String,
+    /// Repeated message field that receives the whole batch as one message; without it the body
+    /// is a stream of length-delimited messages, one per row.
+    pub batch_field: Option<String>,
+    /// Drop row fields that the message does not define instead of failing.
+    #[serde(default)]
+    pub ignore_unknown_fields: bool,
+}
+
+impl FormatOptions {
+    /// Checks the format options, and that the binary formats have the options they are
+    /// encoded with.
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        if let Some(csv) = &self.csv {
+            csv.validate(&field.join("csv"))?;
+        }
+        if let Some(xml) = &self.xml {
+            xml.validate(&field.join("xml"))?;
+        }
+        match self.format {
+            PayloadFormat::Avro if self.avro.is_none() => {
+                return Err(ConfigError::MissingRequiredField(field.join("avro")));
+            }
+            PayloadFormat::Protobuf if self.protobuf.is_none() => {
+                return Err(ConfigError::MissingRequiredField(field.join("protobuf")));
+            }
+            _ => {}
+        }
+        let required = self
+            .avro
+            .iter()
+            .map(|avro| ("avro.schema_file", avro.schema_file.as_str()))
+            .chain(self.protobuf.iter().flat_map(|protobuf| {
+                [
+                    ("protobuf.descriptor_set", protobuf.descriptor_set.as_str()),
+                    ("protobuf.message", protobuf.message.as_str()),
+                ]
+            }));
+        for (name, value) in required {
+            if value.trim().is_empty() {
+                return Err(ConfigError::MissingRequiredField(field.join(name)));
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Default for RequestConfig {
     fn default() -> Self {
         Self {
-            format: default_request_format(),
+            body: FormatOptions::default(),
             batch_size: Some(100),
             timeout_seconds: Some(30),
             retry_attempts: Some(3),
             retry_delay_seconds: Some(1),
-            retry_backoff: Some("exponential".to_string()),
+            retry_backoff: Some(RetryBackoff::Exponential),
+            retry_jitter: None,
+            retry_max_delay_seconds: None,
+            retry_on_status: None,
+            on_batch_failure: BatchFailurePolicy::Abort,
+            circuit_breaker: None,
+        }
+    }
+}
+impl CsvOptions {
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        if let Some(delimiter) = self
+            .delimiter
+            .as_ref()
+            .filter(|delimiter| !(delimiter.len() == 1 && delimiter.is_ascii()))
+        {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("delimiter"),
+                value: format!("'{delimiter}' (expected a single ASCII character)"),
+            });
+        }
+        if self
+            .columns
+            .as_ref()
+            .is_some_and(|columns| columns.is_empty())
+        {
+            return Err(ConfigError::MissingRequiredField(field.join("columns")));
+        }
+        Ok(())
+    }
+}
+
+impl XmlOptions {
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        let names = [
+            ("root", self.root.as_ref()),
+            ("record", self.record.as_ref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((field.join(name), value?)))
+        .chain(
+            self.names
+                .iter()
+                .flatten()
+                .map(|(source, name)| (field.join("names").key(source), name)),
+        );
+        for (field, name) in names {
+            if !is_xml_name(name) {
+                return Err(ConfigError::InvalidValue {
+                    field,
+                    value: format!("'{name}' is not a valid XML name"),
+                });
+            }
+        }
+        if let Some(attribute) = self
+            .attributes
+            .iter()
+            .flatten()
+            .find(|attribute| attribute.trim().is_empty())
+        {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("attributes"),
+                value: format!("'{attribute}'"),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl RequestConfig {
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        self.body.validate(field)?;
+        if let Some(jitter) = self.retry_jitter
+            && !(0.0..=1.0).contains(&jitter)
+        {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("retry_jitter"),
+                value: jitter.to_string(),
+            });
+        }
+        if let Some(status) = self
+            .retry_on_status
+            .iter()
+            .flatten()
+            .find(|status| !(100..=599).contains(*status))
+        {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("retry_on_status"),
+                value: status.to_string(),
+            });
+        }
+        if let Some(breaker) = &self.circuit_breaker {
+            for (name, value) in [
+                ("failure_threshold", breaker.failure_threshold),
+                ("cooldown_seconds", breaker.cooldown_seconds),
+            ] {
+                if value == Some(0) {
+                    return Err(ConfigError::InvalidValue {
+                        field: field.join("circuit_breaker").join(name),
+                        value: "0".to_string(),
+                    });
+                }
+            }
         }
+        Ok(())
     }
 }
@@ -1,16 +1,22 @@
 use crate::config::ConfigError;
 use crate::config::utils::default_timezone;
 use crate::config::utils::default_true;
+use chrono::{DateTime, Utc};
+use croner::Cron;
+use croner::errors::CronError;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tokio_cron_scheduler::Job;
 /// Enhanced schedule config with cron validation
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct ScheduleConfig {
     pub cron: String,
     #[serde(default = "default_timezone")]
     pub timezone: String,
     #[serde(default = "default_true")]
     pub enabled: bool,
+    /// Also runs the query once as soon as the daemon has started.
+    #[serde(default)]
+    pub run_on_start: bool,
 }
 impl ScheduleConfig {
     pub fn validate(&self) -> Result<(), ConfigError> {
@@ -20,19 +26,69 @@ impl ScheduleConfig {
     }
 }
 
+/// Fields of a six-field expression, in order.
+const FIELDS: [&str; 6] = [
+    "second",
+    "minute",
+    "hour",
+    "day of month",
+    "month",
+    "day of week",
+];
+
+/// `cron` with the seconds field the scheduler requires, after checking that it parses the way
+/// the scheduler will parse it.
 pub fn normalized_cron(cron: &str) -> Result<String, ConfigError> {
     let parts = cron.split_whitespace().collect::<Vec<_>>();
     let normalized = match parts.len() {
         5 => format!("0 {cron}"),
         6 => cron.to_string(),
-        _ => return Err(ConfigError::InvalidSchedule(cron.to_string())),
+        count => {
+            return Err(ConfigError::InvalidSchedule(format!(
+                "'{cron}' has {count} fields; expected 5, or 6 with seconds first"
+            )));
+        }
     };
 
-    Job::new_async(normalized.clone(), |_uuid, _lock| Box::pin(async {}))
-        .map_err(|_| ConfigError::InvalidSchedule(cron.to_string()))?;
+    parse(&normalized)
+        .map_err(|error| ConfigError::InvalidSchedule(describe(cron, &normalized, error)))?;
     Ok(normalized)
 }
 
+/// The next `count` times after `after` that `cron` fires, in UTC like the scheduler.
+pub fn next_fire_times(
+    cron: &str,
+    after: DateTime<Utc>,
+    count: usize,
+) -> Result<Vec<DateTime<Utc>>, ConfigError> {
+    let schedule = parse(&normalized_cron(cron)?)
+        .map_err(|error| ConfigError::InvalidSchedule(format!("'{cron}': {error}")))?;
+    Ok(schedule.iter_after(after).take(count).collect())
+}
+
+fn parse(normalized: &str) -> Result<Cron, CronError> {
+    Cron::new(normalized)
+        .with_seconds_required()
+        .with_dom_and_dow()
+        .parse()
+}
+
+/// Names the first field that is invalid on its own, since the parser's errors do not.
+fn describe(cron: &str, normalized: &str, error: CronError) -> String {
+    let fields = normalized.split_whitespace().collect::<Vec<_>>();
+    for (index, field) in fields.iter().enumerate() {
+        let mut alone = ["*"; 6];
+        alone[index] = field;
+        if let Err(error) = parse(&alone.join(" ")) {
+            return format!(
+                "'{cron}': the {} field '{field}' is invalid: {error}",
+                FIELDS[index]
+            );
+        }
+    }
+    format!("'{cron}': {error}")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -50,5 +106,29 @@ mod tests {
     #[test]
     fn rejects_invalid_cron() {
         assert!(normalized_cron("not a cron").is_err());
+        let error = normalized_cron("0 99 * * *").unwrap_err().to_string();
+        assert!(error.contains("the hour field '99'"), "{error}");
+        let error = normalized_cron("0 0 * 13 *").unwrap_err().to_string();
+        assert!(error.contains("the month field '13'"), "{error}");
+    }
+
+    #[test]
+    fn computes_the_next_fire_times() {
+        let after = DateTime::parse_from_rfc3339("2025-01-31T23:50:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let times = next_fire_times("0 */6 * * *", after, 3)
+            .unwrap()
+            .iter()
+            .map(DateTime::to_rfc3339)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "2025-02-01T00:00:00+00:00",
+                "2025-02-01T06:00:00+00:00",
+                "2025-02-01T12:00:00+00:00"
+            ],
+            times
+        );
     }
 }
@@ -0,0 +1,69 @@
+//! JSON Schema for configuration files (`yetii schema`), generated from the configuration types
+//! like the [`docs`](super::docs) reference, so it always matches the binary. Editors use it for
+//! completion and validation of `yetii.yaml`, and CI can check files with any JSON Schema tool.
+
+use crate::config::include::{INCLUDE_KEY, QUERIES_DIR_KEY};
+use crate::config::yetii::YetiiConfig;
+use serde_json::{Value, json};
+
+/// The schema as pretty-printed JSON, with the `include:` and `queries_dir:` keys that are
+/// expanded before the configuration is parsed.
+pub fn render() -> String {
+    let mut schema = serde_json::to_value(schemars::schema_for!(YetiiConfig))
+        .expect("configuration schema is always serializable");
+    if let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) {
+        properties.insert(
+            INCLUDE_KEY.to_string(),
+            json!({
+                "description": "Files or directories whose top-level sections are merged into this file, relative to it.",
+                "anyOf": [
+                    { "type": "string" },
+                    { "type": "array", "items": { "type": "string" } }
+                ]
+            }),
+        );
+        properties.insert(
+            QUERIES_DIR_KEY.to_string(),
+            json!({
+                "description": "Directory of YAML files holding one query, or a list of queries, each; relative to this file.",
+                "type": "string"
+            }),
+        );
+    }
+    let mut rendered =
+        serde_json::to_string_pretty(&schema).expect("configuration schema is always serializable");
+    rendered.push('\n');
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_every_top_level_section() {
+        let schema: Value = serde_json::from_str(&render()).unwrap();
+        assert!(
+            schema["$schema"]
+                .as_str()
+                .unwrap()
+                .starts_with("https://json-schema.org/")
+        );
+        let properties = schema["properties"].as_object().unwrap();
+        for section in [
+            "version",
+            "databases",
+            "queries",
+            INCLUDE_KEY,
+            QUERIES_DIR_KEY,
+        ] {
+            assert!(properties.contains_key(section), "{section}");
+        }
+        assert!(
+            schema["$defs"]
+                .as_object()
+                .unwrap()
+                .contains_key("QueryConfig")
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+pub(crate) const REDACTED: &str = "***";
+
+/// A credential from the configuration. `{:?}`, `{}`, and serialization show `***`, so a
+/// logged or rendered config never carries it; [`Secret::expose`] is the only way to read it.
+#[derive(Clone, Default, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl Secret<String> {
+    /// A `${VAR}` reference names where the secret comes from rather than holding it.
+    fn is_reference(&self) -> bool {
+        self.0.starts_with("${") && self.0.ends_with('}')
+    }
+}
+
+impl From<String> for Secret<String> {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+// `${VAR}` references are kept so templates such as `yetii init` output still resolve.
+impl Serialize for Secret<String> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.is_reference() {
+            serializer.serialize_str(&self.0)
+        } else {
+            serializer.serialize_str(REDACTED)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_everything_but_references() {
+        let secret: Secret<String> = serde_yaml::from_str("hunter2").unwrap();
+        assert_eq!("hunter2", secret.expose());
+        assert_eq!("***", format!("{secret:?}"));
+        assert_eq!("***", secret.to_string());
+        assert_eq!("'***'\n", serde_yaml::to_string(&secret).unwrap());
+        assert_eq!(
+            "${DB_PASSWORD}\n",
+            serde_yaml::to_string(&Secret::from("${DB_PASSWORD}")).unwrap()
+        );
+    }
+}
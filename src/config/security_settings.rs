@@ -1,15 +1,21 @@
-use crate::config::ConfigError;
+use crate::config::units;
 use crate::config::utils::default_false;
 use crate::config::utils::default_timeout_seconds;
 use crate::config::utils::default_true;
+use crate::config::{ConfigError, FieldPath};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct SecuritySettings {
     #[serde(default = "default_false")]
     pub encrypt_config: bool,
     #[serde(default = "default_true")]
     pub validate_ssl: bool,
-    #[serde(default = "default_timeout_seconds")]
+    #[serde(
+        default = "default_timeout_seconds",
+        deserialize_with = "units::seconds"
+    )]
+    #[schemars(with = "Option<units::DurationValue>")]
     pub timeout_seconds: Option<u32>,
 }
 impl Default for SecuritySettings {
@@ -23,8 +29,13 @@ impl Default for SecuritySettings {
 }
 impl SecuritySettings {
     pub fn validate(&self) -> Result<(), ConfigError> {
-        if self.timeout_seconds > Some(600) {
-            return Err(ConfigError::InvalidTimeout(self.timeout_seconds));
+        if let Some(timeout) = self.timeout_seconds
+            && timeout > 600
+        {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::new("global_settings.security.timeout_seconds"),
+                value: format!("{timeout} is more than 600 seconds"),
+            });
         }
         Ok(())
     }
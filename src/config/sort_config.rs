@@ -0,0 +1,36 @@
+use crate::config::{ConfigError, FieldPath};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Globally orders delivered rows with a bounded-memory external merge sort.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct SortConfig {
+    /// Sort keys applied in order, using delivered (post-transform) field names.
+    pub by: Vec<SortKey>,
+    /// Rows sorted in memory before a sorted run is spilled to the job workspace.
+    pub memory_rows: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct SortKey {
+    pub field: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl SortConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        if self.by.is_empty() || self.by.iter().any(|key| key.field.trim().is_empty()) {
+            return Err(ConfigError::MissingRequiredField(
+                FieldPath::query(query_name).join("sort.by"),
+            ));
+        }
+        if self.memory_rows == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::query(query_name).join("sort.memory_rows"),
+                value: "0".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use crate::config::endpoint_config::{EndpointConfig, HttpMethod};
+use crate::config::{ConfigError, FieldPath};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// HTTP endpoint read by a pull query; the records it returns are written with `query.sql`.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct HttpSourceConfig {
+    /// Endpoint to read from; `method` must be `GET`.
+    pub endpoint: EndpointConfig,
+    /// Static query-string parameters added to every page request.
+    pub params: Option<HashMap<String, String>>,
+    /// Dot path to the array of records in the response body, e.g. `data.items`;
+    /// the body itself must be the array when omitted.
+    pub records_path: Option<String>,
+    pub pagination: Option<PaginationConfig>,
+    /// Stop after this many pages even if the endpoint reports more.
+    pub max_pages: Option<u32>,
+    /// Records written per database transaction (default 500).
+    pub batch_size: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[serde(tag = "type")]
+pub enum PaginationConfig {
+    /// Numbered pages; stops at the first page with fewer than `page_size` records
+    /// (or no records when `page_size` is not set).
+    #[serde(rename = "page")]
+    Page {
+        #[serde(default = "default_page_param")]
+        page_param: String,
+        #[serde(default = "default_start_page")]
+        start_page: u32,
+        size_param: Option<String>,
+        page_size: Option<u32>,
+    },
+    /// Opaque cursor read from each response and sent with the next request; stops when the
+    /// cursor is missing, null, or empty.
+    #[serde(rename = "cursor")]
+    Cursor {
+        cursor_param: String,
+        /// Dot path to the next cursor in the response body, e.g. `meta.next_cursor`.
+        cursor_path: String,
+        size_param: Option<String>,
+        page_size: Option<u32>,
+    },
+    /// Follows the `rel="next"` URL of the `Link` response header (RFC 8288).
+    #[serde(rename = "link_header")]
+    LinkHeader {
+        size_param: Option<String>,
+        page_size: Option<u32>,
+    },
+}
+
+fn default_page_param() -> String {
+    "page".to_string()
+}
+
+fn default_start_page() -> u32 {
+    1
+}
+
+impl HttpSourceConfig {
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::query(query_name).join("source").join(name);
+        self.endpoint.validate(&field("endpoint"))?;
+        if self.endpoint.method != HttpMethod::Get {
+            return Err(ConfigError::InvalidValue {
+                field: field("endpoint.method"),
+                value: format!("{} (pull sources only support GET)", self.endpoint.method),
+            });
+        }
+        if self
+            .records_path
+            .as_ref()
+            .is_some_and(|path| path.trim().is_empty())
+        {
+            return Err(ConfigError::MissingRequiredField(field("records_path")));
+        }
+        if self.max_pages == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                field: field("max_pages"),
+                value: "0".to_string(),
+            });
+        }
+        if self.batch_size == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                field: field("batch_size"),
+                value: "0".to_string(),
+            });
+        }
+        let (size_param, page_size) = match &self.pagination {
+            None => return Ok(()),
+            Some(PaginationConfig::Page {
+                page_param,
+                size_param,
+                page_size,
+                ..
+            }) => {
+                if page_param.trim().is_empty() {
+                    return Err(ConfigError::MissingRequiredField(field(
+                        "pagination.page_param",
+                    )));
+                }
+                (size_param, page_size)
+            }
+            Some(PaginationConfig::Cursor {
+                cursor_param,
+                cursor_path,
+                size_param,
+                page_size,
+            }) => {
+                if cursor_param.trim().is_empty() {
+                    return Err(ConfigError::MissingRequiredField(field(
+                        "pagination.cursor_param",
+                    )));
+                }
+                if cursor_path.trim().is_empty() {
+                    return Err(ConfigError::MissingRequiredField(field(
+                        "pagination.cursor_path",
+                    )));
+                }
+                (size_param, page_size)
+            }
+            Some(PaginationConfig::LinkHeader {
+                size_param,
+                page_size,
+            }) => (size_param, page_size),
+        };
+        if *page_size == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                field: field("pagination.page_size"),
+                value: "0".to_string(),
+            });
+        }
+        if size_param.is_some() && page_size.is_none() {
+            return Err(ConfigError::MissingRequiredField(
+                field("pagination.page_size").note("required with size_param"),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -1,28 +1,205 @@
-use crate::config::ConfigError;
+use crate::config::units;
+use crate::config::utils::default_true;
+use crate::config::{ConfigError, FieldPath};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct SqlQuery {
     pub sql: String,
     pub parameters: Option<HashMap<String, QueryParameter>>,
     pub validation: Option<QueryValidation>,
+    /// Planner estimates checked with `EXPLAIN` before the query runs (PostgreSQL and MySQL).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_check: Option<CostCheck>,
+    /// What `check-config` checks in `sql`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lint: Option<SqlLint>,
+}
+/// `check-config` parses each query's SQL for the dialect of its database, matches placeholders
+/// with `parameters`, and rejects unfiltered writes.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct SqlLint {
+    /// Parse the SQL; turn off for vendor syntax the parser does not know.
+    #[serde(default = "default_true")]
+    pub syntax: bool,
+    /// Accept `UPDATE` and `DELETE` without `WHERE`, and `TRUNCATE`.
+    #[serde(default)]
+    pub allow_unfiltered_writes: bool,
+}
+impl Default for SqlLint {
+    fn default() -> Self {
+        Self {
+            syntax: true,
+            allow_unfiltered_writes: false,
+        }
+    }
 }
 impl SqlQuery {
-    pub fn validate(&self) -> Result<(), ConfigError> {
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
         if self.sql.trim().is_empty() {
-            return Err(ConfigError::MissingRequiredField("query.sql".to_string()));
+            return Err(ConfigError::MissingRequiredField(field.join("sql")));
+        }
+        if let Some(cost_check) = &self.cost_check {
+            cost_check.validate(&field.join("cost_check"))?;
+        }
+        Ok(())
+    }
+}
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct CostCheck {
+    /// Highest acceptable planner cost: PostgreSQL's total cost or MySQL's `query_cost`.
+    pub max_cost: Option<f64>,
+    /// Highest acceptable row estimate for any single step of the plan.
+    pub max_rows: Option<f64>,
+    #[serde(default)]
+    pub action: CostCheckAction,
+}
+impl CostCheck {
+    fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        if self.max_cost.is_none() && self.max_rows.is_none() {
+            return Err(ConfigError::MissingRequiredField(
+                field.join("max_cost").note("or max_rows"),
+            ));
+        }
+        for (name, limit) in [("max_cost", self.max_cost), ("max_rows", self.max_rows)] {
+            if let Some(limit) = limit
+                && !(limit.is_finite() && limit > 0.0)
+            {
+                return Err(ConfigError::InvalidValue {
+                    field: field.join(name),
+                    value: limit.to_string(),
+                });
+            }
         }
         Ok(())
     }
 }
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// What happens when the planner's estimate exceeds a `cost_check` limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostCheckAction {
+    /// Fail the query without running it.
+    #[default]
+    Block,
+    /// Log a warning and run the query anyway.
+    Warn,
+}
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct QueryParameter {
     #[serde(rename = "type")]
     pub param_type: String,
     pub default: Option<String>,
+    /// Where the value comes from: `state_file[:<watermark>]`, `now`, `env:<VAR>`,
+    /// `flow:<variable>`, `command:<shell command>`, `http:<url>`, or `query:<query name>`.
+    /// Without one, `default` is used.
     pub source: Option<String>,
+    /// How a `command:`, `http:`, or `query:` source is read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_options: Option<ParameterSourceOptions>,
+}
+impl QueryParameter {
+    /// The source read when a job starts, if it is one.
+    pub fn external_source(&self) -> Option<ExternalSource<'_>> {
+        ExternalSource::parse(self.source.as_deref()?)
+    }
+
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        let invalid = |name: &str, value: &str| ConfigError::InvalidValue {
+            field: field.join(name),
+            value: value.to_string(),
+        };
+        match self.external_source() {
+            Some(ExternalSource::Command("")) => {
+                return Err(invalid("source", "command: needs a shell command"));
+            }
+            Some(ExternalSource::Http(url))
+                if !url::Url::parse(url)
+                    .is_ok_and(|url| matches!(url.scheme(), "http" | "https")) =>
+            {
+                return Err(invalid("source", &format!("'{url}' is not an http(s) URL")));
+            }
+            Some(ExternalSource::Query("")) => {
+                return Err(invalid("source", "query: needs a query name"));
+            }
+            Some(_) => {}
+            None if self.source_options.is_some() => {
+                return Err(invalid(
+                    "source_options",
+                    "only command:, http:, and query: sources take options",
+                ));
+            }
+            None => {}
+        }
+        if let Some(options) = &self.source_options {
+            if options.on_error == ParameterSourceErrorAction::UseDefault && self.default.is_none()
+            {
+                return Err(invalid(
+                    "source_options.on_error",
+                    "use_default needs a default",
+                ));
+            }
+            if options
+                .path
+                .as_deref()
+                .is_some_and(|path| path.trim().is_empty())
+            {
+                return Err(invalid("source_options.path", "path must not be empty"));
+            }
+        }
+        Ok(())
+    }
+}
+/// A parameter source read when a job starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalSource<'a> {
+    /// Trimmed standard output of a `sh -c` command.
+    Command(&'a str),
+    /// Body of a GET request, or the value at `source_options.path`.
+    Http(&'a str),
+    /// A column of the first row another query returns.
+    Query(&'a str),
+}
+impl<'a> ExternalSource<'a> {
+    pub fn parse(source: &'a str) -> Option<Self> {
+        if let Some(command) = source.strip_prefix("command:") {
+            Some(Self::Command(command.trim()))
+        } else if let Some(url) = source.strip_prefix("http:") {
+            Some(Self::Http(url.trim()))
+        } else {
+            source
+                .strip_prefix("query:")
+                .map(|name| Self::Query(name.trim()))
+        }
+    }
+}
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, Serialize)]
+pub struct ParameterSourceOptions {
+    /// JSONPath into an `http:` response such as `$.data[0].id`, or the column of a `query:`
+    /// source's row. Without it, the whole trimmed body, or the row's only column.
+    pub path: Option<String>,
+    /// Reuse a resolved value in later jobs of the same process for this long.
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub cache_seconds: Option<u32>,
+    /// Time allowed for the command, request, or query (default 30 seconds).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+    #[serde(default)]
+    pub on_error: ParameterSourceErrorAction,
+}
+/// What happens when a parameter source cannot be read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterSourceErrorAction {
+    /// Fail the query without running it.
+    #[default]
+    Fail,
+    /// Log a warning and use the parameter's `default`.
+    UseDefault,
 }
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct QueryValidation {
     pub strict_mapping: Option<bool>,
     pub warn_unmapped_columns: Option<bool>,
@@ -1,15 +1,25 @@
-use crate::config::ConfigError;
+use crate::config::secret::Secret;
+use crate::config::units;
 use crate::config::utils::default_true;
+use crate::config::{ConfigError, FieldPath};
+use chrono::format::{Item, StrftimeItems};
+use indexmap::IndexMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct TransformConfig {
     #[serde(default = "default_true")]
     pub enabled: bool,
-    pub mappings: Option<HashMap<String, String>>,
+    /// Renames fields, source name to target name; targets keep this order in CSV output.
+    pub mappings: Option<IndexMap<String, String>>,
     pub group_by: Option<String>,
     pub filters: Option<Vec<DataFilter>>,
     pub conversions: Option<HashMap<String, DataConversion>>,
+    /// JSON Lines file that receives rows dropped by `on_error: skip_row` conversions.
+    pub reject_file: Option<String>,
+    /// Lookups that add reference data to each row after conversions and before mappings.
+    pub enrich: Option<Vec<EnrichConfig>>,
 }
 impl Default for TransformConfig {
     fn default() -> Self {
@@ -19,24 +29,378 @@ impl Default for TransformConfig {
             group_by: None,
             filters: None,
             conversions: None,
+            reject_file: None,
+            enrich: None,
         }
     }
 }
 impl TransformConfig {
-    pub fn validate(&self) -> Result<(), ConfigError> {
-        // Add specific validation logic for transformations
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        for (name, conversion) in self.conversions.iter().flatten() {
+            conversion.validate(&field.join("conversions").key(name))?;
+        }
+        if self
+            .reject_file
+            .as_ref()
+            .is_some_and(|path| path.trim().is_empty())
+        {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("reject_file"),
+                value: "path must not be empty".to_string(),
+            });
+        }
+        for (index, enrich) in self.enrich.iter().flatten().enumerate() {
+            enrich.validate(&field.join("enrich").key(index.to_string()))?;
+        }
         Ok(())
     }
 }
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct DataFilter {
     pub field: String,
-    pub condition: String,
+    pub condition: FilterCondition,
     pub value: Option<serde_json::Value>,
 }
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// How a filter tests its field; `equals` and `not_equals` compare with `value`.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterCondition {
+    NotNull,
+    IsNull,
+    #[serde(alias = "eq")]
+    Equals,
+    #[serde(alias = "ne")]
+    NotEquals,
+}
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, Serialize)]
 pub struct DataConversion {
     pub from: String,
-    pub to: String,
-    pub format: Option<String>,
+    /// The `to` type, with the `format` it accepts.
+    #[serde(flatten)]
+    pub target: ConversionTarget,
+    /// Decimal separator used by the source, e.g. `,` for `1.234,56`.
+    pub decimal_separator: Option<char>,
+    /// Digit grouping separator used by the source, e.g. `.` for `1.234,56`.
+    pub thousands_separator: Option<char>,
+    /// Source date patterns tried in order, e.g. `%d/%m/%Y`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_formats: Vec<String>,
+    /// `lenient` trims surrounding text such as currency symbols and trailing time parts.
+    #[serde(default)]
+    pub mode: ConversionMode,
+    /// What to do with a row whose value cannot be converted.
+    #[serde(default)]
+    pub on_error: ConversionErrorAction,
+}
+impl DataConversion {
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        let separators = [self.decimal_separator, self.thousands_separator];
+        for separator in separators.into_iter().flatten() {
+            if separator.is_ascii_digit() || separator == '-' || separator == '+' {
+                return Err(ConfigError::InvalidValue {
+                    field: field.clone(),
+                    value: format!("'{separator}' cannot be a number separator"),
+                });
+            }
+        }
+        if self.thousands_separator == Some(self.decimal_separator.unwrap_or('.')) {
+            return Err(ConfigError::InvalidValue {
+                field: field.clone(),
+                value: "decimal_separator and thousands_separator must differ".to_string(),
+            });
+        }
+        if let ConversionTarget::Date {
+            format: Some(format),
+        }
+        | ConversionTarget::Datetime {
+            format: Some(format),
+        } = &self.target
+            && StrftimeItems::new(format).any(|item| item == Item::Error)
+        {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("format"),
+                value: format!("'{format}' is not a valid strftime pattern"),
+            });
+        }
+        if self
+            .input_formats
+            .iter()
+            .any(|pattern| pattern.trim().is_empty())
+        {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("input_formats"),
+                value: "patterns must not be empty".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+/// Type a conversion produces, named by `to`, with the `format` of the targets that take one.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(tag = "to", rename_all = "snake_case")]
+pub enum ConversionTarget {
+    #[default]
+    #[serde(alias = "text", alias = "iso8601_string")]
+    String,
+    #[serde(alias = "int")]
+    Integer,
+    #[serde(alias = "float", alias = "double", alias = "decimal")]
+    Number,
+    #[serde(alias = "boolean")]
+    Bool,
+    Date {
+        /// Output pattern in chrono strftime syntax (default `%Y-%m-%d`).
+        format: Option<String>,
+    },
+    #[serde(alias = "timestamp")]
+    Datetime {
+        /// Output pattern in chrono strftime syntax (default `%Y-%m-%dT%H:%M:%S%.fZ`).
+        format: Option<String>,
+    },
+    #[serde(alias = "uom")]
+    UnitOfMeasure,
+    Country {
+        #[serde(default)]
+        format: CountryFormat,
+    },
+    Currency,
+    #[serde(alias = "ean")]
+    Gtin {
+        /// The code is kept at its own length without one.
+        format: Option<GtinFormat>,
+    },
+}
+impl ConversionTarget {
+    /// The `to` name, as in conversion errors.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConversionTarget::String => "string",
+            ConversionTarget::Integer => "integer",
+            ConversionTarget::Number => "number",
+            ConversionTarget::Bool => "bool",
+            ConversionTarget::Date { .. } => "date",
+            ConversionTarget::Datetime { .. } => "datetime",
+            ConversionTarget::UnitOfMeasure => "unit_of_measure",
+            ConversionTarget::Country { .. } => "country",
+            ConversionTarget::Currency => "currency",
+            ConversionTarget::Gtin { .. } => "gtin",
+        }
+    }
+}
+/// ISO 3166 code a `country` conversion produces.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CountryFormat {
+    #[default]
+    Alpha2,
+    Alpha3,
+}
+/// Length a `gtin` conversion pads its codes to.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GtinFormat {
+    /// Zero-padded to 14 digits.
+    Gtin14,
+}
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionMode {
+    #[default]
+    Strict,
+    Lenient,
+}
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionErrorAction {
+    /// Fail the query.
+    #[default]
+    Fail,
+    /// Drop the row, appending it to `transform.reject_file` when configured.
+    SkipRow,
+    /// Replace the value with null.
+    SetNull,
+    /// Deliver the original value unchanged.
+    Keep,
+}
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+#[serde(tag = "type")]
+pub enum EnrichConfig {
+    #[serde(rename = "redis")]
+    Redis(RedisLookupConfig),
+    #[serde(rename = "http")]
+    Http(HttpLookupConfig),
+}
+impl EnrichConfig {
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        match self {
+            EnrichConfig::Redis(redis) => redis.validate(field),
+            EnrichConfig::Http(http) => http.validate(field),
+        }
+    }
+
+    /// The key (Redis) or URL (HTTP) template rendered from each row.
+    pub fn template(&self) -> &str {
+        match self {
+            EnrichConfig::Redis(redis) => &redis.key,
+            EnrichConfig::Http(http) => &http.url,
+        }
+    }
+
+    pub fn fields(&self) -> Option<&[String]> {
+        match self {
+            EnrichConfig::Redis(redis) => redis.fields.as_deref(),
+            EnrichConfig::Http(http) => http.fields.as_deref(),
+        }
+    }
+
+    pub fn target(&self) -> Option<&str> {
+        match self {
+            EnrichConfig::Redis(redis) => redis.target.as_deref(),
+            EnrichConfig::Http(http) => http.target.as_deref(),
+        }
+    }
+
+    pub fn on_missing(&self) -> LookupMissAction {
+        match self {
+            EnrichConfig::Redis(redis) => redis.on_missing,
+            EnrichConfig::Http(http) => http.on_missing,
+        }
+    }
+}
+/// Looks up one Redis key per row and merges the stored record into the row.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct RedisLookupConfig {
+    /// `redis://` or `rediss://` URL.
+    pub url: Secret<String>,
+    /// Key template, e.g. `customer:{{customer_id}}`.
+    pub key: String,
+    /// How the value is stored.
+    #[serde(default)]
+    pub value: RedisValueType,
+    /// Fields copied from the record; all fields when omitted.
+    pub fields: Option<Vec<String>>,
+    /// Field that receives the record as an object, or the raw value for `string`;
+    /// record fields are merged into the row when omitted.
+    pub target: Option<String>,
+    #[serde(default)]
+    pub on_missing: LookupMissAction,
+    /// Connection and command timeout (default 30).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+}
+impl RedisLookupConfig {
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        crate::config::destination_config::validate_redis_url(
+            self.url.expose(),
+            &field.join("url"),
+        )?;
+        crate::config::destination_config::validate_key_template(&self.key, &field.join("key"))?;
+        if self.value == RedisValueType::String && self.target.is_none() {
+            return Err(ConfigError::MissingRequiredField(
+                field.join("target").note("required for value: string"),
+            ));
+        }
+        if self.value == RedisValueType::String && self.fields.is_some() {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("fields"),
+                value: "not supported for value: string".to_string(),
+            });
+        }
+        if self.timeout_seconds == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                field: field.join("timeout_seconds"),
+                value: "0".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+/// Requests one URL per distinct row with `GET` and merges the JSON response into the row.
+/// Responses are cached for the life of the process and revalidated with `If-None-Match` or
+/// `If-Modified-Since` once they are older than `cache_ttl_seconds`.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct HttpLookupConfig {
+    /// `http://` or `https://` URL template, e.g. `https://crm/api/customers/{{customer_id}}`;
+    /// row values are percent-encoded.
+    pub url: String,
+    /// Extra request headers, e.g. `Authorization`.
+    pub headers: Option<HashMap<String, String>>,
+    /// Fields copied from the response; all fields when omitted.
+    pub fields: Option<Vec<String>>,
+    /// Field that receives the response; its fields are merged into the row when omitted.
+    pub target: Option<String>,
+    /// Applies to `404 Not Found` and `null` responses as well as rows with a null key field.
+    #[serde(default)]
+    pub on_missing: LookupMissAction,
+    /// Request timeout (default 30).
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub timeout_seconds: Option<u32>,
+    /// How long a response is reused without contacting the endpoint (default 300); `0`
+    /// revalidates on every run.
+    #[serde(default, deserialize_with = "units::seconds")]
+    #[schemars(with = "Option<units::DurationValue>")]
+    pub cache_ttl_seconds: Option<u32>,
+    /// Responses kept in memory (default 10000); the oldest are dropped first.
+    pub cache_max_entries: Option<u32>,
+    /// Requests in flight at once (default 8).
+    pub concurrency: Option<u32>,
+}
+impl HttpLookupConfig {
+    pub fn validate(&self, field: &FieldPath) -> Result<(), ConfigError> {
+        crate::config::destination_config::validate_key_template(&self.url, &field.join("url"))?;
+        let placeholders = crate::sinks::redis::template_fields(&self.url)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|name| (name.to_string(), serde_json::Value::from(1)))
+            .collect();
+        let sample = crate::sinks::redis::render_key(&self.url, &placeholders).unwrap_or_default();
+        match url::Url::parse(&sample) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => {}
+            _ => {
+                return Err(ConfigError::InvalidValue {
+                    field: field.join("url"),
+                    value: "expected an http:// or https:// URL".to_string(),
+                });
+            }
+        }
+        for (name, value) in [
+            ("timeout_seconds", self.timeout_seconds),
+            ("cache_max_entries", self.cache_max_entries),
+            ("concurrency", self.concurrency),
+        ] {
+            if value == Some(0) {
+                return Err(ConfigError::InvalidValue {
+                    field: field.join(name),
+                    value: "0".to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedisValueType {
+    /// A Redis hash (`HGETALL`).
+    #[default]
+    Hash,
+    /// A JSON object stored as a string (`GET`).
+    Json,
+    /// A plain string stored under `target` (`GET`).
+    String,
+}
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LookupMissAction {
+    /// Leave the row unchanged.
+    #[default]
+    Keep,
+    /// Set `target`, or each listed field, to null.
+    Null,
+    /// Drop the row.
+    SkipRow,
+    /// Fail the query.
+    Fail,
 }
@@ -0,0 +1,220 @@
+//! Durations (`90s`, `1h30m`) and sizes (`100MB`) for fields whose name carries the unit of
+//! their plain integer form, so `timeout_seconds: 90` and `timeout_seconds: 1m30s` are the same.
+//!
+//! Fields use `#[serde(deserialize_with = "units::seconds")]` (or `minutes`, `megabytes`) and
+//! keep their integer type; the schema documents them with [`DurationValue`] or [`SizeValue`].
+//! Durations are parsed by `humantime` and sizes by `parse-size`.
+
+use parse_size::Config as SizeConfig;
+use schemars::JsonSchema;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+
+const SIZES: SizeConfig = SizeConfig::new().with_binary();
+
+/// A whole number in the unit named by the field, or a duration such as `30s`, `5m`,
+/// `1h30m`, or `2d`.
+#[derive(JsonSchema)]
+#[serde(untagged)]
+#[allow(dead_code)]
+pub enum DurationValue {
+    Number(u64),
+    Text(String),
+}
+
+/// A whole number of MB, or a size such as `100MB` or `2GB` that is a whole number of MiB;
+/// multiples are binary, so `1GB` is 1024 MB.
+#[derive(JsonSchema)]
+#[serde(untagged)]
+#[allow(dead_code)]
+pub enum SizeValue {
+    Number(u64),
+    Text(String),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Raw {
+    Number(u64),
+    Text(String),
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Unit {
+    Seconds,
+    Minutes,
+    Megabytes,
+}
+
+impl Unit {
+    fn name(self) -> &'static str {
+        match self {
+            Unit::Seconds => "seconds",
+            Unit::Minutes => "minutes",
+            Unit::Megabytes => "MB",
+        }
+    }
+
+    /// Size of one unit in nanoseconds or bytes.
+    fn scale(self) -> u128 {
+        match self {
+            Unit::Seconds => 1_000_000_000,
+            Unit::Minutes => 60_000_000_000,
+            Unit::Megabytes => 1 << 20,
+        }
+    }
+}
+
+impl Raw {
+    fn into_units(self, unit: Unit) -> Result<u64, String> {
+        let text = match self {
+            Raw::Number(number) => return Ok(number),
+            Raw::Text(text) => text,
+        };
+        if let Ok(number) = text.trim().parse::<u64>() {
+            return Ok(number);
+        }
+        let amount = match unit {
+            Unit::Seconds | Unit::Minutes => humantime::parse_duration(text.trim())
+                .map(|duration| duration.as_nanos())
+                .map_err(|error| error.to_string()),
+            Unit::Megabytes => SIZES
+                .parse_size(text.trim())
+                .map(u128::from)
+                .map_err(|error| error.to_string()),
+        }
+        .map_err(|reason| format!("invalid value '{text}': {reason}"))?;
+        if amount % unit.scale() != 0 {
+            return Err(format!(
+                "invalid value '{text}': not a whole number of {}",
+                unit.name()
+            ));
+        }
+        u64::try_from(amount / unit.scale())
+            .map_err(|_| format!("invalid value '{text}': too large"))
+    }
+}
+
+/// Implemented for the integer types (and their `Option`s) that unit fields keep.
+pub(crate) trait UnitField: Sized {
+    fn deserialize_in<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        unit: Unit,
+    ) -> Result<Self, D::Error>;
+}
+
+macro_rules! unit_field {
+    ($($integer:ty),*) => {$(
+        impl UnitField for $integer {
+            fn deserialize_in<'de, D: Deserializer<'de>>(
+                deserializer: D,
+                unit: Unit,
+            ) -> Result<Self, D::Error> {
+                let units = Raw::deserialize(deserializer)?
+                    .into_units(unit)
+                    .map_err(D::Error::custom)?;
+                <$integer>::try_from(units).map_err(|_| {
+                    D::Error::custom(format!("{units} {} is too large", unit.name()))
+                })
+            }
+        }
+
+        impl UnitField for Option<$integer> {
+            fn deserialize_in<'de, D: Deserializer<'de>>(
+                deserializer: D,
+                unit: Unit,
+            ) -> Result<Self, D::Error> {
+                match Option::<Raw>::deserialize(deserializer)? {
+                    Some(raw) => {
+                        let units = raw.into_units(unit).map_err(D::Error::custom)?;
+                        <$integer>::try_from(units).map(Some).map_err(|_| {
+                            D::Error::custom(format!("{units} {} is too large", unit.name()))
+                        })
+                    }
+                    None => Ok(None),
+                }
+            }
+        }
+    )*};
+}
+
+unit_field!(u32, u64);
+
+pub(crate) fn seconds<'de, D: Deserializer<'de>, T: UnitField>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    T::deserialize_in(deserializer, Unit::Seconds)
+}
+
+pub(crate) fn minutes<'de, D: Deserializer<'de>, T: UnitField>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    T::deserialize_in(deserializer, Unit::Minutes)
+}
+
+pub(crate) fn megabytes<'de, D: Deserializer<'de>, T: UnitField>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    T::deserialize_in(deserializer, Unit::Megabytes)
+}
+
+/// Parses a whole number of seconds or a duration such as `30d`, for command-line values.
+pub(crate) fn parse_seconds(text: &str) -> Result<u64, String> {
+    Raw::Text(text.to_string()).into_units(Unit::Seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Fields {
+        #[serde(default, deserialize_with = "seconds")]
+        timeout_seconds: Option<u32>,
+        #[serde(deserialize_with = "minutes")]
+        job_timeout_minutes: u32,
+        #[serde(default, deserialize_with = "megabytes")]
+        quota_mb: Option<u64>,
+    }
+
+    fn parse(yaml: &str) -> Result<Fields, String> {
+        serde_yaml::from_str(yaml).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn accepts_integers_durations_and_sizes() {
+        let legacy = parse("timeout_seconds: 90\njob_timeout_minutes: 30\nquota_mb: 100").unwrap();
+        assert_eq!(Some(90), legacy.timeout_seconds);
+        assert_eq!(30, legacy.job_timeout_minutes);
+        assert_eq!(Some(100), legacy.quota_mb);
+
+        let typed =
+            parse("timeout_seconds: 1m30s\njob_timeout_minutes: 1h 30m\nquota_mb: 2GB").unwrap();
+        assert_eq!(Some(90), typed.timeout_seconds);
+        assert_eq!(90, typed.job_timeout_minutes);
+        assert_eq!(Some(2048), typed.quota_mb);
+
+        let omitted = parse("job_timeout_minutes: '45'").unwrap();
+        assert_eq!(None, omitted.timeout_seconds);
+        assert_eq!(45, omitted.job_timeout_minutes);
+    }
+
+    #[test]
+    fn rejects_unknown_units_and_fractions_of_the_field_unit() {
+        assert!(
+            parse("timeout_seconds: 30x\njob_timeout_minutes: 1")
+                .unwrap_err()
+                .contains("unknown time unit \"x\"")
+        );
+        assert!(
+            parse("job_timeout_minutes: 90s")
+                .unwrap_err()
+                .contains("not a whole number of minutes")
+        );
+        assert!(
+            parse("job_timeout_minutes: 1\nquota_mb: 512KB")
+                .unwrap_err()
+                .contains("not a whole number of MB")
+        );
+    }
+}
@@ -1,9 +1,12 @@
-use crate::config::ConfigError;
 use crate::config::sql_query::QueryParameter;
+use crate::config::{ConfigError, FieldPath};
+use chrono::{FixedOffset, LocalResult, NaiveDateTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum WatermarkStrategy {
     Max,
@@ -11,7 +14,7 @@ pub enum WatermarkStrategy {
     None,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct WatermarkConfig {
     pub strategy: WatermarkStrategy,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -24,6 +27,11 @@ pub struct WatermarkConfig {
     pub parameters: Option<Vec<String>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub page_size: Option<usize>,
+    /// Zone of the local wall-clock time in `timestamp` cursor columns without an offset,
+    /// e.g. `Europe/Berlin` or `+02:00`. Around a daylight saving change the stored value is
+    /// read back from before the repeated hour.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
 }
 
 impl WatermarkConfig {
@@ -106,6 +114,25 @@ impl WatermarkConfig {
         if self.page_size == Some(0) {
             return Err(invalid(query_name, "page_size must be greater than zero"));
         }
+        if let Some(timezone) = &self.timezone {
+            if WatermarkTimezone::parse(timezone).is_none() {
+                return Err(invalid(
+                    query_name,
+                    &format!("timezone '{timezone}' is not a time zone name or UTC offset"),
+                ));
+            }
+            let has_timestamp = self.cursor_parameters().into_iter().any(|name| {
+                parameters
+                    .and_then(|parameters| parameters.get(name))
+                    .is_some_and(|parameter| is_timestamp_type(&parameter.param_type))
+            });
+            if !has_timestamp {
+                return Err(invalid(
+                    query_name,
+                    "timezone applies to timestamp cursors, and none is configured",
+                ));
+            }
+        }
 
         Ok(())
     }
@@ -124,6 +151,10 @@ impl WatermarkConfig {
         }
     }
 
+    pub fn timezone(&self) -> Option<WatermarkTimezone> {
+        self.timezone.as_deref().and_then(WatermarkTimezone::parse)
+    }
+
     pub fn cursor_parameters(&self) -> Vec<&str> {
         match self.strategy {
             WatermarkStrategy::Max => self.parameter.iter().map(String::as_str).collect(),
@@ -138,6 +169,169 @@ impl WatermarkConfig {
     }
 }
 
+/// How the wall-clock values of `timestamp` cursor columns map to instants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatermarkTimezone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl WatermarkTimezone {
+    /// An IANA name such as `Europe/Berlin`, `UTC`, or a fixed offset such as `+02:00`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        match text.parse::<FixedOffset>() {
+            Ok(offset) => Some(Self::Fixed(offset)),
+            Err(_) => text.parse::<Tz>().ok().map(Self::Named),
+        }
+    }
+
+    /// For a local time the clocks pass twice, how far apart the two passes are.
+    pub fn repeated_by(&self, local: NaiveDateTime) -> Option<TimeDelta> {
+        match self {
+            Self::Named(zone) => match zone.from_local_datetime(&local) {
+                LocalResult::Ambiguous(earliest, latest) => Some(latest - earliest),
+                _ => None,
+            },
+            Self::Fixed(_) => None,
+        }
+    }
+
+    /// Whether the clocks jump over `local`, e.g. 02:30 on the night they are put forward.
+    pub fn skips(&self, local: NaiveDateTime) -> bool {
+        match self {
+            Self::Named(zone) => matches!(zone.from_local_datetime(&local), LocalResult::None),
+            Self::Fixed(_) => false,
+        }
+    }
+}
+
+pub fn is_timestamp_type(param_type: &str) -> bool {
+    matches!(
+        param_type.to_ascii_lowercase().as_str(),
+        "timestamp" | "datetime"
+    )
+}
+
+/// Shorthand for a single-column `max` watermark: the query's SQL is wrapped to read only rows
+/// past the stored value, ordered by `column`, so it needs no `WHERE` or parameter of its own.
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
+pub struct IncrementalConfig {
+    /// Column whose highest delivered value is stored, e.g. `updated_at` or `id`.
+    pub column: String,
+    #[serde(rename = "type")]
+    pub cursor_type: CursorType,
+    /// Value used before anything is stored. Defaults to `0` for integers,
+    /// `1970-01-01T00:00:00Z` for timestamps, and `1970-01-01` for dates; required otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial: Option<String>,
+    /// Zone of local wall-clock `timestamp` values; see `watermark.timezone`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+/// Type of an `incremental` cursor column.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CursorType {
+    #[serde(alias = "int", alias = "bigint", alias = "long")]
+    Integer,
+    #[serde(alias = "double")]
+    Float,
+    #[serde(alias = "numeric")]
+    Decimal,
+    #[serde(alias = "datetime")]
+    Timestamp,
+    Date,
+    Time,
+}
+
+impl CursorType {
+    /// The query parameter type the cursor is bound as.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CursorType::Integer => "integer",
+            CursorType::Float => "float",
+            CursorType::Decimal => "decimal",
+            CursorType::Timestamp => "timestamp",
+            CursorType::Date => "date",
+            CursorType::Time => "time",
+        }
+    }
+}
+
+impl IncrementalConfig {
+    /// Query parameter, and state watermark, that holds the stored value.
+    pub const PARAMETER: &'static str = "incremental_watermark";
+
+    pub fn validate(&self, query_name: &str) -> Result<(), ConfigError> {
+        let field = |name: &str| FieldPath::query(query_name).join("incremental").join(name);
+        if !crate::config::destination_config::is_sql_identifier(&self.column) {
+            return Err(ConfigError::InvalidValue {
+                field: field("column"),
+                value: format!("{} (must be a plain column name)", self.column),
+            });
+        }
+        if let Some(timezone) = &self.timezone
+            && (WatermarkTimezone::parse(timezone).is_none()
+                || self.cursor_type != CursorType::Timestamp)
+        {
+            return Err(ConfigError::InvalidValue {
+                field: field("timezone"),
+                value: format!("{timezone} (needs a time zone and a timestamp cursor)"),
+            });
+        }
+        if self.initial_value().is_none() {
+            return Err(ConfigError::MissingRequiredField(field("initial").note(
+                format!("no default for type {}", self.cursor_type.as_str()),
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn initial_value(&self) -> Option<String> {
+        let default = match self.cursor_type {
+            CursorType::Integer => Some("0"),
+            CursorType::Timestamp => Some("1970-01-01T00:00:00Z"),
+            CursorType::Date => Some("1970-01-01"),
+            CursorType::Float | CursorType::Decimal | CursorType::Time => None,
+        };
+        self.initial.clone().or(default.map(str::to_string))
+    }
+
+    /// The state parameter and `max` watermark the shorthand stands for.
+    pub fn expand(&self) -> (QueryParameter, WatermarkConfig) {
+        (
+            QueryParameter {
+                param_type: self.cursor_type.as_str().to_string(),
+                default: self.initial_value(),
+                source: Some("state_file".to_string()),
+                source_options: None,
+            },
+            WatermarkConfig {
+                strategy: WatermarkStrategy::Max,
+                column: Some(self.column.clone()),
+                parameter: Some(Self::PARAMETER.to_string()),
+                columns: None,
+                parameters: None,
+                page_size: None,
+                timezone: self.timezone.clone(),
+            },
+        )
+    }
+
+    /// Wraps `sql` to read the rows past the stored value in cursor order.
+    pub fn scope_sql(&self, sql: &str) -> String {
+        let sql = sql.trim().trim_end_matches(';').trim_end();
+        // No `AS` before the alias: Oracle rejects it for tables.
+        format!(
+            "SELECT * FROM (\n{sql}\n) yetii_incremental WHERE {column} > ${parameter} ORDER BY {column}",
+            column = self.column,
+            parameter = Self::PARAMETER
+        )
+    }
+}
+
 pub fn is_state_parameter(parameter: &QueryParameter) -> bool {
     parameter
         .source
@@ -211,7 +405,7 @@ fn has_duplicates(values: &[String]) -> bool {
 
 fn invalid(query_name: &str, reason: &str) -> ConfigError {
     ConfigError::InvalidValue {
-        field: format!("query '{query_name}'.watermark"),
+        field: FieldPath::query(query_name).join("watermark"),
         value: reason.to_string(),
     }
 }
@@ -225,6 +419,7 @@ mod tests {
             param_type: param_type.to_string(),
             default: Some("0".to_string()),
             source: Some("state_file".to_string()),
+            source_options: None,
         }
     }
 
@@ -237,6 +432,7 @@ mod tests {
             columns: None,
             parameters: None,
             page_size: None,
+            timezone: None,
         };
         let mut parameters = HashMap::new();
         parameters.insert("last_id".to_string(), state_parameter("bigint"));
@@ -257,6 +453,7 @@ mod tests {
             columns: None,
             parameters: None,
             page_size: None,
+            timezone: None,
         };
 
         assert!(watermark.validate("orders", None).is_err());
@@ -271,6 +468,7 @@ mod tests {
             columns: None,
             parameters: None,
             page_size: None,
+            timezone: None,
         };
         let mut parameters = HashMap::new();
         parameters.insert("cursor".to_string(), state_parameter("boolean"));
@@ -295,6 +493,7 @@ mod tests {
                 "last_id".to_string(),
             ]),
             page_size: Some(1000),
+            timezone: None,
         };
         let mut parameters = HashMap::new();
         parameters.insert("last_tenant".to_string(), state_parameter("bigint"));
@@ -317,10 +516,29 @@ mod tests {
             columns: Some(vec!["updated_at".to_string(), "id".to_string()]),
             parameters: Some(vec!["last_updated".to_string()]),
             page_size: Some(100),
+            timezone: None,
         };
         assert!(watermark.validate("orders", None).is_err());
 
         watermark.parameters = Some(vec!["cursor".to_string(), "cursor".to_string()]);
         assert!(watermark.validate("orders", None).is_err());
     }
+
+    #[test]
+    fn incremental_type_is_checked_when_parsed() {
+        let incremental: IncrementalConfig =
+            serde_yaml::from_str("column: updated_at\ntype: datetime\n").unwrap();
+        assert_eq!(CursorType::Timestamp, incremental.cursor_type);
+        assert_eq!(
+            Some("1970-01-01T00:00:00Z".to_string()),
+            incremental.initial_value()
+        );
+
+        let error =
+            serde_yaml::from_str::<IncrementalConfig>("column: code\ntype: string\n").unwrap_err();
+        assert!(
+            error.to_string().contains("unknown variant `string`"),
+            "{error}"
+        );
+    }
 }
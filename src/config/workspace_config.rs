@@ -0,0 +1,36 @@
+use crate::config::units;
+use crate::config::{ConfigError, FieldPath};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Scratch space used by sorted queries and file-based sinks.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, Serialize)]
+pub struct WorkspaceConfig {
+    /// Base directory for per-job scratch directories. Defaults to the system temp directory.
+    pub temp_dir: Option<String>,
+    /// Maximum bytes a single job may write to its scratch directory, in megabytes.
+    #[serde(default, deserialize_with = "units::megabytes")]
+    #[schemars(with = "Option<units::SizeValue>")]
+    pub quota_mb: Option<u64>,
+}
+
+impl WorkspaceConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self
+            .temp_dir
+            .as_deref()
+            .is_some_and(|dir| dir.trim().is_empty())
+        {
+            return Err(ConfigError::MissingRequiredField(FieldPath::new(
+                "execution.workspace.temp_dir",
+            )));
+        }
+        if self.quota_mb == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::new("execution.workspace.quota_mb"),
+                value: "0".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
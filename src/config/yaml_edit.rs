@@ -0,0 +1,519 @@
+//! In-place edits of block-style YAML that keep comments, blank lines, and key order.
+//!
+//! Config-rewriting commands go through this instead of re-serializing `YetiiConfig`,
+//! which would drop every comment in a documented configuration file.
+
+use serde_yaml::Value;
+
+#[derive(Debug, thiserror::Error)]
+pub enum YamlEditError {
+    #[error("configuration path must not be empty")]
+    EmptyPath,
+    #[error("'{path}' is not a block mapping and cannot be edited in place")]
+    NotBlock { path: String },
+    #[error("sequence '{path}' has no item '{item}'")]
+    MissingItem { path: String, item: String },
+    #[error("'{path}' is not a sequence")]
+    NotSequence { path: String },
+    #[error("failed to render YAML value: {0}")]
+    Render(serde_yaml::Error),
+    #[error("edited configuration is not valid YAML: {0}")]
+    Invalid(serde_yaml::Error),
+}
+
+pub struct YamlDocument {
+    lines: Vec<String>,
+    trailing_newline: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Node {
+    Document,
+    Key { line: usize, col: usize },
+    Item { line: usize, col: usize },
+}
+
+impl YamlDocument {
+    pub fn parse(text: &str) -> Result<Self, YamlEditError> {
+        serde_yaml::from_str::<Value>(text).map_err(YamlEditError::Invalid)?;
+        Ok(Self {
+            lines: text.lines().map(str::to_string).collect(),
+            trailing_newline: text.ends_with('\n') || text.is_empty(),
+        })
+    }
+
+    pub fn render(&self) -> String {
+        let mut text = self.lines.join("\n");
+        if self.trailing_newline {
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Parses the edited text, guaranteeing edits never produce invalid YAML.
+    pub fn to_value(&self) -> Result<Value, YamlEditError> {
+        serde_yaml::from_str(&self.render()).map_err(YamlEditError::Invalid)
+    }
+
+    /// Sets `path` (dot separated; sequence items by index or by their `name`) to `value`,
+    /// creating missing mapping keys.
+    pub fn set(&mut self, path: &str, value: &Value) -> Result<(), YamlEditError> {
+        let segments = split_path(path)?;
+        let (last, parents) = segments.split_last().expect("path is not empty");
+        let mut node = Node::Document;
+        for (index, segment) in parents.iter().enumerate() {
+            let walked = segments[..=index].join(".");
+            node = match self.child(node, segment, &walked)? {
+                Some(child) => child,
+                None => self.insert_key(node, segment, None, &walked)?,
+            };
+        }
+
+        match self.child(node, last, path)? {
+            Some(Node::Key { line, col }) => self.replace_value(line, col, value),
+            Some(_) => Err(YamlEditError::NotBlock {
+                path: path.to_string(),
+            }),
+            None => self.insert_key(node, last, Some(value), path).map(|_| ()),
+        }
+    }
+
+    /// Appends `value` as a new item to the sequence at `path`.
+    pub fn append(&mut self, path: &str, value: &Value) -> Result<(), YamlEditError> {
+        let segments = split_path(path)?;
+        let mut node = Node::Document;
+        for (index, segment) in segments.iter().enumerate() {
+            let walked = segments[..=index].join(".");
+            node =
+                self.child(node, segment, &walked)?
+                    .ok_or_else(|| YamlEditError::NotSequence {
+                        path: walked.clone(),
+                    })?;
+        }
+        let Node::Key { line, col } = node else {
+            return Err(YamlEditError::NotSequence {
+                path: path.to_string(),
+            });
+        };
+
+        let (start, end) = self.key_children(line, col);
+        let dash_col = match self.first_content(start, end) {
+            Some(first) if is_item(&self.lines[first]) => indent(&self.lines[first]),
+            Some(_) => {
+                return Err(YamlEditError::NotSequence {
+                    path: path.to_string(),
+                });
+            }
+            None if inline_value(&self.lines[line], col).is_empty() => col + 2,
+            None => {
+                return Err(YamlEditError::NotBlock {
+                    path: path.to_string(),
+                });
+            }
+        };
+        let rendered = render_block(value)?;
+        let mut item = Vec::new();
+        for (index, rendered_line) in rendered.iter().enumerate() {
+            let prefix = if index == 0 { "- " } else { "  " };
+            item.push(format!("{}{prefix}{rendered_line}", " ".repeat(dash_col)));
+        }
+        let at = self.insert_position(start, end);
+        self.lines.splice(at..at, item);
+        Ok(())
+    }
+
+    /// 1-based line and column of the deepest node of `path` that exists, for pointing at a
+    /// problem.
+    pub fn locate(&self, keys: &[String]) -> Option<(usize, usize)> {
+        let path = keys.join(".");
+        let mut node = Node::Document;
+        let mut found = None;
+        for segment in keys {
+            match self.child(node, segment, &path) {
+                Ok(Some(child @ (Node::Key { line, col } | Node::Item { line, col }))) => {
+                    node = child;
+                    found = Some((line + 1, col + 1));
+                }
+                _ => break,
+            }
+        }
+        found
+    }
+
+    fn child(&self, node: Node, segment: &str, path: &str) -> Result<Option<Node>, YamlEditError> {
+        let (start, end, key_col) = match node {
+            Node::Document => (0, self.lines.len(), Some(0)),
+            Node::Item { line, col } => (line, self.item_end(line, col), Some(col + 2)),
+            Node::Key { line, col } => {
+                if !inline_value(&self.lines[line], col).is_empty() {
+                    return Err(YamlEditError::NotBlock {
+                        path: path.to_string(),
+                    });
+                }
+                let (start, end) = self.key_children(line, col);
+                match self.first_content(start, end) {
+                    Some(first) if is_item(&self.lines[first]) => {
+                        return self.find_item(
+                            start,
+                            end,
+                            indent(&self.lines[first]),
+                            segment,
+                            path,
+                        );
+                    }
+                    Some(first) => (start, end, Some(indent(&self.lines[first]))),
+                    None => (start, end, None),
+                }
+            }
+        };
+        let Some(key_col) = key_col else {
+            return Ok(None);
+        };
+
+        Ok((start..end)
+            .find(|&line| key_at(&self.lines[line], key_col) == Some(segment))
+            .map(|line| Node::Key { line, col: key_col }))
+    }
+
+    fn find_item(
+        &self,
+        start: usize,
+        end: usize,
+        dash_col: usize,
+        segment: &str,
+        path: &str,
+    ) -> Result<Option<Node>, YamlEditError> {
+        let items = (start..end)
+            .filter(|&line| indent(&self.lines[line]) == dash_col && is_item(&self.lines[line]))
+            .collect::<Vec<_>>();
+        let found = match segment.parse::<usize>() {
+            Ok(index) => items.get(index).copied(),
+            Err(_) => items.into_iter().find(|&line| {
+                let item = Node::Item {
+                    line,
+                    col: dash_col,
+                };
+                matches!(
+                    self.child(item, "name", path),
+                    Ok(Some(Node::Key { line, col }))
+                        if unquote(inline_value(&self.lines[line], col)) == segment
+                )
+            }),
+        };
+        found
+            .map(|line| {
+                Some(Node::Item {
+                    line,
+                    col: dash_col,
+                })
+            })
+            .ok_or_else(|| YamlEditError::MissingItem {
+                path: path
+                    .rsplit_once('.')
+                    .map_or("", |(parent, _)| parent)
+                    .to_string(),
+                item: segment.to_string(),
+            })
+    }
+
+    fn insert_key(
+        &mut self,
+        parent: Node,
+        key: &str,
+        value: Option<&Value>,
+        path: &str,
+    ) -> Result<Node, YamlEditError> {
+        let (start, end, col) = match parent {
+            Node::Document => (0, self.lines.len(), 0),
+            Node::Item { line, col } => (line, self.item_end(line, col), col + 2),
+            Node::Key { line, col } => {
+                if !inline_value(&self.lines[line], col).is_empty() {
+                    return Err(YamlEditError::NotBlock {
+                        path: path.to_string(),
+                    });
+                }
+                let (start, end) = self.key_children(line, col);
+                let child_col = self
+                    .first_content(start, end)
+                    .map(|first| indent(&self.lines[first]))
+                    .unwrap_or(col + 2);
+                (start, end, child_col)
+            }
+        };
+        let at = self.insert_position(start, end);
+        let mut inserted = Vec::new();
+        match value.map(render_block).transpose()? {
+            Some(rendered) if rendered.len() == 1 && !is_collection(value) => {
+                inserted.push(format!("{}{key}: {}", " ".repeat(col), rendered[0]));
+            }
+            Some(rendered) => {
+                inserted.push(format!("{}{key}:", " ".repeat(col)));
+                inserted.extend(
+                    rendered
+                        .into_iter()
+                        .map(|line| format!("{}{line}", " ".repeat(col + 2))),
+                );
+            }
+            None => inserted.push(format!("{}{key}:", " ".repeat(col))),
+        }
+        self.lines.splice(at..at, inserted);
+        Ok(Node::Key { line: at, col })
+    }
+
+    fn replace_value(
+        &mut self,
+        line: usize,
+        col: usize,
+        value: &Value,
+    ) -> Result<(), YamlEditError> {
+        let (start, end) = self.key_children(line, col);
+        let children_end = self.insert_position(start, end);
+        let rendered = render_block(value)?;
+        let current = &self.lines[line];
+        let existing = value_part(current, col);
+        let comment = comment_part(existing);
+        let head = current[..current.len() - existing.len()]
+            .trim_end()
+            .to_string();
+
+        let mut replacement = Vec::new();
+        if rendered.len() == 1 && !is_collection(Some(value)) {
+            replacement.push(format!("{head} {}{comment}", rendered[0]));
+        } else {
+            replacement.push(format!("{head}{comment}"));
+            replacement.extend(
+                rendered
+                    .into_iter()
+                    .map(|rendered_line| format!("{}{rendered_line}", " ".repeat(col + 2))),
+            );
+        }
+        self.lines.splice(line..children_end, replacement);
+        Ok(())
+    }
+
+    /// Lines belonging to a key's block value: deeper-indented lines, or `- ` items at the same column.
+    fn key_children(&self, line: usize, col: usize) -> (usize, usize) {
+        let end = (line + 1..self.lines.len())
+            .find(|&index| {
+                let current = &self.lines[index];
+                if is_blank_or_comment(current) {
+                    return false;
+                }
+                let current_indent = indent(current);
+                current_indent < col || (current_indent == col && !is_item(current))
+            })
+            .unwrap_or(self.lines.len());
+        (line + 1, end)
+    }
+
+    fn item_end(&self, line: usize, col: usize) -> usize {
+        (line + 1..self.lines.len())
+            .find(|&index| {
+                let current = &self.lines[index];
+                !is_blank_or_comment(current) && indent(current) <= col
+            })
+            .unwrap_or(self.lines.len())
+    }
+
+    fn first_content(&self, start: usize, end: usize) -> Option<usize> {
+        (start..end).find(|&line| !is_blank_or_comment(&self.lines[line]))
+    }
+
+    /// Inserts after the last content line so comments introducing the next section stay put.
+    fn insert_position(&self, start: usize, end: usize) -> usize {
+        (start..end)
+            .rev()
+            .find(|&line| !is_blank_or_comment(&self.lines[line]))
+            .map(|line| line + 1)
+            .unwrap_or(start)
+    }
+}
+
+fn split_path(path: &str) -> Result<Vec<&str>, YamlEditError> {
+    let segments = path
+        .split('.')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    if segments.is_empty() {
+        return Err(YamlEditError::EmptyPath);
+    }
+    Ok(segments)
+}
+
+fn render_block(value: &Value) -> Result<Vec<String>, YamlEditError> {
+    let rendered = serde_yaml::to_string(value).map_err(YamlEditError::Render)?;
+    Ok(rendered
+        .trim_start_matches("---\n")
+        .trim_end()
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+fn is_collection(value: Option<&Value>) -> bool {
+    match value {
+        Some(Value::Mapping(mapping)) => !mapping.is_empty(),
+        Some(Value::Sequence(sequence)) => !sequence.is_empty(),
+        _ => false,
+    }
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn is_blank_or_comment(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+fn is_item(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed == "-" || trimmed.starts_with("- ")
+}
+
+/// Returns the key when `line` has a mapping key starting at `col`; `- ` before it counts as indentation.
+fn key_at(line: &str, col: usize) -> Option<&str> {
+    let prefix = line.get(..col)?;
+    if !prefix
+        .chars()
+        .all(|character| character == ' ' || character == '-')
+        || prefix.matches('-').count() > 1
+    {
+        return None;
+    }
+    if prefix.contains('-') && !prefix.trim_end().ends_with('-') {
+        return None;
+    }
+    let rest = &line[col..];
+    if rest.starts_with([' ', '#', '-']) {
+        return None;
+    }
+    let (key, _) = rest.split_once(':')?;
+    let key = key.trim();
+    Some(unquote(key))
+}
+
+/// Everything after the key's colon, trailing comment included.
+fn value_part(line: &str, col: usize) -> &str {
+    line.get(col..)
+        .and_then(|rest| rest.split_once(':'))
+        .map(|(_, value)| value.trim_start())
+        .unwrap_or_default()
+}
+
+fn inline_value(line: &str, col: usize) -> &str {
+    strip_comment(value_part(line, col))
+}
+
+fn strip_comment(value: &str) -> &str {
+    let position = comment_position(value).unwrap_or(value.len());
+    value[..position].trim()
+}
+
+fn comment_part(value: &str) -> String {
+    comment_position(value)
+        .map(|position| format!(" {}", value[position..].trim_end()))
+        .unwrap_or_default()
+}
+
+fn comment_position(value: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, character) in value.char_indices() {
+        match (quote, character) {
+            (None, '\'' | '"') => quote = Some(character),
+            (Some(open), current) if open == current => quote = None,
+            (None, '#') if previous.is_whitespace() => return Some(index),
+            _ => {}
+        }
+        previous = character;
+    }
+    None
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        })
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "# Production sync\nversion: \"1.0.0\"\n\nqueries:\n  # Orders go to the ERP API\n  - name: orders\n    enabled: true # keep on\n    endpoint:\n      url: https://old.example.test\n  - name: customers\n    enabled: false\n\n# Execution settings\nexecution:\n  mode: sequential\n";
+
+    fn value(yaml: &str) -> Value {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn sets_existing_values_and_keeps_comments() {
+        let mut document = YamlDocument::parse(CONFIG).unwrap();
+
+        document
+            .set("queries.orders.enabled", &value("false"))
+            .unwrap();
+        document
+            .set("queries.1.endpoint.url", &value("https://new.example.test"))
+            .unwrap();
+        document.set("execution.mode", &value("parallel")).unwrap();
+
+        let rendered = document.render();
+        assert!(rendered.starts_with("# Production sync\nversion: \"1.0.0\"\n"));
+        assert!(rendered.contains("  # Orders go to the ERP API\n"));
+        assert!(rendered.contains("    enabled: false # keep on\n"));
+        assert!(rendered.contains(
+            "  - name: customers\n    enabled: false\n    endpoint:\n      url: https://new.example.test\n\n# Execution settings\n"
+        ));
+        assert!(rendered.ends_with("execution:\n  mode: parallel\n"));
+        document.to_value().unwrap();
+    }
+
+    #[test]
+    fn creates_missing_sections_at_the_end_of_their_parent() {
+        let mut document = YamlDocument::parse(CONFIG).unwrap();
+
+        document
+            .set("execution.scheduler.max_concurrent_jobs", &value("4"))
+            .unwrap();
+
+        assert!(document.render().ends_with(
+            "execution:\n  mode: sequential\n  scheduler:\n    max_concurrent_jobs: 4\n"
+        ));
+    }
+
+    #[test]
+    fn appends_sequence_items_before_following_comments() {
+        let mut document = YamlDocument::parse(CONFIG).unwrap();
+
+        document
+            .append("queries", &value("name: invoices\nenabled: true\n"))
+            .unwrap();
+
+        let rendered = document.render();
+        assert!(rendered.contains(
+            "    enabled: false\n  - name: invoices\n    enabled: true\n\n# Execution settings\n"
+        ));
+        let parsed = document.to_value().unwrap();
+        assert_eq!(3, parsed["queries"].as_sequence().unwrap().len());
+    }
+
+    #[test]
+    fn reports_unknown_sequence_items() {
+        let mut document = YamlDocument::parse(CONFIG).unwrap();
+
+        assert!(matches!(
+            document.set("queries.missing.enabled", &value("true")),
+            Err(YamlEditError::MissingItem { item, .. }) if item == "missing"
+        ));
+    }
+}
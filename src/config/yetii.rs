@@ -1,111 +1,446 @@
-use crate::config::ConfigError;
-use crate::config::database::DatabaseConfigs;
-use crate::config::environment_config::EnvironmentOverride;
+use crate::config::database::{DatabaseConfigs, DatabaseType};
+use crate::config::destination_config::DestinationConfig;
+use crate::config::endpoint_config::{EndpointAuth, EndpointConfig};
+use crate::config::environment_config::{EnvironmentOverride, scope_sql};
 use crate::config::execution_config::ExecutionConfig;
+use crate::config::flow_config::FlowConfig;
 use crate::config::global_settings::GlobalSettings;
 use crate::config::monitor_config::MonitoringConfig;
 use crate::config::query_config::QueryConfig;
+use crate::config::sql_query::ExternalSource;
+use crate::config::transform_config::TransformConfig;
 use crate::config::utils::default_version;
+use crate::config::watermark_config::IncrementalConfig;
+use crate::config::{ConfigError, FieldPath};
+use chrono::NaiveDate;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+/// A finding that does not make the configuration invalid.
+#[derive(Debug)]
+pub struct ConfigWarning {
+    /// Stable identifier, as for [`ConfigError::code`].
+    pub code: &'static str,
+    pub field: FieldPath,
+    pub message: String,
+}
 
 /// Root configuration structure for the ERP integration system.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 pub struct YetiiConfig {
+    /// Configuration format version.
     #[serde(default = "default_version")]
     pub version: Option<String>,
+    /// Human-readable name of this sync configuration.
     pub name: Option<String>,
     pub description: Option<String>,
+    /// Source databases reached through ODBC.
     pub databases: DatabaseConfigs,
     #[serde(default)]
     pub global_settings: GlobalSettings,
+    /// Credentials shared by endpoints, referenced with `auth_profile: <name>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_profiles: Option<HashMap<String, EndpointAuth>>,
+    /// Endpoints shared by queries, referenced with `endpoint: <name>` or
+    /// `endpoint: { ref: <name>, ... }` to override individual settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoints: Option<HashMap<String, EndpointConfig>>,
+    /// Transform settings shared by queries, applied with `transform: { extends: <name> }`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform_profiles: Option<HashMap<String, TransformConfig>>,
+    /// Queries to execute and deliver.
     pub queries: Vec<QueryConfig>,
+    /// Named pipelines that run several queries as one unit, with `yetii run --flow <name>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flows: Option<Vec<FlowConfig>>,
+    /// Execution mode, state management, and scheduler settings.
     #[serde(default)]
     pub execution: ExecutionConfig,
+    /// Health checks, metrics, and notifications.
     pub monitoring: Option<MonitoringConfig>,
+    /// Named overrides applied on top of the base configuration.
     pub environments: Option<HashMap<String, EnvironmentOverride>>,
 }
 impl YetiiConfig {
-    /// Validates the entire configuration
+    /// Starts a configuration for `init` and tests. At least one database is required; the
+    /// rest starts as YAML that leaves it out would set it, with no queries.
+    pub(crate) fn builder(databases: impl Into<DatabaseConfigs>) -> YetiiConfigBuilder {
+        YetiiConfigBuilder(YetiiConfig {
+            version: default_version(),
+            name: None,
+            description: None,
+            databases: databases.into(),
+            global_settings: GlobalSettings::default(),
+            auth_profiles: None,
+            endpoints: None,
+            transform_profiles: None,
+            queries: Vec::new(),
+            flows: None,
+            execution: ExecutionConfig::default(),
+            monitoring: None,
+            environments: None,
+        })
+    }
+
+    /// Validates the entire configuration, stopping at the first problem.
     pub fn validate(&self) -> Result<(), ConfigError> {
-        // Validate version format
+        match self.validation_errors().into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Every problem with the configuration. Each database, profile, endpoint, query, flow and
+    /// section reports its first problem, so one mistake does not hide the rest.
+    pub fn validation_errors(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
         if self.version.is_none() {
-            return Err(ConfigError::MissingRequiredField("version".to_string()));
+            errors.push(ConfigError::MissingRequiredField(FieldPath::new("version")));
         }
 
-        // Validate database configuration
-        self.databases.validate()?;
+        errors.extend(self.databases.validation_errors());
+        errors.extend(self.global_settings.validation_errors());
 
-        // Validate global settings
-        self.global_settings.validate()?;
+        for (name, auth) in self.auth_profiles.iter().flatten() {
+            errors.extend(
+                auth.validate(&FieldPath::new("auth_profiles").key(name))
+                    .err(),
+            );
+        }
+        for (name, endpoint) in self.endpoints.iter().flatten() {
+            errors.extend(
+                endpoint
+                    .validate(&FieldPath::new("endpoints").key(name))
+                    .err(),
+            );
+        }
+        for (name, profile) in self.transform_profiles.iter().flatten() {
+            errors.extend(
+                profile
+                    .validate(&FieldPath::new("transform_profiles").key(name))
+                    .err(),
+            );
+        }
 
-        // Validate all queries
-        for query in &self.queries {
-            query.validate()?;
-            if query
-                .watermark
+        if self
+            .execution
+            .scheduler
+            .as_ref()
+            .is_some_and(|scheduler| scheduler.reload.endpoint.is_some())
+            && !self
+                .monitoring
                 .as_ref()
-                .is_some_and(|watermark| watermark.is_incremental())
-                && !self
-                    .execution
-                    .state_management
-                    .as_ref()
-                    .is_some_and(|state| state.enabled)
+                .filter(|monitoring| monitoring.enabled)
+                .and_then(|monitoring| monitoring.health_check.as_ref())
+                .is_some_and(|health| health.enabled)
+        {
+            errors.push(ConfigError::InvalidValue {
+                field: FieldPath::new("execution.scheduler.reload.endpoint"),
+                value: "served on monitoring.health_check, which is not enabled".to_string(),
+            });
+        }
+
+        for query in &self.queries {
+            errors.extend(self.validate_query(query).err());
+        }
+
+        let query_names = self
+            .queries
+            .iter()
+            .map(|query| query.name.as_str())
+            .collect::<HashSet<_>>();
+        let mut flow_names = HashSet::new();
+        for flow in self.flows.iter().flatten() {
+            errors.extend(flow.validate(&query_names).err());
+            if !flow_names.insert(flow.name.as_str()) {
+                errors.push(ConfigError::InvalidValue {
+                    field: FieldPath::flow(&flow.name).join("name"),
+                    value: "flow names must be unique".to_string(),
+                });
+            }
+        }
+
+        for (environment, env_override) in self.environments.iter().flatten() {
+            errors.extend(self.validate_row_filters(environment, env_override).err());
+        }
+
+        errors.extend(self.execution.validate().err());
+        if let Some(monitoring) = &self.monitoring {
+            errors.extend(monitoring.validate().err());
+        }
+
+        errors
+    }
+
+    /// Checks one query, including its references to databases, queries and monitoring.
+    fn validate_query(&self, query: &QueryConfig) -> Result<(), ConfigError> {
+        query.validate()?;
+        for (name, parameter) in query.query.parameters.iter().flatten() {
+            let Some(ExternalSource::Query(source)) = parameter.external_source() else {
+                continue;
+            };
+            // The source query runs with its own defaults, so it cannot be this query.
+            if source == query.name
+                || !self
+                    .queries
+                    .iter()
+                    .any(|other| other.name == source && other.source.is_none())
             {
                 return Err(ConfigError::InvalidValue {
-                    field: format!("query '{}'.watermark", query.name),
-                    value: "incremental watermarks require execution.state_management.enabled=true"
-                        .to_string(),
+                    field: FieldPath::query(&query.name)
+                        .join("query.parameters")
+                        .key(name)
+                        .join("source"),
+                    value: format!("'{source}' is not another database query"),
                 });
             }
-            match self.databases.resolve_for_query(query.database.as_deref()) {
-                Some(_) => {}
-                None if self.databases.len() > 1 && query.database.is_none() => {
-                    return Err(ConfigError::MissingRequiredField(format!(
-                        "query '{}'.database",
-                        query.name
-                    )));
-                }
+        }
+        if let Some(notifications) = &query.notifications {
+            notifications.validate(
+                &query.name,
+                self.monitoring
+                    .as_ref()
+                    .and_then(|monitoring| monitoring.notifications.as_ref()),
+            )?;
+        }
+        if query
+            .watermark
+            .as_ref()
+            .is_some_and(|watermark| watermark.is_incremental())
+            && !self
+                .execution
+                .state_management
+                .as_ref()
+                .is_some_and(|state| state.enabled)
+        {
+            return Err(ConfigError::InvalidValue {
+                field: FieldPath::query(&query.name).join("watermark"),
+                value: "incremental watermarks require execution.state_management.enabled=true"
+                    .to_string(),
+            });
+        }
+        if let Some(DestinationConfig::Database(destination)) = &query.destination {
+            match self.databases.get(&destination.database) {
                 None => {
                     return Err(ConfigError::InvalidValue {
-                        field: format!("query '{}'.database", query.name),
-                        value: query
-                            .database
-                            .clone()
-                            .unwrap_or_else(|| "<missing>".to_string()),
+                        field: FieldPath::query(&query.name).join("destination.database"),
+                        value: destination.database.clone(),
                     });
                 }
+                Some(database) if database.db_type == DatabaseType::Generator => {
+                    return Err(ConfigError::InvalidValue {
+                        field: FieldPath::query(&query.name).join("destination.database"),
+                        value: format!(
+                            "generator database '{}' cannot be written to",
+                            destination.database
+                        ),
+                    });
+                }
+                Some(_) => {}
             }
         }
-
-        // Validate execution config
-        self.execution.validate()?;
-        if let Some(monitoring) = &self.monitoring {
-            monitoring.validate()?;
+        match self.databases.resolve_for_query(query.database.as_deref()) {
+            Some(database) => {
+                // A generator returns the same rows for every page, so paging never ends.
+                if database.db_type == DatabaseType::Generator
+                    && query
+                        .watermark
+                        .as_ref()
+                        .is_some_and(|watermark| watermark.page_size.is_some())
+                {
+                    return Err(ConfigError::InvalidValue {
+                        field: FieldPath::query(&query.name).join("watermark.page_size"),
+                        value: "generator databases return all rows in one page".to_string(),
+                    });
+                }
+                if query.query.cost_check.is_some()
+                    && !matches!(
+                        database.db_type,
+                        DatabaseType::Postgres | DatabaseType::Mysql
+                    )
+                {
+                    return Err(ConfigError::InvalidValue {
+                        field: FieldPath::query(&query.name).join("query.cost_check"),
+                        value: "EXPLAIN cost checks support postgres and mysql databases only"
+                            .to_string(),
+                    });
+                }
+            }
+            None if self.databases.len() > 1 && query.database.is_none() => {
+                return Err(ConfigError::MissingRequiredField(
+                    FieldPath::query(&query.name).join("database"),
+                ));
+            }
+            None => {
+                return Err(ConfigError::InvalidValue {
+                    field: FieldPath::query(&query.name).join("database"),
+                    value: query
+                        .database
+                        .clone()
+                        .unwrap_or_else(|| "<missing>".to_string()),
+                });
+            }
         }
 
         Ok(())
     }
 
-    /// Gets the effective configuration for a specific environment
-    #[allow(unused)]
-    pub fn for_environment(&self, env: &str) -> Self {
-        let mut config = self.clone();
+    /// Problems that do not make the configuration invalid but deserve attention.
+    pub fn warnings(&self, today: NaiveDate) -> Vec<ConfigWarning> {
+        self.queries
+            .iter()
+            .filter_map(|query| {
+                let until = query.expired_disable(today)?;
+                Some(ConfigWarning {
+                    code: "expired_disable",
+                    field: FieldPath::query(&query.name).join("until"),
+                    message: format!(
+                        "query '{}' was disabled until {until} and runs again; set enabled: true and remove until",
+                        query.name
+                    ),
+                })
+            })
+            .collect()
+    }
 
-        if let Some(overrides) = &self.environments
-            && let Some(env_override) = overrides.get(env)
+    /// Wraps the SQL of queries targeted by `env`'s `row_filters`; pull queries are left alone.
+    pub fn apply_row_filters(&mut self, env: &str) {
+        let Some(row_filters) = self
+            .environments
+            .as_ref()
+            .and_then(|overrides| overrides.get(env))
+            .and_then(|env_override| env_override.row_filters.as_ref())
+        else {
+            return;
+        };
+        for query in self
+            .queries
+            .iter_mut()
+            .filter(|query| query.source.is_none())
         {
-            if let Some(global_settings) = &env_override.global_settings {
-                config.global_settings = global_settings.clone();
+            let predicates = row_filters
+                .iter()
+                .filter(|filter| filter.applies_to(&query.name))
+                .map(|filter| filter.predicate.as_str())
+                .collect::<Vec<_>>();
+            if !predicates.is_empty() {
+                query.query.sql = scope_sql(&query.query.sql, &predicates);
+            }
+        }
+    }
+
+    /// Turns each query's `incremental` shorthand into its state parameter, `max` watermark, and
+    /// wrapped SQL. Runs after `apply_row_filters` so the cursor `ORDER BY` stays outermost.
+    pub fn apply_incremental(&mut self) {
+        for query in &mut self.queries {
+            let Some(incremental) = &query.incremental else {
+                continue;
+            };
+            // Conflicting settings are left for validation to report.
+            if query.watermark.is_some() || query.source.is_some() {
+                continue;
             }
-            if let Some(databases) = &env_override.databases {
-                config.databases = databases.clone();
+            let (parameter, watermark) = incremental.expand();
+            query.query.sql = incremental.scope_sql(&query.query.sql);
+            query
+                .query
+                .parameters
+                .get_or_insert_with(Default::default)
+                .insert(IncrementalConfig::PARAMETER.to_string(), parameter);
+            query.watermark = Some(watermark);
+        }
+    }
+
+    fn validate_row_filters(
+        &self,
+        environment: &str,
+        env_override: &EnvironmentOverride,
+    ) -> Result<(), ConfigError> {
+        for (index, filter) in env_override.row_filters.iter().flatten().enumerate() {
+            let field = |name: &str| {
+                FieldPath::new("environments")
+                    .key(environment)
+                    .join("row_filters")
+                    .key(index.to_string())
+                    .join(name)
+            };
+            if filter.predicate.trim().is_empty() {
+                return Err(ConfigError::MissingRequiredField(field("predicate")));
+            }
+            for name in filter.queries.iter().flatten() {
+                let Some(query) = self.queries.iter().find(|query| &query.name == name) else {
+                    return Err(ConfigError::InvalidValue {
+                        field: field("queries"),
+                        value: format!("unknown query '{name}'"),
+                    });
+                };
+                if query.source.is_some() {
+                    return Err(ConfigError::InvalidValue {
+                        field: field("queries"),
+                        value: format!("'{name}' is a pull query and does not read rows"),
+                    });
+                }
             }
-            if let Some(monitoring) = &env_override.monitoring {
-                config.monitoring = Some(monitoring.clone());
+            // The LIMIT of a paged query would page over unfiltered rows, so a short filtered
+            // page would end the run early.
+            if let Some(query) = self.queries.iter().find(|query| {
+                query.source.is_none()
+                    && filter.applies_to(&query.name)
+                    && query
+                        .watermark
+                        .as_ref()
+                        .is_some_and(|watermark| watermark.page_size.is_some())
+            }) {
+                return Err(ConfigError::InvalidValue {
+                    field: field("queries"),
+                    value: format!(
+                        "'{}' pages with watermark.page_size; filter it in its SQL instead",
+                        query.name
+                    ),
+                });
             }
         }
+        Ok(())
+    }
+}
+
+/// A [`YetiiConfig`] under construction, from [`YetiiConfig::builder`].
+pub(crate) struct YetiiConfigBuilder(YetiiConfig);
+
+impl YetiiConfigBuilder {
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.0.name = Some(name.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.0.description = Some(description.into());
+        self
+    }
+
+    pub fn global_settings(mut self, global_settings: GlobalSettings) -> Self {
+        self.0.global_settings = global_settings;
+        self
+    }
+
+    pub fn query(mut self, query: QueryConfig) -> Self {
+        self.0.queries.push(query);
+        self
+    }
+
+    pub fn execution(mut self, execution: ExecutionConfig) -> Self {
+        self.0.execution = execution;
+        self
+    }
+
+    pub fn monitoring(mut self, monitoring: MonitoringConfig) -> Self {
+        self.0.monitoring = Some(monitoring);
+        self
+    }
 
-        config
+    /// The configuration, unvalidated; call [`YetiiConfig::validate`] before using it.
+    pub fn build(self) -> YetiiConfig {
+        self.0
     }
 }
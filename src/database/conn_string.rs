@@ -37,13 +37,14 @@ pub fn build_connection_string(db: &DatabaseConfig) -> String {
                 escape_value(&format!("//{}:{}/{}", db.host, db.port, db.database))
             ));
         }
+        DatabaseType::Generator => {}
     }
 
     if let Some(username) = &db.auth.username {
         attributes.push(format!("Uid={}", escape_value(username)));
     }
     if let Some(password) = &db.auth.password {
-        attributes.push(format!("Pwd={}", escape_value(password)));
+        attributes.push(format!("Pwd={}", escape_value(password.expose())));
     }
 
     let mut options = db.connection_options.iter().collect::<Vec<_>>();
@@ -127,9 +128,11 @@ mod tests {
             schema: None,
             auth: AuthConfig {
                 username: Some("sync".to_string()),
-                password: Some("p;ass}word".to_string()),
+                password: Some("p;ass}word".into()),
+                dynamic: None,
             },
             pool: ConnectionConfig::default(),
+            generator: None,
         }
     }
 
@@ -0,0 +1,358 @@
+//! Short-lived credentials for `auth.dynamic`: fetched as each connection opens, so a run never
+//! holds a long-lived password. Vault leases are renewed for as long as the session lives.
+
+use super::error::DbError;
+use crate::config::database::{DatabaseConfig, DynamicCredentials};
+use crate::http::sigv4;
+use aws_credential_types::Credentials;
+use chrono::Utc;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use url::Url;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// RDS accepts a token for 15 minutes; it is only checked when the connection opens.
+const RDS_TOKEN_EXPIRES_SECONDS: u64 = 900;
+
+/// Keeps a Vault lease renewed until dropped with its session.
+pub struct CredentialLease {
+    renewal: JoinHandle<()>,
+}
+
+impl Drop for CredentialLease {
+    fn drop(&mut self) {
+        self.renewal.abort();
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VaultSecret {
+    lease_id: String,
+    lease_duration: u64,
+    renewable: bool,
+    data: VaultCredentials,
+}
+
+#[derive(Debug, Deserialize)]
+struct VaultCredentials {
+    username: String,
+    password: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct VaultRenewal {
+    lease_duration: u64,
+    renewable: bool,
+}
+
+/// `db` with `auth.dynamic` resolved into a username and password, plus the lease to hold while
+/// connected. Databases without dynamic credentials are returned unchanged.
+pub async fn resolve(
+    db: &DatabaseConfig,
+) -> Result<(DatabaseConfig, Option<CredentialLease>), DbError> {
+    let mut db = db.clone();
+    let Some(dynamic) = db.auth.dynamic.take() else {
+        return Ok((db, None));
+    };
+    let error = |message: String| DbError::Credentials {
+        database: db.name.clone(),
+        message,
+    };
+    match dynamic {
+        DynamicCredentials::Vault {
+            address,
+            token,
+            mount,
+            role,
+            namespace,
+        } => {
+            let vault = Vault {
+                client: Client::builder()
+                    .timeout(REQUEST_TIMEOUT)
+                    .build()
+                    .map_err(|source| error(source.to_string()))?,
+                address: address.trim_end_matches('/').to_string(),
+                token: token.expose().clone(),
+                namespace,
+            };
+            let secret = vault
+                .fetch(&mount, &role)
+                .await
+                .map_err(|message| error(format!("Vault: {message}")))?;
+            tracing::info!(
+                database = %db.name,
+                lease_seconds = secret.lease_duration,
+                "leased database credentials from Vault"
+            );
+            db.auth.username = Some(secret.data.username);
+            db.auth.password = Some(secret.data.password.into());
+            let lease = (secret.renewable && secret.lease_duration > 0).then(|| CredentialLease {
+                renewal: tokio::spawn(vault.renew_until_dropped(
+                    db.name.clone(),
+                    secret.lease_id,
+                    secret.lease_duration,
+                )),
+            });
+            Ok((db, lease))
+        }
+        DynamicCredentials::RdsIam {
+            region,
+            access_key_id,
+            secret_access_key,
+            session_token,
+            profile,
+        } => {
+            let credentials = sigv4::resolve_credentials(
+                access_key_id.as_deref(),
+                secret_access_key.as_ref().map(|key| key.expose().as_str()),
+                session_token.as_ref().map(|token| token.expose().as_str()),
+                profile.as_deref(),
+            )
+            .await
+            .map_err(|source| error(source.to_string()))?;
+            let username = db.auth.username.clone().unwrap_or_default();
+            let token = rds_auth_token(&db.host, db.port, &username, &region, &credentials)
+                .map_err(|source| error(source.to_string()))?;
+            tracing::debug!(database = %db.name, "signed an RDS IAM authentication token");
+            db.auth.password = Some(token.into());
+            Ok((db, None))
+        }
+    }
+}
+
+/// The token RDS accepts as the password: a presigned `connect` URL without its scheme.
+fn rds_auth_token(
+    host: &str,
+    port: u16,
+    username: &str,
+    region: &str,
+    credentials: &Credentials,
+) -> Result<String, sigv4::SigV4Error> {
+    let mut url = Url::parse(&format!("https://{host}:{port}/"))
+        .map_err(|_| sigv4::SigV4Error::MissingHost)?;
+    url.query_pairs_mut()
+        .append_pair("Action", "connect")
+        .append_pair("DBUser", username);
+    let signed = sigv4::presign_url(
+        &url,
+        credentials,
+        region,
+        "rds-db",
+        RDS_TOKEN_EXPIRES_SECONDS,
+        Utc::now(),
+    )?;
+    Ok(signed.trim_start_matches("https://").to_string())
+}
+
+struct Vault {
+    client: Client,
+    address: String,
+    token: String,
+    namespace: Option<String>,
+}
+
+impl Vault {
+    fn request(&self, builder: RequestBuilder) -> RequestBuilder {
+        let builder = builder.header("X-Vault-Token", &self.token);
+        match &self.namespace {
+            Some(namespace) => builder.header("X-Vault-Namespace", namespace),
+            None => builder,
+        }
+    }
+
+    async fn fetch(&self, mount: &str, role: &str) -> Result<VaultSecret, String> {
+        let url = format!(
+            "{}/v1/{}/creds/{role}",
+            self.address,
+            mount.trim_matches('/')
+        );
+        self.send(self.client.get(url)).await
+    }
+
+    async fn renew(&self, lease_id: &str, increment: u64) -> Result<VaultRenewal, String> {
+        let url = format!("{}/v1/sys/leases/renew", self.address);
+        self.send(
+            self.client
+                .put(url)
+                .json(&json!({ "lease_id": lease_id, "increment": increment })),
+        )
+        .await
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        builder: RequestBuilder,
+    ) -> Result<T, String> {
+        let response = self
+            .request(builder)
+            .send()
+            .await
+            .map_err(|error| error.to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("HTTP {status}: {}", body.trim()));
+        }
+        response.json().await.map_err(|error| error.to_string())
+    }
+
+    /// Renews the lease after two thirds of each granted duration. Vault stops granting time at
+    /// the role's `max_ttl`; the connection then lasts until the lease expires.
+    async fn renew_until_dropped(self, database: String, lease_id: String, mut duration: u64) {
+        loop {
+            tokio::time::sleep(renewal_delay(duration)).await;
+            match self.renew(&lease_id, duration).await {
+                Ok(renewal) if renewal.renewable && renewal.lease_duration > 0 => {
+                    tracing::debug!(
+                        database = %database,
+                        lease_seconds = renewal.lease_duration,
+                        "renewed Vault database lease"
+                    );
+                    duration = renewal.lease_duration;
+                }
+                Ok(renewal) => {
+                    tracing::warn!(
+                        database = %database,
+                        lease_seconds = renewal.lease_duration,
+                        "Vault database lease can no longer be renewed"
+                    );
+                    return;
+                }
+                Err(error) => {
+                    tracing::warn!(
+                        database = %database,
+                        error = %error,
+                        "Vault database lease renewal failed"
+                    );
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn renewal_delay(lease_seconds: u64) -> Duration {
+    Duration::from_secs((lease_seconds * 2 / 3).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::database::{AuthConfig, DatabaseType};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn database(dynamic: DynamicCredentials) -> DatabaseConfig {
+        DatabaseConfig {
+            name: "warehouse".to_string(),
+            db_type: DatabaseType::Postgres,
+            driver: None,
+            connection_string: None,
+            connection_options: Default::default(),
+            host: "warehouse.abc123.eu-west-1.rds.amazonaws.com".to_string(),
+            port: 5432,
+            database: "analytics".to_string(),
+            schema: None,
+            auth: AuthConfig {
+                username: Some("yetii".to_string()),
+                password: None,
+                dynamic: Some(dynamic),
+            },
+            pool: Default::default(),
+            generator: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn leases_credentials_from_vault() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0; 4096];
+            let read = stream.read(&mut buffer).await.unwrap();
+            let body = r#"{"lease_id":"database/creds/reader/abc","lease_duration":3600,"renewable":true,"data":{"username":"v-reader-1","password":"leased"}}"#;
+            stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                        body.len()
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buffer[..read]).to_ascii_lowercase()
+        });
+
+        let (resolved, lease) = resolve(&database(DynamicCredentials::Vault {
+            address: format!("http://{address}/"),
+            token: "root-token".into(),
+            mount: "database".to_string(),
+            role: "reader".to_string(),
+            namespace: None,
+        }))
+        .await
+        .unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("get /v1/database/creds/reader "));
+        assert!(request.contains("x-vault-token: root-token"));
+        assert_eq!(Some("v-reader-1"), resolved.auth.username.as_deref());
+        assert_eq!("leased", resolved.auth.password.unwrap().expose());
+        assert!(resolved.auth.dynamic.is_none());
+        assert!(lease.is_some());
+        assert_eq!(Duration::from_secs(2400), renewal_delay(3600));
+    }
+
+    #[tokio::test]
+    async fn signs_an_rds_iam_token_as_the_password() {
+        let (resolved, lease) = resolve(&database(DynamicCredentials::RdsIam {
+            region: "eu-west-1".to_string(),
+            access_key_id: Some("AKIDEXAMPLE".to_string()),
+            secret_access_key: Some("secret".into()),
+            session_token: None,
+            profile: None,
+        }))
+        .await
+        .unwrap();
+
+        let token = resolved.auth.password.unwrap().expose().clone();
+        assert!(token.starts_with(
+            "warehouse.abc123.eu-west-1.rds.amazonaws.com:5432/?Action=connect&DBUser=yetii&X-Amz-Algorithm=AWS4-HMAC-SHA256&X-Amz-Credential=AKIDEXAMPLE%2F"
+        ));
+        assert!(token.contains("%2Feu-west-1%2Frds-db%2Faws4_request&"));
+        assert!(token.contains("&X-Amz-Expires=900&X-Amz-SignedHeaders=host&X-Amz-Signature="));
+        assert!(lease.is_none());
+    }
+
+    #[test]
+    fn signs_the_session_token_of_temporary_credentials() {
+        // Instance roles, IRSA, and SSO all hand out temporary credentials.
+        let credentials = Credentials::new(
+            "ASIAEXAMPLE",
+            "secret",
+            Some("session/token".to_string()),
+            None,
+            "test",
+        );
+
+        let token = rds_auth_token(
+            "warehouse.abc123.eu-west-1.rds.amazonaws.com",
+            5432,
+            "yetii",
+            "eu-west-1",
+            &credentials,
+        )
+        .unwrap();
+
+        assert!(token.contains("X-Amz-Credential=ASIAEXAMPLE%2F"), "{token}");
+        assert!(
+            token.contains("X-Amz-Security-Token=session%2Ftoken"),
+            "{token}"
+        );
+    }
+}
@@ -6,12 +6,24 @@ pub enum DbError {
     Connect(odbc_api::Error),
     #[error("failed to execute the ODBC query: {0}")]
     Execute(odbc_api::Error),
+    #[error("failed to prepare the ODBC query: {0}")]
+    Prepare(odbc_api::Error),
     #[error("failed to read the ODBC result set: {0}")]
     Fetch(odbc_api::Error),
+    #[error("failed to manage the ODBC transaction: {0}")]
+    Transaction(odbc_api::Error),
     #[error("invalid query parameter configuration: {0}")]
     Parameter(String),
     #[error("ODBC worker task failed: {0}")]
     Join(#[from] tokio::task::JoinError),
     #[error("ODBC worker stopped unexpectedly: {0}")]
     Worker(String),
+    #[error("failed to read the query plan: {0}")]
+    Explain(String),
+    #[error("database '{0}' is a generator and cannot be written to")]
+    GeneratorWrite(String),
+    #[error("failed to fetch credentials for database '{database}': {message}")]
+    Credentials { database: String, message: String },
+    #[error("chaos fault injected: {0}")]
+    Injected(&'static str),
 }
@@ -0,0 +1,122 @@
+use crate::config::database::DatabaseType;
+use crate::database::error::DbError;
+use serde_json::{Map, Value};
+
+/// Planner estimates read from an `EXPLAIN` plan.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlanEstimate {
+    /// PostgreSQL's total cost or MySQL's `query_cost`.
+    pub cost: Option<f64>,
+    /// Largest row estimate of any single plan step.
+    pub rows: Option<f64>,
+}
+
+/// Wraps `sql` in the database's JSON `EXPLAIN`, or `None` when the database has no support.
+pub fn explain_sql(db_type: &DatabaseType, sql: &str) -> Option<String> {
+    let sql = sql.trim().trim_end_matches(';');
+    match db_type {
+        DatabaseType::Postgres => Some(format!("EXPLAIN (FORMAT JSON) {sql}")),
+        DatabaseType::Mysql => Some(format!("EXPLAIN FORMAT=JSON {sql}")),
+        DatabaseType::Mssql | DatabaseType::Oracle | DatabaseType::Generator => None,
+    }
+}
+
+/// Reads the estimates from the single-column result of [`explain_sql`].
+pub fn parse_plan(
+    db_type: &DatabaseType,
+    rows: &[Map<String, Value>],
+) -> Result<PlanEstimate, DbError> {
+    let plan = rows
+        .first()
+        .and_then(|row| row.values().next())
+        .ok_or_else(|| DbError::Explain("EXPLAIN returned no plan".to_string()))?;
+    let plan = match plan {
+        Value::String(text) => serde_json::from_str(text)
+            .map_err(|error| DbError::Explain(format!("EXPLAIN plan is not JSON: {error}")))?,
+        plan => plan.clone(),
+    };
+    let (cost_key, rows_key) = match db_type {
+        DatabaseType::Postgres => ("Total Cost", "Plan Rows"),
+        DatabaseType::Mysql => ("query_cost", "rows_examined_per_scan"),
+        DatabaseType::Mssql | DatabaseType::Oracle | DatabaseType::Generator => {
+            return Err(DbError::Explain(
+                "EXPLAIN is only supported for postgres and mysql".to_string(),
+            ));
+        }
+    };
+    Ok(PlanEstimate {
+        cost: max_number(&plan, cost_key),
+        rows: max_number(&plan, rows_key),
+    })
+}
+
+/// Largest value stored under `key` anywhere in the plan; MySQL reports some numbers as strings.
+fn max_number(plan: &Value, key: &str) -> Option<f64> {
+    let own = |object: &Map<String, Value>| match object.get(key)? {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    };
+    let children: Box<dyn Iterator<Item = &Value>> = match plan {
+        Value::Object(object) => Box::new(object.values()),
+        Value::Array(items) => Box::new(items.iter()),
+        _ => return None,
+    };
+    let nested = children.filter_map(|child| max_number(child, key));
+    plan.as_object()
+        .and_then(own)
+        .into_iter()
+        .chain(nested)
+        .reduce(f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn plan_row(plan: Value) -> Vec<Map<String, Value>> {
+        let mut row = Map::new();
+        row.insert("QUERY PLAN".to_string(), Value::String(plan.to_string()));
+        vec![row]
+    }
+
+    #[test]
+    fn reads_the_largest_estimates_from_postgres_and_mysql_plans() {
+        let postgres = plan_row(json!([{
+            "Plan": {
+                "Node Type": "Hash Join",
+                "Total Cost": 4520.5,
+                "Plan Rows": 120,
+                "Plans": [
+                    {"Node Type": "Seq Scan", "Total Cost": 3100.0, "Plan Rows": 2_500_000},
+                    {"Node Type": "Hash", "Total Cost": 12.0, "Plan Rows": 40}
+                ]
+            }
+        }]));
+        assert_eq!(
+            parse_plan(&DatabaseType::Postgres, &postgres).unwrap(),
+            PlanEstimate {
+                cost: Some(4520.5),
+                rows: Some(2_500_000.0),
+            }
+        );
+
+        let mysql = plan_row(json!({
+            "query_block": {
+                "cost_info": {"query_cost": "981.25"},
+                "nested_loop": [
+                    {"table": {"table_name": "orders", "rows_examined_per_scan": 9000}},
+                    {"table": {"table_name": "customers", "rows_examined_per_scan": 1}}
+                ]
+            }
+        }));
+        assert_eq!(
+            parse_plan(&DatabaseType::Mysql, &mysql).unwrap(),
+            PlanEstimate {
+                cost: Some(981.25),
+                rows: Some(9000.0),
+            }
+        );
+    }
+}
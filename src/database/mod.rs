@@ -1,17 +1,23 @@
 pub mod conn_string;
+pub mod credentials;
 pub mod error;
+pub mod explain;
+pub mod generator;
 
-use crate::config::database::DatabaseConfig;
+use crate::chaos::{self, Fault};
+use crate::config::database::{DatabaseConfig, DatabaseType};
+use crate::config::generator_config::GeneratorConfig;
 use crate::config::sql_query::QueryParameter;
 use chrono::Utc;
 use conn_string::{build_connection_string, redacted_connection_description};
 use error::DbError;
+use explain::PlanEstimate;
 use odbc_api::buffers::{AnySlice, BufferDesc, ColumnarAnyBuffer};
 use odbc_api::parameter::InputParameter;
 use odbc_api::sys::{Date, Time, Timestamp};
 use odbc_api::{
     Bit, ColumnDescription, Connection, ConnectionOptions, Cursor, Environment, IntoParameter,
-    Nullability,
+    Nullability, ResultSetMetadata,
 };
 use once_cell::sync::OnceCell;
 use serde_json::{Map, Number, Value};
@@ -26,16 +32,39 @@ pub type QueryParameters = HashMap<String, QueryParameter>;
 pub struct QueryRequest {
     pub sql: String,
     pub parameters: Option<QueryParameters>,
+    /// Stop fetching once this many rows have been read; the rest of the result set is discarded.
+    pub max_rows: Option<usize>,
 }
 
-struct SessionCommand {
-    query: QueryRequest,
-    response: oneshot::Sender<Result<Vec<Map<String, Value>>, DbError>>,
+/// The rows of a query together with its result columns, which are known even when no rows
+/// come back.
+#[derive(Debug, Default)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Map<String, Value>>,
+}
+
+enum SessionCommand {
+    Query {
+        query: QueryRequest,
+        response: oneshot::Sender<Result<ResultSet, DbError>>,
+    },
+    Write {
+        sql: String,
+        rows: Vec<Map<String, Value>>,
+        response: oneshot::Sender<Result<usize, DbError>>,
+    },
+    Prepare {
+        sql: String,
+        response: oneshot::Sender<Result<(), DbError>>,
+    },
 }
 
 pub struct QuerySession {
+    db_type: DatabaseType,
     sender: mpsc::UnboundedSender<SessionCommand>,
     _worker: tokio::task::JoinHandle<()>,
+    _credential_lease: Option<credentials::CredentialLease>,
 }
 
 pub fn env() -> Result<&'static Environment, DbError> {
@@ -46,7 +75,14 @@ pub fn env() -> Result<&'static Environment, DbError> {
 }
 
 pub async fn open_session(db: &DatabaseConfig) -> Result<QuerySession, DbError> {
-    let db = db.clone();
+    if chaos::inject(Fault::DbConnect) {
+        return Err(DbError::Injected(Fault::DbConnect.as_str()));
+    }
+    if let Some(generator) = &db.generator {
+        return Ok(generator_session(db.name.clone(), generator.clone()));
+    }
+    let (db, credential_lease) = credentials::resolve(db).await?;
+    let db_type = db.db_type.clone();
     let (sender, mut receiver) = mpsc::unbounded_channel::<SessionCommand>();
     let (ready_sender, ready_receiver) = oneshot::channel();
     let worker = tokio::task::spawn_blocking(move || {
@@ -71,14 +107,32 @@ pub async fn open_session(db: &DatabaseConfig) -> Result<QuerySession, DbError>
             return;
         }
 
+        let timeout_seconds = db.pool.timeout_seconds.map(|timeout| timeout as usize);
         while let Some(command) = receiver.blocking_recv() {
-            let rows = run_query_on_connection(
-                &connection,
-                &command.query.sql,
-                command.query.parameters.as_ref(),
-                db.pool.timeout_seconds.map(|timeout| timeout as usize),
-            );
-            let _ = command.response.send(rows);
+            match command {
+                SessionCommand::Query { query, response } => {
+                    let result = run_query_on_connection(
+                        &connection,
+                        &query.sql,
+                        query.parameters.as_ref(),
+                        timeout_seconds,
+                        query.max_rows,
+                    );
+                    let _ = response.send(result);
+                }
+                SessionCommand::Write {
+                    sql,
+                    rows,
+                    response,
+                } => {
+                    let written =
+                        write_rows_on_connection(&connection, &sql, &rows, timeout_seconds);
+                    let _ = response.send(written);
+                }
+                SessionCommand::Prepare { sql, response } => {
+                    let _ = response.send(prepare_on_connection(&connection, &sql));
+                }
+            }
         }
     });
 
@@ -86,16 +140,147 @@ pub async fn open_session(db: &DatabaseConfig) -> Result<QuerySession, DbError>
         .await
         .map_err(|_| DbError::Worker("connection worker exited during startup".to_string()))??;
     Ok(QuerySession {
+        db_type,
         sender,
         _worker: worker,
+        _credential_lease: credential_lease,
     })
 }
 
+/// A session that answers every query with generated rows and refuses writes.
+fn generator_session(name: String, generator: GeneratorConfig) -> QuerySession {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<SessionCommand>();
+    let worker = tokio::spawn(async move {
+        while let Some(command) = receiver.recv().await {
+            match command {
+                SessionCommand::Query { query, response } => {
+                    let _ = response.send(Ok(ResultSet {
+                        columns: generator.columns.keys().cloned().collect(),
+                        rows: generator::generate(&generator, query.max_rows),
+                    }));
+                }
+                SessionCommand::Write { response, .. } => {
+                    let _ = response.send(Err(DbError::GeneratorWrite(name.clone())));
+                }
+                SessionCommand::Prepare { response, .. } => {
+                    let _ = response.send(Ok(()));
+                }
+            }
+        }
+    });
+    QuerySession {
+        db_type: DatabaseType::Generator,
+        sender,
+        _worker: worker,
+        _credential_lease: None,
+    }
+}
+
+/// A session that answers queries with `pages` in turn, then with empty results.
+#[cfg(test)]
+pub(crate) fn scripted_session(pages: Vec<ResultSet>) -> QuerySession {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<SessionCommand>();
+    let worker = tokio::spawn(async move {
+        let mut pages = pages.into_iter();
+        while let Some(command) = receiver.recv().await {
+            match command {
+                SessionCommand::Query { response, .. } => {
+                    let _ = response.send(Ok(pages.next().unwrap_or_default()));
+                }
+                SessionCommand::Write { response, .. } => {
+                    let _ = response.send(Ok(0));
+                }
+                SessionCommand::Prepare { response, .. } => {
+                    let _ = response.send(Ok(()));
+                }
+            }
+        }
+    });
+    QuerySession {
+        db_type: DatabaseType::Generator,
+        sender,
+        _worker: worker,
+        _credential_lease: None,
+    }
+}
+
 impl QuerySession {
     pub async fn run(&self, query: QueryRequest) -> Result<Vec<Map<String, Value>>, DbError> {
+        self.query(query).await.map(|result| result.rows)
+    }
+
+    /// Like [`QuerySession::run`], keeping the result columns.
+    pub async fn query(&self, query: QueryRequest) -> Result<ResultSet, DbError> {
+        if chaos::inject(Fault::DbTimeout) {
+            return Err(DbError::Injected(Fault::DbTimeout.as_str()));
+        }
+        let (response, receiver) = oneshot::channel();
+        self.sender
+            .send(SessionCommand::Query { query, response })
+            .map_err(|_| DbError::Worker("query worker is no longer running".to_string()))?;
+        receiver
+            .await
+            .map_err(|_| DbError::Worker("query worker exited before responding".to_string()))?
+    }
+
+    /// Asks the planner for its estimates of `query` without running it.
+    pub async fn explain(&self, query: QueryRequest) -> Result<PlanEstimate, DbError> {
+        let sql = explain::explain_sql(&self.db_type, &query.sql).ok_or_else(|| {
+            DbError::Explain("EXPLAIN is only supported for postgres and mysql".to_string())
+        })?;
+        let rows = self
+            .run(QueryRequest {
+                sql,
+                parameters: query.parameters,
+                max_rows: None,
+            })
+            .await?;
+        explain::parse_plan(&self.db_type, &rows)
+    }
+
+    /// Checks that the database accepts `query` without running it. PostgreSQL and MySQL plan
+    /// it with `EXPLAIN`; other databases, and SQL whose placeholders bind record fields rather
+    /// than parameters, only prepare it.
+    pub async fn validate(&self, query: QueryRequest, binds_records: bool) -> Result<(), DbError> {
+        match explain::explain_sql(&self.db_type, &query.sql).filter(|_| !binds_records) {
+            Some(sql) => self
+                .run(QueryRequest {
+                    sql,
+                    parameters: query.parameters,
+                    max_rows: None,
+                })
+                .await
+                .map(drop),
+            None => {
+                let (response, receiver) = oneshot::channel();
+                self.sender
+                    .send(SessionCommand::Prepare {
+                        sql: query.sql,
+                        response,
+                    })
+                    .map_err(|_| {
+                        DbError::Worker("query worker is no longer running".to_string())
+                    })?;
+                receiver.await.map_err(|_| {
+                    DbError::Worker("query worker exited before responding".to_string())
+                })?
+            }
+        }
+    }
+
+    /// Executes `sql` once per row in a single transaction, binding each `:field`/`$field`
+    /// placeholder to the row's value (NULL when the row lacks the field).
+    pub async fn write(&self, sql: &str, rows: Vec<Map<String, Value>>) -> Result<usize, DbError> {
+        if chaos::inject(Fault::DbTimeout) {
+            return Err(DbError::Injected(Fault::DbTimeout.as_str()));
+        }
         let (response, receiver) = oneshot::channel();
         self.sender
-            .send(SessionCommand { query, response })
+            .send(SessionCommand::Write {
+                sql: sql.to_string(),
+                rows,
+                response,
+            })
             .map_err(|_| DbError::Worker("query worker is no longer running".to_string()))?;
         receiver
             .await
@@ -103,21 +288,85 @@ impl QuerySession {
     }
 }
 
+fn write_rows_on_connection(
+    connection: &Connection<'_>,
+    sql: &str,
+    rows: &[Map<String, Value>],
+    timeout_seconds: Option<usize>,
+) -> Result<usize, DbError> {
+    let (sql, names) = rewrite_named_placeholders(sql, |_| true);
+    if names.is_empty() {
+        return Err(DbError::Parameter(
+            "write SQL has no :field or $field placeholders to bind record values to".to_string(),
+        ));
+    }
+
+    connection
+        .set_autocommit(false)
+        .map_err(DbError::Transaction)?;
+    let result = rows.iter().try_for_each(|row| {
+        let params = names
+            .iter()
+            .map(|name| json_to_input(row.get(name).unwrap_or(&Value::Null)))
+            .collect::<Vec<_>>();
+        connection
+            .execute(&sql, params.as_slice(), timeout_seconds)
+            .map(drop)
+            .map_err(DbError::Execute)
+    });
+    let result = match result {
+        Ok(()) => connection.commit().map_err(DbError::Transaction),
+        Err(error) => {
+            if let Err(rollback) = connection.rollback() {
+                tracing::warn!(error = %rollback, "failed to roll back the write transaction");
+            }
+            Err(error)
+        }
+    };
+    let restored = connection
+        .set_autocommit(true)
+        .map_err(DbError::Transaction);
+    result.and(restored).map(|()| rows.len())
+}
+
+fn prepare_on_connection(connection: &Connection<'_>, sql: &str) -> Result<(), DbError> {
+    let (sql, _) = rewrite_named_placeholders(sql, |_| true);
+    let mut prepared = connection.prepare(&sql).map_err(DbError::Prepare)?;
+    // Drivers that defer preparing until the result set is described resolve names here.
+    prepared.num_result_cols().map_err(DbError::Prepare)?;
+    Ok(())
+}
+
+/// Binds a record value; arrays and objects are written as JSON text.
+fn json_to_input(value: &Value) -> Box<dyn InputParameter> {
+    match value {
+        Value::Null => Box::new(None::<String>.into_parameter()),
+        Value::Bool(value) => Box::new(Bit::from_bool(*value)),
+        Value::Number(number) => match number.as_i64() {
+            Some(value) => Box::new(value),
+            None => Box::new(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(value) => Box::new(value.clone().into_parameter()),
+        other => Box::new(other.to_string().into_parameter()),
+    }
+}
+
 fn run_query_on_connection(
     connection: &Connection<'_>,
     sql: &str,
     parameters: Option<&QueryParameters>,
     timeout_seconds: Option<usize>,
-) -> Result<Vec<Map<String, Value>>, DbError> {
+    max_rows: Option<usize>,
+) -> Result<ResultSet, DbError> {
     let prepared = prepare_sql_and_params(sql, parameters)?;
     let Some(cursor) = connection
         .execute(&prepared.sql, prepared.params.as_slice(), timeout_seconds)
         .map_err(DbError::Execute)?
     else {
-        return Ok(Vec::new());
+        return Ok(ResultSet::default());
     };
 
-    fetch_rows_typed(cursor)
+    fetch_rows_typed(cursor, max_rows)
 }
 
 struct PreparedQuery {
@@ -136,7 +385,8 @@ fn prepare_sql_and_params(
         });
     };
 
-    let (sql, ordered_names) = rewrite_named_placeholders(sql, parameters);
+    let (sql, ordered_names) =
+        rewrite_named_placeholders(sql, |name| parameters.contains_key(name));
     let ordered_names = if ordered_names.is_empty() {
         positional_parameter_names(&sql, parameters)?
     } else {
@@ -169,7 +419,19 @@ fn prepare_sql_and_params(
     Ok(PreparedQuery { sql, params })
 }
 
-fn rewrite_named_placeholders(sql: &str, parameters: &QueryParameters) -> (String, Vec<String>) {
+/// The distinct `$name`/`:name` placeholders of `sql` in order of first use, outside quotes
+/// and comments.
+pub fn placeholder_names(sql: &str) -> Vec<String> {
+    let mut names = rewrite_named_placeholders(sql, |_| true).1;
+    let mut seen = HashSet::new();
+    names.retain(|name| seen.insert(name.clone()));
+    names
+}
+
+fn rewrite_named_placeholders(
+    sql: &str,
+    is_parameter: impl Fn(&str) -> bool,
+) -> (String, Vec<String>) {
     let mut output = String::with_capacity(sql.len());
     let mut names = Vec::new();
     let mut chars = sql.char_indices().peekable();
@@ -225,6 +487,7 @@ fn rewrite_named_placeholders(sql: &str, parameters: &QueryParameters) -> (Strin
             ('"', _) => in_double_quote = true,
             (':' | '$', _) => {
                 if ch == ':' && next == Some(':') {
+                    chars.next();
                     continue;
                 }
                 let name_start = index + ch.len_utf8();
@@ -233,7 +496,7 @@ fn rewrite_named_placeholders(sql: &str, parameters: &QueryParameters) -> (Strin
                     continue;
                 }
                 let name = &sql[name_start..name_end];
-                if parameters.contains_key(name) {
+                if is_parameter(name) {
                     output.push_str(&sql[last..index]);
                     output.push('?');
                     names.push(name.to_string());
@@ -306,7 +569,7 @@ fn positional_parameter_names(
     )))
 }
 
-fn count_positional_placeholders(sql: &str) -> usize {
+pub fn count_positional_placeholders(sql: &str) -> usize {
     let mut count = 0;
     let mut chars = sql.chars().peekable();
     let mut in_single_quote = false;
@@ -437,7 +700,10 @@ fn parse_bool(name: &str, value: &str) -> Result<bool, DbError> {
     }
 }
 
-fn fetch_rows_typed(mut cursor: impl Cursor) -> Result<Vec<Map<String, Value>>, DbError> {
+fn fetch_rows_typed(
+    mut cursor: impl Cursor,
+    max_rows: Option<usize>,
+) -> Result<ResultSet, DbError> {
     let column_count = cursor.num_result_cols().map_err(DbError::Fetch)? as u16;
     let mut columns = Vec::with_capacity(column_count as usize);
     let mut buffer_descs = Vec::with_capacity(column_count as usize);
@@ -461,12 +727,16 @@ fn fetch_rows_typed(mut cursor: impl Cursor) -> Result<Vec<Map<String, Value>>,
         buffer_descs.push(desc);
     }
 
-    let buffers = ColumnarAnyBuffer::try_from_descs(500, buffer_descs).map_err(DbError::Fetch)?;
+    let max_rows = max_rows.unwrap_or(usize::MAX);
+    let buffers = ColumnarAnyBuffer::try_from_descs(max_rows.clamp(1, 500), buffer_descs)
+        .map_err(DbError::Fetch)?;
     let mut row_set_cursor = cursor.bind_buffer(buffers).map_err(DbError::Fetch)?;
     let mut rows = Vec::new();
 
-    while let Some(batch) = row_set_cursor.fetch().map_err(DbError::Fetch)? {
-        for row_index in 0..batch.num_rows() {
+    while rows.len() < max_rows
+        && let Some(batch) = row_set_cursor.fetch().map_err(DbError::Fetch)?
+    {
+        for row_index in 0..batch.num_rows().min(max_rows - rows.len()) {
             let mut row = Map::with_capacity(columns.len());
             for (column_index, column_name) in columns.iter().enumerate() {
                 row.insert(
@@ -478,7 +748,7 @@ fn fetch_rows_typed(mut cursor: impl Cursor) -> Result<Vec<Map<String, Value>>,
         }
     }
 
-    Ok(rows)
+    Ok(ResultSet { columns, rows })
 }
 
 fn clamp_text_buffer(desc: BufferDesc) -> BufferDesc {
@@ -651,6 +921,7 @@ mod tests {
             param_type: param_type.to_string(),
             default: Some(default.to_string()),
             source: None,
+            source_options: None,
         }
     }
 
@@ -682,7 +953,7 @@ mod tests {
         params.insert("real".to_string(), param("string", "value"));
         let (sql, names) = rewrite_named_placeholders(
             "SELECT '$fake', col::text FROM t -- :fake\nWHERE col = :real",
-            &params,
+            |name| params.contains_key(name),
         );
 
         assert_eq!(
@@ -692,6 +963,20 @@ mod tests {
         assert_eq!(vec!["real"], names);
     }
 
+    #[test]
+    fn binds_every_named_placeholder_for_record_writes() {
+        let (sql, names) = rewrite_named_placeholders(
+            "INSERT INTO items (id, payload) VALUES (:id, CAST($payload AS jsonb)::jsonb)",
+            |_| true,
+        );
+
+        assert_eq!(
+            "INSERT INTO items (id, payload) VALUES (?, CAST(? AS jsonb)::jsonb)",
+            sql
+        );
+        assert_eq!(vec!["id", "payload"], names);
+    }
+
     #[test]
     fn rejects_ambiguous_positional_parameters() {
         let mut params = QueryParameters::new();
@@ -0,0 +1,553 @@
+//! Append-only run history: one JSON line per query execution, whatever triggered it, and one
+//! per flow run, read back by `yetii history`. Compaction moves entries outside the retention limits to gzipped archives.
+
+use crate::config::execution_config::{HistoryArchiveConfig, HistoryConfig, StateBackendConfig};
+use crate::config::yetii::YetiiConfig;
+use crate::sinks::s3::{S3Error, S3Sink};
+use chrono::{DateTime, Utc};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const FILE_NAME: &str = "history.jsonl";
+const DEFAULT_DIRECTORY: &str = ".yetii";
+
+/// Held while a line is appended or the file is replaced, so compaction never drops an entry
+/// appended while it ran.
+static WRITE: Mutex<()> = Mutex::new(());
+/// One compaction at a time, however many runs finish together.
+static COMPACTION: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("history file '{path}' failed: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("history file '{path}' line {line} is invalid: {source}")]
+    InvalidEntry {
+        path: PathBuf,
+        line: usize,
+        source: serde_json::Error,
+    },
+    #[error("history archive upload failed: {0}")]
+    Upload(#[from] S3Error),
+}
+
+/// What started an execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    /// `yetii run`, including `--flow`.
+    Manual,
+    /// The daemon's cron schedule.
+    Schedule,
+    /// The daemon's `run_on_start`.
+    Startup,
+}
+
+/// What an entry records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryKind {
+    #[default]
+    Query,
+    /// A whole flow; each step's query has its own entry naming the flow in `flow`.
+    Flow,
+}
+
+impl HistoryKind {
+    fn is_query(&self) -> bool {
+        *self == HistoryKind::Query
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryStatus {
+    Success,
+    /// Some batches were delivered and others failed.
+    Partial,
+    Failure,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    #[serde(default, skip_serializing_if = "HistoryKind::is_query")]
+    pub kind: HistoryKind,
+    /// The query, or the flow of a `flow` entry.
+    pub query: String,
+    /// The flow the query ran in as a step.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flow: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    pub trigger: Trigger,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub status: HistoryStatus,
+    pub rows_read: usize,
+    pub rows_delivered: usize,
+    pub batches_sent: usize,
+    pub batches_failed: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl fmt::Display for HistoryEntry {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self.status {
+            HistoryStatus::Success => "success",
+            HistoryStatus::Partial => "partial",
+            HistoryStatus::Failure => "failure",
+        };
+        let trigger = match self.trigger {
+            Trigger::Manual => "manual",
+            Trigger::Schedule => "schedule",
+            Trigger::Startup => "startup",
+        };
+        let name = match self.kind {
+            HistoryKind::Query => self.query.clone(),
+            HistoryKind::Flow => format!("flow:{}", self.query),
+        };
+        write!(
+            formatter,
+            "{} {name} {status} trigger={trigger} duration_ms={} rows_read={} rows_delivered={} batches_sent={} batches_failed={}",
+            self.started_at
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            (self.finished_at - self.started_at).num_milliseconds(),
+            self.rows_read,
+            self.rows_delivered,
+            self.batches_sent,
+            self.batches_failed,
+        )?;
+        if let Some(flow) = &self.flow {
+            write!(formatter, " flow={flow}")?;
+        }
+        if let Some(run_id) = &self.run_id {
+            write!(formatter, " run_id={run_id}")?;
+        }
+        if let Some(error) = &self.error {
+            write!(formatter, " error={error:?}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryLog {
+    path: PathBuf,
+}
+
+impl HistoryLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The configured history, `None` when `execution.history.enabled` is false. Without a
+    /// `file`, it is kept in the directory of the state file or SQLite database.
+    pub fn from_config(config: &YetiiConfig) -> Option<Self> {
+        let history = config.execution.history.as_ref();
+        if history.is_some_and(|history| !history.enabled) {
+            return None;
+        }
+        if let Some(file) = history.and_then(|history| history.file.as_deref()) {
+            return Some(Self::new(file));
+        }
+        let state_path = config
+            .execution
+            .state_management
+            .as_ref()
+            .filter(|state_management| state_management.enabled)
+            .and_then(|state_management| match &state_management.backend {
+                None | Some(StateBackendConfig::File) => Some(&state_management.state_file),
+                Some(StateBackendConfig::Sqlite { path }) => Some(path),
+                Some(StateBackendConfig::Redis { .. }) => None,
+            });
+        let directory = match state_path.and_then(|path| Path::new(path).parent()) {
+            Some(parent) => parent.to_path_buf(),
+            None => PathBuf::from(DEFAULT_DIRECTORY),
+        };
+        Some(Self::new(directory.join(FILE_NAME)))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends one line. Each line is written in a single call, so entries from concurrent
+    /// daemon runs do not interleave.
+    pub fn append(&self, entry: &HistoryEntry) -> Result<(), HistoryError> {
+        let io_error = |source| HistoryError::Io {
+            path: self.path.clone(),
+            source,
+        };
+        if let Some(parent) = self
+            .path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let mut line = serde_json::to_vec(entry).expect("history entries serialize");
+        line.push(b'\n');
+        let _write = WRITE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(&line))
+            .map_err(io_error)
+    }
+
+    /// The last `limit` entries, of `query` only when given, oldest first. A missing file is
+    /// an empty history.
+    pub fn read(
+        &self,
+        query: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>, HistoryError> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => {
+                return Err(HistoryError::Io {
+                    path: self.path.clone(),
+                    source,
+                });
+            }
+        };
+        let mut entries = VecDeque::with_capacity(limit);
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|source| HistoryError::Io {
+                path: self.path.clone(),
+                source,
+            })?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: HistoryEntry =
+                serde_json::from_str(&line).map_err(|source| HistoryError::InvalidEntry {
+                    path: self.path.clone(),
+                    line: index + 1,
+                    source,
+                })?;
+            if query.is_some_and(|query| query != entry.query) {
+                continue;
+            }
+            if entries.len() == limit {
+                entries.pop_front();
+            }
+            if limit > 0 {
+                entries.push_back(entry);
+            }
+        }
+        Ok(entries.into())
+    }
+
+    /// Removes the entries outside `retention`, archiving them first when `archive` is set.
+    /// Entries appended while the archive uploads are kept. With `batched`, nothing is removed
+    /// until the entries due reach a tenth of those kept, so archives are not one entry each.
+    pub async fn compact(
+        &self,
+        retention: &Retention,
+        archive: Option<&HistoryArchiveConfig>,
+        batched: bool,
+        now: DateTime<Utc>,
+    ) -> Result<Compaction, HistoryError> {
+        let _compacting = COMPACTION.lock().await;
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(Compaction::default());
+            }
+            Err(source) => {
+                return Err(HistoryError::Io {
+                    path: self.path.clone(),
+                    source,
+                });
+            }
+        };
+        let (kept, removed) = self.split(&contents, retention, now)?;
+        if removed.is_empty() || (batched && removed.len() < kept.len() / 10) {
+            return Ok(Compaction {
+                kept: kept.len() + removed.len(),
+                ..Compaction::default()
+            });
+        }
+        let mut compaction = Compaction {
+            kept: kept.len(),
+            removed: removed.len(),
+            archive: None,
+        };
+        if let Some(archive) = archive {
+            compaction.archive = Some(self.archive(archive, &removed, now).await?);
+        }
+        self.replace(contents.len(), &kept)?;
+        Ok(compaction)
+    }
+
+    /// The lines to keep and to remove, each oldest first.
+    fn split<'a>(
+        &self,
+        contents: &'a str,
+        retention: &Retention,
+        now: DateTime<Utc>,
+    ) -> Result<(Vec<&'a str>, Vec<&'a str>), HistoryError> {
+        // An age too large to subtract keeps every entry.
+        let cutoff = retention
+            .max_age_seconds
+            .and_then(|seconds| chrono::Duration::try_seconds(i64::try_from(seconds).ok()?))
+            .and_then(|max_age| now.checked_sub_signed(max_age));
+        let mut kept = Vec::new();
+        let mut removed = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: HistoryEntry =
+                serde_json::from_str(line).map_err(|source| HistoryError::InvalidEntry {
+                    path: self.path.clone(),
+                    line: index + 1,
+                    source,
+                })?;
+            if cutoff.is_some_and(|cutoff| entry.finished_at < cutoff) {
+                removed.push(line);
+            } else {
+                kept.push(line);
+            }
+        }
+        if let Some(max_entries) = retention.max_entries
+            && kept.len() > max_entries
+        {
+            removed.extend(kept.drain(..kept.len() - max_entries));
+        }
+        Ok((kept, removed))
+    }
+
+    /// Writes `lines` gzipped to the archive directory or bucket; returns where they went.
+    async fn archive(
+        &self,
+        archive: &HistoryArchiveConfig,
+        lines: &[&str],
+        now: DateTime<Utc>,
+    ) -> Result<String, HistoryError> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        for line in lines {
+            encoder
+                .write_all(line.as_bytes())
+                .and_then(|()| encoder.write_all(b"\n"))
+                .expect("writing to memory succeeds");
+        }
+        let bytes = encoder.finish().expect("writing to memory succeeds");
+
+        if let Some(s3) = &archive.s3 {
+            let sink = S3Sink::new(s3, "history", Vec::new())?;
+            return Ok(sink.upload("application/gzip", &bytes).await?);
+        }
+        let directory = Path::new(archive.directory.as_deref().unwrap_or(DEFAULT_DIRECTORY));
+        let path = directory.join(format!("history-{}.jsonl.gz", now.format("%Y%m%dT%H%M%SZ")));
+        fs::create_dir_all(directory)
+            .and_then(|()| OpenOptions::new().write(true).create_new(true).open(&path))
+            .and_then(|mut file| file.write_all(&bytes))
+            .map_err(|source| HistoryError::Io {
+                path: path.clone(),
+                source,
+            })?;
+        Ok(path.display().to_string())
+    }
+
+    /// Replaces the file with `kept` and whatever was appended past `read_length`.
+    fn replace(&self, read_length: usize, kept: &[&str]) -> Result<(), HistoryError> {
+        let io_error = |source| HistoryError::Io {
+            path: self.path.clone(),
+            source,
+        };
+        let _write = WRITE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let current = fs::read(&self.path).map_err(io_error)?;
+        let mut contents = Vec::with_capacity(current.len());
+        for line in kept {
+            contents.extend_from_slice(line.as_bytes());
+            contents.push(b'\n');
+        }
+        contents.extend_from_slice(current.get(read_length..).unwrap_or_default());
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        fs::write(&temporary, contents)
+            .and_then(|()| fs::rename(&temporary, &self.path))
+            .map_err(io_error)
+    }
+}
+
+/// Which entries compaction keeps; no limit keeps everything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Retention {
+    pub max_entries: Option<usize>,
+    pub max_age_seconds: Option<u64>,
+}
+
+impl Retention {
+    pub fn from_config(config: &HistoryConfig) -> Self {
+        Self {
+            max_entries: config.max_entries,
+            max_age_seconds: config.max_age_seconds,
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_entries.is_none() && self.max_age_seconds.is_none()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Compaction {
+    pub kept: usize,
+    pub removed: usize,
+    /// Archive file or `s3://` object of the removed entries.
+    pub archive: Option<String>,
+}
+
+/// Compacts the history after a run when `execution.history` sets retention limits. Failures
+/// are logged; the history keeps growing until a later compaction succeeds.
+pub async fn compact_configured(config: &YetiiConfig) {
+    let Some(history_config) = config.execution.history.as_ref() else {
+        return;
+    };
+    let retention = Retention::from_config(history_config);
+    let Some(history) = HistoryLog::from_config(config).filter(|_| !retention.is_unlimited())
+    else {
+        return;
+    };
+    match history
+        .compact(
+            &retention,
+            history_config.archive.as_ref(),
+            true,
+            Utc::now(),
+        )
+        .await
+    {
+        Ok(compaction) if compaction.removed > 0 => tracing::info!(
+            removed = compaction.removed,
+            kept = compaction.kept,
+            archive = compaction.archive.as_deref().unwrap_or("none"),
+            "run history compacted"
+        ),
+        Ok(_) => {}
+        Err(error) => tracing::warn!(error = %error, "run history compaction failed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(query: &str, status: HistoryStatus) -> HistoryEntry {
+        let started_at = "2026-10-16T08:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        HistoryEntry {
+            kind: HistoryKind::Query,
+            query: query.to_string(),
+            flow: None,
+            run_id: Some("00000000000000aa".to_string()),
+            trigger: Trigger::Schedule,
+            started_at,
+            finished_at: started_at + chrono::Duration::milliseconds(1500),
+            status,
+            rows_read: 10,
+            rows_delivered: 8,
+            batches_sent: 4,
+            batches_failed: 1,
+            error: (status != HistoryStatus::Success).then(|| "batch 5: HTTP 500".to_string()),
+        }
+    }
+
+    #[test]
+    fn appends_and_reads_back_the_latest_entries() {
+        let path = std::env::temp_dir().join(format!(
+            "yetii-history-test-{}/history.jsonl",
+            std::process::id()
+        ));
+        let history = HistoryLog::new(&path);
+        assert!(history.read(None, 10).unwrap().is_empty());
+
+        history
+            .append(&entry("orders", HistoryStatus::Success))
+            .unwrap();
+        history
+            .append(&entry("customers", HistoryStatus::Success))
+            .unwrap();
+        history
+            .append(&entry("orders", HistoryStatus::Partial))
+            .unwrap();
+
+        let latest = history.read(None, 2).unwrap();
+        assert_eq!(
+            vec!["customers", "orders"],
+            latest
+                .iter()
+                .map(|entry| entry.query.as_str())
+                .collect::<Vec<_>>()
+        );
+        let orders = history.read(Some("orders"), 10).unwrap();
+        assert_eq!(2, orders.len());
+        assert_eq!(
+            "2026-10-16T08:00:00Z orders partial trigger=schedule duration_ms=1500 rows_read=10 rows_delivered=8 batches_sent=4 batches_failed=1 run_id=00000000000000aa error=\"batch 5: HTTP 500\"",
+            orders[1].to_string()
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn compacts_old_and_excess_entries_into_an_archive() {
+        let directory =
+            std::env::temp_dir().join(format!("yetii-history-compact-{}", std::process::id()));
+        let history = HistoryLog::new(directory.join("history.jsonl"));
+        let now = "2026-10-16T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        for (query, days_ago) in [("stale", 40), ("a", 3), ("b", 2), ("c", 1)] {
+            let mut entry = entry(query, HistoryStatus::Success);
+            entry.finished_at = now - chrono::Duration::days(days_ago);
+            history.append(&entry).unwrap();
+        }
+        let archive = HistoryArchiveConfig {
+            directory: Some(directory.join("archive").display().to_string()),
+            s3: None,
+        };
+
+        let retention = Retention {
+            max_entries: Some(2),
+            max_age_seconds: Some(30 * 86_400),
+        };
+        let compaction = history
+            .compact(&retention, Some(&archive), false, now)
+            .await
+            .unwrap();
+
+        assert_eq!((2, 2), (compaction.kept, compaction.removed));
+        let queries = |entries: Vec<HistoryEntry>| {
+            entries
+                .into_iter()
+                .map(|entry| entry.query)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["b", "c"], queries(history.read(None, 10).unwrap()));
+        let archived = HistoryLog::new(directory.join("archived.jsonl"));
+        let mut decoder =
+            flate2::read::GzDecoder::new(File::open(compaction.archive.unwrap()).unwrap());
+        io::copy(&mut decoder, &mut File::create(archived.path()).unwrap()).unwrap();
+        assert_eq!(
+            vec!["stale", "a"],
+            queries(archived.read(None, 10).unwrap())
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+use apache_avro::schema::{InnerDecimalSchema, Name, ResolvedSchema};
+use apache_avro::types::Value as Avro;
+use apache_avro::{Decimal, Schema, Writer};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A parsed Avro schema, which is embedded in every container file.
+#[derive(Debug, Clone)]
+pub struct AvroSchema {
+    /// Boxed because a parsed schema is several hundred bytes and lives in every sink.
+    schema: Box<Schema>,
+}
+
+impl AvroSchema {
+    pub fn parse(json: &Value) -> Result<Self, String> {
+        let schema = Schema::parse(json).map_err(|error| error.to_string())?;
+        if !matches!(schema, Schema::Record(_)) {
+            return Err("the top-level type must be a record".to_string());
+        }
+        Ok(Self {
+            schema: Box::new(schema),
+        })
+    }
+
+    /// Writes the rows as an object container file. Blocks are not compressed
+    /// (`avro.codec` is `null`).
+    pub fn encode_container(&self, rows: &[&Value]) -> Result<Vec<u8>, String> {
+        let resolved =
+            ResolvedSchema::try_from(&*self.schema).map_err(|error| error.to_string())?;
+        let names = resolved.get_names();
+        let mut writer =
+            Writer::new(&self.schema, Vec::new()).map_err(|error| error.to_string())?;
+        for (index, row) in rows.iter().enumerate() {
+            to_avro(&self.schema, row, names)
+                .resolve_with_names(&self.schema, names)
+                .and_then(|value| writer.append_value(value))
+                .map_err(|error| format!("row {}: {error}", index + 1))?;
+        }
+        writer.into_inner().map_err(|error| error.to_string())
+    }
+}
+
+/// Maps a row value onto `schema`, converting the strings drivers return for numbers, dates,
+/// timestamps, and decimals. Anything left unconverted is rejected when the value is resolved
+/// against the schema.
+fn to_avro<S: std::borrow::Borrow<Schema> + std::fmt::Debug>(
+    schema: &Schema,
+    value: &Value,
+    names: &HashMap<Name, S>,
+) -> Avro {
+    match (schema, value) {
+        (Schema::Ref { name }, _) => match names.get(name) {
+            Some(schema) => to_avro(schema.borrow(), value, names),
+            None => fallback(value),
+        },
+        (Schema::Record(record), Value::Object(object)) => Avro::Record(
+            record
+                .fields
+                .iter()
+                .filter_map(|field| match object.get(&field.name) {
+                    Some(value) => Some((field.name.clone(), to_avro(&field.schema, value, names))),
+                    // Fields without a default that allow null are left null; the rest are
+                    // filled from their default, or reported missing, by resolution.
+                    None if field.default.is_none() && accepts_null(&field.schema) => {
+                        Some((field.name.clone(), Avro::Null))
+                    }
+                    None => None,
+                })
+                .collect(),
+        ),
+        (Schema::Union(union), _) => {
+            // The first branch that accepts the value is written.
+            for (index, variant) in union.variants().iter().enumerate() {
+                let candidate = to_avro(variant, value, names);
+                if candidate.clone().resolve_with_names(variant, names).is_ok() {
+                    return Avro::Union(index as u32, Box::new(candidate));
+                }
+            }
+            fallback(value)
+        }
+        (Schema::Array(array), Value::Array(items)) => Avro::Array(
+            items
+                .iter()
+                .map(|item| to_avro(&array.items, item, names))
+                .collect(),
+        ),
+        (Schema::Map(map), Value::Object(object)) => Avro::Map(
+            object
+                .iter()
+                .map(|(key, item)| (key.clone(), to_avro(&map.types, item, names)))
+                .collect(),
+        ),
+        // Integers as strings, as some drivers return for `BIGINT`.
+        (Schema::Int | Schema::Long, Value::String(text)) => text
+            .trim()
+            .parse()
+            .map(Avro::Long)
+            .unwrap_or_else(|_| fallback(value)),
+        (Schema::Float | Schema::Double, Value::String(text)) => text
+            .trim()
+            .parse()
+            .map(Avro::Double)
+            .unwrap_or_else(|_| fallback(value)),
+        (Schema::String, Value::Number(_) | Value::Bool(_)) => Avro::String(value.to_string()),
+        // Checked against the size on resolution, which strings are not.
+        (Schema::Fixed(_), Value::String(text)) => Avro::Bytes(text.as_bytes().to_vec()),
+        (Schema::Date, Value::String(text)) => NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| i32::try_from((date - NaiveDate::default()).num_days()).ok())
+            .map(Avro::Date)
+            .unwrap_or_else(|| fallback(value)),
+        (Schema::TimestampMillis, Value::String(text)) => parse_timestamp(text)
+            .map(|timestamp| Avro::TimestampMillis(timestamp.and_utc().timestamp_millis()))
+            .unwrap_or_else(|| fallback(value)),
+        (Schema::TimestampMicros, Value::String(text)) => parse_timestamp(text)
+            .map(|timestamp| Avro::TimestampMicros(timestamp.and_utc().timestamp_micros()))
+            .unwrap_or_else(|| fallback(value)),
+        (Schema::Decimal(decimal), Value::String(_) | Value::Number(_)) => {
+            // The library checks the byte length, not the value, against the precision.
+            let len = match &decimal.inner {
+                InnerDecimalSchema::Fixed(fixed) => fixed.size,
+                InnerDecimalSchema::Bytes => (1..16)
+                    .find(|len| (2f64.powi(8 * len - 1) - 1.0).log10() >= decimal.precision as f64)
+                    .unwrap_or(16) as usize,
+            };
+            unscaled_decimal(value, decimal.scale)
+                .and_then(|unscaled| sign_extended(unscaled, len))
+                .map(|bytes| Avro::Decimal(Decimal::from(bytes)))
+                .unwrap_or_else(|| fallback(value))
+        }
+        _ => fallback(value),
+    }
+}
+
+fn fallback(value: &Value) -> Avro {
+    Avro::try_from(value.clone()).unwrap_or(Avro::String(value.to_string()))
+}
+
+fn accepts_null(schema: &Schema) -> bool {
+    match schema {
+        Schema::Null => true,
+        Schema::Union(union) => union.variants().iter().any(accepts_null),
+        _ => false,
+    }
+}
+
+/// RFC 3339 timestamps, or naive `YYYY-MM-DD HH:MM:SS[.fff]` timestamps taken as UTC.
+fn parse_timestamp(text: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|timestamp| timestamp.naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f"))
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f"))
+        .ok()
+}
+
+/// `12.5` at scale 2 is 1250. Extra fractional digits are rejected rather than rounded.
+fn unscaled_decimal(value: &Value, scale: usize) -> Option<i128> {
+    let text = match value {
+        Value::Number(number) => number.to_string(),
+        Value::String(text) => text.trim().to_string(),
+        _ => return None,
+    };
+    if text.contains(['e', 'E']) {
+        let scaled = text.parse::<f64>().ok()? * 10f64.powi(scale as i32);
+        return (scaled.fract() == 0.0).then_some(scaled as i128);
+    }
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.as_str()),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > scale
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|char| char.is_ascii_digit())
+    {
+        return None;
+    }
+    let digits = format!("{whole}{fraction:0<width$}", width = scale);
+    let unscaled = digits.parse::<i128>().ok()?;
+    Some(if negative { -unscaled } else { unscaled })
+}
+
+/// Two's-complement bytes of `len`, or `None` when the value does not fit.
+fn sign_extended(unscaled: i128, len: usize) -> Option<Vec<u8>> {
+    let bytes = unscaled.to_be_bytes();
+    let len = len.min(bytes.len());
+    let (dropped, kept) = bytes.split_at(bytes.len() - len);
+    let sign = if unscaled < 0 { 0xff } else { 0x00 };
+    (dropped.iter().all(|byte| *byte == sign) && (kept[0] & 0x80 != 0) == (unscaled < 0))
+        .then(|| kept.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apache_avro::Reader;
+
+    #[test]
+    fn encodes_rows_into_a_container_file() {
+        let schema = AvroSchema::parse(&serde_json::json!({
+            "type": "record",
+            "name": "Order",
+            "namespace": "erp.orders",
+            "fields": [
+                {"name": "id", "type": "long"},
+                {"name": "customer", "type": ["null", "string"]},
+                {"name": "placed_on", "type": {"type": "int", "logicalType": "date"}},
+                {"name": "total", "type": {"type": "bytes", "logicalType": "decimal", "precision": 9, "scale": 2}},
+                {"name": "status", "type": {"type": "enum", "name": "Status", "symbols": ["open", "paid"]}},
+                {"name": "tags", "type": {"type": "array", "items": "string"}, "default": []}
+            ]
+        }))
+        .unwrap();
+        let row = serde_json::json!({
+            "id": "1", "customer": "Ada", "placed_on": "1970-01-03", "total": "-1.5", "status": "paid"
+        });
+
+        let bytes = schema.encode_container(&[&row]).unwrap();
+        assert!(bytes.starts_with(b"Obj\x01"));
+        let records = Reader::new(bytes.as_slice())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            vec![Avro::Record(vec![
+                ("id".to_string(), Avro::Long(1)),
+                (
+                    "customer".to_string(),
+                    Avro::Union(1, Box::new(Avro::String("Ada".to_string())))
+                ),
+                ("placed_on".to_string(), Avro::Date(2)),
+                (
+                    "total".to_string(),
+                    Avro::Decimal(Decimal::from([0xff, 0x6a]))
+                ),
+                ("status".to_string(), Avro::Enum(1, "paid".to_string())),
+                ("tags".to_string(), Avro::Array(Vec::new())),
+            ])],
+            records
+        );
+
+        let missing = serde_json::json!({"customer": null});
+        let error = schema.encode_container(&[&missing]).unwrap_err();
+        assert!(
+            error.starts_with("row 1:") && error.contains("\"id\""),
+            "{error}"
+        );
+        assert!(AvroSchema::parse(&serde_json::json!("string")).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+//! Per-endpoint circuit breakers. They are shared by every query of the process that delivers
+//! to the same endpoint, so an endpoint that keeps failing stays paused across the daemon's
+//! scheduled runs. The state lives in memory only and starts closed in every new process.
+
+use crate::config::request_config::CircuitBreakerConfig;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+static BREAKERS: LazyLock<Mutex<HashMap<String, Arc<CircuitBreaker>>>> =
+    LazyLock::new(Default::default);
+
+pub(crate) struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// The cool-down has passed and one probe batch, sent at `since`, is in flight.
+    HalfOpen {
+        since: Instant,
+    },
+}
+
+/// A change worth telling someone about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transition {
+    Opened { failures: u32 },
+    Closed,
+}
+
+impl CircuitBreaker {
+    /// The breaker for `key` (method and URL). It is created with `config` on first use and
+    /// replaced, closed, when a reloaded configuration changes the threshold or cool-down.
+    pub(crate) fn for_endpoint(key: &str, config: &CircuitBreakerConfig) -> Arc<Self> {
+        let mut breakers = BREAKERS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let breaker = Self::new(config);
+        match breakers.get(key) {
+            Some(existing)
+                if existing.threshold == breaker.threshold
+                    && existing.cooldown == breaker.cooldown =>
+            {
+                existing.clone()
+            }
+            _ => {
+                let breaker = Arc::new(breaker);
+                breakers.insert(key.to_string(), breaker.clone());
+                breaker
+            }
+        }
+    }
+
+    fn new(config: &CircuitBreakerConfig) -> Self {
+        Self {
+            threshold: config.failure_threshold.unwrap_or(5).max(1),
+            cooldown: Duration::from_secs(config.cooldown_seconds.unwrap_or(60) as u64),
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    pub(crate) fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+
+    /// Whether a batch may be sent now; when the circuit is open, how long until the next probe.
+    /// A probe that has not been recorded within a cool-down, for example because its run was
+    /// cancelled, is given up and another batch is let through in its place.
+    pub(crate) fn acquire(&self, now: Instant) -> Result<(), Duration> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match *state {
+            State::Closed { .. } => Ok(()),
+            State::Open { until } if now < until => Err(until - now),
+            State::HalfOpen { since } if now < since + self.cooldown => {
+                Err(since + self.cooldown - now)
+            }
+            State::Open { .. } | State::HalfOpen { .. } => {
+                *state = State::HalfOpen { since: now };
+                Ok(())
+            }
+        }
+    }
+
+    /// Records the outcome of a batch let through by [`acquire`](Self::acquire).
+    pub(crate) fn record(&self, failed: bool, now: Instant) -> Option<Transition> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (next, transition) = match (*state, failed) {
+            (State::HalfOpen { .. }, false) => {
+                (State::Closed { failures: 0 }, Some(Transition::Closed))
+            }
+            (_, false) => (State::Closed { failures: 0 }, None),
+            (State::Closed { failures }, true) if failures + 1 >= self.threshold => (
+                State::Open {
+                    until: now + self.cooldown,
+                },
+                Some(Transition::Opened {
+                    failures: failures + 1,
+                }),
+            ),
+            (State::Closed { failures }, true) => (
+                State::Closed {
+                    failures: failures + 1,
+                },
+                None,
+            ),
+            // A failed probe pauses delivery for another cool-down without a new notification.
+            (State::HalfOpen { .. } | State::Open { .. }, true) => (
+                State::Open {
+                    until: now + self.cooldown,
+                },
+                None,
+            ),
+        };
+        *state = next;
+        transition
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_threshold_and_closes_after_a_successful_probe() {
+        let breaker = CircuitBreaker::new(&CircuitBreakerConfig {
+            failure_threshold: Some(2),
+            cooldown_seconds: Some(30),
+        });
+        let start = Instant::now();
+
+        assert_eq!(Ok(()), breaker.acquire(start));
+        assert_eq!(None, breaker.record(true, start));
+        assert_eq!(
+            Some(Transition::Opened { failures: 2 }),
+            breaker.record(true, start)
+        );
+        assert_eq!(
+            Err(Duration::from_secs(20)),
+            breaker.acquire(start + Duration::from_secs(10))
+        );
+
+        let probe = start + Duration::from_secs(30);
+        assert_eq!(Ok(()), breaker.acquire(probe));
+        assert_eq!(Err(Duration::from_secs(30)), breaker.acquire(probe));
+        assert_eq!(None, breaker.record(true, probe));
+        assert!(breaker.acquire(probe + Duration::from_secs(29)).is_err());
+
+        let second_probe = probe + Duration::from_secs(30);
+        assert_eq!(Ok(()), breaker.acquire(second_probe));
+        assert_eq!(
+            Some(Transition::Closed),
+            breaker.record(false, second_probe)
+        );
+        assert_eq!(Ok(()), breaker.acquire(second_probe));
+    }
+
+    #[test]
+    fn an_abandoned_probe_is_replaced_after_a_cooldown() {
+        let breaker = CircuitBreaker::new(&CircuitBreakerConfig {
+            failure_threshold: Some(1),
+            cooldown_seconds: Some(30),
+        });
+        let start = Instant::now();
+        breaker.record(true, start);
+
+        // The probe's run is cancelled before it records an outcome.
+        let probe = start + Duration::from_secs(30);
+        assert_eq!(Ok(()), breaker.acquire(probe));
+        assert_eq!(
+            Err(Duration::from_secs(20)),
+            breaker.acquire(probe + Duration::from_secs(10))
+        );
+
+        let retry = probe + Duration::from_secs(30);
+        assert_eq!(Ok(()), breaker.acquire(retry));
+        assert_eq!(Some(Transition::Closed), breaker.record(false, retry));
+    }
+
+    #[test]
+    fn a_changed_config_rebuilds_the_shared_breaker() {
+        let key = "POST http://circuit.test/changed";
+        let config = CircuitBreakerConfig {
+            failure_threshold: Some(1),
+            cooldown_seconds: Some(30),
+        };
+        let breaker = CircuitBreaker::for_endpoint(key, &config);
+        breaker.record(true, Instant::now());
+        assert!(Arc::ptr_eq(
+            &breaker,
+            &CircuitBreaker::for_endpoint(key, &config)
+        ));
+
+        let reloaded = CircuitBreaker::for_endpoint(
+            key,
+            &CircuitBreakerConfig {
+                cooldown_seconds: Some(5),
+                ..config
+            },
+        );
+        assert_eq!(Duration::from_secs(5), reloaded.cooldown());
+        assert_eq!(Ok(()), reloaded.acquire(Instant::now()));
+    }
+}
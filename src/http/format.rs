@@ -0,0 +1,624 @@
+use crate::config::request_config::{
+    AvroOptions, CsvOptions, CsvQuoteStyle, FormatOptions, ProtobufOptions, XmlOptions,
+};
+use crate::http::avro::AvroSchema;
+use prost_reflect::prost::Message;
+use prost_reflect::{DescriptorPool, DeserializeOptions, DynamicMessage, Kind, MessageDescriptor};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+
+/// Request body formats; `format:` values are lower case, and upper case is accepted too.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadFormat {
+    #[default]
+    #[serde(alias = "JSON")]
+    Json,
+    /// Newline-delimited JSON: one row per line, each line terminated by `\n`.
+    #[serde(alias = "NDJSON")]
+    Ndjson,
+    /// One CSV record per row, laid out by [`BodyLayout`].
+    #[serde(alias = "CSV")]
+    Csv,
+    /// One element per row inside a root element, laid out by [`BodyLayout`].
+    #[serde(alias = "XML")]
+    Xml,
+    /// Avro object container file written with [`BodyLayout::avro`].
+    #[serde(alias = "AVRO")]
+    Avro,
+    /// Protobuf messages of [`BodyLayout::protobuf`].
+    #[serde(alias = "PROTOBUF")]
+    Protobuf,
+}
+
+/// How non-JSON bodies are laid out; the JSON formats ignore it.
+#[derive(Debug, Clone, Default)]
+pub struct BodyLayout {
+    pub csv: CsvOptions,
+    pub xml: XmlOptions,
+    pub avro: Option<AvroSchema>,
+    pub protobuf: Option<ProtobufLayout>,
+    /// Fields written first, as CSV columns when `csv.columns` is not set and as the first
+    /// child elements in XML; usually the mapping targets.
+    pub leading_columns: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProtobufLayout {
+    message: MessageDescriptor,
+    batch_field: Option<String>,
+    ignore_unknown_fields: bool,
+}
+
+impl FormatOptions {
+    /// Builds the body layout, reading the Avro schema and protobuf descriptor set the options
+    /// name.
+    pub fn layout(&self) -> Result<BodyLayout, FormatError> {
+        Ok(BodyLayout {
+            csv: self.csv.clone().unwrap_or_default(),
+            xml: self.xml.clone().unwrap_or_default(),
+            avro: self.avro.as_ref().map(load_avro_schema).transpose()?,
+            protobuf: self
+                .protobuf
+                .as_ref()
+                .map(load_protobuf_message)
+                .transpose()?,
+            leading_columns: Vec::new(),
+        })
+    }
+}
+
+fn load_avro_schema(options: &AvroOptions) -> Result<AvroSchema, FormatError> {
+    let error = |reason: String| FormatError::Schema {
+        format: "avro",
+        path: options.schema_file.clone(),
+        reason,
+    };
+    let text = std::fs::read_to_string(&options.schema_file)
+        .map_err(|source| error(source.to_string()))?;
+    let json = serde_json::from_str(&text).map_err(|source| error(source.to_string()))?;
+    AvroSchema::parse(&json).map_err(error)
+}
+
+fn load_protobuf_message(options: &ProtobufOptions) -> Result<ProtobufLayout, FormatError> {
+    let error = |reason: String| FormatError::Schema {
+        format: "protobuf",
+        path: options.descriptor_set.clone(),
+        reason,
+    };
+    let bytes =
+        std::fs::read(&options.descriptor_set).map_err(|source| error(source.to_string()))?;
+    let pool =
+        DescriptorPool::decode(bytes.as_slice()).map_err(|source| error(source.to_string()))?;
+    let message = pool
+        .get_message_by_name(options.message.trim_start_matches('.'))
+        .ok_or_else(|| error(format!("message '{}' is not defined", options.message)))?;
+    if let Some(field) = &options.batch_field {
+        let valid = message.get_field_by_name(field).is_some_and(|descriptor| {
+            descriptor.is_list() && matches!(descriptor.kind(), Kind::Message(_))
+        });
+        if !valid {
+            return Err(error(format!(
+                "batch_field '{field}' is not a repeated message field of {}",
+                message.full_name()
+            )));
+        }
+    }
+    Ok(ProtobufLayout {
+        message,
+        batch_field: options.batch_field.clone(),
+        ignore_unknown_fields: options.ignore_unknown_fields,
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FormatError {
+    #[error("failed to serialize {format} payload: {reason}")]
+    Serialize {
+        format: &'static str,
+        reason: String,
+    },
+    #[error("failed to load {format} schema '{path}': {reason}")]
+    Schema {
+        format: &'static str,
+        path: String,
+        reason: String,
+    },
+}
+
+pub struct EncodedBody {
+    pub content_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+impl PayloadFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayloadFormat::Json => "json",
+            PayloadFormat::Ndjson => "ndjson",
+            PayloadFormat::Csv => "csv",
+            PayloadFormat::Xml => "xml",
+            PayloadFormat::Avro => "avro",
+            PayloadFormat::Protobuf => "protobuf",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            PayloadFormat::Json => "application/json",
+            PayloadFormat::Ndjson => "application/x-ndjson",
+            PayloadFormat::Csv => "text/csv",
+            PayloadFormat::Xml => "application/xml",
+            PayloadFormat::Avro => "application/avro",
+            PayloadFormat::Protobuf => "application/x-protobuf",
+        }
+    }
+
+    pub fn encode(&self, body: &Value, layout: &BodyLayout) -> Result<EncodedBody, FormatError> {
+        let bytes = match self {
+            PayloadFormat::Json => {
+                serde_json::to_vec(body).map_err(|error| self.serialize_error(error))?
+            }
+            PayloadFormat::Ndjson => {
+                let rows = match body {
+                    Value::Array(rows) => rows.as_slice(),
+                    row => std::slice::from_ref(row),
+                };
+                let mut bytes = Vec::new();
+                for row in rows {
+                    serde_json::to_writer(&mut bytes, row)
+                        .map_err(|error| self.serialize_error(error))?;
+                    bytes.push(b'\n');
+                }
+                bytes
+            }
+            PayloadFormat::Csv => self.encode_csv(body, layout)?,
+            PayloadFormat::Xml => self.encode_xml(body, layout)?,
+            PayloadFormat::Avro => {
+                let schema = layout
+                    .avro
+                    .as_ref()
+                    .ok_or_else(|| self.serialize_error("avro.schema_file is not configured"))?;
+                let rows = self.rows(body).iter().collect::<Vec<_>>();
+                schema
+                    .encode_container(&rows)
+                    .map_err(|reason| self.serialize_error(reason))?
+            }
+            PayloadFormat::Protobuf => self.encode_protobuf(body, layout)?,
+        };
+        Ok(EncodedBody {
+            content_type: self.content_type(),
+            bytes,
+        })
+    }
+
+    fn encode_csv(&self, body: &Value, layout: &BodyLayout) -> Result<Vec<u8>, FormatError> {
+        let rows = self.object_rows(body)?;
+        let columns = match &layout.csv.columns {
+            Some(columns) => columns.clone(),
+            None => {
+                let remaining = rows
+                    .iter()
+                    .flat_map(|row| row.keys())
+                    .filter(|name| !layout.leading_columns.contains(name))
+                    .collect::<BTreeSet<_>>();
+                let mut columns = layout.leading_columns.clone();
+                columns.extend(remaining.into_iter().cloned());
+                columns
+            }
+        };
+
+        let options = &layout.csv;
+        let delimiter = options
+            .delimiter
+            .as_ref()
+            .and_then(|delimiter| delimiter.bytes().next())
+            .unwrap_or(b',');
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .quote_style(match options.quote {
+                CsvQuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+                CsvQuoteStyle::Always => csv::QuoteStyle::Always,
+                CsvQuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+                CsvQuoteStyle::Never => csv::QuoteStyle::Never,
+            })
+            .from_writer(Vec::new());
+        if options.header.unwrap_or(true) {
+            writer
+                .write_record(&columns)
+                .map_err(|error| self.serialize_error(error))?;
+        }
+        for row in rows {
+            writer
+                .write_record(columns.iter().map(|column| csv_field(row, column)))
+                .map_err(|error| self.serialize_error(error))?;
+        }
+        writer
+            .into_inner()
+            .map_err(|error| self.serialize_error(error.error()))
+    }
+
+    fn encode_xml(&self, body: &Value, layout: &BodyLayout) -> Result<Vec<u8>, FormatError> {
+        let rows = self.object_rows(body)?;
+        let options = &layout.xml;
+        let root = options.root.as_deref().unwrap_or("rows");
+        let record = options.record.as_deref().unwrap_or("row");
+        let attributes = options.attributes.as_deref().unwrap_or_default();
+        let name_of = |field: &str| {
+            let name = options
+                .names
+                .as_ref()
+                .and_then(|names| names.get(field))
+                .map_or(field, String::as_str);
+            match is_xml_name(name) {
+                true => Ok(name.to_string()),
+                false => Err(self.serialize_error(format!(
+                    "field '{field}' is not a valid XML name; rename it with xml.names"
+                ))),
+            }
+        };
+
+        let mut xml = String::new();
+        if options.declaration.unwrap_or(true) {
+            xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        }
+        xml.push_str(&format!("<{root}>"));
+        for row in rows {
+            xml.push_str(&format!("<{record}"));
+            for field in attributes {
+                if let Some(value) = row.get(field).filter(|value| !value.is_null()) {
+                    let name = name_of(field)?;
+                    xml.push_str(&format!(" {name}=\"{}\"", escape_xml(&text_of(value))));
+                }
+            }
+            let children = layout
+                .leading_columns
+                .iter()
+                .filter(|field| row.contains_key(*field))
+                .chain(
+                    row.keys()
+                        .filter(|field| !layout.leading_columns.contains(field)),
+                )
+                .filter(|field| !attributes.contains(field))
+                .collect::<Vec<_>>();
+            if children.is_empty() {
+                xml.push_str("/>");
+                continue;
+            }
+            xml.push('>');
+            for field in children {
+                self.write_element(&mut xml, &name_of(field)?, &row[field])?;
+            }
+            xml.push_str(&format!("</{record}>"));
+        }
+        xml.push_str(&format!("</{root}>"));
+        Ok(xml.into_bytes())
+    }
+
+    /// Null is an empty element, arrays repeat the element per item, and objects nest.
+    fn write_element(
+        &self,
+        xml: &mut String,
+        name: &str,
+        value: &Value,
+    ) -> Result<(), FormatError> {
+        match value {
+            Value::Null => xml.push_str(&format!("<{name}/>")),
+            Value::Array(items) => {
+                for item in items {
+                    self.write_element(xml, name, item)?;
+                }
+            }
+            Value::Object(fields) => {
+                xml.push_str(&format!("<{name}>"));
+                for (key, value) in fields {
+                    if !is_xml_name(key) {
+                        return Err(self.serialize_error(format!(
+                            "nested field '{key}' is not a valid XML name"
+                        )));
+                    }
+                    self.write_element(xml, key, value)?;
+                }
+                xml.push_str(&format!("</{name}>"));
+            }
+            value => xml.push_str(&format!("<{name}>{}</{name}>", escape_xml(&text_of(value)))),
+        }
+        Ok(())
+    }
+
+    /// Length-delimited messages, one per row, or a single message holding the batch in
+    /// `batch_field`.
+    fn encode_protobuf(&self, body: &Value, layout: &BodyLayout) -> Result<Vec<u8>, FormatError> {
+        let protobuf = layout
+            .protobuf
+            .as_ref()
+            .ok_or_else(|| self.serialize_error("protobuf options are not configured"))?;
+        let options =
+            DeserializeOptions::new().deny_unknown_fields(!protobuf.ignore_unknown_fields);
+        let to_message = |value: &Value| {
+            DynamicMessage::deserialize_with_options(protobuf.message.clone(), value, &options)
+                .map_err(|error| self.serialize_error(error))
+        };
+        let rows = self.rows(body);
+        match &protobuf.batch_field {
+            Some(field) => {
+                let batch = Value::Object(Map::from_iter([(
+                    field.clone(),
+                    Value::Array(rows.to_vec()),
+                )]));
+                Ok(to_message(&batch)?.encode_to_vec())
+            }
+            None => {
+                let mut bytes = Vec::new();
+                for row in rows {
+                    to_message(row)?
+                        .encode_length_delimited(&mut bytes)
+                        .map_err(|error| self.serialize_error(error))?;
+                }
+                Ok(bytes)
+            }
+        }
+    }
+
+    fn rows<'v>(&self, body: &'v Value) -> &'v [Value] {
+        match body {
+            Value::Array(rows) => rows.as_slice(),
+            row => std::slice::from_ref(row),
+        }
+    }
+
+    fn object_rows<'v>(&self, body: &'v Value) -> Result<Vec<&'v Map<String, Value>>, FormatError> {
+        self.rows(body)
+            .iter()
+            .map(|row| {
+                row.as_object()
+                    .ok_or_else(|| self.serialize_error("rows must be JSON objects"))
+            })
+            .collect()
+    }
+
+    fn serialize_error(&self, error: impl std::fmt::Display) -> FormatError {
+        FormatError::Serialize {
+            format: self.as_str(),
+            reason: error.to_string(),
+        }
+    }
+}
+
+/// Checks for a name that can be used as an XML element or attribute name. Colons are not
+/// accepted, so configured names cannot introduce namespace prefixes.
+pub fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|char| char.is_alphanumeric() || matches!(char, '-' | '_' | '.'))
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            char => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+/// Strings as they are; other values, including arrays and objects, as JSON text.
+fn text_of(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Missing and null fields are empty; arrays and objects are written as JSON text.
+fn csv_field(row: &Map<String, Value>, column: &str) -> String {
+    match row.get(column) {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(value)) => value.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_json_array_with_content_type() {
+        let encoded = PayloadFormat::Json
+            .encode(&serde_json::json!([{"id": 1}]), &BodyLayout::default())
+            .unwrap();
+
+        assert_eq!("application/json", encoded.content_type);
+        assert_eq!(br#"[{"id":1}]"#.to_vec(), encoded.bytes);
+    }
+
+    #[test]
+    fn encodes_each_row_on_its_own_ndjson_line() {
+        let encoded = PayloadFormat::Ndjson
+            .encode(
+                &serde_json::json!([{"id": 1}, {"note": "a\nb"}]),
+                &BodyLayout::default(),
+            )
+            .unwrap();
+
+        assert_eq!("application/x-ndjson", encoded.content_type);
+        assert_eq!(
+            b"{\"id\":1}\n{\"note\":\"a\\nb\"}\n".to_vec(),
+            encoded.bytes
+        );
+    }
+
+    #[test]
+    fn lays_out_csv_columns_by_mapping_order_then_name() {
+        let rows = serde_json::json!([
+            {"total": 12.5, "name": "Ada, Countess", "zone": "eu", "id": 1},
+            {"id": 2, "name": "Grace", "tags": ["a"], "total": null},
+        ]);
+        let mut layout = BodyLayout {
+            leading_columns: vec!["id".to_string(), "name".to_string()],
+            ..BodyLayout::default()
+        };
+
+        let encoded = PayloadFormat::Csv.encode(&rows, &layout).unwrap();
+        assert_eq!("text/csv", encoded.content_type);
+        assert_eq!(
+            "id,name,tags,total,zone\n1,\"Ada, Countess\",,12.5,eu\n2,Grace,\"[\"\"a\"\"]\",,\n",
+            String::from_utf8(encoded.bytes).unwrap()
+        );
+
+        layout.csv = CsvOptions {
+            delimiter: Some(";".to_string()),
+            header: Some(false),
+            quote: CsvQuoteStyle::NonNumeric,
+            columns: Some(vec!["total".to_string(), "name".to_string()]),
+        };
+        assert_eq!(
+            b"12.5;\"Ada, Countess\"\n\"\";\"Grace\"\n".to_vec(),
+            PayloadFormat::Csv.encode(&rows, &layout).unwrap().bytes
+        );
+    }
+
+    #[test]
+    fn writes_rows_as_xml_elements_and_configured_attributes() {
+        let rows = serde_json::json!([
+            {"id": 7, "customer name": "Ada & Co", "note": null, "lines": [{"sku": "A-1"}, {"sku": "B<2>"}]},
+            {"id": 8},
+        ]);
+        let layout = BodyLayout {
+            xml: XmlOptions {
+                root: Some("Orders".to_string()),
+                record: Some("Order".to_string()),
+                attributes: Some(vec!["id".to_string()]),
+                names: Some(
+                    [("customer name".to_string(), "Customer".to_string())]
+                        .into_iter()
+                        .collect(),
+                ),
+                declaration: None,
+            },
+            ..BodyLayout::default()
+        };
+
+        let encoded = PayloadFormat::Xml.encode(&rows, &layout).unwrap();
+        assert_eq!("application/xml", encoded.content_type);
+        assert_eq!(
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<Orders><Order id=\"7\"><Customer>Ada &amp; Co</Customer>",
+                "<lines><sku>A-1</sku></lines><lines><sku>B&lt;2&gt;</sku></lines>",
+                "<note/></Order><Order id=\"8\"/></Orders>"
+            ),
+            String::from_utf8(encoded.bytes).unwrap()
+        );
+        assert!(
+            PayloadFormat::Xml
+                .encode(
+                    &serde_json::json!([{"bad name": 1}]),
+                    &BodyLayout::default()
+                )
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn encodes_protobuf_rows_length_delimited_or_as_one_batch_message() {
+        use prost_reflect::prost_types::field_descriptor_proto::{Label, Type};
+        use prost_reflect::prost_types::{
+            DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+        };
+
+        let field = |name: &str, number, r#type: Type, type_name: Option<&str>, label: Label| {
+            FieldDescriptorProto {
+                name: Some(name.to_string()),
+                number: Some(number),
+                label: Some(label as i32),
+                r#type: Some(r#type as i32),
+                type_name: type_name.map(str::to_string),
+                json_name: Some(name.to_string()),
+                ..Default::default()
+            }
+        };
+        let pool = DescriptorPool::from_file_descriptor_set(FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("orders.proto".to_string()),
+                package: Some("orders.v1".to_string()),
+                syntax: Some("proto3".to_string()),
+                message_type: vec![
+                    DescriptorProto {
+                        name: Some("Order".to_string()),
+                        field: vec![field("id", 1, Type::Int64, None, Label::Optional)],
+                        ..Default::default()
+                    },
+                    DescriptorProto {
+                        name: Some("OrderBatch".to_string()),
+                        field: vec![field(
+                            "orders",
+                            1,
+                            Type::Message,
+                            Some(".orders.v1.Order"),
+                            Label::Repeated,
+                        )],
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+        })
+        .unwrap();
+        let mut layout = BodyLayout {
+            protobuf: Some(ProtobufLayout {
+                message: pool.get_message_by_name("orders.v1.Order").unwrap(),
+                batch_field: None,
+                ignore_unknown_fields: false,
+            }),
+            ..BodyLayout::default()
+        };
+        let rows = serde_json::json!([{"id": 1}, {"id": 300}]);
+
+        let encoded = PayloadFormat::Protobuf.encode(&rows, &layout).unwrap();
+        assert_eq!("application/x-protobuf", encoded.content_type);
+        assert_eq!(vec![2, 0x08, 1, 3, 0x08, 0xac, 0x02], encoded.bytes);
+        assert!(
+            PayloadFormat::Protobuf
+                .encode(&serde_json::json!([{"sku": "A"}]), &layout)
+                .is_err()
+        );
+
+        layout.protobuf = Some(ProtobufLayout {
+            message: pool.get_message_by_name("orders.v1.OrderBatch").unwrap(),
+            batch_field: Some("orders".to_string()),
+            ignore_unknown_fields: false,
+        });
+        assert_eq!(
+            vec![0x0a, 2, 0x08, 1, 0x0a, 3, 0x08, 0xac, 0x02],
+            PayloadFormat::Protobuf
+                .encode(&rows, &layout)
+                .unwrap()
+                .bytes
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_formats() {
+        assert_eq!(
+            PayloadFormat::Ndjson,
+            serde_yaml::from_str::<PayloadFormat>("NDJSON").unwrap()
+        );
+        let error = serde_yaml::from_str::<PayloadFormat>("yaml").unwrap_err();
+        assert!(
+            error.to_string().contains("expected one of `JSON`, `json`"),
+            "{error}"
+        );
+    }
+}
@@ -1,25 +1,76 @@
+pub mod avro;
+pub(crate) mod circuit;
+pub mod format;
 mod oauth2;
-mod retry;
+pub(crate) mod retry;
+pub(crate) mod sigv4;
 
-use crate::config::endpoint_config::{EndpointAuth, EndpointConfig};
+use crate::chaos::{self, Fault};
+use crate::config::endpoint_config::{DuplicatePolicy, EndpointAuth, EndpointConfig, HttpMethod};
 use crate::config::request_config::RequestConfig;
+use crate::notifications;
+use chrono::Utc;
+use circuit::{CircuitBreaker, Transition};
+use format::{BodyLayout, EncodedBody, FormatError};
 use oauth2::OAuth2Client;
-use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderName, HeaderValue, LINK};
 use reqwest::{Client, Method, StatusCode};
-use retry::{RetryPolicy, is_transient_status};
+use retry::RetryPolicy;
 use serde_json::Value;
-use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 #[derive(Clone)]
 pub struct HttpSender {
     client: Client,
     oauth2: OAuth2Client,
     retry_policy: RetryPolicy,
+    timeout: Duration,
+    layout: BodyLayout,
+    /// Index of the URL batches go to first: 0 for the endpoint's `url`, then its
+    /// `failover_url` entries. It only moves forward, so one failover lasts the sender's life.
+    active_url: Arc<AtomicUsize>,
 }
 
 #[derive(Debug)]
 pub struct SendOutcome {
     pub status: StatusCode,
+    /// Set when the endpoint reported the batch as a duplicate and `handle_duplicates` resolved it.
+    pub duplicate: Option<DuplicateOutcome>,
+}
+
+/// The request a batch would be sent as, from [`HttpSender::preview`].
+#[derive(Debug)]
+pub struct RequestPreview {
+    pub method: String,
+    pub url: String,
+    /// Header names and values; credentials are replaced by `***`.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Which OAuth2 token a request carries.
+#[derive(Clone, Copy)]
+enum OAuth2Token {
+    Cached,
+    Refreshed,
+    /// A placeholder, so previews never contact the token endpoint.
+    Withheld,
+}
+
+/// One response read by a pull query.
+#[derive(Debug)]
+pub struct FetchedPage {
+    pub body: Value,
+    /// Absolute `rel="next"` URL from the `Link` header, if any.
+    pub next_link: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateOutcome {
+    Skipped,
+    Updated,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -34,10 +85,31 @@ pub enum HttpError {
     InvalidHeaderValue { name: String, reason: String },
     #[error(transparent)]
     OAuth2(#[from] oauth2::OAuth2Error),
+    #[error(transparent)]
+    Format(#[from] FormatError),
+    #[error("AWS SigV4 signing failed: {0}")]
+    SigV4(#[from] sigv4::SigV4Error),
+    #[error("HTTP request timed out after {}s", .0.as_secs())]
+    Timeout(Duration),
     #[error("HTTP request failed: {0}")]
     Request(#[from] reqwest::Error),
     #[error("endpoint returned unexpected status {status}: {body}")]
-    UnexpectedStatus { status: StatusCode, body: String },
+    UnexpectedStatus {
+        status: StatusCode,
+        body: String,
+        retry_after: Option<Duration>,
+    },
+    #[error("endpoint rejected the batch as a duplicate with status {status}: {body}")]
+    Duplicate { status: StatusCode, body: String },
+    #[error("endpoint returned a body that is not valid JSON: {0}")]
+    InvalidBody(serde_json::Error),
+    #[error("chaos fault injected: {0}")]
+    Injected(&'static str),
+    #[error(
+        "circuit breaker for {url} is open after repeated failures; next attempt in {}s",
+        .retry_in.as_secs()
+    )]
+    CircuitOpen { url: String, retry_in: Duration },
 }
 
 impl HttpSender {
@@ -51,16 +123,121 @@ impl HttpSender {
             oauth2: OAuth2Client::new(client.clone()),
             client,
             retry_policy: RetryPolicy::from_request(request),
+            timeout,
+            layout: request.body.layout()?,
+            active_url: Arc::new(AtomicUsize::new(0)),
         })
     }
 
+    /// Sets the fields that lead `csv` and `xml` bodies; see [`BodyLayout`].
+    pub fn with_leading_columns(mut self, columns: Vec<String>) -> Self {
+        self.layout.leading_columns = columns;
+        self
+    }
+
+    /// Delivers one batch, honouring the endpoint's `circuit_breaker`. When the URL in use
+    /// still fails after its retries, or its circuit is open, the batch goes to the next
+    /// `failover_url`, which later batches then start with.
     pub async fn send(
         &self,
         endpoint: &EndpointConfig,
         rows: &[Value],
     ) -> Result<SendOutcome, HttpError> {
-        self.send_value(endpoint, &Value::Array(rows.to_vec()))
-            .await
+        let urls: Vec<&str> = std::iter::once(endpoint.url.as_str())
+            .chain(endpoint.failover_url.iter().map(String::as_str))
+            .collect();
+        let mut index = self.active_url.load(Ordering::Relaxed).min(urls.len() - 1);
+        loop {
+            let target = EndpointConfig {
+                url: urls[index].to_string(),
+                failover_url: Vec::new(),
+                ..endpoint.clone()
+            };
+            let result = self.send_to(&target, rows).await;
+            let exhausted = result.as_ref().is_err_and(|error| {
+                error.is_retryable(&self.retry_policy)
+                    || matches!(error, HttpError::CircuitOpen { .. })
+            });
+            let Some(next) = urls.get(index + 1).filter(|_| exhausted) else {
+                return result;
+            };
+            let error = result
+                .err()
+                .map(|error| error.to_string())
+                .unwrap_or_default();
+            tracing::warn!(
+                url = %urls[index],
+                failover_url = %next,
+                error = %error,
+                "endpoint failed after retries; failing over"
+            );
+            if self
+                .active_url
+                .compare_exchange(index, index + 1, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+            {
+                notifications::notify_failover(urls[index], next, &error).await;
+            }
+            index += 1;
+        }
+    }
+
+    async fn send_to(
+        &self,
+        endpoint: &EndpointConfig,
+        rows: &[Value],
+    ) -> Result<SendOutcome, HttpError> {
+        let Some(config) = &endpoint.request.circuit_breaker else {
+            return self
+                .send_value(endpoint, &Value::Array(rows.to_vec()))
+                .await;
+        };
+        let body = endpoint
+            .request
+            .body
+            .format
+            .encode(&Value::Array(rows.to_vec()), &self.layout)?;
+        let breaker =
+            CircuitBreaker::for_endpoint(&format!("{} {}", endpoint.method, endpoint.url), config);
+        breaker
+            .acquire(Instant::now())
+            .map_err(|retry_in| HttpError::CircuitOpen {
+                url: endpoint.url.clone(),
+                retry_in,
+            })?;
+        let result = self
+            .with_retries("HTTP delivery", || self.send_once(endpoint, &body))
+            .await;
+        let failed = result
+            .as_ref()
+            .is_err_and(|error| error.is_retryable(&self.retry_policy));
+        match breaker.record(failed, Instant::now()) {
+            Some(Transition::Opened { failures }) => {
+                tracing::error!(
+                    url = %endpoint.url,
+                    failures,
+                    cooldown_seconds = breaker.cooldown().as_secs(),
+                    "circuit breaker opened; delivery to the endpoint is paused"
+                );
+                notifications::notify_circuit(
+                    &endpoint.url,
+                    Transition::Opened { failures },
+                    breaker.cooldown(),
+                )
+                .await;
+            }
+            Some(Transition::Closed) => {
+                tracing::info!(url = %endpoint.url, "circuit breaker closed; probe batch succeeded");
+                notifications::notify_circuit(
+                    &endpoint.url,
+                    Transition::Closed,
+                    breaker.cooldown(),
+                )
+                .await;
+            }
+            None => {}
+        }
+        result
     }
 
     pub async fn send_value(
@@ -68,23 +245,92 @@ impl HttpSender {
         endpoint: &EndpointConfig,
         body: &Value,
     ) -> Result<SendOutcome, HttpError> {
+        let body = endpoint.request.body.format.encode(body, &self.layout)?;
+        self.with_retries("HTTP delivery", || self.send_once(endpoint, &body))
+            .await
+    }
+
+    /// Builds the request for one batch the way [`send`](Self::send) would, without sending it.
+    /// OAuth2 tokens are not fetched, and credential headers are masked.
+    pub async fn preview(
+        &self,
+        endpoint: &EndpointConfig,
+        rows: &[Value],
+    ) -> Result<RequestPreview, HttpError> {
+        let body = endpoint
+            .request
+            .body
+            .format
+            .encode(&Value::Array(rows.to_vec()), &self.layout)?;
+        let request = self
+            .build_request(
+                endpoint,
+                &endpoint.url,
+                endpoint.method.as_str(),
+                Some(&body),
+                OAuth2Token::Withheld,
+            )
+            .await?;
+        let headers = request
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = if value.is_sensitive()
+                    || name == AUTHORIZATION
+                    || name.as_str() == "x-amz-security-token"
+                {
+                    "***".to_string()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.to_string(), value)
+            })
+            .collect();
+        Ok(RequestPreview {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            headers,
+            body: body.bytes,
+        })
+    }
+
+    /// Reads one page from `url` with the endpoint's method, headers, and auth.
+    pub async fn fetch(
+        &self,
+        endpoint: &EndpointConfig,
+        url: &str,
+    ) -> Result<FetchedPage, HttpError> {
+        self.with_retries("HTTP fetch", || self.fetch_once(endpoint, url))
+            .await
+    }
+
+    async fn with_retries<T, F, Fut>(&self, operation: &str, mut attempt: F) -> Result<T, HttpError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, HttpError>>,
+    {
         let mut retry_index = 0;
 
         loop {
-            match self.send_once(endpoint, body).await {
+            match attempt().await {
                 Ok(outcome) => return Ok(outcome),
                 Err(error)
-                    if retry_index < self.retry_policy.max_retries && error.is_retryable() =>
+                    if retry_index < self.retry_policy.max_retries
+                        && error.is_retryable(&self.retry_policy) =>
                 {
                     retry_index += 1;
                     crate::monitoring::record_http_retry();
-                    let delay = self.retry_policy.delay_for_retry(retry_index);
+                    let retry_after = match &error {
+                        HttpError::UnexpectedStatus { retry_after, .. } => *retry_after,
+                        _ => None,
+                    };
+                    let delay = self.retry_policy.next_delay(retry_index, retry_after);
                     tracing::warn!(
                         retry = retry_index,
                         max_retries = self.retry_policy.max_retries,
                         delay_ms = delay.as_millis(),
                         error = %error,
-                        "HTTP delivery failed; retrying"
+                        "{operation} failed; retrying"
                     );
                     tokio::time::sleep(delay).await;
                 }
@@ -93,70 +339,223 @@ impl HttpSender {
         }
     }
 
+    async fn fetch_once(
+        &self,
+        endpoint: &EndpointConfig,
+        url: &str,
+    ) -> Result<FetchedPage, HttpError> {
+        let response = self
+            .execute_with_auth_refresh(endpoint, url, endpoint.method.as_str(), None)
+            .await?;
+        let response = self.validate_response(endpoint, response).await?;
+        let next_link = response
+            .headers()
+            .get_all(LINK)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(next_link)
+            .map(|link| resolve_link(url, &link));
+        let bytes = response.bytes().await.map_err(HttpError::Request)?;
+        let body = if bytes.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&bytes).map_err(HttpError::InvalidBody)?
+        };
+        Ok(FetchedPage { body, next_link })
+    }
+
     async fn send_once(
         &self,
         endpoint: &EndpointConfig,
-        body: &Value,
+        body: &EncodedBody,
     ) -> Result<SendOutcome, HttpError> {
-        let response = self.execute_request(endpoint, body, false).await?;
+        if chaos::inject(Fault::Endpoint500) {
+            return Err(HttpError::UnexpectedStatus {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: "injected by chaos configuration".to_string(),
+                retry_after: None,
+            });
+        }
+        if chaos::inject(Fault::EndpointTimeout) {
+            return Err(HttpError::Injected(Fault::EndpointTimeout.as_str()));
+        }
+
+        let response = self
+            .execute_with_auth_refresh(
+                endpoint,
+                &endpoint.url,
+                endpoint.method.as_str(),
+                Some(body),
+            )
+            .await?;
+        if let Some(config) = &endpoint.response
+            && config.is_duplicate(response.status().as_u16())
+        {
+            let status = response.status();
+            match config.handle_duplicates {
+                DuplicatePolicy::Skip => {
+                    tracing::info!(
+                        status = status.as_u16(),
+                        "endpoint reported a duplicate batch; skipping"
+                    );
+                    return Ok(SendOutcome {
+                        status,
+                        duplicate: Some(DuplicateOutcome::Skipped),
+                    });
+                }
+                DuplicatePolicy::Error => {
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(HttpError::Duplicate {
+                        status,
+                        body: truncate(&body, 1024),
+                    });
+                }
+                DuplicatePolicy::Update => {
+                    let method = config.update_method.unwrap_or(HttpMethod::Put).as_str();
+                    tracing::info!(
+                        status = status.as_u16(),
+                        method,
+                        "endpoint reported a duplicate batch; resending as update"
+                    );
+                    let response = self
+                        .execute_with_auth_refresh(endpoint, &endpoint.url, method, Some(body))
+                        .await?;
+                    let response = self.validate_response(endpoint, response).await?;
+                    return Ok(SendOutcome {
+                        status: response.status(),
+                        duplicate: Some(DuplicateOutcome::Updated),
+                    });
+                }
+            }
+        }
+
+        let response = self.validate_response(endpoint, response).await?;
+        Ok(SendOutcome {
+            status: response.status(),
+            duplicate: None,
+        })
+    }
+
+    async fn execute_with_auth_refresh(
+        &self,
+        endpoint: &EndpointConfig,
+        url: &str,
+        method: &str,
+        body: Option<&EncodedBody>,
+    ) -> Result<reqwest::Response, HttpError> {
+        let response = self
+            .execute_request(endpoint, url, method, body, OAuth2Token::Cached)
+            .await?;
         if response.status() == StatusCode::UNAUTHORIZED
             && matches!(endpoint.auth, Some(EndpointAuth::OAuth2 { .. }))
         {
             tracing::warn!("endpoint returned 401; refreshing OAuth2 token and retrying once");
             return self
-                .validate_response(endpoint, self.execute_request(endpoint, body, true).await?)
+                .execute_request(endpoint, url, method, body, OAuth2Token::Refreshed)
                 .await;
         }
-
-        self.validate_response(endpoint, response).await
+        Ok(response)
     }
 
     async fn execute_request(
         &self,
         endpoint: &EndpointConfig,
-        body: &Value,
-        refresh_oauth2: bool,
+        url: &str,
+        method: &str,
+        body: Option<&EncodedBody>,
+        oauth2_token: OAuth2Token,
     ) -> Result<reqwest::Response, HttpError> {
-        let method = parse_method(&endpoint.method)?;
-        let mut request = self.client.request(method, &endpoint.url);
+        let request = self
+            .build_request(endpoint, url, method, body, oauth2_token)
+            .await?;
+        self.client.execute(request).await.map_err(|error| {
+            if error.is_timeout() {
+                HttpError::Timeout(self.timeout)
+            } else {
+                HttpError::Request(error)
+            }
+        })
+    }
+
+    async fn build_request(
+        &self,
+        endpoint: &EndpointConfig,
+        url: &str,
+        method: &str,
+        body: Option<&EncodedBody>,
+        oauth2_token: OAuth2Token,
+    ) -> Result<reqwest::Request, HttpError> {
+        let method = parse_method(method)?;
+        let mut request = self.client.request(method, url);
 
+        let mut has_content_type = false;
         if let Some(headers) = &endpoint.headers {
             for (name, value) in headers {
+                has_content_type |= name.eq_ignore_ascii_case(CONTENT_TYPE.as_str());
                 request = add_header(request, name, value)?;
             }
         }
+        if let Some(body) = body
+            && !has_content_type
+        {
+            request = request.header(CONTENT_TYPE, body.content_type);
+        }
 
         if let Some(auth) = &endpoint.auth {
             request = match auth {
                 EndpointAuth::Bearer { token, header_name } => {
                     let name = header_name.as_deref().unwrap_or("Authorization");
-                    add_header(request, name, &format!("Bearer {token}"))?
+                    add_secret_header(request, name, &format!("Bearer {}", token.expose()))?
                 }
                 EndpointAuth::ApiKey { header_name, token } => {
-                    add_header(request, header_name, token)?
+                    add_secret_header(request, header_name, token.expose())?
                 }
                 EndpointAuth::Basic { username, password } => {
-                    request.basic_auth(username, Some(password))
+                    request.basic_auth(username, Some(password.expose()))
                 }
                 EndpointAuth::OAuth2 { .. } => {
-                    let token = if refresh_oauth2 {
-                        self.oauth2.refresh_token_for(auth).await?
-                    } else {
-                        self.oauth2.token_for(auth).await?
+                    let token = match oauth2_token {
+                        OAuth2Token::Cached => self.oauth2.token_for(auth).await?,
+                        OAuth2Token::Refreshed => self.oauth2.refresh_token_for(auth).await?,
+                        OAuth2Token::Withheld => "withheld".to_string(),
                     };
                     request.bearer_auth(token)
                 }
+                // Signing covers the final URL, headers, and body, so it happens after build.
+                EndpointAuth::AwsSigV4 { .. } => request,
             };
         }
 
-        request.json(body).send().await.map_err(HttpError::Request)
+        if let Some(body) = body {
+            request = request.body(body.bytes.clone());
+        }
+        let mut request = request.build().map_err(HttpError::Request)?;
+        if let Some(EndpointAuth::AwsSigV4 {
+            region,
+            service,
+            access_key_id,
+            secret_access_key,
+            session_token,
+            profile,
+        }) = &endpoint.auth
+        {
+            let credentials = sigv4::resolve_credentials(
+                access_key_id.as_deref(),
+                secret_access_key.as_ref().map(|key| key.expose().as_str()),
+                session_token.as_ref().map(|token| token.expose().as_str()),
+                profile.as_deref(),
+            )
+            .await?;
+            sigv4::sign(&mut request, &credentials, region, service, Utc::now())?;
+        }
+        Ok(request)
     }
 
     async fn validate_response(
         &self,
         endpoint: &EndpointConfig,
         response: reqwest::Response,
-    ) -> Result<SendOutcome, HttpError> {
+    ) -> Result<reqwest::Response, HttpError> {
         let status = response.status();
         let success_codes = endpoint
             .response
@@ -166,33 +565,72 @@ impl HttpSender {
             .unwrap_or(&[200, 201, 202, 204]);
 
         if !success_codes.contains(&status.as_u16()) {
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(retry::parse_retry_after);
             let body = response.text().await.unwrap_or_default();
             return Err(HttpError::UnexpectedStatus {
                 status,
                 body: truncate(&body, 1024),
+                retry_after,
             });
         }
 
-        Ok(SendOutcome { status })
+        Ok(response)
     }
 }
 
 impl HttpError {
-    fn is_retryable(&self) -> bool {
+    fn is_retryable(&self, policy: &RetryPolicy) -> bool {
         match self {
             HttpError::Request(error) => {
                 error.is_connect() || error.is_timeout() || error.status().is_none()
             }
-            HttpError::UnexpectedStatus { status, .. } => is_transient_status(*status),
+            HttpError::UnexpectedStatus { status, .. } => policy.is_retryable_status(*status),
+            HttpError::Timeout(_) | HttpError::Injected(_) => true,
             HttpError::BuildClient(_)
             | HttpError::InvalidMethod(_)
             | HttpError::InvalidHeaderName { .. }
             | HttpError::InvalidHeaderValue { .. }
-            | HttpError::OAuth2(_) => false,
+            | HttpError::Format(_)
+            | HttpError::SigV4(_)
+            | HttpError::OAuth2(_)
+            | HttpError::Duplicate { .. }
+            | HttpError::InvalidBody(_)
+            | HttpError::CircuitOpen { .. } => false,
         }
     }
 }
 
+/// Returns the `rel="next"` target of a `Link` header value (RFC 8288).
+fn next_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let mut parts = link.split(';');
+        let target = parts.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+        parts
+            .filter_map(|param| param.split_once('='))
+            .any(|(name, value)| {
+                name.trim().eq_ignore_ascii_case("rel")
+                    && value
+                        .trim()
+                        .trim_matches('"')
+                        .split_ascii_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("next"))
+            })
+            .then(|| target.to_string())
+    })
+}
+
+/// Resolves a possibly relative link against the URL of the request that returned it.
+fn resolve_link(base: &str, link: &str) -> String {
+    url::Url::parse(base)
+        .and_then(|base| base.join(link))
+        .map(String::from)
+        .unwrap_or_else(|_| link.to_string())
+}
+
 fn parse_method(method: &str) -> Result<Method, HttpError> {
     method
         .to_ascii_uppercase()
@@ -205,6 +643,22 @@ fn add_header(
     name: &str,
     value: &str,
 ) -> Result<reqwest::RequestBuilder, HttpError> {
+    let (header_name, header_value) = parse_header(name, value)?;
+    Ok(request.header(header_name, header_value))
+}
+
+/// Marks the value sensitive so it is never printed by header `Debug` output.
+fn add_secret_header(
+    request: reqwest::RequestBuilder,
+    name: &str,
+    value: &str,
+) -> Result<reqwest::RequestBuilder, HttpError> {
+    let (header_name, mut header_value) = parse_header(name, value)?;
+    header_value.set_sensitive(true);
+    Ok(request.header(header_name, header_value))
+}
+
+fn parse_header(name: &str, value: &str) -> Result<(HeaderName, HeaderValue), HttpError> {
     let header_name =
         HeaderName::from_bytes(name.as_bytes()).map_err(|error| HttpError::InvalidHeaderName {
             name: name.to_string(),
@@ -215,7 +669,7 @@ fn add_header(
             name: name.to_string(),
             reason: error.to_string(),
         })?;
-    Ok(request.header(header_name, header_value))
+    Ok((header_name, header_value))
 }
 
 fn truncate(value: &str, max_chars: usize) -> String {
@@ -232,6 +686,7 @@ fn truncate(value: &str, max_chars: usize) -> String {
 mod tests {
     use super::*;
     use crate::config::endpoint_config::{EndpointAuth, ResponseConfig};
+    use crate::config::request_config::RetryBackoff;
     use std::collections::HashMap;
     use std::sync::{
         Arc,
@@ -283,16 +738,19 @@ mod tests {
         headers.insert("X-Source".to_string(), "yetii".to_string());
         let endpoint = EndpointConfig {
             url: format!("http://{address}/sync"),
-            method: "POST".to_string(),
+            failover_url: Vec::new(),
+            method: HttpMethod::Post,
             auth: Some(EndpointAuth::Bearer {
-                token: "secret".to_string(),
+                token: "secret".into(),
                 header_name: None,
             }),
             headers: Some(headers),
             request: RequestConfig::default(),
             response: Some(ResponseConfig {
                 success_codes: vec![202],
-                handle_duplicates: "skip".to_string(),
+                handle_duplicates: DuplicatePolicy::Skip,
+                duplicate_codes: vec![409],
+                update_method: None,
             }),
         };
         let sender = HttpSender::new(&endpoint.request).unwrap();
@@ -308,6 +766,7 @@ mod tests {
         assert!(request.starts_with("POST /sync HTTP/1.1"));
         assert!(request_lower.contains("x-source: yetii"));
         assert!(request_lower.contains("authorization: bearer secret"));
+        assert!(request_lower.contains("content-type: application/json"));
         assert!(request.contains(r#"[{"id":"42"}]"#));
     }
 
@@ -334,18 +793,21 @@ mod tests {
 
         let endpoint = EndpointConfig {
             url: format!("http://{address}/sync"),
-            method: "POST".to_string(),
+            failover_url: Vec::new(),
+            method: HttpMethod::Post,
             auth: None,
             headers: None,
             request: RequestConfig {
                 retry_attempts: Some(2),
                 retry_delay_seconds: Some(0),
-                retry_backoff: Some("fixed".to_string()),
+                retry_backoff: Some(RetryBackoff::Fixed),
                 ..RequestConfig::default()
             },
             response: Some(ResponseConfig {
                 success_codes: vec![202],
-                handle_duplicates: "skip".to_string(),
+                handle_duplicates: DuplicatePolicy::Skip,
+                duplicate_codes: vec![409],
+                update_method: None,
             }),
         };
         let sender = HttpSender::new(&endpoint.request).unwrap();
@@ -360,6 +822,64 @@ mod tests {
         assert_eq!(3, attempts.load(Ordering::SeqCst));
     }
 
+    #[tokio::test]
+    async fn fails_over_after_retries_and_stays_on_the_failover_url() {
+        let primary = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let primary_address = primary.local_addr().unwrap();
+        let failover = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let failover_address = failover.local_addr().unwrap();
+        let primary_attempts = Arc::new(AtomicUsize::new(0));
+        let server_attempts = primary_attempts.clone();
+        let primary_server = tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = primary.accept().await.unwrap();
+                read_request(&mut stream).await;
+                server_attempts.fetch_add(1, Ordering::SeqCst);
+                stream
+                    .write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n")
+                    .await
+                    .unwrap();
+            }
+        });
+        let failover_server = tokio::spawn(async move {
+            for _ in 0..2 {
+                let (mut stream, _) = failover.accept().await.unwrap();
+                read_request(&mut stream).await;
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let endpoint = EndpointConfig {
+            url: format!("http://{primary_address}/sync"),
+            failover_url: vec![format!("http://{failover_address}/sync")],
+            method: HttpMethod::Post,
+            auth: None,
+            headers: None,
+            request: RequestConfig {
+                retry_attempts: Some(1),
+                retry_delay_seconds: Some(0),
+                ..RequestConfig::default()
+            },
+            response: None,
+        };
+        let sender = HttpSender::new(&endpoint.request).unwrap();
+
+        for id in 1..=2 {
+            let outcome = sender
+                .send(&endpoint, &[serde_json::json!({"id": id})])
+                .await
+                .unwrap();
+            assert_eq!(StatusCode::OK, outcome.status);
+        }
+        failover_server.await.unwrap();
+        // The primary saw both attempts of the first batch and none of the second.
+        assert_eq!(2, primary_attempts.load(Ordering::SeqCst));
+        primary_server.abort();
+    }
+
     #[tokio::test]
     async fn does_not_retry_permanent_client_error() {
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -379,7 +899,8 @@ mod tests {
 
         let endpoint = EndpointConfig {
             url: format!("http://{address}/sync"),
-            method: "POST".to_string(),
+            failover_url: Vec::new(),
+            method: HttpMethod::Post,
             auth: None,
             headers: None,
             request: RequestConfig {
@@ -389,7 +910,9 @@ mod tests {
             },
             response: Some(ResponseConfig {
                 success_codes: vec![202],
-                handle_duplicates: "skip".to_string(),
+                handle_duplicates: DuplicatePolicy::Skip,
+                duplicate_codes: vec![409],
+                update_method: None,
             }),
         };
         let sender = HttpSender::new(&endpoint.request).unwrap();
@@ -405,6 +928,179 @@ mod tests {
         assert_eq!(1, attempts.load(Ordering::SeqCst));
     }
 
+    #[tokio::test]
+    async fn applies_api_key_and_basic_auth() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut captured = Vec::new();
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                captured.push(String::from_utf8(read_request(&mut stream).await).unwrap());
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .await
+                    .unwrap();
+            }
+            captured
+        });
+
+        for auth in [
+            EndpointAuth::ApiKey {
+                header_name: "X-Api-Key".to_string(),
+                token: "key-123".into(),
+            },
+            EndpointAuth::Basic {
+                username: "sync".to_string(),
+                password: "pw".into(),
+            },
+        ] {
+            let endpoint = EndpointConfig {
+                url: format!("http://{address}/sync"),
+                failover_url: Vec::new(),
+                method: HttpMethod::Post,
+                auth: Some(auth),
+                headers: None,
+                request: RequestConfig::default(),
+                response: None,
+            };
+            HttpSender::new(&endpoint.request)
+                .unwrap()
+                .send(&endpoint, &[serde_json::json!({"id": 1})])
+                .await
+                .unwrap();
+        }
+        let requests = server.await.unwrap();
+
+        assert!(
+            requests[0]
+                .to_ascii_lowercase()
+                .contains("x-api-key: key-123")
+        );
+        assert!(
+            requests[1]
+                .to_ascii_lowercase()
+                .contains("authorization: basic c3luyzpwdw==")
+        );
+    }
+
+    #[tokio::test]
+    async fn signs_requests_with_aws_sigv4() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let request = String::from_utf8(read_request(&mut stream).await).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            request
+        });
+        let endpoint = EndpointConfig {
+            url: format!("http://{address}/prod/rows"),
+            failover_url: Vec::new(),
+            method: HttpMethod::Post,
+            auth: Some(EndpointAuth::AwsSigV4 {
+                region: "eu-west-1".to_string(),
+                service: "execute-api".to_string(),
+                access_key_id: Some("AKIDEXAMPLE".to_string()),
+                secret_access_key: Some("secret".into()),
+                session_token: Some("session".into()),
+                profile: None,
+            }),
+            headers: None,
+            request: RequestConfig::default(),
+            response: None,
+        };
+
+        HttpSender::new(&endpoint.request)
+            .unwrap()
+            .send(&endpoint, &[serde_json::json!({"id": 1})])
+            .await
+            .unwrap();
+        let request = server.await.unwrap().to_ascii_lowercase();
+
+        assert!(request.contains("authorization: aws4-hmac-sha256 credential=akidexample/"));
+        assert!(request.contains("/eu-west-1/execute-api/aws4_request, signedheaders=content-type;host;x-amz-date;x-amz-security-token, signature="));
+        assert!(request.contains("x-amz-security-token: session"));
+    }
+
+    #[tokio::test]
+    async fn previews_requests_without_sending_or_fetching_tokens() {
+        let mut headers = HashMap::new();
+        headers.insert("X-Source".to_string(), "yetii".to_string());
+        let endpoint = EndpointConfig {
+            url: "http://127.0.0.1:9/rows".to_string(),
+            failover_url: Vec::new(),
+            method: HttpMethod::Put,
+            auth: Some(EndpointAuth::OAuth2 {
+                client_id: "client".to_string(),
+                client_secret: "secret".into(),
+                token_url: "http://127.0.0.1:9/token".to_string(),
+                scopes: None,
+                audience: None,
+            }),
+            headers: Some(headers),
+            request: RequestConfig::default(),
+            response: None,
+        };
+
+        let preview = HttpSender::new(&endpoint.request)
+            .unwrap()
+            .preview(&endpoint, &[serde_json::json!({"id": 1})])
+            .await
+            .unwrap();
+
+        assert_eq!("PUT", preview.method);
+        assert_eq!("http://127.0.0.1:9/rows", preview.url);
+        let header = |name: &str| {
+            preview
+                .headers
+                .iter()
+                .find(|(header, _)| header == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(Some("yetii"), header("x-source"));
+        assert_eq!(Some("application/json"), header("content-type"));
+        assert_eq!(Some("***"), header("authorization"));
+        assert_eq!(br#"[{"id":1}]"#, preview.body.as_slice());
+    }
+
+    #[tokio::test]
+    async fn reports_request_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_request(&mut stream).await;
+            tokio::time::sleep(Duration::from_secs(3)).await;
+        });
+
+        let endpoint = EndpointConfig {
+            url: format!("http://{address}/sync"),
+            failover_url: Vec::new(),
+            method: HttpMethod::Post,
+            auth: None,
+            headers: None,
+            request: RequestConfig {
+                timeout_seconds: Some(1),
+                retry_attempts: Some(0),
+                ..RequestConfig::default()
+            },
+            response: None,
+        };
+        let sender = HttpSender::new(&endpoint.request).unwrap();
+
+        let error = sender
+            .send(&endpoint, &[serde_json::json!({"id": 1})])
+            .await
+            .unwrap_err();
+        server.abort();
+
+        assert!(matches!(error, HttpError::Timeout(timeout) if timeout.as_secs() == 1));
+    }
+
     #[tokio::test]
     async fn oauth2_fetches_and_reuses_bearer_token() {
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -442,10 +1138,11 @@ mod tests {
 
         let endpoint = EndpointConfig {
             url: format!("http://{address}/sync"),
-            method: "POST".to_string(),
+            failover_url: Vec::new(),
+            method: HttpMethod::Post,
             auth: Some(EndpointAuth::OAuth2 {
                 client_id: "client".to_string(),
-                client_secret: "secret".to_string(),
+                client_secret: "secret".into(),
                 token_url: format!("http://{address}/token"),
                 scopes: Some(vec!["rows.write".to_string()]),
                 audience: None,
@@ -457,7 +1154,9 @@ mod tests {
             },
             response: Some(ResponseConfig {
                 success_codes: vec![202],
-                handle_duplicates: "skip".to_string(),
+                handle_duplicates: DuplicatePolicy::Skip,
+                duplicate_codes: vec![409],
+                update_method: None,
             }),
         };
         let sender = HttpSender::new(&endpoint.request).unwrap();
@@ -523,10 +1222,11 @@ mod tests {
 
         let endpoint = EndpointConfig {
             url: format!("http://{address}/sync"),
-            method: "POST".to_string(),
+            failover_url: Vec::new(),
+            method: HttpMethod::Post,
             auth: Some(EndpointAuth::OAuth2 {
                 client_id: "client".to_string(),
-                client_secret: "secret".to_string(),
+                client_secret: "secret".into(),
                 token_url: format!("http://{address}/token"),
                 scopes: None,
                 audience: None,
@@ -538,7 +1238,9 @@ mod tests {
             },
             response: Some(ResponseConfig {
                 success_codes: vec![202],
-                handle_duplicates: "skip".to_string(),
+                handle_duplicates: DuplicatePolicy::Skip,
+                duplicate_codes: vec![409],
+                update_method: None,
             }),
         };
         let sender = HttpSender::new(&endpoint.request).unwrap();
@@ -564,6 +1266,107 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn resolves_duplicate_responses_by_policy() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut methods = Vec::new();
+            for response in ["409 Conflict", "409 Conflict", "200 OK", "409 Conflict"] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let request = String::from_utf8(read_request(&mut stream).await).unwrap();
+                methods.push(request.split_whitespace().next().unwrap().to_string());
+                let response = format!("HTTP/1.1 {response}\r\nContent-Length: 0\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            methods
+        });
+
+        let mut endpoint = EndpointConfig {
+            url: format!("http://{address}/sync"),
+            failover_url: Vec::new(),
+            method: HttpMethod::Post,
+            auth: None,
+            headers: None,
+            request: RequestConfig {
+                retry_attempts: Some(0),
+                ..RequestConfig::default()
+            },
+            response: Some(ResponseConfig {
+                success_codes: vec![200],
+                handle_duplicates: DuplicatePolicy::Skip,
+                duplicate_codes: vec![409],
+                update_method: None,
+            }),
+        };
+        let sender = HttpSender::new(&endpoint.request).unwrap();
+        let rows = [serde_json::json!({"id": 1})];
+
+        let skipped = sender.send(&endpoint, &rows).await.unwrap();
+        endpoint.response.as_mut().unwrap().handle_duplicates = DuplicatePolicy::Update;
+        let updated = sender.send(&endpoint, &rows).await.unwrap();
+        endpoint.response.as_mut().unwrap().handle_duplicates = DuplicatePolicy::Error;
+        let error = sender.send(&endpoint, &rows).await.unwrap_err();
+
+        assert_eq!(Some(DuplicateOutcome::Skipped), skipped.duplicate);
+        assert_eq!(Some(DuplicateOutcome::Updated), updated.duplicate);
+        assert_eq!(StatusCode::OK, updated.status);
+        assert!(
+            matches!(error, HttpError::Duplicate { status, .. } if status == StatusCode::CONFLICT)
+        );
+        assert_eq!(vec!["POST", "POST", "PUT", "POST"], server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn fetches_json_pages_and_follows_relative_link_header() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let request = String::from_utf8(read_request(&mut stream).await).unwrap();
+            let body = r#"[{"id":1}]"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nLink: </items?page=1>; rel=\"prev\", </items?page=3>; rel=\"next\"\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            request
+        });
+
+        let endpoint = EndpointConfig {
+            url: format!("http://{address}/items"),
+            failover_url: Vec::new(),
+            method: HttpMethod::Get,
+            auth: Some(EndpointAuth::ApiKey {
+                header_name: "X-Api-Key".to_string(),
+                token: "secret".into(),
+            }),
+            headers: None,
+            request: RequestConfig::default(),
+            response: None,
+        };
+        let sender = HttpSender::new(&endpoint.request).unwrap();
+
+        let page = sender
+            .fetch(&endpoint, &format!("http://{address}/items?page=2"))
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+
+        assert_eq!(serde_json::json!([{"id": 1}]), page.body);
+        assert_eq!(
+            Some(format!("http://{address}/items?page=3")),
+            page.next_link
+        );
+        assert!(request.starts_with("GET /items?page=2 HTTP/1.1"));
+        assert!(request.to_ascii_lowercase().contains("x-api-key: secret"));
+        assert!(!request.to_ascii_lowercase().contains("content-type"));
+        assert_eq!(
+            None,
+            next_link(r#"<https://example.com/?page=1>; rel="prev""#)
+        );
+    }
+
     async fn read_request(stream: &mut tokio::net::TcpStream) -> Vec<u8> {
         let mut bytes = Vec::new();
         let mut buffer = [0_u8; 2048];
@@ -99,7 +99,7 @@ impl OAuth2Client {
         let token = self
             .fetch_token(
                 client_id,
-                client_secret,
+                client_secret.expose(),
                 token_url,
                 scopes.as_deref(),
                 audience.as_deref(),
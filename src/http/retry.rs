@@ -1,70 +1,175 @@
-use crate::config::request_config::RequestConfig;
+use crate::config::destination_config::RetrySettings;
+use crate::config::request_config::{RequestConfig, RetryBackoff};
 use reqwest::StatusCode;
 use std::time::Duration;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum RetryBackoff {
-    Fixed,
-    Exponential,
-}
+const DEFAULT_JITTER: f64 = 0.2;
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(300);
+const DEFAULT_RETRY_STATUSES: &[u16] = &[408, 425, 429, 500, 502, 503, 504];
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct RetryPolicy {
     pub max_retries: u32,
     pub delay: Duration,
     pub backoff: RetryBackoff,
+    pub jitter: f64,
+    pub max_delay: Duration,
+    pub retry_statuses: Vec<u16>,
 }
 
 impl RetryPolicy {
     pub fn from_request(request: &RequestConfig) -> Self {
-        let backoff = match request.retry_backoff.as_deref() {
-            Some(value) if value.eq_ignore_ascii_case("fixed") => RetryBackoff::Fixed,
-            _ => RetryBackoff::Exponential,
-        };
+        Self {
+            retry_statuses: request
+                .retry_on_status
+                .clone()
+                .unwrap_or_else(|| DEFAULT_RETRY_STATUSES.to_vec()),
+            ..Self::new(
+                request.retry_attempts,
+                request.retry_delay_seconds,
+                request.retry_backoff,
+                request.retry_jitter,
+                request.retry_max_delay_seconds,
+            )
+        }
+    }
 
+    /// Builds a policy from a destination's `retry_*` settings.
+    pub fn from_settings(settings: &RetrySettings) -> Self {
+        Self::new(
+            settings.retry_attempts,
+            settings.retry_delay_seconds,
+            settings.retry_backoff,
+            settings.retry_jitter,
+            settings.retry_max_delay_seconds,
+        )
+    }
+
+    /// Builds a policy from the `retry_*` settings shared by HTTP endpoints and other sinks.
+    pub fn new(
+        attempts: Option<u32>,
+        delay_seconds: Option<u32>,
+        backoff: Option<RetryBackoff>,
+        jitter: Option<f64>,
+        max_delay_seconds: Option<u32>,
+    ) -> Self {
         Self {
-            max_retries: request.retry_attempts.unwrap_or(0),
-            delay: Duration::from_secs(request.retry_delay_seconds.unwrap_or(1) as u64),
-            backoff,
+            max_retries: attempts.unwrap_or(0),
+            delay: Duration::from_secs(delay_seconds.unwrap_or(1) as u64),
+            backoff: backoff.unwrap_or_default(),
+            jitter: jitter.unwrap_or(DEFAULT_JITTER).clamp(0.0, 1.0),
+            max_delay: max_delay_seconds
+                .map(|seconds| Duration::from_secs(seconds as u64))
+                .unwrap_or(DEFAULT_MAX_DELAY),
+            retry_statuses: DEFAULT_RETRY_STATUSES.to_vec(),
         }
     }
 
-    pub fn delay_for_retry(self, retry_index: u32) -> Duration {
-        match self.backoff {
+    /// Delay before retry `retry_index` (1-based) without jitter, capped at `max_delay`.
+    pub fn delay_for_retry(&self, retry_index: u32) -> Duration {
+        let exponent = retry_index.saturating_sub(1);
+        let delay = match self.backoff {
             RetryBackoff::Fixed => self.delay,
-            RetryBackoff::Exponential => {
-                let multiplier = 2_u32.saturating_pow(retry_index.saturating_sub(1));
-                self.delay.saturating_mul(multiplier)
-            }
+            RetryBackoff::Linear => self.delay.saturating_mul(retry_index.max(1)),
+            RetryBackoff::Exponential => self
+                .delay
+                .saturating_mul(2_u32.saturating_pow(exponent.min(31))),
+        };
+        delay.min(self.max_delay)
+    }
+
+    /// Jittered delay for the retry, never shorter than a server-provided `Retry-After`.
+    pub fn next_delay(&self, retry_index: u32, retry_after: Option<Duration>) -> Duration {
+        let base = self.delay_for_retry(retry_index);
+        let factor = 1.0 + self.jitter * (rand::random::<f64>() * 2.0 - 1.0);
+        let delay = base.mul_f64(factor).min(self.max_delay);
+        match retry_after {
+            Some(retry_after) => delay.max(retry_after.min(self.max_delay)),
+            None => delay,
         }
     }
+
+    pub fn is_retryable_status(&self, status: StatusCode) -> bool {
+        self.retry_statuses.contains(&status.as_u16())
+    }
 }
 
-pub fn is_transient_status(status: StatusCode) -> bool {
-    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504)
+/// Parses `Retry-After` as delay seconds or an HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn exponential_delay_doubles_each_retry() {
-        let policy = RetryPolicy {
+    fn policy(backoff: RetryBackoff) -> RetryPolicy {
+        RetryPolicy {
             max_retries: 3,
             delay: Duration::from_secs(2),
-            backoff: RetryBackoff::Exponential,
-        };
+            backoff,
+            jitter: 0.0,
+            max_delay: Duration::from_secs(300),
+            retry_statuses: DEFAULT_RETRY_STATUSES.to_vec(),
+        }
+    }
+
+    #[test]
+    fn exponential_delay_doubles_each_retry() {
+        let policy = policy(RetryBackoff::Exponential);
 
         assert_eq!(Duration::from_secs(2), policy.delay_for_retry(1));
         assert_eq!(Duration::from_secs(4), policy.delay_for_retry(2));
         assert_eq!(Duration::from_secs(8), policy.delay_for_retry(3));
+        assert_eq!(Duration::from_secs(300), policy.delay_for_retry(40));
+    }
+
+    #[test]
+    fn linear_delay_grows_by_base_and_jitter_stays_in_range() {
+        let linear = policy(RetryBackoff::Linear);
+        assert_eq!(Duration::from_secs(6), linear.delay_for_retry(3));
+
+        let jittered = RetryPolicy {
+            jitter: 0.5,
+            ..policy(RetryBackoff::Fixed)
+        };
+        for _ in 0..100 {
+            let delay = jittered.next_delay(1, None);
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(3));
+        }
+        assert_eq!(
+            Duration::from_secs(30),
+            linear.next_delay(1, Some(Duration::from_secs(30)))
+        );
     }
 
     #[test]
     fn only_expected_statuses_are_transient() {
-        assert!(is_transient_status(StatusCode::TOO_MANY_REQUESTS));
-        assert!(is_transient_status(StatusCode::BAD_GATEWAY));
-        assert!(!is_transient_status(StatusCode::BAD_REQUEST));
+        let default = policy(RetryBackoff::Fixed);
+        assert!(default.is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(default.is_retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(!default.is_retryable_status(StatusCode::BAD_REQUEST));
+
+        let custom = RetryPolicy {
+            retry_statuses: vec![409],
+            ..policy(RetryBackoff::Fixed)
+        };
+        assert!(custom.is_retryable_status(StatusCode::CONFLICT));
+        assert!(!custom.is_retryable_status(StatusCode::BAD_GATEWAY));
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_dates() {
+        assert_eq!(Some(Duration::from_secs(120)), parse_retry_after("120"));
+        assert_eq!(None, parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"));
+        let later = (chrono::Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        assert!(parse_retry_after(&later).is_some_and(|delay| delay > Duration::from_secs(80)));
     }
 }
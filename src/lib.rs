@@ -0,0 +1,90 @@
+//! Yetii's modules, built once for the `YETII` binary and the benches under `benches/`. The
+//! binary calls [`run`]; [`bench`] is the only other entry point.
+
+mod capabilities;
+mod chaos;
+mod cli;
+mod commands;
+mod config;
+mod database;
+mod history;
+mod http;
+mod logging;
+mod monitoring;
+mod notifications;
+mod pull;
+mod sinks;
+mod sort;
+mod state;
+mod transform;
+mod workspace;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::process::ExitCode;
+
+/// Transform steps for `benches/transforms.rs`; not a stable API.
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::config::transform_config::TransformConfig;
+    pub use crate::transform::bench::{Step, steps};
+}
+
+/// Parses the command line and runs the command.
+#[tokio::main]
+pub async fn run() -> Result<ExitCode> {
+    let yetii = cli::Yetii::parse();
+    let loads_config = !matches!(
+        yetii.commands,
+        cli::Commands::Init { .. }
+            | cli::Commands::Bootstrap
+            | cli::Commands::CheckExistingOdbc
+            | cli::Commands::Version
+            | cli::Commands::CheckConfig { .. }
+            | cli::Commands::Config { .. }
+            | cli::Commands::Schema { .. }
+            | cli::Commands::Completions { .. }
+            | cli::Commands::Secret { .. }
+            | cli::Commands::Encrypt { .. }
+            | cli::Commands::Decrypt { .. }
+            | cli::Commands::SupportBundle { .. }
+            | cli::Commands::Status { .. }
+    );
+    if let Some(path) = &yetii.env_file {
+        config::dotenv::set_env_file(path)?;
+    }
+    if let Some(name) = &yetii.environment {
+        config::environment_config::set_environment(name)?;
+    }
+    // Loaded before tracing starts, so the configured logging applies from the first event.
+    let logging_config = if loads_config {
+        config::load_config_once(yetii.config_path())
+            .with_context(|| format!("failed to load configuration '{}'", yetii.config_path()))?;
+        config::get_config()?.global_settings.logging.clone()
+    } else {
+        Default::default()
+    };
+    // `exec` and `dry-run` write data to standard output, so their console logs go to standard
+    // error.
+    let stdout_is_data = matches!(
+        yetii.commands,
+        cli::Commands::Exec { .. }
+            | cli::Commands::DryRun { .. }
+            | cli::Commands::Decrypt { .. }
+            | cli::Commands::Schema { .. }
+            | cli::Commands::Completions { .. }
+    );
+    logging::initialize(&logging_config, yetii.verbose, stdout_is_data)?;
+    if let Some(spec) = &yetii.chaos {
+        chaos::install(spec).context("invalid --chaos specification")?;
+        tracing::warn!(chaos = %spec, "chaos fault injection is enabled; never use this in production");
+    }
+
+    match commands::going_through_commands(&yetii).await {
+        Err(error) if error.downcast_ref::<commands::PartialDelivery>().is_some() => {
+            eprintln!("Error: {error:?}");
+            Ok(ExitCode::from(commands::PARTIAL_DELIVERY_EXIT_CODE))
+        }
+        result => result.map(|()| ExitCode::SUCCESS),
+    }
+}
@@ -0,0 +1,162 @@
+//! Tracing subscriber set up from `global_settings.logging`: the level, JSON or text lines, and
+//! the console or a file, rotated by size when `rotation` is set.
+
+use crate::config::logging::{LogFormat, LogOutput, LogRotation, Logging};
+use anyhow::{Context, Result, anyhow};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+/// Installs the global subscriber. `RUST_LOG` overrides the level, and `--verbose` raises it
+/// to `debug`. With `stdout_is_data`, console output goes to standard error.
+pub fn initialize(logging: &Logging, verbose: bool, stdout_is_data: bool) -> Result<()> {
+    let level = if verbose {
+        "debug"
+    } else {
+        logging.level.as_str()
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+    let to_file = logging.output == LogOutput::File;
+    let writer = if to_file {
+        let path = logging
+            .file_path
+            .as_deref()
+            .ok_or_else(|| anyhow!("logging.output is file but logging.file_path is not set"))?;
+        file_writer(Path::new(path), logging.rotation.as_ref())?
+    } else if stdout_is_data {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(!to_file);
+    match logging.format {
+        LogFormat::Plain => builder.compact().with_target(false).try_init(),
+        LogFormat::Structured => builder.try_init(),
+        LogFormat::Json => builder.json().with_target(false).try_init(),
+    }
+    .map_err(|error| anyhow!("failed to initialize tracing: {error}"))
+}
+
+/// Appends to `path`, creating it and its directory when missing.
+fn file_writer(path: &Path, rotation: Option<&LogRotation>) -> Result<BoxMakeWriter> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create log directory '{}'", parent.display()))?;
+    }
+    let open = || format!("failed to open log file '{}'", path.display());
+    Ok(match rotation {
+        Some(rotation) => BoxMakeWriter::new(Mutex::new(
+            RotatingFile::open(
+                path,
+                u64::from(rotation.max_size_mb) * 1024 * 1024,
+                rotation.max_files,
+            )
+            .with_context(open)?,
+        )),
+        None => BoxMakeWriter::new(Mutex::new(append(path).with_context(open)?)),
+    })
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// A log file that is renamed to `<path>.1` once it would grow past `max_bytes`, shifting older
+/// files to `.2`, `.3`, ... and deleting those beyond `max_files`.
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: u32,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_bytes: u64, max_files: u32) -> io::Result<Self> {
+        let file = append(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            let _ = std::fs::remove_file(self.rotated(self.max_files));
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated(index);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated(1))?;
+        }
+        self.file = append(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn rotated(&self, index: u32) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{index}"));
+        PathBuf::from(name)
+    }
+}
+
+impl Write for RotatingFile {
+    // Each event arrives in one write, so a line is never split across files.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_by_size_and_keeps_max_files() {
+        let directory =
+            std::env::temp_dir().join(format!("yetii-log-rotation-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("yetii.log");
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        assert_eq!("fourth\n", read(path.clone()));
+        assert_eq!("third\n", read(directory.join("yetii.log.1")));
+        assert_eq!("second\n", read(directory.join("yetii.log.2")));
+        assert!(!directory.join("yetii.log.3").exists());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -1,14 +1,32 @@
-use crate::config::monitor_config::MonitoringConfig;
+pub mod otlp;
+
+use crate::config::monitor_config::{
+    MetricsConfig, MetricsMode, MonitoringConfig, ServerFailureAction,
+};
 use anyhow::{Context, Result, anyhow};
 use chrono::{DateTime, Utc};
 use serde::Serialize;
-use serde_json::json;
+use serde_json::{Value, json};
 use std::collections::{BTreeMap, HashMap};
 use std::sync::{LazyLock, Mutex};
 use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpListener;
-use tokio::sync::watch;
+use tokio::sync::{mpsc, watch};
+
+/// How often a server that could not bind tries again when `server_retry_seconds` is not set.
+const DEFAULT_SERVER_RETRY_SECONDS: u32 = 30;
+/// Upper bounds, in seconds, of the batch duration histogram buckets.
+const BATCH_DURATION_BUCKETS: [f64; 11] =
+    [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+tokio::task_local! {
+    /// The query whose work runs in the current task, for metrics recorded deeper down such
+    /// as HTTP retries.
+    static CURRENT_QUERY: String;
+    /// ID of the `run` or flow whose work runs in the current task.
+    static CURRENT_RUN: String;
+}
 
 static METRICS: LazyLock<Mutex<MetricsState>> =
     LazyLock::new(|| Mutex::new(MetricsState::default()));
@@ -27,6 +45,40 @@ struct MetricsState {
     http_retries: u64,
     overlap_skips: u64,
     queries: BTreeMap<String, QueryMetrics>,
+    schedule: Option<Schedule>,
+    databases: BTreeMap<String, DatabaseStatus>,
+    subsystems: BTreeMap<String, SubsystemStatus>,
+}
+
+/// State of a monitoring server or of notification delivery.
+#[derive(Debug, Serialize)]
+struct SubsystemStatus {
+    up: bool,
+    since: DateTime<Utc>,
+    failures: u64,
+    error: Option<String>,
+}
+
+/// The daemon's current schedule.
+#[derive(Debug, Serialize)]
+struct Schedule {
+    loaded_at: DateTime<Utc>,
+    jobs: Vec<ScheduledJob>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledJob {
+    pub query: String,
+    pub cron: String,
+}
+
+/// Result of the last connection check of a database.
+#[derive(Debug, Serialize)]
+struct DatabaseStatus {
+    reachable: bool,
+    checked_at: DateTime<Utc>,
+    latency_ms: u64,
+    error: Option<String>,
 }
 
 #[derive(Debug, Default, Serialize)]
@@ -38,15 +90,60 @@ struct QueryMetrics {
     pages: u64,
     batches: u64,
     last_duration_ms: u64,
+    last_started_at: Option<DateTime<Utc>>,
     last_success_at: Option<DateTime<Utc>>,
     last_failure_at: Option<DateTime<Utc>>,
     last_error: Option<String>,
+    rows_delivered: u64,
+    batches_failed: u64,
+    retries: u64,
+    #[serde(skip)]
+    batch_durations: Histogram,
+}
+
+/// Cumulative Prometheus histogram over [`BATCH_DURATION_BUCKETS`].
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; BATCH_DURATION_BUCKETS.len()],
+    count: u64,
+    sum: f64,
 }
 
-#[derive(Default)]
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bound, bucket) in BATCH_DURATION_BUCKETS.iter().zip(&mut self.buckets) {
+            if seconds <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+#[derive(Clone, Default)]
 struct Routes {
     health_path: Option<String>,
     metrics_path: Option<String>,
+    reload: Option<ReloadEndpoint>,
+}
+
+impl Routes {
+    /// The subsystem name under which the listener's state is reported.
+    fn subsystem(&self) -> &'static str {
+        match (self.health_path.is_some(), self.metrics_path.is_some()) {
+            (true, true) => "monitoring_server",
+            (true, false) => "health_server",
+            _ => "metrics_server",
+        }
+    }
+}
+
+/// `POST` on `path` asks the daemon to reload its configuration through `sender`.
+#[derive(Clone)]
+pub struct ReloadEndpoint {
+    pub path: String,
+    pub sender: mpsc::UnboundedSender<&'static str>,
 }
 
 pub struct MonitoringServer {
@@ -79,6 +176,7 @@ pub fn query_started(query: &str) {
     let query = metrics.queries.entry(query.to_string()).or_default();
     query.active = true;
     query.runs += 1;
+    query.last_started_at = Some(Utc::now());
 }
 
 pub fn query_succeeded(query: &str, rows: usize, pages: usize, batches: usize, duration: Duration) {
@@ -122,8 +220,112 @@ pub fn query_failed(
     query.last_error = Some(error.to_string());
 }
 
+/// Replaces the schedule reported by the health endpoint, at startup and after a reload.
+pub fn set_schedule(jobs: Vec<ScheduledJob>) {
+    lock_metrics().schedule = Some(Schedule {
+        loaded_at: Utc::now(),
+        jobs,
+    });
+}
+
+/// Replaces the database statuses with one round of connection checks: the database's name,
+/// the error when it refused the connection, and how long the attempt took.
+pub fn databases_checked(checks: Vec<(String, Option<String>, Duration)>) {
+    let checked_at = Utc::now();
+    lock_metrics().databases = checks
+        .into_iter()
+        .map(|(database, error, duration)| {
+            let status = DatabaseStatus {
+                reachable: error.is_none(),
+                checked_at,
+                latency_ms: duration.as_millis().min(u64::MAX as u128) as u64,
+                error,
+            };
+            (database, status)
+        })
+        .collect();
+}
+
+/// Marks `subsystem` as failing, e.g. a server that cannot bind or an unreachable notification
+/// service. The daemon keeps running and reports it as `degraded`.
+pub fn subsystem_failed(subsystem: &str, error: &str) {
+    let mut metrics = lock_metrics();
+    let status = metrics
+        .subsystems
+        .entry(subsystem.to_string())
+        .or_insert_with(|| SubsystemStatus {
+            up: false,
+            since: Utc::now(),
+            failures: 0,
+            error: None,
+        });
+    if status.up {
+        status.up = false;
+        status.since = Utc::now();
+    }
+    status.failures += 1;
+    status.error = Some(error.to_string());
+}
+
+/// Marks `subsystem` as working again; a subsystem that never failed is added as up.
+pub fn subsystem_up(subsystem: &str) {
+    let mut metrics = lock_metrics();
+    let status = metrics
+        .subsystems
+        .entry(subsystem.to_string())
+        .or_insert_with(|| SubsystemStatus {
+            up: true,
+            since: Utc::now(),
+            failures: 0,
+            error: None,
+        });
+    if !status.up {
+        status.up = true;
+        status.since = Utc::now();
+        status.error = None;
+    }
+}
+
+/// Runs `future` with `query` as the query that metrics recorded inside it belong to.
+pub async fn for_query<F: Future>(query: &str, future: F) -> F::Output {
+    CURRENT_QUERY.scope(query.to_string(), future).await
+}
+
+/// A new ID for a run, unique enough to tell interleaved runs apart in logs.
+pub fn new_run_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+pub async fn for_run<F: Future>(run_id: &str, future: F) -> F::Output {
+    CURRENT_RUN.scope(run_id.to_string(), future).await
+}
+
+/// `None` outside a run, e.g. for daemon lifecycle events.
+pub fn current_run_id() -> Option<String> {
+    CURRENT_RUN.try_with(String::clone).ok()
+}
+
+pub fn batch_delivered(query: &str, rows: usize, duration: Duration) {
+    let mut metrics = lock_metrics();
+    let query = metrics.queries.entry(query.to_string()).or_default();
+    query.rows_delivered += rows as u64;
+    query.batch_durations.observe(duration.as_secs_f64());
+}
+
+pub fn batch_failed(query: &str, duration: Duration) {
+    let mut metrics = lock_metrics();
+    let query = metrics.queries.entry(query.to_string()).or_default();
+    query.batches_failed += 1;
+    query.batch_durations.observe(duration.as_secs_f64());
+}
+
 pub fn record_http_retry() {
-    lock_metrics().http_retries += 1;
+    let query = CURRENT_QUERY.try_with(String::clone).ok();
+    let mut metrics = lock_metrics();
+    metrics.http_retries += 1;
+    if let Some(query) = query {
+        metrics.queries.entry(query).or_default().retries += 1;
+    }
 }
 
 pub fn record_overlap_skip(query: &str) {
@@ -136,7 +338,10 @@ pub fn record_overlap_skip(query: &str) {
         .last_error = Some("overlapping scheduled execution skipped".to_string());
 }
 
-pub async fn start(config: Option<&MonitoringConfig>) -> Result<Option<MonitoringServer>> {
+pub async fn start(
+    config: Option<&MonitoringConfig>,
+    reload: Option<ReloadEndpoint>,
+) -> Result<Option<MonitoringServer>> {
     let Some(config) = config.filter(|config| config.enabled) else {
         return Ok(None);
     };
@@ -144,12 +349,21 @@ pub async fn start(config: Option<&MonitoringConfig>) -> Result<Option<Monitorin
 
     let mut listeners = HashMap::<String, Routes>::new();
     if let Some(health) = config.health_check.as_ref().filter(|health| health.enabled) {
-        listeners
+        let routes = listeners
             .entry(format!("127.0.0.1:{}", health.port))
-            .or_default()
-            .health_path = Some(health.endpoint.clone());
+            .or_default();
+        routes.health_path = Some(health.endpoint.clone());
+        routes.reload = reload;
     }
-    if let Some(metrics) = config.metrics.as_ref().filter(|metrics| metrics.enabled) {
+    let push = config
+        .metrics
+        .as_ref()
+        .filter(|metrics| metrics.enabled && metrics.mode == MetricsMode::Push);
+    if let Some(metrics) = config
+        .metrics
+        .as_ref()
+        .filter(|metrics| metrics.enabled && metrics.mode == MetricsMode::Serve)
+    {
         let url = url::Url::parse(&metrics.endpoint)
             .with_context(|| format!("invalid metrics endpoint '{}'", metrics.endpoint))?;
         let host = match url.host_str().unwrap_or("127.0.0.1") {
@@ -168,23 +382,123 @@ pub async fn start(config: Option<&MonitoringConfig>) -> Result<Option<Monitorin
         };
         listeners.entry(address).or_default().metrics_path = Some(path);
     }
-    if listeners.is_empty() {
+    if listeners.is_empty() && push.is_none() {
         return Ok(None);
     }
 
+    let policy = config.failure_policy.clone().unwrap_or_default();
+    let retry = Duration::from_secs(
+        policy
+            .server_retry_seconds
+            .unwrap_or(DEFAULT_SERVER_RETRY_SECONDS)
+            .into(),
+    );
     let (shutdown, _) = watch::channel(false);
     let mut handles = Vec::new();
     for (address, routes) in listeners {
-        let listener = TcpListener::bind(&address)
-            .await
-            .with_context(|| format!("failed to bind monitoring server to {address}"))?;
         let receiver = shutdown.subscribe();
-        tracing::info!(address, "monitoring server listening");
-        handles.push(tokio::spawn(serve(listener, routes, receiver)));
+        let subsystem = routes.subsystem();
+        match TcpListener::bind(&address).await {
+            Ok(listener) => {
+                tracing::info!(address, "monitoring server listening");
+                subsystem_up(subsystem);
+                handles.push(tokio::spawn(serve(listener, routes, receiver)));
+            }
+            Err(error) if policy.server == ServerFailureAction::Fail => {
+                return Err(error)
+                    .with_context(|| format!("failed to bind monitoring server to {address}"));
+            }
+            Err(error) => {
+                let retrying = policy.server == ServerFailureAction::Retry;
+                tracing::warn!(
+                    address,
+                    error = %error,
+                    retry_seconds = retrying.then_some(retry.as_secs()),
+                    "monitoring server could not bind; the daemon runs without it"
+                );
+                subsystem_failed(subsystem, &format!("failed to bind {address}: {error}"));
+                if retrying {
+                    handles.push(tokio::spawn(bind_later(address, routes, receiver, retry)));
+                }
+            }
+        }
+    }
+    if let Some(metrics) = push {
+        handles.push(tokio::spawn(push_metrics(
+            metrics.clone(),
+            shutdown.subscribe(),
+        )));
     }
     Ok(Some(MonitoringServer { shutdown, handles }))
 }
 
+/// Sends the metrics to a Pushgateway every `interval_seconds`, and once more at shutdown so
+/// the last runs are not lost.
+async fn push_metrics(metrics: MetricsConfig, mut shutdown: watch::Receiver<bool>) {
+    let client = reqwest::Client::new();
+    let interval = Duration::from_secs(metrics.interval_seconds.into());
+    tracing::info!(endpoint = %metrics.endpoint, interval_seconds = metrics.interval_seconds, "pushing metrics");
+    loop {
+        let stopping = tokio::select! {
+            _ = tokio::time::sleep(interval) => false,
+            changed = shutdown.changed() => changed.is_err() || *shutdown.borrow(),
+        };
+        let pushed = client
+            .put(&metrics.endpoint)
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .timeout(interval.max(Duration::from_secs(1)))
+            .body(metrics_body())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match pushed {
+            Ok(_) => subsystem_up("metrics_push"),
+            Err(error) => {
+                tracing::warn!(endpoint = %metrics.endpoint, error = %error, "metrics push failed");
+                subsystem_failed("metrics_push", &error.to_string());
+            }
+        }
+        if stopping {
+            return;
+        }
+    }
+}
+
+/// Tries to bind `address` every `retry` until it succeeds, then serves it.
+async fn bind_later(
+    address: String,
+    routes: Routes,
+    mut shutdown: watch::Receiver<bool>,
+    retry: Duration,
+) {
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(retry) => {}
+            changed = shutdown.changed() => {
+                if changed.is_err() || *shutdown.borrow() {
+                    return;
+                }
+                continue;
+            }
+        }
+        match TcpListener::bind(&address).await {
+            Ok(listener) => {
+                tracing::info!(address, "monitoring server listening after retrying");
+                subsystem_up(routes.subsystem());
+                serve(listener, routes, shutdown).await;
+                return;
+            }
+            Err(error) => {
+                tracing::debug!(address, error = %error, "monitoring server still cannot bind");
+                subsystem_failed(
+                    routes.subsystem(),
+                    &format!("failed to bind {address}: {error}"),
+                );
+            }
+        }
+    }
+}
+
 impl MonitoringServer {
     pub async fn shutdown(self) {
         let _ = self.shutdown.send(true);
@@ -200,10 +514,9 @@ async fn serve(listener: TcpListener, routes: Routes, mut shutdown: watch::Recei
             result = listener.accept() => {
                 match result {
                     Ok((stream, _)) => {
-                        let health_path = routes.health_path.clone();
-                        let metrics_path = routes.metrics_path.clone();
+                        let routes = routes.clone();
                         tokio::spawn(async move {
-                            if let Err(error) = respond(stream, health_path.as_deref(), metrics_path.as_deref()).await {
+                            if let Err(error) = respond(stream, &routes).await {
                                 tracing::debug!(error = %error, "monitoring request failed");
                             }
                         });
@@ -220,21 +533,39 @@ async fn serve(listener: TcpListener, routes: Routes, mut shutdown: watch::Recei
     }
 }
 
-async fn respond(
-    mut stream: tokio::net::TcpStream,
-    health_path: Option<&str>,
-    metrics_path: Option<&str>,
-) -> Result<()> {
+async fn respond(mut stream: tokio::net::TcpStream, routes: &Routes) -> Result<()> {
     let mut buffer = [0_u8; 4096];
     let read = stream.read(&mut buffer).await?;
     let request = String::from_utf8_lossy(&buffer[..read]);
-    let path = request
+    let mut request_line = request
         .lines()
         .next()
-        .and_then(|line| line.split_whitespace().nth(1))
-        .unwrap_or("/");
-
-    let (status, content_type, body) = if health_path == Some(path) {
+        .map(str::split_whitespace)
+        .into_iter()
+        .flatten();
+    let method = request_line.next().unwrap_or("GET");
+    let path = request_line.next().unwrap_or("/");
+
+    let reload = routes.reload.as_ref().filter(|reload| reload.path == path);
+    let (status, content_type, body) = if let Some(reload) = reload {
+        match method {
+            "POST" if reload.sender.send("endpoint").is_ok() => (
+                "202 Accepted",
+                "application/json",
+                "{\"status\":\"reload requested\"}\n".to_string(),
+            ),
+            "POST" => (
+                "503 Service Unavailable",
+                "application/json",
+                "{\"error\":\"daemon is shutting down\"}\n".to_string(),
+            ),
+            _ => (
+                "405 Method Not Allowed",
+                "application/json",
+                "{\"error\":\"use POST\"}\n".to_string(),
+            ),
+        }
+    } else if routes.health_path.as_deref() == Some(path) {
         let (healthy, body) = health_body();
         (
             if healthy {
@@ -245,7 +576,7 @@ async fn respond(
             "application/json",
             body,
         )
-    } else if metrics_path == Some(path) {
+    } else if routes.metrics_path.as_deref() == Some(path) {
         ("200 OK", "text/plain; version=0.0.4", metrics_body())
     } else {
         (
@@ -262,20 +593,46 @@ async fn respond(
     Ok(())
 }
 
+/// Unreachable databases and failing monitoring subsystems make the daemon `degraded` but still
+/// healthy: restarting it would fix neither.
 fn health_body() -> (bool, String) {
+    let status = status();
+    let healthy = status["status"] != "unavailable";
+    (healthy, format!("{status}\n"))
+}
+
+/// The daemon's state as served by the health endpoint and the control socket.
+pub fn status() -> Value {
     let metrics = lock_metrics();
-    let healthy = metrics.ready && !metrics.shutting_down;
-    let body = json!({
-        "status": if healthy { "healthy" } else { "unavailable" },
+    let status = if !metrics.ready || metrics.shutting_down {
+        "unavailable"
+    } else if metrics
+        .databases
+        .values()
+        .any(|database| !database.reachable)
+        || metrics.subsystems.values().any(|subsystem| !subsystem.up)
+    {
+        "degraded"
+    } else {
+        "healthy"
+    };
+    json!({
+        "status": status,
+        "pid": std::process::id(),
         "ready": metrics.ready,
         "shutting_down": metrics.shutting_down,
         "started_at": metrics.started_at,
         "active_queries": metrics.active_queries,
+        "scheduler": metrics.schedule,
+        "databases": metrics.databases,
+        "monitoring": metrics.subsystems,
         "queries": metrics.queries,
-    });
-    (healthy, format!("{body}\n"))
+    })
 }
 
+/// Reads one per-query metric for the exposition.
+type QueryCounter = fn(&QueryMetrics) -> u64;
+
 fn metrics_body() -> String {
     let metrics = lock_metrics();
     let mut output = format!(
@@ -298,13 +655,70 @@ fn metrics_body() -> String {
         metrics.http_retries,
         metrics.overlap_skips,
     );
+    if !metrics.subsystems.is_empty() {
+        output.push_str("# TYPE yetii_monitoring_subsystem_up gauge\n");
+    }
+    for (name, subsystem) in &metrics.subsystems {
+        output.push_str(&format!(
+            "yetii_monitoring_subsystem_up{{subsystem=\"{}\"}} {}\n",
+            escape_label(name),
+            u8::from(subsystem.up)
+        ));
+    }
+    let families: [(&str, &str, QueryCounter); 8] = [
+        ("yetii_query_runs_total", "counter", |query| query.runs),
+        ("yetii_query_failures_total", "counter", |query| {
+            query.failures
+        }),
+        ("yetii_query_last_duration_ms", "gauge", |query| {
+            query.last_duration_ms
+        }),
+        ("yetii_query_rows_fetched_total", "counter", |query| {
+            query.rows
+        }),
+        ("yetii_query_rows_delivered_total", "counter", |query| {
+            query.rows_delivered
+        }),
+        ("yetii_query_batches_total", "counter", |query| {
+            query.batches
+        }),
+        ("yetii_query_batch_failures_total", "counter", |query| {
+            query.batches_failed
+        }),
+        ("yetii_query_retries_total", "counter", |query| {
+            query.retries
+        }),
+    ];
+    for (family, kind, value) in families {
+        if metrics.queries.is_empty() {
+            break;
+        }
+        output.push_str(&format!("# TYPE {family} {kind}\n"));
+        for (name, query) in &metrics.queries {
+            output.push_str(&format!(
+                "{family}{{query=\"{}\"}} {}\n",
+                escape_label(name),
+                value(query)
+            ));
+        }
+    }
+    if !metrics.queries.is_empty() {
+        output.push_str("# TYPE yetii_batch_duration_seconds histogram\n");
+    }
     for (name, query) in &metrics.queries {
         let name = escape_label(name);
+        let histogram = &query.batch_durations;
+        for (bound, count) in BATCH_DURATION_BUCKETS.iter().zip(histogram.buckets) {
+            output.push_str(&format!(
+                "yetii_batch_duration_seconds_bucket{{query=\"{name}\",le=\"{bound}\"}} {count}\n"
+            ));
+        }
         output.push_str(&format!(
-            "yetii_query_runs_total{{query=\"{name}\"}} {}\n\
-yetii_query_failures_total{{query=\"{name}\"}} {}\n\
-yetii_query_last_duration_ms{{query=\"{name}\"}} {}\n",
-            query.runs, query.failures, query.last_duration_ms
+            "yetii_batch_duration_seconds_bucket{{query=\"{name}\",le=\"+Inf\"}} {count}\n\
+yetii_batch_duration_seconds_sum{{query=\"{name}\"}} {}\n\
+yetii_batch_duration_seconds_count{{query=\"{name}\"}} {count}\n",
+            histogram.sum,
+            count = histogram.count
         ));
     }
     output
@@ -331,24 +745,74 @@ mod tests {
     fn metrics_include_query_and_retry_counters() {
         initialize();
         set_ready(true);
-        query_started("orders");
-        record_http_retry();
-        query_succeeded("orders", 25, 2, 3, Duration::from_millis(40));
+        query_started("metered");
+        CURRENT_QUERY.sync_scope("metered".to_string(), record_http_retry);
+        batch_delivered("metered", 20, Duration::from_millis(80));
+        batch_failed("metered", Duration::from_secs(3));
+        query_succeeded("metered", 25, 2, 3, Duration::from_millis(40));
 
         let body = metrics_body();
 
         assert!(body.contains("yetii_ready 1"));
         assert!(body.contains("yetii_http_retries_total"));
-        assert!(body.contains("yetii_query_runs_total{query=\"orders\"}"));
+        assert!(body.contains("yetii_query_runs_total{query=\"metered\"}"));
+        assert!(body.contains("yetii_query_retries_total{query=\"metered\"} 1\n"));
+        assert!(body.contains("yetii_query_rows_delivered_total{query=\"metered\"} 20\n"));
+        assert!(body.contains("yetii_query_batch_failures_total{query=\"metered\"} 1\n"));
+        assert!(
+            body.contains("yetii_batch_duration_seconds_bucket{query=\"metered\",le=\"0.1\"} 1\n")
+        );
+        assert!(
+            body.contains("yetii_batch_duration_seconds_bucket{query=\"metered\",le=\"5\"} 2\n")
+        );
+        assert!(body.contains("yetii_batch_duration_seconds_count{query=\"metered\"} 2\n"));
+    }
+
+    /// Held by tests that read the health `status`, which other tests' subsystems would change.
+    static STATUS: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    #[tokio::test]
+    async fn server_that_cannot_bind_is_retried_in_background() {
+        let _status = STATUS.lock().await;
+        let blocker = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = blocker.local_addr().unwrap().port();
+        let mut config: MonitoringConfig = serde_yaml::from_str(&format!(
+            "enabled: true\nhealth_check:\n  enabled: true\n  endpoint: /health\n  port: {port}\nfailure_policy:\n  server: fail\n  server_retry_seconds: 1\n"
+        ))
+        .unwrap();
+        assert!(start(Some(&config), None).await.is_err());
+
+        config.failure_policy.as_mut().unwrap().server = ServerFailureAction::Retry;
+        let server = start(Some(&config), None).await.unwrap().unwrap();
+        assert!(!lock_metrics().subsystems["health_server"].up);
+        drop(blocker);
+        let mut attempts = 0;
+        while !lock_metrics().subsystems["health_server"].up {
+            attempts += 1;
+            assert!(attempts < 50, "server was not bound again");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let health = reqwest::get(format!("http://127.0.0.1:{port}/health"))
+            .await
+            .unwrap();
+        let health = health.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(true, health["monitoring"]["health_server"]["up"]);
+        server.shutdown().await;
     }
 
     #[tokio::test]
     async fn health_and_metrics_routes_respond() {
+        let _status = STATUS.lock().await;
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
         let address = listener.local_addr().unwrap();
+        let (reload, mut reloads) = mpsc::unbounded_channel();
         let routes = Routes {
             health_path: Some("/health".to_string()),
             metrics_path: Some("/metrics".to_string()),
+            reload: Some(ReloadEndpoint {
+                path: "/-/reload".to_string(),
+                sender: reload,
+            }),
         };
         let (_shutdown, receiver) = watch::channel(false);
         let server = tokio::spawn(serve(listener, routes, receiver));
@@ -363,6 +827,25 @@ mod tests {
             health.json::<serde_json::Value>().await.unwrap()["status"]
         );
 
+        set_schedule(vec![ScheduledJob {
+            query: "orders".to_string(),
+            cron: "0 */5 * * * *".to_string(),
+        }]);
+        databases_checked(vec![(
+            "warehouse".to_string(),
+            Some("login timeout expired".to_string()),
+            Duration::from_millis(12),
+        )]);
+        let health = reqwest::get(format!("http://{address}/health"))
+            .await
+            .unwrap();
+        assert!(health.status().is_success());
+        let health = health.json::<serde_json::Value>().await.unwrap();
+        assert_eq!("degraded", health["status"]);
+        assert_eq!("orders", health["scheduler"]["jobs"][0]["query"]);
+        assert_eq!(false, health["databases"]["warehouse"]["reachable"]);
+        databases_checked(Vec::new());
+
         let metrics = reqwest::get(format!("http://{address}/metrics"))
             .await
             .unwrap()
@@ -371,6 +854,30 @@ mod tests {
             .unwrap();
         assert!(metrics.contains("yetii_ready"));
 
+        let client = reqwest::Client::new();
+        let reload_url = format!("http://{address}/-/reload");
+        assert_eq!(
+            405,
+            client
+                .get(&reload_url)
+                .send()
+                .await
+                .unwrap()
+                .status()
+                .as_u16()
+        );
+        assert_eq!(
+            202,
+            client
+                .post(&reload_url)
+                .send()
+                .await
+                .unwrap()
+                .status()
+                .as_u16()
+        );
+        assert_eq!(Some("endpoint"), reloads.recv().await);
+
         server.abort();
     }
 }
@@ -0,0 +1,273 @@
+//! OpenTelemetry traces of query runs, exported with the OTLP/HTTP JSON exporter. Each run is one trace: a
+//! root span for the query and child spans for every fetch, transform, and deliver stage.
+
+use crate::config::monitor_config::{MonitoringConfig, TracingConfig};
+use opentelemetry::trace::{
+    SpanContext, SpanId, SpanKind, Status, TraceFlags, TraceId, TraceState,
+};
+use opentelemetry::{InstrumentationScope, KeyValue};
+use opentelemetry_otlp::{Protocol, WithExportConfig, WithHttpConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::{SpanData, SpanEvents, SpanExporter, SpanLinks};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+const DEFAULT_SERVICE_NAME: &str = "yetii";
+const DEFAULT_TIMEOUT_SECONDS: u32 = 10;
+
+tokio::task_local! {
+    /// The trace that spans started in the current task belong to.
+    static CURRENT_TRACE: Arc<Mutex<Vec<Span>>>;
+}
+
+#[derive(Debug, Clone)]
+struct Span {
+    span_id: u64,
+    name: &'static str,
+    start: SystemTime,
+    end: SystemTime,
+}
+
+/// A query run being traced.
+pub struct QueryTrace {
+    config: TracingConfig,
+    query: String,
+    trace_id: u128,
+    span_id: u64,
+    start: SystemTime,
+    spans: Arc<Mutex<Vec<Span>>>,
+}
+
+impl QueryTrace {
+    /// `None` when monitoring or tracing is off.
+    pub fn start(monitoring: Option<&MonitoringConfig>, query: &str) -> Option<Self> {
+        let config = monitoring
+            .filter(|monitoring| monitoring.enabled)?
+            .tracing
+            .as_ref()
+            .filter(|tracing| tracing.enabled)?;
+        Some(Self {
+            config: config.clone(),
+            query: query.to_string(),
+            trace_id: rand::random::<u128>().max(1),
+            span_id: rand::random::<u64>().max(1),
+            start: SystemTime::now(),
+            spans: Arc::new(Mutex::new(Vec::new())),
+        })
+    }
+
+    /// Runs `future` with the spans it starts recorded in this trace.
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        CURRENT_TRACE.scope(self.spans.clone(), future).await
+    }
+
+    /// Ends the root span and sends the trace. An export failure is logged and shown as the
+    /// `tracing` monitoring subsystem; the run's outcome is not affected.
+    pub async fn finish(self, attributes: &[(&str, Value)], error: Option<&str>) {
+        let endpoint = traces_url(&self.config.endpoint);
+        let spans = self.span_data(SystemTime::now(), attributes, error);
+        let timeout = Duration::from_secs(
+            self.config
+                .timeout_seconds
+                .unwrap_or(DEFAULT_TIMEOUT_SECONDS)
+                .into(),
+        );
+        let exported = match opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_protocol(Protocol::HttpJson)
+            .with_endpoint(endpoint.clone())
+            .with_timeout(timeout)
+            .with_headers(self.config.headers.clone())
+            .build()
+        {
+            Ok(mut exporter) => {
+                exporter.set_resource(&self.resource());
+                exporter
+                    .export(spans)
+                    .await
+                    .map_err(|error| error.to_string())
+            }
+            Err(error) => Err(error.to_string()),
+        };
+        match exported {
+            Ok(()) => super::subsystem_up("tracing"),
+            Err(error) => {
+                tracing::warn!(
+                    query = %self.query,
+                    endpoint,
+                    error = %error,
+                    "trace export failed"
+                );
+                super::subsystem_failed("tracing", &error);
+            }
+        }
+    }
+
+    fn resource(&self) -> Resource {
+        Resource::builder_empty()
+            .with_service_name(
+                self.config
+                    .service_name
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string()),
+            )
+            .build()
+    }
+
+    /// The root span followed by the stage spans recorded under it.
+    fn span_data(
+        &self,
+        end: SystemTime,
+        attributes: &[(&str, Value)],
+        error: Option<&str>,
+    ) -> Vec<SpanData> {
+        let scope = InstrumentationScope::builder("yetii")
+            .with_version(env!("CARGO_PKG_VERSION"))
+            .build();
+        let trace_id = TraceId::from(self.trace_id);
+        let root_id = SpanId::from(self.span_id);
+        let query = KeyValue::new("yetii.query", self.query.clone());
+        let span = |span_id: SpanId, parent_span_id: SpanId, name: String, start, end| SpanData {
+            span_context: SpanContext::new(
+                trace_id,
+                span_id,
+                TraceFlags::SAMPLED,
+                false,
+                TraceState::default(),
+            ),
+            parent_span_id,
+            parent_span_is_remote: false,
+            span_kind: SpanKind::Internal,
+            name: name.into(),
+            start_time: start,
+            end_time: end,
+            attributes: vec![query.clone()],
+            dropped_attributes_count: 0,
+            events: SpanEvents::default(),
+            links: SpanLinks::default(),
+            status: Status::Unset,
+            instrumentation_scope: scope.clone(),
+        };
+
+        let mut root = span(
+            root_id,
+            SpanId::INVALID,
+            format!("query {}", self.query),
+            self.start,
+            end,
+        );
+        root.attributes.extend(
+            attributes
+                .iter()
+                .map(|(key, value)| KeyValue::new(format!("yetii.{key}"), attribute(value))),
+        );
+        root.status = match error {
+            Some(message) => Status::error(message.to_string()),
+            None => Status::Ok,
+        };
+        let children = self
+            .spans
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        let mut spans = vec![root];
+        spans.extend(children.into_iter().map(|child| {
+            span(
+                SpanId::from(child.span_id),
+                root_id,
+                child.name.to_string(),
+                child.start,
+                child.end,
+            )
+        }));
+        spans
+    }
+}
+
+/// Runs `future` as a child span named `name` of the current task's trace, if any.
+pub async fn span<F: Future>(name: &'static str, future: F) -> F::Output {
+    let start = SystemTime::now();
+    let output = future.await;
+    let _ = CURRENT_TRACE.try_with(|spans| {
+        spans
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(Span {
+                span_id: rand::random::<u64>().max(1),
+                name,
+                start,
+                end: SystemTime::now(),
+            });
+    });
+    output
+}
+
+/// OTLP/HTTP collectors take traces at `/v1/traces`; a bare collector URL gets that path.
+fn traces_url(endpoint: &str) -> String {
+    match url::Url::parse(endpoint) {
+        Ok(url) if matches!(url.path(), "" | "/") => {
+            format!("{}/v1/traces", endpoint.trim_end_matches('/'))
+        }
+        _ => endpoint.to_string(),
+    }
+}
+
+fn attribute(value: &Value) -> opentelemetry::Value {
+    match value {
+        Value::Bool(value) => (*value).into(),
+        Value::Number(number) => match number.as_i64() {
+            Some(number) => number.into(),
+            None => number.as_f64().unwrap_or_default().into(),
+        },
+        Value::String(text) => text.clone().into(),
+        other => other.to_string().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn records_stage_spans_under_the_query_span() {
+        let monitoring: MonitoringConfig = serde_yaml::from_str(
+            "enabled: true\ntracing:\n  endpoint: http://127.0.0.1:4318\n  service_name: erp-sync\n",
+        )
+        .unwrap();
+        let trace = QueryTrace::start(Some(&monitoring), "orders").unwrap();
+        let rows = trace
+            .scope(async {
+                let rows = span("fetch", async { 3 }).await;
+                span("deliver", async { rows }).await
+            })
+            .await;
+        // Outside a trace, spans only run their future.
+        assert_eq!(7, span("fetch", async { 7 }).await);
+
+        let spans = trace.span_data(SystemTime::now(), &[("rows_read", Value::from(rows))], None);
+        assert_eq!(3, spans.len());
+        assert_eq!("query orders", spans[0].name);
+        assert_eq!(
+            KeyValue::new("yetii.rows_read", 3_i64),
+            spans[0].attributes[1]
+        );
+        assert_eq!(Status::Ok, spans[0].status);
+        assert_eq!("fetch", spans[1].name);
+        assert_eq!(spans[0].span_context.span_id(), spans[2].parent_span_id);
+        assert_eq!(
+            spans[0].span_context.trace_id(),
+            spans[2].span_context.trace_id()
+        );
+        assert_eq!(
+            Some(opentelemetry::Value::from("erp-sync")),
+            trace
+                .resource()
+                .get(&opentelemetry::Key::new("service.name"))
+        );
+        assert_eq!(
+            "http://127.0.0.1:4318/v1/traces",
+            traces_url(&monitoring.tracing.unwrap().endpoint)
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Email channel: a plain-text summary of a query outcome sent over SMTP.
+
+use super::template::render_text;
+use super::{NotificationEvent, event_fields};
+use crate::config::monitor_config::{EmailChannelConfig, SmtpTls};
+use anyhow::{Context, Result, anyhow};
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT_SECONDS: u32 = 30;
+const DEFAULT_SUBJECT: &str = "[yetii] query {{query}}: {{status}}";
+const DEFAULT_BODY: &str = "Query: {{query}}
+Status: {{status}}
+Rows read: {{rows_read}}
+Batches sent: {{batches_sent}}
+Duration: {{duration_ms}} ms
+Error: {{error}}
+Environment: {{environment}}
+Finished at: {{finished_at}}
+";
+
+pub(super) async fn send(email: &EmailChannelConfig, event: &NotificationEvent) -> Result<()> {
+    let message = message(email, event)?;
+    let host = email.smtp_host.as_str();
+    let mut transport = match email.tls {
+        SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+        SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+    };
+    if let Some(port) = email.smtp_port {
+        transport = transport.port(port);
+    }
+    if let (Some(username), Some(password)) = (&email.username, &email.password) {
+        transport = transport.credentials(Credentials::new(
+            username.clone(),
+            password.expose().clone(),
+        ));
+    }
+    let timeout = email.timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECONDS);
+    transport = transport.timeout(Some(Duration::from_secs(timeout.into())));
+    transport
+        .build()
+        .send(message)
+        .await
+        .with_context(|| format!("SMTP server '{host}' did not accept the email"))?;
+    Ok(())
+}
+
+fn message(email: &EmailChannelConfig, event: &NotificationEvent) -> Result<Message> {
+    let fields = event_fields(event);
+    let subject = render_text(email.subject.as_deref().unwrap_or(DEFAULT_SUBJECT), &fields)?;
+    let body = render_text(email.body.as_deref().unwrap_or(DEFAULT_BODY), &fields)?;
+    let sender = email
+        .sender()
+        .ok_or_else(|| anyhow!("email channel has no from address"))?;
+    let mut message = Message::builder()
+        .from(mailbox(sender)?)
+        .subject(subject.trim())
+        .header(ContentType::TEXT_PLAIN);
+    for recipient in &email.recipients {
+        message = message.to(mailbox(recipient)?);
+    }
+    message.body(body).context("failed to build the email")
+}
+
+fn mailbox(address: &str) -> Result<Mailbox> {
+    address
+        .parse()
+        .with_context(|| format!("'{address}' is not an email address"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn sends_the_templated_summary_over_smtp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut transcript = String::new();
+            writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+            let mut in_data = false;
+            while let Some(line) = lines.next_line().await.unwrap() {
+                transcript.push_str(&line);
+                transcript.push('\n');
+                let reply: &[u8] = if in_data {
+                    if line != "." {
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250 localhost\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line == "QUIT" {
+                    writer.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+            transcript
+        });
+        let email: EmailChannelConfig = serde_yaml::from_str(&format!(
+            "smtp_host: 127.0.0.1\nsmtp_port: {port}\ntls: none\nfrom: Yetii <yetii@example.test>\nrecipients: [ops@example.test]\nsubject: \"{{{{query}}}} {{{{status}}}} after {{{{rows_read}}}} rows\"\n"
+        ))
+        .unwrap();
+        let event = NotificationEvent::query_outcome(
+            "orders",
+            false,
+            Some("endpoint returned 500".to_string()),
+            42,
+            1,
+            0,
+            Duration::from_millis(1500),
+        );
+
+        send(&email, &event).await.unwrap();
+
+        let transcript = server.await.unwrap();
+        assert!(transcript.contains("MAIL FROM:<yetii@example.test>"));
+        assert!(transcript.contains("RCPT TO:<ops@example.test>"));
+        assert!(transcript.contains("Subject: orders failure after 42 rows"));
+        assert!(transcript.contains("Error: endpoint returned 500"));
+    }
+}
@@ -1,20 +1,61 @@
+mod email;
+mod slack;
 mod template;
 
-use crate::config::endpoint_config::{EndpointConfig, ResponseConfig};
+use crate::chaos::{self, Fault};
+use crate::config::endpoint_config::{DuplicatePolicy, EndpointConfig, HttpMethod, ResponseConfig};
 use crate::config::monitor_config::{
-    MonitoringConfig, NotificationChannel, NotificationEventKind, NotificationServiceConfig,
-    NotificationSettings,
+    MonitoringConfig, MonitoringFailurePolicy, NotificationChannel, NotificationEndpointConfig,
+    NotificationEventKind, NotificationFailureAction, NotificationRetryConfig,
+    NotificationServiceConfig, NotificationServiceType, NotificationSettings,
+    QueryNotificationConfig,
 };
 use crate::config::request_config::RequestConfig;
 use crate::http::HttpSender;
+use crate::http::circuit::Transition;
+use crate::monitoring;
 use anyhow::{Context, Result, anyhow};
 use chrono::{DateTime, Utc};
 use serde::Serialize;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
 use std::time::Duration;
 use template::render_template;
 
+/// How often undelivered notifications are sent again when `notification_retry_seconds` is
+/// not set.
+const DEFAULT_RETRY_SECONDS: u32 = 60;
+/// Undelivered notifications kept when `notification_queue_size` is not set.
+const DEFAULT_QUEUE_SIZE: usize = 100;
+/// Retries of a webhook channel delivery when its `retry.attempts` is not set.
+const WEBHOOK_RETRY_ATTEMPTS: u32 = 3;
+
+static RETRY_QUEUE: LazyLock<Mutex<RetryQueue>> =
+    LazyLock::new(|| Mutex::new(RetryQueue::default()));
+
+/// Service deliveries that failed and are sent again in the background.
+#[derive(Default)]
+struct RetryQueue {
+    pending: VecDeque<(NotificationServiceConfig, NotificationEvent)>,
+    capacity: usize,
+    retrying: bool,
+}
+
+impl RetryQueue {
+    /// Adds deliveries behind the older ones already queued and drops the oldest beyond the
+    /// capacity. Returns how many were dropped.
+    fn push(
+        &mut self,
+        deliveries: impl IntoIterator<Item = (NotificationServiceConfig, NotificationEvent)>,
+    ) -> usize {
+        self.pending.extend(deliveries);
+        let dropped = self.pending.len().saturating_sub(self.capacity);
+        self.pending.drain(..dropped);
+        dropped
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct NotificationEvent {
     pub event: NotificationEventKind,
@@ -29,6 +70,10 @@ pub struct NotificationEvent {
     pub duration_ms: u64,
     pub error: Option<String>,
     pub environment: Option<String>,
+    /// The run the event happened in; also on every log line of that run.
+    pub run_id: Option<String>,
+    /// URL of the endpoint a `circuit_opened` or `circuit_closed` event is about.
+    pub endpoint: Option<String>,
     pub occurred_at: DateTime<Utc>,
     pub started_at: Option<DateTime<Utc>>,
     pub finished_at: Option<DateTime<Utc>>,
@@ -64,6 +109,8 @@ impl NotificationEvent {
             duration_ms,
             error,
             environment: None,
+            run_id: monitoring::current_run_id(),
+            endpoint: None,
             occurred_at: Utc::now(),
             started_at: None,
             finished_at: Some(Utc::now()),
@@ -96,12 +143,101 @@ impl NotificationEvent {
             duration_ms,
             error: (!success).then(|| format!("{failures} query failure(s)")),
             environment: None,
+            run_id: monitoring::current_run_id(),
+            endpoint: None,
             occurred_at: Utc::now(),
             started_at: None,
             finished_at: Some(Utc::now()),
         }
     }
 
+    /// Outcome of a flow; the flow's name is in `query`.
+    pub fn flow_outcome(
+        flow: impl Into<String>,
+        success: bool,
+        error: Option<String>,
+        rows_read: usize,
+        pages_read: usize,
+        batches_sent: usize,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            event: if success {
+                NotificationEventKind::FlowSuccess
+            } else {
+                NotificationEventKind::FlowFailure
+            },
+            ..Self::query_outcome(
+                flow,
+                success,
+                error,
+                rows_read,
+                pages_read,
+                batches_sent,
+                duration,
+            )
+        }
+    }
+
+    pub fn circuit(url: &str, transition: Transition, cooldown: Duration) -> Self {
+        let (event, failures, error) = match transition {
+            Transition::Opened { failures } => (
+                NotificationEventKind::CircuitOpened,
+                failures as usize,
+                Some(format!(
+                    "{failures} consecutive batches to {url} failed; delivery is paused for {}s",
+                    cooldown.as_secs()
+                )),
+            ),
+            Transition::Closed => (NotificationEventKind::CircuitClosed, 0, None),
+        };
+        Self {
+            status: event.as_str().to_string(),
+            success: error.is_none(),
+            event,
+            query_name: String::new(),
+            query: String::new(),
+            rows_read: 0,
+            pages_read: 0,
+            batches_sent: 0,
+            failures,
+            duration_ms: 0,
+            error,
+            environment: None,
+            run_id: monitoring::current_run_id(),
+            endpoint: Some(url.to_string()),
+            occurred_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+        }
+    }
+
+    /// Delivery moved from `url` to `failover_url` after `error`.
+    pub fn failover(url: &str, failover_url: &str, error: &str) -> Self {
+        let event = NotificationEventKind::EndpointFailover;
+        Self {
+            status: event.as_str().to_string(),
+            event,
+            success: false,
+            query_name: String::new(),
+            query: String::new(),
+            rows_read: 0,
+            pages_read: 0,
+            batches_sent: 0,
+            failures: 1,
+            duration_ms: 0,
+            error: Some(format!(
+                "delivery to {url} failed after retries ({error}); batches now go to {failover_url}"
+            )),
+            environment: None,
+            run_id: monitoring::current_run_id(),
+            endpoint: Some(url.to_string()),
+            occurred_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+        }
+    }
+
     pub fn daemon_lifecycle(event: NotificationEventKind) -> Self {
         let success = matches!(
             event,
@@ -120,6 +256,8 @@ impl NotificationEvent {
             duration_ms: 0,
             error: None,
             environment: None,
+            run_id: monitoring::current_run_id(),
+            endpoint: None,
             occurred_at: Utc::now(),
             started_at: None,
             finished_at: None,
@@ -127,6 +265,35 @@ impl NotificationEvent {
     }
 }
 
+/// Reports a circuit breaker change for `url` to the configured notification services.
+pub async fn notify_circuit(url: &str, transition: Transition, cooldown: Duration) {
+    let monitoring_config = {
+        let Ok(config) = crate::config::get_config() else {
+            return;
+        };
+        config.monitoring.clone()
+    };
+    let event = NotificationEvent::circuit(url, transition, cooldown);
+    if let Err(error) = notify(monitoring_config.as_ref(), &event).await {
+        tracing::warn!(url, error = %error, "circuit breaker notification failed");
+    }
+}
+
+/// Reports that delivery moved from `url` to `failover_url` to the configured notification
+/// services.
+pub async fn notify_failover(url: &str, failover_url: &str, error: &str) {
+    let monitoring_config = {
+        let Ok(config) = crate::config::get_config() else {
+            return;
+        };
+        config.monitoring.clone()
+    };
+    let event = NotificationEvent::failover(url, failover_url, error);
+    if let Err(error) = notify(monitoring_config.as_ref(), &event).await {
+        tracing::warn!(url, error = %error, "endpoint failover notification failed");
+    }
+}
+
 pub async fn notify(config: Option<&MonitoringConfig>, event: &NotificationEvent) -> Result<()> {
     let Some(settings) = config
         .filter(|config| config.enabled)
@@ -136,74 +303,298 @@ pub async fn notify(config: Option<&MonitoringConfig>, event: &NotificationEvent
         return Ok(());
     };
 
+    deliver(
+        settings,
+        config.and_then(|config| config.failure_policy.as_ref()),
+        event,
+    )
+    .await
+}
+
+/// Like [`notify`], but with the query's `notifications` overrides applied.
+pub async fn notify_query(
+    config: Option<&MonitoringConfig>,
+    overrides: Option<&QueryNotificationConfig>,
+    event: &NotificationEvent,
+) -> Result<()> {
+    let Some(overrides) = overrides else {
+        return notify(config, event).await;
+    };
+    match settings_for_query(config, overrides) {
+        Some(settings) => {
+            let policy = config.and_then(|config| config.failure_policy.as_ref());
+            deliver(&settings, policy, event).await
+        }
+        None => Ok(()),
+    }
+}
+
+/// Effective settings for a query's events. `monitoring.enabled: false` still disables everything.
+fn settings_for_query(
+    config: Option<&MonitoringConfig>,
+    overrides: &QueryNotificationConfig,
+) -> Option<NotificationSettings> {
+    if !overrides.enabled || config.is_some_and(|config| !config.enabled) {
+        return None;
+    }
+    let global = config.and_then(|config| config.notifications.as_ref());
+    let mut settings = global.cloned().unwrap_or(NotificationSettings {
+        enabled: true,
+        on_failure: false,
+        on_success: false,
+        channels: Vec::new(),
+        services: Vec::new(),
+    });
+    settings.enabled = true;
+    settings.on_success = overrides.on_success.unwrap_or(settings.on_success);
+    settings.on_failure = overrides.on_failure.unwrap_or(settings.on_failure);
+    if let Some(channels) = &overrides.channels {
+        settings.channels = channels.clone();
+    }
+    if let Some(names) = &overrides.services {
+        settings
+            .services
+            .retain(|service| names.contains(&service.name));
+        for service in &mut settings.services {
+            service.events.retain(|event| {
+                !matches!(
+                    event,
+                    NotificationEventKind::QuerySuccess | NotificationEventKind::QueryFailure
+                )
+            });
+            if settings.on_success {
+                service.events.push(NotificationEventKind::QuerySuccess);
+            }
+            if settings.on_failure {
+                service.events.push(NotificationEventKind::QueryFailure);
+            }
+        }
+    }
+    Some(settings)
+}
+
+async fn deliver(
+    settings: &NotificationSettings,
+    policy: Option<&MonitoringFailurePolicy>,
+    event: &NotificationEvent,
+) -> Result<()> {
+    let policy = policy.cloned().unwrap_or_default();
     let mut errors = Vec::new();
-    deliver_legacy_channels(settings, event, &mut errors).await;
-    deliver_services(settings, event, &mut errors).await;
+    let mut delivered = 0;
+    let mut failed = deliver_legacy_channels(settings, event, &mut delivered, &mut errors).await;
+    failed.extend(deliver_services(settings, event, &mut delivered, &mut errors).await);
 
+    if !failed.is_empty() && policy.notifications == NotificationFailureAction::Retry {
+        queue_retries(failed, &policy);
+    }
     if errors.is_empty() {
+        let retrying = lock_retry_queue().retrying;
+        if delivered > 0 && !retrying {
+            monitoring::subsystem_up("notifications");
+        }
         Ok(())
     } else {
-        Err(anyhow!(errors.join("; ")))
+        let error = errors.join("; ");
+        monitoring::subsystem_failed("notifications", &error);
+        Err(anyhow!(error))
+    }
+}
+
+/// Queues failed service deliveries and starts the background retries when they are not
+/// running yet.
+fn queue_retries(
+    failed: Vec<(NotificationServiceConfig, NotificationEvent)>,
+    policy: &MonitoringFailurePolicy,
+) {
+    let mut queue = lock_retry_queue();
+    queue.capacity = policy.notification_queue_size.unwrap_or(DEFAULT_QUEUE_SIZE);
+    let dropped = queue.push(failed);
+    if dropped > 0 {
+        tracing::warn!(
+            dropped,
+            "notification retry queue is full; dropped the oldest notifications"
+        );
+    }
+    if !queue.retrying {
+        queue.retrying = true;
+        let interval = Duration::from_secs(
+            policy
+                .notification_retry_seconds
+                .unwrap_or(DEFAULT_RETRY_SECONDS)
+                .into(),
+        );
+        tokio::spawn(retry_queued(interval));
+    }
+}
+
+/// Sends the queued notifications again every `interval` until the queue is empty.
+async fn retry_queued(interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let pending = std::mem::take(&mut lock_retry_queue().pending);
+        let count = pending.len();
+        let mut failed = Vec::new();
+        let mut last_error = None;
+        for (service, event) in pending {
+            match deliver_service(&service, &event).await {
+                Ok(()) => {}
+                Err(error) => {
+                    last_error = Some(format!(
+                        "notification service '{}' failed: {error:#}",
+                        service.name
+                    ));
+                    failed.push((service, event));
+                }
+            }
+        }
+        tracing::info!(
+            delivered = count - failed.len(),
+            failed = failed.len(),
+            "retried undelivered notifications"
+        );
+
+        let mut queue = lock_retry_queue();
+        // Notifications queued while this round ran are newer than the ones that failed again.
+        let newer = std::mem::take(&mut queue.pending);
+        queue.push(failed);
+        queue.push(newer);
+        match last_error {
+            None if queue.pending.is_empty() => {
+                queue.retrying = false;
+                monitoring::subsystem_up("notifications");
+                return;
+            }
+            _ => monitoring::subsystem_failed(
+                "notifications",
+                &format!(
+                    "{} notification(s) waiting for retry; {}",
+                    queue.pending.len(),
+                    last_error.as_deref().unwrap_or("queued after a failure")
+                ),
+            ),
+        }
     }
 }
 
+fn lock_retry_queue() -> std::sync::MutexGuard<'static, RetryQueue> {
+    RETRY_QUEUE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Returns the webhook deliveries that failed; emails and Slack messages are not queued for
+/// retry.
 async fn deliver_legacy_channels(
     settings: &NotificationSettings,
     event: &NotificationEvent,
+    delivered: &mut usize,
     errors: &mut Vec<String>,
-) {
+) -> Vec<(NotificationServiceConfig, NotificationEvent)> {
+    let mut failed = Vec::new();
     if !matches!(
         event.event,
         NotificationEventKind::QuerySuccess | NotificationEventKind::QueryFailure
     ) {
-        return;
+        return failed;
     }
     if (event.success && !settings.on_success) || (!event.success && !settings.on_failure) {
-        return;
+        return failed;
     }
 
-    let client = reqwest::Client::new();
     for channel in &settings.channels {
         match channel {
-            NotificationChannel::Webhook { url } => match client.post(url).json(event).send().await
-            {
-                Ok(response) if response.status().is_success() => {}
-                Ok(response) => errors.push(format!(
-                    "notification webhook '{url}' returned {}",
-                    response.status()
+            NotificationChannel::Webhook { url, retry } => {
+                let service = webhook_service(url, retry.as_ref());
+                match deliver_service(&service, event).await {
+                    Ok(()) => *delivered += 1,
+                    Err(error) => {
+                        errors.push(format!("notification webhook '{url}' failed: {error:#}"));
+                        failed.push((service, event.clone()));
+                    }
+                }
+            }
+            NotificationChannel::Email(email) => match email::send(email, event).await {
+                Ok(()) => *delivered += 1,
+                Err(error) => errors.push(format!(
+                    "notification email to {} failed: {error:#}",
+                    email.recipients.join(", ")
                 )),
-                Err(error) => errors.push(format!("notification webhook '{url}' failed: {error}")),
             },
-            NotificationChannel::Email { .. } => errors.push(
-                "email notifications require SMTP authentication/TLS settings and are not implemented"
-                    .to_string(),
-            ),
+            NotificationChannel::Slack(slack_channel) => {
+                match slack::send(slack_channel, event).await {
+                    Ok(()) => *delivered += 1,
+                    Err(error) => errors.push(format!("notification to Slack failed: {error:#}")),
+                }
+            }
         }
     }
+    failed
 }
 
+/// A webhook channel as a service for query events, so it is sent, retried, and queued for
+/// later like one.
+fn webhook_service(
+    url: &str,
+    retry: Option<&NotificationRetryConfig>,
+) -> NotificationServiceConfig {
+    let mut retry = retry.cloned().unwrap_or_default();
+    retry.attempts = retry.attempts.or(Some(WEBHOOK_RETRY_ATTEMPTS));
+    NotificationServiceConfig {
+        name: format!("webhook {url}"),
+        service_type: NotificationServiceType::Http,
+        enabled: true,
+        events: vec![
+            NotificationEventKind::QuerySuccess,
+            NotificationEventKind::QueryFailure,
+        ],
+        endpoint: NotificationEndpointConfig {
+            url: url.to_string(),
+            method: HttpMethod::Post,
+        },
+        auth: None,
+        headers: None,
+        payload: None,
+        response: None,
+        retry: Some(retry),
+    }
+}
+
+/// Returns the deliveries that failed.
 async fn deliver_services(
     settings: &NotificationSettings,
     event: &NotificationEvent,
+    delivered: &mut usize,
     errors: &mut Vec<String>,
-) {
+) -> Vec<(NotificationServiceConfig, NotificationEvent)> {
+    let mut failed = Vec::new();
     for service in &settings.services {
         if !service.enabled || !service.events.contains(&event.event) {
             continue;
         }
-        if let Err(error) = deliver_service(service, event).await {
-            errors.push(format!(
-                "notification service '{}' failed: {error:#}",
-                service.name
-            ));
+        match deliver_service(service, event).await {
+            Ok(()) => *delivered += 1,
+            Err(error) => {
+                errors.push(format!(
+                    "notification service '{}' failed: {error:#}",
+                    service.name
+                ));
+                failed.push((service.clone(), event.clone()));
+            }
         }
     }
+    failed
 }
 
 async fn deliver_service(
     service: &NotificationServiceConfig,
     event: &NotificationEvent,
 ) -> Result<()> {
+    if chaos::inject(Fault::Notification) {
+        return Err(anyhow!(
+            "chaos fault injected: {}",
+            Fault::Notification.as_str()
+        ));
+    }
     let payload = render_payload(service, event)
         .with_context(|| format!("failed to render payload for '{}'", service.name))?;
     let endpoint = service_endpoint(service);
@@ -250,6 +641,20 @@ fn event_fields(event: &NotificationEvent) -> HashMap<&'static str, Value> {
             .as_ref()
             .map_or(Value::Null, |environment| Value::from(environment.clone())),
     );
+    fields.insert(
+        "run_id",
+        event
+            .run_id
+            .as_ref()
+            .map_or(Value::Null, |run_id| Value::from(run_id.clone())),
+    );
+    fields.insert(
+        "endpoint",
+        event
+            .endpoint
+            .as_ref()
+            .map_or(Value::Null, |endpoint| Value::from(endpoint.clone())),
+    );
     fields.insert("occurred_at", Value::from(event.occurred_at.to_rfc3339()));
     fields.insert(
         "started_at",
@@ -269,7 +674,8 @@ fn event_fields(event: &NotificationEvent) -> HashMap<&'static str, Value> {
 fn service_endpoint(service: &NotificationServiceConfig) -> EndpointConfig {
     EndpointConfig {
         url: service.endpoint.url.clone(),
-        method: service.endpoint.method.clone(),
+        failover_url: Vec::new(),
+        method: service.endpoint.method,
         auth: service.auth.clone(),
         headers: service.headers.clone(),
         request: RequestConfig {
@@ -279,10 +685,7 @@ fn service_endpoint(service: &NotificationServiceConfig) -> EndpointConfig {
                 .and_then(|retry| retry.timeout_seconds),
             retry_attempts: service.retry.as_ref().and_then(|retry| retry.attempts),
             retry_delay_seconds: service.retry.as_ref().and_then(|retry| retry.delay_seconds),
-            retry_backoff: service
-                .retry
-                .as_ref()
-                .and_then(|retry| retry.backoff.clone()),
+            retry_backoff: service.retry.as_ref().and_then(|retry| retry.backoff),
             ..RequestConfig::default()
         },
         response: Some(ResponseConfig {
@@ -292,7 +695,9 @@ fn service_endpoint(service: &NotificationServiceConfig) -> EndpointConfig {
                 .map(|response| response.success_codes.clone())
                 .filter(|codes| !codes.is_empty())
                 .unwrap_or_else(|| vec![200, 201, 202, 204]),
-            handle_duplicates: "skip".to_string(),
+            handle_duplicates: DuplicatePolicy::Error,
+            duplicate_codes: Vec::new(),
+            update_method: None,
         }),
     }
 }
@@ -302,9 +707,9 @@ mod tests {
     use super::*;
     use crate::config::endpoint_config::EndpointAuth;
     use crate::config::monitor_config::{
-        NotificationEndpointConfig, NotificationPayloadConfig, NotificationResponseConfig,
-        NotificationRetryConfig, NotificationServiceType,
+        NotificationPayloadConfig, NotificationPayloadFormat, NotificationResponseConfig,
     };
+    use crate::config::request_config::RetryBackoff;
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio::net::TcpListener;
 
@@ -338,10 +743,10 @@ mod tests {
                     events: vec![NotificationEventKind::QueryFailure],
                     endpoint: NotificationEndpointConfig {
                         url: format!("http://{address}/events"),
-                        method: "POST".to_string(),
+                        method: HttpMethod::Post,
                     },
                     auth: Some(EndpointAuth::Bearer {
-                        token: "token".to_string(),
+                        token: "token".into(),
                         header_name: None,
                     }),
                     headers: Some(HashMap::from([(
@@ -349,7 +754,7 @@ mod tests {
                         "yetii".to_string(),
                     )])),
                     payload: Some(NotificationPayloadConfig {
-                        format: "json".to_string(),
+                        format: NotificationPayloadFormat::Json,
                         template: serde_json::json!({
                             "event": "{{event}}",
                             "query": "{{query_name}}",
@@ -363,11 +768,13 @@ mod tests {
                     retry: Some(NotificationRetryConfig {
                         attempts: Some(0),
                         delay_seconds: Some(0),
-                        backoff: Some("fixed".to_string()),
+                        backoff: Some(RetryBackoff::Fixed),
                         timeout_seconds: Some(5),
                     }),
                 }],
             }),
+            failure_policy: None,
+            tracing: None,
         };
         let event = NotificationEvent::query_outcome(
             "orders",
@@ -415,9 +822,12 @@ mod tests {
                 on_success: false,
                 channels: vec![NotificationChannel::Webhook {
                     url: format!("http://{address}/alert"),
+                    retry: None,
                 }],
                 services: vec![],
             }),
+            failure_policy: None,
+            tracing: None,
         };
         let event = NotificationEvent::query_outcome(
             "orders",
@@ -438,6 +848,145 @@ mod tests {
         assert!(request.contains("\"event\":\"query_failure\""));
     }
 
+    #[tokio::test]
+    async fn webhook_channel_retries_a_failed_delivery() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in [
+                "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            ] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                requests.push(String::from_utf8(read_request(&mut stream).await).unwrap());
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+        let settings: NotificationSettings = serde_yaml::from_str(&format!(
+            "on_success: true\nchannels:\n  - type: webhook\n    url: http://{address}/hook\n    retry:\n      delay_seconds: 0\n"
+        ))
+        .unwrap();
+        let event = NotificationEvent::query_outcome(
+            "orders",
+            true,
+            None,
+            12,
+            1,
+            1,
+            Duration::from_millis(250),
+        );
+
+        deliver(&settings, None, &event).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert_eq!(2, requests.len());
+        for field in [
+            r#""query":"orders""#,
+            r#""status":"success""#,
+            r#""rows_read":12"#,
+            r#""duration_ms":250"#,
+            r#""error":null"#,
+        ] {
+            assert!(requests[1].contains(field), "{field} missing");
+        }
+    }
+
+    #[test]
+    fn retry_queue_drops_the_oldest_notifications_when_full() {
+        let service: NotificationServiceConfig = serde_yaml::from_str(
+            "name: ops\ntype: http\nevents: [query_failure]\nendpoint:\n  url: https://example.test/events\n",
+        )
+        .unwrap();
+        let event = |query: &str| {
+            NotificationEvent::query_outcome(query, false, None, 0, 0, 0, Duration::ZERO)
+        };
+        let mut queue = RetryQueue {
+            capacity: 2,
+            ..RetryQueue::default()
+        };
+
+        assert_eq!(0, queue.push([(service.clone(), event("orders"))]));
+        assert_eq!(
+            1,
+            queue.push([
+                (service.clone(), event("customers")),
+                (service, event("invoices"))
+            ])
+        );
+        let queries = queue
+            .pending
+            .iter()
+            .map(|(_, event)| event.query.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["customers", "invoices"], queries);
+    }
+
+    #[test]
+    fn query_overrides_select_services_channels_and_outcomes() {
+        let service = |name: &str, events| NotificationServiceConfig {
+            name: name.to_string(),
+            service_type: NotificationServiceType::Http,
+            enabled: true,
+            events,
+            endpoint: NotificationEndpointConfig {
+                url: format!("https://{name}.example.test/events"),
+                method: HttpMethod::Post,
+            },
+            auth: None,
+            headers: None,
+            payload: None,
+            response: None,
+            retry: None,
+        };
+        let config = MonitoringConfig {
+            enabled: true,
+            metrics: None,
+            health_check: None,
+            notifications: Some(NotificationSettings {
+                enabled: true,
+                on_failure: true,
+                on_success: true,
+                channels: vec![NotificationChannel::Webhook {
+                    url: "https://chat.example.test/hook".to_string(),
+                    retry: None,
+                }],
+                services: vec![
+                    service("on_call", vec![NotificationEventKind::RunFailure]),
+                    service("audit", vec![NotificationEventKind::QuerySuccess]),
+                ],
+            }),
+            failure_policy: None,
+            tracing: None,
+        };
+        let payroll = QueryNotificationConfig {
+            enabled: true,
+            on_success: Some(false),
+            on_failure: None,
+            channels: Some(vec![]),
+            services: Some(vec!["on_call".to_string()]),
+        };
+
+        let settings = settings_for_query(Some(&config), &payroll).unwrap();
+
+        assert!(!settings.on_success && settings.on_failure);
+        assert!(settings.channels.is_empty());
+        assert_eq!(1, settings.services.len());
+        assert_eq!(
+            vec![
+                NotificationEventKind::RunFailure,
+                NotificationEventKind::QueryFailure
+            ],
+            settings.services[0].events
+        );
+        let quiet = QueryNotificationConfig {
+            enabled: false,
+            ..payroll
+        };
+        assert!(settings_for_query(Some(&config), &quiet).is_none());
+    }
+
     async fn read_request(stream: &mut tokio::net::TcpStream) -> Vec<u8> {
         let mut bytes = Vec::new();
         let mut buffer = [0_u8; 2048];
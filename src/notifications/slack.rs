@@ -0,0 +1,168 @@
+//! Slack channel: a Block Kit message about a query outcome, posted to an incoming webhook or
+//! with a bot token through `chat.postMessage`.
+
+use super::template::{render_template, render_text};
+use super::{NotificationEvent, event_fields};
+use crate::config::monitor_config::SlackChannelConfig;
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const POST_MESSAGE_URL: &str = "https://slack.com/api/chat.postMessage";
+const DEFAULT_TIMEOUT_SECONDS: u32 = 10;
+
+pub(super) async fn send(slack: &SlackChannelConfig, event: &NotificationEvent) -> Result<()> {
+    let message = message(slack, event)?;
+    let timeout = slack.timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECONDS);
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(timeout.into()))
+        .build()?;
+    let request = match (&slack.webhook_url, &slack.token) {
+        (Some(url), _) => client.post(url.expose()),
+        (None, Some(token)) => client.post(POST_MESSAGE_URL).bearer_auth(token.expose()),
+        (None, None) => bail!("Slack channel has neither webhook_url nor token"),
+    };
+    let response = request
+        .json(&message)
+        .send()
+        .await
+        // The webhook URL is the credential, so it is kept out of the error.
+        .map_err(reqwest::Error::without_url)
+        .context("Slack request failed")?;
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        bail!("Slack returned {status}: {}", body.trim());
+    }
+    // The Web API answers 200 with `"ok": false` when it refuses a message.
+    if slack.token.is_some() && slack.webhook_url.is_none() {
+        let reply: Value = serde_json::from_str(&body).context("Slack returned invalid JSON")?;
+        if reply["ok"] != Value::Bool(true) {
+            return Err(anyhow!(
+                "Slack refused the message: {}",
+                reply["error"].as_str().unwrap_or("unknown error")
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn message(slack: &SlackChannelConfig, event: &NotificationEvent) -> Result<Value> {
+    let fields = event_fields(event);
+    let title = match &slack.title {
+        Some(title) => render_text(title, &fields)?,
+        None => format!(
+            "{} {} {}",
+            if event.success {
+                ":white_check_mark:"
+            } else {
+                ":x:"
+            },
+            event.query,
+            event.status
+        ),
+    };
+    let blocks = match &slack.blocks {
+        Some(blocks) => render_template(blocks, &fields)?,
+        None => default_blocks(slack, &title, event, &fields)?,
+    };
+    let mut message = json!({"text": title, "blocks": blocks});
+    if let Some(channel) = &slack.channel {
+        message["channel"] = Value::from(channel.clone());
+    }
+    Ok(message)
+}
+
+/// A header, the run's figures or the `text` template, the error in a code block, and the
+/// environment and finish time as context.
+fn default_blocks(
+    slack: &SlackChannelConfig,
+    title: &str,
+    event: &NotificationEvent,
+    fields: &HashMap<&'static str, Value>,
+) -> Result<Value> {
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": {"type": "plain_text", "text": title, "emoji": true},
+    })];
+    match &slack.text {
+        Some(text) => blocks.push(json!({
+            "type": "section",
+            "text": {"type": "mrkdwn", "text": render_text(text, fields)?},
+        })),
+        None => blocks.push(json!({
+            "type": "section",
+            "fields": [
+                mrkdwn(format!("*Rows read*\n{}", event.rows_read)),
+                mrkdwn(format!("*Batches sent*\n{}", event.batches_sent)),
+                mrkdwn(format!("*Duration*\n{} ms", event.duration_ms)),
+                mrkdwn(format!("*Failures*\n{}", event.failures)),
+            ],
+        })),
+    }
+    if let Some(error) = &event.error {
+        blocks.push(json!({
+            "type": "section",
+            "text": {"type": "mrkdwn", "text": format!("```{}```", error.replace("```", "'''"))},
+        }));
+    }
+    let mut context = Vec::new();
+    if let Some(environment) = &event.environment {
+        context.push(mrkdwn(format!("Environment: *{environment}*")));
+    }
+    let finished_at = event.finished_at.unwrap_or(event.occurred_at);
+    context.push(mrkdwn(format!(
+        "Finished <!date^{}^{{date_short_pretty}} {{time_secs}}|{}>",
+        finished_at.timestamp(),
+        finished_at.to_rfc3339()
+    )));
+    blocks.push(json!({"type": "context", "elements": context}));
+    Ok(Value::Array(blocks))
+}
+
+fn mrkdwn(text: String) -> Value {
+    json!({"type": "mrkdwn", "text": text})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_default_and_templated_messages() {
+        let event = NotificationEvent::query_outcome(
+            "orders",
+            false,
+            Some("endpoint returned 500".to_string()),
+            42,
+            1,
+            0,
+            Duration::from_millis(1500),
+        );
+        let slack: SlackChannelConfig =
+            serde_yaml::from_str("token: xoxb-test\nchannel: \"#data-alerts\"\n").unwrap();
+
+        let default = message(&slack, &event).unwrap();
+        assert_eq!("#data-alerts", default["channel"]);
+        assert_eq!(":x: orders failure", default["text"]);
+        assert_eq!("header", default["blocks"][0]["type"]);
+        assert_eq!("*Rows read*\n42", default["blocks"][1]["fields"][0]["text"]);
+        assert_eq!(
+            "```endpoint returned 500```",
+            default["blocks"][2]["text"]["text"]
+        );
+
+        let slack: SlackChannelConfig = serde_yaml::from_str(
+            "webhook_url: https://hooks.slack.test/T/B/x\ntitle: \"{{query}} is {{status}}\"\nblocks:\n  - type: section\n    text:\n      type: mrkdwn\n      text: \"*{{query}}* read {{rows_read}} rows\"\n",
+        )
+        .unwrap();
+        let templated = message(&slack, &event).unwrap();
+        assert_eq!("orders is failure", templated["text"]);
+        assert_eq!(
+            json!([{"type": "section", "text": {"type": "mrkdwn", "text": "*orders* read 42 rows"}}]),
+            templated["blocks"]
+        );
+        assert!(templated.get("channel").is_none());
+    }
+}
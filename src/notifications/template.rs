@@ -18,6 +18,11 @@ pub fn render_template(template: &Value, fields: &HashMap<&'static str, Value>)
     }
 }
 
+/// Renders `template` as text; placeholders of any type are written as strings.
+pub fn render_text(template: &str, fields: &HashMap<&'static str, Value>) -> Result<String> {
+    render_string(template, fields).map(|value| value_as_string(&value))
+}
+
 fn render_string(value: &str, fields: &HashMap<&'static str, Value>) -> Result<Value> {
     if let Some(name) = exact_placeholder(value) {
         return fields
@@ -48,7 +53,7 @@ fn render_string(value: &str, fields: &HashMap<&'static str, Value>) -> Result<V
 fn exact_placeholder(value: &str) -> Option<&str> {
     let trimmed = value.trim();
     let inner = trimmed.strip_prefix("{{")?.strip_suffix("}}")?.trim();
-    (!inner.is_empty()).then_some(inner)
+    (!inner.is_empty() && !inner.contains("{{") && !inner.contains("}}")).then_some(inner)
 }
 
 fn value_as_string(value: &Value) -> String {
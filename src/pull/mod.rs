@@ -0,0 +1,318 @@
+use crate::config::source_config::{HttpSourceConfig, PaginationConfig};
+use crate::http::{FetchedPage, HttpError, HttpSender};
+use serde_json::{Map, Value};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PullError {
+    #[error(transparent)]
+    Http(#[from] HttpError),
+    #[error("invalid source URL '{url}': {reason}")]
+    InvalidUrl { url: String, reason: String },
+    #[error("response has no array at records_path '{0}'")]
+    MissingRecords(String),
+    #[error("record {index} of the response is not a JSON object")]
+    InvalidRecord { index: usize },
+}
+
+/// Walks the pages of a pull query's source, one request per call to [`Pager::next_page`].
+pub struct Pager<'a> {
+    source: &'a HttpSourceConfig,
+    next: Option<NextPage>,
+    pages: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NextPage {
+    First,
+    Number(u32),
+    Cursor(String),
+    Link(String),
+}
+
+impl<'a> Pager<'a> {
+    pub fn new(source: &'a HttpSourceConfig) -> Self {
+        Self {
+            source,
+            next: Some(NextPage::First),
+            pages: 0,
+        }
+    }
+
+    pub fn pages(&self) -> u32 {
+        self.pages
+    }
+
+    /// Fetches the next page; `None` once the source is exhausted or `max_pages` is reached.
+    pub async fn next_page(
+        &mut self,
+        sender: &HttpSender,
+    ) -> Result<Option<Vec<Map<String, Value>>>, PullError> {
+        let Some(next) = self.next.take() else {
+            return Ok(None);
+        };
+        if self
+            .source
+            .max_pages
+            .is_some_and(|max_pages| self.pages >= max_pages)
+        {
+            tracing::warn!(
+                max_pages = self.pages,
+                "pull source still has pages; stopping at max_pages"
+            );
+            return Ok(None);
+        }
+
+        let url = page_url(self.source, &next)?;
+        tracing::debug!(page = self.pages + 1, "fetching pull source page");
+        let page = sender.fetch(&self.source.endpoint, &url).await?;
+        let records = extract_records(&page.body, self.source.records_path.as_deref())?;
+        self.pages += 1;
+        self.next = following_page(self.source.pagination.as_ref(), &next, &page, records.len());
+        Ok(Some(records))
+    }
+}
+
+/// Builds the request URL for a page from the endpoint URL, static params, and paging params.
+fn page_url(source: &HttpSourceConfig, next: &NextPage) -> Result<String, PullError> {
+    let url = match next {
+        NextPage::Link(link) => return Ok(link.clone()),
+        _ => &source.endpoint.url,
+    };
+    let mut parsed = url::Url::parse(url).map_err(|error| PullError::InvalidUrl {
+        url: url.clone(),
+        reason: error.to_string(),
+    })?;
+
+    let mut params = source
+        .params
+        .iter()
+        .flatten()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect::<Vec<_>>();
+    params.sort();
+    let (size_param, page_size) = match &source.pagination {
+        None => (None, None),
+        Some(PaginationConfig::Page {
+            page_param,
+            start_page,
+            size_param,
+            page_size,
+        }) => {
+            let number = match next {
+                NextPage::Number(number) => *number,
+                _ => *start_page,
+            };
+            params.push((page_param.clone(), number.to_string()));
+            (size_param.as_ref(), *page_size)
+        }
+        Some(PaginationConfig::Cursor {
+            cursor_param,
+            size_param,
+            page_size,
+            ..
+        }) => {
+            if let NextPage::Cursor(cursor) = next {
+                params.push((cursor_param.clone(), cursor.clone()));
+            }
+            (size_param.as_ref(), *page_size)
+        }
+        Some(PaginationConfig::LinkHeader {
+            size_param,
+            page_size,
+        }) => (size_param.as_ref(), *page_size),
+    };
+    if let (Some(size_param), Some(page_size)) = (size_param, page_size) {
+        params.push((size_param.to_string(), page_size.to_string()));
+    }
+
+    if !params.is_empty() {
+        parsed.query_pairs_mut().extend_pairs(params);
+    }
+    Ok(parsed.into())
+}
+
+fn following_page(
+    pagination: Option<&PaginationConfig>,
+    current: &NextPage,
+    page: &FetchedPage,
+    records: usize,
+) -> Option<NextPage> {
+    if records == 0 {
+        return None;
+    }
+    match pagination? {
+        PaginationConfig::Page {
+            start_page,
+            page_size,
+            ..
+        } => {
+            if page_size.is_some_and(|page_size| records < page_size as usize) {
+                return None;
+            }
+            let number = match current {
+                NextPage::Number(number) => *number,
+                _ => *start_page,
+            };
+            Some(NextPage::Number(number + 1))
+        }
+        PaginationConfig::Cursor { cursor_path, .. } => {
+            match value_at_path(&page.body, cursor_path)? {
+                Value::String(cursor) if !cursor.is_empty() => {
+                    Some(NextPage::Cursor(cursor.clone()))
+                }
+                Value::Number(cursor) => Some(NextPage::Cursor(cursor.to_string())),
+                _ => None,
+            }
+        }
+        PaginationConfig::LinkHeader { .. } => page.next_link.clone().map(NextPage::Link),
+    }
+}
+
+fn extract_records(
+    body: &Value,
+    records_path: Option<&str>,
+) -> Result<Vec<Map<String, Value>>, PullError> {
+    let records = match records_path {
+        Some(path) => value_at_path(body, path),
+        None => Some(body),
+    };
+    let Some(Value::Array(records)) = records else {
+        return Err(PullError::MissingRecords(
+            records_path.unwrap_or(".").to_string(),
+        ));
+    };
+    records
+        .iter()
+        .enumerate()
+        .map(|(index, record)| match record {
+            Value::Object(record) => Ok(record.clone()),
+            _ => Err(PullError::InvalidRecord { index }),
+        })
+        .collect()
+}
+
+/// Follows a dot path such as `meta.next_cursor` through nested objects.
+fn value_at_path<'v>(value: &'v Value, path: &str) -> Option<&'v Value> {
+    path.split('.')
+        .try_fold(value, |value, segment| value.get(segment.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::endpoint_config::{EndpointConfig, HttpMethod};
+    use crate::config::request_config::RequestConfig;
+    use std::collections::HashMap;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn source(url: String, pagination: PaginationConfig) -> HttpSourceConfig {
+        HttpSourceConfig {
+            endpoint: EndpointConfig {
+                url,
+                failover_url: Vec::new(),
+                method: HttpMethod::Get,
+                auth: None,
+                headers: None,
+                request: RequestConfig::default(),
+                response: None,
+            },
+            params: Some(HashMap::from([(
+                "status".to_string(),
+                "active".to_string(),
+            )])),
+            records_path: Some("data.items".to_string()),
+            pagination: Some(pagination),
+            max_pages: None,
+            batch_size: None,
+        }
+    }
+
+    #[test]
+    fn builds_page_urls_and_stops_on_short_pages() {
+        let source = source(
+            "https://api.example.com/v1/items?region=eu".to_string(),
+            PaginationConfig::Page {
+                page_param: "page".to_string(),
+                start_page: 1,
+                size_param: Some("per_page".to_string()),
+                page_size: Some(2),
+            },
+        );
+        let page = FetchedPage {
+            body: Value::Null,
+            next_link: None,
+        };
+
+        assert_eq!(
+            "https://api.example.com/v1/items?region=eu&status=active&page=1&per_page=2",
+            page_url(&source, &NextPage::First).unwrap()
+        );
+        let second = following_page(source.pagination.as_ref(), &NextPage::First, &page, 2);
+        assert_eq!(Some(NextPage::Number(2)), second);
+        assert_eq!(
+            "https://api.example.com/v1/items?region=eu&status=active&page=2&per_page=2",
+            page_url(&source, &second.unwrap()).unwrap()
+        );
+        assert_eq!(
+            None,
+            following_page(source.pagination.as_ref(), &NextPage::Number(2), &page, 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn follows_cursors_until_the_source_is_exhausted() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut targets = Vec::new();
+            for body in [
+                r#"{"data":{"items":[{"id":1},{"id":2}]},"meta":{"next":"abc"}}"#,
+                r#"{"data":{"items":[{"id":3}]},"meta":{"next":null}}"#,
+            ] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0_u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let request = String::from_utf8(request).unwrap();
+                targets.push(request.split_whitespace().nth(1).unwrap().to_string());
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            targets
+        });
+
+        let source = source(
+            format!("http://{address}/items"),
+            PaginationConfig::Cursor {
+                cursor_param: "after".to_string(),
+                cursor_path: "meta.next".to_string(),
+                size_param: None,
+                page_size: None,
+            },
+        );
+        let sender = HttpSender::new(&source.endpoint.request).unwrap();
+        let mut pager = Pager::new(&source);
+        let mut ids = Vec::new();
+        while let Some(records) = pager.next_page(&sender).await.unwrap() {
+            ids.extend(records.into_iter().map(|record| record["id"].clone()));
+        }
+
+        assert_eq!(vec![1, 2, 3], ids);
+        assert_eq!(2, pager.pages());
+        assert_eq!(
+            vec!["/items?status=active", "/items?status=active&after=abc"],
+            server.await.unwrap()
+        );
+        assert!(matches!(
+            extract_records(&serde_json::json!({"data": [1]}), Some("data")),
+            Err(PullError::InvalidRecord { index: 0 })
+        ));
+    }
+}
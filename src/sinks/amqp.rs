@@ -0,0 +1,335 @@
+use crate::config::destination_config::AmqpSinkConfig;
+use crate::http::retry::RetryPolicy;
+use crate::sinks::redis::{MissingKeyField, render_key};
+use crate::sinks::tls::TlsConfigError;
+use crate::sinks::with_retries;
+use lapin::options::{BasicPublishOptions, ConfirmSelectOptions};
+use lapin::tcp::{OwnedIdentity, OwnedTLSConfig};
+use lapin::types::{AMQPValue, FieldTable};
+use lapin::uri::{AMQPScheme, AMQPUri};
+use lapin::{BasicProperties, Channel, Confirmation, Connection, ConnectionProperties, ErrorKind};
+use serde_json::{Map, Value};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AmqpError {
+    #[error("invalid AMQP URL: {0}")]
+    InvalidUrl(String),
+    #[error(transparent)]
+    Tls(#[from] TlsConfigError),
+    #[error(transparent)]
+    RoutingKey(#[from] MissingKeyField),
+    #[error("AMQP {operation} failed: {source}")]
+    Client {
+        operation: &'static str,
+        source: lapin::Error,
+    },
+    #[error("AMQP broker did not respond within {}s", .0.as_secs())]
+    Timeout(Duration),
+    #[error("AMQP broker rejected {0} message(s)")]
+    Nacked(usize),
+    #[error("no queue is bound for routing key '{routing_key}': {text}")]
+    Unroutable { routing_key: String, text: String },
+}
+
+impl AmqpError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            AmqpError::Timeout(_) | AmqpError::Nacked(_) => true,
+            AmqpError::Client { source, .. } => match source.kind() {
+                // connection-forced, resource-error, and internal-error are transient; access
+                // refusals and missing exchanges are not.
+                ErrorKind::ProtocolError(error) => [320, 506, 541].contains(&error.get_id()),
+                ErrorKind::IOError(_)
+                | ErrorKind::InvalidChannelState(..)
+                | ErrorKind::InvalidConnectionState(_)
+                | ErrorKind::MissingHeartbeatError => true,
+                _ => false,
+            },
+            AmqpError::InvalidUrl(_)
+            | AmqpError::Tls(_)
+            | AmqpError::RoutingKey(_)
+            | AmqpError::Unroutable { .. } => false,
+        }
+    }
+}
+
+fn client(operation: &'static str) -> impl FnOnce(lapin::Error) -> AmqpError {
+    move |source| AmqpError::Client { operation, source }
+}
+
+/// Publishes rows to an exchange on one channel in confirm mode.
+pub struct AmqpSink {
+    uri: AMQPUri,
+    tls: OwnedTLSConfig,
+    exchange: String,
+    routing_key: String,
+    mandatory: bool,
+    properties: BasicProperties,
+    timeout: Duration,
+    retry_policy: RetryPolicy,
+    /// Dropped after any failure so the retry starts on a fresh connection.
+    session: Mutex<Option<(Connection, Channel)>>,
+}
+
+impl AmqpSink {
+    pub fn new(config: &AmqpSinkConfig) -> Result<Self, AmqpError> {
+        let mut uri = config
+            .url
+            .expose()
+            .parse::<AMQPUri>()
+            .map_err(AmqpError::InvalidUrl)?;
+        if uri.vhost.is_empty() {
+            uri.vhost = "/".to_string();
+        }
+        uri.authority.userinfo.username = config
+            .username
+            .clone()
+            .unwrap_or_else(|| "guest".to_string());
+        uri.authority.userinfo.password = config
+            .password
+            .as_ref()
+            .map_or_else(|| "guest".to_string(), |password| password.expose().clone());
+        let tls = match (&uri.scheme, &config.tls) {
+            (AMQPScheme::AMQPS, Some(tls)) => OwnedTLSConfig {
+                identity: match (&tls.client_cert_file, &tls.client_key_file) {
+                    (Some(cert), Some(key)) => Some(OwnedIdentity::PKCS8 {
+                        pem: read_tls_file(cert)?,
+                        key: read_tls_file(key)?,
+                    }),
+                    _ => None,
+                },
+                cert_chain: tls
+                    .ca_file
+                    .as_deref()
+                    .map(read_tls_file)
+                    .transpose()?
+                    .map(|pem| String::from_utf8_lossy(&pem).into_owned()),
+            },
+            _ => OwnedTLSConfig::default(),
+        };
+
+        let mut headers = FieldTable::default();
+        for (name, value) in config.headers.clone().unwrap_or_default() {
+            headers.insert(name.into(), AMQPValue::LongString(value.into()));
+        }
+        let mut properties = BasicProperties::default()
+            .with_content_type("application/json".into())
+            .with_delivery_mode(if config.persistent.unwrap_or(true) {
+                2
+            } else {
+                1
+            });
+        if !headers.inner().is_empty() {
+            properties = properties.with_headers(headers);
+        }
+
+        Ok(Self {
+            uri,
+            tls,
+            exchange: config.exchange.clone().unwrap_or_default(),
+            routing_key: config.routing_key.clone(),
+            mandatory: config.mandatory,
+            properties,
+            timeout: Duration::from_secs(config.timeout_seconds.unwrap_or(30) as u64),
+            retry_policy: RetryPolicy::from_settings(&config.retry),
+            session: Mutex::new(None),
+        })
+    }
+
+    /// Publishes one message per row and waits until the broker has confirmed every one;
+    /// returns the number of messages published.
+    pub async fn send(&self, rows: &[Value]) -> Result<usize, AmqpError> {
+        let messages = build_messages(&self.routing_key, rows)?;
+        with_retries(
+            &self.retry_policy,
+            "AMQP publish",
+            || self.publish_batch(&messages),
+            |error: &AmqpError| error.is_retryable().then_some(None),
+        )
+        .await?;
+        Ok(messages.len())
+    }
+
+    async fn publish_batch(&self, messages: &[(String, Vec<u8>)]) -> Result<(), AmqpError> {
+        let mut slot = self.session.lock().await;
+        let result = tokio::time::timeout(self.timeout, async {
+            let (_, channel) = match &mut *slot {
+                Some(session) => session,
+                None => slot.insert(self.open().await?),
+            };
+            self.publish(channel, messages).await
+        })
+        .await
+        .unwrap_or(Err(AmqpError::Timeout(self.timeout)));
+        if result.is_err() {
+            *slot = None;
+        }
+        result
+    }
+
+    /// Connects and opens a channel in confirm mode.
+    async fn open(&self) -> Result<(Connection, Channel), AmqpError> {
+        let runtime = lapin::runtime::default_runtime().map_err(client("connect"))?;
+        let connection = Connection::connect_uri_with_config(
+            self.uri.clone(),
+            ConnectionProperties::default(),
+            self.tls.clone(),
+            runtime,
+        )
+        .await
+        .map_err(client("connect"))?;
+        let channel = connection
+            .create_channel()
+            .await
+            .map_err(client("channel open"))?;
+        channel
+            .confirm_select(ConfirmSelectOptions::default())
+            .await
+            .map_err(client("confirm select"))?;
+        Ok((connection, channel))
+    }
+
+    async fn publish(
+        &self,
+        channel: &Channel,
+        messages: &[(String, Vec<u8>)],
+    ) -> Result<(), AmqpError> {
+        let mut confirms = Vec::with_capacity(messages.len());
+        for (routing_key, body) in messages {
+            let confirm = channel
+                .basic_publish(
+                    self.exchange.as_str().into(),
+                    routing_key.as_str().into(),
+                    BasicPublishOptions {
+                        mandatory: self.mandatory,
+                        ..BasicPublishOptions::default()
+                    },
+                    body,
+                    self.properties.clone(),
+                )
+                .await
+                .map_err(client("publish"))?;
+            confirms.push(confirm);
+        }
+
+        let mut returned = None;
+        let mut nacked = 0;
+        for confirm in confirms {
+            let confirmation = confirm.await.map_err(client("publish confirm"))?;
+            if confirmation.is_nack() {
+                nacked += 1;
+            }
+            // Returned messages are still confirmed afterwards.
+            if let Some(message) = Confirmation::take_message(confirmation) {
+                returned.get_or_insert(AmqpError::Unroutable {
+                    routing_key: message.delivery.routing_key.to_string(),
+                    text: message.reply_text.to_string(),
+                });
+            }
+        }
+        match returned {
+            Some(error) => Err(error),
+            None if nacked > 0 => Err(AmqpError::Nacked(nacked)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Renders each row's routing key and serializes the row as the message body.
+fn build_messages(routing_key: &str, rows: &[Value]) -> Result<Vec<(String, Vec<u8>)>, AmqpError> {
+    let empty = Map::new();
+    rows.iter()
+        .map(|row| {
+            let routing_key = render_key(routing_key, row.as_object().unwrap_or(&empty))?;
+            Ok((routing_key, row.to_string().into_bytes()))
+        })
+        .collect()
+}
+
+fn read_tls_file(path: &str) -> Result<Vec<u8>, TlsConfigError> {
+    std::fs::read(path).map_err(|error| TlsConfigError::File {
+        path: path.to_string(),
+        reason: error.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn renders_a_routing_key_per_row() {
+        let messages = build_messages(
+            "orders.{{region}}.created",
+            &[
+                json!({"id": 1, "region": "eu"}),
+                json!({"id": 2, "region": "us"}),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                (
+                    "orders.eu.created".to_string(),
+                    br#"{"id":1,"region":"eu"}"#.to_vec()
+                ),
+                (
+                    "orders.us.created".to_string(),
+                    br#"{"id":2,"region":"us"}"#.to_vec()
+                ),
+            ],
+            messages
+        );
+        assert!(matches!(
+            build_messages("orders.{{region}}", &[json!({"id": 3})]),
+            Err(AmqpError::RoutingKey(_))
+        ));
+    }
+
+    #[test]
+    fn takes_credentials_from_the_config_and_decodes_the_virtual_host() {
+        let config: AmqpSinkConfig = serde_yaml::from_str(
+            "url: amqp://rabbit.internal/erp%2Fprod\nrouting_key: orders\nusername: yetii\npassword: secret\nheaders:\n  source: yetii\n",
+        )
+        .unwrap();
+        let sink = AmqpSink::new(&config).unwrap();
+
+        assert_eq!("erp/prod", sink.uri.vhost);
+        assert_eq!(5672, sink.uri.authority.port);
+        assert_eq!("yetii", sink.uri.authority.userinfo.username);
+        assert_eq!("secret", sink.uri.authority.userinfo.password);
+        assert_eq!(Some(2), *sink.properties.delivery_mode());
+        assert!(
+            sink.properties
+                .headers()
+                .as_ref()
+                .unwrap()
+                .inner()
+                .contains_key("source")
+        );
+
+        let config: AmqpSinkConfig =
+            serde_yaml::from_str("url: amqps://rabbit.internal\nrouting_key: orders\n").unwrap();
+        let sink = AmqpSink::new(&config).unwrap();
+        assert_eq!("/", sink.uri.vhost);
+        assert_eq!(5671, sink.uri.authority.port);
+        assert_eq!("guest", sink.uri.authority.userinfo.username);
+    }
+
+    #[test]
+    fn keeps_the_url_out_of_debug_output() {
+        let config: AmqpSinkConfig = serde_yaml::from_str(
+            "url: amqps://rabbit.internal/erp\nrouting_key: orders\npassword: hunter2\n",
+        )
+        .unwrap();
+
+        let debug = format!("{config:?}");
+        assert!(debug.contains("url: ***"));
+        assert!(!debug.contains("rabbit.internal"));
+        assert!(!debug.contains("hunter2"));
+    }
+}
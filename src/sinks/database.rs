@@ -0,0 +1,239 @@
+use crate::config::database::{DatabaseConfigs, DatabaseType};
+use crate::config::destination_config::{DatabaseSinkConfig, DatabaseWriteMode, is_sql_identifier};
+use crate::database::error::DbError;
+use crate::database::{self, QuerySession};
+use serde_json::{Map, Value};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DatabaseSinkError {
+    #[error("destination database '{0}' is not configured")]
+    UnknownDatabase(String),
+    #[error("destination database '{database}' could not be opened: {source}")]
+    Connect { database: String, source: DbError },
+    #[error("row {0} is not an object and cannot be written to a table")]
+    NotAnObject(usize),
+    #[error("field '{0}' is not a plain column name; list the target columns in `columns`")]
+    InvalidColumn(String),
+    #[error("key column '{0}' is not among the written columns")]
+    MissingKeyColumn(String),
+    #[error("writing to table '{table}' failed: {source}")]
+    Write { table: String, source: DbError },
+}
+
+/// Writes each batch into a table of another configured database in one transaction.
+pub struct DatabaseSink {
+    session: QuerySession,
+    db_type: DatabaseType,
+    table: String,
+    mode: DatabaseWriteMode,
+    columns: Option<Vec<String>>,
+    key_columns: Vec<String>,
+}
+
+impl DatabaseSink {
+    pub async fn connect(
+        config: &DatabaseSinkConfig,
+        databases: &DatabaseConfigs,
+    ) -> Result<Self, DatabaseSinkError> {
+        let database = databases
+            .get(&config.database)
+            .ok_or_else(|| DatabaseSinkError::UnknownDatabase(config.database.clone()))?;
+        let session = database::open_session(database).await.map_err(|source| {
+            DatabaseSinkError::Connect {
+                database: config.database.clone(),
+                source,
+            }
+        })?;
+        Ok(Self {
+            session,
+            db_type: database.db_type.clone(),
+            table: config.table.clone(),
+            mode: config.mode,
+            columns: config.columns.clone(),
+            key_columns: config.key_columns.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Writes the rows and returns how many were inserted or upserted.
+    pub async fn send(&self, rows: &[Value]) -> Result<usize, DatabaseSinkError> {
+        let rows = rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| {
+                row.as_object()
+                    .cloned()
+                    .ok_or(DatabaseSinkError::NotAnObject(index + 1))
+            })
+            .collect::<Result<Vec<Map<String, Value>>, _>>()?;
+        let Some(first) = rows.first() else {
+            return Ok(0);
+        };
+        let columns = match &self.columns {
+            Some(columns) => columns.clone(),
+            None => {
+                if let Some(name) = first.keys().find(|name| !is_sql_identifier(name)) {
+                    return Err(DatabaseSinkError::InvalidColumn(name.clone()));
+                }
+                first.keys().cloned().collect()
+            }
+        };
+        if let Some(key) = self.key_columns.iter().find(|key| !columns.contains(key)) {
+            return Err(DatabaseSinkError::MissingKeyColumn(key.clone()));
+        }
+        let sql = write_sql(
+            &self.db_type,
+            &self.table,
+            &columns,
+            self.mode,
+            &self.key_columns,
+        );
+        self.session
+            .write(&sql, rows)
+            .await
+            .map_err(|source| DatabaseSinkError::Write {
+                table: self.table.clone(),
+                source,
+            })
+    }
+}
+
+/// Builds the per-row statement with a `:column` placeholder for every column.
+fn write_sql(
+    db_type: &DatabaseType,
+    table: &str,
+    columns: &[String],
+    mode: DatabaseWriteMode,
+    key_columns: &[String],
+) -> String {
+    let column_list = columns.join(", ");
+    let placeholders = columns
+        .iter()
+        .map(|column| format!(":{column}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let insert = format!("INSERT INTO {table} ({column_list}) VALUES ({placeholders})");
+    if mode == DatabaseWriteMode::Insert {
+        return insert;
+    }
+
+    let updated = columns
+        .iter()
+        .filter(|column| !key_columns.contains(column))
+        .collect::<Vec<_>>();
+    let assignments = |value: &dyn Fn(&str) -> String| {
+        updated
+            .iter()
+            .map(|column| format!("{column} = {}", value(column)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match db_type {
+        // Generator sessions refuse writes; validation rejects them as destinations.
+        DatabaseType::Generator => insert,
+        DatabaseType::Postgres if updated.is_empty() => format!(
+            "{insert} ON CONFLICT ({}) DO NOTHING",
+            key_columns.join(", ")
+        ),
+        DatabaseType::Postgres => format!(
+            "{insert} ON CONFLICT ({}) DO UPDATE SET {}",
+            key_columns.join(", "),
+            assignments(&|column| format!("EXCLUDED.{column}"))
+        ),
+        // Rewriting the first key onto itself turns duplicates into no-ops.
+        DatabaseType::Mysql if updated.is_empty() => {
+            format!("{insert} ON DUPLICATE KEY UPDATE {0} = {0}", key_columns[0])
+        }
+        DatabaseType::Mysql => format!(
+            "{insert} ON DUPLICATE KEY UPDATE {}",
+            assignments(&|column| format!("VALUES({column})"))
+        ),
+        DatabaseType::Mssql | DatabaseType::Oracle => {
+            let selected = columns
+                .iter()
+                .map(|column| format!(":{column} AS {column}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let matched = key_columns
+                .iter()
+                .map(|column| format!("target.{column} = source.{column}"))
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            let source_values = columns
+                .iter()
+                .map(|column| format!("source.{column}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let (source, alias, terminator) = match db_type {
+                DatabaseType::Oracle => (format!("SELECT {selected} FROM dual"), "", ""),
+                _ => (format!("SELECT {selected}"), "AS ", ";"),
+            };
+            let when_matched = if updated.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    " WHEN MATCHED THEN UPDATE SET {}",
+                    assignments(&|column| format!("source.{column}"))
+                )
+            };
+            format!(
+                "MERGE INTO {table} {alias}target USING ({source}) {alias}source ON ({matched}){when_matched} WHEN NOT MATCHED THEN INSERT ({column_list}) VALUES ({source_values}){terminator}"
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn builds_insert_and_dialect_specific_upserts() {
+        let columns = names(&["id", "status", "total"]);
+        let keys = names(&["id"]);
+
+        assert_eq!(
+            "INSERT INTO analytics.orders (id, status, total) VALUES (:id, :status, :total)",
+            write_sql(
+                &DatabaseType::Mssql,
+                "analytics.orders",
+                &columns,
+                DatabaseWriteMode::Insert,
+                &[]
+            )
+        );
+        assert_eq!(
+            "INSERT INTO orders (id, status, total) VALUES (:id, :status, :total) ON CONFLICT (id) DO UPDATE SET status = EXCLUDED.status, total = EXCLUDED.total",
+            write_sql(
+                &DatabaseType::Postgres,
+                "orders",
+                &columns,
+                DatabaseWriteMode::Upsert,
+                &keys
+            )
+        );
+        assert_eq!(
+            "INSERT INTO orders (id, status, total) VALUES (:id, :status, :total) ON DUPLICATE KEY UPDATE status = VALUES(status), total = VALUES(total)",
+            write_sql(
+                &DatabaseType::Mysql,
+                "orders",
+                &columns,
+                DatabaseWriteMode::Upsert,
+                &keys
+            )
+        );
+        assert_eq!(
+            "MERGE INTO orders target USING (SELECT :id AS id, :status AS status, :total AS total FROM dual) source ON (target.id = source.id) WHEN MATCHED THEN UPDATE SET status = source.status, total = source.total WHEN NOT MATCHED THEN INSERT (id, status, total) VALUES (source.id, source.status, source.total)",
+            write_sql(
+                &DatabaseType::Oracle,
+                "orders",
+                &columns,
+                DatabaseWriteMode::Upsert,
+                &keys
+            )
+        );
+    }
+}
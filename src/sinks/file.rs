@@ -0,0 +1,172 @@
+use crate::config::destination_config::FileSinkConfig;
+use crate::http::format::{BodyLayout, FormatError, PayloadFormat};
+use crate::sinks::manifest::{Manifest, ManifestError};
+use crate::sinks::redis::MissingKeyField;
+use crate::sinks::render_file_name;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, thiserror::Error)]
+pub enum FileSinkError {
+    #[error(transparent)]
+    Format(#[from] FormatError),
+    #[error(transparent)]
+    FileName(#[from] MissingKeyField),
+    #[error(transparent)]
+    Manifest(#[from] ManifestError),
+    #[error("output file '{}' already exists; set overwrite: true to replace it", .0.display())]
+    Exists(PathBuf),
+    #[error("failed to write '{}': {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// Writes each batch as one file in a local directory.
+pub struct FileSink {
+    directory: PathBuf,
+    file_name: String,
+    query: String,
+    format: PayloadFormat,
+    layout: BodyLayout,
+    temp_suffix: String,
+    overwrite: bool,
+    manifest: Option<Manifest>,
+    sequence: AtomicUsize,
+}
+
+impl FileSink {
+    /// `leading_columns` orders `csv` and `xml` files; see [`BodyLayout`].
+    pub fn new(
+        config: &FileSinkConfig,
+        query_name: &str,
+        leading_columns: Vec<String>,
+    ) -> Result<Self, FileSinkError> {
+        Ok(Self {
+            directory: PathBuf::from(&config.directory),
+            file_name: config.file_name.clone(),
+            query: query_name.to_string(),
+            format: config.body.format,
+            layout: BodyLayout {
+                leading_columns,
+                ..config.body.layout()?
+            },
+            temp_suffix: config
+                .temp_suffix
+                .clone()
+                .unwrap_or_else(|| ".part".to_string()),
+            overwrite: config.overwrite,
+            manifest: config
+                .manifest
+                .as_ref()
+                .map(|manifest| Manifest::new(manifest, query_name))
+                .transpose()?,
+            sequence: AtomicUsize::new(0),
+        })
+    }
+
+    /// Writes the batch under the temporary name and renames it into place, so readers watching
+    /// the directory never see a partial file; returns the final path.
+    pub async fn send(&self, rows: &[Value]) -> Result<PathBuf, FileSinkError> {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        let name = render_file_name(&self.file_name, &self.query, sequence)?;
+        let path = self.directory.join(&name);
+        let body = self
+            .format
+            .encode(&Value::Array(rows.to_vec()), &self.layout)?;
+
+        self.write(&path, &body.bytes).await?;
+        if let Some(manifest) = &self.manifest {
+            manifest.record(&name, rows.len(), &body.bytes);
+        }
+        Ok(path)
+    }
+
+    /// Writes the manifest of the files sent so far, after its signature; `None` without a
+    /// manifest or when no file was written.
+    pub async fn finish(&self) -> Result<Option<PathBuf>, FileSinkError> {
+        let Some(manifest) = &self.manifest else {
+            return Ok(None);
+        };
+        let Some(document) = manifest.render()? else {
+            return Ok(None);
+        };
+        if let Some((name, signature)) = &document.signature {
+            self.write(&self.directory.join(name), signature).await?;
+        }
+        let path = self.directory.join(&document.name);
+        self.write(&path, &document.body).await?;
+        Ok(Some(path))
+    }
+
+    async fn write(&self, path: &Path, bytes: &[u8]) -> Result<(), FileSinkError> {
+        tokio::fs::create_dir_all(&self.directory)
+            .await
+            .map_err(io_error(&self.directory))?;
+        if !self.overwrite && tokio::fs::try_exists(path).await.map_err(io_error(path))? {
+            return Err(FileSinkError::Exists(path.to_path_buf()));
+        }
+        if self.temp_suffix.is_empty() {
+            return tokio::fs::write(path, bytes).await.map_err(io_error(path));
+        }
+        let mut temp_path = path.as_os_str().to_os_string();
+        temp_path.push(&self.temp_suffix);
+        let temp_path = PathBuf::from(temp_path);
+        tokio::fs::write(&temp_path, bytes)
+            .await
+            .map_err(io_error(&temp_path))?;
+        if let Err(error) = tokio::fs::rename(&temp_path, path).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(io_error(path)(error));
+        }
+        Ok(())
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> FileSinkError + '_ {
+    move |source| FileSinkError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn writes_each_batch_to_a_numbered_file_and_refuses_to_overwrite() {
+        let directory =
+            std::env::temp_dir().join(format!("yetii-file-sink-{}", std::process::id()));
+        let config: FileSinkConfig = serde_yaml::from_str(&format!(
+            "directory: {}\nfile_name: \"{{{{query}}}}_{{{{sequence}}}}.ndjson\"\nformat: ndjson\n",
+            directory.display()
+        ))
+        .unwrap();
+        let sink = FileSink::new(&config, "orders", Vec::new()).unwrap();
+
+        let first = sink
+            .send(&[json!({"id": 1}), json!({"id": 2})])
+            .await
+            .unwrap();
+        assert_eq!(directory.join("orders_1.ndjson"), first);
+        assert_eq!(
+            "{\"id\":1}\n{\"id\":2}\n",
+            std::fs::read_to_string(&first).unwrap()
+        );
+        assert!(!directory.join("orders_1.ndjson.part").exists());
+
+        let second = sink.send(&[json!({"id": 3})]).await.unwrap();
+        assert_eq!(directory.join("orders_2.ndjson"), second);
+
+        let rerun = FileSink::new(&config, "orders", Vec::new()).unwrap();
+        assert!(matches!(
+            rerun.send(&[json!({"id": 4})]).await,
+            Err(FileSinkError::Exists(_))
+        ));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,358 @@
+use crate::config::destination_config::{FtpMode, FtpSinkConfig, FtpTlsMode};
+use crate::http::format::{BodyLayout, FormatError, PayloadFormat};
+use crate::http::retry::RetryPolicy;
+use crate::sinks::redis::MissingKeyField;
+use crate::sinks::render_file_name;
+use crate::sinks::tls::{TlsConfigError, client_config};
+use crate::sinks::with_retries;
+use rustls::pki_types::ServerName;
+use serde_json::Value;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use suppaftp::tokio::{AsyncRustlsConnector, AsyncRustlsFtpStream};
+use suppaftp::types::FileType;
+use suppaftp::{Mode, Status};
+use tokio_rustls::TlsConnector;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FtpError {
+    #[error("invalid FTP URL: {0}")]
+    InvalidUrl(String),
+    #[error(transparent)]
+    Tls(#[from] TlsConfigError),
+    #[error(transparent)]
+    Format(#[from] FormatError),
+    #[error(transparent)]
+    FileName(#[from] MissingKeyField),
+    #[error("FTP server did not respond within {}s", .0.as_secs())]
+    Timeout(Duration),
+    #[error("FTP {operation} failed: {source}")]
+    Ftp {
+        operation: &'static str,
+        source: suppaftp::FtpError,
+    },
+}
+
+impl FtpError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            FtpError::Timeout(_) => true,
+            FtpError::Ftp { source, .. } => match source {
+                suppaftp::FtpError::ConnectionError(_) => true,
+                // 4xx replies are transient negative completions (RFC 959).
+                suppaftp::FtpError::UnexpectedResponse(response) => {
+                    (400..500).contains(&response.status.code())
+                }
+                _ => false,
+            },
+            FtpError::InvalidUrl(_)
+            | FtpError::Tls(_)
+            | FtpError::Format(_)
+            | FtpError::FileName(_) => false,
+        }
+    }
+}
+
+struct FtpTls {
+    connector: TlsConnector,
+    mode: FtpTlsMode,
+}
+
+/// Uploads each batch as one file over a fresh control connection.
+pub struct FtpSink {
+    host: String,
+    port: u16,
+    tls: Option<FtpTls>,
+    username: String,
+    password: String,
+    directory: Option<String>,
+    file_name: String,
+    query: String,
+    format: PayloadFormat,
+    layout: BodyLayout,
+    mode: FtpMode,
+    temp_suffix: String,
+    timeout: Duration,
+    retry_policy: RetryPolicy,
+    sequence: AtomicUsize,
+}
+
+impl FtpSink {
+    /// `leading_columns` orders `csv` and `xml` files; see [`BodyLayout`].
+    pub fn new(
+        config: &FtpSinkConfig,
+        query_name: &str,
+        leading_columns: Vec<String>,
+    ) -> Result<Self, FtpError> {
+        let url = url::Url::parse(&config.url)
+            .map_err(|error| FtpError::InvalidUrl(error.to_string()))?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| FtpError::InvalidUrl("missing host".to_string()))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        let tls = match url.scheme() {
+            "ftps" => {
+                ServerName::try_from(host.as_str())
+                    .map_err(|error| FtpError::InvalidUrl(error.to_string()))?;
+                Some(FtpTls {
+                    connector: TlsConnector::from(Arc::new(client_config(config.tls.as_ref())?)),
+                    mode: config.tls_mode,
+                })
+            }
+            _ => None,
+        };
+        let default_port = match &tls {
+            Some(tls) if tls.mode == FtpTlsMode::Implicit => 990,
+            _ => 21,
+        };
+
+        Ok(Self {
+            port: url.port().unwrap_or(default_port),
+            host,
+            tls,
+            username: config
+                .username
+                .clone()
+                .unwrap_or_else(|| "anonymous".to_string()),
+            password: config.password.as_ref().map_or_else(
+                || "anonymous@".to_string(),
+                |password| password.expose().clone(),
+            ),
+            directory: config.directory.clone(),
+            file_name: config.file_name.clone(),
+            query: query_name.to_string(),
+            format: config.body.format,
+            layout: BodyLayout {
+                leading_columns,
+                ..config.body.layout()?
+            },
+            mode: config.mode,
+            temp_suffix: config
+                .temp_suffix
+                .clone()
+                .unwrap_or_else(|| ".part".to_string()),
+            timeout: Duration::from_secs(config.timeout_seconds.unwrap_or(30) as u64),
+            retry_policy: RetryPolicy::from_settings(&config.retry),
+            sequence: AtomicUsize::new(0),
+        })
+    }
+
+    /// Uploads the batch as one file; returns its remote path.
+    pub async fn send(&self, rows: &[Value]) -> Result<String, FtpError> {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        let name = render_file_name(&self.file_name, &self.query, sequence)?;
+        let path = match &self.directory {
+            Some(directory) => format!("{}/{name}", directory.trim_end_matches('/')),
+            None => name,
+        };
+        let body = self
+            .format
+            .encode(&Value::Array(rows.to_vec()), &self.layout)?;
+
+        with_retries(
+            &self.retry_policy,
+            "FTP upload",
+            || self.upload(&path, &body.bytes),
+            |error: &FtpError| error.is_retryable().then_some(None),
+        )
+        .await?;
+        Ok(path)
+    }
+
+    async fn upload(&self, path: &str, bytes: &[u8]) -> Result<(), FtpError> {
+        let mut ftp = self.login().await?;
+        let result = self.store(&mut ftp, path, bytes).await;
+        if let Err(error) = self.timed("QUIT", ftp.quit()).await {
+            tracing::debug!(error = %error, "FTP QUIT failed");
+        }
+        result
+    }
+
+    async fn login(&self) -> Result<AsyncRustlsFtpStream, FtpError> {
+        let address = (self.host.as_str(), self.port);
+        let mut ftp = match &self.tls {
+            Some(tls) if tls.mode == FtpTlsMode::Implicit => {
+                let ftp = AsyncRustlsFtpStream::connect_secure_implicit(
+                    address,
+                    AsyncRustlsConnector::from(tls.connector.clone()),
+                    &self.host,
+                );
+                let mut ftp = self.timed("connect", ftp).await?;
+                // `AUTH TLS` sends these on explicit connections.
+                self.timed("PBSZ", ftp.custom_command("PBSZ 0", &[Status::CommandOk]))
+                    .await?;
+                self.timed("PROT", ftp.custom_command("PROT P", &[Status::CommandOk]))
+                    .await?;
+                ftp
+            }
+            Some(tls) => {
+                let ftp = self
+                    .timed("connect", AsyncRustlsFtpStream::connect(address))
+                    .await?;
+                let secure = ftp.into_secure(
+                    AsyncRustlsConnector::from(tls.connector.clone()),
+                    &self.host,
+                );
+                self.timed("AUTH TLS", secure).await?
+            }
+            None => {
+                self.timed("connect", AsyncRustlsFtpStream::connect(address))
+                    .await?
+            }
+        };
+        self.timed(
+            "login",
+            ftp.login(self.username.as_str(), self.password.as_str()),
+        )
+        .await?;
+        self.timed("TYPE", ftp.transfer_type(FileType::Binary))
+            .await?;
+
+        match self.mode {
+            FtpMode::Passive => {
+                let peer = ftp
+                    .get_ref()
+                    .await
+                    .peer_addr()
+                    .map_err(|error| FtpError::Ftp {
+                        operation: "connect",
+                        source: suppaftp::FtpError::ConnectionError(error),
+                    })?;
+                // The announced host is ignored in favour of the control connection's peer,
+                // which keeps servers behind NAT reachable; PASV cannot announce IPv6 at all.
+                ftp.set_mode(if peer.is_ipv6() {
+                    Mode::ExtendedPassive
+                } else {
+                    Mode::Passive
+                });
+                ftp.set_passive_nat_workaround(true);
+                Ok(ftp)
+            }
+            FtpMode::Active => Ok(ftp.active_mode(self.timeout)),
+        }
+    }
+
+    /// Writes `bytes` under the temporary name, then renames the file into place.
+    async fn store(
+        &self,
+        ftp: &mut AsyncRustlsFtpStream,
+        path: &str,
+        bytes: &[u8],
+    ) -> Result<(), FtpError> {
+        let upload_path = format!("{path}{}", self.temp_suffix);
+        let mut reader = bytes;
+        self.timed("STOR", ftp.put_file(upload_path.as_str(), &mut reader))
+            .await?;
+        if upload_path != path {
+            self.timed("rename", ftp.rename(upload_path.as_str(), path))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn timed<T>(
+        &self,
+        operation: &'static str,
+        future: impl Future<Output = suppaftp::FtpResult<T>>,
+    ) -> Result<T, FtpError> {
+        tokio::time::timeout(self.timeout, future)
+            .await
+            .map_err(|_| FtpError::Timeout(self.timeout))?
+            .map_err(|source| FtpError::Ftp { operation, source })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn uploads_under_a_temporary_name_and_renames_in_passive_mode() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut control = BufReader::new(stream);
+            control
+                .write_all(b"220-Legacy VAN\r\n220 ready\r\n")
+                .await
+                .unwrap();
+            let mut data_listener = None;
+            let mut commands = Vec::new();
+            let mut uploaded = String::new();
+            loop {
+                let mut line = String::new();
+                control.read_line(&mut line).await.unwrap();
+                let line = line.trim_end().to_string();
+                let reply = match line.split_once(' ').map_or(line.as_str(), |(verb, _)| verb) {
+                    "USER" => "331 password required".to_string(),
+                    "PASS" => "230 logged in".to_string(),
+                    "TYPE" => "200 binary".to_string(),
+                    "PASV" => {
+                        let data = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                        let port = data.local_addr().unwrap().port();
+                        data_listener = Some(data);
+                        format!(
+                            "227 Entering Passive Mode (127,0,0,1,{},{})",
+                            port >> 8,
+                            port & 0xff
+                        )
+                    }
+                    "STOR" => {
+                        control.write_all(b"150 send it\r\n").await.unwrap();
+                        let data = data_listener.take().unwrap();
+                        let (mut data, _) = data.accept().await.unwrap();
+                        data.read_to_string(&mut uploaded).await.unwrap();
+                        "226 stored".to_string()
+                    }
+                    "RNFR" => "350 ready for destination".to_string(),
+                    "RNTO" => "250 renamed".to_string(),
+                    "QUIT" => "221 bye".to_string(),
+                    _ => "502 not implemented".to_string(),
+                };
+                commands.push(line.clone());
+                control
+                    .write_all(format!("{reply}\r\n").as_bytes())
+                    .await
+                    .unwrap();
+                if line == "QUIT" {
+                    return (commands, uploaded);
+                }
+            }
+        });
+
+        let config: FtpSinkConfig = serde_yaml::from_str(&format!(
+            "url: ftp://{address}\nusername: partner\npassword: secret\ndirectory: /inbound/\nfile_name: \"{{{{query}}}}_{{{{sequence}}}}.ndjson\"\nformat: ndjson\nretry_attempts: 0\n"
+        ))
+        .unwrap();
+        config.validate("orders").unwrap();
+        let sink = FtpSink::new(&config, "orders", Vec::new()).unwrap();
+
+        let path = sink
+            .send(&[serde_json::json!({"id": 1}), serde_json::json!({"id": 2})])
+            .await
+            .unwrap();
+        let (commands, uploaded) = server.await.unwrap();
+
+        assert_eq!("/inbound/orders_1.ndjson", path);
+        assert_eq!("{\"id\":1}\n{\"id\":2}\n", uploaded);
+        assert_eq!(
+            vec![
+                "USER partner",
+                "PASS secret",
+                "TYPE I",
+                "PASV",
+                "STOR /inbound/orders_1.ndjson.part",
+                "RNFR /inbound/orders_1.ndjson.part",
+                "RNTO /inbound/orders_1.ndjson",
+                "QUIT",
+            ],
+            commands
+        );
+    }
+}
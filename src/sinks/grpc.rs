@@ -0,0 +1,680 @@
+use crate::config::destination_config::{GrpcSinkConfig, GrpcStatusCode};
+use crate::http::retry::RetryPolicy;
+use crate::sinks::with_retries;
+use prost_reflect::prost::Message;
+use prost_reflect::prost_types::FileDescriptorProto;
+use prost_reflect::{
+    DescriptorPool, DeserializeOptions, DynamicMessage, Kind, MessageDescriptor, MethodDescriptor,
+};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue};
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use tonic::{Code, Status};
+use tonic_reflection::pb::v1::ServerReflectionRequest;
+use tonic_reflection::pb::v1::server_reflection_client::ServerReflectionClient;
+use tonic_reflection::pb::v1::server_reflection_request::MessageRequest;
+use tonic_reflection::pb::v1::server_reflection_response::MessageResponse;
+
+const DEFAULT_RETRY_CODES: &[Code] = &[
+    Code::Unavailable,
+    Code::DeadlineExceeded,
+    Code::ResourceExhausted,
+    Code::Aborted,
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum GrpcError {
+    #[error("invalid gRPC url '{url}': {reason}")]
+    InvalidUrl { url: String, reason: String },
+    #[error("failed to read descriptor set '{path}': {source}")]
+    ReadDescriptorSet {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("invalid protobuf descriptors: {0}")]
+    Descriptor(#[from] prost_reflect::DescriptorError),
+    #[error("gRPC server reflection failed: {0}")]
+    Reflection(String),
+    #[error("gRPC method '{0}' was not found in the descriptors")]
+    UnknownMethod(String),
+    #[error("gRPC method '{0}' is streaming; only unary methods are supported")]
+    StreamingMethod(String),
+    #[error("batch_field '{field}' is not a repeated message field of '{message}'")]
+    InvalidBatchField { field: String, message: String },
+    #[error("invalid gRPC metadata '{name}'")]
+    InvalidMetadata { name: String },
+    #[error("row does not match '{message}': {reason}")]
+    Mapping { message: String, reason: String },
+    #[error("gRPC call failed with {code:?}: {message}")]
+    Status { code: Code, message: String },
+}
+
+impl From<Status> for GrpcError {
+    fn from(status: Status) -> Self {
+        GrpcError::Status {
+            code: status.code(),
+            message: status.message().to_string(),
+        }
+    }
+}
+
+/// Parses a gRPC status code name such as `unavailable` or `DEADLINE_EXCEEDED`.
+fn status_code(code: GrpcStatusCode) -> Code {
+    match code {
+        GrpcStatusCode::Cancelled => Code::Cancelled,
+        GrpcStatusCode::Unknown => Code::Unknown,
+        GrpcStatusCode::InvalidArgument => Code::InvalidArgument,
+        GrpcStatusCode::DeadlineExceeded => Code::DeadlineExceeded,
+        GrpcStatusCode::NotFound => Code::NotFound,
+        GrpcStatusCode::AlreadyExists => Code::AlreadyExists,
+        GrpcStatusCode::PermissionDenied => Code::PermissionDenied,
+        GrpcStatusCode::ResourceExhausted => Code::ResourceExhausted,
+        GrpcStatusCode::FailedPrecondition => Code::FailedPrecondition,
+        GrpcStatusCode::Aborted => Code::Aborted,
+        GrpcStatusCode::OutOfRange => Code::OutOfRange,
+        GrpcStatusCode::Unimplemented => Code::Unimplemented,
+        GrpcStatusCode::Internal => Code::Internal,
+        GrpcStatusCode::Unavailable => Code::Unavailable,
+        GrpcStatusCode::DataLoss => Code::DataLoss,
+        GrpcStatusCode::Unauthenticated => Code::Unauthenticated,
+    }
+}
+
+pub struct GrpcSink {
+    channel: Channel,
+    method: MethodDescriptor,
+    path: PathAndQuery,
+    batch_field: Option<String>,
+    metadata: Vec<(AsciiMetadataKey, AsciiMetadataValue)>,
+    deadline: Duration,
+    deserialize: DeserializeOptions,
+    retry_policy: RetryPolicy,
+    retry_codes: Vec<Code>,
+}
+
+impl GrpcSink {
+    /// Resolves the method from the descriptor set or server reflection. The channel connects
+    /// lazily, so an unreachable server surfaces as a retryable `Unavailable` on the first call.
+    pub async fn connect(config: &GrpcSinkConfig) -> Result<Self, GrpcError> {
+        let deadline = Duration::from_secs(config.deadline_seconds.unwrap_or(30) as u64);
+        let invalid_url = |reason: String| GrpcError::InvalidUrl {
+            url: config.url.clone(),
+            reason,
+        };
+        let mut endpoint = Endpoint::from_shared(config.url.clone())
+            .map_err(|error| invalid_url(error.to_string()))?
+            .timeout(deadline)
+            .connect_timeout(deadline);
+        if config.url.starts_with("https://") {
+            endpoint = endpoint
+                .tls_config(ClientTlsConfig::new().with_webpki_roots())
+                .map_err(|error| invalid_url(error.to_string()))?;
+        }
+        let channel = endpoint.connect_lazy();
+
+        let mut metadata = Vec::new();
+        for (name, value) in config.metadata.iter().flatten() {
+            let invalid = || GrpcError::InvalidMetadata { name: name.clone() };
+            metadata.push((
+                name.to_ascii_lowercase().parse().map_err(|_| invalid())?,
+                value.parse().map_err(|_| invalid())?,
+            ));
+        }
+
+        let qualified = config.method.trim_start_matches('/');
+        let (service, method_name) = qualified
+            .split_once('/')
+            .ok_or_else(|| GrpcError::UnknownMethod(config.method.clone()))?;
+        let pool = match &config.descriptor_set {
+            Some(path) => {
+                let bytes = std::fs::read(path).map_err(|source| GrpcError::ReadDescriptorSet {
+                    path: path.clone(),
+                    source,
+                })?;
+                DescriptorPool::decode(bytes.as_slice())?
+            }
+            None => reflect(channel.clone(), service, &metadata).await?,
+        };
+        let method = pool
+            .get_service_by_name(service)
+            .and_then(|service| {
+                service
+                    .methods()
+                    .find(|method| method.name() == method_name)
+            })
+            .ok_or_else(|| GrpcError::UnknownMethod(qualified.to_string()))?;
+        if method.is_client_streaming() || method.is_server_streaming() {
+            return Err(GrpcError::StreamingMethod(qualified.to_string()));
+        }
+        if let Some(field) = &config.batch_field {
+            let input = method.input();
+            let valid = input.get_field_by_name(field).is_some_and(|descriptor| {
+                descriptor.is_list() && matches!(descriptor.kind(), Kind::Message(_))
+            });
+            if !valid {
+                return Err(GrpcError::InvalidBatchField {
+                    field: field.clone(),
+                    message: input.full_name().to_string(),
+                });
+            }
+        }
+
+        Ok(Self {
+            channel,
+            path: format!("/{service}/{method_name}")
+                .parse()
+                .map_err(|_| GrpcError::UnknownMethod(qualified.to_string()))?,
+            method,
+            batch_field: config.batch_field.clone(),
+            metadata,
+            deadline,
+            deserialize: DeserializeOptions::new()
+                .deny_unknown_fields(!config.ignore_unknown_fields),
+            retry_policy: RetryPolicy::from_settings(&config.retry),
+            retry_codes: match &config.retry_on_codes {
+                Some(codes) => codes.iter().copied().map(status_code).collect(),
+                None => DEFAULT_RETRY_CODES.to_vec(),
+            },
+        })
+    }
+
+    /// Sends the rows as one request (`batch_field`) or one request per row; returns the call count.
+    pub async fn send(&self, rows: &[Value]) -> Result<usize, GrpcError> {
+        let messages = match &self.batch_field {
+            Some(field) => vec![self.to_message(&Value::Object(Map::from_iter([(
+                field.clone(),
+                Value::Array(rows.to_vec()),
+            )])))?],
+            None => rows
+                .iter()
+                .map(|row| self.to_message(row))
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        for message in &messages {
+            self.call_with_retry(message).await?;
+        }
+        Ok(messages.len())
+    }
+
+    fn to_message(&self, value: &Value) -> Result<DynamicMessage, GrpcError> {
+        let input = self.method.input();
+        DynamicMessage::deserialize_with_options(input.clone(), value, &self.deserialize).map_err(
+            |error| GrpcError::Mapping {
+                message: input.full_name().to_string(),
+                reason: error.to_string(),
+            },
+        )
+    }
+
+    async fn call_with_retry(&self, message: &DynamicMessage) -> Result<(), GrpcError> {
+        with_retries(
+            &self.retry_policy,
+            &format!("gRPC call to {}", self.path),
+            || self.call(message),
+            |status: &Status| self.retry_codes.contains(&status.code()).then_some(None),
+        )
+        .await
+        .map_err(GrpcError::from)
+    }
+
+    async fn call(&self, message: &DynamicMessage) -> Result<(), Status> {
+        let mut client = tonic::client::Grpc::new(self.channel.clone());
+        client
+            .ready()
+            .await
+            .map_err(|error| Status::unavailable(format!("gRPC channel not ready: {error}")))?;
+        let mut request = tonic::Request::new(message.clone());
+        request.set_timeout(self.deadline);
+        for (name, value) in &self.metadata {
+            request.metadata_mut().insert(name.clone(), value.clone());
+        }
+        client
+            .unary(
+                request,
+                self.path.clone(),
+                DynamicCodec::new(self.method.output()),
+            )
+            .await
+            .map(|_| ())
+    }
+}
+
+/// Fetches the file declaring `service`, plus its imports, through gRPC server reflection.
+async fn reflect(
+    channel: Channel,
+    service: &str,
+    metadata: &[(AsciiMetadataKey, AsciiMetadataValue)],
+) -> Result<DescriptorPool, GrpcError> {
+    let mut client = ServerReflectionClient::new(channel);
+    let mut files = HashMap::<String, FileDescriptorProto>::new();
+    let mut pending = vec![MessageRequest::FileContainingSymbol(service.to_string())];
+
+    while let Some(message_request) = pending.pop() {
+        if let MessageRequest::FileByFilename(name) = &message_request
+            && files.contains_key(name)
+        {
+            continue;
+        }
+        let mut request = tonic::Request::new(tokio_stream::iter([ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(message_request),
+        }]));
+        for (name, value) in metadata {
+            request.metadata_mut().insert(name.clone(), value.clone());
+        }
+        let response = client
+            .server_reflection_info(request)
+            .await
+            .map_err(|status| GrpcError::Reflection(status.message().to_string()))?
+            .into_inner()
+            .message()
+            .await
+            .map_err(|status| GrpcError::Reflection(status.message().to_string()))?
+            .and_then(|response| response.message_response);
+
+        match response {
+            Some(MessageResponse::FileDescriptorResponse(found)) => {
+                for bytes in found.file_descriptor_proto {
+                    let file = FileDescriptorProto::decode(bytes.as_slice())
+                        .map_err(|error| GrpcError::Reflection(error.to_string()))?;
+                    pending.extend(
+                        file.dependency
+                            .iter()
+                            .filter(|dependency| !files.contains_key(*dependency))
+                            .map(|dependency| MessageRequest::FileByFilename(dependency.clone())),
+                    );
+                    files.insert(file.name().to_string(), file);
+                }
+            }
+            Some(MessageResponse::ErrorResponse(error)) => {
+                return Err(GrpcError::Reflection(error.error_message));
+            }
+            _ => {
+                return Err(GrpcError::Reflection(
+                    "unexpected server reflection response".to_string(),
+                ));
+            }
+        }
+    }
+
+    let mut pool = DescriptorPool::new();
+    pool.add_file_descriptor_protos(files.into_values())?;
+    Ok(pool)
+}
+
+/// Encodes any `DynamicMessage` and decodes responses of one message type.
+#[derive(Clone)]
+struct DynamicCodec {
+    decode: MessageDescriptor,
+}
+
+impl DynamicCodec {
+    fn new(decode: MessageDescriptor) -> Self {
+        Self { decode }
+    }
+}
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = DynamicEncoder;
+    type Decoder = DynamicDecoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        DynamicEncoder
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        DynamicDecoder(self.decode.clone())
+    }
+}
+
+struct DynamicEncoder;
+
+impl Encoder for DynamicEncoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item.encode(dst)
+            .map_err(|error| Status::internal(format!("failed to encode message: {error}")))
+    }
+}
+
+struct DynamicDecoder(MessageDescriptor);
+
+impl Decoder for DynamicDecoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        DynamicMessage::decode(self.0.clone(), src)
+            .map(Some)
+            .map_err(|error| Status::internal(format!("failed to decode message: {error}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::destination_config::RetrySettings;
+    use prost_reflect::prost_types::field_descriptor_proto::{Label, Type};
+    use prost_reflect::prost_types::{
+        DescriptorProto, FieldDescriptorProto, FileDescriptorSet, MethodDescriptorProto,
+        ServiceDescriptorProto,
+    };
+    use std::sync::{Arc, Mutex};
+    use tonic::codegen::{BoxFuture, Context, Poll, Service, http};
+    use tonic::server::NamedService;
+
+    fn field(
+        name: &str,
+        number: i32,
+        r#type: Type,
+        type_name: Option<&str>,
+    ) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(Label::Optional as i32),
+            r#type: Some(r#type as i32),
+            type_name: type_name.map(str::to_string),
+            json_name: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn message(name: &str, fields: Vec<FieldDescriptorProto>) -> DescriptorProto {
+        DescriptorProto {
+            name: Some(name.to_string()),
+            field: fields,
+            ..Default::default()
+        }
+    }
+
+    fn method(name: &str, input: &str) -> MethodDescriptorProto {
+        MethodDescriptorProto {
+            name: Some(name.to_string()),
+            input_type: Some(format!(".ingest.v1.{input}")),
+            output_type: Some(".ingest.v1.IngestResponse".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn descriptor_set() -> FileDescriptorSet {
+        let mut records = field("records", 1, Type::Message, Some(".ingest.v1.Record"));
+        records.label = Some(Label::Repeated as i32);
+        FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("ingest/v1/ingest.proto".to_string()),
+                package: Some("ingest.v1".to_string()),
+                syntax: Some("proto3".to_string()),
+                message_type: vec![
+                    message(
+                        "Record",
+                        vec![
+                            field("id", 1, Type::Int64, None),
+                            field("name", 2, Type::String, None),
+                        ],
+                    ),
+                    message("IngestRequest", vec![records]),
+                    message(
+                        "IngestResponse",
+                        vec![field("accepted", 1, Type::Uint32, None)],
+                    ),
+                ],
+                service: vec![ServiceDescriptorProto {
+                    name: Some("IngestService".to_string()),
+                    method: vec![
+                        method("Ingest", "IngestRequest"),
+                        method("IngestOne", "Record"),
+                    ],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        }
+    }
+
+    fn config(url: String, method: &str) -> GrpcSinkConfig {
+        GrpcSinkConfig {
+            url,
+            method: method.to_string(),
+            descriptor_set: None,
+            batch_field: None,
+            batch_size: None,
+            deadline_seconds: Some(5),
+            metadata: None,
+            ignore_unknown_fields: false,
+            retry: RetrySettings {
+                retry_attempts: Some(1),
+                retry_delay_seconds: Some(0),
+                retry_jitter: Some(0.0),
+                ..Default::default()
+            },
+            retry_on_codes: None,
+            on_batch_failure: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn maps_rows_with_a_descriptor_set_file() {
+        let path = std::env::temp_dir().join(format!("yetii-grpc-{}.pb", std::process::id()));
+        std::fs::write(&path, descriptor_set().encode_to_vec()).unwrap();
+        let mut config = config(
+            "http://127.0.0.1:1".to_string(),
+            "ingest.v1.IngestService/Ingest",
+        );
+        config.descriptor_set = Some(path.display().to_string());
+        config.batch_field = Some("records".to_string());
+        let sink = GrpcSink::connect(&config).await.unwrap();
+
+        let message = sink
+            .to_message(&serde_json::json!({ "records": [{ "id": "7", "name": "Ada" }] }))
+            .unwrap();
+        assert_eq!(
+            serde_json::json!({ "records": [{ "id": "7", "name": "Ada" }] }),
+            serde_json::to_value(&message).unwrap()
+        );
+        assert!(matches!(
+            sink.to_message(&serde_json::json!({ "records": [{ "id": 1, "email": "x" }] })),
+            Err(GrpcError::Mapping { .. })
+        ));
+
+        config.ignore_unknown_fields = true;
+        let lenient = GrpcSink::connect(&config).await.unwrap();
+        assert!(
+            lenient
+                .to_message(&serde_json::json!({ "records": [{ "id": 1, "email": "x" }] }))
+                .is_ok()
+        );
+
+        config.batch_field = Some("missing".to_string());
+        assert!(matches!(
+            GrpcSink::connect(&config).await,
+            Err(GrpcError::InvalidBatchField { .. })
+        ));
+    }
+
+    #[test]
+    fn parses_retry_codes_by_name() {
+        let base = "url: http://127.0.0.1:1\nmethod: ingest.v1.IngestService/Ingest\n";
+        let config: GrpcSinkConfig = serde_yaml::from_str(&format!(
+            "{base}retry_on_codes: [DEADLINE_EXCEEDED, aborted]\n"
+        ))
+        .unwrap();
+        assert_eq!(
+            vec![Code::DeadlineExceeded, Code::Aborted],
+            config
+                .retry_on_codes
+                .unwrap()
+                .into_iter()
+                .map(status_code)
+                .collect::<Vec<_>>()
+        );
+
+        let error =
+            serde_yaml::from_str::<GrpcSinkConfig>(&format!("{base}retry_on_codes: [teapot]\n"))
+                .unwrap_err();
+        assert!(
+            error.to_string().contains("unknown variant `teapot`"),
+            "{error}"
+        );
+    }
+
+    type Received = Arc<Mutex<Vec<(String, Value, Option<String>)>>>;
+
+    /// Minimal `IngestService` that fails its first call with `UNAVAILABLE`.
+    #[derive(Clone)]
+    struct IngestServer {
+        pool: DescriptorPool,
+        received: Received,
+        calls: Arc<Mutex<usize>>,
+    }
+
+    impl NamedService for IngestServer {
+        const NAME: &'static str = "ingest.v1.IngestService";
+    }
+
+    impl Service<http::Request<tonic::body::Body>> for IngestServer {
+        type Response = http::Response<tonic::body::Body>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<tonic::body::Body>) -> Self::Future {
+            let name = request.uri().path().rsplit('/').next().unwrap().to_string();
+            let method = self
+                .pool
+                .get_service_by_name(Self::NAME)
+                .unwrap()
+                .methods()
+                .find(|method| method.name() == name)
+                .unwrap();
+            let handler = IngestHandler {
+                name,
+                response: method.output(),
+                received: self.received.clone(),
+                calls: self.calls.clone(),
+            };
+            Box::pin(async move {
+                Ok(tonic::server::Grpc::new(DynamicCodec::new(method.input()))
+                    .unary(handler, request)
+                    .await)
+            })
+        }
+    }
+
+    struct IngestHandler {
+        name: String,
+        response: MessageDescriptor,
+        received: Received,
+        calls: Arc<Mutex<usize>>,
+    }
+
+    impl Service<tonic::Request<DynamicMessage>> for IngestHandler {
+        type Response = tonic::Response<DynamicMessage>;
+        type Error = Status;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: tonic::Request<DynamicMessage>) -> Self::Future {
+            let mut calls = self.calls.lock().unwrap();
+            *calls += 1;
+            if *calls == 1 {
+                return Box::pin(async { Err(Status::unavailable("warming up")) });
+            }
+            let tenant = request
+                .metadata()
+                .get("x-tenant")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            self.received.lock().unwrap().push((
+                self.name.clone(),
+                serde_json::to_value(request.get_ref()).unwrap(),
+                tenant,
+            ));
+            let response = DynamicMessage::new(self.response.clone());
+            Box::pin(async move { Ok(tonic::Response::new(response)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn delivers_batches_using_server_reflection() {
+        let descriptors = descriptor_set();
+        let reflection = tonic_reflection::server::Builder::configure()
+            .register_file_descriptor_set(descriptors.clone())
+            .build_v1()
+            .unwrap();
+        let server = IngestServer {
+            pool: DescriptorPool::from_file_descriptor_set(descriptors).unwrap(),
+            received: Received::default(),
+            calls: Arc::default(),
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(reflection)
+                .add_service(server.clone())
+                .serve_with_incoming(tonic::transport::server::TcpIncoming::from(listener)),
+        );
+
+        let mut config = config(
+            format!("http://{address}"),
+            "ingest.v1.IngestService/Ingest",
+        );
+        config.batch_field = Some("records".to_string());
+        config.metadata = Some(HashMap::from([(
+            "X-Tenant".to_string(),
+            "acme".to_string(),
+        )]));
+        let sink = GrpcSink::connect(&config).await.unwrap();
+        let rows = vec![
+            serde_json::json!({ "id": 1, "name": "Ada" }),
+            serde_json::json!({ "id": 2, "name": "Grace" }),
+        ];
+        assert_eq!(1, sink.send(&rows).await.unwrap());
+
+        config.method = "ingest.v1.IngestService/IngestOne".to_string();
+        config.batch_field = None;
+        let per_row = GrpcSink::connect(&config).await.unwrap();
+        assert_eq!(2, per_row.send(&rows).await.unwrap());
+
+        let received = server.received.lock().unwrap().clone();
+        assert_eq!(
+            vec![
+                (
+                    "Ingest".to_string(),
+                    serde_json::json!({ "records": [
+                        { "id": "1", "name": "Ada" },
+                        { "id": "2", "name": "Grace" },
+                    ] }),
+                    Some("acme".to_string()),
+                ),
+                (
+                    "IngestOne".to_string(),
+                    serde_json::json!({ "id": "1", "name": "Ada" }),
+                    Some("acme".to_string()),
+                ),
+                (
+                    "IngestOne".to_string(),
+                    serde_json::json!({ "id": "2", "name": "Grace" }),
+                    Some("acme".to_string()),
+                ),
+            ],
+            received
+        );
+        assert_eq!(4, *server.calls.lock().unwrap());
+    }
+}
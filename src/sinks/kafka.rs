@@ -0,0 +1,413 @@
+use crate::config::destination_config::{
+    KafkaAcks, KafkaCompression, KafkaMessageMode, KafkaSaslMechanism, KafkaSinkConfig,
+};
+use crate::http::format::{BodyLayout, FormatError, PayloadFormat};
+use crate::http::retry::RetryPolicy;
+use crate::sinks::with_retries;
+use rdkafka::ClientConfig;
+use rdkafka::error::{KafkaError as ClientError, RDKafkaErrorCode};
+use rdkafka::message::{Header, OwnedHeaders};
+use rdkafka::producer::{DeliveryFuture, FutureProducer, FutureRecord};
+use serde_json::Value;
+use std::time::Duration;
+
+/// How long to wait for room in the producer's queue before trying to enqueue again.
+const QUEUE_FULL_BACKOFF: Duration = Duration::from_millis(50);
+
+#[derive(Debug, thiserror::Error)]
+pub enum KafkaError {
+    #[error("invalid Kafka producer configuration: {0}")]
+    Client(ClientError),
+    #[error(transparent)]
+    Format(#[from] FormatError),
+    #[error("Kafka produce failed: {0}")]
+    Produce(ClientError),
+    #[error("Kafka producer stopped before the broker acknowledged the batch")]
+    Cancelled,
+}
+
+impl KafkaError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            KafkaError::Produce(error) => matches!(
+                error.rdkafka_error_code(),
+                Some(
+                    RDKafkaErrorCode::MessageTimedOut
+                        | RDKafkaErrorCode::AllBrokersDown
+                        | RDKafkaErrorCode::BrokerTransportFailure
+                        | RDKafkaErrorCode::UnknownTopicOrPartition
+                        | RDKafkaErrorCode::LeaderNotAvailable
+                        | RDKafkaErrorCode::NotLeaderForPartition
+                        | RDKafkaErrorCode::RequestTimedOut
+                        | RDKafkaErrorCode::NetworkException
+                        | RDKafkaErrorCode::NotEnoughReplicas
+                        | RDKafkaErrorCode::NotEnoughReplicasAfterAppend
+                        | RDKafkaErrorCode::KafkaStorageError
+                )
+            ),
+            KafkaError::Cancelled => true,
+            KafkaError::Client(_) | KafkaError::Format(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Record {
+    key: Option<Vec<u8>>,
+    value: Vec<u8>,
+}
+
+/// Turns delivered rows into Kafka messages: one JSON message per row, or one encoded
+/// message per batch.
+struct Messages {
+    mode: KafkaMessageMode,
+    key_field: Option<String>,
+    format: PayloadFormat,
+    layout: BodyLayout,
+}
+
+impl Messages {
+    fn new(config: &KafkaSinkConfig, leading_columns: Vec<String>) -> Result<Self, KafkaError> {
+        Ok(Self {
+            mode: config.mode,
+            key_field: config.key_field.clone(),
+            format: config.body.format,
+            layout: BodyLayout {
+                leading_columns,
+                ..config.body.layout()?
+            },
+        })
+    }
+
+    fn records(&self, rows: &[Value]) -> Result<Vec<Record>, KafkaError> {
+        match self.mode {
+            KafkaMessageMode::Row => Ok(rows
+                .iter()
+                .map(|row| Record {
+                    key: self
+                        .key_field
+                        .as_ref()
+                        .and_then(|field| match row.get(field) {
+                            None | Some(Value::Null) => None,
+                            Some(Value::String(key)) => Some(key.clone().into_bytes()),
+                            Some(key) => Some(key.to_string().into_bytes()),
+                        }),
+                    value: row.to_string().into_bytes(),
+                })
+                .collect()),
+            KafkaMessageMode::Batch => {
+                let body = self
+                    .format
+                    .encode(&Value::Array(rows.to_vec()), &self.layout)?;
+                Ok(vec![Record {
+                    key: None,
+                    value: body.bytes,
+                }])
+            }
+        }
+    }
+}
+
+/// Produces to a Kafka topic through librdkafka.
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+    messages: Messages,
+    headers: Vec<(String, String)>,
+    retry_policy: RetryPolicy,
+}
+
+impl KafkaSink {
+    /// `leading_columns` orders `csv` and `xml` batch messages; see [`BodyLayout`]. The
+    /// brokers are contacted by the first batch.
+    pub fn new(
+        config: &KafkaSinkConfig,
+        query_name: &str,
+        leading_columns: Vec<String>,
+    ) -> Result<Self, KafkaError> {
+        let mut headers = config
+            .headers
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<Vec<_>>();
+        headers.sort();
+
+        Ok(Self {
+            producer: client_config(config, query_name)
+                .create()
+                .map_err(KafkaError::Client)?,
+            topic: config.topic.clone(),
+            messages: Messages::new(config, leading_columns)?,
+            headers,
+            retry_policy: RetryPolicy::from_settings(&config.retry),
+        })
+    }
+
+    /// Publishes the batch and waits for the configured acknowledgement; returns the number of
+    /// messages written. A retried batch resends every message, including those already
+    /// acknowledged.
+    pub async fn send(&self, rows: &[Value]) -> Result<usize, KafkaError> {
+        let records = self.messages.records(rows)?;
+        with_retries(
+            &self.retry_policy,
+            "Kafka produce",
+            || self.produce(&records),
+            |error: &KafkaError| error.is_retryable().then_some(None),
+        )
+        .await?;
+        Ok(records.len())
+    }
+
+    async fn produce(&self, records: &[Record]) -> Result<(), KafkaError> {
+        let mut deliveries = Vec::with_capacity(records.len());
+        for record in records {
+            deliveries.push(self.enqueue(record).await?);
+        }
+        for delivery in deliveries {
+            delivery
+                .await
+                .map_err(|_| KafkaError::Cancelled)?
+                .map_err(|(error, _)| KafkaError::Produce(error))?;
+        }
+        Ok(())
+    }
+
+    /// Hands one message to the producer, waiting while its local queue is full.
+    async fn enqueue(&self, record: &Record) -> Result<DeliveryFuture, KafkaError> {
+        let mut message = FutureRecord::to(&self.topic)
+            .payload(&record.value)
+            .headers(self.headers.iter().fold(
+                OwnedHeaders::new_with_capacity(self.headers.len()),
+                |headers, (key, value)| {
+                    headers.insert(Header {
+                        key,
+                        value: Some(value),
+                    })
+                },
+            ));
+        if let Some(key) = &record.key {
+            message = message.key(key);
+        }
+        loop {
+            match self.producer.send_result(message) {
+                Ok(delivery) => return Ok(delivery),
+                Err((ClientError::MessageProduction(RDKafkaErrorCode::QueueFull), returned)) => {
+                    message = returned;
+                    tokio::time::sleep(QUEUE_FULL_BACKOFF).await;
+                }
+                Err((error, _)) => return Err(KafkaError::Produce(error)),
+            }
+        }
+    }
+}
+
+/// librdkafka producer properties for the destination.
+fn client_config(config: &KafkaSinkConfig, query_name: &str) -> ClientConfig {
+    let timeout_ms = (config.timeout_seconds.unwrap_or(30) as u64 * 1000).to_string();
+    let mut client = ClientConfig::new();
+    client
+        .set("bootstrap.servers", config.brokers.join(","))
+        .set(
+            "client.id",
+            config
+                .client_id
+                .clone()
+                .unwrap_or_else(|| format!("yetii-{query_name}")),
+        )
+        // Keyed messages land on the same partition as with the Java client.
+        .set("partitioner", "murmur2_random")
+        .set("socket.connection.setup.timeout.ms", &timeout_ms)
+        .set("request.timeout.ms", &timeout_ms)
+        .set("message.timeout.ms", &timeout_ms)
+        .set(
+            "compression.type",
+            match config.compression {
+                KafkaCompression::None => "none",
+                KafkaCompression::Gzip => "gzip",
+                KafkaCompression::Snappy => "snappy",
+                KafkaCompression::Lz4 => "lz4",
+                KafkaCompression::Zstd => "zstd",
+            },
+        );
+    match config.acks {
+        // Idempotence keeps librdkafka's own retries from writing a message twice.
+        KafkaAcks::All => client.set("acks", "all").set("enable.idempotence", "true"),
+        KafkaAcks::Leader => client.set("acks", "1"),
+    };
+
+    client.set(
+        "security.protocol",
+        match (config.tls.is_some(), config.sasl.is_some()) {
+            (false, false) => "plaintext",
+            (true, false) => "ssl",
+            (false, true) => "sasl_plaintext",
+            (true, true) => "sasl_ssl",
+        },
+    );
+    if let Some(tls) = &config.tls {
+        for (property, path) in [
+            ("ssl.ca.location", &tls.ca_file),
+            ("ssl.certificate.location", &tls.client_cert_file),
+            ("ssl.key.location", &tls.client_key_file),
+        ] {
+            if let Some(path) = path {
+                client.set(property, path);
+            }
+        }
+    }
+    if let Some(sasl) = &config.sasl {
+        client
+            .set(
+                "sasl.mechanism",
+                match sasl.mechanism {
+                    KafkaSaslMechanism::Plain => "PLAIN",
+                    KafkaSaslMechanism::ScramSha256 => "SCRAM-SHA-256",
+                    KafkaSaslMechanism::ScramSha512 => "SCRAM-SHA-512",
+                },
+            )
+            .set("sasl.username", &sasl.username)
+            .set("sasl.password", sasl.password.expose());
+    }
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config(yaml: &str) -> KafkaSinkConfig {
+        serde_yaml::from_str(&format!("brokers: [\"kafka:9092\"]\ntopic: orders\n{yaml}")).unwrap()
+    }
+
+    #[test]
+    fn keys_row_messages_by_the_key_field() {
+        let messages = Messages::new(&config("key_field: id"), Vec::new()).unwrap();
+
+        let records = messages
+            .records(&[
+                json!({"id": 7, "total": 1.5}),
+                json!({"id": "A-1"}),
+                json!({"id": null}),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                Record {
+                    key: Some(b"7".to_vec()),
+                    value: br#"{"id":7,"total":1.5}"#.to_vec(),
+                },
+                Record {
+                    key: Some(b"A-1".to_vec()),
+                    value: br#"{"id":"A-1"}"#.to_vec(),
+                },
+                Record {
+                    key: None,
+                    value: br#"{"id":null}"#.to_vec(),
+                },
+            ],
+            records
+        );
+    }
+
+    #[test]
+    fn encodes_a_batch_as_one_message() {
+        let messages =
+            Messages::new(&config("mode: batch\nformat: csv"), vec!["id".to_string()]).unwrap();
+
+        let records = messages
+            .records(&[json!({"total": 1.5, "id": 7}), json!({"total": 2, "id": 8})])
+            .unwrap();
+
+        assert_eq!(1, records.len());
+        assert_eq!(None, records[0].key);
+        assert_eq!(
+            "id,total\n7,1.5\n8,2\n",
+            String::from_utf8_lossy(&records[0].value)
+        );
+    }
+
+    #[test]
+    fn maps_tls_sasl_and_acks_to_producer_properties() {
+        let client = client_config(
+            &config(
+                "acks: leader\ncompression: zstd\ntls:\n  ca_file: /etc/ca.pem\nsasl:\n  mechanism: scram_sha512\n  username: yetii\n  password: secret\n",
+            ),
+            "orders",
+        );
+
+        assert_eq!(Some("sasl_ssl"), client.get("security.protocol"));
+        assert_eq!(Some("SCRAM-SHA-512"), client.get("sasl.mechanism"));
+        assert_eq!(Some("/etc/ca.pem"), client.get("ssl.ca.location"));
+        assert_eq!(Some("1"), client.get("acks"));
+        assert_eq!(None, client.get("enable.idempotence"));
+        assert_eq!(Some("zstd"), client.get("compression.type"));
+        assert_eq!(Some("yetii-orders"), client.get("client.id"));
+
+        let client = client_config(&config(""), "orders");
+        assert_eq!(Some("plaintext"), client.get("security.protocol"));
+        assert_eq!(Some("true"), client.get("enable.idempotence"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn produces_keyed_rows_with_headers() {
+        use rdkafka::Message;
+        use rdkafka::consumer::{BaseConsumer, Consumer};
+        use rdkafka::message::Headers;
+        use rdkafka::mocking::MockCluster;
+        use rdkafka::{Offset, TopicPartitionList};
+
+        let cluster = MockCluster::new(1).unwrap();
+        cluster.create_topic("orders", 1, 1).unwrap();
+        let sink = KafkaSink::new(
+            &serde_yaml::from_str(&format!(
+                "brokers: [\"{}\"]\ntopic: orders\nkey_field: id\nheaders:\n  source: yetii\n",
+                cluster.bootstrap_servers()
+            ))
+            .unwrap(),
+            "orders",
+            Vec::new(),
+        )
+        .unwrap();
+
+        let sent = sink
+            .send(&[json!({"id": 7, "total": 1.5}), json!({"id": 8})])
+            .await
+            .unwrap();
+        assert_eq!(2, sent);
+
+        let consumer: BaseConsumer = ClientConfig::new()
+            .set("bootstrap.servers", cluster.bootstrap_servers())
+            .set("group.id", "yetii-test")
+            .create()
+            .unwrap();
+        let mut partitions = TopicPartitionList::new();
+        partitions
+            .add_partition_offset("orders", 0, Offset::Beginning)
+            .unwrap();
+        consumer.assign(&partitions).unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        let mut received = Vec::new();
+        while received.len() < 2 && std::time::Instant::now() < deadline {
+            let Some(message) = consumer.poll(Duration::from_millis(100)) else {
+                continue;
+            };
+            let message = message.unwrap();
+            let header = message.headers().unwrap().get(0);
+            assert_eq!(("source", Some(&b"yetii"[..])), (header.key, header.value));
+            received.push((
+                message.key().unwrap().to_vec(),
+                message.payload().unwrap().to_vec(),
+            ));
+        }
+        assert_eq!(
+            vec![
+                (b"7".to_vec(), br#"{"id":7,"total":1.5}"#.to_vec()),
+                (b"8".to_vec(), br#"{"id":8}"#.to_vec()),
+            ],
+            received
+        );
+    }
+}
@@ -0,0 +1,460 @@
+pub mod amqp;
+pub mod database;
+pub mod file;
+pub mod ftp;
+pub mod grpc;
+pub mod kafka;
+pub mod manifest;
+pub mod mqtt;
+pub mod redis;
+pub mod s3;
+pub mod sftp;
+pub mod tls;
+
+use crate::config::database::DatabaseConfigs;
+use crate::config::destination_config::DestinationConfig;
+use crate::config::endpoint_config::EndpointConfig;
+use crate::config::query_config::QueryConfig;
+use crate::config::request_config::BatchFailurePolicy;
+use crate::http::retry::RetryPolicy;
+use crate::http::{DuplicateOutcome, HttpError, HttpSender};
+use chrono::Utc;
+use serde_json::{Map, Value};
+use std::fmt::Display;
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SinkError {
+    #[error("query '{0}' has neither an endpoint nor a destination")]
+    MissingTarget(String),
+    #[error(transparent)]
+    Http(#[from] HttpError),
+    #[error(transparent)]
+    Grpc(#[from] grpc::GrpcError),
+    #[error(transparent)]
+    Redis(#[from] redis::RedisSinkError),
+    #[error(transparent)]
+    Mqtt(#[from] mqtt::MqttSinkError),
+    #[error(transparent)]
+    Ftp(#[from] ftp::FtpError),
+    #[error(transparent)]
+    File(#[from] file::FileSinkError),
+    #[error(transparent)]
+    S3(#[from] s3::S3Error),
+    #[error(transparent)]
+    Sftp(#[from] sftp::SftpError),
+    #[error(transparent)]
+    Kafka(#[from] kafka::KafkaError),
+    #[error(transparent)]
+    Amqp(#[from] amqp::AmqpError),
+    #[error(transparent)]
+    Database(#[from] database::DatabaseSinkError),
+}
+
+/// Fields renamed by the query's transform, in mapping order.
+fn mapping_targets(query: &QueryConfig) -> Vec<String> {
+    match &query.transform.mappings {
+        Some(mappings) if query.transform.enabled => mappings.values().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Runs `attempt` until it succeeds or `policy`'s retries are spent. `retry` decides whether an
+/// error is transient: `None` fails at once, `Some(retry_after)` retries after the policy's delay
+/// or the server-requested one, whichever is longer.
+pub(crate) async fn with_retries<T, E, Fut>(
+    policy: &RetryPolicy,
+    operation: &str,
+    mut attempt: impl FnMut() -> Fut,
+    retry: impl Fn(&E) -> Option<Option<Duration>>,
+) -> Result<T, E>
+where
+    Fut: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    let mut retry_index = 0;
+    loop {
+        let error = match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+        let retry_after = match retry(&error) {
+            Some(retry_after) if retry_index < policy.max_retries => retry_after,
+            _ => return Err(error),
+        };
+        retry_index += 1;
+        let delay = policy.next_delay(retry_index, retry_after);
+        tracing::warn!(
+            retry = retry_index,
+            max_retries = policy.max_retries,
+            delay_ms = delay.as_millis(),
+            error = %error,
+            "{operation} failed; retrying"
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Renders the `file_name` template of the file-writing destinations.
+pub(crate) fn render_file_name(
+    template: &str,
+    query: &str,
+    sequence: usize,
+) -> Result<String, redis::MissingKeyField> {
+    let now = Utc::now();
+    let placeholders = Map::from_iter([
+        ("query".to_string(), Value::String(query.to_string())),
+        (
+            "date".to_string(),
+            Value::String(now.format("%Y-%m-%d").to_string()),
+        ),
+        (
+            "timestamp".to_string(),
+            Value::String(now.format("%Y%m%dT%H%M%SZ").to_string()),
+        ),
+        ("sequence".to_string(), Value::from(sequence)),
+    ]);
+    redis::render_key(template, &placeholders)
+}
+
+/// Result of one delivered batch.
+pub struct BatchOutcome {
+    /// Short description of the response for logging.
+    pub detail: String,
+    pub duplicate: Option<DuplicateOutcome>,
+}
+
+/// Delivery target of a query: its HTTP `endpoint` or its `destination`.
+pub enum Sink {
+    Http {
+        sender: HttpSender,
+        endpoint: Box<EndpointConfig>,
+    },
+    Grpc {
+        sink: grpc::GrpcSink,
+        batch_size: Option<u32>,
+        on_batch_failure: BatchFailurePolicy,
+    },
+    Redis {
+        sink: redis::RedisSink,
+        batch_size: Option<u32>,
+        on_batch_failure: BatchFailurePolicy,
+    },
+    Mqtt {
+        sink: Box<mqtt::MqttSink>,
+        batch_size: Option<u32>,
+        on_batch_failure: BatchFailurePolicy,
+    },
+    Ftp {
+        sink: Box<ftp::FtpSink>,
+        batch_size: Option<u32>,
+        on_batch_failure: BatchFailurePolicy,
+    },
+    File {
+        sink: Box<file::FileSink>,
+        batch_size: Option<u32>,
+        on_batch_failure: BatchFailurePolicy,
+    },
+    S3 {
+        sink: Box<s3::S3Sink>,
+        batch_size: Option<u32>,
+        on_batch_failure: BatchFailurePolicy,
+    },
+    Sftp {
+        sink: Box<sftp::SftpSink>,
+        batch_size: Option<u32>,
+        on_batch_failure: BatchFailurePolicy,
+    },
+    Kafka {
+        sink: Box<kafka::KafkaSink>,
+        batch_size: Option<u32>,
+        on_batch_failure: BatchFailurePolicy,
+    },
+    Amqp {
+        sink: Box<amqp::AmqpSink>,
+        batch_size: Option<u32>,
+        on_batch_failure: BatchFailurePolicy,
+    },
+    Database {
+        sink: Box<database::DatabaseSink>,
+        batch_size: Option<u32>,
+        on_batch_failure: BatchFailurePolicy,
+    },
+}
+
+impl Sink {
+    /// `databases` resolves the target of a `database` destination.
+    pub async fn for_query(
+        query: &QueryConfig,
+        databases: &DatabaseConfigs,
+    ) -> Result<Self, SinkError> {
+        match (&query.endpoint, &query.destination) {
+            (Some(endpoint), _) => Ok(Sink::Http {
+                sender: HttpSender::new(&endpoint.request)?
+                    .with_leading_columns(mapping_targets(query)),
+                endpoint: Box::new(endpoint.clone()),
+            }),
+            (None, Some(DestinationConfig::Grpc(config))) => Ok(Sink::Grpc {
+                sink: grpc::GrpcSink::connect(config).await?,
+                batch_size: config.batch_size,
+                on_batch_failure: config.on_batch_failure,
+            }),
+            (None, Some(DestinationConfig::Redis(config))) => Ok(Sink::Redis {
+                sink: redis::RedisSink::connect(config).await?,
+                batch_size: config.batch_size,
+                on_batch_failure: config.on_batch_failure,
+            }),
+            (None, Some(DestinationConfig::Mqtt(config))) => Ok(Sink::Mqtt {
+                sink: Box::new(mqtt::MqttSink::new(config, &query.name)?),
+                batch_size: config.batch_size,
+                on_batch_failure: config.on_batch_failure,
+            }),
+            (None, Some(DestinationConfig::Ftp(config))) => Ok(Sink::Ftp {
+                sink: Box::new(ftp::FtpSink::new(
+                    config,
+                    &query.name,
+                    mapping_targets(query),
+                )?),
+                batch_size: config.batch_size,
+                on_batch_failure: config.on_batch_failure,
+            }),
+            (None, Some(DestinationConfig::File(config))) => Ok(Sink::File {
+                sink: Box::new(file::FileSink::new(
+                    config,
+                    &query.name,
+                    mapping_targets(query),
+                )?),
+                batch_size: config.batch_size,
+                on_batch_failure: config.on_batch_failure,
+            }),
+            (None, Some(DestinationConfig::S3(config))) => Ok(Sink::S3 {
+                sink: Box::new(s3::S3Sink::new(
+                    config,
+                    &query.name,
+                    mapping_targets(query),
+                )?),
+                batch_size: config.batch_size,
+                on_batch_failure: config.on_batch_failure,
+            }),
+            (None, Some(DestinationConfig::Sftp(config))) => Ok(Sink::Sftp {
+                sink: Box::new(sftp::SftpSink::new(
+                    config,
+                    &query.name,
+                    mapping_targets(query),
+                )?),
+                batch_size: config.batch_size,
+                on_batch_failure: config.on_batch_failure,
+            }),
+            (None, Some(DestinationConfig::Kafka(config))) => Ok(Sink::Kafka {
+                sink: Box::new(kafka::KafkaSink::new(
+                    config,
+                    &query.name,
+                    mapping_targets(query),
+                )?),
+                batch_size: config.batch_size,
+                on_batch_failure: config.on_batch_failure,
+            }),
+            (None, Some(DestinationConfig::Amqp(config))) => Ok(Sink::Amqp {
+                sink: Box::new(amqp::AmqpSink::new(config)?),
+                batch_size: config.batch_size,
+                on_batch_failure: config.on_batch_failure,
+            }),
+            (None, Some(DestinationConfig::Database(config))) => Ok(Sink::Database {
+                sink: Box::new(database::DatabaseSink::connect(config, databases).await?),
+                batch_size: config.batch_size,
+                on_batch_failure: config.on_batch_failure,
+            }),
+            (None, None) => Err(SinkError::MissingTarget(query.name.clone())),
+        }
+    }
+
+    pub fn batch_size(&self) -> usize {
+        let batch_size = match self {
+            Sink::Http { endpoint, .. } => endpoint.request.batch_size,
+            Sink::Grpc { batch_size, .. }
+            | Sink::Redis { batch_size, .. }
+            | Sink::Mqtt { batch_size, .. }
+            | Sink::Ftp { batch_size, .. }
+            | Sink::File { batch_size, .. }
+            | Sink::S3 { batch_size, .. }
+            | Sink::Sftp { batch_size, .. }
+            | Sink::Kafka { batch_size, .. }
+            | Sink::Amqp { batch_size, .. }
+            | Sink::Database { batch_size, .. } => *batch_size,
+        };
+        batch_size.unwrap_or(100) as usize
+    }
+
+    pub fn on_batch_failure(&self) -> BatchFailurePolicy {
+        match self {
+            Sink::Http { endpoint, .. } => endpoint.request.on_batch_failure,
+            Sink::Grpc {
+                on_batch_failure, ..
+            }
+            | Sink::Redis {
+                on_batch_failure, ..
+            }
+            | Sink::Mqtt {
+                on_batch_failure, ..
+            }
+            | Sink::Ftp {
+                on_batch_failure, ..
+            }
+            | Sink::File {
+                on_batch_failure, ..
+            }
+            | Sink::S3 {
+                on_batch_failure, ..
+            }
+            | Sink::Sftp {
+                on_batch_failure, ..
+            }
+            | Sink::Kafka {
+                on_batch_failure, ..
+            }
+            | Sink::Amqp {
+                on_batch_failure, ..
+            }
+            | Sink::Database {
+                on_batch_failure, ..
+            } => *on_batch_failure,
+        }
+    }
+
+    /// Delivers what the destination writes once per run after the last batch, such as a
+    /// manifest; returns where it went.
+    pub async fn finish(&self) -> Result<Option<String>, SinkError> {
+        match self {
+            Sink::File { sink, .. } => {
+                Ok(sink.finish().await?.map(|path| path.display().to_string()))
+            }
+            Sink::S3 { sink, .. } => Ok(sink.finish().await?),
+            _ => Ok(None),
+        }
+    }
+
+    pub async fn send(&self, rows: &[Value]) -> Result<BatchOutcome, SinkError> {
+        match self {
+            Sink::Http { sender, endpoint } => {
+                let outcome = sender.send(endpoint, rows).await?;
+                Ok(BatchOutcome {
+                    detail: format!("status {}", outcome.status.as_u16()),
+                    duplicate: outcome.duplicate,
+                })
+            }
+            Sink::Grpc { sink, .. } => {
+                let calls = sink.send(rows).await?;
+                Ok(BatchOutcome {
+                    detail: format!("{calls} gRPC call(s)"),
+                    duplicate: None,
+                })
+            }
+            Sink::Redis { sink, .. } => {
+                let written = sink.send(rows).await?;
+                Ok(BatchOutcome {
+                    detail: format!("{written} Redis write(s)"),
+                    duplicate: None,
+                })
+            }
+            Sink::Mqtt { sink, .. } => {
+                let published = sink.send(rows).await?;
+                Ok(BatchOutcome {
+                    detail: format!("{published} MQTT message(s)"),
+                    duplicate: None,
+                })
+            }
+            Sink::Ftp { sink, .. } => {
+                let path = sink.send(rows).await?;
+                Ok(BatchOutcome {
+                    detail: format!("uploaded {path}"),
+                    duplicate: None,
+                })
+            }
+            Sink::File { sink, .. } => {
+                let path = sink.send(rows).await?;
+                Ok(BatchOutcome {
+                    detail: format!("wrote {}", path.display()),
+                    duplicate: None,
+                })
+            }
+            Sink::S3 { sink, .. } => {
+                let location = sink.send(rows).await?;
+                Ok(BatchOutcome {
+                    detail: format!("uploaded {location}"),
+                    duplicate: None,
+                })
+            }
+            Sink::Sftp { sink, .. } => {
+                let path = sink.send(rows).await?;
+                Ok(BatchOutcome {
+                    detail: format!("uploaded {path}"),
+                    duplicate: None,
+                })
+            }
+            Sink::Kafka { sink, .. } => {
+                let published = sink.send(rows).await?;
+                Ok(BatchOutcome {
+                    detail: format!("{published} Kafka message(s)"),
+                    duplicate: None,
+                })
+            }
+            Sink::Amqp { sink, .. } => {
+                let published = sink.send(rows).await?;
+                Ok(BatchOutcome {
+                    detail: format!("{published} AMQP message(s)"),
+                    duplicate: None,
+                })
+            }
+            Sink::Database { sink, .. } => {
+                let written = sink.send(rows).await?;
+                Ok(BatchOutcome {
+                    detail: format!("{written} row(s) written"),
+                    duplicate: None,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::destination_config::RetrySettings;
+    use std::cell::Cell;
+
+    #[tokio::test]
+    async fn retries_transient_errors_until_the_attempts_are_spent() {
+        let policy = RetryPolicy::from_settings(&RetrySettings {
+            retry_attempts: Some(2),
+            retry_delay_seconds: Some(0),
+            ..Default::default()
+        });
+        let attempts = Cell::new(0);
+        let result: Result<(), String> = with_retries(
+            &policy,
+            "test delivery",
+            || {
+                attempts.set(attempts.get() + 1);
+                async { Err("unavailable".to_string()) }
+            },
+            |_: &String| Some(None),
+        )
+        .await;
+        assert_eq!(Err("unavailable".to_string()), result);
+        assert_eq!(3, attempts.get());
+
+        attempts.set(0);
+        let result: Result<(), String> = with_retries(
+            &policy,
+            "test delivery",
+            || {
+                attempts.set(attempts.get() + 1);
+                async { Err("rejected".to_string()) }
+            },
+            |_: &String| None,
+        )
+        .await;
+        assert_eq!(Err("rejected".to_string()), result);
+        assert_eq!(1, attempts.get());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::config::destination_config::MqttSinkConfig;
+use crate::http::retry::RetryPolicy;
+use crate::sinks::redis::{MissingKeyField, render_key};
+use crate::sinks::tls::{TlsConfigError, client_config};
+use crate::sinks::with_retries;
+use rumqttc::{
+    AsyncClient, ConnectionError, Event, EventLoop, MqttOptions, Outgoing, Packet, QoS,
+    TlsConfiguration, Transport,
+};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Largest message the sink will publish; the client library defaults to 10 KiB.
+const MAX_PACKET_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MqttSinkError {
+    #[error("invalid MQTT URL: {0}")]
+    InvalidUrl(String),
+    #[error(transparent)]
+    Tls(#[from] TlsConfigError),
+    #[error(transparent)]
+    Topic(#[from] MissingKeyField),
+    #[error("MQTT connection failed: {0}")]
+    Connection(Box<ConnectionError>),
+    #[error("MQTT client stopped before the batch was queued")]
+    ClientStopped,
+    #[error("MQTT broker did not acknowledge the batch within {}s", .0.as_secs())]
+    Timeout(Duration),
+}
+
+impl MqttSinkError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            MqttSinkError::Timeout(_) | MqttSinkError::ClientStopped => true,
+            MqttSinkError::Connection(error) => matches!(
+                **error,
+                ConnectionError::Io(_)
+                    | ConnectionError::NetworkTimeout
+                    | ConnectionError::FlushTimeout
+                    | ConnectionError::MqttState(_)
+                    | ConnectionError::RequestsDone
+            ),
+            MqttSinkError::InvalidUrl(_) | MqttSinkError::Tls(_) | MqttSinkError::Topic(_) => false,
+        }
+    }
+}
+
+pub struct MqttSink {
+    options: MqttOptions,
+    qos: QoS,
+    retain: bool,
+    topic: String,
+    timeout: Duration,
+    retry_policy: RetryPolicy,
+    session: Mutex<Option<Session>>,
+}
+
+/// Client handle and the event loop that performs its network I/O; the loop only runs while a
+/// batch is being published.
+struct Session {
+    client: AsyncClient,
+    event_loop: EventLoop,
+    last_used: Instant,
+}
+
+impl MqttSink {
+    /// Prepares the client; the broker connection is opened by the first batch.
+    pub fn new(config: &MqttSinkConfig, query_name: &str) -> Result<Self, MqttSinkError> {
+        let url = url::Url::parse(&config.url)
+            .map_err(|error| MqttSinkError::InvalidUrl(error.to_string()))?;
+        let tls = url.scheme() == "mqtts";
+        let host = url
+            .host_str()
+            .ok_or_else(|| MqttSinkError::InvalidUrl("missing host".to_string()))?;
+        let port = url.port().unwrap_or(if tls { 8883 } else { 1883 });
+        let client_id = config
+            .client_id
+            .clone()
+            .unwrap_or_else(|| format!("yetii-{query_name}"));
+
+        let mut options = MqttOptions::new(client_id, host, port);
+        options
+            .set_keep_alive(Duration::from_secs(
+                config.keep_alive_seconds.unwrap_or(30) as u64
+            ))
+            .set_max_packet_size(MAX_PACKET_BYTES, MAX_PACKET_BYTES);
+        if let Some(username) = &config.username {
+            let password = config
+                .password
+                .as_ref()
+                .map(|password| password.expose().as_str());
+            options.set_credentials(username, password.unwrap_or_default());
+        }
+        if tls {
+            let tls_config = client_config(config.tls.as_ref())?;
+            options.set_transport(Transport::tls_with_config(TlsConfiguration::Rustls(
+                Arc::new(tls_config),
+            )));
+        }
+
+        Ok(Self {
+            options,
+            qos: match config.qos.unwrap_or(1) {
+                0 => QoS::AtMostOnce,
+                1 => QoS::AtLeastOnce,
+                _ => QoS::ExactlyOnce,
+            },
+            retain: config.retain,
+            topic: config.topic.clone(),
+            timeout: Duration::from_secs(config.timeout_seconds.unwrap_or(30) as u64),
+            retry_policy: RetryPolicy::from_settings(&config.retry),
+            session: Mutex::new(None),
+        })
+    }
+
+    /// Publishes one message per row and waits until the broker has acknowledged every one
+    /// (for QoS 0, until each has been written); returns the number of messages published.
+    pub async fn send(&self, rows: &[Value]) -> Result<usize, MqttSinkError> {
+        let messages = build_messages(&self.topic, rows)?;
+        with_retries(
+            &self.retry_policy,
+            "MQTT publish",
+            || self.publish_batch(&messages),
+            |error: &MqttSinkError| error.is_retryable().then_some(None),
+        )
+        .await?;
+        Ok(messages.len())
+    }
+
+    async fn publish_batch(&self, messages: &[(String, Vec<u8>)]) -> Result<(), MqttSinkError> {
+        let mut session = self.session.lock().await;
+        // Nothing polls the event loop between batches, so keep-alive pings are not sent and the
+        // broker may already have closed an idle connection.
+        if session
+            .as_ref()
+            .is_some_and(|session| session.last_used.elapsed() >= self.options.keep_alive())
+        {
+            *session = None;
+        }
+        let current = session.get_or_insert_with(|| {
+            let (client, event_loop) = AsyncClient::new(self.options.clone(), 10);
+            Session {
+                client,
+                event_loop,
+                last_used: Instant::now(),
+            }
+        });
+
+        let result = tokio::time::timeout(
+            self.timeout,
+            publish_and_confirm(current, self.qos, self.retain, messages),
+        )
+        .await
+        .unwrap_or(Err(MqttSinkError::Timeout(self.timeout)));
+        match result {
+            Ok(()) => current.last_used = Instant::now(),
+            // A fresh session keeps unacknowledged messages from being replayed next to the retry.
+            Err(_) => *session = None,
+        }
+        result
+    }
+}
+
+async fn publish_and_confirm(
+    session: &mut Session,
+    qos: QoS,
+    retain: bool,
+    messages: &[(String, Vec<u8>)],
+) -> Result<(), MqttSinkError> {
+    let client = &session.client;
+    let event_loop = &mut session.event_loop;
+    let publish = async {
+        for (topic, payload) in messages {
+            client
+                .publish(topic.as_str(), qos, retain, payload.clone())
+                .await
+                .map_err(|_| MqttSinkError::ClientStopped)?;
+        }
+        Ok::<_, MqttSinkError>(())
+    };
+    let confirm = async {
+        let mut confirmed = 0;
+        while confirmed < messages.len() {
+            let event = event_loop
+                .poll()
+                .await
+                .map_err(|error| MqttSinkError::Connection(Box::new(error)))?;
+            confirmed += match (qos, event) {
+                (QoS::AtMostOnce, Event::Outgoing(Outgoing::Publish(_)))
+                | (QoS::AtLeastOnce, Event::Incoming(Packet::PubAck(_)))
+                | (QoS::ExactlyOnce, Event::Incoming(Packet::PubComp(_))) => 1,
+                _ => 0,
+            };
+        }
+        Ok::<_, MqttSinkError>(())
+    };
+    tokio::try_join!(publish, confirm).map(|_| ())
+}
+
+fn build_messages(topic: &str, rows: &[Value]) -> Result<Vec<(String, Vec<u8>)>, MissingKeyField> {
+    let empty = Map::new();
+    rows.iter()
+        .map(|row| {
+            let topic = render_key(topic, row.as_object().unwrap_or(&empty))?;
+            Ok((topic, row.to_string().into_bytes()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+    use rumqttc::{ConnAck, ConnectReturnCode, PubAck};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn publishes_rows_to_templated_topics_and_waits_for_acks() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let broker = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = BytesMut::new();
+            let mut published = Vec::new();
+            while published.len() < 2 {
+                let packet = match Packet::read(&mut buffer, MAX_PACKET_BYTES) {
+                    Ok(packet) => packet,
+                    Err(_) => {
+                        stream.read_buf(&mut buffer).await.unwrap();
+                        continue;
+                    }
+                };
+                let mut reply = BytesMut::new();
+                match packet {
+                    Packet::Connect(_) => {
+                        ConnAck::new(ConnectReturnCode::Success, false)
+                            .write(&mut reply)
+                            .unwrap();
+                    }
+                    Packet::Publish(publish) => {
+                        PubAck::new(publish.pkid).write(&mut reply).unwrap();
+                        published.push((
+                            publish.topic,
+                            publish.qos,
+                            publish.retain,
+                            String::from_utf8(publish.payload.to_vec()).unwrap(),
+                        ));
+                    }
+                    _ => {}
+                }
+                stream.write_all(&reply).await.unwrap();
+            }
+            // The stream is returned so the connection stays open until the last ack is read.
+            (published, stream)
+        });
+
+        let config: MqttSinkConfig = serde_yaml::from_str(&format!(
+            "url: mqtt://{address}\ntopic: plant/{{{{line}}}}/status\nretain: true\n"
+        ))
+        .unwrap();
+        config.validate("machines").unwrap();
+        let sink = MqttSink::new(&config, "machines").unwrap();
+
+        let rows = [
+            serde_json::json!({ "line": "a", "state": "running" }),
+            serde_json::json!({ "line": "b", "state": "stopped" }),
+        ];
+        assert_eq!(2, sink.send(&rows).await.unwrap());
+        assert_eq!(
+            vec![
+                (
+                    "plant/a/status".to_string(),
+                    QoS::AtLeastOnce,
+                    true,
+                    r#"{"line":"a","state":"running"}"#.to_string()
+                ),
+                (
+                    "plant/b/status".to_string(),
+                    QoS::AtLeastOnce,
+                    true,
+                    r#"{"line":"b","state":"stopped"}"#.to_string()
+                ),
+            ],
+            broker.await.unwrap().0
+        );
+        assert!(
+            build_messages("plant/{{line}}", &[serde_json::json!({ "state": "idle" })]).is_err()
+        );
+    }
+}
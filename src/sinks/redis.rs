@@ -0,0 +1,265 @@
+use crate::config::destination_config::{RedisSinkConfig, RedisWriteMode};
+use crate::http::retry::RetryPolicy;
+use crate::sinks::with_retries;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::streams::StreamMaxlen;
+use redis::{Pipeline, RedisError, RedisResult};
+use serde_json::{Map, Value};
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RedisSinkError {
+    #[error("failed to connect to Redis: {0}")]
+    Connect(RedisError),
+    #[error(transparent)]
+    Key(#[from] MissingKeyField),
+    #[error("Redis write failed: {0}")]
+    Command(RedisError),
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("key template '{template}' references missing or null field '{field}'")]
+pub struct MissingKeyField {
+    pub template: String,
+    pub field: String,
+}
+
+/// Opens a connection that reconnects in the background after it is dropped.
+pub(crate) async fn connect(url: &str, timeout: Duration) -> RedisResult<ConnectionManager> {
+    redis::Client::open(url)?
+        .get_connection_manager_with_config(
+            ConnectionManagerConfig::new()
+                .set_connection_timeout(timeout)
+                .set_response_timeout(timeout),
+        )
+        .await
+}
+
+/// Returns the `{{field}}` placeholders of a key template, or `None` if one is unterminated.
+pub(crate) fn template_fields(template: &str) -> Option<Vec<&str>> {
+    let mut fields = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after_start = &rest[start + 2..];
+        let end = after_start.find("}}")?;
+        fields.push(after_start[..end].trim());
+        rest = &after_start[end + 2..];
+    }
+    Some(fields)
+}
+
+/// Replaces each `{{field}}` in `template` with the row's value; null and absent fields are errors.
+pub(crate) fn render_key(
+    template: &str,
+    row: &Map<String, Value>,
+) -> Result<String, MissingKeyField> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after_start = &rest[start + 2..];
+        let Some(end) = after_start.find("}}") else {
+            break;
+        };
+        let field = after_start[..end].trim();
+        match row.get(field) {
+            Some(value) if !value.is_null() => output.push_str(&value_as_string(value)),
+            _ => {
+                return Err(MissingKeyField {
+                    template: template.to_string(),
+                    field: field.to_string(),
+                });
+            }
+        }
+        rest = &after_start[end + 2..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+pub(crate) fn value_as_string(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        other => other.to_string(),
+    }
+}
+
+pub struct RedisSink {
+    connection: ConnectionManager,
+    config: RedisSinkConfig,
+    retry_policy: RetryPolicy,
+}
+
+impl RedisSink {
+    pub async fn connect(config: &RedisSinkConfig) -> Result<Self, RedisSinkError> {
+        let timeout = Duration::from_secs(config.timeout_seconds.unwrap_or(30) as u64);
+        Ok(Self {
+            connection: connect(config.url.expose(), timeout)
+                .await
+                .map_err(RedisSinkError::Connect)?,
+            config: config.clone(),
+            retry_policy: RetryPolicy::from_settings(&config.retry),
+        })
+    }
+
+    /// Writes the batch in one atomic pipeline; returns the number of rows written.
+    pub async fn send(&self, rows: &[Value]) -> Result<usize, RedisSinkError> {
+        let pipeline = build_pipeline(&self.config, rows)?;
+        with_retries(
+            &self.retry_policy,
+            "Redis write",
+            || {
+                let mut connection = self.connection.clone();
+                let pipeline = &pipeline;
+                async move { pipeline.query_async::<()>(&mut connection).await }
+            },
+            |error: &RedisError| {
+                (error.is_io_error() || error.is_timeout() || error.is_connection_dropped())
+                    .then_some(None)
+            },
+        )
+        .await
+        .map_err(RedisSinkError::Command)?;
+        Ok(rows.len())
+    }
+}
+
+fn build_pipeline(config: &RedisSinkConfig, rows: &[Value]) -> Result<Pipeline, MissingKeyField> {
+    let mut pipeline = redis::pipe();
+    pipeline.atomic();
+    let empty = Map::new();
+    for row in rows {
+        let object = row.as_object().unwrap_or(&empty);
+        let key = render_key(&config.key, object)?;
+        let fields = object
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(name, value)| (name.as_str(), value_as_string(value)))
+            .collect::<Vec<_>>();
+        match config.mode {
+            RedisWriteMode::Hash => {
+                pipeline.del(&key).ignore();
+                if !fields.is_empty() {
+                    pipeline.cmd("HSET").arg(&key).arg(&fields).ignore();
+                }
+                if let Some(ttl) = config.ttl_seconds {
+                    pipeline.expire(&key, ttl as i64).ignore();
+                }
+            }
+            RedisWriteMode::Json => {
+                match config.ttl_seconds {
+                    Some(ttl) => pipeline.set_ex(&key, row.to_string(), ttl),
+                    None => pipeline.set(&key, row.to_string()),
+                }
+                .ignore();
+            }
+            // XADD needs at least one field, so rows whose fields are all null are not appended.
+            RedisWriteMode::Stream if fields.is_empty() => {}
+            RedisWriteMode::Stream => {
+                match config.stream_max_len {
+                    Some(max_len) => {
+                        pipeline.xadd_maxlen(&key, StreamMaxlen::Approx(max_len), "*", &fields)
+                    }
+                    None => pipeline.xadd(&key, "*", &fields),
+                }
+                .ignore();
+            }
+            RedisWriteMode::List => {
+                pipeline.rpush(&key, row.to_string()).ignore();
+            }
+        }
+    }
+    Ok(pipeline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(mode: RedisWriteMode, key: &str) -> RedisSinkConfig {
+        RedisSinkConfig {
+            url: "redis://localhost:6379/0".into(),
+            mode,
+            key: key.to_string(),
+            ttl_seconds: None,
+            stream_max_len: None,
+            batch_size: None,
+            timeout_seconds: None,
+            retry: Default::default(),
+            on_batch_failure: Default::default(),
+        }
+    }
+
+    fn commands(pipeline: &Pipeline) -> Vec<String> {
+        pipeline
+            .cmd_iter()
+            .map(|command| {
+                command
+                    .args_iter()
+                    .map(|arg| match arg {
+                        redis::Arg::Simple(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+                        redis::Arg::Cursor => "<cursor>".to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn builds_commands_for_each_write_mode() {
+        let rows = [
+            serde_json::json!({ "id": 7, "name": "Ada", "note": null }),
+            serde_json::json!({ "id": 8, "name": "Grace", "note": "vip" }),
+        ];
+
+        let mut hash = config(RedisWriteMode::Hash, "customer:{{id}}");
+        hash.ttl_seconds = Some(60);
+        assert_eq!(
+            vec![
+                "DEL customer:7",
+                "HSET customer:7 id 7 name Ada",
+                "EXPIRE customer:7 60",
+                "DEL customer:8",
+                "HSET customer:8 id 8 name Grace note vip",
+                "EXPIRE customer:8 60",
+            ],
+            commands(&build_pipeline(&hash, &rows).unwrap())
+        );
+
+        let mut stream = config(RedisWriteMode::Stream, "customers");
+        stream.stream_max_len = Some(1000);
+        assert_eq!(
+            "XADD customers MAXLEN ~ 1000 * id 7 name Ada",
+            commands(&build_pipeline(&stream, &rows[..1]).unwrap())[0]
+        );
+        assert_eq!(
+            vec![r#"SET c:7 {"id":7,"name":"Ada","note":null}"#],
+            commands(
+                &build_pipeline(&config(RedisWriteMode::Json, "c:{{ id }}"), &rows[..1]).unwrap()
+            )
+        );
+        assert_eq!(
+            vec![r#"RPUSH queue {"id":7,"name":"Ada","note":null}"#],
+            commands(&build_pipeline(&config(RedisWriteMode::List, "queue"), &rows[..1]).unwrap())
+        );
+
+        let error = build_pipeline(&config(RedisWriteMode::Hash, "note:{{note}}"), &rows)
+            .err()
+            .unwrap();
+        assert_eq!("note", error.field);
+        assert_eq!(None, template_fields("customer:{{id"));
+    }
+
+    #[test]
+    fn keeps_the_url_out_of_debug_output() {
+        let config: RedisSinkConfig =
+            serde_yaml::from_str("url: redis://:hunter2@cache:6379/0\nkey: \"customer:{{id}}\"\n")
+                .unwrap();
+
+        assert_eq!("redis://:hunter2@cache:6379/0", config.url.expose());
+        let debug = format!("{config:?}");
+        assert!(debug.contains("url: ***"));
+        assert!(!debug.contains("hunter2"));
+    }
+}
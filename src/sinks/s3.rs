@@ -0,0 +1,272 @@
+use crate::config::destination_config::S3SinkConfig;
+use crate::http::format::{BodyLayout, FormatError};
+use crate::http::retry::RetryPolicy;
+use crate::sinks::manifest::{Manifest, ManifestError};
+use crate::sinks::redis::MissingKeyField;
+use crate::sinks::render_file_name;
+use aws_config::BehaviorVersion;
+use aws_config::profile::ProfileFileCredentialsProvider;
+use aws_sdk_s3::Client;
+use aws_sdk_s3::config::retry::RetryConfig;
+use aws_sdk_s3::config::timeout::TimeoutConfig;
+use aws_sdk_s3::config::{Credentials, Region};
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{ServerSideEncryption, StorageClass};
+use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+#[derive(Debug, thiserror::Error)]
+pub enum S3Error {
+    #[error(transparent)]
+    Format(#[from] FormatError),
+    #[error(transparent)]
+    Key(#[from] MissingKeyField),
+    #[error(transparent)]
+    Manifest(#[from] ManifestError),
+    #[error("S3 upload of '{key}' failed: {message}")]
+    Upload { key: String, message: String },
+}
+
+/// Uploads each batch as one object with `PutObject`.
+pub struct S3Sink {
+    config: S3SinkConfig,
+    /// Loaded on the first upload, since resolving credentials may call AWS.
+    client: OnceCell<Client>,
+    query: String,
+    layout: BodyLayout,
+    manifest: Option<Manifest>,
+    sequence: AtomicUsize,
+}
+
+impl S3Sink {
+    /// `leading_columns` orders `csv` and `xml` objects; see [`BodyLayout`].
+    pub fn new(
+        config: &S3SinkConfig,
+        query_name: &str,
+        leading_columns: Vec<String>,
+    ) -> Result<Self, S3Error> {
+        Ok(Self {
+            config: config.clone(),
+            client: OnceCell::new(),
+            query: query_name.to_string(),
+            layout: BodyLayout {
+                leading_columns,
+                ..config.body.layout()?
+            },
+            manifest: config
+                .manifest
+                .as_ref()
+                .map(|manifest| Manifest::new(manifest, query_name))
+                .transpose()?,
+            sequence: AtomicUsize::new(0),
+        })
+    }
+
+    /// Uploads the batch as one object; returns its `s3://` location.
+    pub async fn send(&self, rows: &[Value]) -> Result<String, S3Error> {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        let name = render_file_name(&self.config.file_name, &self.query, sequence)?;
+        let key = self.key(name, sequence)?;
+        let body = self
+            .config
+            .body
+            .format
+            .encode(&Value::Array(rows.to_vec()), &self.layout)?;
+
+        self.put(&key, body.content_type, &body.bytes).await?;
+        if let Some(manifest) = &self.manifest {
+            manifest.record(&key, rows.len(), &body.bytes);
+        }
+        Ok(self.location(&key))
+    }
+
+    /// Uploads `bytes` as the next object named by `file_name`, for content that is not a batch
+    /// of rows; returns its `s3://` location.
+    pub async fn upload(&self, content_type: &str, bytes: &[u8]) -> Result<String, S3Error> {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        let name = render_file_name(&self.config.file_name, &self.query, sequence)?;
+        let key = self.key(name, sequence)?;
+        self.put(&key, content_type, bytes).await?;
+        Ok(self.location(&key))
+    }
+
+    /// Uploads the manifest of the objects sent so far, after its signature; `None` without a
+    /// manifest or when no object was uploaded.
+    pub async fn finish(&self) -> Result<Option<String>, S3Error> {
+        let Some(manifest) = &self.manifest else {
+            return Ok(None);
+        };
+        let Some(document) = manifest.render()? else {
+            return Ok(None);
+        };
+        let sequence = manifest.file_count();
+        if let Some((name, signature)) = document.signature {
+            let key = self.key(name, sequence)?;
+            self.put(&key, "application/octet-stream", &signature)
+                .await?;
+        }
+        let key = self.key(document.name, sequence)?;
+        self.put(&key, "application/json", &document.body).await?;
+        Ok(Some(self.location(&key)))
+    }
+
+    /// `name` under the rendered `prefix`.
+    fn key(&self, name: String, sequence: usize) -> Result<String, S3Error> {
+        Ok(match &self.config.prefix {
+            Some(prefix) => {
+                let prefix = render_file_name(prefix, &self.query, sequence)?;
+                format!("{}/{name}", prefix.trim_matches('/'))
+            }
+            None => name,
+        })
+    }
+
+    fn location(&self, key: &str) -> String {
+        format!("s3://{}/{key}", self.config.bucket)
+    }
+
+    /// `PutObject`; the SDK retries throttling and transient failures. The SDK's futures are
+    /// boxed, as they are too deeply nested to be inlined into every sink's future.
+    async fn put(&self, key: &str, content_type: &str, bytes: &[u8]) -> Result<(), S3Error> {
+        let client = self
+            .client
+            .get_or_init(|| Box::pin(s3_client(&self.config)))
+            .await;
+        let request = client
+            .put_object()
+            .bucket(&self.config.bucket)
+            .key(key)
+            .content_type(content_type)
+            .body(ByteStream::from(bytes.to_vec()))
+            .set_server_side_encryption(
+                self.config
+                    .server_side_encryption
+                    .map(|encryption| ServerSideEncryption::from(encryption.header_value())),
+            )
+            .set_ssekms_key_id(self.config.kms_key_id.clone())
+            .set_storage_class(self.config.storage_class.as_deref().map(StorageClass::from))
+            .send();
+        Box::pin(request).await.map_err(|error| S3Error::Upload {
+            key: key.to_string(),
+            message: DisplayErrorContext(&error).to_string(),
+        })?;
+        Ok(())
+    }
+}
+
+/// Credentials come from the inline keys, then the named profile, then the default AWS chain.
+/// Custom endpoints use path-style addressing unless `path_style: false`.
+async fn s3_client(config: &S3SinkConfig) -> Client {
+    let retry = RetryPolicy::from_settings(&config.retry);
+    let mut loader = aws_config::defaults(BehaviorVersion::latest())
+        .region(Region::new(config.region.clone()))
+        .retry_config(
+            RetryConfig::standard()
+                .with_max_attempts(retry.max_retries + 1)
+                .with_initial_backoff(retry.delay)
+                .with_max_backoff(retry.max_delay),
+        )
+        .timeout_config(
+            TimeoutConfig::builder()
+                .operation_attempt_timeout(Duration::from_secs(
+                    config.timeout_seconds.unwrap_or(30) as u64,
+                ))
+                .build(),
+        );
+    if let (Some(access_key_id), Some(secret_access_key)) =
+        (&config.access_key_id, &config.secret_access_key)
+    {
+        loader = loader.credentials_provider(Credentials::new(
+            access_key_id,
+            secret_access_key.expose(),
+            config
+                .session_token
+                .as_ref()
+                .map(|token| token.expose().clone()),
+            None,
+            "s3 destination",
+        ));
+    } else if let Some(profile) = &config.profile {
+        loader = loader.credentials_provider(
+            ProfileFileCredentialsProvider::builder()
+                .profile_name(profile)
+                .build(),
+        );
+    }
+
+    let mut builder = aws_sdk_s3::config::Builder::from(&loader.load().await)
+        .force_path_style(config.path_style.unwrap_or(config.endpoint.is_some()));
+    if let Some(endpoint) = &config.endpoint {
+        builder = builder.endpoint_url(endpoint);
+    }
+    Client::from_conf(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// The request head and body, read up to `Content-Length`.
+    async fn read_request(stream: &mut TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0_u8; 4096];
+        loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request).to_ascii_lowercase();
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length = text
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length: "))
+                    .map_or(0, |length| length.trim().parse::<usize>().unwrap());
+                if request.len() >= end + 4 + length {
+                    return String::from_utf8_lossy(&request).into_owned();
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn puts_encrypted_objects_under_the_rendered_key() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in [
+                "HTTP/1.1 503 Service Unavailable\r\nConnection: close\r\nContent-Length: 61\r\n\r\n<Error><Code>SlowDown</Code><Message>Reduce</Message></Error>",
+                "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+            ] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                requests.push(read_request(&mut stream).await);
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+
+        let config: S3SinkConfig = serde_yaml::from_str(&format!(
+            "bucket: lake\nregion: eu-west-1\nendpoint: http://{address}\nprefix: \"raw/{{{{query}}}}/dt=2026-01-31\"\nfile_name: \"part-{{{{sequence}}}}.ndjson\"\nformat: ndjson\naccess_key_id: AKIDEXAMPLE\nsecret_access_key: secret\nserver_side_encryption: aws_kms\nkms_key_id: alias/lake\nretry_attempts: 1\nretry_delay_seconds: 0\n"
+        ))
+        .unwrap();
+        let sink = S3Sink::new(&config, "orders", Vec::new()).unwrap();
+
+        let location = sink.send(&[json!({"id": 7})]).await.unwrap();
+        assert_eq!("s3://lake/raw/orders/dt=2026-01-31/part-1.ndjson", location);
+
+        let requests = server.await.unwrap();
+        assert_eq!(2, requests.len());
+        let request = requests[1].to_ascii_lowercase();
+        assert!(request.starts_with("put /lake/raw/orders/dt%3d2026-01-31/part-1.ndjson"));
+        assert!(request.contains("x-amz-server-side-encryption: aws:kms"));
+        assert!(request.contains("x-amz-server-side-encryption-aws-kms-key-id: alias/lake"));
+        assert!(request.contains("authorization: aws4-hmac-sha256 credential=akidexample/"));
+        assert!(request.contains("x-amz-checksum-crc32: "));
+        assert!(request.contains("amz-sdk-request: attempt=2; max=2"));
+        assert!(request.contains("{\"id\":7}"));
+    }
+}
@@ -0,0 +1,351 @@
+use crate::config::destination_config::SftpSinkConfig;
+use crate::http::format::{BodyLayout, FormatError, PayloadFormat};
+use crate::http::retry::RetryPolicy;
+use crate::sinks::redis::MissingKeyField;
+use crate::sinks::{render_file_name, with_retries};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use serde_json::Value;
+use ssh2::{CheckResult, ErrorCode, HashType, KnownHostFileKind, Session};
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+// SFTP status codes that will not change on retry (draft-ietf-secsh-filexfer-02).
+const SFTP_NO_SUCH_FILE: i32 = 2;
+const SFTP_PERMISSION_DENIED: i32 = 3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SftpError {
+    #[error(transparent)]
+    Format(#[from] FormatError),
+    #[error(transparent)]
+    FileName(#[from] MissingKeyField),
+    #[error("failed to connect to the SFTP server: {0}")]
+    Connect(#[from] std::io::Error),
+    #[error("{0}")]
+    HostKey(String),
+    #[error("SFTP authentication as '{username}' failed: {source}")]
+    Auth {
+        username: String,
+        source: ssh2::Error,
+    },
+    #[error("SFTP {operation} failed: {source}")]
+    Ssh {
+        operation: String,
+        source: ssh2::Error,
+    },
+    #[error("SFTP worker task failed: {0}")]
+    Join(#[from] tokio::task::JoinError),
+}
+
+impl SftpError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            SftpError::Connect(_) => true,
+            SftpError::Ssh { source, .. } => !matches!(
+                source.code(),
+                ErrorCode::SFTP(SFTP_NO_SUCH_FILE | SFTP_PERMISSION_DENIED)
+            ),
+            SftpError::Format(_)
+            | SftpError::FileName(_)
+            | SftpError::HostKey(_)
+            | SftpError::Auth { .. }
+            | SftpError::Join(_) => false,
+        }
+    }
+}
+
+fn ssh(operation: impl Into<String>) -> impl FnOnce(ssh2::Error) -> SftpError {
+    let operation = operation.into();
+    move |source| SftpError::Ssh { operation, source }
+}
+
+enum Credentials {
+    Password(String),
+    Key {
+        file: PathBuf,
+        passphrase: Option<String>,
+    },
+}
+
+enum HostKeyCheck {
+    Fingerprint(String),
+    KnownHosts(PathBuf),
+}
+
+/// Connection settings moved into the blocking worker for each upload.
+struct Server {
+    host: String,
+    port: u16,
+    username: String,
+    credentials: Credentials,
+    host_key: HostKeyCheck,
+    timeout: Duration,
+}
+
+/// Uploads each batch as one file over a fresh SSH session; libssh2 blocks, so every upload
+/// runs on a blocking worker like the ODBC sessions do.
+pub struct SftpSink {
+    server: Arc<Server>,
+    directory: Option<String>,
+    create_directories: bool,
+    file_name: String,
+    query: String,
+    format: PayloadFormat,
+    layout: BodyLayout,
+    temp_suffix: String,
+    retry_policy: RetryPolicy,
+    sequence: AtomicUsize,
+}
+
+impl SftpSink {
+    /// `leading_columns` orders `csv` and `xml` files; see [`BodyLayout`].
+    pub fn new(
+        config: &SftpSinkConfig,
+        query_name: &str,
+        leading_columns: Vec<String>,
+    ) -> Result<Self, SftpError> {
+        let credentials = match (&config.password, &config.private_key_file) {
+            (Some(password), _) => Credentials::Password(password.expose().clone()),
+            (None, file) => Credentials::Key {
+                file: PathBuf::from(file.clone().unwrap_or_default()),
+                passphrase: config
+                    .private_key_passphrase
+                    .as_ref()
+                    .map(|passphrase| passphrase.expose().clone()),
+            },
+        };
+        let host_key = match &config.host_key_fingerprint {
+            Some(fingerprint) => {
+                HostKeyCheck::Fingerprint(fingerprint.trim_end_matches('=').to_string())
+            }
+            None => HostKeyCheck::KnownHosts(match &config.known_hosts_file {
+                Some(path) => PathBuf::from(path),
+                None => std::env::var_os("HOME")
+                    .or_else(|| std::env::var_os("USERPROFILE"))
+                    .map(|home| PathBuf::from(home).join(".ssh").join("known_hosts"))
+                    .unwrap_or_else(|| PathBuf::from("known_hosts")),
+            }),
+        };
+
+        Ok(Self {
+            server: Arc::new(Server {
+                host: config.host.clone(),
+                port: config.port.unwrap_or(22),
+                username: config.username.clone(),
+                credentials,
+                host_key,
+                timeout: Duration::from_secs(config.timeout_seconds.unwrap_or(30) as u64),
+            }),
+            directory: config.directory.clone(),
+            create_directories: config.create_directories,
+            file_name: config.file_name.clone(),
+            query: query_name.to_string(),
+            format: config.body.format,
+            layout: BodyLayout {
+                leading_columns,
+                ..config.body.layout()?
+            },
+            temp_suffix: config
+                .temp_suffix
+                .clone()
+                .unwrap_or_else(|| ".part".to_string()),
+            retry_policy: RetryPolicy::from_settings(&config.retry),
+            sequence: AtomicUsize::new(0),
+        })
+    }
+
+    /// Uploads the batch as one file; returns its remote path.
+    pub async fn send(&self, rows: &[Value]) -> Result<String, SftpError> {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed) + 1;
+        let name = render_file_name(&self.file_name, &self.query, sequence)?;
+        let directory = self
+            .directory
+            .as_deref()
+            .map(|directory| render_file_name(directory, &self.query, sequence))
+            .transpose()?;
+        let path = match &directory {
+            Some(directory) => format!("{}/{name}", directory.trim_end_matches('/')),
+            None => name,
+        };
+        let body = Arc::new(
+            self.format
+                .encode(&Value::Array(rows.to_vec()), &self.layout)?
+                .bytes,
+        );
+
+        with_retries(
+            &self.retry_policy,
+            "SFTP upload",
+            || {
+                let upload = Upload {
+                    server: Arc::clone(&self.server),
+                    directory: directory.clone().filter(|_| self.create_directories),
+                    path: path.clone(),
+                    temp_suffix: self.temp_suffix.clone(),
+                    bytes: Arc::clone(&body),
+                };
+                async move { tokio::task::spawn_blocking(move || upload.run()).await? }
+            },
+            |error: &SftpError| error.is_retryable().then_some(None),
+        )
+        .await?;
+        Ok(path)
+    }
+}
+
+struct Upload {
+    server: Arc<Server>,
+    /// Directory to create first, when `create_directories` is set.
+    directory: Option<String>,
+    path: String,
+    temp_suffix: String,
+    bytes: Arc<Vec<u8>>,
+}
+
+impl Upload {
+    fn run(self) -> Result<(), SftpError> {
+        let session = self.server.connect()?;
+        let sftp = session.sftp().map_err(ssh("subsystem start"))?;
+        if let Some(directory) = &self.directory {
+            create_directories(&sftp, directory)?;
+        }
+
+        let path = Path::new(&self.path);
+        let upload_path = PathBuf::from(format!("{}{}", self.path, self.temp_suffix));
+        let mut file = sftp
+            .create(&upload_path)
+            .map_err(ssh(format!("create '{}'", upload_path.display())))?;
+        file.write_all(&self.bytes)?;
+        // Closing flushes the last write and surfaces errors such as a full disk.
+        file.close()
+            .map_err(ssh(format!("close '{}'", upload_path.display())))?;
+        drop(file);
+        if upload_path != path
+            && let Err(error) = sftp.rename(&upload_path, path, None)
+        {
+            let _ = sftp.unlink(&upload_path);
+            return Err(ssh(format!("rename to '{}'", path.display()))(error));
+        }
+        let _ = session.disconnect(None, "upload complete", None);
+        Ok(())
+    }
+}
+
+impl Server {
+    fn connect(&self) -> Result<Session, SftpError> {
+        let address = (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "host has no address")
+            })?;
+        let tcp = TcpStream::connect_timeout(&address, self.timeout)?;
+        let mut session = Session::new().map_err(ssh("session setup"))?;
+        session.set_timeout(self.timeout.as_millis() as u32);
+        session.set_tcp_stream(tcp);
+        session.handshake().map_err(ssh("handshake"))?;
+        self.verify_host_key(&session)?;
+
+        let auth = match &self.credentials {
+            Credentials::Password(password) => session.userauth_password(&self.username, password),
+            Credentials::Key { file, passphrase } => {
+                session.userauth_pubkey_file(&self.username, None, file, passphrase.as_deref())
+            }
+        };
+        auth.map_err(|source| SftpError::Auth {
+            username: self.username.clone(),
+            source,
+        })?;
+        Ok(session)
+    }
+
+    fn verify_host_key(&self, session: &Session) -> Result<(), SftpError> {
+        let fingerprint = session
+            .host_key_hash(HashType::Sha256)
+            .map(fingerprint)
+            .ok_or_else(|| SftpError::HostKey("the SFTP server sent no host key".to_string()))?;
+        let target = format!("{}:{}", self.host, self.port);
+        match &self.host_key {
+            HostKeyCheck::Fingerprint(expected) if *expected == fingerprint => Ok(()),
+            HostKeyCheck::Fingerprint(expected) => Err(SftpError::HostKey(format!(
+                "host key of {target} is {fingerprint}, not the pinned {expected}"
+            ))),
+            HostKeyCheck::KnownHosts(file) => {
+                let (key, _) = session.host_key().ok_or_else(|| {
+                    SftpError::HostKey("the SFTP server sent no host key".to_string())
+                })?;
+                let mut known_hosts = session.known_hosts().map_err(ssh("known_hosts setup"))?;
+                // A missing file simply knows no hosts.
+                let _ = known_hosts.read_file(file, KnownHostFileKind::OpenSSH);
+                match known_hosts.check_port(&self.host, self.port, key) {
+                    CheckResult::Match => Ok(()),
+                    CheckResult::Mismatch => Err(SftpError::HostKey(format!(
+                        "host key of {target} ({fingerprint}) does not match '{}'; the key changed or the connection is intercepted",
+                        file.display()
+                    ))),
+                    CheckResult::NotFound | CheckResult::Failure => {
+                        Err(SftpError::HostKey(format!(
+                            "host key of {target} ({fingerprint}) is not in '{}'; add it with `ssh-keyscan -p {} {}` or set host_key_fingerprint",
+                            file.display(),
+                            self.port,
+                            self.host
+                        )))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// OpenSSH-style `SHA256:` fingerprint of a host key hash.
+fn fingerprint(hash: &[u8]) -> String {
+    format!("SHA256:{}", STANDARD_NO_PAD.encode(hash))
+}
+
+/// Creates each missing component of `directory`.
+fn create_directories(sftp: &ssh2::Sftp, directory: &str) -> Result<(), SftpError> {
+    let mut current = PathBuf::new();
+    if directory.starts_with('/') {
+        current.push("/");
+    }
+    for component in directory
+        .split('/')
+        .filter(|component| !component.is_empty())
+    {
+        current.push(component);
+        if sftp.stat(&current).is_err() {
+            sftp.mkdir(&current, 0o755)
+                .map_err(ssh(format!("mkdir '{}'", current.display())))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprints_match_openssh_and_missing_files_are_not_retried() {
+        assert_eq!(
+            "SHA256:AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8",
+            fingerprint(&(0_u8..32).collect::<Vec<_>>())
+        );
+
+        let missing = SftpError::Ssh {
+            operation: "create 'in/a.csv.part'".to_string(),
+            source: ssh2::Error::new(ErrorCode::SFTP(SFTP_NO_SUCH_FILE), "no such file"),
+        };
+        assert!(!missing.is_retryable());
+        let dropped = SftpError::Ssh {
+            operation: "close 'in/a.csv.part'".to_string(),
+            source: ssh2::Error::new(ErrorCode::Session(-7), "socket send failed"),
+        };
+        assert!(dropped.is_retryable());
+    }
+}
@@ -0,0 +1,61 @@
+use crate::config::destination_config::ClientTlsConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ClientConfig, RootCertStore};
+use std::sync::Arc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsConfigError {
+    #[error("failed to read TLS file '{path}': {reason}")]
+    File { path: String, reason: String },
+    #[error("invalid TLS configuration: {0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+/// Builds a rustls client configuration from the optional CA bundle and client identity.
+pub(crate) fn client_config(tls: Option<&ClientTlsConfig>) -> Result<ClientConfig, TlsConfigError> {
+    let mut roots = RootCertStore::empty();
+    match tls.and_then(|tls| tls.ca_file.as_deref()) {
+        Some(path) => {
+            for certificate in read_certificates(path)? {
+                roots.add(certificate)?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+
+    let builder =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots);
+    let client_auth = tls.and_then(|tls| {
+        tls.client_cert_file
+            .as_deref()
+            .zip(tls.client_key_file.as_deref())
+    });
+    match client_auth {
+        Some((cert_path, key_path)) => {
+            let key =
+                PrivateKeyDer::from_pem_file(key_path).map_err(|error| TlsConfigError::File {
+                    path: key_path.to_string(),
+                    reason: error.to_string(),
+                })?;
+            Ok(builder.with_client_auth_cert(read_certificates(cert_path)?, key)?)
+        }
+        None => Ok(builder.with_no_client_auth()),
+    }
+}
+
+fn read_certificates(path: &str) -> Result<Vec<CertificateDer<'static>>, TlsConfigError> {
+    let file_error = |reason: String| TlsConfigError::File {
+        path: path.to_string(),
+        reason,
+    };
+    let certificates = CertificateDer::pem_file_iter(path)
+        .and_then(|certificates| certificates.collect::<Result<Vec<_>, _>>())
+        .map_err(|error| file_error(error.to_string()))?;
+    if certificates.is_empty() {
+        return Err(file_error("no PEM certificates found".to_string()));
+    }
+    Ok(certificates)
+}
@@ -0,0 +1,331 @@
+use crate::config::sort_config::{SortConfig, SortKey};
+use crate::workspace::{JobWorkspace, SpillFile, WorkspaceError};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
+use std::path::PathBuf;
+
+const DEFAULT_MEMORY_ROWS: usize = 100_000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SortError {
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+    #[error("failed to read sorted run '{path}': {reason}")]
+    Run { path: PathBuf, reason: String },
+}
+
+/// Buffers rows in memory and spills sorted runs to the job workspace once
+/// `memory_rows` is reached; `finish` merges the runs back in order. Run files are deleted,
+/// and their bytes returned to the workspace quota, when the merged rows are dropped.
+pub struct ExternalSorter<'a> {
+    keys: Vec<SortKey>,
+    memory_rows: usize,
+    buffer: Vec<Map<String, Value>>,
+    runs: Vec<SpillFile>,
+    workspace: &'a JobWorkspace,
+}
+
+impl<'a> ExternalSorter<'a> {
+    pub fn new(config: &SortConfig, workspace: &'a JobWorkspace) -> Self {
+        Self {
+            keys: config.by.clone(),
+            memory_rows: config.memory_rows.unwrap_or(DEFAULT_MEMORY_ROWS),
+            buffer: Vec::new(),
+            runs: Vec::new(),
+            workspace,
+        }
+    }
+
+    pub fn push(&mut self, rows: Vec<Map<String, Value>>) -> Result<(), SortError> {
+        for row in rows {
+            self.buffer.push(row);
+            if self.buffer.len() >= self.memory_rows {
+                self.spill()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<SortedRows, SortError> {
+        if self.runs.is_empty() {
+            let keys = self.keys.clone();
+            self.buffer
+                .sort_by(|left, right| compare_rows(left, right, &keys));
+            return Ok(SortedRows::Memory(
+                std::mem::take(&mut self.buffer).into_iter(),
+            ));
+        }
+
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+        tracing::debug!(runs = self.runs.len(), "merging sorted runs");
+        let mut readers = Vec::new();
+        let mut heap = BinaryHeap::new();
+        for run in std::mem::take(&mut self.runs) {
+            let file = File::open(run.path()).map_err(|error| SortError::Run {
+                path: run.path().to_path_buf(),
+                reason: error.to_string(),
+            })?;
+            let mut reader = RunReader {
+                run,
+                lines: BufReader::new(file).lines(),
+            };
+            if let Some(row) = reader.next_row()? {
+                heap.push(HeapEntry {
+                    row,
+                    run: readers.len(),
+                    keys: self.keys.clone(),
+                });
+            }
+            readers.push(reader);
+        }
+        Ok(SortedRows::Merge { heap, readers })
+    }
+
+    fn spill(&mut self) -> Result<(), SortError> {
+        let keys = self.keys.clone();
+        self.buffer
+            .sort_by(|left, right| compare_rows(left, right, &keys));
+        let mut writer = self
+            .workspace
+            .create_file(&format!("sort-run-{}.jsonl", self.runs.len()))?;
+        for row in self.buffer.drain(..) {
+            writer.write_record(&Value::Object(row))?;
+        }
+        self.runs.push(writer.finish()?);
+        Ok(())
+    }
+}
+
+pub enum SortedRows {
+    Memory(std::vec::IntoIter<Map<String, Value>>),
+    Merge {
+        heap: BinaryHeap<HeapEntry>,
+        readers: Vec<RunReader>,
+    },
+}
+
+impl Iterator for SortedRows {
+    type Item = Result<Map<String, Value>, SortError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            SortedRows::Memory(rows) => rows.next().map(Ok),
+            SortedRows::Merge { heap, readers } => {
+                let entry = heap.pop()?;
+                match readers[entry.run].next_row() {
+                    Ok(Some(row)) => heap.push(HeapEntry {
+                        row,
+                        run: entry.run,
+                        keys: entry.keys.clone(),
+                    }),
+                    Ok(None) => {}
+                    Err(error) => return Some(Err(error)),
+                }
+                Some(Ok(entry.row))
+            }
+        }
+    }
+}
+
+// `lines` is declared first so the file is closed before the run is deleted.
+pub struct RunReader {
+    lines: Lines<BufReader<File>>,
+    run: SpillFile,
+}
+
+impl RunReader {
+    fn next_row(&mut self) -> Result<Option<Map<String, Value>>, SortError> {
+        let Some(line) = self.lines.next() else {
+            return Ok(None);
+        };
+        let line = line.map_err(|error| self.error(error))?;
+        match serde_json::from_str(&line).map_err(|error| self.error(error))? {
+            Value::Object(row) => Ok(Some(row)),
+            other => Err(self.error(format!("expected an object, found {other}"))),
+        }
+    }
+
+    fn error(&self, reason: impl std::fmt::Display) -> SortError {
+        SortError::Run {
+            path: self.run.path().to_path_buf(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+pub struct HeapEntry {
+    row: Map<String, Value>,
+    run: usize,
+    keys: Vec<SortKey>,
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry {}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapEntry {
+    // BinaryHeap is a max-heap; reverse so the smallest row (then lowest run) pops first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_rows(&other.row, &self.row, &self.keys).then(other.run.cmp(&self.run))
+    }
+}
+
+fn compare_rows(
+    left: &Map<String, Value>,
+    right: &Map<String, Value>,
+    keys: &[SortKey],
+) -> Ordering {
+    keys.iter()
+        .map(|key| {
+            let ordering = compare_values(
+                left.get(&key.field).unwrap_or(&Value::Null),
+                right.get(&key.field).unwrap_or(&Value::Null),
+            );
+            if key.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        })
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Orders nulls first, then booleans, numbers, and strings; other values by their JSON text.
+fn compare_values(left: &Value, right: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+            Value::Array(_) | Value::Object(_) => 4,
+        }
+    }
+
+    match (left, right) {
+        (Value::Bool(left), Value::Bool(right)) => left.cmp(right),
+        (Value::Number(left), Value::Number(right)) => match (left.as_i64(), right.as_i64()) {
+            (Some(left), Some(right)) => left.cmp(&right),
+            _ => left
+                .as_f64()
+                .unwrap_or_default()
+                .total_cmp(&right.as_f64().unwrap_or_default()),
+        },
+        (Value::String(left), Value::String(right)) => left.cmp(right),
+        _ => rank(left)
+            .cmp(&rank(right))
+            .then_with(|| left.to_string().cmp(&right.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sort_config(memory_rows: usize) -> SortConfig {
+        SortConfig {
+            by: vec![
+                SortKey {
+                    field: "region".to_string(),
+                    descending: false,
+                },
+                SortKey {
+                    field: "amount".to_string(),
+                    descending: true,
+                },
+            ],
+            memory_rows: Some(memory_rows),
+        }
+    }
+
+    fn rows() -> Vec<Map<String, Value>> {
+        [
+            ("b", 1),
+            ("a", 2),
+            ("b", 7),
+            ("a", 9),
+            ("c", 3),
+            ("a", 4),
+            ("b", 5),
+        ]
+        .into_iter()
+        .map(|(region, amount)| {
+            serde_json::json!({"region": region, "amount": amount})
+                .as_object()
+                .unwrap()
+                .clone()
+        })
+        .collect()
+    }
+
+    fn sorted(memory_rows: usize) -> Vec<(String, i64)> {
+        let workspace = JobWorkspace::create(None, "sort-test").unwrap();
+        let mut sorter = ExternalSorter::new(&sort_config(memory_rows), &workspace);
+        let rows = rows();
+        let (first, second) = rows.split_at(3);
+        sorter.push(first.to_vec()).unwrap();
+        sorter.push(second.to_vec()).unwrap();
+
+        let sorted = sorter
+            .finish()
+            .unwrap()
+            .map(|row| {
+                let row = row.unwrap();
+                (
+                    row["region"].as_str().unwrap().to_string(),
+                    row["amount"].as_i64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(0, workspace.used_bytes());
+        sorted
+    }
+
+    #[test]
+    fn merges_spilled_runs_in_global_order() {
+        let expected = vec![
+            ("a".to_string(), 9),
+            ("a".to_string(), 4),
+            ("a".to_string(), 2),
+            ("b".to_string(), 7),
+            ("b".to_string(), 5),
+            ("b".to_string(), 1),
+            ("c".to_string(), 3),
+        ];
+
+        assert_eq!(expected, sorted(100));
+        assert_eq!(expected, sorted(2));
+    }
+
+    #[test]
+    fn orders_nulls_before_values_and_numbers_numerically() {
+        assert_eq!(
+            Ordering::Less,
+            compare_values(&Value::Null, &serde_json::json!(0))
+        );
+        assert_eq!(
+            Ordering::Less,
+            compare_values(&serde_json::json!(2), &serde_json::json!(10))
+        );
+        assert_eq!(
+            Ordering::Less,
+            compare_values(&serde_json::json!(1.5), &serde_json::json!(2))
+        );
+    }
+}
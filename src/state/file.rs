@@ -0,0 +1,238 @@
+//! The JSON state file, written whole on every change with rotated backups.
+
+use super::{QueryState, StateBackend, YetiiState};
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub(super) struct StateFile {
+    path: PathBuf,
+    backup_states: u32,
+}
+
+impl StateFile {
+    pub(super) fn new(path: impl Into<PathBuf>, backup_states: u32) -> Self {
+        Self {
+            path: path.into(),
+            backup_states,
+        }
+    }
+
+    fn save(&self, state: &YetiiState) -> Result<()> {
+        let content = serde_json::to_string_pretty(state).context("failed to serialize state")?;
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("failed to create state directory '{}'", parent.display())
+            })?;
+        }
+
+        let temp_path = temporary_path(&self.path);
+        write_synced(&temp_path, format!("{content}\n").as_bytes()).with_context(|| {
+            format!("failed to write temporary state '{}'", temp_path.display())
+        })?;
+        self.rotate_backups()?;
+        // The rename replaces the state file in one step, so a crash leaves either version.
+        std::fs::rename(&temp_path, &self.path).with_context(|| {
+            format!(
+                "failed to move temporary state '{}' to '{}'",
+                temp_path.display(),
+                self.path.display()
+            )
+        })?;
+        Ok(())
+    }
+
+    fn rotate_backups(&self) -> Result<()> {
+        if self.backup_states == 0 || !self.path.exists() {
+            return Ok(());
+        }
+
+        for index in (2..=self.backup_states).rev() {
+            let from = backup_path(&self.path, index - 1);
+            let to = backup_path(&self.path, index);
+
+            if from.exists() {
+                if to.exists() {
+                    std::fs::remove_file(&to)
+                        .with_context(|| format!("failed to remove backup '{}'", to.display()))?;
+                }
+                std::fs::rename(&from, &to).with_context(|| {
+                    format!(
+                        "failed to rotate state backup '{}' to '{}'",
+                        from.display(),
+                        to.display()
+                    )
+                })?;
+            }
+        }
+
+        // Copied rather than moved so the state file exists until the new version replaces it.
+        let first = backup_path(&self.path, 1);
+        std::fs::copy(&self.path, &first).with_context(|| {
+            format!(
+                "failed to copy state file '{}' to backup '{}'",
+                self.path.display(),
+                first.display()
+            )
+        })?;
+        Ok(())
+    }
+}
+
+impl StateBackend for StateFile {
+    fn location(&self) -> String {
+        format!("state file '{}'", self.path.display())
+    }
+
+    /// Loads the state file, falling back to the newest readable backup when it is missing or
+    /// corrupt so watermarks are not silently reset.
+    fn load(&self) -> Result<YetiiState> {
+        let error = match read_state(&self.path) {
+            Ok(Some(state)) => return Ok(state),
+            Ok(None) => None,
+            Err(error) => Some(error),
+        };
+
+        for index in 1..=self.backup_states {
+            let backup = backup_path(&self.path, index);
+            if let Ok(Some(state)) = read_state(&backup) {
+                tracing::warn!(
+                    state_file = %self.path.display(),
+                    backup = %backup.display(),
+                    error = error.as_ref().map(|error| format!("{error:#}")),
+                    "state file is missing or unreadable; recovered from backup"
+                );
+                return Ok(state);
+            }
+        }
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(YetiiState::default()),
+        }
+    }
+
+    fn update(
+        &self,
+        query_name: &str,
+        change: &mut dyn FnMut(&mut QueryState) -> Result<()>,
+    ) -> Result<QueryState> {
+        let mut state = self.load()?;
+        let query_state = state.queries.entry(query_name.to_string()).or_default();
+        change(query_state)?;
+        let updated = query_state.clone();
+        self.save(&state)?;
+        Ok(updated)
+    }
+}
+
+/// `None` when the file does not exist or is empty.
+fn read_state(path: &Path) -> Result<Option<YetiiState>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read state file '{}'", path.display()))?;
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+
+    serde_json::from_str(&content)
+        .map(Some)
+        .with_context(|| format!("failed to parse state file '{}'", path.display()))
+}
+
+fn write_synced(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+fn backup_path(path: &Path, index: u32) -> PathBuf {
+    let mut backup = OsString::from(path.as_os_str());
+    backup.push(format!(".{index}"));
+    PathBuf::from(backup)
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut temporary = OsString::from(path.as_os_str());
+    temporary.push(format!(".tmp-{}", std::process::id()));
+    PathBuf::from(temporary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn temp_state_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "yetii-state-file-test-{name}-{}-{}.json",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap()
+        ))
+    }
+
+    #[test]
+    fn save_rotates_backups() {
+        let path = temp_state_path("rotation");
+        let store = StateFile::new(&path, 2);
+        let mut state = YetiiState::default();
+
+        state
+            .record_success("first", Utc::now(), Utc::now(), 1, 1, None)
+            .unwrap();
+        store.save(&state).unwrap();
+        state
+            .record_success("second", Utc::now(), Utc::now(), 2, 1, None)
+            .unwrap();
+        store.save(&state).unwrap();
+        state
+            .record_success("third", Utc::now(), Utc::now(), 3, 1, None)
+            .unwrap();
+        store.save(&state).unwrap();
+
+        assert!(path.exists());
+        assert!(backup_path(&path, 1).exists());
+        assert!(backup_path(&path, 2).exists());
+
+        let loaded = store.load().unwrap();
+        assert!(loaded.query("third").is_some());
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(backup_path(&path, 1));
+        let _ = std::fs::remove_file(backup_path(&path, 2));
+    }
+
+    #[test]
+    fn corrupt_state_recovers_from_newest_readable_backup() {
+        let path = temp_state_path("recovery");
+        let store = StateFile::new(&path, 2);
+        let mut state = YetiiState::default();
+        for name in ["first", "second", "third"] {
+            state
+                .record_success(name, Utc::now(), Utc::now(), 1, 1, None)
+                .unwrap();
+            store.save(&state).unwrap();
+        }
+        std::fs::write(&path, "{\"queries\": {").unwrap();
+        std::fs::write(backup_path(&path, 1), "not json").unwrap();
+
+        let recovered = store.load();
+        let without_backups = StateFile::new(&path, 0).load();
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(backup_path(&path, 1));
+        let _ = std::fs::remove_file(backup_path(&path, 2));
+
+        let recovered = recovered.unwrap();
+        assert!(recovered.query("first").is_some());
+        assert!(recovered.query("third").is_none());
+        assert!(without_backups.is_err());
+    }
+}
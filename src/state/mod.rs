@@ -1,23 +1,46 @@
-use crate::config::execution_config::StateManagement;
+use crate::config::execution_config::{StateBackendConfig, StateManagement};
 use crate::config::query_config::QueryConfig;
 use crate::config::sql_query::QueryParameter;
-use crate::config::watermark_config::WatermarkStrategy;
+use crate::config::watermark_config::{
+    WatermarkConfig, WatermarkStrategy, WatermarkTimezone, is_timestamp_type,
+};
+use crate::monitoring;
 use anyhow::{Context, Result, anyhow};
 use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
-use std::ffi::OsString;
-use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::sync::Mutex;
 
+mod file;
+mod redis;
+mod sqlite;
+
+use file::StateFile;
+use redis::RedisState;
+use sqlite::SqliteState;
+
 static STATE_WRITE_LOCK: Mutex<()> = Mutex::const_new(());
 
+/// Where [`YetiiState`] is kept. `update` reads, changes, and writes one query's state as a
+/// unit, also against other processes sharing the store.
+pub trait StateBackend: std::fmt::Debug + Send + Sync {
+    /// Describes the store for messages, without credentials.
+    fn location(&self) -> String;
+    fn load(&self) -> Result<YetiiState>;
+    /// `change` may be called again when another process wrote the query's state meanwhile.
+    fn update(
+        &self,
+        query_name: &str,
+        change: &mut dyn FnMut(&mut QueryState) -> Result<()>,
+    ) -> Result<QueryState>;
+}
+
 #[derive(Debug, Clone)]
 pub struct StateStore {
-    path: PathBuf,
-    backup_states: u32,
+    backend: Arc<dyn StateBackend>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
@@ -36,8 +59,36 @@ pub struct QueryState {
     pub last_rows_read: Option<usize>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_batches_sent: Option<usize>,
+    /// Outcome of the last run that delivered anything.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_outcome: Option<QueryOutcome>,
+    /// The run that recorded `last_outcome`, matching its log lines and notifications.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run_id: Option<String>,
     #[serde(default)]
     pub watermarks: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<Checkpoint>,
+}
+
+/// Progress of a run with `checkpoint`, saved while it delivers. It is cleared when the run
+/// succeeds or the watermark advances, so `rows_delivered` always counts from the saved
+/// watermark.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub started_at: DateTime<Utc>,
+    pub saved_at: DateTime<Utc>,
+    /// Rows delivered in order, without a failed batch among them; a resumed run skips them.
+    pub rows_delivered: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryOutcome {
+    Success,
+    /// Some batches were delivered and others failed; the watermark covers only the batches
+    /// delivered before the first failure.
+    Partial,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -60,127 +111,121 @@ pub struct WatermarkUpdate {
 
 impl StateStore {
     pub fn from_config(config: &StateManagement) -> Self {
-        Self {
-            path: PathBuf::from(&config.state_file),
-            backup_states: config.backup_states,
-        }
+        let backend: Arc<dyn StateBackend> = match &config.backend {
+            None | Some(StateBackendConfig::File) => {
+                Arc::new(StateFile::new(&config.state_file, config.backup_states))
+            }
+            Some(StateBackendConfig::Sqlite { path }) => Arc::new(SqliteState::new(path)),
+            Some(StateBackendConfig::Redis { url, key }) => {
+                Arc::new(RedisState::new(url.expose(), key))
+            }
+        };
+        Self { backend }
     }
 
     #[cfg(test)]
-    pub fn new(path: impl Into<PathBuf>, backup_states: u32) -> Self {
+    pub fn new(path: impl Into<std::path::PathBuf>, backup_states: u32) -> Self {
         Self {
-            path: path.into(),
-            backup_states,
+            backend: Arc::new(StateFile::new(path, backup_states)),
         }
     }
 
-    pub fn path(&self) -> &Path {
-        &self.path
+    pub fn location(&self) -> String {
+        self.backend.location()
     }
 
     pub fn load_or_default(&self) -> Result<YetiiState> {
-        if !self.path.exists() {
-            return Ok(YetiiState::default());
-        }
-
-        let content = std::fs::read_to_string(&self.path)
-            .with_context(|| format!("failed to read state file '{}'", self.path.display()))?;
-        if content.trim().is_empty() {
-            return Ok(YetiiState::default());
-        }
+        self.backend.load()
+    }
 
-        serde_json::from_str(&content)
-            .with_context(|| format!("failed to parse state file '{}'", self.path.display()))
+    pub async fn record_success(
+        &self,
+        query_name: &str,
+        started_at: DateTime<Utc>,
+        rows_read: usize,
+        batches_sent: usize,
+        watermark: Option<WatermarkUpdate>,
+    ) -> Result<QueryState> {
+        self.record(
+            query_name,
+            QueryOutcome::Success,
+            started_at,
+            rows_read,
+            batches_sent,
+            watermark,
+        )
+        .await
     }
 
-    pub fn save(&self, state: &YetiiState) -> Result<()> {
-        let content = serde_json::to_string_pretty(state).context("failed to serialize state")?;
-        if let Some(parent) = self.path.parent()
-            && !parent.as_os_str().is_empty()
-        {
-            std::fs::create_dir_all(parent).with_context(|| {
-                format!("failed to create state directory '{}'", parent.display())
-            })?;
-        }
+    pub async fn record_partial(
+        &self,
+        query_name: &str,
+        started_at: DateTime<Utc>,
+        rows_read: usize,
+        batches_sent: usize,
+        watermark: Option<WatermarkUpdate>,
+    ) -> Result<QueryState> {
+        self.record(
+            query_name,
+            QueryOutcome::Partial,
+            started_at,
+            rows_read,
+            batches_sent,
+            watermark,
+        )
+        .await
+    }
 
-        let temp_path = temporary_path(&self.path);
-        std::fs::write(&temp_path, format!("{content}\n")).with_context(|| {
-            format!("failed to write temporary state '{}'", temp_path.display())
-        })?;
-        self.rotate_backups()?;
-        if self.backup_states == 0 && self.path.exists() {
-            std::fs::remove_file(&self.path).with_context(|| {
-                format!("failed to replace state file '{}'", self.path.display())
-            })?;
-        }
-        std::fs::rename(&temp_path, &self.path).with_context(|| {
-            format!(
-                "failed to move temporary state '{}' to '{}'",
-                temp_path.display(),
-                self.path.display()
-            )
-        })?;
-        Ok(())
+    /// Saves the delivery progress of a running query.
+    pub async fn save_checkpoint(&self, query_name: &str, checkpoint: Checkpoint) -> Result<()> {
+        self.update(query_name, move |state| {
+            state.checkpoint = Some(checkpoint.clone());
+            Ok(())
+        })
+        .await
+        .map(drop)
     }
 
-    pub async fn record_success(
+    async fn record(
         &self,
         query_name: &str,
+        outcome: QueryOutcome,
         started_at: DateTime<Utc>,
         rows_read: usize,
         batches_sent: usize,
         watermark: Option<WatermarkUpdate>,
-    ) -> Result<YetiiState> {
-        let _guard = STATE_WRITE_LOCK.lock().await;
-        let store = self.clone();
-        let query_name = query_name.to_string();
-
-        tokio::task::spawn_blocking(move || {
-            let mut state = store.load_or_default()?;
-            state.record_success(
-                &query_name,
+    ) -> Result<QueryState> {
+        let name = query_name.to_string();
+        // The update runs on a blocking thread, outside the run's task.
+        let run_id = monitoring::current_run_id();
+        self.update(query_name, move |state| {
+            state.last_run_id = run_id.clone();
+            state.record(
+                &name,
+                outcome,
                 started_at,
                 Utc::now(),
                 rows_read,
                 batches_sent,
                 watermark.as_ref(),
-            )?;
-            store.save(&state)?;
-            Ok(state)
+            )
         })
         .await
-        .context("state persistence task failed")?
     }
 
-    fn rotate_backups(&self) -> Result<()> {
-        if self.backup_states == 0 || !self.path.exists() {
-            return Ok(());
-        }
-
-        for index in (1..=self.backup_states).rev() {
-            let from = if index == 1 {
-                self.path.clone()
-            } else {
-                backup_path(&self.path, index - 1)
-            };
-            let to = backup_path(&self.path, index);
-
-            if from.exists() {
-                if to.exists() {
-                    std::fs::remove_file(&to)
-                        .with_context(|| format!("failed to remove backup '{}'", to.display()))?;
-                }
-                std::fs::rename(&from, &to).with_context(|| {
-                    format!(
-                        "failed to rotate state backup '{}' to '{}'",
-                        from.display(),
-                        to.display()
-                    )
-                })?;
-            }
-        }
+    /// Applies `change` to the stored state of one query while holding the write lock.
+    async fn update(
+        &self,
+        query_name: &str,
+        mut change: impl FnMut(&mut QueryState) -> Result<()> + Send + 'static,
+    ) -> Result<QueryState> {
+        let _guard = STATE_WRITE_LOCK.lock().await;
+        let backend = self.backend.clone();
+        let query_name = query_name.to_string();
 
-        Ok(())
+        tokio::task::spawn_blocking(move || backend.update(&query_name, &mut change))
+            .await
+            .context("state persistence task failed")?
     }
 }
 
@@ -198,19 +243,50 @@ impl YetiiState {
         batches_sent: usize,
         watermark: Option<&WatermarkUpdate>,
     ) -> Result<()> {
-        let query_state = self.queries.entry(query_name.to_string()).or_default();
-        query_state.last_started_at = Some(started_at);
-        query_state.last_success_at = Some(completed_at);
-        query_state.last_rows_read = Some(rows_read);
-        query_state.last_batches_sent = Some(batches_sent);
+        let state = self.queries.entry(query_name.to_string()).or_default();
+        state.last_run_id = monitoring::current_run_id();
+        state.record(
+            query_name,
+            QueryOutcome::Success,
+            started_at,
+            completed_at,
+            rows_read,
+            batches_sent,
+            watermark,
+        )
+    }
+}
+
+impl QueryState {
+    #[allow(clippy::too_many_arguments)]
+    fn record(
+        &mut self,
+        query_name: &str,
+        outcome: QueryOutcome,
+        started_at: DateTime<Utc>,
+        completed_at: DateTime<Utc>,
+        rows_read: usize,
+        batches_sent: usize,
+        watermark: Option<&WatermarkUpdate>,
+    ) -> Result<()> {
+        self.last_started_at = Some(started_at);
+        if outcome == QueryOutcome::Success {
+            self.last_success_at = Some(completed_at);
+        }
+        self.last_outcome = Some(outcome);
+        self.last_rows_read = Some(rows_read);
+        self.last_batches_sent = Some(batches_sent);
+        // A partial run that kept the watermark still resumes from its last checkpoint.
+        if outcome == QueryOutcome::Success || watermark.is_some() {
+            self.checkpoint = None;
+        }
 
         if let Some(watermark) = watermark {
             let existing = watermark
                 .components
                 .iter()
                 .map(|component| {
-                    query_state
-                        .watermarks
+                    self.watermarks
                         .get(&component.watermark_name)
                         .map(|value| WatermarkComponent {
                             watermark_name: component.watermark_name.clone(),
@@ -236,8 +312,7 @@ impl YetiiState {
             };
             if should_advance {
                 for component in &watermark.components {
-                    query_state
-                        .watermarks
+                    self.watermarks
                         .insert(component.watermark_name.clone(), component.value.clone());
                 }
             }
@@ -314,6 +389,9 @@ pub fn extract_watermark(
     }
 
     let maximum = maximum.expect("non-empty rows set a maximum");
+    if let Some(timezone) = watermark.timezone() {
+        warn_on_daylight_saving_gaps(&query.name, &columns, &parameters, maximum, timezone);
+    }
     let components = columns
         .iter()
         .zip(parameter_names)
@@ -334,6 +412,44 @@ pub fn extract_watermark(
     Ok(Some(WatermarkUpdate { components }))
 }
 
+/// Local timestamps the clocks skip cannot be local time in the configured zone, and a maximum
+/// in a repeated hour is ambiguous until the clocks have passed it twice.
+fn warn_on_daylight_saving_gaps(
+    query_name: &str,
+    columns: &[&str],
+    parameters: &[&QueryParameter],
+    maximum: &Map<String, Value>,
+    timezone: WatermarkTimezone,
+) {
+    for (column, parameter) in columns.iter().zip(parameters) {
+        if !is_timestamp_type(&parameter.param_type) {
+            continue;
+        }
+        let Some((local, _)) = maximum
+            .get(*column)
+            .and_then(Value::as_str)
+            .and_then(local_timestamp)
+        else {
+            continue;
+        };
+        if timezone.skips(local) {
+            tracing::warn!(
+                query = %query_name,
+                column = %column,
+                value = %local,
+                "watermark value is in an hour skipped by a daylight saving change; check that the column holds local time in the configured timezone"
+            );
+        } else if timezone.repeated_by(local).is_some() {
+            tracing::warn!(
+                query = %query_name,
+                column = %column,
+                value = %local,
+                "watermark value is in an hour repeated by a daylight saving change; the next run reads that hour again"
+            );
+        }
+    }
+}
+
 pub fn current_watermark(
     query: &QueryConfig,
     parameters: &BTreeCompatibleParameters,
@@ -504,6 +620,12 @@ pub fn resolve_query_parameters(
                 )
             })?;
 
+        let value = match query.watermark.as_ref().and_then(WatermarkConfig::timezone) {
+            Some(timezone) if is_timestamp_type(&parameter.param_type) => {
+                rewind_repeated_hour(&query.name, parameter_name, value, timezone)
+            }
+            _ => value,
+        };
         parameter.default = Some(value);
         parameter.source = None;
         state_parameters.push(StateParameter {
@@ -515,6 +637,42 @@ pub fn resolve_query_parameters(
     Ok(state_parameters)
 }
 
+/// A local time the clocks pass twice may be from either pass, so the bound is moved back to
+/// the first one: rows of the repeated hour are read again rather than skipped.
+fn rewind_repeated_hour(
+    query_name: &str,
+    parameter_name: &str,
+    value: String,
+    timezone: WatermarkTimezone,
+) -> String {
+    let Some((local, format)) = local_timestamp(&value) else {
+        return value;
+    };
+    let Some(repeated_by) = timezone.repeated_by(local) else {
+        return value;
+    };
+    let bound = (local - repeated_by).format(format).to_string();
+    tracing::warn!(
+        query = %query_name,
+        parameter = %parameter_name,
+        watermark = %value,
+        bound = %bound,
+        "watermark is in an hour repeated by a daylight saving change; rows of that hour are read again and may be sent twice"
+    );
+    bound
+}
+
+/// A timestamp without an offset, with the format it was written in.
+fn local_timestamp(value: &str) -> Option<(NaiveDateTime, &'static str)> {
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .into_iter()
+        .find_map(|format| {
+            NaiveDateTime::parse_from_str(value, format)
+                .ok()
+                .map(|local| (local, format))
+        })
+}
+
 fn state_watermark_name(parameter_name: &str, parameter: &QueryParameter) -> Option<String> {
     match parameter.source.as_deref() {
         Some("state_file") => Some(parameter_name.to_string()),
@@ -530,27 +688,16 @@ fn state_watermark_name(parameter_name: &str, parameter: &QueryParameter) -> Opt
     }
 }
 
-fn backup_path(path: &Path, index: u32) -> PathBuf {
-    let mut backup = OsString::from(path.as_os_str());
-    backup.push(format!(".{index}"));
-    PathBuf::from(backup)
-}
-
-fn temporary_path(path: &Path) -> PathBuf {
-    let mut temporary = OsString::from(path.as_os_str());
-    temporary.push(format!(".tmp-{}", std::process::id()));
-    PathBuf::from(temporary)
-}
-
 pub type BTreeCompatibleParameters = std::collections::HashMap<String, QueryParameter>;
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::endpoint_config::EndpointConfig;
+    use crate::config::endpoint_config::{EndpointConfig, HttpMethod};
     use crate::config::sql_query::SqlQuery;
     use crate::config::transform_config::TransformConfig;
     use std::collections::HashMap;
+    use std::path::PathBuf;
 
     fn temp_state_path(name: &str) -> PathBuf {
         let unique = format!(
@@ -569,6 +716,7 @@ mod tests {
                 param_type: "timestamp".to_string(),
                 default: default.map(str::to_string),
                 source: Some("state_file".to_string()),
+                source_options: None,
             },
         );
 
@@ -576,12 +724,15 @@ mod tests {
             name: "orders_sync".to_string(),
             description: String::new(),
             enabled: true,
+            until: None,
             database: None,
             schedule: None,
             query: SqlQuery {
                 sql: "SELECT * FROM orders WHERE updated_at > $last_run_time".to_string(),
                 parameters: Some(parameters),
                 validation: None,
+                cost_check: None,
+                lint: None,
             },
             watermark: Some(crate::config::watermark_config::WatermarkConfig {
                 strategy: WatermarkStrategy::Max,
@@ -590,16 +741,24 @@ mod tests {
                 columns: None,
                 parameters: None,
                 page_size: None,
+                timezone: None,
             }),
+            incremental: None,
+            checkpoint: None,
             transform: TransformConfig::default(),
-            endpoint: EndpointConfig {
+            sort: None,
+            endpoint: Some(EndpointConfig {
                 url: "https://example.test".to_string(),
-                method: "POST".to_string(),
+                failover_url: Vec::new(),
+                method: HttpMethod::Post,
                 auth: None,
                 headers: None,
                 request: Default::default(),
                 response: None,
-            },
+            }),
+            destination: None,
+            source: None,
+            notifications: None,
         }
     }
 
@@ -659,6 +818,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn local_watermark_in_a_repeated_hour_is_read_from_its_first_pass() {
+        let mut query = query_with_state_parameter(None);
+        query.watermark.as_mut().unwrap().timezone = Some("Europe/Berlin".to_string());
+        query.validate().unwrap();
+        let resolve = |stored: &str| {
+            let mut state = YetiiState::default();
+            state
+                .queries
+                .entry("orders_sync".to_string())
+                .or_default()
+                .watermarks
+                .insert("last_run_time".to_string(), stored.to_string());
+            let mut parameters = query.query.parameters.clone();
+            resolve_query_parameters(&query, &mut parameters, &state).unwrap();
+            parameters.unwrap()["last_run_time"]
+                .default
+                .clone()
+                .unwrap()
+        };
+
+        assert_eq!("2025-10-26 01:30:00", resolve("2025-10-26 02:30:00"));
+        assert_eq!(
+            "2025-10-26T01:15:00.250",
+            resolve("2025-10-26T02:15:00.250")
+        );
+        assert_eq!("2025-10-26 03:30:00", resolve("2025-10-26 03:30:00"));
+        assert_eq!("2025-10-26T02:30:00Z", resolve("2025-10-26T02:30:00Z"));
+    }
+
     #[test]
     fn missing_state_and_default_is_error() {
         let query = query_with_state_parameter(None);
@@ -711,37 +900,6 @@ mod tests {
         );
     }
 
-    #[test]
-    fn save_rotates_backups() {
-        let path = temp_state_path("rotation");
-        let store = StateStore::new(&path, 2);
-        let mut state = YetiiState::default();
-
-        state
-            .record_success("first", Utc::now(), Utc::now(), 1, 1, None)
-            .unwrap();
-        store.save(&state).unwrap();
-        state
-            .record_success("second", Utc::now(), Utc::now(), 2, 1, None)
-            .unwrap();
-        store.save(&state).unwrap();
-        state
-            .record_success("third", Utc::now(), Utc::now(), 3, 1, None)
-            .unwrap();
-        store.save(&state).unwrap();
-
-        assert!(path.exists());
-        assert!(backup_path(&path, 1).exists());
-        assert!(backup_path(&path, 2).exists());
-
-        let loaded = store.load_or_default().unwrap();
-        assert!(loaded.query("third").is_some());
-
-        let _ = std::fs::remove_file(&path);
-        let _ = std::fs::remove_file(backup_path(&path, 1));
-        let _ = std::fs::remove_file(backup_path(&path, 2));
-    }
-
     #[test]
     fn extracts_maximum_timestamp_from_query_rows() {
         let query = query_with_state_parameter(Some("1970-01-01T00:00:00Z"));
@@ -863,6 +1021,7 @@ mod tests {
                     param_type: param_type.to_string(),
                     default: Some(default.to_string()),
                     source: Some("state_file".to_string()),
+                    source_options: None,
                 },
             );
         }
@@ -881,6 +1040,7 @@ mod tests {
                 "last_id".to_string(),
             ]),
             page_size: Some(100),
+            timezone: None,
         });
         query.validate().unwrap();
         let rows = vec![
@@ -929,4 +1089,36 @@ mod tests {
 
         let _ = std::fs::remove_file(&path);
     }
+
+    #[tokio::test]
+    async fn records_the_run_id_of_the_current_run() {
+        let path = temp_state_path("run-id");
+        let store = StateStore::new(&path, 0);
+
+        let recorded = monitoring::for_run(
+            "0123456789abcdef",
+            store.record_success("orders", Utc::now(), 10, 1, None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(Some("0123456789abcdef"), recorded.last_run_id.as_deref());
+
+        let recorded = store
+            .record_success("orders", Utc::now(), 10, 1, None)
+            .await
+            .unwrap();
+        assert_eq!(None, recorded.last_run_id);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn keeps_the_redis_url_out_of_debug_output() {
+        let backend: StateBackendConfig =
+            serde_yaml::from_str("type: redis\nurl: redis://:hunter2@state:6379/2\n").unwrap();
+
+        let debug = format!("{backend:?}");
+        assert!(debug.contains("url: ***"));
+        assert!(!debug.contains("hunter2"));
+    }
 }
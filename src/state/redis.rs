@@ -0,0 +1,102 @@
+//! State in a Redis hash, one field per query, shared by daemons on several hosts. Writes are
+//! optimistic: a change is applied again when another process wrote the hash meanwhile.
+
+use super::{QueryState, StateBackend, YetiiState};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub(super) struct RedisState {
+    url: String,
+    key: String,
+}
+
+impl RedisState {
+    pub(super) fn new(url: &str, key: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            key: key.to_string(),
+        }
+    }
+
+    fn connect(&self) -> Result<redis::Connection> {
+        redis::Client::open(self.url.as_str())
+            .and_then(|client| client.get_connection_with_timeout(CONNECT_TIMEOUT))
+            .with_context(|| format!("failed to connect to {}", self.location()))
+    }
+
+    fn parse(&self, query_name: &str, content: &str) -> Result<QueryState> {
+        serde_json::from_str(content).with_context(|| {
+            format!(
+                "failed to parse state of query '{query_name}' in {}",
+                self.location()
+            )
+        })
+    }
+}
+
+impl StateBackend for RedisState {
+    fn location(&self) -> String {
+        // The URL usually carries the password, so only the host is named.
+        let host = url::Url::parse(&self.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        format!("Redis state '{}' on {host}", self.key)
+    }
+
+    fn load(&self) -> Result<YetiiState> {
+        let mut connection = self.connect()?;
+        let fields: HashMap<String, String> = redis::cmd("HGETALL")
+            .arg(&self.key)
+            .query(&mut connection)
+            .with_context(|| format!("failed to read {}", self.location()))?;
+        let mut state = YetiiState::default();
+        for (query_name, content) in fields {
+            let query_state = self.parse(&query_name, &content)?;
+            state.queries.insert(query_name, query_state);
+        }
+        Ok(state)
+    }
+
+    fn update(
+        &self,
+        query_name: &str,
+        change: &mut dyn FnMut(&mut QueryState) -> Result<()>,
+    ) -> Result<QueryState> {
+        let mut connection = self.connect()?;
+        loop {
+            redis::cmd("WATCH")
+                .arg(&self.key)
+                .query::<()>(&mut connection)?;
+            let content: Option<String> = redis::cmd("HGET")
+                .arg(&self.key)
+                .arg(query_name)
+                .query(&mut connection)
+                .with_context(|| format!("failed to read {}", self.location()))?;
+            let mut query_state = match content {
+                Some(content) => self.parse(query_name, &content)?,
+                None => QueryState::default(),
+            };
+            change(&mut query_state)?;
+            let content =
+                serde_json::to_string(&query_state).context("failed to serialize state")?;
+            // `EXEC` answers nil when the hash changed after `WATCH`.
+            let written: Option<()> = redis::pipe()
+                .atomic()
+                .hset(&self.key, query_name, content)
+                .query(&mut connection)
+                .with_context(|| format!("failed to write {}", self.location()))?;
+            if written.is_some() {
+                return Ok(query_state);
+            }
+            tracing::debug!(
+                query = %query_name,
+                "state changed by another process during the update; retrying"
+            );
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! State in a SQLite database, one row per query, so a change writes only its own query.
+
+use super::{QueryState, StateBackend, YetiiState};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior, params};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long a writer waits for another process's transaction before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub(super) struct SqliteState {
+    path: PathBuf,
+}
+
+impl SqliteState {
+    pub(super) fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("failed to create state directory '{}'", parent.display())
+            })?;
+        }
+        let connection = Connection::open(&self.path)
+            .with_context(|| format!("failed to open {}", self.location()))?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection
+            .pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS query_state (
+                query_name TEXT PRIMARY KEY,
+                state TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(connection)
+    }
+}
+
+impl StateBackend for SqliteState {
+    fn location(&self) -> String {
+        format!("SQLite state '{}'", self.path.display())
+    }
+
+    fn load(&self) -> Result<YetiiState> {
+        let connection = self.connect()?;
+        let mut statement = connection.prepare("SELECT query_name, state FROM query_state")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut state = YetiiState::default();
+        for row in rows {
+            let (query_name, content) = row?;
+            let query_state = serde_json::from_str(&content).with_context(|| {
+                format!(
+                    "failed to parse state of query '{query_name}' in {}",
+                    self.location()
+                )
+            })?;
+            state.queries.insert(query_name, query_state);
+        }
+        Ok(state)
+    }
+
+    fn update(
+        &self,
+        query_name: &str,
+        change: &mut dyn FnMut(&mut QueryState) -> Result<()>,
+    ) -> Result<QueryState> {
+        let mut connection = self.connect()?;
+        // Taking the write lock up front keeps another process from changing the row between
+        // the read and the write.
+        let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let content: Option<String> = transaction
+            .query_row(
+                "SELECT state FROM query_state WHERE query_name = ?1",
+                params![query_name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let mut query_state: QueryState = match content {
+            Some(content) => serde_json::from_str(&content).with_context(|| {
+                format!(
+                    "failed to parse state of query '{query_name}' in {}",
+                    self.location()
+                )
+            })?,
+            None => QueryState::default(),
+        };
+        change(&mut query_state)?;
+        transaction.execute(
+            "INSERT INTO query_state (query_name, state, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (query_name) DO UPDATE SET state = excluded.state, updated_at = excluded.updated_at",
+            params![
+                query_name,
+                serde_json::to_string(&query_state).context("failed to serialize state")?,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        transaction.commit()?;
+        Ok(query_state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_one_query_at_a_time() {
+        let path = std::env::temp_dir().join(format!("yetii-state-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = SqliteState::new(&path);
+
+        store
+            .update("orders", &mut |state| {
+                state
+                    .watermarks
+                    .insert("last_id".to_string(), "7".to_string());
+                Ok(())
+            })
+            .unwrap();
+        let customers = store
+            .update("customers", &mut |state| {
+                state.last_rows_read = Some(3);
+                Ok(())
+            })
+            .unwrap();
+        assert!(
+            store
+                .update("orders", &mut |_| Err(anyhow::anyhow!("rejected")))
+                .is_err()
+        );
+        let state = store.load().unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(Some(3), customers.last_rows_read);
+        assert_eq!(2, state.queries.len());
+        assert_eq!("7", state.query("orders").unwrap().watermarks["last_id"]);
+        assert_eq!(Some(3), state.query("customers").unwrap().last_rows_read);
+    }
+}
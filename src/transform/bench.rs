@@ -0,0 +1,170 @@
+//! Throughput of each transform step on fixture rows, measured by `yetii bench-transforms`.
+
+use super::{TransformError, apply_conversions, apply_filters, apply_mappings, group};
+use crate::config::transform_config::{DataConversion, TransformConfig};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Records per second of one step, from the median of the timed iterations.
+#[derive(Debug, Clone)]
+pub struct StepThroughput {
+    pub step: String,
+    pub records: usize,
+    pub records_per_second: f64,
+}
+
+type RunStep<'a> = Box<dyn Fn(Vec<Map<String, Value>>) -> Result<(), TransformError> + 'a>;
+
+/// One step the transform configures, with the rows it gets in a run.
+pub struct Step<'a> {
+    pub name: String,
+    pub rows: Vec<Map<String, Value>>,
+    run: RunStep<'a>,
+}
+
+impl Step<'_> {
+    /// Applies the step to `rows`, discarding its output.
+    pub fn run(&self, rows: Vec<Map<String, Value>>) -> Result<(), TransformError> {
+        (self.run)(rows)
+    }
+}
+
+/// Every step the transform configures, conversions once per target type. Each step gets the
+/// rows the steps before it produce, as in a run. Enrichment lookups call other systems and
+/// are left out, and rejected rows are not written to the reject file.
+pub fn steps<'a>(
+    rows: &[Map<String, Value>],
+    transform: &'a TransformConfig,
+) -> Result<Vec<Step<'a>>, TransformError> {
+    let mut steps = Vec::new();
+    let mut rows = rows.to_vec();
+    if let Some(filters) = transform.filters.as_deref() {
+        steps.push(Step {
+            name: "filter".to_string(),
+            rows: rows.clone(),
+            run: Box::new(|rows| {
+                apply_filters(rows, Some(filters));
+                Ok(())
+            }),
+        });
+        rows = apply_filters(rows, Some(filters));
+    }
+    if let Some(conversions) = &transform.conversions {
+        let mut by_target: BTreeMap<&str, HashMap<String, DataConversion>> = BTreeMap::new();
+        for (field, conversion) in conversions {
+            by_target
+                .entry(conversion.target.name())
+                .or_default()
+                .insert(field.clone(), conversion.clone());
+        }
+        for (target, conversions) in by_target {
+            steps.push(Step {
+                name: format!("conversion:{target}"),
+                rows: rows.clone(),
+                run: Box::new(move |rows| {
+                    apply_conversions(rows, Some(&conversions), None).map(drop)
+                }),
+            });
+        }
+        rows = apply_conversions(rows, Some(conversions), None)?;
+    }
+    if let Some(mappings) = &transform.mappings {
+        steps.push(Step {
+            name: "mapping".to_string(),
+            rows: rows.clone(),
+            run: Box::new(|mut rows| {
+                apply_mappings(&mut rows, Some(mappings));
+                Ok(())
+            }),
+        });
+        apply_mappings(&mut rows, Some(mappings));
+    }
+    if let Some(field) = transform.group_by.as_deref() {
+        steps.push(Step {
+            name: "group_by".to_string(),
+            rows,
+            run: Box::new(|rows| {
+                group::group_rows(rows, field);
+                Ok(())
+            }),
+        });
+    }
+    Ok(steps)
+}
+
+/// Times each of the transform's [`steps`].
+pub fn measure(
+    rows: &[Map<String, Value>],
+    transform: &TransformConfig,
+    iterations: usize,
+) -> Result<Vec<StepThroughput>, TransformError> {
+    steps(rows, transform)?
+        .iter()
+        .map(|step| time(step, iterations))
+        .collect()
+}
+
+fn time(step: &Step, iterations: usize) -> Result<StepThroughput, TransformError> {
+    // An untimed pass first, so the first sample does not pay for cold caches.
+    step.run(step.rows.clone())?;
+    let mut samples = Vec::with_capacity(iterations.max(1));
+    for _ in 0..iterations.max(1) {
+        let input = step.rows.clone();
+        let started = Instant::now();
+        step.run(input)?;
+        samples.push(started.elapsed());
+    }
+    samples.sort();
+    let median = samples[samples.len() / 2].max(Duration::from_nanos(1));
+    Ok(StepThroughput {
+        step: step.name.clone(),
+        records: step.rows.len(),
+        records_per_second: step.rows.len() as f64 / median.as_secs_f64(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_each_configured_step_and_conversion_target() {
+        let transform: TransformConfig = serde_yaml::from_str(
+            "filters:\n  - field: status\n    condition: ne\n    value: void\nconversions:\n  amount:\n    from: string\n    to: number\n  quantity:\n    from: string\n    to: integer\n  code:\n    from: string\n    to: string\nmappings:\n  amount: total\ngroup_by: customer\n",
+        )
+        .unwrap();
+        let rows: Vec<Map<String, Value>> = (0..20)
+            .map(|index| {
+                serde_json::json!({
+                    "customer": format!("c{}", index % 3),
+                    "status": if index % 4 == 0 { "void" } else { "open" },
+                    "amount": "12.5",
+                    "quantity": "2",
+                    "code": index,
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+            })
+            .collect();
+
+        let results = measure(&rows, &transform, 3).unwrap();
+        let steps: Vec<_> = results.iter().map(|result| result.step.as_str()).collect();
+        assert_eq!(
+            vec![
+                "filter",
+                "conversion:integer",
+                "conversion:number",
+                "conversion:string",
+                "mapping",
+                "group_by"
+            ],
+            steps
+        );
+        assert_eq!(20, results[0].records);
+        // Voided rows are filtered out before the later steps.
+        assert_eq!(15, results[1].records);
+        assert!(results.iter().all(|result| result.records_per_second > 0.0));
+    }
+}
@@ -0,0 +1,659 @@
+//! Built-in lookups for fields that most ERP exports share: units of measure, countries,
+//! currencies, and GTIN/EAN article numbers.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// ISO 3166-1 alpha-2 and alpha-3 codes with the English short name.
+const COUNTRIES: &[(&str, &str, &str)] = &[
+    ("AF", "AFG", "Afghanistan"),
+    ("AX", "ALA", "Åland Islands"),
+    ("AL", "ALB", "Albania"),
+    ("DZ", "DZA", "Algeria"),
+    ("AS", "ASM", "American Samoa"),
+    ("AD", "AND", "Andorra"),
+    ("AO", "AGO", "Angola"),
+    ("AI", "AIA", "Anguilla"),
+    ("AQ", "ATA", "Antarctica"),
+    ("AG", "ATG", "Antigua and Barbuda"),
+    ("AR", "ARG", "Argentina"),
+    ("AM", "ARM", "Armenia"),
+    ("AW", "ABW", "Aruba"),
+    ("AU", "AUS", "Australia"),
+    ("AT", "AUT", "Austria"),
+    ("AZ", "AZE", "Azerbaijan"),
+    ("BS", "BHS", "Bahamas"),
+    ("BH", "BHR", "Bahrain"),
+    ("BD", "BGD", "Bangladesh"),
+    ("BB", "BRB", "Barbados"),
+    ("BY", "BLR", "Belarus"),
+    ("BE", "BEL", "Belgium"),
+    ("BZ", "BLZ", "Belize"),
+    ("BJ", "BEN", "Benin"),
+    ("BM", "BMU", "Bermuda"),
+    ("BT", "BTN", "Bhutan"),
+    ("BO", "BOL", "Bolivia"),
+    ("BQ", "BES", "Bonaire, Sint Eustatius and Saba"),
+    ("BA", "BIH", "Bosnia and Herzegovina"),
+    ("BW", "BWA", "Botswana"),
+    ("BV", "BVT", "Bouvet Island"),
+    ("BR", "BRA", "Brazil"),
+    ("IO", "IOT", "British Indian Ocean Territory"),
+    ("BN", "BRN", "Brunei Darussalam"),
+    ("BG", "BGR", "Bulgaria"),
+    ("BF", "BFA", "Burkina Faso"),
+    ("BI", "BDI", "Burundi"),
+    ("CV", "CPV", "Cabo Verde"),
+    ("KH", "KHM", "Cambodia"),
+    ("CM", "CMR", "Cameroon"),
+    ("CA", "CAN", "Canada"),
+    ("KY", "CYM", "Cayman Islands"),
+    ("CF", "CAF", "Central African Republic"),
+    ("TD", "TCD", "Chad"),
+    ("CL", "CHL", "Chile"),
+    ("CN", "CHN", "China"),
+    ("CX", "CXR", "Christmas Island"),
+    ("CC", "CCK", "Cocos (Keeling) Islands"),
+    ("CO", "COL", "Colombia"),
+    ("KM", "COM", "Comoros"),
+    ("CG", "COG", "Congo"),
+    ("CD", "COD", "Democratic Republic of the Congo"),
+    ("CK", "COK", "Cook Islands"),
+    ("CR", "CRI", "Costa Rica"),
+    ("CI", "CIV", "Côte d'Ivoire"),
+    ("HR", "HRV", "Croatia"),
+    ("CU", "CUB", "Cuba"),
+    ("CW", "CUW", "Curaçao"),
+    ("CY", "CYP", "Cyprus"),
+    ("CZ", "CZE", "Czechia"),
+    ("DK", "DNK", "Denmark"),
+    ("DJ", "DJI", "Djibouti"),
+    ("DM", "DMA", "Dominica"),
+    ("DO", "DOM", "Dominican Republic"),
+    ("EC", "ECU", "Ecuador"),
+    ("EG", "EGY", "Egypt"),
+    ("SV", "SLV", "El Salvador"),
+    ("GQ", "GNQ", "Equatorial Guinea"),
+    ("ER", "ERI", "Eritrea"),
+    ("EE", "EST", "Estonia"),
+    ("SZ", "SWZ", "Eswatini"),
+    ("ET", "ETH", "Ethiopia"),
+    ("FK", "FLK", "Falkland Islands"),
+    ("FO", "FRO", "Faroe Islands"),
+    ("FJ", "FJI", "Fiji"),
+    ("FI", "FIN", "Finland"),
+    ("FR", "FRA", "France"),
+    ("GF", "GUF", "French Guiana"),
+    ("PF", "PYF", "French Polynesia"),
+    ("TF", "ATF", "French Southern Territories"),
+    ("GA", "GAB", "Gabon"),
+    ("GM", "GMB", "Gambia"),
+    ("GE", "GEO", "Georgia"),
+    ("DE", "DEU", "Germany"),
+    ("GH", "GHA", "Ghana"),
+    ("GI", "GIB", "Gibraltar"),
+    ("GR", "GRC", "Greece"),
+    ("GL", "GRL", "Greenland"),
+    ("GD", "GRD", "Grenada"),
+    ("GP", "GLP", "Guadeloupe"),
+    ("GU", "GUM", "Guam"),
+    ("GT", "GTM", "Guatemala"),
+    ("GG", "GGY", "Guernsey"),
+    ("GN", "GIN", "Guinea"),
+    ("GW", "GNB", "Guinea-Bissau"),
+    ("GY", "GUY", "Guyana"),
+    ("HT", "HTI", "Haiti"),
+    ("HM", "HMD", "Heard Island and McDonald Islands"),
+    ("VA", "VAT", "Holy See"),
+    ("HN", "HND", "Honduras"),
+    ("HK", "HKG", "Hong Kong"),
+    ("HU", "HUN", "Hungary"),
+    ("IS", "ISL", "Iceland"),
+    ("IN", "IND", "India"),
+    ("ID", "IDN", "Indonesia"),
+    ("IR", "IRN", "Iran"),
+    ("IQ", "IRQ", "Iraq"),
+    ("IE", "IRL", "Ireland"),
+    ("IM", "IMN", "Isle of Man"),
+    ("IL", "ISR", "Israel"),
+    ("IT", "ITA", "Italy"),
+    ("JM", "JAM", "Jamaica"),
+    ("JP", "JPN", "Japan"),
+    ("JE", "JEY", "Jersey"),
+    ("JO", "JOR", "Jordan"),
+    ("KZ", "KAZ", "Kazakhstan"),
+    ("KE", "KEN", "Kenya"),
+    ("KI", "KIR", "Kiribati"),
+    ("KP", "PRK", "North Korea"),
+    ("KR", "KOR", "South Korea"),
+    ("KW", "KWT", "Kuwait"),
+    ("KG", "KGZ", "Kyrgyzstan"),
+    ("LA", "LAO", "Lao People's Democratic Republic"),
+    ("LV", "LVA", "Latvia"),
+    ("LB", "LBN", "Lebanon"),
+    ("LS", "LSO", "Lesotho"),
+    ("LR", "LBR", "Liberia"),
+    ("LY", "LBY", "Libya"),
+    ("LI", "LIE", "Liechtenstein"),
+    ("LT", "LTU", "Lithuania"),
+    ("LU", "LUX", "Luxembourg"),
+    ("MO", "MAC", "Macao"),
+    ("MG", "MDG", "Madagascar"),
+    ("MW", "MWI", "Malawi"),
+    ("MY", "MYS", "Malaysia"),
+    ("MV", "MDV", "Maldives"),
+    ("ML", "MLI", "Mali"),
+    ("MT", "MLT", "Malta"),
+    ("MH", "MHL", "Marshall Islands"),
+    ("MQ", "MTQ", "Martinique"),
+    ("MR", "MRT", "Mauritania"),
+    ("MU", "MUS", "Mauritius"),
+    ("YT", "MYT", "Mayotte"),
+    ("MX", "MEX", "Mexico"),
+    ("FM", "FSM", "Micronesia"),
+    ("MD", "MDA", "Moldova"),
+    ("MC", "MCO", "Monaco"),
+    ("MN", "MNG", "Mongolia"),
+    ("ME", "MNE", "Montenegro"),
+    ("MS", "MSR", "Montserrat"),
+    ("MA", "MAR", "Morocco"),
+    ("MZ", "MOZ", "Mozambique"),
+    ("MM", "MMR", "Myanmar"),
+    ("NA", "NAM", "Namibia"),
+    ("NR", "NRU", "Nauru"),
+    ("NP", "NPL", "Nepal"),
+    ("NL", "NLD", "Netherlands"),
+    ("NC", "NCL", "New Caledonia"),
+    ("NZ", "NZL", "New Zealand"),
+    ("NI", "NIC", "Nicaragua"),
+    ("NE", "NER", "Niger"),
+    ("NG", "NGA", "Nigeria"),
+    ("NU", "NIU", "Niue"),
+    ("NF", "NFK", "Norfolk Island"),
+    ("MK", "MKD", "North Macedonia"),
+    ("MP", "MNP", "Northern Mariana Islands"),
+    ("NO", "NOR", "Norway"),
+    ("OM", "OMN", "Oman"),
+    ("PK", "PAK", "Pakistan"),
+    ("PW", "PLW", "Palau"),
+    ("PS", "PSE", "Palestine"),
+    ("PA", "PAN", "Panama"),
+    ("PG", "PNG", "Papua New Guinea"),
+    ("PY", "PRY", "Paraguay"),
+    ("PE", "PER", "Peru"),
+    ("PH", "PHL", "Philippines"),
+    ("PN", "PCN", "Pitcairn"),
+    ("PL", "POL", "Poland"),
+    ("PT", "PRT", "Portugal"),
+    ("PR", "PRI", "Puerto Rico"),
+    ("QA", "QAT", "Qatar"),
+    ("RE", "REU", "Réunion"),
+    ("RO", "ROU", "Romania"),
+    ("RU", "RUS", "Russian Federation"),
+    ("RW", "RWA", "Rwanda"),
+    ("BL", "BLM", "Saint Barthélemy"),
+    ("SH", "SHN", "Saint Helena, Ascension and Tristan da Cunha"),
+    ("KN", "KNA", "Saint Kitts and Nevis"),
+    ("LC", "LCA", "Saint Lucia"),
+    ("MF", "MAF", "Saint Martin (French part)"),
+    ("PM", "SPM", "Saint Pierre and Miquelon"),
+    ("VC", "VCT", "Saint Vincent and the Grenadines"),
+    ("WS", "WSM", "Samoa"),
+    ("SM", "SMR", "San Marino"),
+    ("ST", "STP", "Sao Tome and Principe"),
+    ("SA", "SAU", "Saudi Arabia"),
+    ("SN", "SEN", "Senegal"),
+    ("RS", "SRB", "Serbia"),
+    ("SC", "SYC", "Seychelles"),
+    ("SL", "SLE", "Sierra Leone"),
+    ("SG", "SGP", "Singapore"),
+    ("SX", "SXM", "Sint Maarten (Dutch part)"),
+    ("SK", "SVK", "Slovakia"),
+    ("SI", "SVN", "Slovenia"),
+    ("SB", "SLB", "Solomon Islands"),
+    ("SO", "SOM", "Somalia"),
+    ("ZA", "ZAF", "South Africa"),
+    ("GS", "SGS", "South Georgia and the South Sandwich Islands"),
+    ("SS", "SSD", "South Sudan"),
+    ("ES", "ESP", "Spain"),
+    ("LK", "LKA", "Sri Lanka"),
+    ("SD", "SDN", "Sudan"),
+    ("SR", "SUR", "Suriname"),
+    ("SJ", "SJM", "Svalbard and Jan Mayen"),
+    ("SE", "SWE", "Sweden"),
+    ("CH", "CHE", "Switzerland"),
+    ("SY", "SYR", "Syrian Arab Republic"),
+    ("TW", "TWN", "Taiwan"),
+    ("TJ", "TJK", "Tajikistan"),
+    ("TZ", "TZA", "Tanzania"),
+    ("TH", "THA", "Thailand"),
+    ("TL", "TLS", "Timor-Leste"),
+    ("TG", "TGO", "Togo"),
+    ("TK", "TKL", "Tokelau"),
+    ("TO", "TON", "Tonga"),
+    ("TT", "TTO", "Trinidad and Tobago"),
+    ("TN", "TUN", "Tunisia"),
+    ("TR", "TUR", "Türkiye"),
+    ("TM", "TKM", "Turkmenistan"),
+    ("TC", "TCA", "Turks and Caicos Islands"),
+    ("TV", "TUV", "Tuvalu"),
+    ("UG", "UGA", "Uganda"),
+    ("UA", "UKR", "Ukraine"),
+    ("AE", "ARE", "United Arab Emirates"),
+    ("GB", "GBR", "United Kingdom"),
+    ("US", "USA", "United States"),
+    ("UM", "UMI", "United States Minor Outlying Islands"),
+    ("UY", "URY", "Uruguay"),
+    ("UZ", "UZB", "Uzbekistan"),
+    ("VU", "VUT", "Vanuatu"),
+    ("VE", "VEN", "Venezuela"),
+    ("VN", "VNM", "Viet Nam"),
+    ("VG", "VGB", "British Virgin Islands"),
+    ("VI", "VIR", "United States Virgin Islands"),
+    ("WF", "WLF", "Wallis and Futuna"),
+    ("EH", "ESH", "Western Sahara"),
+    ("YE", "YEM", "Yemen"),
+    ("ZM", "ZMB", "Zambia"),
+    ("ZW", "ZWE", "Zimbabwe"),
+];
+
+/// Other names and codes that ERP data commonly uses for a country, with its alpha-2 code.
+const COUNTRY_ALIASES: &[(&str, &str)] = &[
+    ("UK", "GB"),
+    ("Great Britain", "GB"),
+    ("United Kingdom of Great Britain and Northern Ireland", "GB"),
+    ("United States of America", "US"),
+    ("Vietnam", "VN"),
+    ("Russia", "RU"),
+    ("Turkey", "TR"),
+    ("Czech Republic", "CZ"),
+    ("Swaziland", "SZ"),
+    ("Ivory Coast", "CI"),
+    ("Cape Verde", "CV"),
+    ("Macedonia", "MK"),
+    ("Republic of Korea", "KR"),
+    ("Korea, Republic of", "KR"),
+    ("Iran, Islamic Republic of", "IR"),
+    ("Bolivia, Plurinational State of", "BO"),
+    ("Venezuela, Bolivarian Republic of", "VE"),
+    ("Tanzania, United Republic of", "TZ"),
+    ("Moldova, Republic of", "MD"),
+    ("Laos", "LA"),
+    ("Syria", "SY"),
+    ("Brunei", "BN"),
+    ("Burma", "MM"),
+    ("DR Congo", "CD"),
+    ("Congo, Democratic Republic of the", "CD"),
+    ("Vatican City", "VA"),
+    ("Palestine, State of", "PS"),
+    ("Micronesia, Federated States of", "FM"),
+];
+
+/// Active ISO 4217 codes with their numeric code.
+const CURRENCIES: &[(&str, &str)] = &[
+    ("AED", "784"),
+    ("AFN", "971"),
+    ("ALL", "008"),
+    ("AMD", "051"),
+    ("AOA", "973"),
+    ("ARS", "032"),
+    ("AUD", "036"),
+    ("AWG", "533"),
+    ("AZN", "944"),
+    ("BAM", "977"),
+    ("BBD", "052"),
+    ("BDT", "050"),
+    ("BGN", "975"),
+    ("BHD", "048"),
+    ("BIF", "108"),
+    ("BMD", "060"),
+    ("BND", "096"),
+    ("BOB", "068"),
+    ("BRL", "986"),
+    ("BSD", "044"),
+    ("BTN", "064"),
+    ("BWP", "072"),
+    ("BYN", "933"),
+    ("BZD", "084"),
+    ("CAD", "124"),
+    ("CDF", "976"),
+    ("CHF", "756"),
+    ("CLP", "152"),
+    ("CNY", "156"),
+    ("COP", "170"),
+    ("CRC", "188"),
+    ("CUP", "192"),
+    ("CVE", "132"),
+    ("CZK", "203"),
+    ("DJF", "262"),
+    ("DKK", "208"),
+    ("DOP", "214"),
+    ("DZD", "012"),
+    ("EGP", "818"),
+    ("ERN", "232"),
+    ("ETB", "230"),
+    ("EUR", "978"),
+    ("FJD", "242"),
+    ("FKP", "238"),
+    ("GBP", "826"),
+    ("GEL", "981"),
+    ("GHS", "936"),
+    ("GIP", "292"),
+    ("GMD", "270"),
+    ("GNF", "324"),
+    ("GTQ", "320"),
+    ("GYD", "328"),
+    ("HKD", "344"),
+    ("HNL", "340"),
+    ("HTG", "332"),
+    ("HUF", "348"),
+    ("IDR", "360"),
+    ("ILS", "376"),
+    ("INR", "356"),
+    ("IQD", "368"),
+    ("IRR", "364"),
+    ("ISK", "352"),
+    ("JMD", "388"),
+    ("JOD", "400"),
+    ("JPY", "392"),
+    ("KES", "404"),
+    ("KGS", "417"),
+    ("KHR", "116"),
+    ("KMF", "174"),
+    ("KPW", "408"),
+    ("KRW", "410"),
+    ("KWD", "414"),
+    ("KYD", "136"),
+    ("KZT", "398"),
+    ("LAK", "418"),
+    ("LBP", "422"),
+    ("LKR", "144"),
+    ("LRD", "430"),
+    ("LSL", "426"),
+    ("LYD", "434"),
+    ("MAD", "504"),
+    ("MDL", "498"),
+    ("MGA", "969"),
+    ("MKD", "807"),
+    ("MMK", "104"),
+    ("MNT", "496"),
+    ("MOP", "446"),
+    ("MRU", "929"),
+    ("MUR", "480"),
+    ("MVR", "462"),
+    ("MWK", "454"),
+    ("MXN", "484"),
+    ("MYR", "458"),
+    ("MZN", "943"),
+    ("NAD", "516"),
+    ("NGN", "566"),
+    ("NIO", "558"),
+    ("NOK", "578"),
+    ("NPR", "524"),
+    ("NZD", "554"),
+    ("OMR", "512"),
+    ("PAB", "590"),
+    ("PEN", "604"),
+    ("PGK", "598"),
+    ("PHP", "608"),
+    ("PKR", "586"),
+    ("PLN", "985"),
+    ("PYG", "600"),
+    ("QAR", "634"),
+    ("RON", "946"),
+    ("RSD", "941"),
+    ("RUB", "643"),
+    ("RWF", "646"),
+    ("SAR", "682"),
+    ("SBD", "090"),
+    ("SCR", "690"),
+    ("SDG", "938"),
+    ("SEK", "752"),
+    ("SGD", "702"),
+    ("SHP", "654"),
+    ("SLE", "925"),
+    ("SOS", "706"),
+    ("SRD", "968"),
+    ("SSP", "728"),
+    ("STN", "930"),
+    ("SVC", "222"),
+    ("SYP", "760"),
+    ("SZL", "748"),
+    ("THB", "764"),
+    ("TJS", "972"),
+    ("TMT", "934"),
+    ("TND", "788"),
+    ("TOP", "776"),
+    ("TRY", "949"),
+    ("TTD", "780"),
+    ("TWD", "901"),
+    ("TZS", "834"),
+    ("UAH", "980"),
+    ("UGX", "800"),
+    ("USD", "840"),
+    ("UYU", "858"),
+    ("UZS", "860"),
+    ("VES", "928"),
+    ("VND", "704"),
+    ("VUV", "548"),
+    ("WST", "882"),
+    ("XAF", "950"),
+    ("XCD", "951"),
+    ("XCG", "532"),
+    ("XOF", "952"),
+    ("XPF", "953"),
+    ("YER", "886"),
+    ("ZAR", "710"),
+    ("ZMW", "967"),
+    ("ZWG", "924"),
+];
+
+/// Currency symbols that name a single currency. `$`, `¥`, and `kr` are shared by several and
+/// are not converted.
+const CURRENCY_SYMBOLS: &[(&str, &str)] = &[
+    ("€", "EUR"),
+    ("£", "GBP"),
+    ("₹", "INR"),
+    ("₩", "KRW"),
+    ("₦", "NGN"),
+    ("₺", "TRY"),
+    ("₴", "UAH"),
+    ("₱", "PHP"),
+    ("₽", "RUB"),
+    ("₫", "VND"),
+    ("₪", "ILS"),
+];
+
+/// UN/ECE Recommendation 20 codes and the spellings ERP systems use for them.
+const UNITS_OF_MEASURE: &[(&str, &[&str])] = &[
+    ("H87", &["PC", "PCS", "PCE", "ST", "STK", "PIECE", "PIECES"]),
+    ("EA", &["EACH"]),
+    ("C62", &["UNIT", "UNITS"]),
+    ("KGM", &["KG", "KGS", "KILO", "KILOGRAM", "KILOGRAMS"]),
+    ("GRM", &["G", "GR", "GRAM", "GRAMS"]),
+    ("MGM", &["MG"]),
+    ("TNE", &["T", "TO", "TONNE", "TONNES"]),
+    ("LBR", &["LB", "LBS"]),
+    ("ONZ", &["OZ"]),
+    ("LTR", &["L", "LT", "LITER", "LITRE", "LITERS", "LITRES"]),
+    ("MLT", &["ML"]),
+    ("MTQ", &["M3", "CBM"]),
+    ("MTK", &["M2", "SQM"]),
+    ("MTR", &["M", "METER", "METRE", "METERS", "METRES"]),
+    ("KMT", &["KM"]),
+    ("CMT", &["CM"]),
+    ("MMT", &["MM"]),
+    ("INH", &["IN", "INCH"]),
+    ("FOT", &["FT", "FOOT", "FEET"]),
+    ("HUR", &["H", "HR", "HRS", "HOUR", "HOURS"]),
+    ("MIN", &["MINUTE", "MINUTES"]),
+    ("DAY", &["D", "DAYS", "TAG"]),
+    ("KWH", &[]),
+    ("BX", &["BOX"]),
+    ("CT", &["CTN", "CARTON"]),
+    ("PK", &["PAK", "PACK"]),
+    ("PF", &["PAL", "PALLET"]),
+    ("SET", &["SETS"]),
+    ("PR", &["PAIR", "PAIRS"]),
+    ("DZN", &["DZ", "DOZEN"]),
+];
+
+static COUNTRY_INDEX: LazyLock<HashMap<String, usize>> = LazyLock::new(|| {
+    let mut index = HashMap::new();
+    for (position, (alpha2, alpha3, name)) in COUNTRIES.iter().enumerate() {
+        index.insert(alpha2.to_string(), position);
+        index.insert(alpha3.to_string(), position);
+        index.insert(name_key(name), position);
+    }
+    for (alias, alpha2) in COUNTRY_ALIASES {
+        if let Some(position) = COUNTRIES.iter().position(|(code, _, _)| code == alpha2) {
+            index.insert(name_key(alias), position);
+        }
+    }
+    index
+});
+
+static UNIT_INDEX: LazyLock<HashMap<&'static str, &'static str>> = LazyLock::new(|| {
+    UNITS_OF_MEASURE
+        .iter()
+        .flat_map(|(code, aliases)| {
+            std::iter::once((*code, *code)).chain(aliases.iter().map(|alias| (*alias, *code)))
+        })
+        .collect()
+});
+
+/// Names are matched on their letters and digits only, ignoring case, so `Cote d'Ivoire` and
+/// `COTE DIVOIRE` are one key. Codes are upper case and so never collide with a name key.
+fn name_key(name: &str) -> String {
+    name.chars()
+        .filter(|character| character.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .map(|character| match character {
+            'á' | 'à' | 'â' | 'ä' | 'å' | 'ã' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ó' | 'ò' | 'ô' | 'ö' | 'õ' => 'o',
+            'ú' | 'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            other => other,
+        })
+        .collect()
+}
+
+/// The ISO 3166-1 code of a country given by alpha-2 code, alpha-3 code, or English name.
+pub(super) fn country(text: &str, alpha3: bool) -> Option<&'static str> {
+    let text = text.trim();
+    let position = COUNTRY_INDEX
+        .get(&text.to_ascii_uppercase())
+        .filter(|_| matches!(text.len(), 2 | 3))
+        .or_else(|| COUNTRY_INDEX.get(&name_key(text)))?;
+    let (alpha2, code3, _) = COUNTRIES[*position];
+    Some(if alpha3 { code3 } else { alpha2 })
+}
+
+/// The ISO 4217 code of a currency given by code, numeric code, or unambiguous symbol.
+pub(super) fn currency(text: &str) -> Option<&'static str> {
+    let text = text.trim();
+    if !text.is_empty() && text.len() <= 3 && text.chars().all(|c| c.is_ascii_digit()) {
+        let numeric = format!("{text:0>3}");
+        return CURRENCIES
+            .iter()
+            .find(|(_, code)| *code == numeric)
+            .map(|(alpha, _)| *alpha);
+    }
+    let upper = text.to_ascii_uppercase();
+    CURRENCIES
+        .iter()
+        .map(|(alpha, _)| *alpha)
+        .find(|alpha| *alpha == upper)
+        .or_else(|| {
+            CURRENCY_SYMBOLS
+                .iter()
+                .find(|(symbol, _)| *symbol == text)
+                .map(|(_, alpha)| *alpha)
+        })
+}
+
+/// The UN/ECE Recommendation 20 code of a unit of measure.
+pub(super) fn unit_of_measure(text: &str) -> Option<&'static str> {
+    let text = text.trim().trim_end_matches('.').to_ascii_uppercase();
+    UNIT_INDEX.get(text.as_str()).copied()
+}
+
+/// Checks a GTIN-8, -12, -13, or -14 (EAN-8, UPC-A, EAN-13) and returns its digits, padded
+/// with leading zeros to 14 when `gtin14` is set. Spaces and hyphens are ignored.
+pub(super) fn gtin(text: &str, gtin14: bool) -> Result<String, String> {
+    let digits = text
+        .chars()
+        .filter(|character| !matches!(character, ' ' | '-'))
+        .collect::<String>();
+    if !digits.chars().all(|character| character.is_ascii_digit()) {
+        return Err(format!("'{text}' is not all digits"));
+    }
+    if !matches!(digits.len(), 8 | 12 | 13 | 14) {
+        return Err(format!(
+            "'{text}' has {} digits; a GTIN has 8, 12, 13, or 14",
+            digits.len()
+        ));
+    }
+    let values = digits
+        .bytes()
+        .map(|digit| u32::from(digit - b'0'))
+        .collect::<Vec<_>>();
+    let (check, body) = values.split_last().expect("a GTIN has digits");
+    // Weights alternate 3, 1, ... from the digit left of the check digit.
+    let sum: u32 = body
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(position, digit)| if position % 2 == 0 { digit * 3 } else { *digit })
+        .sum();
+    let expected = (10 - sum % 10) % 10;
+    if *check != expected {
+        return Err(format!(
+            "'{text}' has check digit {check}, expected {expected}"
+        ));
+    }
+    Ok(if gtin14 {
+        format!("{digits:0>14}")
+    } else {
+        digits
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_countries_currencies_and_units() {
+        assert_eq!(Some("DE"), country("deu", false));
+        assert_eq!(Some("CIV"), country("Cote d'Ivoire", true));
+        assert_eq!(Some("GB"), country(" uk ", false));
+        assert_eq!(None, country("Atlantis", false));
+        assert_eq!(Some("EUR"), currency("978"));
+        assert_eq!(Some("ALL"), currency("8"));
+        assert_eq!(Some("KES"), currency("kes"));
+        assert_eq!(Some("GBP"), currency("£"));
+        assert_eq!(None, currency("$"));
+        assert_eq!(Some("H87"), unit_of_measure("Stk."));
+        assert_eq!(Some("KGM"), unit_of_measure("kg"));
+        assert_eq!(None, unit_of_measure("bushel"));
+    }
+
+    #[test]
+    fn checks_gtin_check_digits() {
+        assert_eq!(
+            Ok("4006381333931".to_string()),
+            gtin("4006381333931", false)
+        );
+        assert_eq!(
+            Ok("00036000291452".to_string()),
+            gtin("0-36000-29145-2", true)
+        );
+        assert_eq!(Ok("96385074".to_string()), gtin("96385074", false));
+        assert!(gtin("4006381333932", false).is_err());
+        assert!(gtin("400638133393", false).is_err());
+        assert!(gtin("40063813339A1", false).is_err());
+    }
+}
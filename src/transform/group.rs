@@ -0,0 +1,72 @@
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Groups rows by `field` into `{ <field>: key, "rows": [...] }` objects, in the order each
+/// key is first seen.
+///
+/// Grouping runs in memory on the rows it is given, which is one page of query results, so
+/// memory use is bounded by the page size rather than by the whole result set.
+pub(super) fn group_rows(rows: Vec<Map<String, Value>>, field: &str) -> Vec<Map<String, Value>> {
+    let mut positions = HashMap::new();
+    let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+
+    for row in rows {
+        let key = row.get(field).cloned().unwrap_or(Value::Null);
+        let index = *positions.entry(key.to_string()).or_insert_with(|| {
+            groups.push((key, Vec::new()));
+            groups.len() - 1
+        });
+        groups[index].1.push(Value::Object(row));
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, rows)| {
+            let mut group = Map::new();
+            group.insert(field.to_string(), key);
+            group.insert("rows".to_string(), Value::Array(rows));
+            group
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows() -> Vec<Map<String, Value>> {
+        (0..10)
+            .map(|id| {
+                serde_json::json!({"id": id, "region": if id % 3 == 0 { "north" } else { "south" }})
+                    .as_object()
+                    .unwrap()
+                    .clone()
+            })
+            .collect()
+    }
+
+    fn sizes(groups: &[Map<String, Value>]) -> Vec<(String, usize)> {
+        let mut sizes = groups
+            .iter()
+            .map(|group| {
+                (
+                    group["region"].as_str().unwrap().to_string(),
+                    group["rows"].as_array().unwrap().len(),
+                )
+            })
+            .collect::<Vec<_>>();
+        sizes.sort();
+        sizes
+    }
+
+    #[test]
+    fn groups_rows_in_first_seen_order() {
+        let groups = group_rows(rows(), "region");
+
+        assert_eq!("north", groups[0]["region"]);
+        assert_eq!(
+            vec![("north".to_string(), 4), ("south".to_string(), 6)],
+            sizes(&groups)
+        );
+    }
+}
@@ -0,0 +1,382 @@
+//! `GET` lookups for `transform.enrich` entries of `type: http`. Responses are cached by URL for
+//! the life of the process, so daemon runs and queries that look up the same record share it:
+//! within `cache_ttl_seconds` no request is sent, and after that the response is revalidated
+//! with its `ETag` or `Last-Modified` and reused when the endpoint answers `304 Not Modified`.
+
+use crate::config::transform_config::HttpLookupConfig;
+use crate::sinks::redis::{render_key, template_fields, value_as_string};
+use crate::transform::TransformError;
+use reqwest::header::{
+    ETAG, HeaderMap, HeaderName, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::{Client, StatusCode};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+static RESPONSES: LazyLock<Mutex<HashMap<String, CachedResponse>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    /// `None` for `404 Not Found` and `null` bodies.
+    record: Option<Value>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// When the response was last received or revalidated.
+    checked_at: Instant,
+}
+
+enum Fetched {
+    Record {
+        record: Option<Value>,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+    NotModified,
+}
+
+pub(crate) struct HttpLookup {
+    config: HttpLookupConfig,
+    client: Client,
+}
+
+impl HttpLookup {
+    pub(crate) fn new(config: &HttpLookupConfig) -> Result<Self, TransformError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in config.headers.iter().flatten() {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|error| request_error(&config.url, error))?;
+            let value =
+                HeaderValue::from_str(value).map_err(|error| request_error(&config.url, error))?;
+            headers.insert(name, value);
+        }
+        let client = Client::builder()
+            .timeout(Duration::from_secs(
+                config.timeout_seconds.unwrap_or(30) as u64
+            ))
+            .default_headers(headers)
+            .build()
+            .map_err(|error| request_error(&config.url, error))?;
+        Ok(Self {
+            config: config.clone(),
+            client,
+        })
+    }
+
+    /// The row's URL with percent-encoded values; `None` when a field is null or absent.
+    pub(crate) fn url(&self, row: &Map<String, Value>) -> Option<String> {
+        let values = template_fields(&self.config.url)?
+            .into_iter()
+            .map(|name| {
+                let value = row.get(name).filter(|value| !value.is_null())?;
+                Some((
+                    name.to_string(),
+                    Value::String(encode(&value_as_string(value))),
+                ))
+            })
+            .collect::<Option<Map<_, _>>>()?;
+        render_key(&self.config.url, &values).ok()
+    }
+
+    /// Requests each distinct URL that is not fresh in the cache; URLs without a record are
+    /// missing from the returned map.
+    pub(crate) async fn fetch(
+        &self,
+        urls: &[Option<String>],
+    ) -> Result<HashMap<String, Value>, TransformError> {
+        let mut unique = urls.iter().flatten().collect::<Vec<_>>();
+        unique.sort_unstable();
+        unique.dedup();
+
+        let ttl = Duration::from_secs(self.config.cache_ttl_seconds.unwrap_or(300) as u64);
+        let now = Instant::now();
+        let mut records = HashMap::with_capacity(unique.len());
+        let mut stale = Vec::new();
+        {
+            let cache = responses();
+            for url in &unique {
+                match cache.get(*url) {
+                    Some(cached) if now < cached.checked_at + ttl => {
+                        if let Some(record) = &cached.record {
+                            records.insert((*url).clone(), record.clone());
+                        }
+                    }
+                    cached => stale.push(((*url).clone(), cached.cloned())),
+                }
+            }
+        }
+
+        let requested = stale.len();
+        let mut revalidated = 0;
+        let concurrency = self.config.concurrency.unwrap_or(8).max(1) as usize;
+        for chunk in stale.chunks(concurrency) {
+            let mut requests = JoinSet::new();
+            for (url, cached) in chunk.iter().cloned() {
+                let client = self.client.clone();
+                requests.spawn(async move {
+                    let fetched = get(&client, &url, cached.as_ref()).await;
+                    (url, cached, fetched)
+                });
+            }
+            let mut responses_received = Vec::with_capacity(chunk.len());
+            while let Some(result) = requests.join_next().await {
+                responses_received
+                    .push(result.map_err(|error| request_error(&self.config.url, error))?);
+            }
+
+            let checked_at = Instant::now();
+            let mut cache = responses();
+            for (url, cached, fetched) in responses_received {
+                let entry = match (fetched?, cached) {
+                    (Fetched::NotModified, Some(cached)) => {
+                        revalidated += 1;
+                        CachedResponse {
+                            checked_at,
+                            ..cached
+                        }
+                    }
+                    (Fetched::NotModified, None) => {
+                        return Err(request_error(
+                            &url,
+                            "304 Not Modified for a request without validators",
+                        ));
+                    }
+                    (
+                        Fetched::Record {
+                            record,
+                            etag,
+                            last_modified,
+                        },
+                        _,
+                    ) => CachedResponse {
+                        record,
+                        etag,
+                        last_modified,
+                        checked_at,
+                    },
+                };
+                if let Some(record) = &entry.record {
+                    records.insert(url.clone(), record.clone());
+                }
+                cache.insert(url, entry);
+            }
+            evict(
+                &mut cache,
+                self.config.cache_max_entries.unwrap_or(10_000) as usize,
+            );
+        }
+
+        tracing::debug!(
+            url = %self.config.url,
+            distinct = unique.len(),
+            cached = unique.len() - requested,
+            requested,
+            revalidated,
+            "enrichment lookups"
+        );
+        Ok(records)
+    }
+}
+
+async fn get(
+    client: &Client,
+    url: &str,
+    cached: Option<&CachedResponse>,
+) -> Result<Fetched, TransformError> {
+    let mut request = client.get(url);
+    if let Some(cached) = cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|error| request_error(url, error))?;
+
+    let status = response.status();
+    if status == StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value: &HeaderValue| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+    if status == StatusCode::NOT_FOUND {
+        return Ok(Fetched::Record {
+            record: None,
+            etag,
+            last_modified,
+        });
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(request_error(url, format!("status {status}: {body}")));
+    }
+    let body = response
+        .bytes()
+        .await
+        .map_err(|error| request_error(url, error))?;
+    let record: Value =
+        serde_json::from_slice(&body).map_err(|error| TransformError::InvalidLookupValue {
+            key: url.to_string(),
+            reason: error.to_string(),
+        })?;
+    Ok(Fetched::Record {
+        record: (!record.is_null()).then_some(record),
+        etag,
+        last_modified,
+    })
+}
+
+fn responses() -> MutexGuard<'static, HashMap<String, CachedResponse>> {
+    RESPONSES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Drops the least recently checked responses once the cache holds more than `max_entries`,
+/// leaving room for a tenth more so that eviction does not run on every page.
+fn evict(cache: &mut HashMap<String, CachedResponse>, max_entries: usize) {
+    if cache.len() <= max_entries {
+        return;
+    }
+    let mut ages = cache
+        .iter()
+        .map(|(url, cached)| (cached.checked_at, url.clone()))
+        .collect::<Vec<_>>();
+    ages.sort_unstable();
+    let excess = cache.len() - max_entries * 9 / 10;
+    for (_, url) in ages.into_iter().take(excess) {
+        cache.remove(&url);
+    }
+}
+
+/// Percent-encodes a value for any part of a URL.
+fn encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes())
+        .collect::<String>()
+        .replace('+', "%20")
+}
+
+fn request_error(url: &str, reason: impl Display) -> TransformError {
+    TransformError::LookupRequest {
+        url: url.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::transform_config::LookupMissAction;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn lookup(url: String, cache_ttl_seconds: u32) -> HttpLookup {
+        HttpLookup::new(&HttpLookupConfig {
+            url,
+            headers: None,
+            fields: None,
+            target: None,
+            on_missing: LookupMissAction::Keep,
+            timeout_seconds: None,
+            cache_ttl_seconds: Some(cache_ttl_seconds),
+            cache_max_entries: None,
+            concurrency: None,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn caches_responses_and_revalidates_them_with_etags() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0_u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let request = String::from_utf8(request).unwrap().to_ascii_lowercase();
+                let path = request.split_whitespace().nth(1).unwrap().to_string();
+                let revalidating = request.contains("if-none-match: \"v1\"");
+                let (status, etag, body) = match (path.as_str(), revalidating) {
+                    ("/customers/1", true) => ("304 Not Modified", "", ""),
+                    ("/customers/1", false) => ("200 OK", "ETag: \"v1\"\r\n", r#"{"tier":"gold"}"#),
+                    _ => ("404 Not Found", "", ""),
+                };
+                log.lock().unwrap().push(format!(
+                    "{path}{}",
+                    if revalidating { " (etag)" } else { "" }
+                ));
+                let response = format!(
+                    "HTTP/1.1 {status}\r\n{etag}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let url = format!("http://{address}/customers/{{{{customer_id}}}}");
+        let revalidating = lookup(url.clone(), 0);
+        let rows = [
+            serde_json::json!({ "customer_id": 1 }),
+            serde_json::json!({ "customer_id": 1 }),
+            serde_json::json!({ "customer_id": "a b" }),
+            serde_json::json!({ "customer_id": null }),
+        ];
+        let urls = rows
+            .iter()
+            .map(|row| revalidating.url(row.as_object().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(Some(format!("http://{address}/customers/a%20b")), urls[2]);
+        assert_eq!(None, urls[3]);
+
+        let sent = |requests: &Arc<Mutex<Vec<String>>>| {
+            let mut sent = requests.lock().unwrap().clone();
+            sent.sort();
+            sent
+        };
+        let expected = HashMap::from([(
+            format!("http://{address}/customers/1"),
+            serde_json::json!({ "tier": "gold" }),
+        )]);
+
+        assert_eq!(expected, revalidating.fetch(&urls).await.unwrap());
+        assert_eq!(vec!["/customers/1", "/customers/a%20b"], sent(&requests));
+
+        assert_eq!(expected, revalidating.fetch(&urls).await.unwrap());
+        assert_eq!(
+            vec![
+                "/customers/1",
+                "/customers/1 (etag)",
+                "/customers/a%20b",
+                "/customers/a%20b",
+            ],
+            sent(&requests)
+        );
+
+        // A lookup with a TTL reuses the shared responses without contacting the endpoint.
+        assert_eq!(expected, lookup(url, 300).fetch(&urls).await.unwrap());
+        assert_eq!(4, requests.lock().unwrap().len());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::config::transform_config::{ConversionMode, DataConversion};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+const DEFAULT_DATE_FORMATS: &[&str] = &["%Y-%m-%d"];
+const DEFAULT_DATETIME_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+
+/// Parses a number written with the conversion's decimal and thousands separators.
+///
+/// Strict mode requires the whole text to be a number with correctly placed groups;
+/// lenient mode ignores anything that is not a digit, sign, or separator.
+pub(super) fn parse_number(text: &str, conversion: &DataConversion) -> Option<f64> {
+    let decimal = conversion.decimal_separator.unwrap_or('.');
+    let thousands = conversion.thousands_separator;
+    let text = text.trim();
+    let text = match conversion.mode {
+        ConversionMode::Strict => text.to_string(),
+        ConversionMode::Lenient => text
+            .chars()
+            .filter(|character| {
+                character.is_ascii_digit()
+                    || matches!(character, '-' | '+')
+                    || *character == decimal
+                    || Some(*character) == thousands
+            })
+            .collect(),
+    };
+
+    let (sign, unsigned) = match text.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", text.strip_prefix('+').unwrap_or(&text)),
+    };
+    let (integer, fraction) = match unsigned.split_once(decimal) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (unsigned, None),
+    };
+    let integer = match thousands {
+        Some(separator) if integer.contains(separator) => {
+            let groups = integer.split(separator).collect::<Vec<_>>();
+            let well_formed = (1..=3).contains(&groups[0].len())
+                && groups[1..].iter().all(|group| group.len() == 3);
+            if !well_formed && conversion.mode == ConversionMode::Strict {
+                return None;
+            }
+            groups.concat()
+        }
+        _ => integer.to_string(),
+    };
+
+    let digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    if !digits(&integer) || fraction.is_some_and(|fraction| !digits(fraction)) {
+        return None;
+    }
+    let normalized = match fraction {
+        Some(fraction) => format!("{sign}{integer}.{fraction}"),
+        None => format!("{sign}{integer}"),
+    };
+    normalized.parse().ok()
+}
+
+/// Parses a date with the conversion's `input_formats`, falling back to ISO 8601.
+pub(super) fn parse_date(text: &str, conversion: &DataConversion) -> Option<NaiveDate> {
+    let text = text.trim();
+    for pattern in patterns(conversion, DEFAULT_DATE_FORMATS) {
+        let parsed = match conversion.mode {
+            ConversionMode::Strict => NaiveDate::parse_from_str(text, pattern).ok(),
+            ConversionMode::Lenient => NaiveDate::parse_and_remainder(text, pattern)
+                .ok()
+                .map(|(date, _)| date),
+        };
+        if parsed.is_some() {
+            return parsed;
+        }
+    }
+    None
+}
+
+/// Parses a date-time with the conversion's `input_formats`, falling back to RFC 3339 and ISO 8601.
+/// Date-only patterns yield midnight.
+pub(super) fn parse_datetime(text: &str, conversion: &DataConversion) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    if conversion.input_formats.is_empty()
+        && let Ok(parsed) = DateTime::parse_from_rfc3339(text)
+    {
+        return Some(parsed.naive_utc());
+    }
+    for pattern in patterns(conversion, DEFAULT_DATETIME_FORMATS) {
+        let parsed = match conversion.mode {
+            ConversionMode::Strict => NaiveDateTime::parse_from_str(text, pattern).ok(),
+            ConversionMode::Lenient => NaiveDateTime::parse_and_remainder(text, pattern)
+                .ok()
+                .map(|(datetime, _)| datetime),
+        };
+        if parsed.is_some() {
+            return parsed;
+        }
+    }
+    parse_date(text, conversion).and_then(|date| date.and_hms_opt(0, 0, 0))
+}
+
+fn patterns<'a>(
+    conversion: &'a DataConversion,
+    defaults: &'static [&'static str],
+) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+    if conversion.input_formats.is_empty() {
+        Box::new(defaults.iter().copied())
+    } else {
+        Box::new(conversion.input_formats.iter().map(String::as_str))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::transform_config::ConversionTarget;
+
+    fn european(mode: ConversionMode) -> DataConversion {
+        DataConversion {
+            target: ConversionTarget::Number,
+            decimal_separator: Some(','),
+            thousands_separator: Some('.'),
+            input_formats: vec!["%d/%m/%Y".to_string()],
+            mode,
+            ..DataConversion::default()
+        }
+    }
+
+    #[test]
+    fn parses_numbers_with_configured_separators() {
+        let strict = european(ConversionMode::Strict);
+
+        assert_eq!(Some(1234.56), parse_number("1.234,56", &strict));
+        assert_eq!(Some(-1234567.0), parse_number("-1.234.567", &strict));
+        assert_eq!(Some(0.5), parse_number("0,5", &strict));
+        assert_eq!(None, parse_number("12.34,5", &strict));
+        assert_eq!(None, parse_number("€ 1.234,56", &strict));
+        assert_eq!(
+            Some(1234.56),
+            parse_number("€ 1.234,56", &european(ConversionMode::Lenient))
+        );
+    }
+
+    #[test]
+    fn parses_dates_with_configured_patterns() {
+        let strict = european(ConversionMode::Strict);
+        let expected = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+
+        assert_eq!(Some(expected), parse_date("31/12/2024", &strict));
+        assert_eq!(None, parse_date("31/12/2024 08:15", &strict));
+        assert_eq!(
+            Some(expected),
+            parse_date("31/12/2024 08:15", &european(ConversionMode::Lenient))
+        );
+        assert_eq!(
+            expected.and_hms_opt(0, 0, 0),
+            parse_datetime("31/12/2024", &strict)
+        );
+        assert_eq!(
+            expected.and_hms_opt(8, 15, 0),
+            parse_datetime("2024-12-31T08:15:00Z", &DataConversion::default())
+        );
+    }
+}
@@ -1,156 +1,334 @@
-use crate::config::transform_config::{DataConversion, DataFilter, TransformConfig};
+pub mod bench;
+mod catalog;
+pub mod enrich;
+mod group;
+mod http_lookup;
+mod locale;
+
+use crate::config::transform_config::{
+    ConversionErrorAction, ConversionTarget, CountryFormat, DataConversion, DataFilter,
+    FilterCondition, GtinFormat, TransformConfig,
+};
+pub use enrich::Enricher;
 use serde_json::{Map, Number, Value};
+use std::io::Write;
 
 #[derive(Debug, thiserror::Error)]
 pub enum TransformError {
-    #[error("unsupported transform filter condition '{0}'")]
-    UnsupportedFilter(String),
-    #[error("unsupported transform conversion target '{0}'")]
-    UnsupportedConversion(String),
     #[error("failed to convert field '{field}' to {target}: {reason}")]
     Conversion {
         field: String,
         target: String,
         reason: String,
     },
-    #[error("group_by transforms are not implemented yet")]
-    GroupByUnsupported,
+    #[error("filter fields {fields} are not result columns; available columns: {available}")]
+    UnknownFilterFields { fields: String, available: String },
+    #[error("failed to write rejected rows to '{path}': {source}")]
+    RejectFile {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("enrichment lookup failed: {0}")]
+    Lookup(redis::RedisError),
+    #[error("enrichment value at '{key}' is invalid: {reason}")]
+    InvalidLookupValue { key: String, reason: String },
+    #[error("enrichment request to {url} failed: {reason}")]
+    LookupRequest { url: String, reason: String },
+    #[error("enrichment key '{0}' was not found")]
+    LookupMissing(String),
+}
+
+/// Checks that every filter references a column present in the query result, reporting all
+/// that do not.
+pub fn validate_filter_fields<'a>(
+    transform: &TransformConfig,
+    columns: impl IntoIterator<Item = &'a String>,
+) -> Result<(), TransformError> {
+    let Some(filters) = transform.filters.as_deref().filter(|_| transform.enabled) else {
+        return Ok(());
+    };
+
+    let mut columns = columns.into_iter().map(String::as_str).collect::<Vec<_>>();
+    columns.sort_unstable();
+    let mut missing = Vec::new();
+    for filter in filters {
+        let field = filter.field.as_str();
+        if !columns.contains(&field) && !missing.contains(&field) {
+            missing.push(field);
+        }
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(TransformError::UnknownFilterFields {
+        fields: missing
+            .iter()
+            .map(|field| format!("'{field}'"))
+            .collect::<Vec<_>>()
+            .join(", "),
+        available: columns.join(", "),
+    })
 }
 
-pub fn apply(
+/// Applies filters, conversions, enrichment lookups, mappings, and finally `group_by` on the
+/// mapped field names.
+///
+/// Group-by works in memory on the rows passed in, one page at a time.
+pub async fn apply(
     rows: Vec<Map<String, Value>>,
     transform: &TransformConfig,
+    enricher: Option<&Enricher>,
 ) -> Result<Vec<Map<String, Value>>, TransformError> {
     if !transform.enabled {
         return Ok(rows);
     }
-    if transform.group_by.is_some() {
-        return Err(TransformError::GroupByUnsupported);
-    }
 
-    let mut rows = apply_filters(rows, transform.filters.as_deref())?;
-    apply_conversions(&mut rows, transform.conversions.as_ref())?;
+    let rows = apply_filters(rows, transform.filters.as_deref());
+    let mut rows = apply_conversions(
+        rows,
+        transform.conversions.as_ref(),
+        transform.reject_file.as_deref(),
+    )?;
+    if let Some(enricher) = enricher {
+        rows = enricher.apply(rows).await?;
+    }
     apply_mappings(&mut rows, transform.mappings.as_ref());
-    Ok(rows)
+    match transform.group_by.as_deref() {
+        Some(field) => Ok(group::group_rows(rows, field)),
+        None => Ok(rows),
+    }
 }
 
 fn apply_filters(
     rows: Vec<Map<String, Value>>,
     filters: Option<&[DataFilter]>,
-) -> Result<Vec<Map<String, Value>>, TransformError> {
+) -> Vec<Map<String, Value>> {
     let Some(filters) = filters else {
-        return Ok(rows);
+        return rows;
     };
 
     rows.into_iter()
-        .filter_map(|row| {
-            match filters.iter().try_fold(true, |keep, filter| {
-                Ok(keep && row_matches_filter(&row, filter)?)
-            }) {
-                Ok(true) => Some(Ok(row)),
-                Ok(false) => None,
-                Err(error) => Some(Err(error)),
-            }
-        })
+        .filter(|row| filters.iter().all(|filter| row_matches_filter(row, filter)))
         .collect()
 }
 
-fn row_matches_filter(
-    row: &Map<String, Value>,
-    filter: &DataFilter,
-) -> Result<bool, TransformError> {
+fn row_matches_filter(row: &Map<String, Value>, filter: &DataFilter) -> bool {
     let value = row.get(&filter.field).unwrap_or(&Value::Null);
-    match filter.condition.as_str() {
-        "not_null" => Ok(!value.is_null()),
-        "is_null" => Ok(value.is_null()),
-        "eq" | "equals" => Ok(filter.value.as_ref() == Some(value)),
-        "ne" | "not_equals" => Ok(filter
+    match filter.condition {
+        FilterCondition::NotNull => !value.is_null(),
+        FilterCondition::IsNull => value.is_null(),
+        FilterCondition::Equals => filter.value.as_ref() == Some(value),
+        FilterCondition::NotEquals => filter
             .value
             .as_ref()
-            .is_some_and(|expected| expected != value)),
-        other => Err(TransformError::UnsupportedFilter(other.to_string())),
+            .is_some_and(|expected| expected != value),
     }
 }
 
+/// Converts each configured field, routing rows that fail a conversion by its `on_error`.
 fn apply_conversions(
-    rows: &mut [Map<String, Value>],
+    rows: Vec<Map<String, Value>>,
     conversions: Option<&std::collections::HashMap<String, DataConversion>>,
-) -> Result<(), TransformError> {
+    reject_file: Option<&str>,
+) -> Result<Vec<Map<String, Value>>, TransformError> {
     let Some(conversions) = conversions else {
-        return Ok(());
+        return Ok(rows);
     };
 
-    for row in rows {
+    let mut converted = Vec::with_capacity(rows.len());
+    let mut rejected = Vec::new();
+    'rows: for mut row in rows {
         for (field, conversion) in conversions {
             let Some(value) = row.get(field).cloned() else {
                 continue;
             };
-            row.insert(
-                field.clone(),
-                convert_value(field, value, conversion.to.as_str())?,
-            );
+            match convert_value(field, value, conversion) {
+                Ok(value) => {
+                    row.insert(field.clone(), value);
+                }
+                Err(error @ TransformError::Conversion { .. }) => match conversion.on_error {
+                    ConversionErrorAction::Fail => return Err(error),
+                    ConversionErrorAction::SetNull => {
+                        tracing::debug!(%error, "conversion failed; value set to null");
+                        row.insert(field.clone(), Value::Null);
+                    }
+                    ConversionErrorAction::Keep => {
+                        tracing::debug!(%error, "conversion failed; original value kept");
+                    }
+                    ConversionErrorAction::SkipRow => {
+                        rejected.push(serde_json::json!({
+                            "field": field,
+                            "error": error.to_string(),
+                            "row": row,
+                        }));
+                        continue 'rows;
+                    }
+                },
+                Err(error) => return Err(error),
+            }
         }
+        converted.push(row);
+    }
+
+    if !rejected.is_empty() {
+        tracing::warn!(
+            rejected = rejected.len(),
+            reject_file = reject_file.unwrap_or("<none>"),
+            "skipped rows that failed conversion"
+        );
+        if let Some(path) = reject_file {
+            write_rejects(path, &rejected)?;
+        }
+    }
+    Ok(converted)
+}
+
+fn write_rejects(path: &str, rejected: &[Value]) -> Result<(), TransformError> {
+    let error = |source| TransformError::RejectFile {
+        path: path.to_string(),
+        source,
+    };
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(error)?;
+    let mut lines = Vec::new();
+    for entry in rejected {
+        lines.extend(entry.to_string().into_bytes());
+        lines.push(b'\n');
     }
-    Ok(())
+    file.write_all(&lines).map_err(error)
 }
 
-fn convert_value(field: &str, value: Value, target: &str) -> Result<Value, TransformError> {
+fn convert_value(
+    field: &str,
+    value: Value,
+    conversion: &DataConversion,
+) -> Result<Value, TransformError> {
     if value.is_null() {
         return Ok(Value::Null);
     }
 
-    match target {
-        "string" | "text" | "iso8601_string" => Ok(match value {
+    let target = conversion.target.name();
+    let failed = |reason: String| TransformError::Conversion {
+        field: field.to_string(),
+        target: target.to_string(),
+        reason,
+    };
+    match &conversion.target {
+        ConversionTarget::String => Ok(match value {
             Value::String(value) => Value::String(value),
             Value::Number(value) => Value::String(value.to_string()),
             Value::Bool(value) => Value::String(value.to_string()),
             other => Value::String(other.to_string()),
         }),
-        "integer" | "int" => {
-            let value = as_i64(field, &value, target)?;
+        ConversionTarget::Integer => {
+            let value = as_i64(field, &value, conversion)?;
             Ok(Value::Number(Number::from(value)))
         }
-        "number" | "float" | "double" | "decimal" => {
-            let value = as_f64(field, &value, target)?;
+        ConversionTarget::Number => {
+            let value = as_f64(field, &value, conversion)?;
             Number::from_f64(value)
                 .map(Value::Number)
-                .ok_or_else(|| TransformError::Conversion {
-                    field: field.to_string(),
-                    target: target.to_string(),
-                    reason: "number is not finite".to_string(),
-                })
+                .ok_or_else(|| failed("number is not finite".to_string()))
+        }
+        ConversionTarget::Bool => Ok(Value::Bool(as_bool(field, &value, target)?)),
+        ConversionTarget::Date { format } => {
+            let date = value
+                .as_str()
+                .and_then(|text| locale::parse_date(text, conversion))
+                .ok_or_else(|| failed(format!("cannot parse {value} as a date")))?;
+            let format = format.as_deref().unwrap_or("%Y-%m-%d");
+            Ok(Value::String(date.format(format).to_string()))
+        }
+        ConversionTarget::Datetime { format } => {
+            let datetime = value
+                .as_str()
+                .and_then(|text| locale::parse_datetime(text, conversion))
+                .ok_or_else(|| failed(format!("cannot parse {value} as a date-time")))?;
+            let format = format.as_deref().unwrap_or("%Y-%m-%dT%H:%M:%S%.fZ");
+            Ok(Value::String(datetime.format(format).to_string()))
         }
-        "bool" | "boolean" => Ok(Value::Bool(as_bool(field, &value, target)?)),
-        other => Err(TransformError::UnsupportedConversion(other.to_string())),
+        ConversionTarget::UnitOfMeasure => {
+            let text = as_catalog_text(&value);
+            catalog::unit_of_measure(&text)
+                .map(|code| Value::String(code.to_string()))
+                .ok_or_else(|| failed(format!("'{text}' is not a known unit of measure")))
+        }
+        ConversionTarget::Country { format } => {
+            let text = as_catalog_text(&value);
+            catalog::country(&text, *format == CountryFormat::Alpha3)
+                .map(|code| Value::String(code.to_string()))
+                .ok_or_else(|| failed(format!("'{text}' is not a known country")))
+        }
+        ConversionTarget::Currency => {
+            let text = as_catalog_text(&value);
+            catalog::currency(&text)
+                .map(|code| Value::String(code.to_string()))
+                .ok_or_else(|| failed(format!("'{text}' is not a known currency")))
+        }
+        ConversionTarget::Gtin { format } => catalog::gtin(
+            &as_catalog_text(&value),
+            *format == Some(GtinFormat::Gtin14),
+        )
+        .map(Value::String)
+        .map_err(failed),
+    }
+}
+
+/// Catalog lookups read codes as text; numeric columns such as currency numbers are formatted.
+fn as_catalog_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
     }
 }
 
-fn as_i64(field: &str, value: &Value, target: &str) -> Result<i64, TransformError> {
+fn as_i64(field: &str, value: &Value, conversion: &DataConversion) -> Result<i64, TransformError> {
     match value {
         Value::Number(number) => number
             .as_i64()
-            .or_else(|| number.as_f64().map(|value| value as i64)),
-        Value::String(value) => value.parse::<i64>().ok(),
+            .or_else(|| number.as_f64().and_then(whole_number)),
+        Value::String(text) => text
+            .parse::<i64>()
+            .ok()
+            .or_else(|| locale::parse_number(text, conversion).and_then(whole_number)),
         Value::Bool(value) => Some(i64::from(*value)),
         _ => None,
     }
     .ok_or_else(|| TransformError::Conversion {
         field: field.to_string(),
-        target: target.to_string(),
+        target: conversion.target.name().to_string(),
         reason: format!("cannot convert {value} to integer"),
     })
 }
 
-fn as_f64(field: &str, value: &Value, target: &str) -> Result<f64, TransformError> {
+/// `number` as an integer, unless it has a fraction or is out of range.
+fn whole_number(number: f64) -> Option<i64> {
+    // -2^63 and 2^63 are exact as floats; only the former fits.
+    (number.fract() == 0.0 && (-(2f64.powi(63))..2f64.powi(63)).contains(&number))
+        .then_some(number as i64)
+}
+
+fn as_f64(field: &str, value: &Value, conversion: &DataConversion) -> Result<f64, TransformError> {
     match value {
         Value::Number(number) => number.as_f64(),
-        Value::String(value) => value.parse::<f64>().ok(),
+        Value::String(text)
+            if conversion.decimal_separator.is_none()
+                && conversion.thousands_separator.is_none() =>
+        {
+            text.parse::<f64>()
+                .ok()
+                .or_else(|| locale::parse_number(text, conversion))
+        }
+        Value::String(text) => locale::parse_number(text, conversion),
         Value::Bool(value) => Some(if *value { 1.0 } else { 0.0 }),
         _ => None,
     }
     .ok_or_else(|| TransformError::Conversion {
         field: field.to_string(),
-        target: target.to_string(),
+        target: conversion.target.name().to_string(),
         reason: format!("cannot convert {value} to number"),
     })
 }
@@ -175,7 +353,7 @@ fn as_bool(field: &str, value: &Value, target: &str) -> Result<bool, TransformEr
 
 fn apply_mappings(
     rows: &mut [Map<String, Value>],
-    mappings: Option<&std::collections::HashMap<String, String>>,
+    mappings: Option<&indexmap::IndexMap<String, String>>,
 ) {
     let Some(mappings) = mappings else {
         return;
@@ -197,28 +375,30 @@ fn apply_mappings(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::FieldPath;
     use crate::config::transform_config::DataConversion;
+    use chrono::Utc;
     use std::collections::HashMap;
 
-    #[test]
-    fn filters_converts_and_maps_rows() {
-        let mut mappings = HashMap::new();
+    #[tokio::test]
+    async fn filters_converts_and_maps_rows() {
+        let mut mappings = indexmap::IndexMap::new();
         mappings.insert("amount".to_string(), "total_amount".to_string());
         let mut conversions = HashMap::new();
         conversions.insert(
             "amount".to_string(),
             DataConversion {
                 from: "string".to_string(),
-                to: "number".to_string(),
-                format: None,
+                target: ConversionTarget::Number,
+                ..DataConversion::default()
             },
         );
         conversions.insert(
             "active".to_string(),
             DataConversion {
                 from: "string".to_string(),
-                to: "bool".to_string(),
-                format: None,
+                target: ConversionTarget::Bool,
+                ..DataConversion::default()
             },
         );
         let transform = TransformConfig {
@@ -227,10 +407,12 @@ mod tests {
             group_by: None,
             filters: Some(vec![DataFilter {
                 field: "email".to_string(),
-                condition: "not_null".to_string(),
+                condition: FilterCondition::NotNull,
                 value: None,
             }]),
             conversions: Some(conversions),
+            reject_file: None,
+            enrich: None,
         };
         let rows = vec![
             serde_json::json!({"email": "a@example.test", "amount": "42.5", "active": "true"})
@@ -243,7 +425,7 @@ mod tests {
                 .clone(),
         ];
 
-        let rows = apply(rows, &transform).unwrap();
+        let rows = apply(rows, &transform, None).await.unwrap();
 
         assert_eq!(1, rows.len());
         assert_eq!(serde_json::json!(42.5), rows[0]["total_amount"]);
@@ -252,13 +434,175 @@ mod tests {
     }
 
     #[test]
-    fn disabled_transform_is_passthrough() {
+    fn conversion_targets_and_formats_are_checked_when_parsed() {
+        let conversion: DataConversion =
+            serde_yaml::from_str("from: string\nto: country\nformat: alpha3\n").unwrap();
+        assert_eq!(
+            ConversionTarget::Country {
+                format: CountryFormat::Alpha3
+            },
+            conversion.target
+        );
+        let conversion: DataConversion =
+            serde_yaml::from_str("from: string\nto: int\ndecimal_separator: ','\n").unwrap();
+        assert_eq!(ConversionTarget::Integer, conversion.target);
+        assert_eq!(Some(','), conversion.decimal_separator);
+
+        for (yaml, expected) in [
+            ("to: integr\n", "unknown variant `integr`"),
+            ("to: country\nformat: alpha4\n", "unknown variant `alpha4`"),
+            ("to: gtin\nformat: gtin13\n", "unknown variant `gtin13`"),
+        ] {
+            let error = serde_yaml::from_str::<DataConversion>(&format!("from: string\n{yaml}"))
+                .unwrap_err();
+            assert!(error.to_string().contains(expected), "{error}");
+        }
+        let conversion: DataConversion =
+            serde_yaml::from_str("from: string\nto: date\nformat: '%Y-%Q'\n").unwrap();
+        assert!(
+            conversion
+                .validate(&FieldPath::new("transform.conversions.due"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn integer_conversion_rejects_fractions() {
+        let conversion = DataConversion {
+            target: ConversionTarget::Integer,
+            ..DataConversion::default()
+        };
+
+        assert_eq!(
+            serde_json::json!(3),
+            convert_value("quantity", serde_json::json!(3.0), &conversion).unwrap()
+        );
+        for value in [serde_json::json!(2.5), serde_json::json!(1e300)] {
+            assert!(matches!(
+                convert_value("quantity", value, &conversion),
+                Err(TransformError::Conversion { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn filter_conditions_are_checked_when_parsed() {
+        let filter: DataFilter =
+            serde_yaml::from_str("field: status\ncondition: ne\nvalue: void\n").unwrap();
+        assert_eq!(FilterCondition::NotEquals, filter.condition);
+
+        let error = serde_yaml::from_str::<DataFilter>("field: status\ncondition: greater_than\n")
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("unknown variant `greater_than`"),
+            "{error}"
+        );
+        let schema = serde_json::to_value(schemars::schema_for!(DataFilter)).unwrap();
+        assert_eq!(
+            serde_json::json!(["not_null", "is_null", "equals", "not_equals"]),
+            schema["$defs"]["FilterCondition"]["enum"]
+        );
+    }
+
+    #[test]
+    fn reports_filter_fields_missing_from_result_columns() {
+        let transform = TransformConfig {
+            filters: Some(
+                ["emial", "id", "totl"]
+                    .into_iter()
+                    .map(|field| DataFilter {
+                        field: field.to_string(),
+                        condition: FilterCondition::NotNull,
+                        value: None,
+                    })
+                    .collect(),
+            ),
+            ..TransformConfig::default()
+        };
+        let row = serde_json::json!({"id": 1, "email": "a@example.test"})
+            .as_object()
+            .unwrap()
+            .clone();
+
+        let error = validate_filter_fields(&transform, row.keys()).unwrap_err();
+
+        assert_eq!(
+            "filter fields 'emial', 'totl' are not result columns; available columns: email, id",
+            error.to_string()
+        );
+        let valid = TransformConfig {
+            filters: Some(vec![DataFilter {
+                field: "email".to_string(),
+                condition: FilterCondition::NotNull,
+                value: None,
+            }]),
+            ..TransformConfig::default()
+        };
+        assert!(validate_filter_fields(&valid, row.keys()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn routes_rows_that_fail_locale_conversions() {
+        let reject_file = std::env::temp_dir().join(format!(
+            "yetii-rejects-{}-{}.jsonl",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let mut conversions = HashMap::new();
+        conversions.insert(
+            "amount".to_string(),
+            DataConversion {
+                target: ConversionTarget::Number,
+                decimal_separator: Some(','),
+                thousands_separator: Some('.'),
+                on_error: ConversionErrorAction::SkipRow,
+                ..DataConversion::default()
+            },
+        );
+        conversions.insert(
+            "due".to_string(),
+            DataConversion {
+                target: ConversionTarget::Date { format: None },
+                input_formats: vec!["%d/%m/%Y".to_string()],
+                on_error: ConversionErrorAction::SetNull,
+                ..DataConversion::default()
+            },
+        );
+        let transform = TransformConfig {
+            conversions: Some(conversions),
+            reject_file: Some(reject_file.display().to_string()),
+            ..TransformConfig::default()
+        };
+        let rows = [
+            serde_json::json!({"id": 1, "amount": "1.234,56", "due": "31/12/2024"}),
+            serde_json::json!({"id": 2, "amount": "n/a", "due": "31/12/2024"}),
+            serde_json::json!({"id": 3, "amount": "7", "due": "2024-12-31"}),
+        ]
+        .into_iter()
+        .map(|row| row.as_object().unwrap().clone())
+        .collect();
+
+        let rows = apply(rows, &transform, None).await.unwrap();
+        let rejects = std::fs::read_to_string(&reject_file).unwrap();
+        std::fs::remove_file(&reject_file).unwrap();
+
+        assert_eq!(2, rows.len());
+        assert_eq!(serde_json::json!(1234.56), rows[0]["amount"]);
+        assert_eq!(serde_json::json!("2024-12-31"), rows[0]["due"]);
+        assert_eq!(Value::Null, rows[1]["due"]);
+        let rejected: Value = serde_json::from_str(rejects.trim()).unwrap();
+        assert_eq!(serde_json::json!(2), rejected["row"]["id"]);
+        assert_eq!(serde_json::json!("amount"), rejected["field"]);
+    }
+
+    #[tokio::test]
+    async fn disabled_transform_is_passthrough() {
         let rows = vec![serde_json::json!({"a": 1}).as_object().unwrap().clone()];
         let transform = TransformConfig {
             enabled: false,
             ..TransformConfig::default()
         };
 
-        assert_eq!(rows, apply(rows.clone(), &transform).unwrap());
+        assert_eq!(rows, apply(rows.clone(), &transform, None).await.unwrap());
     }
 }
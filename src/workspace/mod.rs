@@ -0,0 +1,286 @@
+use crate::config::workspace_config::WorkspaceConfig;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Scratch directory owned by one query execution; removed when dropped.
+#[derive(Debug)]
+pub struct JobWorkspace {
+    path: PathBuf,
+    quota_bytes: Option<u64>,
+    used_bytes: Arc<AtomicU64>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorkspaceError {
+    #[error("failed to prepare workspace '{path}': {source}")]
+    Create {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("workspace file '{path}' failed: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("workspace quota of {quota_bytes} bytes exceeded in '{path}'")]
+    QuotaExceeded { path: PathBuf, quota_bytes: u64 },
+    #[error("workspace file '{path}' contains an invalid record: {reason}")]
+    InvalidRecord { path: PathBuf, reason: String },
+}
+
+impl JobWorkspace {
+    pub fn create(config: Option<&WorkspaceConfig>, job: &str) -> Result<Self, WorkspaceError> {
+        let base = config
+            .and_then(|config| config.temp_dir.as_deref())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::temp_dir().join("yetii"));
+        let job = job
+            .chars()
+            .map(|character| {
+                if character.is_ascii_alphanumeric() || matches!(character, '-' | '_') {
+                    character
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        let path = base.join(format!(
+            "{job}-{}-{}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        fs::create_dir_all(&path).map_err(|source| WorkspaceError::Create {
+            path: path.clone(),
+            source,
+        })?;
+        tracing::debug!(path = %path.display(), "created job workspace");
+
+        Ok(Self {
+            path,
+            quota_bytes: config
+                .and_then(|config| config.quota_mb)
+                .map(|quota_mb| quota_mb * 1024 * 1024),
+            used_bytes: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn used_bytes(&self) -> u64 {
+        self.used_bytes.load(Ordering::SeqCst)
+    }
+
+    /// Creates a JSON-lines scratch file whose writes count against the job quota until
+    /// the file is dropped.
+    pub fn create_file(&self, name: &str) -> Result<SpillWriter, WorkspaceError> {
+        let path = self.path.join(name);
+        let file = File::create(&path).map_err(|source| WorkspaceError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        Ok(SpillWriter {
+            writer: BufWriter::new(file),
+            file: SpillFile {
+                path,
+                bytes: 0,
+                used_bytes: self.used_bytes.clone(),
+            },
+            quota_bytes: self.quota_bytes,
+            workspace: self.path.clone(),
+        })
+    }
+}
+
+impl Drop for JobWorkspace {
+    fn drop(&mut self) {
+        tracing::debug!(
+            path = %self.path.display(),
+            used_bytes = self.used_bytes(),
+            "removing job workspace"
+        );
+        if let Err(error) = fs::remove_dir_all(&self.path)
+            && error.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::warn!(
+                path = %self.path.display(),
+                error = %error,
+                "failed to clean up job workspace"
+            );
+        }
+    }
+}
+
+/// Writes one scratch file. Dropping it unfinished deletes the partial file.
+pub struct SpillWriter {
+    writer: BufWriter<File>,
+    file: SpillFile,
+    quota_bytes: Option<u64>,
+    workspace: PathBuf,
+}
+
+impl SpillWriter {
+    pub fn write_record(&mut self, record: &Value) -> Result<(), WorkspaceError> {
+        let mut line =
+            serde_json::to_vec(record).map_err(|error| WorkspaceError::InvalidRecord {
+                path: self.file.path.clone(),
+                reason: error.to_string(),
+            })?;
+        line.push(b'\n');
+        self.write_bytes(&line)
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), WorkspaceError> {
+        let len = bytes.len() as u64;
+        let used = self.file.used_bytes.fetch_add(len, Ordering::SeqCst) + len;
+        if let Some(quota_bytes) = self.quota_bytes
+            && used > quota_bytes
+        {
+            self.file.used_bytes.fetch_sub(len, Ordering::SeqCst);
+            return Err(WorkspaceError::QuotaExceeded {
+                path: self.workspace.clone(),
+                quota_bytes,
+            });
+        }
+        self.file.bytes += len;
+        self.writer
+            .write_all(bytes)
+            .map_err(|source| self.io_error(source))
+    }
+
+    /// Flushes the file and hands it over for reading back.
+    pub fn finish(mut self) -> Result<SpillFile, WorkspaceError> {
+        self.writer
+            .flush()
+            .map_err(|source| self.io_error(source))?;
+        Ok(self.file)
+    }
+
+    fn io_error(&self, source: std::io::Error) -> WorkspaceError {
+        WorkspaceError::Io {
+            path: self.file.path.clone(),
+            source,
+        }
+    }
+}
+
+/// A scratch file in the job workspace. Dropping it deletes the file and returns its bytes
+/// to the job quota.
+#[derive(Debug)]
+pub struct SpillFile {
+    path: PathBuf,
+    bytes: u64,
+    used_bytes: Arc<AtomicU64>,
+}
+
+impl SpillFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_file(&self.path)
+            && error.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::warn!(
+                path = %self.path.display(),
+                error = %error,
+                "failed to remove workspace file"
+            );
+            return;
+        }
+        self.used_bytes.fetch_sub(self.bytes, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(dir: &Path, quota_mb: Option<u64>) -> WorkspaceConfig {
+        WorkspaceConfig {
+            temp_dir: Some(dir.display().to_string()),
+            quota_mb,
+        }
+    }
+
+    #[test]
+    fn removes_scratch_directory_when_dropped() {
+        let base = std::env::temp_dir().join(format!("yetii-workspace-{}", std::process::id()));
+        let workspace = JobWorkspace::create(Some(&config(&base, None)), "orders/sync").unwrap();
+        let path = workspace.path().to_path_buf();
+        let mut file = workspace.create_file("spill.jsonl").unwrap();
+        file.write_record(&serde_json::json!({"id": 1})).unwrap();
+        let file = file.finish().unwrap();
+
+        assert!(path.starts_with(&base));
+        assert!(
+            path.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("orders_sync-")
+        );
+        assert_eq!("{\"id\":1}\n", fs::read_to_string(file.path()).unwrap());
+
+        drop(file);
+        drop(workspace);
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn enforces_quota_across_files() {
+        let base = std::env::temp_dir().join(format!("yetii-quota-{}", std::process::id()));
+        let workspace = JobWorkspace::create(Some(&config(&base, Some(1))), "quota").unwrap();
+        let chunk = vec![b'x'; 600 * 1024];
+
+        let mut first = workspace.create_file("a").unwrap();
+        first.write_bytes(&chunk).unwrap();
+        let error = workspace
+            .create_file("b")
+            .unwrap()
+            .write_bytes(&chunk)
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            WorkspaceError::QuotaExceeded { quota_bytes, .. } if quota_bytes == 1024 * 1024
+        ));
+        drop(workspace);
+        let _ = fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn deleted_files_return_their_bytes_to_the_quota() {
+        let base = std::env::temp_dir().join(format!("yetii-release-{}", std::process::id()));
+        let workspace = JobWorkspace::create(Some(&config(&base, Some(1))), "pages").unwrap();
+        let chunk = vec![b'x'; 600 * 1024];
+
+        for page in 0..4 {
+            let mut writer = workspace.create_file(&format!("page-{page}")).unwrap();
+            writer.write_bytes(&chunk).unwrap();
+            assert!(matches!(
+                writer.write_bytes(&chunk),
+                Err(WorkspaceError::QuotaExceeded { .. })
+            ));
+            let file = writer.finish().unwrap();
+            assert_eq!(chunk.len() as u64, workspace.used_bytes());
+
+            let path = file.path().to_path_buf();
+            drop(file);
+            assert!(!path.exists());
+            assert_eq!(0, workspace.used_bytes());
+        }
+
+        drop(workspace);
+        let _ = fs::remove_dir_all(base);
+    }
+}